@@ -1,3 +1,335 @@
+//! Tauri's build step, plus `commands.ts`: the arguments and result of
+//! every `#[tauri::command]`, read from the sources, so the frontend's
+//! `invoke` calls are typed. The types they name are exported by ts-rs
+//! into the same folder when `cargo test` runs (`npm run bindings` runs
+//! just those), so the build fails if one of them isn't marked
+//! `#[ts(export)]` and would never get a file. None of these files are
+//! committed; `npm run typecheck` generates them and runs `tsc`, and the
+//! release workflow runs it before building.
+//!
+//! ts-rs types `i64` and `u64` as `bigint`, but serde sends them as plain
+//! JSON numbers, so the build also fails on such a field in a TS type that
+//! isn't marked `#[ts(type = "number")]`. `commands.ts` types them as
+//! `number` throughout.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Parameters Tauri fills in itself rather than taking from the caller.
+const INJECTED: &[&str] = &["State", "AppHandle", "Window", "WebviewWindow"];
+
+/// Integers ts-rs exports as `bigint`.
+const BIGINTS: &[&str] = &["i64", "u64", "i128", "u128"];
+
 fn main() {
+    println!("cargo:rerun-if-changed=src");
+    match write_commands() {
+        Ok(checks) if !checks.unexported.is_empty() => panic!(
+            "commands use types ts-rs doesn't export: {}; derive TS and add #[ts(export)]",
+            checks.unexported.join(", ")
+        ),
+        Ok(checks) if !checks.bigints.is_empty() => panic!(
+            "ts-rs would export these as bigint: {}; add #[ts(type = \"number\")]",
+            checks.bigints.join(", ")
+        ),
+        Ok(_) => {}
+        Err(e) => println!("cargo:warning=could not generate commands.ts: {}", e),
+    }
     tauri_build::build()
 }
+
+struct Command {
+    name: String,
+    args: Vec<(String, String)>,
+    returns: String,
+}
+
+/// What `write_commands` found wrong with the exported types.
+struct Checks {
+    /// Types `commands.ts` imports that no source file exports.
+    unexported: Vec<String>,
+    /// `Type.field`s ts-rs would export as `bigint`.
+    bigints: Vec<String>,
+}
+
+/// Write `commands.ts`, checking the types it relies on.
+fn write_commands() -> Result<Checks, Box<dyn std::error::Error>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir("src")?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "rs"))
+        .collect();
+    files.sort();
+    let mut commands = Vec::new();
+    let mut named = BTreeSet::new();
+    let mut exported = BTreeSet::new();
+    let mut bigints = Vec::new();
+    for file in files {
+        let ast = syn::parse_file(&std::fs::read_to_string(&file)?)?;
+        for item in ast.items {
+            match &item {
+                syn::Item::Struct(s) => {
+                    exported.extend(exported_name(&s.attrs, &s.ident));
+                    if derives_ts(&s.attrs) {
+                        bigints.extend(bigint_fields(&s.ident.to_string(), &s.fields));
+                    }
+                }
+                syn::Item::Enum(e) => {
+                    exported.extend(exported_name(&e.attrs, &e.ident));
+                    if derives_ts(&e.attrs) {
+                        for variant in &e.variants {
+                            let owner = format!("{}::{}", e.ident, variant.ident);
+                            bigints.extend(bigint_fields(&owner, &variant.fields));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            let syn::Item::Fn(f) = item else { continue };
+            if !f.attrs.iter().any(is_command) {
+                continue;
+            }
+            let mut args = Vec::new();
+            for input in &f.sig.inputs {
+                let syn::FnArg::Typed(arg) = input else {
+                    continue;
+                };
+                let syn::Pat::Ident(ident) = &*arg.pat else {
+                    continue;
+                };
+                if injected(&arg.ty) {
+                    continue;
+                }
+                args.push((
+                    camel_case(&ident.ident.to_string()),
+                    ts_type(&arg.ty, &mut named),
+                ));
+            }
+            let returns = match &f.sig.output {
+                syn::ReturnType::Default => "null".to_string(),
+                syn::ReturnType::Type(_, ty) => ts_type(ty, &mut named),
+            };
+            commands.push(Command {
+                name: f.sig.ident.to_string(),
+                args,
+                returns,
+            });
+        }
+    }
+    commands.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = String::from(
+        "// Generated by build.rs from the #[tauri::command] functions. Do not edit.\n\n",
+    );
+    for name in &named {
+        writeln!(out, "import type {{ {0} }} from \"./{0}\";", name)?;
+    }
+    out.push_str("\n/** Arguments and result of each command, by command name. */\nexport interface Commands {\n");
+    for command in &commands {
+        let args: Vec<String> = command
+            .args
+            .iter()
+            .map(|(name, ty)| match ty.strip_suffix(" | null") {
+                Some(inner) => format!("{}?: {} | null", name, inner),
+                None => format!("{}: {}", name, ty),
+            })
+            .collect();
+        let args = if args.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", args.join("; "))
+        };
+        writeln!(
+            out,
+            "  {}: {{ args: {}; returns: {} }};",
+            command.name, args, command.returns
+        )?;
+    }
+    out.push_str("}\n");
+
+    let dir = std::env::var("TS_RS_EXPORT_DIR").unwrap_or_else(|_| "bindings".into());
+    let path = Path::new(&dir).join("commands.ts");
+    // Leave the file alone when nothing changed, so the dev server doesn't
+    // reload on every build.
+    if std::fs::read_to_string(&path).ok().as_deref() != Some(out.as_str()) {
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(path, out)?;
+    }
+    Ok(Checks {
+        unexported: named.difference(&exported).cloned().collect(),
+        bigints,
+    })
+}
+
+fn derives_ts(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("derive"))
+        .any(|a| {
+            let mut ts = false;
+            let _ = a.parse_nested_meta(|meta| {
+                ts |= meta.path.is_ident("TS");
+                Ok(())
+            });
+            ts
+        })
+}
+
+/// The fields of `owner` ts-rs would export as `bigint`: those mentioning
+/// a 64-bit integer without a `#[ts(type = ...)]` or `#[ts(as = ...)]`.
+fn bigint_fields(owner: &str, fields: &syn::Fields) -> Vec<String> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| mentions_bigint(&f.ty) && !overrides_type(&f.attrs))
+        .map(|(i, f)| match &f.ident {
+            Some(ident) => format!("{}.{}", owner, ident),
+            None => format!("{}.{}", owner, i),
+        })
+        .collect()
+}
+
+fn overrides_type(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().filter(|a| a.path().is_ident("ts")).any(|a| {
+        let mut overridden = false;
+        let _ = a.parse_nested_meta(|meta| {
+            overridden |= meta.path.is_ident("type") || meta.path.is_ident("as");
+            if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+        overridden
+    })
+}
+
+fn mentions_bigint(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(r) => mentions_bigint(&r.elem),
+        syn::Type::Slice(s) => mentions_bigint(&s.elem),
+        syn::Type::Array(a) => mentions_bigint(&a.elem),
+        syn::Type::Tuple(t) => t.elems.iter().any(mentions_bigint),
+        syn::Type::Path(_) => last_segment(ty).is_some_and(|segment| {
+            BIGINTS.contains(&segment.ident.to_string().as_str())
+                || generic_args(segment).into_iter().any(mentions_bigint)
+        }),
+        _ => false,
+    }
+}
+
+/// The name ts-rs exports a struct or enum under, if it is exported.
+fn exported_name(attrs: &[syn::Attribute], ident: &syn::Ident) -> Option<String> {
+    let mut export = false;
+    let mut name = ident.to_string();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("ts")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("export") {
+                export = true;
+            } else if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<syn::LitStr>()?.value();
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+    }
+    export.then_some(name)
+}
+
+fn is_command(attr: &syn::Attribute) -> bool {
+    let segments: Vec<String> = attr
+        .path()
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect();
+    segments == ["tauri", "command"]
+}
+
+fn last_segment(ty: &syn::Type) -> Option<&syn::PathSegment> {
+    match ty {
+        syn::Type::Path(p) => p.path.segments.last(),
+        syn::Type::Reference(r) => last_segment(&r.elem),
+        _ => None,
+    }
+}
+
+fn injected(ty: &syn::Type) -> bool {
+    last_segment(ty).is_some_and(|s| INJECTED.contains(&s.ident.to_string().as_str()))
+}
+
+fn generic_args(segment: &syn::PathSegment) -> Vec<&syn::Type> {
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(a) => a
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(t) => Some(t),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The TypeScript for a Rust type as serde sends it, adding the names of
+/// types ts-rs exports to `named`.
+fn ts_type(ty: &syn::Type, named: &mut BTreeSet<String>) -> String {
+    match ty {
+        syn::Type::Reference(r) => ts_type(&r.elem, named),
+        syn::Type::Tuple(t) if t.elems.is_empty() => "null".into(),
+        syn::Type::Tuple(t) => format!(
+            "[{}]",
+            t.elems
+                .iter()
+                .map(|e| ts_type(e, named))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        syn::Type::Slice(s) => format!("Array<{}>", ts_type(&s.elem, named)),
+        syn::Type::Path(_) => {
+            let segment = last_segment(ty).expect("path type");
+            let args: Vec<String> = generic_args(segment)
+                .into_iter()
+                .map(|t| ts_type(t, named))
+                .collect();
+            let arg = |i: usize| args.get(i).cloned().unwrap_or_else(|| "unknown".into());
+            match segment.ident.to_string().as_str() {
+                "String" | "str" | "PathBuf" | "Path" => "string".into(),
+                "bool" => "boolean".into(),
+                "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize"
+                | "f32" | "f64" => "number".into(),
+                "Value" => "unknown".into(),
+                "AppResult" | "Result" | "Box" => arg(0),
+                "Option" => format!("{} | null", arg(0)),
+                "Vec" | "HashSet" | "BTreeSet" => format!("Array<{}>", arg(0)),
+                "HashMap" | "BTreeMap" => format!("Record<{}, {}>", arg(0), arg(1)),
+                name => {
+                    named.insert(name.to_string());
+                    if args.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{}<{}>", name, args.join(", "))
+                    }
+                }
+            }
+        }
+        _ => "unknown".into(),
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,23 @@
+#!/usr/bin/env bash
+# Fetch the Hunspell dictionaries the spell-checker ships with, from
+# LibreOffice's dictionaries repository at a pinned release, as
+# `<lang>.aff` and `<lang>.dic` next to this script. The release workflow
+# runs this before bundling; run it once locally to spell-check in dev.
+set -euo pipefail
+
+TAG=libreoffice-24.8.4.2
+BASE="https://raw.githubusercontent.com/LibreOffice/dictionaries/$TAG"
+DIR="$(cd "$(dirname "$0")" && pwd)"
+
+# <lang> <path in the repository without extension> <licence file>
+while read -r lang path licence; do
+  curl -fsSL "$BASE/$path.aff" -o "$DIR/$lang.aff"
+  curl -fsSL "$BASE/$path.dic" -o "$DIR/$lang.dic"
+  curl -fsSL "$BASE/$licence" -o "$DIR/$lang.LICENSE.txt"
+done <<'LIST'
+en_US en/en_US en/README_en_US.txt
+en_GB en/en_GB en/README_en_GB.txt
+de_DE de/de_DE_frami de/COPYING_GPLv2
+fr_FR fr_FR/fr fr_FR/README_fr.txt
+es_ES es/es_ES es/README.txt
+LIST
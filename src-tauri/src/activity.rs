@@ -0,0 +1,192 @@
+use crate::clock::DateRange;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::settings;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Settings key for the name recorded as the actor on activity entries.
+pub const ACTOR_SETTING: &str = "profile.display_name";
+const DEFAULT_ACTOR: &str = "local user";
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+/// One field's before and after values. Long text is stored whole; the
+/// frontend decides how much to show.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityEntry {
+    #[ts(type = "number")]
+    pub id: i64,
+    pub project_id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub action: String,
+    pub actor: String,
+    pub summary: String,
+    pub changes: Vec<FieldChange>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ActivityPage {
+    pub entries: Vec<ActivityEntry>,
+    /// Pass as `before` to fetch the next (older) page; `None` at the end.
+    #[ts(type = "number | null")]
+    pub next_cursor: Option<i64>,
+}
+
+fn row_to_entry(row: &Row) -> rusqlite::Result<ActivityEntry> {
+    let changes_json: String = row.get(7)?;
+    Ok(ActivityEntry {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        entity_type: row.get(2)?,
+        entity_id: row.get(3)?,
+        action: row.get(4)?,
+        actor: row.get(5)?,
+        summary: row.get(6)?,
+        changes: serde_json::from_str(&changes_json).unwrap_or_default(),
+        created_at: row.get(8)?,
+    })
+}
+
+/// Push a change onto `changes` if the value actually differs.
+pub fn diff(changes: &mut Vec<FieldChange>, field: &str, old: impl ToString, new: impl ToString) {
+    let (old, new) = (old.to_string(), new.to_string());
+    if old != new {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            old,
+            new,
+        });
+    }
+}
+
+/// Append an entry to a project's activity log. Updates that changed none
+/// of the tracked fields are not worth an entry and are skipped.
+pub fn record(
+    conn: &Connection,
+    project_id: &str,
+    entity_type: &str,
+    entity_id: &str,
+    action: &str,
+    summary: &str,
+    changes: &[FieldChange],
+) -> AppResult<()> {
+    if action == "updated" && changes.is_empty() {
+        return Ok(());
+    }
+    let actor = settings::get(conn, ACTOR_SETTING)?
+        .filter(|a| !a.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_ACTOR.to_string());
+    conn.execute(
+        "INSERT INTO activity_log (project_id, entity_type, entity_id, action, actor, summary, changes_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            project_id,
+            entity_type,
+            entity_id,
+            action,
+            actor,
+            summary,
+            serde_json::to_string(changes)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// `record` for something that belongs to a scene, when only the scene id
+/// is at hand.
+pub fn record_for_scene(
+    conn: &Connection,
+    scene_id: &str,
+    entity_type: &str,
+    entity_id: &str,
+    action: &str,
+    summary: &str,
+    changes: &[FieldChange],
+) -> AppResult<()> {
+    let project_id: String = conn.query_row(
+        "SELECT project_id FROM scenes WHERE id = ?1",
+        [scene_id],
+        |r| r.get(0),
+    )?;
+    record(
+        conn,
+        &project_id,
+        entity_type,
+        entity_id,
+        action,
+        summary,
+        changes,
+    )
+}
+
+/// Newest-first activity for a project, `limit` entries at a time. Pass the
+/// previous page's `next_cursor` as `before` to keep going back. `range`
+/// limits it to entries made in a span of the user's local dates.
+pub fn page(
+    conn: &Connection,
+    project_id: &str,
+    before: Option<i64>,
+    limit: Option<u32>,
+    range: &DateRange,
+) -> AppResult<ActivityPage> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let (from, to) = range.bounds()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, entity_type, entity_id, action, actor, summary, changes_json, created_at
+         FROM activity_log
+         WHERE project_id = ?1 AND (?2 IS NULL OR id < ?2)
+            AND (?4 IS NULL OR created_at >= ?4) AND (?5 IS NULL OR created_at < ?5)
+         ORDER BY id DESC LIMIT ?3",
+    )?;
+    // One extra row tells us whether there is another page.
+    let mut entries = stmt
+        .query_map(
+            params![project_id, before, limit + 1, from, to],
+            row_to_entry,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let next_cursor = if entries.len() > limit as usize {
+        entries.truncate(limit as usize);
+        entries.last().map(|e| e.id)
+    } else {
+        None
+    };
+    Ok(ActivityPage {
+        entries,
+        next_cursor,
+    })
+}
+
+#[tauri::command]
+pub fn get_activity(
+    db: State<'_, Database>,
+    project_id: String,
+    before: Option<i64>,
+    limit: Option<u32>,
+    range: Option<DateRange>,
+) -> AppResult<ActivityPage> {
+    page(
+        &db.conn(),
+        &project_id,
+        before,
+        limit,
+        &range.unwrap_or_default(),
+    )
+}
@@ -0,0 +1,175 @@
+//! Opt-in feature usage counts. Nothing is recorded until the user turns
+//! collection on, and nothing leaves the machine unless they also turn on
+//! sharing and pick an endpoint. Only aggregate counts are kept: feature,
+//! a coarse detail like the provider key, and the day.
+
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::providers;
+use crate::schedule;
+use crate::settings;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const ENABLED_SETTING: &str = "analytics.enabled";
+const SHARE_SETTING: &str = "analytics.share";
+const ENDPOINT_SETTING: &str = "analytics.endpoint";
+
+/// Count one use of `feature` in a project. Does nothing unless collection
+/// is on or if the project is confidential, and never fails the caller:
+/// analytics must not get in the way of real work.
+pub fn track(conn: &Connection, project_id: &str, feature: &str, detail: &str) {
+    let result = settings::get_bool(conn, ENABLED_SETTING).and_then(|enabled| {
+        if enabled && !confidential::is_confidential(conn, project_id)? {
+            conn.execute(
+                "INSERT INTO usage_counts (feature, detail, day, count)
+                 VALUES (?1, ?2, date('now'), 1)
+                 ON CONFLICT(feature, detail, day) DO UPDATE SET count = count + 1",
+                params![feature, detail],
+            )?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        tracing::debug!(feature, error = %e, "failed to record usage");
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AnalyticsSettings {
+    pub enabled: bool,
+    /// Allow `send_usage_report` to upload the aggregated counts.
+    #[serde(default)]
+    pub share: bool,
+    #[serde(default)]
+    pub endpoint: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct UsageCount {
+    pub feature: String,
+    pub detail: String,
+    #[ts(type = "number")]
+    pub count: i64,
+    pub first_used: String,
+    pub last_used: String,
+}
+
+/// Collected counts per feature and detail, optionally only from `since`
+/// (`YYYY-MM-DD`) onwards.
+pub fn summary(conn: &Connection, since: Option<&str>) -> AppResult<Vec<UsageCount>> {
+    if let Some(since) = since {
+        schedule::validate_date(since)?;
+    }
+    let mut stmt = conn.prepare(
+        "SELECT feature, detail, SUM(count), MIN(day), MAX(day) FROM usage_counts
+         WHERE ?1 IS NULL OR day >= ?1
+         GROUP BY feature, detail
+         ORDER BY feature, SUM(count) DESC, detail",
+    )?;
+    let rows = stmt
+        .query_map([since], |r| {
+            Ok(UsageCount {
+                feature: r.get(0)?,
+                detail: r.get(1)?,
+                count: r.get(2)?,
+                first_used: r.get(3)?,
+                last_used: r.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+pub fn load_settings(conn: &Connection) -> AppResult<AnalyticsSettings> {
+    Ok(AnalyticsSettings {
+        enabled: settings::get_bool(conn, ENABLED_SETTING)?,
+        share: settings::get_bool(conn, SHARE_SETTING)?,
+        endpoint: settings::get(conn, ENDPOINT_SETTING)?.unwrap_or_default(),
+    })
+}
+
+pub fn save_settings(conn: &Connection, input: &AnalyticsSettings) -> AppResult<()> {
+    let endpoint = input.endpoint.trim();
+    if input.share && !endpoint.starts_with("https://") {
+        return Err(AppError::Invalid(
+            "sharing usage data needs an https:// endpoint".into(),
+        ));
+    }
+    settings::set(conn, ENABLED_SETTING, &input.enabled.to_string())?;
+    settings::set(conn, SHARE_SETTING, &input.share.to_string())?;
+    settings::set(conn, ENDPOINT_SETTING, endpoint)
+}
+
+/// Delete everything collected so far. Returns the number of rows removed.
+pub fn purge(conn: &Connection) -> AppResult<usize> {
+    Ok(conn.execute("DELETE FROM usage_counts", [])?)
+}
+
+/// The endpoint and counts to upload. Refuses unless the user has turned
+/// sharing on and picked an endpoint.
+pub fn report(conn: &Connection) -> AppResult<(String, Vec<UsageCount>)> {
+    if !settings::get_bool(conn, SHARE_SETTING)? {
+        return Err(AppError::Invalid("usage sharing is turned off".into()));
+    }
+    let endpoint = settings::get(conn, ENDPOINT_SETTING)?.unwrap_or_default();
+    if endpoint.is_empty() {
+        return Err(AppError::Invalid("no usage endpoint configured".into()));
+    }
+    Ok((endpoint, summary(conn, None)?))
+}
+
+#[tauri::command]
+pub fn get_analytics_settings(db: State<'_, Database>) -> AppResult<AnalyticsSettings> {
+    load_settings(&db.conn())
+}
+
+#[tauri::command]
+pub fn save_analytics_settings(db: State<'_, Database>, input: AnalyticsSettings) -> AppResult<()> {
+    save_settings(&db.conn(), &input)
+}
+
+#[tauri::command]
+pub fn get_usage_summary(
+    db: State<'_, Database>,
+    since: Option<String>,
+) -> AppResult<Vec<UsageCount>> {
+    summary(&db.conn(), since.as_deref())
+}
+
+#[tauri::command]
+pub fn purge_usage_data(db: State<'_, Database>) -> AppResult<usize> {
+    purge(&db.conn())
+}
+
+/// Upload the aggregated counts to the configured endpoint. Refuses unless
+/// the user has turned sharing on.
+#[tauri::command]
+pub async fn send_usage_report(app: AppHandle) -> AppResult<usize> {
+    let (endpoint, counts) = report(&app.state::<Database>().conn())?;
+    let body = json!({
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "usage": counts,
+    });
+    let resp = providers::client()
+        .post(&endpoint)
+        .json(&body)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(AppError::Provider(format!(
+            "usage endpoint returned {}",
+            resp.status()
+        )));
+    }
+    Ok(counts.len())
+}
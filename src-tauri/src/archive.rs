@@ -0,0 +1,245 @@
+//! Archiving finished projects to keep the working database and media
+//! folder lean. An archived project's media files (takes, frames, moodboard
+//! images, reference clips, voice samples) and character photos move into
+//! one zip under `archives/` in the app data dir; its rows stay, marked
+//! archived and read-only, and it drops out of the launcher and search.
+//! `unarchive` puts everything back where it was.
+//!
+//! Files another project still uses are left in place, and only files
+//! under the media folder are archived; renders saved elsewhere are the
+//! user's own.
+
+use crate::activity;
+use crate::clock;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::projects::{self, ProjectSummary};
+use crate::roles::{self, Role};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Bumped when the archive layout changes incompatibly.
+const ARCHIVE_FORMAT: u32 = 1;
+const MANIFEST: &str = "archive.json";
+
+/// Every column that can point at a media file, as a query for one
+/// project's values. Each compares the project with `= ?1`.
+const FILE_QUERIES: &[&str] = &[
+    "SELECT video_url FROM scenes WHERE project_id = ?1",
+    "SELECT conditioning_image FROM scenes WHERE project_id = ?1",
+    "SELECT j.video_url FROM video_jobs j JOIN scenes s ON s.id = j.scene_id WHERE s.project_id = ?1",
+    "SELECT i.image FROM moodboard_items i JOIN moodboards m ON m.id = i.moodboard_id
+     WHERE m.project_id = ?1",
+    "SELECT file_path FROM reference_clips WHERE project_id = ?1",
+    "SELECT v.file_path FROM voice_samples v JOIN characters c ON c.id = v.character_id
+     WHERE c.project_id = ?1",
+    "SELECT path FROM renders WHERE project_id = ?1",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: u32,
+    project_id: String,
+    /// Archived files, relative to the app data dir; also their names in
+    /// the zip.
+    files: Vec<String>,
+    /// Character photos (data URIs) by character id.
+    photos: HashMap<String, String>,
+}
+
+fn zip_err(e: zip::result::ZipError) -> AppError {
+    AppError::Io(format!("project archive: {}", e))
+}
+
+fn archive_path(root: &Path, project_id: &str) -> PathBuf {
+    root.join("archives").join(format!("{}.zip", project_id))
+}
+
+/// Values of every file column for the project, or for every other
+/// project with `others`.
+fn referenced(conn: &Connection, project_id: &str, others: bool) -> AppResult<BTreeSet<String>> {
+    let mut values = BTreeSet::new();
+    for query in FILE_QUERIES {
+        let query = if others {
+            query.replace("= ?1", "<> ?1")
+        } else {
+            query.to_string()
+        };
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map([project_id], |r| r.get::<_, Option<String>>(0))?;
+        for value in rows {
+            values.extend(value?.filter(|v| !v.is_empty()));
+        }
+    }
+    Ok(values)
+}
+
+/// The project's media files no other project uses, relative to `root`.
+pub fn project_files(conn: &Connection, project_id: &str, root: &Path) -> AppResult<Vec<PathBuf>> {
+    let shared = referenced(conn, project_id, true)?;
+    let media = root.join("media");
+    Ok(referenced(conn, project_id, false)?
+        .difference(&shared)
+        .map(PathBuf::from)
+        .filter(|path| path.starts_with(&media) && path.is_file())
+        .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+        .collect())
+}
+
+fn write_archive(out: &Path, root: &Path, manifest: &Manifest) -> AppResult<()> {
+    let partial = out.with_extension("zip.partial");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&partial)?);
+    // Video is already compressed; deflating it again only costs time.
+    let stored =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for file in &manifest.files {
+        zip.start_file(file.as_str(), stored).map_err(zip_err)?;
+        std::io::copy(&mut std::fs::File::open(root.join(file))?, &mut zip)?;
+    }
+    zip.start_file(MANIFEST, deflated).map_err(zip_err)?;
+    zip.write_all(serde_json::to_string(manifest)?.as_bytes())?;
+    zip.finish().map_err(zip_err)?;
+    std::fs::rename(&partial, out)?;
+    Ok(())
+}
+
+/// Compact a finished project: zip its media and character photos into
+/// the archive folder, remove them from the working set, and mark the
+/// project archived. It stays read-only and out of the launcher and
+/// search until `unarchive`. `root` is the app data dir.
+pub fn archive(conn: &Connection, id: &str, root: &Path) -> AppResult<ProjectSummary> {
+    let project = projects::get_project(conn, id)?;
+    roles::ensure_role(conn, id, Role::Owner)?;
+    if project.archived_at.is_some() {
+        return Ok(project);
+    }
+    let files = project_files(conn, id, root)?;
+    let photos = {
+        let mut stmt = conn.prepare(
+            "SELECT id, photo_data FROM characters WHERE project_id = ?1 AND photo_data <> ''",
+        )?;
+        let rows = stmt.query_map([id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect::<rusqlite::Result<HashMap<String, String>>>()?
+    };
+    let manifest = Manifest {
+        format: ARCHIVE_FORMAT,
+        project_id: id.to_string(),
+        files: files
+            .iter()
+            .map(|f| f.to_string_lossy().replace('\\', "/"))
+            .collect(),
+        photos,
+    };
+    let out = archive_path(root, id);
+    std::fs::create_dir_all(out.parent().unwrap_or(root))?;
+    write_archive(&out, root, &manifest)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE characters SET photo_data = '' WHERE project_id = ?1",
+        [id],
+    )?;
+    tx.execute(
+        "UPDATE projects SET archived_at = ?2 WHERE id = ?1",
+        params![id, clock::now()],
+    )?;
+    activity::record(
+        &tx,
+        id,
+        "project",
+        id,
+        "archived",
+        &format!(
+            "Archived project {} ({} files)",
+            project.name,
+            manifest.files.len()
+        ),
+        &[],
+    )?;
+    tx.commit()?;
+    for file in &files {
+        if let Err(e) = std::fs::remove_file(root.join(file)) {
+            tracing::warn!(path = %file.display(), error = %e, "archived file not removed");
+        }
+    }
+    projects::get_project(conn, id)
+}
+
+#[tauri::command]
+pub fn archive_project(db: State<'_, Database>, id: String) -> AppResult<ProjectSummary> {
+    archive(&db.conn(), &id, &db::app_dir())
+}
+
+/// Bring an archived project back: restore its files and photos from the
+/// archive under `root` and make it editable and listed again.
+pub fn unarchive(conn: &Connection, id: &str, root: &Path) -> AppResult<ProjectSummary> {
+    let project = projects::get_project(conn, id)?;
+    roles::ensure_role(conn, id, Role::Owner)?;
+    if project.archived_at.is_none() {
+        return Ok(project);
+    }
+    let path = archive_path(root, id);
+    let mut zip =
+        zip::ZipArchive::new(std::fs::File::open(&path).map_err(|e| {
+            AppError::NotFound(format!("archive of project {} ({})", project.name, e))
+        })?)
+        .map_err(zip_err)?;
+    let manifest: Manifest = {
+        let mut json = String::new();
+        zip.by_name(MANIFEST)
+            .map_err(zip_err)?
+            .read_to_string(&mut json)?;
+        serde_json::from_str(&json)?
+    };
+    if manifest.format > ARCHIVE_FORMAT {
+        return Err(AppError::Invalid(format!(
+            "project archive format {} is newer than this app supports; update the app",
+            manifest.format
+        )));
+    }
+    for file in &manifest.files {
+        let mut entry = zip.by_name(file).map_err(zip_err)?;
+        // Only ever write back under the app data dir.
+        let rel = entry
+            .enclosed_name()
+            .ok_or_else(|| AppError::Invalid(format!("bad path in project archive: {}", file)))?;
+        let dest = root.join(rel);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut entry, &mut std::fs::File::create(&dest)?)?;
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for (character_id, photo) in &manifest.photos {
+        tx.execute(
+            "UPDATE characters SET photo_data = ?2 WHERE id = ?1 AND project_id = ?3",
+            params![character_id, photo, id],
+        )?;
+    }
+    tx.execute("UPDATE projects SET archived_at = NULL WHERE id = ?1", [id])?;
+    activity::record(
+        &tx,
+        id,
+        "project",
+        id,
+        "unarchived",
+        &format!("Restored project {} from its archive", project.name),
+        &[],
+    )?;
+    tx.commit()?;
+    std::fs::remove_file(&path).ok();
+    projects::get_project(conn, id)
+}
+
+#[tauri::command]
+pub fn unarchive_project(db: State<'_, Database>, id: String) -> AppResult<ProjectSummary> {
+    unarchive(&db.conn(), &id, &db::app_dir())
+}
@@ -0,0 +1,823 @@
+//! Encrypted, incremental backups of the active workspace's database and
+//! the media folder to S3-compatible storage (AWS S3, Backblaze B2, MinIO).
+//!
+//! Every file is encrypted client-side with a key derived from the backup
+//! passphrase and stored under `objects/` by a keyed hash of its contents,
+//! so unchanged files are never uploaded twice. Each run then writes a
+//! small encrypted manifest under `snapshots/` listing what it contains.
+
+use crate::confidential;
+use crate::db::{self, Database};
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::ics;
+use crate::network::NetworkMonitor;
+use crate::secrets;
+use crate::settings;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const ENDPOINT_SETTING: &str = "backup.endpoint";
+const REGION_SETTING: &str = "backup.region";
+const BUCKET_SETTING: &str = "backup.bucket";
+const PREFIX_SETTING: &str = "backup.prefix";
+const PATH_STYLE_SETTING: &str = "backup.path_style";
+pub const ACCESS_KEY_SETTING: &str = "backup.access_key_id";
+/// Hours between scheduled backups; `0` or unset means manual only.
+const INTERVAL_SETTING: &str = "backup.interval_hours";
+/// Unix seconds of the last successful backup.
+pub const LAST_RUN_SETTING: &str = "backup.last_run";
+const SECRET_KEY_SECRET: &str = "backup.secret_access_key";
+const PASSPHRASE_SECRET: &str = "backup.passphrase";
+
+const SCHEDULE_CHECK: Duration = Duration::from_secs(15 * 60);
+const KDF_ROUNDS: u32 = 210_000;
+const NONCE_LEN: usize = 12;
+
+/// Set while a backup or restore is running so they never overlap.
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Uploads can be large; the shared provider client's timeout is too short.
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(15))
+            .timeout(Duration::from_secs(30 * 60))
+            .build()
+            .expect("failed to build HTTP client")
+    })
+}
+
+/// An S3-compatible bucket, signed with AWS Signature Version 4.
+pub struct S3Target {
+    endpoint: reqwest::Url,
+    region: String,
+    bucket: String,
+    prefix: String,
+    /// `https://host/bucket/key` instead of `https://bucket.host/key`.
+    /// MinIO and most self-hosted stores need this.
+    path_style: bool,
+    access_key: String,
+    secret_key: String,
+}
+
+/// RFC 3986 encoding as SigV4 expects; `/` is kept in paths.
+fn uri_encode(s: &str, keep_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b'/' if keep_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Text of every `<tag>` element in an XML body. Good enough for the flat
+/// ListObjectsV2 response.
+fn xml_values(body: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    body.split(&open)
+        .skip(1)
+        .filter_map(|part| part.split(&close).next())
+        .map(|v| {
+            v.replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&")
+        })
+        .collect()
+}
+
+impl S3Target {
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        let host = if self.path_style {
+            host.to_string()
+        } else {
+            format!("{}.{}", self.bucket, host)
+        };
+        match self.endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host,
+        }
+    }
+
+    /// Canonical (already encoded) path for an object key, or the bucket
+    /// root when `key` is empty.
+    fn path(&self, key: &str) -> String {
+        let object = if key.is_empty() {
+            String::new()
+        } else {
+            uri_encode(&format!("{}{}", self.prefix, key), true)
+        };
+        if self.path_style {
+            format!("/{}/{}", self.bucket, object)
+        } else {
+            format!("/{}", object)
+        }
+    }
+
+    /// Build a signed request. `query` must already be sorted by name.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> reqwest::RequestBuilder {
+        let host = self.host();
+        let amz_date = ics::utc_stamp();
+        let date = &amz_date[..8];
+        let payload_hash = sha256_hex(&body);
+        let canonical_query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", uri_encode(k, false), uri_encode(v, false)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            path,
+            canonical_query,
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let signing_key = [self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            hmac(
+                format!("AWS4{}", self.secret_key).as_bytes(),
+                date.as_bytes(),
+            ),
+            |key, part| hmac(&key, part.as_bytes()),
+        );
+        let signature = hex(&hmac(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+
+        let mut url = format!("{}://{}{}", self.endpoint.scheme(), host, path);
+        if !canonical_query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query);
+        }
+        client()
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization)
+            .body(body)
+    }
+
+    async fn send(request: reqwest::RequestBuilder, what: &str) -> AppResult<reqwest::Response> {
+        let res = request.send().await?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            let code = xml_values(&body, "Code").into_iter().next().unwrap_or(body);
+            return Err(AppError::Provider(format!(
+                "backup storage {} ({}): {}",
+                what, status, code
+            )));
+        }
+        Ok(res)
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>) -> AppResult<()> {
+        let request = self.request(reqwest::Method::PUT, &self.path(key), &[], body);
+        Self::send(request, "upload").await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> AppResult<Vec<u8>> {
+        let request = self.request(reqwest::Method::GET, &self.path(key), &[], Vec::new());
+        Ok(Self::send(request, "download")
+            .await?
+            .bytes()
+            .await?
+            .to_vec())
+    }
+
+    /// Keys under `prefix`, relative to the target's own prefix.
+    async fn list(&self, prefix: &str) -> AppResult<Vec<String>> {
+        let full_prefix = format!("{}{}", self.prefix, prefix);
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = Vec::new();
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.clone()));
+            }
+            query.push(("list-type", "2".to_string()));
+            query.push(("prefix", full_prefix.clone()));
+            let request = self.request(reqwest::Method::GET, &self.path(""), &query, Vec::new());
+            let body = Self::send(request, "listing").await?.text().await?;
+            keys.extend(
+                xml_values(&body, "Key")
+                    .into_iter()
+                    .filter_map(|k| k.strip_prefix(&self.prefix).map(str::to_string)),
+            );
+            token = xml_values(&body, "NextContinuationToken")
+                .into_iter()
+                .next();
+            let truncated =
+                xml_values(&body, "IsTruncated").first().map(String::as_str) == Some("true");
+            if !truncated || token.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+}
+
+/// Where backups go. Only S3-compatible storage for now; other kinds of
+/// target slot in as further variants.
+pub enum BackupTarget {
+    S3(S3Target),
+}
+
+impl BackupTarget {
+    pub async fn put(&self, key: &str, body: Vec<u8>) -> AppResult<()> {
+        match self {
+            BackupTarget::S3(s3) => s3.put(key, body).await,
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> AppResult<Vec<u8>> {
+        match self {
+            BackupTarget::S3(s3) => s3.get(key).await,
+        }
+    }
+
+    pub async fn list(&self, prefix: &str) -> AppResult<Vec<String>> {
+        match self {
+            BackupTarget::S3(s3) => s3.list(prefix).await,
+        }
+    }
+
+    /// Ties the derived key to this bucket and prefix.
+    fn salt(&self) -> String {
+        match self {
+            BackupTarget::S3(s3) => format!("aidc-backup:{}/{}", s3.bucket, s3.prefix),
+        }
+    }
+}
+
+/// Encrypts objects and names them by a keyed hash of their contents.
+pub struct Sealer {
+    cipher: Aes256Gcm,
+    name_key: [u8; 32],
+}
+
+impl Sealer {
+    fn new(passphrase: &str, salt: &str) -> Self {
+        let mut keys = [0u8; 64];
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            passphrase.as_bytes(),
+            salt.as_bytes(),
+            KDF_ROUNDS,
+            &mut keys,
+        );
+        let mut name_key = [0u8; 32];
+        name_key.copy_from_slice(&keys[32..]);
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&keys[..32])),
+            name_key,
+        }
+    }
+
+    fn object_name(&self, plain: &[u8]) -> String {
+        format!("objects/{}", hex(&hmac(&self.name_key, plain)))
+    }
+
+    fn seal(&self, plain: &[u8]) -> AppResult<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut out = nonce.to_vec();
+        out.extend(
+            self.cipher
+                .encrypt(&nonce, plain)
+                .map_err(|_| AppError::Io("failed to encrypt backup object".into()))?,
+        );
+        Ok(out)
+    }
+
+    fn open(&self, sealed: &[u8]) -> AppResult<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(AppError::Invalid("backup object is truncated".into()));
+        }
+        let (nonce, data) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), data)
+            .map_err(|_| {
+                AppError::Invalid("wrong backup passphrase or corrupted backup object".into())
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    created_at: String,
+    app_version: String,
+    database: String,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestFile {
+    /// Relative to the app data dir, always with `/` separators.
+    path: String,
+    object: String,
+    size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BackupSettings {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub path_style: bool,
+    pub access_key_id: String,
+    #[serde(default)]
+    pub interval_hours: u32,
+    /// Only in responses.
+    #[serde(default)]
+    pub has_secret_access_key: bool,
+    #[serde(default)]
+    pub has_passphrase: bool,
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub last_run: Option<u64>,
+    /// Only in requests; omit to keep the stored value, `""` to clear.
+    #[serde(default, skip_serializing)]
+    pub secret_access_key: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub passphrase: Option<String>,
+}
+
+pub fn load_settings(conn: &Connection) -> AppResult<BackupSettings> {
+    let get = |key| -> AppResult<String> { Ok(settings::get(conn, key)?.unwrap_or_default()) };
+    Ok(BackupSettings {
+        endpoint: get(ENDPOINT_SETTING)?,
+        region: get(REGION_SETTING)?,
+        bucket: get(BUCKET_SETTING)?,
+        prefix: get(PREFIX_SETTING)?,
+        path_style: settings::get_bool(conn, PATH_STYLE_SETTING)?,
+        access_key_id: get(ACCESS_KEY_SETTING)?,
+        interval_hours: get(INTERVAL_SETTING)?.parse().unwrap_or(0),
+        has_secret_access_key: secrets::get(conn, SECRET_KEY_SECRET)?.is_some(),
+        has_passphrase: secrets::get(conn, PASSPHRASE_SECRET)?.is_some(),
+        last_run: get(LAST_RUN_SETTING)?.parse().ok(),
+        secret_access_key: None,
+        passphrase: None,
+    })
+}
+
+/// The configured target and sealer, or an error naming what's missing.
+pub fn configured(conn: &Connection) -> AppResult<(BackupTarget, Sealer)> {
+    let config = load_settings(conn)?;
+    if config.endpoint.is_empty() || config.bucket.is_empty() || config.access_key_id.is_empty() {
+        return Err(AppError::Invalid("cloud backup is not set up".into()));
+    }
+    let secret_key = secrets::get(conn, SECRET_KEY_SECRET)?
+        .ok_or_else(|| AppError::Invalid("backup secret access key is missing".into()))?;
+    let passphrase = secrets::get(conn, PASSPHRASE_SECRET)?
+        .ok_or_else(|| AppError::Invalid("backup passphrase is missing".into()))?;
+    let endpoint = reqwest::Url::parse(&config.endpoint)
+        .map_err(|e| AppError::Invalid(format!("backup endpoint: {}", e)))?;
+    let target = BackupTarget::S3(S3Target {
+        endpoint,
+        region: if config.region.is_empty() {
+            "us-east-1".into()
+        } else {
+            config.region
+        },
+        bucket: config.bucket,
+        prefix: config.prefix,
+        path_style: config.path_style,
+        access_key: config.access_key_id,
+        secret_key,
+    });
+    let sealer = Sealer::new(&passphrase, &target.salt());
+    Ok((target, sealer))
+}
+
+/// Every file under the media folder, relative to the app data dir.
+fn media_files(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> AppResult<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            media_files(root, &path, out)?;
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            out.push(rel.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+fn manifest_path(rel: &Path) -> String {
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Releases the busy flag however the run ends.
+struct BusyGuard;
+
+impl BusyGuard {
+    fn acquire() -> AppResult<Self> {
+        if BUSY.swap(true, Ordering::SeqCst) {
+            return Err(AppError::Invalid(
+                "a backup or restore is already running".into(),
+            ));
+        }
+        Ok(Self)
+    }
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        BUSY.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BackupReport {
+    pub snapshot: String,
+    pub files: usize,
+    pub uploaded: usize,
+    #[ts(type = "number")]
+    pub uploaded_bytes: u64,
+}
+
+/// Upload `plain` unless an identical object is already stored. Returns
+/// the object's key.
+async fn store(
+    target: &BackupTarget,
+    sealer: &Sealer,
+    existing: &HashSet<String>,
+    plain: Vec<u8>,
+    report: &mut BackupReport,
+) -> AppResult<String> {
+    let object = sealer.object_name(&plain);
+    if !existing.contains(&object) {
+        let sealed = sealer.seal(&plain)?;
+        report.uploaded += 1;
+        report.uploaded_bytes += sealed.len() as u64;
+        target.put(&object, sealed).await?;
+    }
+    Ok(object)
+}
+
+/// A consistent copy of the database, made in `app_dir`, to upload without
+/// holding the lock, and the media files to leave out with it. Confidential
+/// projects never leave the machine; see `confidential`.
+pub fn snapshot(
+    conn: &Connection,
+    app_dir: &Path,
+    db_path: &Path,
+) -> AppResult<(Vec<u8>, BTreeSet<PathBuf>)> {
+    let snapshot_db = app_dir.join("backup-snapshot.db");
+    std::fs::remove_file(&snapshot_db).ok();
+    conn.execute("VACUUM INTO ?1", [snapshot_db.to_string_lossy().as_ref()])?;
+    let database = confidential::strip_for_backup(&snapshot_db, db_path, &app_dir.join("media"))
+        .and_then(|left_out| Ok((std::fs::read(&snapshot_db)?, left_out)));
+    std::fs::remove_file(&snapshot_db).ok();
+    database
+}
+
+async fn run(app: &AppHandle) -> AppResult<BackupReport> {
+    let _busy = BusyGuard::acquire()?;
+    let app_dir = db::app_dir();
+    let ((target, sealer), (database, left_out)) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        (
+            configured(&conn)?,
+            snapshot(&conn, &app_dir, &db::get_db_path())?,
+        )
+    };
+
+    let existing: HashSet<String> = target.list("objects/").await?.into_iter().collect();
+    let mut report = BackupReport {
+        snapshot: String::new(),
+        files: 0,
+        uploaded: 0,
+        uploaded_bytes: 0,
+    };
+    let db_object = store(&target, &sealer, &existing, database, &mut report).await?;
+
+    let mut rel_paths = Vec::new();
+    media_files(&app_dir, &app_dir.join("media"), &mut rel_paths)?;
+    let mut files = Vec::new();
+    for rel in rel_paths {
+        if left_out.contains(&app_dir.join(&rel)) {
+            continue;
+        }
+        let plain = std::fs::read(app_dir.join(&rel))?;
+        let size = plain.len() as u64;
+        let object = store(&target, &sealer, &existing, plain, &mut report).await?;
+        files.push(ManifestFile {
+            path: manifest_path(&rel),
+            object,
+            size,
+        });
+    }
+
+    let created_at = ics::utc_stamp();
+    report.files = files.len() + 1;
+    let manifest = Manifest {
+        created_at: created_at.clone(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        database: db_object,
+        files,
+    };
+    let key = format!("snapshots/{}.json", created_at);
+    target
+        .put(&key, sealer.seal(&serde_json::to_vec(&manifest)?)?)
+        .await?;
+    report.snapshot = created_at;
+
+    let db = app.state::<Database>();
+    settings::set(&db.conn(), LAST_RUN_SETTING, &unix_now().to_string())?;
+    Ok(report)
+}
+
+fn due(conn: &Connection) -> AppResult<bool> {
+    let config = load_settings(conn)?;
+    if config.interval_hours == 0 || config.endpoint.is_empty() {
+        return Ok(false);
+    }
+    let last = config.last_run.unwrap_or(0);
+    Ok(unix_now().saturating_sub(last) >= u64::from(config.interval_hours) * 3600)
+}
+
+/// Run scheduled backups in the background while the app is open, when
+/// due and online. Emits `backup-completed` or `backup-failed`.
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULE_CHECK).await;
+            let due = {
+                let db = app.state::<Database>();
+                let conn = db.conn();
+                due(&conn).unwrap_or(false)
+            };
+            if !due || !app.state::<NetworkMonitor>().can_submit() {
+                continue;
+            }
+            match run(&app).await {
+                Ok(report) => {
+                    tracing::info!(snapshot = %report.snapshot, uploaded = report.uploaded, "scheduled backup finished");
+                    AppEvent::BackupCompleted(report).emit(&app);
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "scheduled backup failed");
+                    AppEvent::BackupFailed(e.to_string()).emit(&app);
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_backup_settings(db: State<'_, Database>) -> AppResult<BackupSettings> {
+    load_settings(&db.conn())
+}
+
+pub fn save_settings(conn: &Connection, config: &BackupSettings) -> AppResult<BackupSettings> {
+    if !config.endpoint.trim().is_empty() {
+        reqwest::Url::parse(config.endpoint.trim())
+            .map_err(|e| AppError::Invalid(format!("backup endpoint: {}", e)))?;
+    }
+    let prefix = config.prefix.trim().trim_start_matches('/');
+    let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.to_string()
+    } else {
+        format!("{}/", prefix)
+    };
+    settings::set(conn, ENDPOINT_SETTING, config.endpoint.trim())?;
+    settings::set(conn, REGION_SETTING, config.region.trim())?;
+    settings::set(conn, BUCKET_SETTING, config.bucket.trim())?;
+    settings::set(conn, PREFIX_SETTING, &prefix)?;
+    settings::set(
+        conn,
+        PATH_STYLE_SETTING,
+        if config.path_style { "true" } else { "false" },
+    )?;
+    settings::set(conn, ACCESS_KEY_SETTING, config.access_key_id.trim())?;
+    settings::set(conn, INTERVAL_SETTING, &config.interval_hours.to_string())?;
+    for (secret, value) in [
+        (SECRET_KEY_SECRET, config.secret_access_key.as_deref()),
+        (PASSPHRASE_SECRET, config.passphrase.as_deref()),
+    ] {
+        match value {
+            Some("") => secrets::delete(conn, secret)?,
+            Some(v) => secrets::set(conn, secret, v)?,
+            None => {}
+        }
+    }
+    load_settings(conn)
+}
+
+#[tauri::command]
+pub fn save_backup_settings(
+    db: State<'_, Database>,
+    config: BackupSettings,
+) -> AppResult<BackupSettings> {
+    save_settings(&db.conn(), &config)
+}
+
+/// Back up now. Emits `backup-completed` when done.
+#[tauri::command]
+pub async fn run_backup(app: AppHandle) -> AppResult<BackupReport> {
+    let report = run(&app).await?;
+    AppEvent::BackupCompleted(report.clone()).emit(&app);
+    Ok(report)
+}
+
+/// Snapshot ids (UTC timestamps) among the keys listed under
+/// `snapshots/`, newest first.
+pub fn snapshot_ids(keys: Vec<String>) -> Vec<String> {
+    let mut snapshots: Vec<String> = keys
+        .into_iter()
+        .filter_map(|k| {
+            k.strip_prefix("snapshots/")
+                .and_then(|k| k.strip_suffix(".json"))
+                .map(str::to_string)
+        })
+        .collect();
+    snapshots.sort();
+    snapshots.reverse();
+    snapshots
+}
+
+/// Snapshot ids (UTC timestamps) in the bucket, newest first.
+#[tauri::command]
+pub async fn list_backups(app: AppHandle) -> AppResult<Vec<String>> {
+    let (target, _) = configured(&app.state::<Database>().conn())?;
+    Ok(snapshot_ids(target.list("snapshots/").await?))
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RestoreReport {
+    pub snapshot: String,
+    pub restored_files: usize,
+    pub unchanged_files: usize,
+}
+
+/// Where a file listed in a snapshot goes, relative to the app data dir.
+/// Refuses paths that would land outside it.
+pub fn restore_path(path: &str) -> AppResult<PathBuf> {
+    let rel: PathBuf = path.split('/').collect();
+    if rel.is_absolute() || path.split('/').any(|p| p == "..") {
+        return Err(AppError::Invalid(format!(
+            "unsafe path in backup: {}",
+            path
+        )));
+    }
+    Ok(rel)
+}
+
+/// Check a downloaded database before it replaces the one at `db_path`.
+/// It is keyed like the live file if that is encrypted.
+pub fn verify_restored(restored: &Path, db_path: &Path) -> AppResult<()> {
+    let check = Connection::open(restored).and_then(|conn| {
+        encryption::unlock(&conn, db_path)?;
+        conn.query_row("PRAGMA integrity_check", [], |r| r.get::<_, String>(0))
+    });
+    match check {
+        Ok(ref c) if c == "ok" => Ok(()),
+        other => Err(AppError::Database(format!(
+            "backup database failed its integrity check: {}",
+            other.map_err(|e| e.to_string()).unwrap_or_else(|e| e)
+        ))),
+    }
+}
+
+/// Replace the active workspace's database and media with a snapshot.
+/// Media files not in the snapshot are left alone. Emits
+/// `backup-restored`; the frontend should reload everything.
+#[tauri::command]
+pub async fn restore_backup(app: AppHandle, snapshot: String) -> AppResult<RestoreReport> {
+    let _busy = BusyGuard::acquire()?;
+    let (target, sealer) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        configured(&conn)?
+    };
+    let manifest: Manifest = serde_json::from_slice(
+        &sealer.open(&target.get(&format!("snapshots/{}.json", snapshot)).await?)?,
+    )?;
+
+    let app_dir = db::app_dir();
+    let mut report = RestoreReport {
+        snapshot,
+        restored_files: 0,
+        unchanged_files: 0,
+    };
+    for file in &manifest.files {
+        let dest = app_dir.join(restore_path(&file.path)?);
+        if let Ok(local) = std::fs::read(&dest) {
+            if sealer.object_name(&local) == file.object {
+                report.unchanged_files += 1;
+                continue;
+            }
+        }
+        let plain = sealer.open(&target.get(&file.object).await?)?;
+        if let Some(dir) = dest.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&dest, plain)?;
+        report.restored_files += 1;
+    }
+
+    let database = sealer.open(&target.get(&manifest.database).await?)?;
+    let db_path = db::get_db_path();
+    let tmp = db::with_suffix(&db_path, "restoring");
+    std::fs::write(&tmp, database)?;
+    if let Err(e) = verify_restored(&tmp, &db_path) {
+        std::fs::remove_file(&tmp).ok();
+        return Err(e);
+    }
+    {
+        let db = app.state::<Database>();
+        let mut conn = db.conn();
+        db::swap_file(&mut conn, &db_path, &tmp)?;
+    }
+    {
+        let db = app.state::<Database>();
+        app.state::<NetworkMonitor>().reload(&db);
+    }
+    report.restored_files += 1;
+    tracing::info!(snapshot = %report.snapshot, files = report.restored_files, "restored backup");
+    AppEvent::BackupRestored(report.clone()).emit(&app);
+    Ok(report)
+}
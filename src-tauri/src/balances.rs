@@ -0,0 +1,234 @@
+//! Credit and quota left on each provider account, fetched where the
+//! provider's API reports it and cached in `provider_balances` so screens
+//! and batch checks don't call out every time. Gemini reports no balance.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::{providers, settings, voices};
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+/// Cached balances older than this are fetched again.
+const CACHE_MINUTES: i64 = 10;
+
+pub mod unit {
+    pub const USD: &str = "usd";
+    pub const CHARACTERS: &str = "characters";
+}
+
+struct Account {
+    provider: &'static str,
+    label: &'static str,
+    /// None when the provider doesn't report a balance.
+    unit: Option<&'static str>,
+    key_setting: &'static str,
+}
+
+const ACCOUNTS: &[Account] = &[
+    Account {
+        provider: "fal",
+        label: "Fal.ai",
+        unit: Some(unit::USD),
+        key_setting: providers::FAL_KEY_SETTING,
+    },
+    Account {
+        provider: "elevenlabs",
+        label: "ElevenLabs",
+        unit: Some(unit::CHARACTERS),
+        key_setting: voices::ELEVENLABS_KEY_SETTING,
+    },
+    Account {
+        provider: "gemini",
+        label: "Google Gemini",
+        unit: None,
+        key_setting: "",
+    },
+];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProviderBalance {
+    pub provider: String,
+    pub label: String,
+    /// False when the provider's API doesn't report a balance.
+    pub supported: bool,
+    pub remaining: Option<f64>,
+    /// The plan's allowance, for quota-based providers.
+    pub limit: Option<f64>,
+    pub unit: String,
+    pub fetched_at: Option<String>,
+    /// Why the last fetch failed; the other fields are from before it.
+    pub error: String,
+}
+
+fn cached(conn: &Connection, account: &Account) -> AppResult<Option<ProviderBalance>> {
+    Ok(conn
+        .query_row(
+            "SELECT remaining, quota_limit, unit, error, fetched_at FROM provider_balances
+             WHERE provider = ?1",
+            [account.provider],
+            |r| {
+                Ok(ProviderBalance {
+                    provider: account.provider.to_string(),
+                    label: account.label.to_string(),
+                    supported: true,
+                    remaining: r.get(0)?,
+                    limit: r.get(1)?,
+                    unit: r.get(2)?,
+                    error: r.get(3)?,
+                    fetched_at: r.get(4)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+fn store(conn: &Connection, balance: &ProviderBalance) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO provider_balances (provider, remaining, quota_limit, unit, error, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(provider) DO UPDATE SET
+            remaining = coalesce(excluded.remaining, remaining),
+            quota_limit = coalesce(excluded.quota_limit, quota_limit),
+            unit = excluded.unit, error = excluded.error, fetched_at = excluded.fetched_at",
+        params![
+            balance.provider,
+            balance.remaining,
+            balance.limit,
+            balance.unit,
+            balance.error,
+            balance.fetched_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Remaining balance and allowance, from the provider.
+async fn fetch(account: &Account, api_key: &str) -> AppResult<(f64, Option<f64>)> {
+    match account.provider {
+        "fal" => Ok((providers::fal_balance(api_key).await?, None)),
+        "elevenlabs" => {
+            let (used, limit) = voices::subscription_usage(api_key).await?;
+            Ok(((limit - used).max(0.0), Some(limit)))
+        }
+        other => Err(AppError::Invalid(format!(
+            "{} doesn't report a balance",
+            other
+        ))),
+    }
+}
+
+/// The last Fal.ai credit balance we saw, in USD, however old.
+pub fn fal_remaining(conn: &Connection) -> AppResult<Option<f64>> {
+    Ok(conn
+        .query_row(
+            "SELECT remaining FROM provider_balances WHERE provider = 'fal'",
+            [],
+            |r| r.get(0),
+        )
+        .optional()?
+        .flatten())
+}
+
+/// Balances for every provider account as the cache has them, each with
+/// the API key to fetch a new one with when the cached one is older than
+/// ten minutes or `refresh` is set. Accounts without an API key are
+/// listed with an error and not fetched.
+pub fn cached_balances(
+    conn: &Connection,
+    refresh: bool,
+) -> AppResult<Vec<(ProviderBalance, Option<String>)>> {
+    let fresh_after = clock::format(Utc::now() - Duration::minutes(CACHE_MINUTES));
+    let mut balances = Vec::new();
+    for account in ACCOUNTS {
+        let Some(account_unit) = account.unit else {
+            balances.push((
+                ProviderBalance {
+                    provider: account.provider.to_string(),
+                    label: account.label.to_string(),
+                    supported: false,
+                    remaining: None,
+                    limit: None,
+                    unit: String::new(),
+                    fetched_at: None,
+                    error: String::new(),
+                },
+                None,
+            ));
+            continue;
+        };
+        let cache = cached(conn, account)?;
+        let api_key = settings::get(conn, account.key_setting)?.unwrap_or_default();
+        let current = cache.as_ref().is_some_and(|c| {
+            c.error.is_empty() && c.fetched_at.as_deref() >= Some(fresh_after.as_str())
+        });
+        if current && !refresh {
+            balances.extend(cache.map(|c| (c, None)));
+            continue;
+        }
+        let mut balance = cache.unwrap_or(ProviderBalance {
+            provider: account.provider.to_string(),
+            label: account.label.to_string(),
+            supported: true,
+            remaining: None,
+            limit: None,
+            unit: account_unit.to_string(),
+            fetched_at: None,
+            error: String::new(),
+        });
+        if api_key.is_empty() {
+            balance.error = format!("{} API key not configured", account.label);
+            balances.push((balance, None));
+            continue;
+        }
+        balances.push((balance, Some(api_key)));
+    }
+    Ok(balances)
+}
+
+/// Store what fetching `balance` from its provider returned. A failed
+/// fetch keeps the figures from before it, with the error alongside.
+pub fn record_fetch(
+    conn: &Connection,
+    balance: &mut ProviderBalance,
+    fetched: AppResult<(f64, Option<f64>)>,
+) -> AppResult<()> {
+    match fetched {
+        Ok((remaining, limit)) => {
+            balance.remaining = Some(remaining);
+            balance.limit = limit;
+            balance.error = String::new();
+        }
+        Err(e) => balance.error = e.to_string(),
+    }
+    balance.fetched_at = Some(clock::now());
+    store(conn, balance)
+}
+
+/// Balances for every provider account, from the cache unless it is older
+/// than ten minutes or `refresh` is set.
+#[tauri::command]
+pub async fn get_provider_balances(
+    app: AppHandle,
+    refresh: Option<bool>,
+) -> AppResult<Vec<ProviderBalance>> {
+    let cached = cached_balances(&app.state::<Database>().conn(), refresh.unwrap_or(false))?;
+    let mut balances = Vec::new();
+    for (mut balance, api_key) in cached {
+        if let Some(api_key) = api_key {
+            let account = ACCOUNTS
+                .iter()
+                .find(|a| a.provider == balance.provider)
+                .expect("cached balances come from ACCOUNTS");
+            let fetched = fetch(account, &api_key).await;
+            record_fetch(&app.state::<Database>().conn(), &mut balance, fetched)?;
+        }
+        balances.push(balance);
+    }
+    Ok(balances)
+}
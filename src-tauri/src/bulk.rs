@@ -0,0 +1,439 @@
+//! Bulk scene operations. Each runs in one transaction and reports which
+//! scenes it changed and why the others were skipped, instead of the UI
+//! making one call per scene. Bulk status changes live in `workflow`
+//! (`bulk_transition_scenes`).
+
+use crate::activity::{self, FieldChange};
+use crate::balances;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::generation::{self, GenerationSettings};
+use crate::jobs::{self, kind};
+use crate::moderation;
+use crate::network::NetworkMonitor;
+use crate::providers::{self, GenerationRequest, ModelSpec};
+use crate::scene_characters;
+use crate::scene_links;
+use crate::scenes::{self, Scene};
+use crate::workflow::{self, SceneStatus};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedScene {
+    pub scene_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BulkResult {
+    /// Scenes changed (or, for generation, the jobs queued).
+    pub succeeded: Vec<String>,
+    pub rejected: Vec<RejectedScene>,
+}
+
+impl BulkResult {
+    /// Record a per-scene failure; database errors abort the whole batch.
+    fn reject(&mut self, scene_id: &str, e: AppError) -> AppResult<()> {
+        if let AppError::Database(_) = e {
+            return Err(e);
+        }
+        self.rejected.push(RejectedScene {
+            scene_id: scene_id.to_string(),
+            reason: e.to_string(),
+        });
+        Ok(())
+    }
+}
+
+/// Delete many scenes. Locked scenes are skipped.
+pub fn delete_scenes(conn: &Connection, scene_ids: &[String]) -> AppResult<BulkResult> {
+    let tx = conn.unchecked_transaction()?;
+    let mut result = BulkResult::default();
+    for scene_id in scene_ids {
+        match scenes::delete(&tx, scene_id) {
+            Ok(()) => result.succeeded.push(scene_id.clone()),
+            Err(e) => result.reject(scene_id, e)?,
+        }
+    }
+    tx.commit()?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn bulk_delete_scenes(
+    db: State<'_, Database>,
+    scene_ids: Vec<String>,
+) -> AppResult<BulkResult> {
+    delete_scenes(&db.conn(), &scene_ids)
+}
+
+/// How `assign_characters` changes each scene's cast.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum AssignMode {
+    /// Add the characters, keeping anyone already in the scene.
+    #[default]
+    Add,
+    Remove,
+    /// Make the cast exactly the given characters.
+    Replace,
+}
+
+fn assign_one(
+    conn: &Connection,
+    scene_id: &str,
+    characters: &[String],
+    mode: AssignMode,
+) -> AppResult<bool> {
+    workflow::ensure_editable(conn, scene_id)?;
+    let scene = scenes::get_scene(conn, scene_id)?;
+    let mut cast = scene.characters.clone();
+    match mode {
+        AssignMode::Add => {
+            for c in characters {
+                let id = scene_characters::resolve(conn, &scene.project_id, c)?;
+                if !cast.contains(&id) {
+                    cast.push(id);
+                }
+            }
+        }
+        AssignMode::Remove => {
+            let mut gone = Vec::new();
+            for c in characters {
+                gone.extend(scene_characters::find(conn, &scene.project_id, c)?);
+            }
+            cast.retain(|id| !gone.contains(id));
+        }
+        AssignMode::Replace => {
+            cast.clear();
+            for c in characters {
+                let id = scene_characters::resolve(conn, &scene.project_id, c)?;
+                if !cast.contains(&id) {
+                    cast.push(id);
+                }
+            }
+        }
+    }
+    if cast == scene.characters {
+        return Ok(false);
+    }
+    scene_characters::set_cast(conn, &scene, &cast)?;
+    activity::record(
+        conn,
+        &scene.project_id,
+        "scene",
+        scene_id,
+        "updated",
+        &format!("Recast scene {}", scene.scene_number),
+        &[FieldChange {
+            field: "characters".into(),
+            old: scene_characters::names(conn, &scene.characters)?.join(", "),
+            new: scene_characters::names(conn, &cast)?.join(", "),
+        }],
+    )?;
+    Ok(true)
+}
+
+/// Add, remove or replace characters across many scenes. Scenes whose
+/// cast is already as asked are neither changed nor rejected.
+pub fn assign_characters(
+    conn: &Connection,
+    scene_ids: &[String],
+    characters: &[String],
+    mode: AssignMode,
+) -> AppResult<BulkResult> {
+    let characters: Vec<String> = characters
+        .iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    let tx = conn.unchecked_transaction()?;
+    let mut result = BulkResult::default();
+    for scene_id in scene_ids {
+        match assign_one(&tx, scene_id, &characters, mode) {
+            Ok(true) => result.succeeded.push(scene_id.clone()),
+            Ok(false) => {}
+            Err(e) => result.reject(scene_id, e)?,
+        }
+    }
+    tx.commit()?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn bulk_assign_characters(
+    db: State<'_, Database>,
+    scene_ids: Vec<String>,
+    characters: Vec<String>,
+    mode: Option<AssignMode>,
+) -> AppResult<BulkResult> {
+    assign_characters(
+        &db.conn(),
+        &scene_ids,
+        &characters,
+        mode.unwrap_or_default(),
+    )
+}
+
+/// Options shared by every job in a bulk generation.
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BulkGenerationOptions {
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+    #[serde(default)]
+    pub aspect_ratio: Option<String>,
+    /// Queue the batch even if its estimated cost is more than the Fal.ai
+    /// credit left.
+    #[serde(default)]
+    pub ignore_balance: bool,
+}
+
+fn request_for(
+    scene: &Scene,
+    model: &ModelSpec,
+    settings: &GenerationSettings,
+    options: &BulkGenerationOptions,
+) -> AppResult<GenerationRequest> {
+    if scene.prompt.trim().is_empty() {
+        return Err(AppError::Invalid(format!(
+            "scene {} has no prompt yet",
+            scene.scene_number
+        )));
+    }
+    settings.check_model(model)?;
+    let duration = settings
+        .duration
+        .unwrap_or(scene.duration.max(1) as u32)
+        .min(model.max_duration);
+    Ok(GenerationRequest {
+        prompt: scene.prompt.clone(),
+        negative_prompt: options.negative_prompt.clone(),
+        image_url: export::embeddable_image(&scene.conditioning_image),
+        duration,
+        aspect_ratio: options
+            .aspect_ratio
+            .clone()
+            .unwrap_or_else(|| "16:9".into()),
+        seed: None,
+        resolution: settings.resolution.clone(),
+        motion_strength: settings.motion_strength,
+    })
+}
+
+/// One scene's job in a bulk generation, before it is stored.
+pub struct Prepared {
+    pub scene_id: String,
+    pub model: &'static str,
+    pub request: GenerationRequest,
+    /// Scenes in the batch whose takes this one waits for.
+    pub waits: Vec<String>,
+}
+
+/// Queue a new take for each scene from its current prompt, with the
+/// scene's generation settings (see `generation`): a scene's own model
+/// override wins over `provider`, which wins over the project default.
+/// Scenes linked by `scene_links` are queued upstream first; a dependent
+/// whose upstream scene is in the batch waits for that take to finish (and
+/// starts from its end frame when linked that way), one whose upstream
+/// scene isn't must already have a finished take to continue from. All
+/// jobs are stored in one transaction and then submitted in the background
+/// through the offline queue, oldest first. `succeeded` holds the new job
+/// ids. The whole batch is refused if its estimated cost is more than the
+/// last known Fal.ai balance, unless `ignore_balance` is set.
+#[tauri::command]
+pub async fn bulk_queue_generation(
+    app: AppHandle,
+    scene_ids: Vec<String>,
+    provider: Option<String>,
+    options: Option<BulkGenerationOptions>,
+) -> AppResult<BulkResult> {
+    if let Some(provider) = &provider {
+        providers::model(provider)?;
+    }
+    let options = options.unwrap_or_default();
+    let mut result = BulkResult::default();
+
+    let order = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        scene_links::generation_order(&conn, &scene_ids)?
+    };
+    let batch: HashSet<&str> = order.iter().map(String::as_str).collect();
+    let mut accepted: Vec<Prepared> = Vec::new();
+    for scene_id in &order {
+        let prepared = prepare(
+            &app,
+            scene_id,
+            &batch,
+            &accepted,
+            provider.as_deref(),
+            &options,
+        );
+        let prepared = match prepared.await {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                result.reject(scene_id, e)?;
+                continue;
+            }
+        };
+        match moderation::precheck(&app, scene_id, &prepared.request.prompt).await {
+            Ok(()) => accepted.push(prepared),
+            Err(e) => result.reject(scene_id, e)?,
+        }
+    }
+
+    let queued = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        if !options.ignore_balance {
+            ensure_affordable(&conn, &accepted)?;
+        }
+        store_batch(&conn, &accepted)?
+    };
+
+    for (scene_id, job_id) in queued {
+        workflow::advance_if_allowed(&app, &scene_id, SceneStatus::Generating);
+        jobs::emit_job(&app, &job_id);
+        result.succeeded.push(job_id);
+    }
+    if !result.succeeded.is_empty() && app.state::<NetworkMonitor>().can_submit() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { jobs::flush_deferred(&app).await });
+    }
+    Ok(result)
+}
+
+/// Refuse a batch whose estimated cost is more than the last known Fal.ai
+/// balance.
+pub fn ensure_affordable(conn: &Connection, accepted: &[Prepared]) -> AppResult<()> {
+    let estimate = accepted
+        .iter()
+        .map(|p| providers::model(p.model).map(|m| m.estimate_cost(p.request.duration)))
+        .sum::<AppResult<f64>>()?;
+    let remaining = balances::fal_remaining(conn)?;
+    if let Some(remaining) = remaining.filter(|r| estimate > *r) {
+        return Err(AppError::Invalid(format!(
+            "this batch costs about ${:.2} but only ${:.2} of Fal.ai credit is left",
+            estimate, remaining
+        )));
+    }
+    Ok(())
+}
+
+/// Store the batch's jobs in one transaction, each held until the takes of
+/// the scenes it waits for finish. Returns each scene with its job id.
+pub fn store_batch(conn: &Connection, accepted: &[Prepared]) -> AppResult<Vec<(String, String)>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut queued = Vec::new();
+    let mut job_for: HashMap<&str, String> = HashMap::new();
+    for p in accepted {
+        let job_id = jobs::insert_job(
+            &tx,
+            &p.scene_id,
+            p.model,
+            kind::VIDEO,
+            &serde_json::to_string(&p.request)?,
+        )?;
+        let upstream: Vec<String> = p
+            .waits
+            .iter()
+            .filter_map(|w| job_for.get(w.as_str()).cloned())
+            .collect();
+        jobs::hold(&tx, &job_id, &upstream)?;
+        job_for.insert(&p.scene_id, job_id.clone());
+        queued.push((p.scene_id.clone(), job_id));
+    }
+    tx.commit()?;
+    Ok(queued)
+}
+
+/// The job for one scene of a bulk generation, and the finished takes of
+/// upstream scenes outside the batch whose end frames it may start from.
+/// `accepted` holds the scenes already taken, in order.
+pub fn prepare_scene(
+    conn: &Connection,
+    scene_id: &str,
+    batch: &HashSet<&str>,
+    accepted: &[Prepared],
+    provider: Option<&str>,
+    options: &BulkGenerationOptions,
+) -> AppResult<(Prepared, Vec<(String, String)>)> {
+    let scene = scenes::get_scene(conn, scene_id)?;
+    let defaults = generation::project_defaults(conn, &scene.project_id)?;
+    let model = generation::resolve_model(&scene.generation, provider, &defaults)?;
+    let settings = GenerationSettings {
+        model: Some(model.key.to_string()),
+        ..scene.generation.or(&defaults)
+    };
+    let request = request_for(&scene, model, &settings, options)?;
+    let links = scene_links::upstream_of(conn, scene_id)?;
+    let mut outside = Vec::new();
+    for link in &links {
+        let upstream = scenes::get_scene(conn, &link.upstream_id)?;
+        if batch.contains(link.upstream_id.as_str()) {
+            if !accepted.iter().any(|p| p.scene_id == link.upstream_id) {
+                return Err(AppError::Invalid(format!(
+                    "depends on scene {}, which could not be queued",
+                    upstream.scene_number
+                )));
+            }
+            continue;
+        }
+        match scene_links::finished_take(conn, &link.upstream_id)? {
+            Some(video) => outside.push((link.upstream_id.clone(), video)),
+            None => {
+                return Err(AppError::Invalid(format!(
+                    "depends on scene {}, which has no finished take yet",
+                    upstream.scene_number
+                )))
+            }
+        }
+    }
+    let waits = links
+        .into_iter()
+        .map(|l| l.upstream_id)
+        .filter(|id| batch.contains(id.as_str()))
+        .collect();
+    let prepared = Prepared {
+        scene_id: scene_id.to_string(),
+        model: model.key,
+        request,
+        waits,
+    };
+    Ok((prepared, outside))
+}
+
+/// `prepare_scene`, starting from the end frame of an upstream take outside
+/// the batch where the link asks for it.
+async fn prepare(
+    app: &AppHandle,
+    scene_id: &str,
+    batch: &HashSet<&str>,
+    accepted: &[Prepared],
+    provider: Option<&str>,
+    options: &BulkGenerationOptions,
+) -> AppResult<Prepared> {
+    let (mut prepared, outside) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        prepare_scene(&conn, scene_id, batch, accepted, provider, options)?
+    };
+    for (upstream_id, video) in outside {
+        if let Some(frame) = scene_links::end_frame_for(app, scene_id, &upstream_id, &video).await?
+        {
+            prepared.request.image_url = Some(frame);
+        }
+    }
+    Ok(prepared)
+}
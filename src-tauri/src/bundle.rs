@@ -0,0 +1,322 @@
+//! A project as one self-contained JSON document: the project, its
+//! characters and its scenes. Used to move projects between machines.
+
+use crate::activity;
+use crate::characters::{self, Character, VoiceProfile};
+use crate::clock;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::generation::{self, GenerationSettings};
+use crate::library::{self, AssetLink};
+use crate::ordering;
+use crate::scene_characters::{self, CastMember};
+use crate::scenes::{self, Scene};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the bundle layout changes incompatibly.
+pub const BUNDLE_FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBundle {
+    pub format: u32,
+    /// Increases each time the bundle is published; see `cloud_sync`.
+    #[serde(default)]
+    pub version: i64,
+    /// When the project last changed on the machine that wrote the bundle.
+    pub updated_at: String,
+    pub project: BundleProject,
+    pub characters: Vec<BundleCharacter>,
+    pub scenes: Vec<BundleScene>,
+    /// Shared library assets the project links to, by content hash.
+    #[serde(default)]
+    pub assets: Vec<AssetLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleProject {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub genre: String,
+    #[serde(default)]
+    pub synopsis: String,
+    #[serde(default)]
+    pub tone: String,
+    #[serde(default)]
+    pub generation: GenerationSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleCharacter {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub photo_data: String,
+    pub voice: VoiceProfile,
+}
+
+impl From<Character> for BundleCharacter {
+    fn from(c: Character) -> Self {
+        Self {
+            id: c.id,
+            name: c.name,
+            description: c.description,
+            photo_data: c.photo_data,
+            voice: c.voice,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleScene {
+    pub id: String,
+    pub scene_number: i64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub prompt: String,
+    pub camera_angle: String,
+    pub lighting: String,
+    pub duration: i64,
+    #[serde(default)]
+    pub dialog: String,
+    /// Character ids; bundles from before casts may hold names.
+    #[serde(default)]
+    pub characters: Vec<String>,
+    /// Roles and importance; empty in older bundles, which are cast from
+    /// `characters`.
+    #[serde(default)]
+    pub cast: Vec<CastMember>,
+    pub status: String,
+    #[serde(default)]
+    pub video_url: String,
+    #[serde(default)]
+    pub sort_order: i64,
+    #[serde(default)]
+    pub conditioning_image: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub scene_suffix: String,
+    /// Empty in bundles from before ordering keys; rebalanced on import.
+    #[serde(default)]
+    pub order_key: String,
+    #[serde(default)]
+    pub generation: GenerationSettings,
+}
+
+impl From<Scene> for BundleScene {
+    fn from(s: Scene) -> Self {
+        Self {
+            id: s.id,
+            scene_number: s.scene_number,
+            title: s.title,
+            description: s.description,
+            prompt: s.prompt,
+            camera_angle: s.camera_angle,
+            lighting: s.lighting,
+            duration: s.duration,
+            dialog: s.dialog,
+            characters: s.characters,
+            cast: s.cast,
+            status: s.status,
+            video_url: s.video_url,
+            sort_order: s.sort_order,
+            conditioning_image: s.conditioning_image,
+            location: s.location,
+            scene_suffix: s.scene_suffix,
+            order_key: s.order_key,
+            generation: s.generation,
+        }
+    }
+}
+
+/// Latest change to the project or anything in it, as a SQLite datetime.
+pub fn updated_at(conn: &Connection, project_id: &str) -> AppResult<String> {
+    Ok(conn.query_row(
+        "SELECT max(coalesce(p.updated_at, ''), coalesce(
+            (SELECT max(created_at) FROM activity_log a WHERE a.project_id = p.id), ''))
+         FROM projects p WHERE p.id = ?1",
+        [project_id],
+        |r| r.get(0),
+    )?)
+}
+
+pub fn export_bundle(conn: &Connection, project_id: &str) -> AppResult<ProjectBundle> {
+    let project = export::load_project(conn, project_id)?;
+    Ok(ProjectBundle {
+        format: BUNDLE_FORMAT,
+        version: 0,
+        updated_at: updated_at(conn, project_id)?,
+        project: BundleProject {
+            id: project_id.to_string(),
+            name: project.name,
+            genre: project.genre,
+            synopsis: project.synopsis,
+            tone: project.tone,
+            generation: generation::project_defaults(conn, project_id)?,
+        },
+        characters: characters::list_for_project(conn, project_id)?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        scenes: scenes::list_for_project(conn, project_id)?
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        assets: library::links_for_project(conn, project_id)?,
+    })
+}
+
+/// Make the local copy of the bundle's project match it exactly, creating
+/// the project if needed. Rows are updated in place so comments, takes and
+/// schedules attached to surviving scenes and characters are kept.
+pub fn import_bundle(conn: &Connection, bundle: &ProjectBundle) -> AppResult<()> {
+    if bundle.format > BUNDLE_FORMAT {
+        return Err(AppError::Invalid(format!(
+            "bundle format {} is newer than this app supports; update the app",
+            bundle.format
+        )));
+    }
+    let p = &bundle.project;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO projects (id, name, genre, synopsis, tone, created_at, updated_at,
+            generation_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7)
+         ON CONFLICT(id) DO UPDATE SET name = excluded.name, genre = excluded.genre,
+            synopsis = excluded.synopsis, tone = excluded.tone, updated_at = excluded.updated_at,
+            generation_json = excluded.generation_json",
+        params![
+            p.id,
+            p.name,
+            p.genre,
+            p.synopsis,
+            p.tone,
+            clock::now(),
+            serde_json::to_string(&p.generation)?
+        ],
+    )?;
+
+    for c in &bundle.characters {
+        tx.execute(
+            "INSERT INTO characters (id, project_id, name, description, photo_data, voice_engine,
+                voice_id, voice_name, voice_speed, voice_stability, voice_similarity,
+                voice_clone_status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT(id) DO UPDATE SET name = excluded.name,
+                description = excluded.description, photo_data = excluded.photo_data,
+                voice_engine = excluded.voice_engine, voice_id = excluded.voice_id,
+                voice_name = excluded.voice_name, voice_speed = excluded.voice_speed,
+                voice_stability = excluded.voice_stability,
+                voice_similarity = excluded.voice_similarity,
+                voice_clone_status = excluded.voice_clone_status
+             WHERE characters.project_id = excluded.project_id",
+            params![
+                c.id,
+                p.id,
+                c.name,
+                c.description,
+                c.photo_data,
+                c.voice.engine,
+                c.voice.voice_id,
+                c.voice.voice_name,
+                c.voice.speed,
+                c.voice.stability,
+                c.voice.similarity,
+                c.voice.clone_status
+            ],
+        )?;
+    }
+    let keep: Vec<&str> = bundle.characters.iter().map(|c| c.id.as_str()).collect();
+    tx.execute(
+        "DELETE FROM characters WHERE project_id = ?1
+         AND id NOT IN (SELECT value FROM json_each(?2))",
+        params![p.id, serde_json::to_string(&keep)?],
+    )?;
+
+    for s in &bundle.scenes {
+        tx.execute(
+            "INSERT INTO scenes (id, project_id, scene_number, title, description, prompt,
+                camera_angle, lighting, duration, dialog, status, video_url, sort_order,
+                conditioning_image, location, scene_suffix, order_key, generation_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18)
+             ON CONFLICT(id) DO UPDATE SET scene_number = excluded.scene_number,
+                title = excluded.title, description = excluded.description,
+                prompt = excluded.prompt, camera_angle = excluded.camera_angle,
+                lighting = excluded.lighting, duration = excluded.duration,
+                dialog = excluded.dialog, status = excluded.status, video_url = excluded.video_url,
+                sort_order = excluded.sort_order,
+                conditioning_image = excluded.conditioning_image, location = excluded.location,
+                scene_suffix = excluded.scene_suffix, order_key = excluded.order_key,
+                generation_json = excluded.generation_json
+             WHERE scenes.project_id = excluded.project_id",
+            params![
+                s.id,
+                p.id,
+                s.scene_number,
+                s.title,
+                s.description,
+                s.prompt,
+                s.camera_angle,
+                s.lighting,
+                s.duration,
+                s.dialog,
+                s.status,
+                s.video_url,
+                s.sort_order,
+                s.conditioning_image,
+                s.location,
+                s.scene_suffix,
+                s.order_key,
+                serde_json::to_string(&s.generation)?
+            ],
+        )?;
+        let scene = scenes::get_scene(&tx, &s.id)?;
+        if scene.project_id != p.id {
+            continue;
+        }
+        if s.cast.is_empty() {
+            scene_characters::set_cast(&tx, &scene, &s.characters)?;
+        } else {
+            scene_characters::replace(&tx, &scene, &s.cast)?;
+        }
+    }
+    let keep: Vec<&str> = bundle.scenes.iter().map(|s| s.id.as_str()).collect();
+    tx.execute(
+        "DELETE FROM scenes WHERE project_id = ?1
+         AND id NOT IN (SELECT value FROM json_each(?2))",
+        params![p.id, serde_json::to_string(&keep)?],
+    )?;
+    if bundle.scenes.iter().any(|s| s.order_key.is_empty()) {
+        ordering::rebalance(&tx, &p.id)?;
+    }
+    library::replace_links(&tx, &p.id, &bundle.assets)?;
+    tx.execute(
+        "DELETE FROM comments WHERE (entity_type = 'scene' AND entity_id NOT IN (SELECT id FROM scenes))
+            OR (entity_type = 'character' AND entity_id NOT IN (SELECT id FROM characters))",
+        [],
+    )?;
+
+    activity::record(
+        &tx,
+        &p.id,
+        "project",
+        &p.id,
+        "imported",
+        &format!("Imported bundle version {}", bundle.version),
+        &[],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
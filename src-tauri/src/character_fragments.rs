@@ -0,0 +1,342 @@
+//! Reusable prompt text per character: how they look, how they move, and
+//! what the generator must avoid. The prompt assembler writes these in
+//! wherever the character is in a scene's cast, so every take describes
+//! them the same way. Each edit keeps the previous text as a numbered
+//! version and can flag the character's generated scenes as stale.
+
+use crate::activity::{self, FieldChange};
+use crate::characters;
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::stale;
+use crate::validation::{Validator, MAX_TEXT_LEN};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::State;
+use ts_rs::TS;
+
+/// Stored in `character_fragments.kind`; a character has at most one of
+/// each.
+pub mod kind {
+    /// Face, build, hair, signature look.
+    pub const APPEARANCE: &str = "appearance";
+    /// Posture, gait, habits on screen.
+    pub const MANNERISMS: &str = "mannerisms";
+    /// Terms for the negative prompt, e.g. "beard" for a clean-shaven lead.
+    pub const NEGATIVE: &str = "negative";
+
+    pub const ALL: &[&str] = &[APPEARANCE, MANNERISMS, NEGATIVE];
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CharacterFragment {
+    pub id: String,
+    pub character_id: String,
+    pub kind: String,
+    pub text: String,
+    /// 1 for the first text, bumped by every change to it.
+    #[ts(type = "number")]
+    pub version: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct FragmentVersion {
+    #[ts(type = "number")]
+    pub version: i64,
+    pub text: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct FragmentSave {
+    pub fragment: CharacterFragment,
+    /// Scenes flagged stale by this change.
+    pub stale_scenes: Vec<String>,
+}
+
+/// A scene's character fragments, ready for prompt assembly.
+#[derive(Debug, Clone, Default)]
+pub struct SceneFragments {
+    /// One per cast member with fragments, e.g. "Mara: cropped silver hair,
+    /// scar over left eye; fidgets with her ring".
+    pub characters: Vec<String>,
+    pub negative: Vec<String>,
+}
+
+const FRAGMENT_COLUMNS: &str = "id, character_id, kind, text, version, created_at, updated_at";
+
+fn row_to_fragment(row: &Row) -> rusqlite::Result<CharacterFragment> {
+    Ok(CharacterFragment {
+        id: row.get(0)?,
+        character_id: row.get(1)?,
+        kind: row.get(2)?,
+        text: row.get(3)?,
+        version: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn find(conn: &Connection, character_id: &str, kind: &str) -> AppResult<Option<CharacterFragment>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM character_fragments WHERE character_id = ?1 AND kind = ?2",
+                FRAGMENT_COLUMNS
+            ),
+            params![character_id, kind],
+            row_to_fragment,
+        )
+        .optional()?)
+}
+
+fn list_for_character(conn: &Connection, character_id: &str) -> AppResult<Vec<CharacterFragment>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM character_fragments WHERE character_id = ?1 ORDER BY kind",
+        FRAGMENT_COLUMNS
+    ))?;
+    let fragments = stmt
+        .query_map([character_id], row_to_fragment)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(fragments)
+}
+
+/// Fragments for everyone in a scene's cast, in billing order.
+pub fn for_scene(conn: &Connection, scene_id: &str) -> AppResult<SceneFragments> {
+    let mut stmt = conn.prepare(
+        "SELECT c.name, f.kind, f.text FROM scene_characters sc
+         JOIN characters c ON c.id = sc.character_id
+         JOIN character_fragments f ON f.character_id = sc.character_id
+         WHERE sc.scene_id = ?1 AND trim(f.text) <> ''
+         ORDER BY sc.position, f.kind",
+    )?;
+    let rows = stmt
+        .query_map([scene_id], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut fragments = SceneFragments::default();
+    let mut described: Vec<(String, Vec<String>)> = Vec::new();
+    for (name, fragment_kind, text) in rows {
+        let text = text.trim().to_string();
+        if fragment_kind == kind::NEGATIVE {
+            fragments.negative.push(text);
+            continue;
+        }
+        match described.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, parts)) => parts.push(text),
+            None => described.push((name, vec![text])),
+        }
+    }
+    fragments.characters = described
+        .into_iter()
+        .map(|(name, parts)| format!("{}: {}", name, parts.join("; ")))
+        .collect();
+    Ok(fragments)
+}
+
+pub fn list(conn: &Connection, character_id: &str) -> AppResult<Vec<CharacterFragment>> {
+    characters::get_character(conn, character_id)?;
+    list_for_character(conn, character_id)
+}
+
+#[tauri::command]
+pub fn list_character_fragments(
+    db: State<'_, Database>,
+    character_id: String,
+) -> AppResult<Vec<CharacterFragment>> {
+    list(&db.conn(), &character_id)
+}
+
+/// Every text a fragment has had, newest first.
+pub fn versions(conn: &Connection, fragment_id: &str) -> AppResult<Vec<FragmentVersion>> {
+    let mut stmt = conn.prepare(
+        "SELECT version, text, created_at FROM character_fragment_versions
+         WHERE fragment_id = ?1 ORDER BY version DESC",
+    )?;
+    let versions = stmt
+        .query_map([&fragment_id], |r| {
+            Ok(FragmentVersion {
+                version: r.get(0)?,
+                text: r.get(1)?,
+                created_at: r.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if versions.is_empty() {
+        return Err(AppError::NotFound(format!("fragment {}", fragment_id)));
+    }
+    Ok(versions)
+}
+
+#[tauri::command]
+pub fn list_fragment_versions(
+    db: State<'_, Database>,
+    fragment_id: String,
+) -> AppResult<Vec<FragmentVersion>> {
+    versions(&db.conn(), &fragment_id)
+}
+
+/// Set one of a character's fragments, creating it if need be. A changed
+/// text becomes a new version; with `mark_stale`, scenes featuring the
+/// character that already have takes are flagged for regeneration.
+pub fn save(
+    conn: &Connection,
+    character_id: &str,
+    kind: &str,
+    text: &str,
+    mark_stale: bool,
+) -> AppResult<FragmentSave> {
+    let text = text.trim().to_string();
+    let mut v = Validator::new();
+    v.uuid("characterId", character_id)
+        .one_of("kind", kind, kind::ALL)
+        .max_len("text", &text, MAX_TEXT_LEN);
+    v.finish()?;
+    let tx = conn.unchecked_transaction()?;
+    let character = characters::get_character(&tx, character_id)?;
+    projects::ensure_unlocked(&tx, &character.project_id)?;
+    let before = find(&tx, character_id, kind)?;
+    if let Some(unchanged) = before.as_ref().filter(|f| f.text == text) {
+        return Ok(FragmentSave {
+            fragment: unchanged.clone(),
+            stale_scenes: Vec::new(),
+        });
+    }
+    let now = clock::now();
+    let id = match &before {
+        Some(f) => {
+            tx.execute(
+                "UPDATE character_fragments SET text = ?2, version = version + 1, updated_at = ?3
+                 WHERE id = ?1",
+                params![f.id, text, now],
+            )?;
+            f.id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO character_fragments (id, project_id, character_id, kind, text,
+                    created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                params![id, character.project_id, character_id, kind, text, now],
+            )?;
+            id
+        }
+    };
+    let fragment = find(&tx, character_id, kind)?
+        .ok_or_else(|| AppError::NotFound(format!("fragment {}", id)))?;
+    tx.execute(
+        "INSERT INTO character_fragment_versions (fragment_id, version, text, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![fragment.id, fragment.version, text, now],
+    )?;
+    activity::record(
+        &tx,
+        &character.project_id,
+        "character",
+        character_id,
+        "updated",
+        &format!("Edited {}'s {} fragment", character.name, kind),
+        &[FieldChange {
+            field: format!("fragment.{}", kind),
+            old: before.map(|f| f.text).unwrap_or_default(),
+            new: text,
+        }],
+    )?;
+    let stale_scenes = if mark_stale {
+        stale::mark_featuring(
+            &tx,
+            character_id,
+            &format!("{}'s {} changed", character.name, kind),
+        )?
+    } else {
+        Vec::new()
+    };
+    tx.commit()?;
+    Ok(FragmentSave {
+        fragment,
+        stale_scenes,
+    })
+}
+
+#[tauri::command]
+pub fn save_character_fragment(
+    db: State<'_, Database>,
+    character_id: String,
+    kind: String,
+    text: String,
+    mark_stale: Option<bool>,
+) -> AppResult<FragmentSave> {
+    save(
+        &db.conn(),
+        &character_id,
+        &kind,
+        &text,
+        mark_stale.unwrap_or(false),
+    )
+}
+
+/// Remove a fragment and its history. With `mark_stale`, flags scenes as
+/// `save` does.
+pub fn delete(conn: &Connection, id: &str, mark_stale: bool) -> AppResult<Vec<String>> {
+    let tx = conn.unchecked_transaction()?;
+    let (character_id, fragment_kind, text): (String, String, String) = tx
+        .query_row(
+            "SELECT character_id, kind, text FROM character_fragments WHERE id = ?1",
+            [&id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("fragment {}", id)))?;
+    let character = characters::get_character(&tx, &character_id)?;
+    projects::ensure_unlocked(&tx, &character.project_id)?;
+    tx.execute("DELETE FROM character_fragments WHERE id = ?1", [&id])?;
+    activity::record(
+        &tx,
+        &character.project_id,
+        "character",
+        &character_id,
+        "updated",
+        &format!("Removed {}'s {} fragment", character.name, fragment_kind),
+        &[FieldChange {
+            field: format!("fragment.{}", fragment_kind),
+            old: text,
+            new: String::new(),
+        }],
+    )?;
+    let stale_scenes = if mark_stale {
+        stale::mark_featuring(
+            &tx,
+            &character_id,
+            &format!("{}'s {} changed", character.name, fragment_kind),
+        )?
+    } else {
+        Vec::new()
+    };
+    tx.commit()?;
+    Ok(stale_scenes)
+}
+
+#[tauri::command]
+pub fn delete_character_fragment(
+    db: State<'_, Database>,
+    id: String,
+    mark_stale: Option<bool>,
+) -> AppResult<Vec<String>> {
+    delete(&db.conn(), &id, mark_stale.unwrap_or(false))
+}
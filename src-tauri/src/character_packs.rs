@@ -0,0 +1,337 @@
+//! Character packs: a recurring cast as a portable zip so it can be reused
+//! in other projects or handed to a collaborator.
+//!
+//! A pack holds `pack.json` (names, descriptions and voice profiles), each
+//! character's photo under `photos/`, and their voice cloning samples
+//! under `samples/<character>/`. Importing always creates new characters.
+
+use crate::activity;
+use crate::characters::{self, Character, VoiceProfile};
+use crate::clock;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::projects;
+use crate::voices::{self, VoiceSample};
+use base64::Engine as _;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+use tauri::State;
+use ts_rs::TS;
+
+/// Bumped when the pack layout changes incompatibly.
+pub const PACK_FORMAT: u32 = 1;
+const MANIFEST: &str = "pack.json";
+/// Upper bound for any single file read out of a pack.
+const MAX_ENTRY_BYTES: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PackManifest {
+    pub format: u32,
+    pub name: String,
+    pub exported_at: String,
+    pub characters: Vec<PackCharacter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PackCharacter {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Path of the photo inside the zip, if the character has one.
+    #[serde(default)]
+    pub photo: Option<String>,
+    pub voice: VoiceProfile,
+    #[serde(default)]
+    pub samples: Vec<PackSample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PackSample {
+    /// Path inside the zip.
+    pub file: String,
+    /// Original file name, shown in the voice panel.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PackImport {
+    pub imported: Vec<Character>,
+    /// Names already used by a character in the project.
+    pub skipped: Vec<String>,
+}
+
+fn zip_err(e: zip::result::ZipError) -> AppError {
+    AppError::Invalid(format!("character pack: {}", e))
+}
+
+fn open_pack(path: &str) -> AppResult<zip::ZipArchive<std::fs::File>> {
+    zip::ZipArchive::new(std::fs::File::open(path)?).map_err(zip_err)
+}
+
+fn read_entry(zip: &mut zip::ZipArchive<std::fs::File>, name: &str) -> AppResult<Vec<u8>> {
+    let entry = zip.by_name(name).map_err(zip_err)?;
+    if entry.size() > MAX_ENTRY_BYTES {
+        return Err(AppError::Invalid(format!("{} is too large", name)));
+    }
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.take(MAX_ENTRY_BYTES).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_manifest(zip: &mut zip::ZipArchive<std::fs::File>) -> AppResult<PackManifest> {
+    let manifest: PackManifest = serde_json::from_slice(&read_entry(zip, MANIFEST)?)?;
+    if manifest.format > PACK_FORMAT {
+        return Err(AppError::Invalid(format!(
+            "character pack format {} is newer than this app supports; update the app",
+            manifest.format
+        )));
+    }
+    Ok(manifest)
+}
+
+fn photo_data_uri(bytes: &[u8]) -> AppResult<String> {
+    let format = image::guess_format(bytes)
+        .map_err(|_| AppError::Invalid("character pack photo is not an image".into()))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        format.to_mime_type(),
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// The characters to pack, each with their voice samples.
+pub fn load_cast(
+    conn: &Connection,
+    character_ids: &[String],
+) -> AppResult<Vec<(Character, Vec<VoiceSample>)>> {
+    if character_ids.is_empty() {
+        return Err(AppError::Invalid("choose at least one character".into()));
+    }
+    let mut cast = Vec::new();
+    for id in character_ids {
+        let character = characters::get_character(conn, id)?;
+        let samples = voices::samples_for(conn, id)?;
+        cast.push((character, samples));
+    }
+    Ok(cast)
+}
+
+/// Zip `cast`, with photos and voice samples, into a pack. Returns the
+/// path written.
+pub async fn write(
+    cast: Vec<(Character, Vec<VoiceSample>)>,
+    path: &str,
+    name: Option<String>,
+) -> AppResult<String> {
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| {
+            cast.iter()
+                .map(|(c, _)| c.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+
+    let out = export::resolve_output(path, "characters.aidc-pack.zip")?;
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&out)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut manifest = PackManifest {
+        format: PACK_FORMAT,
+        name,
+        exported_at: crate::ics::utc_stamp(),
+        characters: Vec::new(),
+    };
+
+    for (n, (character, samples)) in cast.into_iter().enumerate() {
+        let photo = match export::image_bytes(&character.photo_data).await {
+            Some(bytes) => {
+                let ext = image::guess_format(&bytes)
+                    .ok()
+                    .and_then(|f| f.extensions_str().first().copied())
+                    .unwrap_or("img");
+                let file = format!("photos/{}.{}", n + 1, ext);
+                zip.start_file(file.as_str(), options).map_err(zip_err)?;
+                zip.write_all(&bytes)?;
+                Some(file)
+            }
+            None => None,
+        };
+
+        let mut pack_samples = Vec::new();
+        for (i, sample) in samples.iter().enumerate() {
+            let Ok(bytes) = std::fs::read(&sample.file_path) else {
+                tracing::warn!(path = %sample.file_path, "voice sample missing; left out of pack");
+                continue;
+            };
+            let ext = Path::new(&sample.file_path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("mp3");
+            let file = format!("samples/{}/{}.{}", n + 1, i + 1, ext);
+            zip.start_file(file.as_str(), options).map_err(zip_err)?;
+            zip.write_all(&bytes)?;
+            pack_samples.push(PackSample {
+                file,
+                name: sample.file_name.clone(),
+            });
+        }
+
+        let mut voice = character.voice;
+        // A clone still in progress belongs to this machine's workflow.
+        if voice.clone_status != "ready" {
+            voice.clone_status = String::new();
+        }
+        manifest.characters.push(PackCharacter {
+            name: character.name,
+            description: character.description,
+            photo,
+            voice,
+            samples: pack_samples,
+        });
+    }
+
+    zip.start_file(MANIFEST, options).map_err(zip_err)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish().map_err(zip_err)?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn export_character_pack(
+    db: State<'_, Database>,
+    character_ids: Vec<String>,
+    path: String,
+    name: Option<String>,
+) -> AppResult<String> {
+    let cast = load_cast(&db.conn(), &character_ids)?;
+    write(cast, &path, name).await
+}
+
+/// What a pack contains, for showing before importing it.
+#[tauri::command]
+pub fn read_character_pack(path: String) -> AppResult<PackManifest> {
+    read_manifest(&mut open_pack(&path)?)
+}
+
+/// Add a pack's characters to a project. Characters whose name is already
+/// taken in the project are skipped rather than duplicated.
+/// Voice samples are copied into `samples_dir`.
+pub fn import(
+    conn: &Connection,
+    project_id: &str,
+    path: &str,
+    samples_dir: &Path,
+) -> AppResult<PackImport> {
+    let mut zip = open_pack(path)?;
+    let manifest = read_manifest(&mut zip)?;
+    projects::ensure_unlocked(conn, project_id)?;
+    let taken: Vec<String> = characters::list_for_project(conn, project_id)?
+        .into_iter()
+        .map(|c| c.name.to_lowercase())
+        .collect();
+
+    let mut result = PackImport {
+        imported: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut written = Vec::new();
+    let tx = conn.unchecked_transaction()?;
+    let outcome = (|| -> AppResult<()> {
+        for pc in &manifest.characters {
+            let name = pc.name.trim();
+            if name.is_empty() || taken.contains(&name.to_lowercase()) {
+                result.skipped.push(pc.name.clone());
+                continue;
+            }
+            let photo_data = match &pc.photo {
+                Some(file) => photo_data_uri(&read_entry(&mut zip, file)?)?,
+                None => String::new(),
+            };
+            let voice = &pc.voice;
+            let id = uuid::Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO characters (id, project_id, name, description, photo_data,
+                    voice_engine, voice_id, voice_name, voice_speed, voice_stability,
+                    voice_similarity, voice_clone_status, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    id,
+                    project_id,
+                    name,
+                    pc.description,
+                    photo_data,
+                    voice.engine,
+                    voice.voice_id,
+                    voice.voice_name,
+                    voice.speed,
+                    voice.stability,
+                    voice.similarity,
+                    voice.clone_status,
+                    clock::now()
+                ],
+            )?;
+
+            for sample in pc.samples.iter().take(voices::MAX_SAMPLES as usize) {
+                let ext = Path::new(&sample.file)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(str::to_lowercase)
+                    .unwrap_or_default();
+                if !voices::SAMPLE_EXTENSIONS.contains(&ext.as_str()) {
+                    continue;
+                }
+                let bytes = read_entry(&mut zip, &sample.file)?;
+                let sample_id = uuid::Uuid::new_v4().to_string();
+                let dest = samples_dir.join(format!("{}.{}", sample_id, ext));
+                std::fs::write(&dest, bytes)?;
+                written.push(dest.clone());
+                tx.execute(
+                    "INSERT INTO voice_samples (id, character_id, file_path, file_name)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![sample_id, id, dest.to_string_lossy(), sample.name],
+                )?;
+            }
+
+            activity::record(
+                &tx,
+                project_id,
+                "character",
+                &id,
+                "created",
+                &format!("Added character {} from pack {}", name, manifest.name),
+                &[],
+            )?;
+            result.imported.push(characters::get_character(&tx, &id)?);
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = outcome.and_then(|_| tx.commit().map_err(AppError::from)) {
+        for file in written {
+            let _ = std::fs::remove_file(file);
+        }
+        return Err(e);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn import_character_pack(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+) -> AppResult<PackImport> {
+    let samples_dir = db::media_dir("voice-samples")?;
+    import(&db.conn(), &project_id, &path, &samples_dir)
+}
@@ -0,0 +1,416 @@
+use crate::activity;
+use crate::clock;
+use crate::comments;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::paging::{self, Direction, Page};
+use crate::projects;
+use crate::validation::{Validate, Validator, MAX_IMAGE_LEN, MAX_NAME_LEN, MAX_TEXT_LEN};
+use crate::versioning;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// How a character sounds. `engine` is `"browser"` (Web Speech, `voice_id`
+/// is a generic profile like `male-deep`) or `"elevenlabs"` (`voice_id` is
+/// an ElevenLabs voice, possibly one we cloned).
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct VoiceProfile {
+    pub engine: String,
+    #[serde(default)]
+    pub voice_id: String,
+    #[serde(default)]
+    pub voice_name: String,
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    #[serde(default = "default_stability")]
+    pub stability: f64,
+    #[serde(default = "default_similarity")]
+    pub similarity: f64,
+    /// `""`, `"pending"`, `"ready"` or `"failed"` for the cloning workflow.
+    #[serde(default)]
+    pub clone_status: String,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+fn default_stability() -> f64 {
+    0.5
+}
+
+fn default_similarity() -> f64 {
+    0.75
+}
+
+pub const VOICE_ENGINES: &[&str] = &["browser", "elevenlabs"];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Character {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub description: String,
+    pub photo_data: String,
+    pub voice: VoiceProfile,
+    pub created_at: String,
+    /// Pass back as `expected_version` when saving; see `versioning`.
+    #[ts(type = "number")]
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CharacterInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub project_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub photo_data: String,
+    /// The `version` the editor loaded; a stale one is rejected.
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub expected_version: Option<i64>,
+}
+
+pub const CHARACTER_COLUMNS: &str = "id, project_id, name, description, photo_data, created_at, voice_engine, voice_id, voice_name, voice_speed, voice_stability, voice_similarity, voice_clone_status, version";
+
+pub fn row_to_character(row: &Row) -> rusqlite::Result<Character> {
+    Ok(Character {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        photo_data: row.get(4)?,
+        created_at: row.get(5)?,
+        voice: VoiceProfile {
+            engine: row.get(6)?,
+            voice_id: row.get(7)?,
+            voice_name: row.get(8)?,
+            speed: row.get(9)?,
+            stability: row.get(10)?,
+            similarity: row.get(11)?,
+            clone_status: row.get(12)?,
+        },
+        version: row.get(13)?,
+    })
+}
+
+pub fn get_character(conn: &Connection, id: &str) -> AppResult<Character> {
+    conn.query_row(
+        &format!("SELECT {} FROM characters WHERE id = ?1", CHARACTER_COLUMNS),
+        [id],
+        row_to_character,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("character {}", id)))
+}
+
+impl Validate for VoiceProfile {
+    fn validate(&self, v: &mut Validator) {
+        v.one_of("engine", &self.engine, VOICE_ENGINES)
+            .max_len("voiceId", &self.voice_id, MAX_NAME_LEN)
+            .max_len("voiceName", &self.voice_name, MAX_NAME_LEN)
+            .range("speed", self.speed, 0.5, 2.0)
+            .range("stability", self.stability, 0.0, 1.0)
+            .range("similarity", self.similarity, 0.0, 1.0);
+    }
+}
+
+impl Validate for CharacterInput {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_uuid("id", self.id.as_deref())
+            .uuid("projectId", &self.project_id)
+            .name("name", &self.name)
+            .max_len("description", &self.description, MAX_TEXT_LEN)
+            .max_len("photoData", &self.photo_data, MAX_IMAGE_LEN);
+    }
+}
+
+pub fn list_for_project(conn: &Connection, project_id: &str) -> AppResult<Vec<Character>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM characters WHERE project_id = ?1 ORDER BY created_at, rowid",
+        CHARACTER_COLUMNS
+    ))?;
+    let characters = stmt
+        .query_map([project_id], row_to_character)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(characters)
+}
+
+#[tauri::command]
+pub fn list_characters(db: State<'_, Database>, project_id: String) -> AppResult<Vec<Character>> {
+    list_for_project(&db.conn(), &project_id)
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum CharacterSortField {
+    /// When they were added, as `list_characters` returns them.
+    #[default]
+    CreatedAt,
+    Name,
+}
+
+/// One page of a project's characters, optionally matching `query` against
+/// name and description, with the total number of matches.
+pub fn list_page(
+    conn: &Connection,
+    project_id: &str,
+    query: Option<&str>,
+    sort: CharacterSortField,
+    direction: Direction,
+    offset: Option<i64>,
+    limit: Option<i64>,
+) -> AppResult<Page<Character>> {
+    let (offset, limit) = paging::window(offset, limit);
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+    let filter = "project_id = ?1
+        AND (?2 IS NULL OR instr(lower(name || ' ' || description), lower(?2)) > 0)";
+    let column = match sort {
+        CharacterSortField::CreatedAt => "created_at",
+        CharacterSortField::Name => "name COLLATE NOCASE",
+    };
+
+    let total = conn.query_row(
+        &format!("SELECT COUNT(*) FROM characters WHERE {}", filter),
+        params![project_id, query],
+        |r| r.get(0),
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM characters WHERE {} ORDER BY {} {}, rowid LIMIT ?3 OFFSET ?4",
+        CHARACTER_COLUMNS,
+        filter,
+        column,
+        direction.sql()
+    ))?;
+    let items = stmt
+        .query_map(params![project_id, query, limit, offset], row_to_character)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Page {
+        items,
+        total,
+        offset,
+        limit,
+    })
+}
+
+#[tauri::command]
+pub fn list_characters_page(
+    db: State<'_, Database>,
+    project_id: String,
+    query: Option<String>,
+    sort: Option<CharacterSortField>,
+    direction: Option<Direction>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+) -> AppResult<Page<Character>> {
+    list_page(
+        &db.conn(),
+        &project_id,
+        query.as_deref(),
+        sort.unwrap_or_default(),
+        direction.unwrap_or_default(),
+        offset,
+        limit,
+    )
+}
+
+/// Create or update a character's basic details. The voice profile is
+/// saved separately with `set_character_voice`.
+pub fn save(conn: &Connection, input: &CharacterInput) -> AppResult<Character> {
+    input.check()?;
+    projects::ensure_unlocked(conn, &input.project_id)?;
+    let id = match &input.id {
+        Some(id) => {
+            let before = get_character(conn, id)?;
+            versioning::check(
+                "character",
+                id,
+                input.expected_version,
+                before.version,
+                &before,
+                input,
+            )?;
+            let changed = conn.execute(
+                "UPDATE characters SET name = ?2, description = ?3, photo_data = ?4
+                 WHERE id = ?1 AND project_id = ?5",
+                params![
+                    id,
+                    input.name.trim(),
+                    input.description,
+                    input.photo_data,
+                    input.project_id
+                ],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("character {}", id)));
+            }
+            let mut changes = Vec::new();
+            activity::diff(&mut changes, "name", &before.name, input.name.trim());
+            activity::diff(
+                &mut changes,
+                "description",
+                &before.description,
+                &input.description,
+            );
+            if before.photo_data != input.photo_data {
+                // Photos are data URIs; note the change without copying them.
+                activity::diff(&mut changes, "photo", "previous photo", "new photo");
+            }
+            activity::record(
+                conn,
+                &input.project_id,
+                "character",
+                id,
+                "updated",
+                &format!("Edited character {}", input.name.trim()),
+                &changes,
+            )?;
+            id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO characters (id, project_id, name, description, photo_data, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    id,
+                    input.project_id,
+                    input.name.trim(),
+                    input.description,
+                    input.photo_data,
+                    clock::now()
+                ],
+            )?;
+            activity::record(
+                conn,
+                &input.project_id,
+                "character",
+                &id,
+                "created",
+                &format!("Added character {}", input.name.trim()),
+                &[],
+            )?;
+            id
+        }
+    };
+    get_character(conn, &id)
+}
+
+#[tauri::command]
+pub fn save_character(db: State<'_, Database>, input: CharacterInput) -> AppResult<Character> {
+    save(&db.conn(), &input)
+}
+
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let character = get_character(conn, id)?;
+    projects::ensure_unlocked(conn, &character.project_id)?;
+    conn.execute("DELETE FROM characters WHERE id = ?1", [id])?;
+    activity::record(
+        conn,
+        &character.project_id,
+        "character",
+        id,
+        "deleted",
+        &format!("Deleted character {}", character.name),
+        &[],
+    )?;
+    comments::delete_for_entity(conn, "character", id)
+}
+
+#[tauri::command]
+pub fn delete_character(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
+
+/// Save a character's voice profile. Clone status is owned by the cloning
+/// workflow and is left untouched here.
+pub fn set_voice(
+    conn: &Connection,
+    character_id: &str,
+    voice: &VoiceProfile,
+    expected_version: Option<i64>,
+) -> AppResult<Character> {
+    let mut v = Validator::new();
+    v.uuid("characterId", character_id).nested("voice", voice);
+    v.finish()?;
+    let before = get_character(conn, character_id)?;
+    projects::ensure_unlocked(conn, &before.project_id)?;
+    versioning::check(
+        "character",
+        character_id,
+        expected_version,
+        before.version,
+        &before,
+        &voice,
+    )?;
+    let changed = conn.execute(
+        "UPDATE characters SET voice_engine = ?2, voice_id = ?3, voice_name = ?4,
+            voice_speed = ?5, voice_stability = ?6, voice_similarity = ?7
+         WHERE id = ?1",
+        params![
+            character_id,
+            voice.engine,
+            voice.voice_id,
+            voice.voice_name,
+            voice.speed,
+            voice.stability,
+            voice.similarity
+        ],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("character {}", character_id)));
+    }
+    let mut changes = Vec::new();
+    activity::diff(
+        &mut changes,
+        "voiceEngine",
+        &before.voice.engine,
+        &voice.engine,
+    );
+    activity::diff(
+        &mut changes,
+        "voiceId",
+        &before.voice.voice_id,
+        &voice.voice_id,
+    );
+    activity::diff(
+        &mut changes,
+        "voiceName",
+        &before.voice.voice_name,
+        &voice.voice_name,
+    );
+    activity::diff(&mut changes, "voiceSpeed", before.voice.speed, voice.speed);
+    activity::record(
+        conn,
+        &before.project_id,
+        "character",
+        character_id,
+        "updated",
+        &format!("Changed {}'s voice", before.name),
+        &changes,
+    )?;
+    get_character(conn, character_id)
+}
+
+#[tauri::command]
+pub fn set_character_voice(
+    db: State<'_, Database>,
+    character_id: String,
+    voice: VoiceProfile,
+    expected_version: Option<i64>,
+) -> AppResult<Character> {
+    set_voice(&db.conn(), &character_id, &voice, expected_version)
+}
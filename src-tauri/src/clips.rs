@@ -0,0 +1,312 @@
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::projects;
+use crate::settings;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Settings key for an explicit yt-dlp binary; otherwise we look for the
+/// bundled sidecar next to the executable, then `PATH`.
+const YTDLP_PATH_SETTING: &str = "tools.ytdlp_path";
+/// Set once the user has accepted the local-use notice.
+const YTDLP_ACK_SETTING: &str = "tools.ytdlp_acknowledged";
+
+pub const LOCAL_USE_NOTICE: &str = "Reference clips are downloaded for private, local study only. \
+Only import material you have the right to use; clips stay on this machine and are never \
+uploaded to generation providers or included in exports.";
+
+const MAX_FILESIZE: &str = "500M";
+
+pub mod status {
+    pub const DOWNLOADING: &str = "downloading";
+    pub const READY: &str = "ready";
+    pub const FAILED: &str = "failed";
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ReferenceClip {
+    pub id: String,
+    pub project_id: String,
+    pub source_url: String,
+    pub title: String,
+    pub file_path: String,
+    pub duration: f64,
+    pub status: String,
+    pub error: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct YtDlpStatus {
+    pub available: bool,
+    pub path: String,
+    pub version: String,
+    pub acknowledged: bool,
+    pub notice: &'static str,
+}
+
+const CLIP_COLUMNS: &str =
+    "id, project_id, source_url, title, file_path, duration, status, error, created_at";
+
+fn row_to_clip(row: &Row) -> rusqlite::Result<ReferenceClip> {
+    Ok(ReferenceClip {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        source_url: row.get(2)?,
+        title: row.get(3)?,
+        file_path: row.get(4)?,
+        duration: row.get(5)?,
+        status: row.get(6)?,
+        error: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+pub fn get_clip(conn: &Connection, id: &str) -> AppResult<ReferenceClip> {
+    conn.query_row(
+        &format!("SELECT {} FROM reference_clips WHERE id = ?1", CLIP_COLUMNS),
+        [id],
+        row_to_clip,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("reference clip {}", id)))
+}
+
+fn ytdlp_binary(conn: &Connection) -> AppResult<PathBuf> {
+    if let Some(path) = settings::get(conn, YTDLP_PATH_SETTING)?.filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    let exe_name = if cfg!(windows) {
+        "yt-dlp.exe"
+    } else {
+        "yt-dlp"
+    };
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(PathBuf::from))
+    {
+        let sidecar = dir.join(exe_name);
+        if sidecar.exists() {
+            return Ok(sidecar);
+        }
+    }
+    Ok(PathBuf::from(exe_name))
+}
+
+/// The yt-dlp binary to run, and whether the local-use notice is accepted.
+pub fn ytdlp(conn: &Connection) -> AppResult<(PathBuf, bool)> {
+    Ok((
+        ytdlp_binary(conn)?,
+        settings::get_bool(conn, YTDLP_ACK_SETTING)?,
+    ))
+}
+
+#[tauri::command]
+pub async fn get_ytdlp_status(app: AppHandle) -> AppResult<YtDlpStatus> {
+    let (binary, acknowledged) = ytdlp(&app.state::<Database>().conn())?;
+    let output = tokio::process::Command::new(&binary)
+        .arg("--version")
+        .output()
+        .await;
+    let version = match output {
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).trim().to_string(),
+        _ => String::new(),
+    };
+    Ok(YtDlpStatus {
+        available: !version.is_empty(),
+        path: binary.to_string_lossy().to_string(),
+        version,
+        acknowledged,
+        notice: LOCAL_USE_NOTICE,
+    })
+}
+
+pub fn acknowledge(conn: &Connection) -> AppResult<()> {
+    settings::set(conn, YTDLP_ACK_SETTING, "true")
+}
+
+#[tauri::command]
+pub fn acknowledge_reference_notice(db: State<'_, Database>) -> AppResult<()> {
+    acknowledge(&db.conn())
+}
+
+/// Store the outcome of a download: the file, title and duration yt-dlp
+/// reported, or the error.
+pub fn record(
+    conn: &Connection,
+    id: &str,
+    result: AppResult<(String, String, f64)>,
+) -> AppResult<ReferenceClip> {
+    match result {
+        Ok((file_path, title, duration)) => conn.execute(
+            "UPDATE reference_clips SET status = ?2, file_path = ?3, title = ?4, duration = ?5
+             WHERE id = ?1",
+            params![id, status::READY, file_path, title, duration],
+        ),
+        Err(e) => conn.execute(
+            "UPDATE reference_clips SET status = ?2, error = ?3 WHERE id = ?1",
+            params![id, status::FAILED, e.to_string()],
+        ),
+    }?;
+    get_clip(conn, id)
+}
+
+fn finish(app: &AppHandle, id: &str, result: AppResult<(String, String, f64)>) {
+    let recorded = record(&app.state::<Database>().conn(), id, result);
+    match recorded {
+        Err(e) => tracing::error!(clip_id = %id, error = %e, "failed to record reference clip"),
+        Ok(clip) => {
+            let event = if clip.status == status::READY {
+                // Downstream analysis (shot detection, transcription) listens for this.
+                AppEvent::ReferenceClipReady(clip)
+            } else {
+                AppEvent::ReferenceClipUpdated(clip)
+            };
+            event.emit(app);
+        }
+    }
+}
+
+async fn download(binary: PathBuf, id: &str, url: &str) -> AppResult<(String, String, f64)> {
+    let template = db::media_dir("reference-clips")?.join(format!("{}.%(ext)s", id));
+    let output = tokio::process::Command::new(&binary)
+        .args([
+            "--no-playlist",
+            "--no-progress",
+            "--restrict-filenames",
+            "--max-filesize",
+            MAX_FILESIZE,
+            "-f",
+            "bv*[height<=1080]+ba/b[height<=1080]/b",
+            "--merge-output-format",
+            "mp4",
+            "-o",
+        ])
+        .arg(&template)
+        .args([
+            "--print",
+            "after_move:filepath",
+            "--print",
+            "after_move:title",
+            "--print",
+            "after_move:duration",
+            "--",
+            url,
+        ])
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not run yt-dlp ({}): {}",
+                binary.display(),
+                e
+            ))
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("yt-dlp failed");
+        return Err(AppError::Provider(last.to_string()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim).filter(|l| !l.is_empty());
+    let file_path = lines
+        .next()
+        .ok_or_else(|| AppError::Provider("yt-dlp produced no file".into()))?
+        .to_string();
+    let title = lines.next().unwrap_or_default().to_string();
+    let duration = lines.next().and_then(|d| d.parse().ok()).unwrap_or(0.0);
+    Ok((file_path, title, duration))
+}
+
+/// Add a clip in `downloading` for `url`, with the binary to fetch it with.
+pub fn queue(
+    conn: &Connection,
+    project_id: &str,
+    url: &str,
+) -> AppResult<(PathBuf, ReferenceClip)> {
+    let url = url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(AppError::Invalid(
+            "only http(s) links can be imported".into(),
+        ));
+    }
+    if !settings::get_bool(conn, YTDLP_ACK_SETTING)? {
+        return Err(AppError::Invalid(format!(
+            "accept the local-use notice first: {}",
+            LOCAL_USE_NOTICE
+        )));
+    }
+    projects::ensure_unlocked(conn, project_id)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO reference_clips (id, project_id, source_url, status) VALUES (?1, ?2, ?3, ?4)",
+        params![id, project_id, url, status::DOWNLOADING],
+    )?;
+    Ok((ytdlp_binary(conn)?, get_clip(conn, &id)?))
+}
+
+/// Start downloading a reference clip with yt-dlp. Returns immediately with
+/// the clip in `downloading`; `reference-clip-ready` or
+/// `reference-clip-updated` (on failure) fires when it finishes.
+#[tauri::command]
+pub fn import_reference_clip(
+    app: AppHandle,
+    project_id: String,
+    url: String,
+) -> AppResult<ReferenceClip> {
+    let (binary, clip) = queue(&app.state::<Database>().conn(), &project_id, &url)?;
+    let (id, url) = (clip.id.clone(), clip.source_url.clone());
+    tauri::async_runtime::spawn(async move {
+        let result = download(binary, &id, &url).await;
+        finish(&app, &id, result);
+    });
+    Ok(clip)
+}
+
+pub fn list(conn: &Connection, project_id: &str) -> AppResult<Vec<ReferenceClip>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM reference_clips WHERE project_id = ?1 ORDER BY created_at DESC, rowid DESC",
+        CLIP_COLUMNS
+    ))?;
+    let clips = stmt
+        .query_map([project_id], row_to_clip)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(clips)
+}
+
+#[tauri::command]
+pub fn list_reference_clips(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<ReferenceClip>> {
+    list(&db.conn(), &project_id)
+}
+
+/// Remove a clip and its downloaded file.
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let clip = get_clip(conn, id)?;
+    projects::ensure_unlocked(conn, &clip.project_id)?;
+    conn.execute("DELETE FROM reference_clips WHERE id = ?1", [id])?;
+    if !clip.file_path.is_empty() {
+        std::fs::remove_file(clip.file_path).ok();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_reference_clip(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
@@ -0,0 +1,72 @@
+//! Timestamps. Everything stored is UTC in ISO 8601 with a `Z` suffix,
+//! e.g. `2026-03-01T18:04:05Z`, so stored values sort and compare as text
+//! and the UI can render them in the user's own timezone.
+//!
+//! SQLite can't change an existing column default, so rows that still get
+//! `datetime('now')` from one are rewritten by insert triggers (migration
+//! 24); everything the app sets itself comes from `now()`.
+
+use crate::error::{AppError, AppResult};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use serde::Deserialize;
+use ts_rs::TS;
+
+const FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+pub fn now() -> String {
+    format(Utc::now())
+}
+
+pub fn format(t: DateTime<Utc>) -> String {
+    t.format(FORMAT).to_string()
+}
+
+/// A span of time for date-filtered queries. Each end is either an
+/// instant with an offset (`2026-03-01T00:00:00-05:00`) or a calendar date
+/// read in `utc_offset_minutes`; a date as `to` includes that whole day.
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DateRange {
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    /// The user's offset from UTC, east positive (JS `-getTimezoneOffset()`).
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+impl DateRange {
+    /// UTC bounds to compare stored timestamps with: `from` inclusive,
+    /// `to` exclusive. Missing ends are None.
+    pub fn bounds(&self) -> AppResult<(Option<String>, Option<String>)> {
+        let offset = FixedOffset::east_opt(self.utc_offset_minutes * 60)
+            .ok_or_else(|| AppError::Invalid("utcOffsetMinutes is out of range".into()))?;
+        let from = self
+            .from
+            .as_deref()
+            .map(|s| bound(s, offset, false))
+            .transpose()?;
+        let to = self
+            .to
+            .as_deref()
+            .map(|s| bound(s, offset, true))
+            .transpose()?;
+        Ok((from, to))
+    }
+}
+
+fn bound(s: &str, offset: FixedOffset, end: bool) -> AppResult<String> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(format(t.with_timezone(&Utc)));
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| AppError::Invalid(format!("'{}' is not a date or timestamp", s)))?;
+    let day = if end { date + Duration::days(1) } else { date };
+    let midnight = offset
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).expect("midnight is valid"))
+        .single()
+        .ok_or_else(|| AppError::Invalid(format!("'{}' is out of range", s)))?;
+    Ok(format(midnight.with_timezone(&Utc)))
+}
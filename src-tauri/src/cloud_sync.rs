@@ -0,0 +1,684 @@
+//! Push and pull project bundles to a shared Google Drive or Dropbox
+//! folder. Each project is one `<project id>.aidc-bundle.json` file there.
+//!
+//! Accounts are connected with OAuth (see `oauth`), which keeps the tokens
+//! in the OS keyring. Conflicts are detected by comparing the bundle's
+//! version with the one we last synced and the project's local change time
+//! with when we last synced it.
+
+use crate::bundle::{self, ProjectBundle};
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::{AppEvent, SignInFailed};
+use crate::oauth::{self, OAuthClient};
+use crate::providers;
+use crate::secrets;
+use crate::settings;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const BUNDLE_SUFFIX: &str = ".aidc-bundle.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum CloudProvider {
+    GoogleDrive,
+    Dropbox,
+}
+
+impl CloudProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CloudProvider::GoogleDrive => "google_drive",
+            CloudProvider::Dropbox => "dropbox",
+        }
+    }
+
+    fn auth_url(self) -> &'static str {
+        match self {
+            CloudProvider::GoogleDrive => "https://accounts.google.com/o/oauth2/v2/auth",
+            CloudProvider::Dropbox => "https://www.dropbox.com/oauth2/authorize",
+        }
+    }
+
+    fn token_url(self) -> &'static str {
+        match self {
+            CloudProvider::GoogleDrive => "https://oauth2.googleapis.com/token",
+            CloudProvider::Dropbox => "https://api.dropboxapi.com/oauth2/token",
+        }
+    }
+
+    /// OAuth client id, from settings (`cloud.<provider>.client_id`) or
+    /// baked in at build time.
+    fn client_id(self, conn: &Connection) -> AppResult<String> {
+        let built_in = match self {
+            CloudProvider::GoogleDrive => option_env!("AIDC_GOOGLE_CLIENT_ID"),
+            CloudProvider::Dropbox => option_env!("AIDC_DROPBOX_APP_KEY"),
+        };
+        settings::get(conn, &format!("cloud.{}.client_id", self.as_str()))?
+            .filter(|v| !v.is_empty())
+            .or(built_in.map(str::to_string))
+            .ok_or_else(|| {
+                AppError::Invalid(format!("no OAuth client configured for {}", self.as_str()))
+            })
+    }
+
+    /// Google requires the (non-confidential) desktop client secret too.
+    fn client_secret(self) -> Option<&'static str> {
+        match self {
+            CloudProvider::GoogleDrive => option_env!("AIDC_GOOGLE_CLIENT_SECRET"),
+            CloudProvider::Dropbox => None,
+        }
+    }
+
+    fn extra_auth_params(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            CloudProvider::GoogleDrive => &[
+                ("scope", "https://www.googleapis.com/auth/drive"),
+                ("access_type", "offline"),
+                ("prompt", "consent"),
+            ],
+            CloudProvider::Dropbox => &[("token_access_type", "offline")],
+        }
+    }
+
+    /// Names the account's tokens in the keyring.
+    fn account(self) -> String {
+        format!("cloud.{}", self.as_str())
+    }
+
+    /// Where older versions kept the refresh token, before the keyring.
+    fn legacy_token_secret(self) -> String {
+        format!("cloud.{}.refresh_token", self.as_str())
+    }
+
+    /// Drive folder id, or Dropbox folder path like `/Team/AIDC`.
+    fn folder_setting(self) -> String {
+        format!("cloud.{}.folder", self.as_str())
+    }
+
+    fn oauth_client(self, conn: &Connection) -> AppResult<OAuthClient> {
+        Ok(OAuthClient {
+            account: self.account(),
+            auth_url: self.auth_url(),
+            token_url: self.token_url(),
+            client_id: self.client_id(conn)?,
+            client_secret: self.client_secret(),
+            extra_auth_params: self.extra_auth_params(),
+        })
+    }
+}
+
+/// Move a refresh token saved by an older version into the keyring.
+fn migrate_legacy_token(conn: &Connection, provider: CloudProvider) -> AppResult<()> {
+    if let Some(refresh) = secrets::get(conn, &provider.legacy_token_secret())? {
+        oauth::store_refresh_token(&provider.account(), &refresh)?;
+        secrets::delete(conn, &provider.legacy_token_secret())?;
+    }
+    Ok(())
+}
+
+async fn access_token(app: &AppHandle, provider: CloudProvider) -> AppResult<String> {
+    let client = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        migrate_legacy_token(&conn, provider)?;
+        provider.oauth_client(&conn)?
+    };
+    oauth::access_token(&client).await
+}
+
+/// Start connecting an account. Returns the sign-in URL for the frontend to
+/// open in the browser; completion is reported with
+/// `cloud-storage-connected` or `cloud-storage-failed`.
+#[tauri::command]
+pub async fn connect_cloud_storage(app: AppHandle, provider: CloudProvider) -> AppResult<String> {
+    let client = provider.oauth_client(&app.state::<Database>().conn())?;
+    oauth::connect(&app, client, move |error| match error {
+        None => AppEvent::CloudStorageConnected(provider),
+        Some(error) => AppEvent::CloudStorageFailed(SignInFailed { provider, error }),
+    })
+    .await
+}
+
+/// Dropbox wants its JSON argument header in ASCII.
+fn dropbox_arg(value: &Value) -> String {
+    value
+        .to_string()
+        .chars()
+        .map(|c| {
+            if c.is_ascii() {
+                c.to_string()
+            } else {
+                let mut units = [0u16; 2];
+                c.encode_utf16(&mut units)
+                    .iter()
+                    .map(|u| format!("\\u{:04x}", u))
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+async fn check(res: reqwest::Response, what: &str) -> AppResult<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    Err(AppError::Provider(format!(
+        "{} ({}): {}",
+        what, status, body
+    )))
+}
+
+/// Id of a file in a Drive folder, if it exists.
+async fn drive_find(token: &str, folder: &str, name: &str) -> AppResult<Option<String>> {
+    let q = format!(
+        "name = '{}' and '{}' in parents and trashed = false",
+        name.replace('\'', "\\'"),
+        folder.replace('\'', "\\'")
+    );
+    let res = providers::client()
+        .get("https://www.googleapis.com/drive/v3/files")
+        .bearer_auth(token)
+        .query(&[
+            ("q", q.as_str()),
+            ("fields", "files(id)"),
+            ("supportsAllDrives", "true"),
+            ("includeItemsFromAllDrives", "true"),
+        ])
+        .send()
+        .await?;
+    let body: Value = check(res, "Google Drive search").await?.json().await?;
+    Ok(body["files"][0]["id"].as_str().map(str::to_string))
+}
+
+async fn download(
+    provider: CloudProvider,
+    token: &str,
+    folder: &str,
+    name: &str,
+) -> AppResult<Option<Vec<u8>>> {
+    match provider {
+        CloudProvider::GoogleDrive => {
+            let Some(id) = drive_find(token, folder, name).await? else {
+                return Ok(None);
+            };
+            let res = providers::client()
+                .get(format!("https://www.googleapis.com/drive/v3/files/{}", id))
+                .bearer_auth(token)
+                .query(&[("alt", "media"), ("supportsAllDrives", "true")])
+                .send()
+                .await?;
+            Ok(Some(
+                check(res, "Google Drive download")
+                    .await?
+                    .bytes()
+                    .await?
+                    .to_vec(),
+            ))
+        }
+        CloudProvider::Dropbox => {
+            let path = format!("{}/{}", folder.trim_end_matches('/'), name);
+            let res = providers::client()
+                .post("https://content.dropboxapi.com/2/files/download")
+                .bearer_auth(token)
+                .header("Dropbox-API-Arg", dropbox_arg(&json!({ "path": path })))
+                .send()
+                .await?;
+            if res.status() == reqwest::StatusCode::CONFLICT {
+                let body = res.text().await.unwrap_or_default();
+                if body.contains("not_found") {
+                    return Ok(None);
+                }
+                return Err(AppError::Provider(format!("Dropbox download: {}", body)));
+            }
+            Ok(Some(
+                check(res, "Dropbox download")
+                    .await?
+                    .bytes()
+                    .await?
+                    .to_vec(),
+            ))
+        }
+    }
+}
+
+async fn upload(
+    provider: CloudProvider,
+    token: &str,
+    folder: &str,
+    name: &str,
+    bytes: Vec<u8>,
+) -> AppResult<()> {
+    match provider {
+        CloudProvider::GoogleDrive => {
+            let id = match drive_find(token, folder, name).await? {
+                Some(id) => id,
+                None => {
+                    let res = providers::client()
+                        .post("https://www.googleapis.com/drive/v3/files")
+                        .bearer_auth(token)
+                        .query(&[("supportsAllDrives", "true")])
+                        .json(&json!({
+                            "name": name,
+                            "parents": [folder],
+                            "mimeType": "application/json",
+                        }))
+                        .send()
+                        .await?;
+                    let body: Value = check(res, "Google Drive create").await?.json().await?;
+                    body["id"]
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or_else(|| AppError::Provider("Drive returned no file id".into()))?
+                }
+            };
+            let res = providers::client()
+                .patch(format!(
+                    "https://www.googleapis.com/upload/drive/v3/files/{}",
+                    id
+                ))
+                .bearer_auth(token)
+                .query(&[("uploadType", "media"), ("supportsAllDrives", "true")])
+                .header("Content-Type", "application/json")
+                .body(bytes)
+                .send()
+                .await?;
+            check(res, "Google Drive upload").await?;
+        }
+        CloudProvider::Dropbox => {
+            let path = format!("{}/{}", folder.trim_end_matches('/'), name);
+            let res = providers::client()
+                .post("https://content.dropboxapi.com/2/files/upload")
+                .bearer_auth(token)
+                .header(
+                    "Dropbox-API-Arg",
+                    dropbox_arg(&json!({ "path": path, "mode": "overwrite", "mute": true })),
+                )
+                .header("Content-Type", "application/octet-stream")
+                .body(bytes)
+                .send()
+                .await?;
+            check(res, "Dropbox upload").await?;
+        }
+    }
+    Ok(())
+}
+
+/// What we last synced a project at.
+pub struct SyncState {
+    pub remote_version: i64,
+    pub synced_at: String,
+}
+
+fn sync_state(
+    conn: &Connection,
+    project_id: &str,
+    provider: CloudProvider,
+) -> AppResult<Option<SyncState>> {
+    Ok(conn
+        .query_row(
+            "SELECT remote_version, synced_at FROM cloud_sync WHERE project_id = ?1 AND provider = ?2",
+            params![project_id, provider.as_str()],
+            |r| {
+                Ok(SyncState {
+                    remote_version: r.get(0)?,
+                    synced_at: r.get(1)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+fn record_sync(
+    conn: &Connection,
+    project_id: &str,
+    provider: CloudProvider,
+    version: i64,
+) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO cloud_sync (project_id, provider, remote_version, synced_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_id, provider) DO UPDATE SET
+            remote_version = excluded.remote_version, synced_at = excluded.synced_at",
+        params![
+            project_id,
+            provider.as_str(),
+            version,
+            bundle::updated_at(conn, project_id)?
+        ],
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SyncResult {
+    /// `pushed`, `pulled`, `up_to_date` or `conflict`. On a conflict
+    /// nothing was changed; retry with `force` to overwrite.
+    pub outcome: String,
+    pub local_updated_at: Option<String>,
+    #[ts(type = "number | null")]
+    pub remote_version: Option<i64>,
+    pub remote_updated_at: Option<String>,
+    #[ts(type = "number | null")]
+    pub last_synced_version: Option<i64>,
+}
+
+/// Both sides of a project's sync: our last sync and local change time,
+/// and the bundle in the shared folder once downloaded.
+pub struct Sync {
+    pub token: String,
+    pub folder: String,
+    pub name: String,
+    pub state: Option<SyncState>,
+    pub local_updated_at: Option<String>,
+    pub remote: Option<ProjectBundle>,
+}
+
+impl Sync {
+    fn local_changed(&self) -> bool {
+        match (&self.local_updated_at, &self.state) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(local), Some(state)) => local > &state.synced_at,
+        }
+    }
+
+    fn remote_changed(&self) -> bool {
+        let synced = self.state.as_ref().map_or(0, |s| s.remote_version);
+        self.remote.as_ref().is_some_and(|r| r.version > synced)
+    }
+
+    fn result(&self, outcome: &str) -> SyncResult {
+        SyncResult {
+            outcome: outcome.to_string(),
+            local_updated_at: self.local_updated_at.clone(),
+            remote_version: self.remote.as_ref().map(|r| r.version),
+            remote_updated_at: self.remote.as_ref().map(|r| r.updated_at.clone()),
+            last_synced_version: self.state.as_ref().map(|s| s.remote_version),
+        }
+    }
+}
+
+/// The local side of a sync; `token` and `remote` are filled in by `begin`.
+pub fn local(conn: &Connection, project_id: &str, provider: CloudProvider) -> AppResult<Sync> {
+    let folder = settings::get(conn, &provider.folder_setting())?
+        .filter(|f| !f.is_empty())
+        .ok_or_else(|| AppError::Invalid(format!("choose a {} folder first", provider.as_str())))?;
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+        [project_id],
+        |r| r.get(0),
+    )?;
+    let local_updated_at = if exists {
+        Some(bundle::updated_at(conn, project_id)?)
+    } else {
+        None
+    };
+    Ok(Sync {
+        token: String::new(),
+        folder,
+        name: format!("{}{}", project_id, BUNDLE_SUFFIX),
+        state: sync_state(conn, project_id, provider)?,
+        local_updated_at,
+        remote: None,
+    })
+}
+
+async fn begin(app: &AppHandle, project_id: &str, provider: CloudProvider) -> AppResult<Sync> {
+    let mut sync = local(&app.state::<Database>().conn(), project_id, provider)?;
+    sync.token = access_token(app, provider).await?;
+    sync.remote = match download(provider, &sync.token, &sync.folder, &sync.name).await? {
+        Some(bytes) => Some(
+            serde_json::from_slice::<ProjectBundle>(&bytes)
+                .map_err(|e| AppError::Invalid(format!("shared bundle is not readable: {}", e)))?,
+        ),
+        None => None,
+    };
+    Ok(sync)
+}
+
+/// Compare both sides without changing anything.
+pub fn status(sync: &Sync) -> SyncResult {
+    let outcome = match (sync.local_changed(), sync.remote_changed()) {
+        (true, true) if sync.local_updated_at.is_some() && sync.remote.is_some() => "conflict",
+        (false, false) => "up_to_date",
+        (true, _) => "local_changes",
+        (false, true) => "remote_changes",
+    };
+    sync.result(outcome)
+}
+
+/// Look at both sides without changing anything.
+#[tauri::command]
+pub async fn get_project_sync_status(
+    app: AppHandle,
+    project_id: String,
+    provider: CloudProvider,
+) -> AppResult<SyncResult> {
+    let sync = begin(&app, &project_id, provider).await?;
+    Ok(status(&sync))
+}
+
+/// The bundle to publish, numbered after both sides' versions, or the
+/// outcome to report instead when there is nothing to push.
+pub fn prepare_push(
+    conn: &Connection,
+    project_id: &str,
+    sync: &Sync,
+    force: bool,
+) -> AppResult<Result<ProjectBundle, SyncResult>> {
+    if sync.local_updated_at.is_none() {
+        return Err(AppError::NotFound(format!("project {}", project_id)));
+    }
+    if sync.remote_changed() && !force {
+        return Ok(Err(sync.result("conflict")));
+    }
+    if !sync.local_changed() && !sync.remote_changed() && sync.remote.is_some() {
+        return Ok(Err(sync.result("up_to_date")));
+    }
+    confidential::ensure_remote_allowed(conn, project_id, "cloud folders")?;
+    let mut bundle = bundle::export_bundle(conn, project_id)?;
+    let previous = sync
+        .remote
+        .as_ref()
+        .map_or(0, |r| r.version)
+        .max(sync.state.as_ref().map_or(0, |s| s.remote_version));
+    bundle.version = previous + 1;
+    Ok(Ok(bundle))
+}
+
+/// Note that `bundle` was uploaded.
+pub fn record_push(
+    conn: &Connection,
+    project_id: &str,
+    provider: CloudProvider,
+    sync: &Sync,
+    bundle: ProjectBundle,
+) -> AppResult<SyncResult> {
+    record_sync(conn, project_id, provider, bundle.version)?;
+    let mut result = sync.result("pushed");
+    result.remote_version = Some(bundle.version);
+    result.remote_updated_at = Some(bundle.updated_at);
+    Ok(result)
+}
+
+/// Publish the local project to the shared folder. Refuses with a
+/// `conflict` outcome if someone else published since our last sync,
+/// unless `force` is set.
+#[tauri::command]
+pub async fn push_project_bundle(
+    app: AppHandle,
+    project_id: String,
+    provider: CloudProvider,
+    force: Option<bool>,
+) -> AppResult<SyncResult> {
+    let sync = begin(&app, &project_id, provider).await?;
+    let prepared = prepare_push(
+        &app.state::<Database>().conn(),
+        &project_id,
+        &sync,
+        force.unwrap_or(false),
+    )?;
+    let bundle = match prepared {
+        Ok(bundle) => bundle,
+        Err(outcome) => return Ok(outcome),
+    };
+    upload(
+        provider,
+        &sync.token,
+        &sync.folder,
+        &sync.name,
+        serde_json::to_vec_pretty(&bundle)?,
+    )
+    .await?;
+    tracing::info!(
+        project_id,
+        version = bundle.version,
+        provider = provider.as_str(),
+        "pushed bundle"
+    );
+    record_push(
+        &app.state::<Database>().conn(),
+        &project_id,
+        provider,
+        &sync,
+        bundle,
+    )
+}
+
+/// Replace the local project with the downloaded bundle, unless that would
+/// lose local changes and `force` is not set.
+pub fn apply_pull(
+    conn: &Connection,
+    project_id: &str,
+    provider: CloudProvider,
+    sync: &Sync,
+    force: bool,
+) -> AppResult<SyncResult> {
+    let Some(remote) = &sync.remote else {
+        return Err(AppError::NotFound(format!(
+            "no shared bundle for project {}",
+            project_id
+        )));
+    };
+    if !sync.remote_changed() && sync.state.is_some() {
+        return Ok(sync.result("up_to_date"));
+    }
+    if sync.local_changed() && sync.local_updated_at.is_some() && !force {
+        return Ok(sync.result("conflict"));
+    }
+    if remote.project.id != project_id {
+        return Err(AppError::Invalid(
+            "shared bundle belongs to a different project".into(),
+        ));
+    }
+    bundle::import_bundle(conn, remote)?;
+    record_sync(conn, project_id, provider, remote.version)?;
+    Ok(sync.result("pulled"))
+}
+
+/// Replace the local project with the shared bundle. Refuses with a
+/// `conflict` outcome if the project also changed locally since the last
+/// sync, unless `force` is set. Emits `project-synced`.
+#[tauri::command]
+pub async fn pull_project_bundle(
+    app: AppHandle,
+    project_id: String,
+    provider: CloudProvider,
+    force: Option<bool>,
+) -> AppResult<SyncResult> {
+    let sync = begin(&app, &project_id, provider).await?;
+    let result = apply_pull(
+        &app.state::<Database>().conn(),
+        &project_id,
+        provider,
+        &sync,
+        force.unwrap_or(false),
+    )?;
+    if result.outcome == "pulled" {
+        tracing::info!(
+            project_id,
+            version = result.remote_version,
+            provider = provider.as_str(),
+            "pulled bundle"
+        );
+        AppEvent::ProjectSynced(project_id.to_string()).emit(&app);
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CloudStorageStatus {
+    pub provider: CloudProvider,
+    /// An OAuth client is available for this provider.
+    pub available: bool,
+    pub connected: bool,
+    pub folder: String,
+}
+
+/// Each provider's account and folder. Connection state is read from the
+/// OS keyring.
+pub fn storage_status(conn: &Connection) -> AppResult<Vec<CloudStorageStatus>> {
+    [CloudProvider::GoogleDrive, CloudProvider::Dropbox]
+        .into_iter()
+        .map(|provider| {
+            Ok(CloudStorageStatus {
+                provider,
+                available: provider.client_id(conn).is_ok(),
+                connected: {
+                    migrate_legacy_token(conn, provider)?;
+                    oauth::is_connected(&provider.account())?
+                },
+                folder: settings::get(conn, &provider.folder_setting())?.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_cloud_storage_status(db: State<'_, Database>) -> AppResult<Vec<CloudStorageStatus>> {
+    storage_status(&db.conn())
+}
+
+/// Set the shared folder: a Drive folder id, or a Dropbox path.
+pub fn set_folder(conn: &Connection, provider: CloudProvider, folder: &str) -> AppResult<()> {
+    let folder = folder.trim();
+    if provider == CloudProvider::Dropbox && !folder.is_empty() && !folder.starts_with('/') {
+        return Err(AppError::Invalid(
+            "Dropbox folders are paths starting with '/'".into(),
+        ));
+    }
+    settings::set(conn, &provider.folder_setting(), folder)
+}
+
+#[tauri::command]
+pub fn set_cloud_folder(
+    db: State<'_, Database>,
+    provider: CloudProvider,
+    folder: String,
+) -> AppResult<()> {
+    set_folder(&db.conn(), provider, &folder)
+}
+
+/// Forget the account, here and in the keyring.
+pub fn disconnect(conn: &Connection, provider: CloudProvider) -> AppResult<()> {
+    secrets::delete(conn, &provider.legacy_token_secret())?;
+    oauth::disconnect(&provider.account())
+}
+
+#[tauri::command]
+pub fn disconnect_cloud_storage(db: State<'_, Database>, provider: CloudProvider) -> AppResult<()> {
+    disconnect(&db.conn(), provider)
+}
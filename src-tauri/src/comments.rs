@@ -0,0 +1,228 @@
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::roles::{self, Role};
+use crate::validation::{Validate, Validator, MAX_TEXT_LEN};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Things a comment can hang off, mapped to a query for their project.
+const ENTITY_PROJECTS: &[(&str, &str)] = &[
+    ("project", "SELECT id FROM projects WHERE id = ?1"),
+    ("scene", "SELECT project_id FROM scenes WHERE id = ?1"),
+    (
+        "character",
+        "SELECT project_id FROM characters WHERE id = ?1",
+    ),
+    (
+        "take",
+        "SELECT s.project_id FROM video_jobs j JOIN scenes s ON s.id = j.scene_id WHERE j.id = ?1",
+    ),
+];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Comment {
+    pub id: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub author_name: String,
+    pub body: String,
+    pub resolved: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CommentInput {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub author_name: String,
+    pub body: String,
+}
+
+impl Validate for CommentInput {
+    fn validate(&self, v: &mut Validator) {
+        let types: Vec<&str> = ENTITY_PROJECTS.iter().map(|(t, _)| *t).collect();
+        v.one_of("entityType", &self.entity_type, &types)
+            .uuid("entityId", &self.entity_id)
+            .name("authorName", &self.author_name)
+            .required("body", &self.body)
+            .max_len("body", &self.body, MAX_TEXT_LEN);
+    }
+}
+
+const COMMENT_COLUMNS: &str =
+    "id, entity_type, entity_id, author_name, body, resolved, created_at, updated_at";
+
+fn row_to_comment(row: &Row) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: row.get(0)?,
+        entity_type: row.get(1)?,
+        entity_id: row.get(2)?,
+        author_name: row.get(3)?,
+        body: row.get(4)?,
+        resolved: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+fn get_comment(conn: &Connection, id: &str) -> AppResult<Comment> {
+    conn.query_row(
+        &format!("SELECT {} FROM comments WHERE id = ?1", COMMENT_COLUMNS),
+        [id],
+        row_to_comment,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("comment {}", id)))
+}
+
+/// Comments aren't foreign-keyed (they point at several tables), so check
+/// the target exists before attaching one, and that the current role on
+/// its project may comment.
+fn check_entity(conn: &Connection, entity_type: &str, entity_id: &str) -> AppResult<()> {
+    let query = ENTITY_PROJECTS
+        .iter()
+        .find(|(t, _)| *t == entity_type)
+        .map(|(_, query)| *query)
+        .ok_or_else(|| AppError::Invalid(format!("cannot comment on '{}'", entity_type)))?;
+    let project_id: String = conn
+        .query_row(query, [entity_id], |r| r.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("{} {}", entity_type, entity_id)))?;
+    roles::ensure_role(conn, &project_id, Role::Commenter)
+}
+
+/// `check_entity` for an existing comment.
+fn check_comment(conn: &Connection, id: &str) -> AppResult<()> {
+    let comment = get_comment(conn, id)?;
+    check_entity(conn, &comment.entity_type, &comment.entity_id)
+}
+
+/// Remove comments attached to an entity that is being deleted.
+pub fn delete_for_entity(conn: &Connection, entity_type: &str, entity_id: &str) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM comments WHERE entity_type = ?1 AND entity_id = ?2",
+        params![entity_type, entity_id],
+    )?;
+    Ok(())
+}
+
+pub fn list(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+    include_resolved: bool,
+) -> AppResult<Vec<Comment>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM comments
+         WHERE entity_type = ?1 AND entity_id = ?2 AND (?3 OR resolved = 0)
+         ORDER BY created_at, rowid",
+        COMMENT_COLUMNS
+    ))?;
+    let comments = stmt
+        .query_map(
+            params![entity_type, entity_id, include_resolved],
+            row_to_comment,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(comments)
+}
+
+#[tauri::command]
+pub fn list_comments(
+    db: State<'_, Database>,
+    entity_type: String,
+    entity_id: String,
+    include_resolved: Option<bool>,
+) -> AppResult<Vec<Comment>> {
+    list(
+        &db.conn(),
+        &entity_type,
+        &entity_id,
+        include_resolved.unwrap_or(true),
+    )
+}
+
+pub fn add(conn: &Connection, input: &CommentInput) -> AppResult<Comment> {
+    input.check()?;
+    let author = input.author_name.trim();
+    check_entity(conn, &input.entity_type, &input.entity_id)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO comments (id, entity_type, entity_id, author_name, body)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            input.entity_type,
+            input.entity_id,
+            author,
+            input.body.trim()
+        ],
+    )?;
+    get_comment(conn, &id)
+}
+
+#[tauri::command]
+pub fn add_comment(db: State<'_, Database>, input: CommentInput) -> AppResult<Comment> {
+    add(&db.conn(), &input)
+}
+
+pub fn edit(conn: &Connection, id: &str, body: &str) -> AppResult<Comment> {
+    let mut v = Validator::new();
+    v.uuid("id", id)
+        .required("body", body)
+        .max_len("body", body, MAX_TEXT_LEN);
+    v.finish()?;
+    check_comment(conn, id)?;
+    let changed = conn.execute(
+        "UPDATE comments SET body = ?2, updated_at = ?3 WHERE id = ?1",
+        params![id, body.trim(), clock::now()],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("comment {}", id)));
+    }
+    get_comment(conn, id)
+}
+
+#[tauri::command]
+pub fn edit_comment(db: State<'_, Database>, id: String, body: String) -> AppResult<Comment> {
+    edit(&db.conn(), &id, &body)
+}
+
+pub fn resolve(conn: &Connection, id: &str, resolved: bool) -> AppResult<Comment> {
+    check_comment(conn, id)?;
+    let changed = conn.execute(
+        "UPDATE comments SET resolved = ?2, updated_at = ?3 WHERE id = ?1",
+        params![id, resolved, clock::now()],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("comment {}", id)));
+    }
+    get_comment(conn, id)
+}
+
+#[tauri::command]
+pub fn resolve_comment(db: State<'_, Database>, id: String, resolved: bool) -> AppResult<Comment> {
+    resolve(&db.conn(), &id, resolved)
+}
+
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    check_comment(conn, id)?;
+    let changed = conn.execute("DELETE FROM comments WHERE id = ?1", [&id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("comment {}", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_comment(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
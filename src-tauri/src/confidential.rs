@@ -0,0 +1,186 @@
+//! Confidential projects stay on this machine. Apart from the video
+//! generations the user submits to the provider they pick, nothing of
+//! theirs goes to a cloud service: LLM calls use the local model if one
+//! is set up (see `llm`) and fail otherwise, moderation runs the local
+//! rules, voices are browser-only, lip-sync, review uploads, publishing
+//! and Notion or Airtable syncs are refused, notifications and analytics
+//! skip them, they can't be pushed to a cloud folder, and cloud backups
+//! leave them, their media and the search index's copy of their text out.
+//!
+//! The checks sit where requests are dispatched rather than in the
+//! commands that start them, so a new feature can't forget them.
+
+use crate::db::Database;
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::projects::{self, ProjectSummary};
+use crate::roles::{self, Role};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+pub fn is_confidential(conn: &Connection, project_id: &str) -> AppResult<bool> {
+    Ok(conn
+        .query_row(
+            "SELECT confidential FROM projects WHERE id = ?1",
+            [project_id],
+            |r| r.get(0),
+        )
+        .optional()?
+        .unwrap_or(false))
+}
+
+/// Refuse to send the project's data to `service`, a cloud service, if
+/// the project is confidential.
+pub fn ensure_remote_allowed(conn: &Connection, project_id: &str, service: &str) -> AppResult<()> {
+    if is_confidential(conn, project_id)? {
+        return Err(AppError::Forbidden(format!(
+            "this project is confidential, so it can't use {}",
+            service
+        )));
+    }
+    Ok(())
+}
+
+/// Columns that hold the id of a project's row, directly or through one
+/// of its scenes, characters or takes; `entity_id` covers comments and
+/// other rows that can hang off any of them.
+const ROW_COLUMNS: &[&str] = &["scene_id", "character_id", "job_id", "entity_id"];
+
+/// Tables with a `column` column.
+fn tables_with(conn: &Connection, column: &str) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT m.name FROM sqlite_master m JOIN pragma_table_info(m.name) p
+         WHERE m.type = 'table' AND p.name = ?1 ORDER BY m.name",
+    )?;
+    let tables = stmt
+        .query_map([column], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tables)
+}
+
+/// Files under `media` that rows of the confidential projects point at,
+/// directly or through one of their scenes.
+fn confidential_media(conn: &Connection, media: &Path) -> AppResult<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut stmt = conn.prepare(
+        "SELECT m.name, p.name FROM sqlite_master m JOIN pragma_table_info(m.name) p
+         WHERE m.type = 'table' AND p.name IN ('project_id', 'scene_id')",
+    )?;
+    let tables = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (table, key) in tables {
+        let filter = if key == "project_id" {
+            "project_id IN (SELECT id FROM projects WHERE confidential = 1)"
+        } else {
+            "scene_id IN (SELECT s.id FROM scenes s JOIN projects p ON p.id = s.project_id
+                WHERE p.confidential = 1)"
+        };
+        let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\" WHERE {}", table, filter))?;
+        let columns = stmt.column_count();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            for i in 0..columns {
+                if let Ok(Some(value)) = row.get::<_, Option<String>>(i) {
+                    if Path::new(&value).starts_with(media) {
+                        files.insert(PathBuf::from(value));
+                    }
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Strip confidential projects out of a copy of the database made for a
+/// cloud backup, and return the media files of theirs to leave out too.
+/// Rows that only point at one of their scenes, characters or takes go
+/// too, the search index is rebuilt without their text, and the copy is
+/// vacuumed so nothing deleted lingers in free pages. It is keyed like
+/// `db_path`, the database it was copied from.
+pub fn strip_for_backup(
+    snapshot: &Path,
+    db_path: &Path,
+    media: &Path,
+) -> AppResult<BTreeSet<PathBuf>> {
+    let conn = Connection::open(snapshot)?;
+    encryption::unlock(&conn, db_path)?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    let any: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE confidential = 1)",
+        [],
+        |r| r.get(0),
+    )?;
+    if !any {
+        return Ok(BTreeSet::new());
+    }
+    let files = confidential_media(&conn, media)?;
+    conn.execute_batch(
+        "CREATE TEMP TABLE confidential_projects AS
+            SELECT id FROM projects WHERE confidential = 1;
+         CREATE TEMP TABLE confidential_rows AS
+            SELECT id FROM confidential_projects
+            UNION SELECT id FROM scenes WHERE project_id IN (SELECT id FROM confidential_projects)
+            UNION SELECT id FROM characters
+                WHERE project_id IN (SELECT id FROM confidential_projects)
+            UNION SELECT j.id FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+                WHERE s.project_id IN (SELECT id FROM confidential_projects);
+         DELETE FROM projects WHERE confidential = 1;",
+    )?;
+    // Triggers fire on the cascade and log the deleted rows, so sweep every
+    // project-scoped table afterwards, then the ones that only name a row.
+    // Everything swept goes, so the order between tables doesn't matter.
+    conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+    for table in tables_with(&conn, "project_id")? {
+        conn.execute(
+            &format!(
+                "DELETE FROM \"{}\" WHERE project_id IN (SELECT id FROM confidential_projects)",
+                table
+            ),
+            [],
+        )?;
+    }
+    for column in ROW_COLUMNS {
+        for table in tables_with(&conn, column)? {
+            conn.execute(
+                &format!(
+                    "DELETE FROM \"{}\" WHERE {} IN (SELECT id FROM confidential_rows)",
+                    table, column
+                ),
+                [],
+            )?;
+        }
+    }
+    // FTS5 only marks deleted rows; their terms stay in the index's
+    // segments until it is rebuilt.
+    conn.execute_batch(
+        "INSERT INTO search_index (search_index) VALUES ('rebuild');
+         DROP TABLE confidential_projects;
+         DROP TABLE confidential_rows;
+         VACUUM;",
+    )?;
+    Ok(files)
+}
+
+/// Mark the project confidential, or clear the mark; owners only.
+pub fn set(conn: &Connection, project_id: &str, confidential: bool) -> AppResult<ProjectSummary> {
+    projects::get_project(conn, project_id)?;
+    roles::ensure_role(conn, project_id, Role::Owner)?;
+    conn.execute(
+        "UPDATE projects SET confidential = ?2 WHERE id = ?1",
+        params![project_id, confidential],
+    )?;
+    tracing::info!(project_id = %project_id, confidential, "set project confidentiality");
+    projects::get_project(conn, project_id)
+}
+
+#[tauri::command]
+pub fn set_project_confidential(
+    db: State<'_, Database>,
+    project_id: String,
+    confidential: bool,
+) -> AppResult<ProjectSummary> {
+    set(&db.conn(), &project_id, confidential)
+}
@@ -0,0 +1,252 @@
+use crate::analytics;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::provenance::{self, Provenance};
+use crate::scenes::{self, Scene};
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgb, RgbImage};
+use rusqlite::Connection;
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+/// Page sizes in pixels at 150 dpi, landscape.
+const PAGE_SIZES: &[(&str, u32, u32)] = &[
+    ("a4", 1754, 1240),
+    ("letter", 1650, 1275),
+    ("a3", 2480, 1754),
+    ("tabloid", 2550, 1650),
+];
+
+const MARGIN: u32 = 48;
+const GAP: u32 = 24;
+const LABEL_HEIGHT: u32 = 40;
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const PLACEHOLDER: Rgb<u8> = Rgb([48, 48, 48]);
+const INK: Rgb<u8> = Rgb([20, 20, 20]);
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ContactSheetOptions {
+    #[serde(default = "default_columns")]
+    pub columns: u32,
+    /// One of `PAGE_SIZES`, or `"custom"` with `width` and `height`.
+    #[serde(default = "default_page")]
+    pub page_size: String,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub portrait: bool,
+    /// `"png"` or `"jpeg"`.
+    #[serde(default = "default_format")]
+    pub format: String,
+}
+
+fn default_columns() -> u32 {
+    4
+}
+
+fn default_page() -> String {
+    "a3".into()
+}
+
+fn default_format() -> String {
+    "png".into()
+}
+
+impl ContactSheetOptions {
+    fn page(&self) -> AppResult<(u32, u32)> {
+        let (w, h) = match self.page_size.as_str() {
+            "custom" => match (self.width, self.height) {
+                (Some(w), Some(h))
+                    if (400..=10_000).contains(&w) && (400..=10_000).contains(&h) =>
+                {
+                    (w, h)
+                }
+                _ => {
+                    return Err(AppError::Invalid(
+                        "custom pages need a width and height of 400–10000 px".into(),
+                    ))
+                }
+            },
+            key => PAGE_SIZES
+                .iter()
+                .find(|(k, _, _)| *k == key)
+                .map(|(_, w, h)| (*w, *h))
+                .ok_or_else(|| AppError::Invalid(format!("unknown page size '{}'", key)))?,
+        };
+        Ok(if self.portrait {
+            (h.min(w), h.max(w))
+        } else {
+            (w, h)
+        })
+    }
+
+    fn image_format(&self) -> AppResult<(ImageFormat, &'static str)> {
+        match self.format.as_str() {
+            "png" => Ok((ImageFormat::Png, "png")),
+            "jpeg" | "jpg" => Ok((ImageFormat::Jpeg, "jpg")),
+            other => Err(AppError::Invalid(format!(
+                "unsupported contact sheet format '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// 5×7 bitmaps for the digits used in scene-number labels, one row per byte
+/// with the low five bits set.
+const DIGITS: [[u8; 7]; 10] = [
+    [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+    [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+    [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+    [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+    [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+    [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+    [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+];
+
+fn fill_rect(img: &mut RgbImage, x: u32, y: u32, w: u32, h: u32, color: Rgb<u8>) {
+    for py in y..(y + h).min(img.height()) {
+        for px in x..(x + w).min(img.width()) {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn draw_number(img: &mut RgbImage, x: u32, y: u32, number: i64, scale: u32) {
+    let mut cursor = x;
+    for digit in number.unsigned_abs().to_string().bytes() {
+        let glyph = &DIGITS[(digit - b'0') as usize];
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..5 {
+                if bits & (0x10 >> col) != 0 {
+                    fill_rect(
+                        img,
+                        cursor + col * scale,
+                        y + row as u32 * scale,
+                        scale,
+                        scale,
+                        INK,
+                    );
+                }
+            }
+        }
+        cursor += 6 * scale;
+    }
+}
+
+fn render_page(
+    scenes: &[(Scene, Option<Vec<u8>>)],
+    (width, height): (u32, u32),
+    columns: u32,
+    cell_width: u32,
+    frame_height: u32,
+) -> RgbImage {
+    let mut page = RgbImage::from_pixel(width, height, BACKGROUND);
+    for (i, (scene, bytes)) in scenes.iter().enumerate() {
+        let i = i as u32;
+        let x = MARGIN + (i % columns) * (cell_width + GAP);
+        let y = MARGIN + (i / columns) * (frame_height + LABEL_HEIGHT + GAP);
+        match bytes
+            .as_deref()
+            .and_then(|b| image::load_from_memory(b).ok())
+        {
+            Some(frame) => {
+                let thumb = frame
+                    .resize_to_fill(cell_width, frame_height, FilterType::Triangle)
+                    .to_rgb8();
+                imageops::overlay(&mut page, &thumb, x.into(), y.into());
+            }
+            None => fill_rect(&mut page, x, y, cell_width, frame_height, PLACEHOLDER),
+        }
+        draw_number(&mut page, x, y + frame_height + 10, scene.scene_number, 3);
+    }
+    page
+}
+
+/// Check `options` and load the scenes to lay out, with the provenance to
+/// embed in the pages.
+pub fn load(
+    conn: &Connection,
+    project_id: &str,
+    options: &ContactSheetOptions,
+) -> AppResult<(Vec<Scene>, Provenance)> {
+    if !(1..=12).contains(&options.columns) {
+        return Err(AppError::Invalid("columns must be 1–12".into()));
+    }
+    options.page()?;
+    options.image_format()?;
+    export::load_project(conn, project_id)?;
+    analytics::track(conn, project_id, "export", "contact_sheet");
+    let scenes = scenes::list_for_project(conn, project_id)?;
+    if scenes.is_empty() {
+        return Err(AppError::Invalid("project has no scenes".into()));
+    }
+    Ok((scenes, provenance::for_project(conn, project_id)?))
+}
+
+/// Render `scenes`' storyboard frames as a grid with scene numbers.
+/// Scenes that don't fit on one page spill onto further pages, written
+/// next to `path` with a `-2`, `-3`… suffix. Returns the written paths.
+pub async fn write(
+    scenes: Vec<Scene>,
+    provenance: Provenance,
+    path: &str,
+    options: &ContactSheetOptions,
+) -> AppResult<Vec<String>> {
+    let (page_w, page_h) = options.page()?;
+    let (format, ext) = options.image_format()?;
+
+    let columns = options.columns;
+    let cell_width = (page_w - 2 * MARGIN - (columns - 1) * GAP) / columns;
+    let frame_height = cell_width * 9 / 16;
+    let rows = ((page_h - 2 * MARGIN + GAP) / (frame_height + LABEL_HEIGHT + GAP)).max(1);
+    let per_page = (rows * columns) as usize;
+
+    let mut frames = Vec::with_capacity(scenes.len());
+    for scene in scenes {
+        let bytes = export::image_bytes(&scene.conditioning_image).await;
+        frames.push((scene, bytes));
+    }
+
+    let first = export::resolve_output(path, &format!("contact-sheet.{}", ext))?;
+    let stem = first
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "contact-sheet".into());
+    let mut written = Vec::new();
+    for (n, chunk) in frames.chunks(per_page).enumerate() {
+        let page = render_page(chunk, (page_w, page_h), columns, cell_width, frame_height);
+        let out = if n == 0 {
+            first.clone()
+        } else {
+            first.with_file_name(format!("{}-{}.{}", stem, n + 1, ext))
+        };
+        page.save_with_format(&out, format)
+            .map_err(|e| AppError::Io(format!("writing contact sheet: {}", e)))?;
+        if format == ImageFormat::Png {
+            provenance.embed_png(&out)?;
+        }
+        written.push(out.to_string_lossy().to_string());
+    }
+    Ok(written)
+}
+
+#[tauri::command]
+pub async fn export_contact_sheet(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+    options: ContactSheetOptions,
+) -> AppResult<Vec<String>> {
+    let (scenes, provenance) = load(&app.state::<Database>().conn(), &project_id, &options)?;
+    write(scenes, provenance, &path, &options).await
+}
@@ -0,0 +1,255 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::llm;
+use crate::props;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const CATEGORIES: &[&str] = &[
+    "wardrobe",
+    "time_of_day",
+    "location",
+    "props",
+    "character",
+    "other",
+];
+const SEVERITIES: &[&str] = &["info", "warning", "error"];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ContinuityNote {
+    pub id: String,
+    pub project_id: String,
+    pub scene_id: String,
+    pub previous_scene_id: String,
+    pub category: String,
+    pub severity: String,
+    pub note: String,
+    pub resolved: bool,
+    pub created_at: String,
+}
+
+const NOTE_COLUMNS: &str =
+    "id, project_id, scene_id, previous_scene_id, category, severity, note, resolved, created_at";
+
+fn row_to_note(row: &Row) -> rusqlite::Result<ContinuityNote> {
+    Ok(ContinuityNote {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        scene_id: row.get(2)?,
+        previous_scene_id: row.get(3)?,
+        category: row.get(4)?,
+        severity: row.get(5)?,
+        note: row.get(6)?,
+        resolved: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+pub fn list(conn: &Connection, project_id: &str) -> AppResult<Vec<ContinuityNote>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM continuity_notes WHERE project_id = ?1 ORDER BY created_at, rowid",
+        NOTE_COLUMNS
+    ))?;
+    let notes = stmt
+        .query_map([project_id], row_to_note)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(notes)
+}
+
+/// A scene as the continuity check describes it to the LLM.
+pub struct SceneSummary {
+    id: String,
+    number: i64,
+    title: String,
+    description: String,
+    prompt: String,
+    lighting: String,
+    characters: String,
+    /// Tracked props and wardrobe, see `props::prompt_fragments`.
+    details: String,
+}
+
+fn build_prompt(scenes: &[SceneSummary]) -> String {
+    let listing = scenes
+        .iter()
+        .map(|s| {
+            format!(
+                "[id: {}] Scene {} — {}\nDescription: {}\nPrompt: {}\nLighting: {}\nCharacters: {}\nProps and wardrobe: {}",
+                s.id, s.number, s.title, s.description, s.prompt, s.lighting, s.characters, s.details
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        "You are a script supervisor checking continuity in a film storyboard.
+
+Scenes in order:
+
+{}
+
+Compare each scene only with the scene immediately before it. Report contradictions a viewer would notice: character wardrobe or appearance changing without reason, time of day jumping (night to noon in continuous action), unexplained location jumps, props appearing or vanishing. The props and wardrobe listed for a scene are tracked on purpose: flag a description or prompt that contradicts them, and a tracked item that disappears between adjacent scenes without reason.
+
+Return a JSON array. Each finding has fields: sceneId (the later scene's id), previousSceneId, category (one of: {}), severity (one of: {}), note (one sentence). Return an empty array if there are no problems. Return ONLY the JSON array.",
+        listing,
+        CATEGORIES.join(", "),
+        SEVERITIES.join(", ")
+    )
+}
+
+/// The project's scenes in order, with cast and tracked details. A check
+/// needs at least two.
+pub fn load(conn: &Connection, project_id: &str) -> AppResult<Vec<SceneSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, scene_number, title, description, prompt, lighting,
+            (SELECT coalesce(group_concat(c.name, ', '), '') FROM scene_characters sc
+             JOIN characters c ON c.id = sc.character_id WHERE sc.scene_id = scenes.id)
+         FROM scenes WHERE project_id = ?1 ORDER BY order_key, sort_order, scene_number",
+    )?;
+    let mut scenes = stmt
+        .query_map([project_id], |r| {
+            Ok(SceneSummary {
+                id: r.get(0)?,
+                number: r.get(1)?,
+                title: r.get(2)?,
+                description: r.get(3)?,
+                prompt: r.get(4)?,
+                lighting: r.get(5)?,
+                characters: r.get(6)?,
+                details: String::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for scene in &mut scenes {
+        scene.details = props::prompt_fragments(conn, &scene.id)?.join("; ");
+    }
+    if scenes.len() < 2 {
+        return Err(AppError::Invalid(
+            "continuity check needs at least two scenes".into(),
+        ));
+    }
+    Ok(scenes)
+}
+
+/// Replace the project's unresolved continuity notes with `findings`.
+/// Resolved notes are kept so dismissals survive a re-check.
+pub fn record(
+    conn: &Connection,
+    project_id: &str,
+    scenes: &[SceneSummary],
+    findings: &[Value],
+) -> AppResult<Vec<ContinuityNote>> {
+    // Only accept findings about pairs that really are adjacent.
+    let pairs: HashSet<(&str, &str)> = scenes
+        .windows(2)
+        .map(|w| (w[1].id.as_str(), w[0].id.as_str()))
+        .collect();
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM continuity_notes WHERE project_id = ?1 AND resolved = 0",
+        [project_id],
+    )?;
+    for finding in findings {
+        let scene_id = finding["sceneId"].as_str().unwrap_or_default();
+        let previous_id = finding["previousSceneId"].as_str().unwrap_or_default();
+        let note = finding["note"].as_str().unwrap_or_default().trim();
+        if note.is_empty() || !pairs.contains(&(scene_id, previous_id)) {
+            continue;
+        }
+        let category = pick(&finding["category"], CATEGORIES, "other");
+        let severity = pick(&finding["severity"], SEVERITIES, "warning");
+        tx.execute(
+            "INSERT INTO continuity_notes (id, project_id, scene_id, previous_scene_id, category, severity, note)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                project_id,
+                scene_id,
+                previous_id,
+                category,
+                severity,
+                note
+            ],
+        )?;
+    }
+    tx.commit()?;
+
+    list(conn, project_id)
+}
+
+/// Ask the LLM to compare each pair of adjacent scenes and `record` its
+/// findings.
+#[tauri::command]
+pub async fn check_continuity(
+    app: AppHandle,
+    project_id: String,
+) -> AppResult<Vec<ContinuityNote>> {
+    let (credential, scenes) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        (
+            llm::credential(&conn, Some(&project_id))?,
+            load(&conn, &project_id)?,
+        )
+    };
+    let findings = llm::into_array(
+        llm::generate_json_seeded(
+            &app.state::<Database>(),
+            &project_id,
+            &credential,
+            &build_prompt(&scenes),
+            0.2,
+            llm::ANALYSIS_SEED,
+        )
+        .await?,
+    );
+    record(
+        &app.state::<Database>().conn(),
+        &project_id,
+        &scenes,
+        &findings,
+    )
+}
+
+fn pick<'a>(value: &Value, allowed: &[&'a str], fallback: &'a str) -> &'a str {
+    value
+        .as_str()
+        .and_then(|v| allowed.iter().find(|a| **a == v))
+        .copied()
+        .unwrap_or(fallback)
+}
+
+#[tauri::command]
+pub fn list_continuity_notes(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<ContinuityNote>> {
+    list(&db.conn(), &project_id)
+}
+
+pub fn resolve(conn: &Connection, id: &str, resolved: bool) -> AppResult<()> {
+    let changed = conn.execute(
+        "UPDATE continuity_notes SET resolved = ?2 WHERE id = ?1",
+        params![id, resolved],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("continuity note {}", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resolve_continuity_note(
+    db: State<'_, Database>,
+    id: String,
+    resolved: bool,
+) -> AppResult<()> {
+    resolve(&db.conn(), &id, resolved)
+}
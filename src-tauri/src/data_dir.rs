@@ -0,0 +1,217 @@
+//! Where the app keeps its data. By default that's the platform data dir;
+//! users can move it (external drive, synced folder). The chosen location
+//! is recorded in a pointer file that always stays in the default dir.
+
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::workspaces;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use ts_rs::TS;
+
+const POINTER_FILE: &str = "data-location.json";
+
+/// Tables holding absolute paths into the data dir, rewritten on a move.
+const PATH_COLUMNS: &[(&str, &str)] = &[
+    ("voice_samples", "file_path"),
+    ("reference_clips", "file_path"),
+    ("moodboard_items", "image"),
+    // Lip-sync requests carry the audio path inside their JSON.
+    ("video_jobs", "request_json"),
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pointer {
+    data_dir: Option<PathBuf>,
+}
+
+pub fn default_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ai-directors-chair")
+}
+
+fn pointer_path() -> PathBuf {
+    default_dir().join(POINTER_FILE)
+}
+
+/// The user-chosen data dir, if one has been set.
+pub fn configured() -> Option<PathBuf> {
+    std::fs::read_to_string(pointer_path())
+        .ok()
+        .and_then(|s| serde_json::from_str::<Pointer>(&s).ok())
+        .and_then(|p| p.data_dir)
+}
+
+fn save_pointer(dir: &Path) -> AppResult<()> {
+    let path = pointer_path();
+    std::fs::create_dir_all(default_dir())?;
+    let tmp = path.with_extension("json.tmp");
+    let pointer = Pointer {
+        data_dir: Some(dir.to_path_buf()),
+    };
+    std::fs::write(&tmp, serde_json::to_string_pretty(&pointer)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DataDirInfo {
+    pub current: String,
+    pub default: String,
+    pub is_custom: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MoveReport {
+    pub from: String,
+    pub to: String,
+    pub files: usize,
+    #[ts(type = "number")]
+    pub bytes: u64,
+}
+
+/// Copy `src` into `dst` recursively, returning every copied file
+/// (relative to `src`) with its size.
+fn copy_tree(src: &Path, dst: &Path, rel: &Path, out: &mut Vec<(PathBuf, u64)>) -> AppResult<()> {
+    std::fs::create_dir_all(dst.join(rel))?;
+    for entry in std::fs::read_dir(src.join(rel))? {
+        let entry = entry?;
+        let rel_path = rel.join(entry.file_name());
+        if rel_path == Path::new(POINTER_FILE) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            copy_tree(src, dst, &rel_path, out)?;
+        } else {
+            let bytes = std::fs::copy(src.join(&rel_path), dst.join(&rel_path))?;
+            out.push((rel_path, bytes));
+        }
+    }
+    Ok(())
+}
+
+/// Repoint stored media paths at the new location and make sure the copy
+/// is a sound database.
+fn fix_up_database(path: &Path, from: &str, to: &str) -> AppResult<()> {
+    let conn = db::connect(path)?;
+    let escaped = |s: &str| s.replace('\\', "\\\\");
+    for (table, column) in PATH_COLUMNS {
+        for (old, new) in [
+            (from.to_string(), to.to_string()),
+            (escaped(from), escaped(to)),
+        ] {
+            conn.execute(
+                &format!(
+                    "UPDATE {t} SET {c} = replace({c}, ?1, ?2) WHERE instr({c}, ?1) > 0",
+                    t = table,
+                    c = column
+                ),
+                [&old, &new],
+            )?;
+        }
+    }
+    let check: String = conn.query_row("PRAGMA integrity_check", [], |r| r.get(0))?;
+    if check != "ok" {
+        return Err(AppError::Database(format!(
+            "copied database {} failed its integrity check: {}",
+            path.display(),
+            check
+        )));
+    }
+    Ok(())
+}
+
+fn check_target(from: &Path, to: &Path) -> AppResult<()> {
+    if !to.is_absolute() {
+        return Err(AppError::Invalid("choose an absolute folder path".into()));
+    }
+    if to.starts_with(from) || from.starts_with(to) {
+        return Err(AppError::Invalid(
+            "the new data folder can't be inside the current one or contain it".into(),
+        ));
+    }
+    if to.exists() && std::fs::read_dir(to)?.next().is_some() {
+        return Err(AppError::Invalid(format!(
+            "{} is not empty; choose an empty or new folder",
+            to.display()
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_data_dir() -> DataDirInfo {
+    DataDirInfo {
+        current: db::app_dir().to_string_lossy().to_string(),
+        default: default_dir().to_string_lossy().to_string(),
+        is_custom: configured().is_some(),
+    }
+}
+
+/// Copy the data in `from` to the empty folder `to`, checking every file
+/// arrived whole and repointing the media paths stored in `databases`
+/// (relative to `from`). On failure `to` is removed again; `from` is never
+/// touched.
+pub fn copy(from: &Path, to: &Path, databases: &[PathBuf]) -> AppResult<MoveReport> {
+    check_target(from, to)?;
+    let mut copied = Vec::new();
+    let result = copy_tree(from, to, Path::new(""), &mut copied).and_then(|_| {
+        for (rel, bytes) in &copied {
+            let size = std::fs::metadata(to.join(rel))?.len();
+            if size != *bytes {
+                return Err(AppError::Io(format!(
+                    "{} copied incompletely",
+                    rel.display()
+                )));
+            }
+        }
+        let (from_str, to_str) = (from.to_string_lossy(), to.to_string_lossy());
+        for file in databases {
+            let path = to.join(file);
+            if path.exists() {
+                fix_up_database(&path, &from_str, &to_str)?;
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        // Leave nothing half-copied behind; the old data is untouched.
+        std::fs::remove_dir_all(to).ok();
+        return Err(e);
+    }
+    Ok(MoveReport {
+        from: from.to_string_lossy().to_string(),
+        to: to.to_string_lossy().to_string(),
+        files: copied.len(),
+        bytes: copied.iter().map(|(_, b)| b).sum(),
+    })
+}
+
+/// Move all app data to `new_path`: every workspace database, media and
+/// logs. The database stays locked during the copy, each copied database
+/// is integrity-checked, and only then is the new location recorded and
+/// the connection switched over. The old folder is left in place for the
+/// user to delete. Logs keep going to the old folder until restart.
+#[tauri::command]
+pub fn move_data_dir(db: State<'_, Database>, new_path: String) -> AppResult<MoveReport> {
+    let from = db::app_dir();
+    let to = PathBuf::from(new_path.trim());
+    let databases: Vec<PathBuf> = workspaces::load()
+        .workspaces
+        .into_iter()
+        .map(|w| PathBuf::from(w.file))
+        .collect();
+    let mut conn = db.conn();
+    let report = copy(&from, &to, &databases)?;
+    save_pointer(&to)?;
+    *conn = db::connect(&workspaces::active_db_path())?;
+    tracing::info!(from = %from.display(), to = %to.display(), files = report.files, "moved data dir");
+    Ok(report)
+}
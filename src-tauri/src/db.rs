@@ -0,0 +1,1650 @@
+use crate::data_dir;
+use crate::encryption;
+use crate::error::AppResult;
+use crate::migration_assistant;
+use crate::windows;
+use crate::workspaces;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Root for everything the app stores: workspace databases, media, logs.
+pub fn app_dir() -> PathBuf {
+    let app_dir = data_dir::configured().unwrap_or_else(data_dir::default_dir);
+    std::fs::create_dir_all(&app_dir).ok();
+    app_dir
+}
+
+/// Database file of the active workspace.
+pub fn get_db_path() -> PathBuf {
+    workspaces::active_db_path()
+}
+
+/// Directory for generated or imported files of one kind (audio, images...),
+/// created on first use and shared by all workspaces.
+pub fn media_dir(kind: &str) -> std::io::Result<PathBuf> {
+    let dir = app_dir().join("media").join(kind);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Shared handle to the application database, registered as Tauri state.
+pub struct Database {
+    conn: Mutex<Connection>,
+}
+
+impl Database {
+    pub fn open(db_path: &Path) -> Result<Self, rusqlite::Error> {
+        Ok(Self {
+            conn: Mutex::new(connect(db_path)?),
+        })
+    }
+
+    /// A fresh, fully migrated database that lives only as long as the
+    /// handle, for tests and throwaway sessions.
+    pub fn open_in_memory() -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open_in_memory()?;
+        prepare(&conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Point the shared handle at another database file, migrating it first.
+    /// Commands that already hold the lock finish against the old file.
+    pub fn reopen(&self, db_path: &Path) -> Result<(), rusqlite::Error> {
+        let conn = connect(db_path)?;
+        *self.conn() = conn;
+        Ok(())
+    }
+
+    /// Lock the connection. A poisoned lock only means another command
+    /// panicked mid-query; SQLite itself is still consistent, so carry on.
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub fn connect(db_path: &Path) -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    encryption::unlock(&conn, db_path)?;
+    migration_assistant::backup_before_migrating(&conn, db_path)?;
+    prepare(&conn)?;
+    Ok(conn)
+}
+
+/// Settings, schema and hooks every connection needs before use.
+fn prepare(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    migrate(conn)?;
+    windows::install(conn);
+    Ok(())
+}
+
+/// Bring a database up to the current schema.
+pub fn migrate(conn: &Connection) -> Result<(), rusqlite::Error> {
+    migrate_to(conn, schema_version())
+}
+
+/// Bring a database up to schema `version` and no further, so a migration
+/// can be checked against the rows an older schema held.
+pub fn migrate_to(conn: &Connection, version: usize) -> Result<(), rusqlite::Error> {
+    init_database(conn)?;
+    run_migrations(conn, version)
+}
+
+/// The `user_version` of a fully migrated database.
+pub fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+/// `path` with `.suffix` appended to the full file name.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", suffix));
+    PathBuf::from(name)
+}
+
+/// Replace the database file behind `conn` with `replacement` and
+/// reconnect. The old file is kept as `.bak` until the new one has opened.
+pub fn swap_file(conn: &mut Connection, db_path: &Path, replacement: &Path) -> AppResult<()> {
+    let backup = with_suffix(db_path, "bak");
+    // Close the file first; Windows won't rename over an open database.
+    *conn = Connection::open_in_memory()?;
+    std::fs::rename(db_path, &backup)?;
+    if let Err(e) = std::fs::rename(replacement, db_path) {
+        std::fs::rename(&backup, db_path).ok();
+        *conn = connect(db_path)?;
+        return Err(e.into());
+    }
+    *conn = connect(db_path)?;
+    std::fs::remove_file(&backup).ok();
+    Ok(())
+}
+
+fn init_database(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS projects (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            genre TEXT DEFAULT 'drama',
+            synopsis TEXT DEFAULT '',
+            tone TEXT DEFAULT 'cinematic',
+            created_at TEXT DEFAULT (datetime('now')),
+            updated_at TEXT DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS characters (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT DEFAULT '',
+            photo_data TEXT DEFAULT '',
+            created_at TEXT DEFAULT (datetime('now')),
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS scenes (
+            id TEXT PRIMARY KEY,
+            project_id TEXT NOT NULL,
+            scene_number INTEGER NOT NULL,
+            title TEXT DEFAULT '',
+            description TEXT DEFAULT '',
+            prompt TEXT DEFAULT '',
+            camera_angle TEXT DEFAULT 'medium shot',
+            lighting TEXT DEFAULT 'natural',
+            duration INTEGER DEFAULT 5,
+            dialog TEXT DEFAULT '',
+            characters_json TEXT DEFAULT '[]',
+            status TEXT DEFAULT 'pending',
+            video_url TEXT DEFAULT '',
+            sort_order INTEGER DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now')),
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS video_jobs (
+            id TEXT PRIMARY KEY,
+            scene_id TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            job_id TEXT NOT NULL,
+            status TEXT DEFAULT 'queued',
+            video_url TEXT DEFAULT '',
+            cost REAL DEFAULT 0.0,
+            started_at TEXT DEFAULT (datetime('now')),
+            completed_at TEXT,
+            FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_characters_project ON characters(project_id);
+        CREATE INDEX IF NOT EXISTS idx_scenes_project ON scenes(project_id);
+        CREATE INDEX IF NOT EXISTS idx_scenes_order ON scenes(project_id, sort_order);
+        CREATE INDEX IF NOT EXISTS idx_jobs_scene ON video_jobs(scene_id);
+    ",
+    )?;
+
+    Ok(())
+}
+
+/// Schema changes on top of the base tables, applied in order and tracked
+/// with `PRAGMA user_version`. Never edit an entry once released — append.
+const MIGRATIONS: &[&str] = &[
+    // 1: deferred submissions for offline mode
+    "
+    ALTER TABLE video_jobs ADD COLUMN request_json TEXT DEFAULT '';
+    ALTER TABLE video_jobs ADD COLUMN error TEXT DEFAULT '';
+    CREATE INDEX IF NOT EXISTS idx_jobs_status ON video_jobs(status);
+    ",
+    // 2: LLM continuity findings between adjacent scenes
+    "
+    CREATE TABLE IF NOT EXISTS continuity_notes (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        scene_id TEXT NOT NULL,
+        previous_scene_id TEXT NOT NULL,
+        category TEXT NOT NULL DEFAULT 'other',
+        severity TEXT NOT NULL DEFAULT 'warning',
+        note TEXT NOT NULL,
+        resolved INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+        FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE,
+        FOREIGN KEY (previous_scene_id) REFERENCES scenes(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_continuity_project ON continuity_notes(project_id);
+    CREATE INDEX IF NOT EXISTS idx_continuity_scene ON continuity_notes(scene_id);
+    ",
+    // 3: cinematography style presets and per-project default
+    "
+    CREATE TABLE IF NOT EXISTS style_presets (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT DEFAULT '',
+        guidance TEXT DEFAULT '',
+        keywords TEXT DEFAULT '',
+        negative_prompt TEXT DEFAULT '',
+        builtin INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now'))
+    );
+    INSERT OR IGNORE INTO style_presets (id, name, description, guidance, keywords, builtin) VALUES
+        ('cinematic', 'Cinematic', 'Polished feature-film look',
+         'Shot on 35mm with controlled camera moves, motivated key light and a professional colour grade.',
+         'cinematic, 35mm film grain, professional color grading, dramatic lighting', 1),
+        ('anamorphic', 'Anamorphic Widescreen', '2.39:1 scope look with oval bokeh and flares',
+         'Anamorphic lenses: horizontal blue lens flares, oval bokeh, slight edge distortion, wide 2.39:1 framing, slow deliberate dolly moves.',
+         'anamorphic lens, horizontal lens flares, oval bokeh, 2.39:1 widescreen, shallow depth of field', 1),
+        ('handheld-doc', 'Handheld Documentary', 'Observational, run-and-gun realism',
+         'Handheld camera following the action, available light, occasional focus hunting, natural skin tones, no stylised grade.',
+         'documentary style, handheld camera, natural lighting, authentic, observational', 1),
+        ('noir', 'Film Noir', 'High-contrast 1940s crime look',
+         'Black and white, hard single-source key light, venetian-blind shadows, low angles, wet streets, smoke.',
+         'film noir, high contrast, black and white, dramatic shadows, chiaroscuro', 1),
+        ('vintage', 'Vintage 70s', 'Warm, grainy 1970s film stock',
+         'Zoom lenses with slow push-ins, warm faded colour, heavy grain, soft halation on highlights.',
+         'vintage film, grainy, 1970s aesthetic, warm tones, faded colors', 1),
+        ('anime', 'Anime', 'Hand-drawn animation look',
+         'Cel-shaded animation with painterly backgrounds, expressive character acting and dynamic camera pans.',
+         'anime style, cel shading, vibrant colors, studio ghibli quality', 1);
+    ALTER TABLE projects ADD COLUMN default_style_preset_id TEXT
+        REFERENCES style_presets(id) ON DELETE SET NULL;
+    ",
+    // 4: managed camera angle / lighting vocabulary
+    "
+    CREATE TABLE IF NOT EXISTS camera_angles (
+        key TEXT PRIMARY KEY,
+        label TEXT NOT NULL,
+        description TEXT DEFAULT '',
+        prompt_fragment TEXT DEFAULT '',
+        is_default INTEGER NOT NULL DEFAULT 0,
+        builtin INTEGER NOT NULL DEFAULT 0,
+        sort_order INTEGER DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS lighting_styles (
+        key TEXT PRIMARY KEY,
+        label TEXT NOT NULL,
+        description TEXT DEFAULT '',
+        prompt_fragment TEXT DEFAULT '',
+        is_default INTEGER NOT NULL DEFAULT 0,
+        builtin INTEGER NOT NULL DEFAULT 0,
+        sort_order INTEGER DEFAULT 0
+    );
+    INSERT OR IGNORE INTO camera_angles (key, label, description, prompt_fragment, is_default, builtin, sort_order) VALUES
+        ('closeup', 'Close-up', 'Face or detail fills the frame',
+         'intimate close-up, shallow depth of field, bokeh background', 0, 1, 1),
+        ('medium', 'Medium', 'Waist-up framing, the workhorse shot',
+         'medium shot, balanced composition, eye-level angle', 1, 1, 2),
+        ('wide', 'Wide', 'Establishes location and geography',
+         'wide establishing shot, epic scale, environmental context', 0, 1, 3),
+        ('dolly', 'Dolly', 'Camera travels on a track toward or alongside the subject',
+         'smooth dolly movement, parallax effect, cinematic motion', 0, 1, 4),
+        ('static', 'Static', 'Locked-off tripod frame',
+         'locked-off tripod shot, stable frame, professional framing', 0, 1, 5),
+        ('aerial', 'Aerial', 'Drone or helicopter view from above',
+         'drone aerial shot, sweeping cinematic movement, epic scale', 0, 1, 6),
+        ('handheld', 'Handheld', 'Operator-held camera with organic movement',
+         'handheld camera, subtle motion, documentary feel', 0, 1, 7),
+        ('low-angle', 'Low Angle', 'Camera below eye line looking up; makes subjects imposing',
+         'low angle shot looking up, imposing perspective', 0, 1, 8),
+        ('high-angle', 'High Angle', 'Camera above eye line looking down; diminishes subjects',
+         'high angle shot looking down, vulnerable perspective', 0, 1, 9),
+        ('over-the-shoulder', 'Over the Shoulder', 'Framed past one character onto another',
+         'over-the-shoulder shot, foreground shoulder out of focus', 0, 1, 10),
+        ('pov', 'POV', 'What a character sees',
+         'first-person point of view shot', 0, 1, 11);
+    INSERT OR IGNORE INTO lighting_styles (key, label, description, prompt_fragment, is_default, builtin, sort_order) VALUES
+        ('natural', 'Natural', 'Available light, realistic exposure',
+         'natural ambient lighting, realistic exposure, documentary style', 1, 1, 1),
+        ('golden', 'Golden Hour', 'Low warm sun shortly after sunrise or before sunset',
+         'golden hour lighting, warm tones, soft diffused sunlight', 0, 1, 2),
+        ('noir', 'Noir', 'Hard key, deep shadows, chiaroscuro',
+         'high contrast noir lighting, deep shadows, dramatic chiaroscuro', 0, 1, 3),
+        ('neon', 'Neon', 'Saturated practical signage at night',
+         'neon city lights, cyberpunk aesthetic, reflective surfaces', 0, 1, 4),
+        ('dramatic', 'Dramatic', 'Single hard source, high contrast, moody',
+         'dramatic lighting, single source, high contrast, moody', 0, 1, 5),
+        ('high-key', 'High Key', 'Bright, even, low-contrast light',
+         'high key lighting, bright even exposure, minimal shadows', 0, 1, 6),
+        ('low-key', 'Low Key', 'Mostly shadow with small pools of light',
+         'low key lighting, dark frame, pools of light', 0, 1, 7),
+        ('blue-hour', 'Blue Hour', 'Cool twilight just after sunset',
+         'blue hour twilight, cool tones, soft ambient light', 0, 1, 8),
+        ('practical', 'Practical', 'Lit by lamps and sources visible in frame',
+         'motivated practical lighting, visible lamps, warm interior glow', 0, 1, 9);
+    UPDATE scenes SET camera_angle = 'medium' WHERE camera_angle IN ('medium shot', '');
+    UPDATE scenes SET lighting = 'natural' WHERE lighting = '';
+    INSERT OR IGNORE INTO camera_angles (key, label)
+        SELECT DISTINCT camera_angle, camera_angle FROM scenes WHERE camera_angle IS NOT NULL;
+    INSERT OR IGNORE INTO lighting_styles (key, label)
+        SELECT DISTINCT lighting, lighting FROM scenes WHERE lighting IS NOT NULL;
+    ",
+    // 5: character voice profiles and cloning samples
+    "
+    ALTER TABLE characters ADD COLUMN voice_engine TEXT NOT NULL DEFAULT 'browser';
+    ALTER TABLE characters ADD COLUMN voice_id TEXT NOT NULL DEFAULT '';
+    ALTER TABLE characters ADD COLUMN voice_name TEXT NOT NULL DEFAULT '';
+    ALTER TABLE characters ADD COLUMN voice_speed REAL NOT NULL DEFAULT 1.0;
+    ALTER TABLE characters ADD COLUMN voice_stability REAL NOT NULL DEFAULT 0.5;
+    ALTER TABLE characters ADD COLUMN voice_similarity REAL NOT NULL DEFAULT 0.75;
+    ALTER TABLE characters ADD COLUMN voice_clone_status TEXT NOT NULL DEFAULT '';
+    CREATE TABLE IF NOT EXISTS voice_samples (
+        id TEXT PRIMARY KEY,
+        character_id TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        file_name TEXT DEFAULT '',
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (character_id) REFERENCES characters(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_voice_samples_character ON voice_samples(character_id);
+    ",
+    // 6: job kinds so lip-sync jobs share the queue with video jobs
+    "
+    ALTER TABLE video_jobs ADD COLUMN kind TEXT NOT NULL DEFAULT 'video';
+    ",
+    // 7: moodboards and scene conditioning images
+    "
+    CREATE TABLE IF NOT EXISTS moodboards (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        description TEXT DEFAULT '',
+        sort_order INTEGER DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS moodboard_items (
+        id TEXT PRIMARY KEY,
+        moodboard_id TEXT NOT NULL,
+        image TEXT NOT NULL,
+        source TEXT NOT NULL DEFAULT 'imported',
+        caption TEXT DEFAULT '',
+        include_in_prompt INTEGER NOT NULL DEFAULT 0,
+        sort_order INTEGER DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (moodboard_id) REFERENCES moodboards(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_moodboards_project ON moodboards(project_id, sort_order);
+    CREATE INDEX IF NOT EXISTS idx_moodboard_items_board ON moodboard_items(moodboard_id, sort_order);
+    ALTER TABLE scenes ADD COLUMN conditioning_image TEXT NOT NULL DEFAULT '';
+    ",
+    // 8: page a fetched moodboard reference came from
+    "
+    ALTER TABLE moodboard_items ADD COLUMN source_url TEXT NOT NULL DEFAULT '';
+    ",
+    // 9: reference clips pulled in with yt-dlp
+    "
+    CREATE TABLE IF NOT EXISTS reference_clips (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        source_url TEXT NOT NULL,
+        title TEXT NOT NULL DEFAULT '',
+        file_path TEXT NOT NULL DEFAULT '',
+        duration REAL NOT NULL DEFAULT 0,
+        status TEXT NOT NULL DEFAULT 'downloading',
+        error TEXT NOT NULL DEFAULT '',
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_reference_clips_project ON reference_clips(project_id);
+    ",
+    // 10: review comments on any entity
+    "
+    CREATE TABLE IF NOT EXISTS comments (
+        id TEXT PRIMARY KEY,
+        entity_type TEXT NOT NULL,
+        entity_id TEXT NOT NULL,
+        author_name TEXT NOT NULL,
+        body TEXT NOT NULL,
+        resolved INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_comments_entity ON comments(entity_type, entity_id);
+    ",
+    // 11: scene review workflow states replace the old free-text status
+    "
+    UPDATE scenes SET status = CASE
+        WHEN status = 'generating' THEN 'generating'
+        WHEN status = 'completed' THEN 'review'
+        WHEN status IN ('draft', 'prompt_ready', 'review', 'approved', 'locked') THEN status
+        WHEN prompt != '' THEN 'prompt_ready'
+        ELSE 'draft'
+    END;
+    CREATE INDEX IF NOT EXISTS idx_scenes_status ON scenes(project_id, status);
+    ",
+    // 12: per-project activity feed / audit log
+    "
+    CREATE TABLE IF NOT EXISTS activity_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project_id TEXT NOT NULL,
+        entity_type TEXT NOT NULL,
+        entity_id TEXT NOT NULL,
+        action TEXT NOT NULL,
+        actor TEXT NOT NULL DEFAULT '',
+        summary TEXT NOT NULL DEFAULT '',
+        changes_json TEXT NOT NULL DEFAULT '[]',
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_activity_project ON activity_log(project_id, id);
+    ",
+    // 13: scene location for breakdowns and scheduling
+    "
+    ALTER TABLE scenes ADD COLUMN location TEXT NOT NULL DEFAULT '';
+    ",
+    // 14: shooting days for live-action pickups
+    "
+    CREATE TABLE IF NOT EXISTS shooting_days (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        shoot_date TEXT NOT NULL,
+        call_time TEXT NOT NULL DEFAULT '',
+        wrap_time TEXT NOT NULL DEFAULT '',
+        location TEXT NOT NULL DEFAULT '',
+        notes TEXT NOT NULL DEFAULT '',
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_shooting_days_project ON shooting_days(project_id, shoot_date);
+    CREATE TABLE IF NOT EXISTS shooting_day_scenes (
+        day_id TEXT NOT NULL,
+        scene_id TEXT NOT NULL UNIQUE,
+        position INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (day_id, scene_id),
+        FOREIGN KEY (day_id) REFERENCES shooting_days(id) ON DELETE CASCADE,
+        FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE
+    );
+    ",
+    // 15: project deadlines and milestones
+    "
+    CREATE TABLE IF NOT EXISTS milestones (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        kind TEXT NOT NULL DEFAULT 'custom',
+        title TEXT NOT NULL,
+        due_date TEXT NOT NULL,
+        notes TEXT NOT NULL DEFAULT '',
+        completed INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now')),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_milestones_project ON milestones(project_id, due_date);
+    ",
+    // 16: outgoing webhooks; their URLs live in the secrets store
+    "
+    CREATE TABLE IF NOT EXISTS webhooks (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL DEFAULT 'generic',
+        events_json TEXT NOT NULL DEFAULT '[]',
+        template TEXT NOT NULL DEFAULT '',
+        enabled INTEGER NOT NULL DEFAULT 1,
+        created_at TEXT DEFAULT (datetime('now'))
+    );
+    ",
+    // 17: opt-in local feature usage counts, one row per feature/detail/day
+    "
+    CREATE TABLE IF NOT EXISTS usage_counts (
+        feature TEXT NOT NULL,
+        detail TEXT NOT NULL DEFAULT '',
+        day TEXT NOT NULL,
+        count INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (feature, detail, day)
+    );
+    ",
+    // 18: full-text index over projects, characters and scenes for global
+    // search, kept current by triggers
+    "
+    CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+        entity_type UNINDEXED,
+        entity_id UNINDEXED,
+        project_id UNINDEXED,
+        title,
+        body,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        SELECT 'project', id, id, name, coalesce(synopsis, '') || ' ' || coalesce(genre, '') FROM projects;
+    INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        SELECT 'character', id, project_id, name, coalesce(description, '') FROM characters;
+    INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        SELECT 'scene', id, project_id, coalesce(title, ''),
+            coalesce(description, '') || ' ' || coalesce(prompt, '') || ' ' || coalesce(dialog, '') || ' ' || location
+        FROM scenes;
+
+    CREATE TRIGGER IF NOT EXISTS search_projects_ai AFTER INSERT ON projects BEGIN
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('project', new.id, new.id, new.name, coalesce(new.synopsis, '') || ' ' || coalesce(new.genre, ''));
+    END;
+    CREATE TRIGGER IF NOT EXISTS search_projects_au AFTER UPDATE ON projects BEGIN
+        DELETE FROM search_index WHERE entity_type = 'project' AND entity_id = old.id;
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('project', new.id, new.id, new.name, coalesce(new.synopsis, '') || ' ' || coalesce(new.genre, ''));
+    END;
+    CREATE TRIGGER IF NOT EXISTS search_projects_ad AFTER DELETE ON projects BEGIN
+        DELETE FROM search_index WHERE project_id = old.id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS search_characters_ai AFTER INSERT ON characters BEGIN
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('character', new.id, new.project_id, new.name, coalesce(new.description, ''));
+    END;
+    CREATE TRIGGER IF NOT EXISTS search_characters_au AFTER UPDATE OF name, description ON characters BEGIN
+        DELETE FROM search_index WHERE entity_type = 'character' AND entity_id = old.id;
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('character', new.id, new.project_id, new.name, coalesce(new.description, ''));
+    END;
+    CREATE TRIGGER IF NOT EXISTS search_characters_ad AFTER DELETE ON characters BEGIN
+        DELETE FROM search_index WHERE entity_type = 'character' AND entity_id = old.id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS search_scenes_ai AFTER INSERT ON scenes BEGIN
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('scene', new.id, new.project_id, coalesce(new.title, ''),
+            coalesce(new.description, '') || ' ' || coalesce(new.prompt, '') || ' ' || coalesce(new.dialog, '') || ' ' || new.location);
+    END;
+    CREATE TRIGGER IF NOT EXISTS search_scenes_au AFTER UPDATE OF title, description, prompt, dialog, location ON scenes BEGIN
+        DELETE FROM search_index WHERE entity_type = 'scene' AND entity_id = old.id;
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('scene', new.id, new.project_id, coalesce(new.title, ''),
+            coalesce(new.description, '') || ' ' || coalesce(new.prompt, '') || ' ' || coalesce(new.dialog, '') || ' ' || new.location);
+    END;
+    CREATE TRIGGER IF NOT EXISTS search_scenes_ad AFTER DELETE ON scenes BEGIN
+        DELETE FROM search_index WHERE entity_type = 'scene' AND entity_id = old.id;
+    END;
+    ",
+    // 19: recent and pinned projects for the launcher; opening a project
+    // no longer needs to touch the search index
+    "
+    ALTER TABLE projects ADD COLUMN last_opened_at TEXT;
+    ALTER TABLE projects ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX IF NOT EXISTS idx_projects_recent ON projects(pinned, last_opened_at);
+    DROP TRIGGER IF EXISTS search_projects_au;
+    CREATE TRIGGER search_projects_au AFTER UPDATE OF name, synopsis, genre ON projects BEGIN
+        DELETE FROM search_index WHERE entity_type = 'project' AND entity_id = old.id;
+        INSERT INTO search_index (entity_type, entity_id, project_id, title, body)
+        VALUES ('project', new.id, new.id, new.name, coalesce(new.synopsis, '') || ' ' || coalesce(new.genre, ''));
+    END;
+    ",
+    // 20: last bundle version pushed to or pulled from each cloud folder
+    "
+    CREATE TABLE IF NOT EXISTS cloud_sync (
+        project_id TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        remote_version INTEGER NOT NULL DEFAULT 0,
+        synced_at TEXT NOT NULL,
+        PRIMARY KEY (project_id, provider),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    ",
+    // 21: change log for multi-device sync. Triggers record every edit to
+    // synced fields with the value it replaced, so another device can
+    // merge it three-way; `applying` mutes them while merging.
+    "
+    CREATE TABLE IF NOT EXISTS sync_meta (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        device_id TEXT NOT NULL,
+        clock INTEGER NOT NULL DEFAULT 0,
+        applying INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT OR IGNORE INTO sync_meta (id, device_id) VALUES (1, lower(hex(randomblob(16))));
+    CREATE TABLE IF NOT EXISTS sync_changes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        origin_device TEXT NOT NULL,
+        origin_seq INTEGER NOT NULL,
+        lamport INTEGER NOT NULL,
+        entity_type TEXT NOT NULL,
+        entity_id TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        op TEXT NOT NULL,
+        field TEXT NOT NULL DEFAULT '',
+        old_value TEXT,
+        new_value TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        UNIQUE (origin_device, origin_seq)
+    );
+    CREATE INDEX IF NOT EXISTS idx_sync_changes_project ON sync_changes(project_id, lamport);
+    CREATE TABLE IF NOT EXISTS sync_conflicts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        change_id INTEGER NOT NULL REFERENCES sync_changes(id),
+        entity_type TEXT NOT NULL,
+        entity_id TEXT NOT NULL,
+        project_id TEXT NOT NULL,
+        field TEXT NOT NULL,
+        local_value TEXT,
+        remote_value TEXT,
+        resolved INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT DEFAULT (datetime('now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_sync_conflicts_project ON sync_conflicts(project_id, resolved);
+
+
+    CREATE TRIGGER IF NOT EXISTS sync_projects_ai AFTER INSERT ON projects
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'insert', '', NULL,
+            json_object('id', new.id, 'name', new.name, 'genre', new.genre, 'synopsis', new.synopsis, 'tone', new.tone)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_projects_au AFTER UPDATE ON projects
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'update', 'name',
+            json_quote(old.name), json_quote(new.name)
+        FROM sync_meta m WHERE old.name IS NOT new.name;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'update', 'genre',
+            json_quote(old.genre), json_quote(new.genre)
+        FROM sync_meta m WHERE old.genre IS NOT new.genre;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'update', 'synopsis',
+            json_quote(old.synopsis), json_quote(new.synopsis)
+        FROM sync_meta m WHERE old.synopsis IS NOT new.synopsis;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'update', 'tone',
+            json_quote(old.tone), json_quote(new.tone)
+        FROM sync_meta m WHERE old.tone IS NOT new.tone;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_projects_ad AFTER DELETE ON projects
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', old.id, old.id, 'delete', '',
+            json_object('id', old.id, 'name', old.name, 'genre', old.genre, 'synopsis', old.synopsis, 'tone', old.tone), NULL
+        FROM sync_meta m;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS sync_characters_ai AFTER INSERT ON characters
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'name', new.name, 'description', new.description, 'photo_data', new.photo_data, 'voice_engine', new.voice_engine, 'voice_id', new.voice_id, 'voice_name', new.voice_name, 'voice_speed', new.voice_speed, 'voice_stability', new.voice_stability, 'voice_similarity', new.voice_similarity)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_characters_au AFTER UPDATE ON characters
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'name',
+            json_quote(old.name), json_quote(new.name)
+        FROM sync_meta m WHERE old.name IS NOT new.name;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'description',
+            json_quote(old.description), json_quote(new.description)
+        FROM sync_meta m WHERE old.description IS NOT new.description;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'photo_data',
+            json_quote(old.photo_data), json_quote(new.photo_data)
+        FROM sync_meta m WHERE old.photo_data IS NOT new.photo_data;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'voice_engine',
+            json_quote(old.voice_engine), json_quote(new.voice_engine)
+        FROM sync_meta m WHERE old.voice_engine IS NOT new.voice_engine;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'voice_id',
+            json_quote(old.voice_id), json_quote(new.voice_id)
+        FROM sync_meta m WHERE old.voice_id IS NOT new.voice_id;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'voice_name',
+            json_quote(old.voice_name), json_quote(new.voice_name)
+        FROM sync_meta m WHERE old.voice_name IS NOT new.voice_name;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'voice_speed',
+            json_quote(old.voice_speed), json_quote(new.voice_speed)
+        FROM sync_meta m WHERE old.voice_speed IS NOT new.voice_speed;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'voice_stability',
+            json_quote(old.voice_stability), json_quote(new.voice_stability)
+        FROM sync_meta m WHERE old.voice_stability IS NOT new.voice_stability;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', new.id, new.project_id, 'update', 'voice_similarity',
+            json_quote(old.voice_similarity), json_quote(new.voice_similarity)
+        FROM sync_meta m WHERE old.voice_similarity IS NOT new.voice_similarity;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_characters_ad AFTER DELETE ON characters
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'character', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'name', old.name, 'description', old.description, 'photo_data', old.photo_data, 'voice_engine', old.voice_engine, 'voice_id', old.voice_id, 'voice_name', old.voice_name, 'voice_speed', old.voice_speed, 'voice_stability', old.voice_stability, 'voice_similarity', old.voice_similarity), NULL
+        FROM sync_meta m;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ai AFTER INSERT ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'scene_number', new.scene_number, 'title', new.title, 'description', new.description, 'prompt', new.prompt, 'camera_angle', new.camera_angle, 'lighting', new.lighting, 'duration', new.duration, 'dialog', new.dialog, 'characters_json', new.characters_json, 'status', new.status, 'video_url', new.video_url, 'sort_order', new.sort_order, 'conditioning_image', new.conditioning_image, 'location', new.location)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_au AFTER UPDATE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'scene_number',
+            json_quote(old.scene_number), json_quote(new.scene_number)
+        FROM sync_meta m WHERE old.scene_number IS NOT new.scene_number;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'title',
+            json_quote(old.title), json_quote(new.title)
+        FROM sync_meta m WHERE old.title IS NOT new.title;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'description',
+            json_quote(old.description), json_quote(new.description)
+        FROM sync_meta m WHERE old.description IS NOT new.description;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'prompt',
+            json_quote(old.prompt), json_quote(new.prompt)
+        FROM sync_meta m WHERE old.prompt IS NOT new.prompt;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'camera_angle',
+            json_quote(old.camera_angle), json_quote(new.camera_angle)
+        FROM sync_meta m WHERE old.camera_angle IS NOT new.camera_angle;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'lighting',
+            json_quote(old.lighting), json_quote(new.lighting)
+        FROM sync_meta m WHERE old.lighting IS NOT new.lighting;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'duration',
+            json_quote(old.duration), json_quote(new.duration)
+        FROM sync_meta m WHERE old.duration IS NOT new.duration;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'dialog',
+            json_quote(old.dialog), json_quote(new.dialog)
+        FROM sync_meta m WHERE old.dialog IS NOT new.dialog;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'characters_json',
+            json_quote(old.characters_json), json_quote(new.characters_json)
+        FROM sync_meta m WHERE old.characters_json IS NOT new.characters_json;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'status',
+            json_quote(old.status), json_quote(new.status)
+        FROM sync_meta m WHERE old.status IS NOT new.status;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'video_url',
+            json_quote(old.video_url), json_quote(new.video_url)
+        FROM sync_meta m WHERE old.video_url IS NOT new.video_url;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'sort_order',
+            json_quote(old.sort_order), json_quote(new.sort_order)
+        FROM sync_meta m WHERE old.sort_order IS NOT new.sort_order;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'conditioning_image',
+            json_quote(old.conditioning_image), json_quote(new.conditioning_image)
+        FROM sync_meta m WHERE old.conditioning_image IS NOT new.conditioning_image;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'location',
+            json_quote(old.location), json_quote(new.location)
+        FROM sync_meta m WHERE old.location IS NOT new.location;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ad AFTER DELETE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'scene_number', old.scene_number, 'title', old.title, 'description', old.description, 'prompt', old.prompt, 'camera_angle', old.camera_angle, 'lighting', old.lighting, 'duration', old.duration, 'dialog', old.dialog, 'characters_json', old.characters_json, 'status', old.status, 'video_url', old.video_url, 'sort_order', old.sort_order, 'conditioning_image', old.conditioning_image, 'location', old.location), NULL
+        FROM sync_meta m;
+    END;
+    ",
+    // 22: links from projects to the shared asset library (by content hash)
+    "
+    CREATE TABLE IF NOT EXISTS project_assets (
+        project_id TEXT NOT NULL,
+        asset_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL DEFAULT '',
+        linked_at TEXT DEFAULT (datetime('now')),
+        PRIMARY KEY (project_id, asset_id),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_project_assets_asset ON project_assets(asset_id);
+    ",
+    // 23: row versions for optimistic concurrency. Only edits to fields a
+    // user saves bump them, so background writes (job results, clone
+    // status, last opened) don't make an open editor stale.
+    "
+    ALTER TABLE projects ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE characters ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE scenes ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE moodboards ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE moodboard_items ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    CREATE TRIGGER IF NOT EXISTS projects_version_au
+    AFTER UPDATE OF name, genre, synopsis, tone, default_style_preset_id ON projects
+    WHEN new.version = old.version BEGIN
+        UPDATE projects SET version = old.version + 1 WHERE id = new.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS characters_version_au
+    AFTER UPDATE OF name, description, photo_data, voice_engine, voice_id, voice_name, voice_speed, voice_stability, voice_similarity ON characters
+    WHEN new.version = old.version BEGIN
+        UPDATE characters SET version = old.version + 1 WHERE id = new.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS scenes_version_au
+    AFTER UPDATE OF scene_number, title, description, prompt, camera_angle, lighting, duration, dialog, characters_json, status, sort_order, conditioning_image, location ON scenes
+    WHEN new.version = old.version BEGIN
+        UPDATE scenes SET version = old.version + 1 WHERE id = new.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS moodboards_version_au
+    AFTER UPDATE OF name, description, sort_order ON moodboards
+    WHEN new.version = old.version BEGIN
+        UPDATE moodboards SET version = old.version + 1 WHERE id = new.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS moodboard_items_version_au
+    AFTER UPDATE OF caption, include_in_prompt, image ON moodboard_items
+    WHEN new.version = old.version BEGIN
+        UPDATE moodboard_items SET version = old.version + 1 WHERE id = new.id;
+    END;
+    ",
+    // 24: timestamps as UTC ISO 8601 (`2026-03-01T18:04:05Z`). Converts
+    // existing rows; column defaults can't be changed in place, so insert
+    // triggers rewrite any default `datetime('now')` value.
+    "
+    UPDATE sync_meta SET applying = 1;
+    UPDATE projects SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE projects SET updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', updated_at) WHERE updated_at LIKE '____-__-__ __:__:__';
+    UPDATE projects SET last_opened_at = strftime('%Y-%m-%dT%H:%M:%SZ', last_opened_at) WHERE last_opened_at LIKE '____-__-__ __:__:__';
+    UPDATE characters SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE scenes SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE video_jobs SET started_at = strftime('%Y-%m-%dT%H:%M:%SZ', started_at) WHERE started_at LIKE '____-__-__ __:__:__';
+    UPDATE video_jobs SET completed_at = strftime('%Y-%m-%dT%H:%M:%SZ', completed_at) WHERE completed_at LIKE '____-__-__ __:__:__';
+    UPDATE continuity_notes SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE style_presets SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE voice_samples SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE moodboards SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE moodboard_items SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE reference_clips SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE comments SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE comments SET updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', updated_at) WHERE updated_at LIKE '____-__-__ __:__:__';
+    UPDATE activity_log SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE shooting_days SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE milestones SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE webhooks SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE cloud_sync SET synced_at = strftime('%Y-%m-%dT%H:%M:%SZ', synced_at) WHERE synced_at LIKE '____-__-__ __:__:__';
+    UPDATE sync_changes SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE sync_conflicts SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE created_at LIKE '____-__-__ __:__:__';
+    UPDATE project_assets SET linked_at = strftime('%Y-%m-%dT%H:%M:%SZ', linked_at) WHERE linked_at LIKE '____-__-__ __:__:__';
+    UPDATE settings SET value = strftime('%Y-%m-%dT%H:%M:%SZ', value) WHERE key LIKE 'notifications.batch.%' AND value LIKE '____-__-__ __:__:__';
+    UPDATE sync_meta SET applying = 0;
+    CREATE TRIGGER IF NOT EXISTS projects_utc_ai AFTER INSERT ON projects
+    WHEN new.created_at LIKE '____-__-__ __:__:__' OR new.updated_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE projects SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at), updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', updated_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS characters_utc_ai AFTER INSERT ON characters
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE characters SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS scenes_utc_ai AFTER INSERT ON scenes
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE scenes SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS video_jobs_utc_ai AFTER INSERT ON video_jobs
+    WHEN new.started_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE video_jobs SET started_at = strftime('%Y-%m-%dT%H:%M:%SZ', started_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS continuity_notes_utc_ai AFTER INSERT ON continuity_notes
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE continuity_notes SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS style_presets_utc_ai AFTER INSERT ON style_presets
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE style_presets SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS voice_samples_utc_ai AFTER INSERT ON voice_samples
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE voice_samples SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS moodboards_utc_ai AFTER INSERT ON moodboards
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE moodboards SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS moodboard_items_utc_ai AFTER INSERT ON moodboard_items
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE moodboard_items SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS reference_clips_utc_ai AFTER INSERT ON reference_clips
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE reference_clips SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS comments_utc_ai AFTER INSERT ON comments
+    WHEN new.created_at LIKE '____-__-__ __:__:__' OR new.updated_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE comments SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at), updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', updated_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS activity_log_utc_ai AFTER INSERT ON activity_log
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE activity_log SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS shooting_days_utc_ai AFTER INSERT ON shooting_days
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE shooting_days SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS milestones_utc_ai AFTER INSERT ON milestones
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE milestones SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS webhooks_utc_ai AFTER INSERT ON webhooks
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE webhooks SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_changes_utc_ai AFTER INSERT ON sync_changes
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE sync_changes SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_conflicts_utc_ai AFTER INSERT ON sync_conflicts
+    WHEN new.created_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE sync_conflicts SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at) WHERE rowid = new.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS project_assets_utc_ai AFTER INSERT ON project_assets
+    WHEN new.linked_at LIKE '____-__-__ __:__:__' BEGIN
+        UPDATE project_assets SET linked_at = strftime('%Y-%m-%dT%H:%M:%SZ', linked_at) WHERE rowid = new.rowid;
+    END;
+    ",
+    // 25: letter suffix for insert-style scene numbers (12A). Scene
+    // snapshots in the sync log now carry it, and edits to it are synced.
+    "
+    ALTER TABLE scenes ADD COLUMN scene_suffix TEXT NOT NULL DEFAULT '';
+    DROP TRIGGER IF EXISTS sync_scenes_ai;
+    DROP TRIGGER IF EXISTS sync_scenes_ad;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ai AFTER INSERT ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'scene_number', new.scene_number, 'title', new.title, 'description', new.description, 'prompt', new.prompt, 'camera_angle', new.camera_angle, 'lighting', new.lighting, 'duration', new.duration, 'dialog', new.dialog, 'characters_json', new.characters_json, 'status', new.status, 'video_url', new.video_url, 'sort_order', new.sort_order, 'conditioning_image', new.conditioning_image, 'location', new.location, 'scene_suffix', new.scene_suffix)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ad AFTER DELETE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'scene_number', old.scene_number, 'title', old.title, 'description', old.description, 'prompt', old.prompt, 'camera_angle', old.camera_angle, 'lighting', old.lighting, 'duration', old.duration, 'dialog', old.dialog, 'characters_json', old.characters_json, 'status', old.status, 'video_url', old.video_url, 'sort_order', old.sort_order, 'conditioning_image', old.conditioning_image, 'location', old.location, 'scene_suffix', old.scene_suffix), NULL
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_suffix_au AFTER UPDATE OF scene_suffix ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 AND old.scene_suffix IS NOT new.scene_suffix BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'scene_suffix',
+            json_quote(old.scene_suffix), json_quote(new.scene_suffix)
+        FROM sync_meta m;
+    END;
+    ",
+    // 26: fractional ordering keys for scenes (see `ordering`), so moving a
+    // scene rewrites one row. Existing order is carried over as evenly
+    // spaced keys; sort_order stays for older clients.
+    "
+    ALTER TABLE scenes ADD COLUMN order_key TEXT NOT NULL DEFAULT '';
+    UPDATE sync_meta SET applying = 1;
+    UPDATE scenes SET order_key = (
+        SELECT printf('a%06dV', r.n) FROM (
+            SELECT id, row_number() OVER (PARTITION BY project_id ORDER BY sort_order, scene_number) AS n
+            FROM scenes
+        ) r WHERE r.id = scenes.id
+    );
+    UPDATE sync_meta SET applying = 0;
+    CREATE INDEX IF NOT EXISTS idx_scenes_order ON scenes(project_id, order_key);
+    DROP TRIGGER IF EXISTS sync_scenes_ai;
+    DROP TRIGGER IF EXISTS sync_scenes_ad;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ai AFTER INSERT ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'scene_number', new.scene_number, 'title', new.title, 'description', new.description, 'prompt', new.prompt, 'camera_angle', new.camera_angle, 'lighting', new.lighting, 'duration', new.duration, 'dialog', new.dialog, 'characters_json', new.characters_json, 'status', new.status, 'video_url', new.video_url, 'sort_order', new.sort_order, 'conditioning_image', new.conditioning_image, 'location', new.location, 'scene_suffix', new.scene_suffix, 'order_key', new.order_key)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ad AFTER DELETE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'scene_number', old.scene_number, 'title', old.title, 'description', old.description, 'prompt', old.prompt, 'camera_angle', old.camera_angle, 'lighting', old.lighting, 'duration', old.duration, 'dialog', old.dialog, 'characters_json', old.characters_json, 'status', old.status, 'video_url', old.video_url, 'sort_order', old.sort_order, 'conditioning_image', old.conditioning_image, 'location', old.location, 'scene_suffix', old.scene_suffix, 'order_key', old.order_key), NULL
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_order_au AFTER UPDATE OF order_key ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 AND old.order_key IS NOT new.order_key BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'order_key',
+            json_quote(old.order_key), json_quote(new.order_key)
+        FROM sync_meta m;
+    END;
+    ",
+    // 27: dependencies between scenes (`scene_links`) and jobs held until
+    // the takes they depend on have finished.
+    "
+    CREATE TABLE IF NOT EXISTS scene_links (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        upstream_id TEXT NOT NULL,
+        downstream_id TEXT NOT NULL,
+        kind TEXT NOT NULL DEFAULT 'end_frame',
+        created_at TEXT NOT NULL,
+        UNIQUE (upstream_id, downstream_id),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+        FOREIGN KEY (upstream_id) REFERENCES scenes(id) ON DELETE CASCADE,
+        FOREIGN KEY (downstream_id) REFERENCES scenes(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_scene_links_project ON scene_links(project_id);
+    CREATE INDEX IF NOT EXISTS idx_scene_links_downstream ON scene_links(downstream_id);
+    CREATE TABLE IF NOT EXISTS video_job_waits (
+        job_id TEXT NOT NULL,
+        upstream_job_id TEXT NOT NULL,
+        PRIMARY KEY (job_id, upstream_job_id),
+        FOREIGN KEY (job_id) REFERENCES video_jobs(id) ON DELETE CASCADE,
+        FOREIGN KEY (upstream_job_id) REFERENCES video_jobs(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_video_job_waits_upstream ON video_job_waits(upstream_job_id);
+    ",
+    // 28: reviewer rating (1-5, NULL until rated) and note on each take
+    "
+    ALTER TABLE video_jobs ADD COLUMN rating INTEGER;
+    ALTER TABLE video_jobs ADD COLUMN note TEXT NOT NULL DEFAULT '';
+    ",
+    // 29: generation settings: project defaults and per-scene overrides,
+    // both JSON (see `generation`) and synced like any other field.
+    "
+    ALTER TABLE projects ADD COLUMN generation_json TEXT NOT NULL DEFAULT '{}';
+    ALTER TABLE scenes ADD COLUMN generation_json TEXT NOT NULL DEFAULT '{}';
+    DROP TRIGGER IF EXISTS sync_projects_ai;
+    DROP TRIGGER IF EXISTS sync_projects_ad;
+    DROP TRIGGER IF EXISTS sync_scenes_ai;
+    DROP TRIGGER IF EXISTS sync_scenes_ad;
+    CREATE TRIGGER IF NOT EXISTS sync_projects_ai AFTER INSERT ON projects
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'insert', '', NULL,
+            json_object('id', new.id, 'name', new.name, 'genre', new.genre, 'synopsis', new.synopsis, 'tone', new.tone, 'generation_json', new.generation_json)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_projects_ad AFTER DELETE ON projects
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', old.id, old.id, 'delete', '',
+            json_object('id', old.id, 'name', old.name, 'genre', old.genre, 'synopsis', old.synopsis, 'tone', old.tone, 'generation_json', old.generation_json), NULL
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_projects_generation_au AFTER UPDATE OF generation_json ON projects
+    WHEN (SELECT applying FROM sync_meta) = 0 AND old.generation_json IS NOT new.generation_json BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'project', new.id, new.id, 'update', 'generation_json',
+            json_quote(old.generation_json), json_quote(new.generation_json)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ai AFTER INSERT ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'scene_number', new.scene_number, 'title', new.title, 'description', new.description, 'prompt', new.prompt, 'camera_angle', new.camera_angle, 'lighting', new.lighting, 'duration', new.duration, 'dialog', new.dialog, 'characters_json', new.characters_json, 'status', new.status, 'video_url', new.video_url, 'sort_order', new.sort_order, 'conditioning_image', new.conditioning_image, 'location', new.location, 'scene_suffix', new.scene_suffix, 'order_key', new.order_key, 'generation_json', new.generation_json)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ad AFTER DELETE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'scene_number', old.scene_number, 'title', old.title, 'description', old.description, 'prompt', old.prompt, 'camera_angle', old.camera_angle, 'lighting', old.lighting, 'duration', old.duration, 'dialog', old.dialog, 'characters_json', old.characters_json, 'status', old.status, 'video_url', old.video_url, 'sort_order', old.sort_order, 'conditioning_image', old.conditioning_image, 'location', old.location, 'scene_suffix', old.scene_suffix, 'order_key', old.order_key, 'generation_json', old.generation_json), NULL
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_generation_au AFTER UPDATE OF generation_json ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 AND old.generation_json IS NOT new.generation_json BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'generation_json',
+            json_quote(old.generation_json), json_quote(new.generation_json)
+        FROM sync_meta m;
+    END;
+    ",
+    // 30: scene tags, set by hand or suggested by the LLM (`source`)
+    "
+    CREATE TABLE IF NOT EXISTS scene_tags (
+        scene_id TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        source TEXT NOT NULL DEFAULT 'manual',
+        created_at TEXT NOT NULL,
+        PRIMARY KEY (scene_id, tag),
+        FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_scene_tags_tag ON scene_tags(tag);
+    ",
+    // 31: scene casts move from `scenes.characters_json` to the
+    // `scene_characters` join table, with a role and importance per scene.
+    // Names in the old lists that matched no character become characters.
+    // Row ids are `scene_id:character_id`, so devices converge on the same
+    // rows. The scene triggers are rebuilt without the old column.
+    "
+    CREATE TABLE IF NOT EXISTS scene_characters (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        scene_id TEXT NOT NULL,
+        character_id TEXT NOT NULL,
+        role TEXT NOT NULL DEFAULT '',
+        importance TEXT NOT NULL DEFAULT 'supporting',
+        position INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL,
+        UNIQUE (scene_id, character_id),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+        FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE,
+        FOREIGN KEY (character_id) REFERENCES characters(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_scene_characters_character ON scene_characters(character_id);
+    INSERT INTO characters (id, project_id, name, created_at)
+    SELECT (lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' || substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6)))),
+        project_id, name, strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+    FROM (
+        SELECT s.project_id, trim(j.value) AS name
+        FROM scenes s,
+            json_each(CASE WHEN json_valid(s.characters_json) THEN s.characters_json ELSE '[]' END) j
+        WHERE j.type = 'text' AND trim(j.value) != ''
+            AND NOT EXISTS (SELECT 1 FROM characters c WHERE c.project_id = s.project_id
+                AND (c.id = j.value OR c.name = trim(j.value) COLLATE NOCASE))
+        GROUP BY s.project_id, lower(trim(j.value))
+    );
+    INSERT OR IGNORE INTO scene_characters (id, project_id, scene_id, character_id, position, created_at)
+    SELECT scene_id || ':' || character_id, project_id, scene_id, character_id, position,
+        strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+    FROM (
+        SELECT s.id AS scene_id, s.project_id, j.key AS position,
+            coalesce(
+                (SELECT c.id FROM characters c WHERE c.project_id = s.project_id AND c.id = j.value),
+                (SELECT c.id FROM characters c WHERE c.project_id = s.project_id
+                    AND c.name = trim(j.value) COLLATE NOCASE ORDER BY c.rowid LIMIT 1)
+            ) AS character_id
+        FROM scenes s,
+            json_each(CASE WHEN json_valid(s.characters_json) THEN s.characters_json ELSE '[]' END) j
+        WHERE j.type = 'text' AND trim(j.value) != ''
+    )
+    WHERE character_id IS NOT NULL;
+    DROP TRIGGER IF EXISTS sync_scenes_ai;
+    DROP TRIGGER IF EXISTS sync_scenes_au;
+    DROP TRIGGER IF EXISTS sync_scenes_ad;
+    DROP TRIGGER IF EXISTS scenes_version_au;
+    ALTER TABLE scenes DROP COLUMN characters_json;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ai AFTER INSERT ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'scene_number', new.scene_number, 'title', new.title, 'description', new.description, 'prompt', new.prompt, 'camera_angle', new.camera_angle, 'lighting', new.lighting, 'duration', new.duration, 'dialog', new.dialog, 'status', new.status, 'video_url', new.video_url, 'sort_order', new.sort_order, 'conditioning_image', new.conditioning_image, 'location', new.location, 'scene_suffix', new.scene_suffix, 'order_key', new.order_key, 'generation_json', new.generation_json)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_au AFTER UPDATE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'scene_number',
+            json_quote(old.scene_number), json_quote(new.scene_number)
+        FROM sync_meta m WHERE old.scene_number IS NOT new.scene_number;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'title',
+            json_quote(old.title), json_quote(new.title)
+        FROM sync_meta m WHERE old.title IS NOT new.title;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'description',
+            json_quote(old.description), json_quote(new.description)
+        FROM sync_meta m WHERE old.description IS NOT new.description;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'prompt',
+            json_quote(old.prompt), json_quote(new.prompt)
+        FROM sync_meta m WHERE old.prompt IS NOT new.prompt;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'camera_angle',
+            json_quote(old.camera_angle), json_quote(new.camera_angle)
+        FROM sync_meta m WHERE old.camera_angle IS NOT new.camera_angle;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'lighting',
+            json_quote(old.lighting), json_quote(new.lighting)
+        FROM sync_meta m WHERE old.lighting IS NOT new.lighting;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'duration',
+            json_quote(old.duration), json_quote(new.duration)
+        FROM sync_meta m WHERE old.duration IS NOT new.duration;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'dialog',
+            json_quote(old.dialog), json_quote(new.dialog)
+        FROM sync_meta m WHERE old.dialog IS NOT new.dialog;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'status',
+            json_quote(old.status), json_quote(new.status)
+        FROM sync_meta m WHERE old.status IS NOT new.status;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'video_url',
+            json_quote(old.video_url), json_quote(new.video_url)
+        FROM sync_meta m WHERE old.video_url IS NOT new.video_url;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'sort_order',
+            json_quote(old.sort_order), json_quote(new.sort_order)
+        FROM sync_meta m WHERE old.sort_order IS NOT new.sort_order;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'conditioning_image',
+            json_quote(old.conditioning_image), json_quote(new.conditioning_image)
+        FROM sync_meta m WHERE old.conditioning_image IS NOT new.conditioning_image;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', new.id, new.project_id, 'update', 'location',
+            json_quote(old.location), json_quote(new.location)
+        FROM sync_meta m WHERE old.location IS NOT new.location;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scenes_ad AFTER DELETE ON scenes
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'scene_number', old.scene_number, 'title', old.title, 'description', old.description, 'prompt', old.prompt, 'camera_angle', old.camera_angle, 'lighting', old.lighting, 'duration', old.duration, 'dialog', old.dialog, 'status', old.status, 'video_url', old.video_url, 'sort_order', old.sort_order, 'conditioning_image', old.conditioning_image, 'location', old.location, 'scene_suffix', old.scene_suffix, 'order_key', old.order_key, 'generation_json', old.generation_json), NULL
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS scenes_version_au
+    AFTER UPDATE OF scene_number, title, description, prompt, camera_angle, lighting, duration, dialog, status, sort_order, conditioning_image, location ON scenes
+    WHEN new.version = old.version BEGIN
+        UPDATE scenes SET version = old.version + 1 WHERE id = new.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS scene_characters_version_ai AFTER INSERT ON scene_characters BEGIN
+        UPDATE scenes SET version = version + 1 WHERE id = new.scene_id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS scene_characters_version_au
+    AFTER UPDATE OF role, importance, position ON scene_characters BEGIN
+        UPDATE scenes SET version = version + 1 WHERE id = new.scene_id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS scene_characters_version_ad AFTER DELETE ON scene_characters BEGIN
+        UPDATE scenes SET version = version + 1 WHERE id = old.scene_id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scene_characters_ai AFTER INSERT ON scene_characters
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene_character', new.id, new.project_id, 'insert', '', NULL,
+            json_object('id', new.id, 'project_id', new.project_id, 'scene_id', new.scene_id, 'character_id', new.character_id, 'role', new.role, 'importance', new.importance, 'position', new.position)
+        FROM sync_meta m;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scene_characters_au AFTER UPDATE ON scene_characters
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene_character', new.id, new.project_id, 'update', 'role',
+            json_quote(old.role), json_quote(new.role)
+        FROM sync_meta m WHERE old.role IS NOT new.role;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene_character', new.id, new.project_id, 'update', 'importance',
+            json_quote(old.importance), json_quote(new.importance)
+        FROM sync_meta m WHERE old.importance IS NOT new.importance;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene_character', new.id, new.project_id, 'update', 'position',
+            json_quote(old.position), json_quote(new.position)
+        FROM sync_meta m WHERE old.position IS NOT new.position;
+    END;
+    CREATE TRIGGER IF NOT EXISTS sync_scene_characters_ad AFTER DELETE ON scene_characters
+    WHEN (SELECT applying FROM sync_meta) = 0 BEGIN
+        UPDATE sync_meta SET clock = clock + 1;
+        INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op, field, old_value, new_value)
+        SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes WHERE origin_device = m.device_id), m.clock, 'scene_character', old.id, old.project_id, 'delete', '',
+            json_object('id', old.id, 'project_id', old.project_id, 'scene_id', old.scene_id, 'character_id', old.character_id, 'role', old.role, 'importance', old.importance, 'position', old.position), NULL
+        FROM sync_meta m;
+    END;
+    ",
+    // 32: props and wardrobe, each attached to a scene, a character, or a
+    // character in one scene
+    "
+    CREATE TABLE IF NOT EXISTS props (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        prompt_fragment TEXT NOT NULL DEFAULT '',
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS wardrobe (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        prompt_fragment TEXT NOT NULL DEFAULT '',
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS prop_links (
+        id INTEGER PRIMARY KEY,
+        item_id TEXT NOT NULL,
+        scene_id TEXT,
+        character_id TEXT,
+        created_at TEXT NOT NULL,
+        CHECK (scene_id IS NOT NULL OR character_id IS NOT NULL),
+        FOREIGN KEY (item_id) REFERENCES props(id) ON DELETE CASCADE,
+        FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE,
+        FOREIGN KEY (character_id) REFERENCES characters(id) ON DELETE CASCADE
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_prop_links_unique
+        ON prop_links(item_id, coalesce(scene_id, ''), coalesce(character_id, ''));
+    CREATE INDEX IF NOT EXISTS idx_prop_links_scene ON prop_links(scene_id);
+    CREATE INDEX IF NOT EXISTS idx_prop_links_character ON prop_links(character_id);
+    CREATE TABLE IF NOT EXISTS wardrobe_links (
+        id INTEGER PRIMARY KEY,
+        item_id TEXT NOT NULL,
+        scene_id TEXT,
+        character_id TEXT,
+        created_at TEXT NOT NULL,
+        CHECK (scene_id IS NOT NULL OR character_id IS NOT NULL),
+        FOREIGN KEY (item_id) REFERENCES wardrobe(id) ON DELETE CASCADE,
+        FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE CASCADE,
+        FOREIGN KEY (character_id) REFERENCES characters(id) ON DELETE CASCADE
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_wardrobe_links_unique
+        ON wardrobe_links(item_id, coalesce(scene_id, ''), coalesce(character_id, ''));
+    CREATE INDEX IF NOT EXISTS idx_wardrobe_links_scene ON wardrobe_links(scene_id);
+    CREATE INDEX IF NOT EXISTS idx_wardrobe_links_character ON wardrobe_links(character_id);
+    ",
+    // 33: reusable per-character prompt fragments with their past texts,
+    // and why a scene needs regenerating (`stale_reason`, empty when current)
+    "
+    CREATE TABLE IF NOT EXISTS character_fragments (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL,
+        character_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        text TEXT NOT NULL DEFAULT '',
+        version INTEGER NOT NULL DEFAULT 1,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        UNIQUE (character_id, kind),
+        FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+        FOREIGN KEY (character_id) REFERENCES characters(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS character_fragment_versions (
+        fragment_id TEXT NOT NULL,
+        version INTEGER NOT NULL,
+        text TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        PRIMARY KEY (fragment_id, version),
+        FOREIGN KEY (fragment_id) REFERENCES character_fragments(id) ON DELETE CASCADE
+    );
+    ALTER TABLE scenes ADD COLUMN stale_reason TEXT NOT NULL DEFAULT '';
+    ",
+    // 34: scenes that already have takes go stale when what their prompt is
+    // built from changes: a cast member's look, the scene's location, or
+    // the project's style preset
+    "
+    CREATE TRIGGER IF NOT EXISTS stale_characters_au
+    AFTER UPDATE OF description, photo_data ON characters
+    WHEN old.description IS NOT new.description OR old.photo_data IS NOT new.photo_data BEGIN
+        UPDATE scenes SET stale_reason = new.name || '''s description changed'
+        WHERE id IN (SELECT scene_id FROM scene_characters WHERE character_id = new.id)
+            AND (video_url <> '' OR EXISTS (SELECT 1 FROM video_jobs j WHERE j.scene_id = scenes.id));
+    END;
+    CREATE TRIGGER IF NOT EXISTS stale_scenes_location_au
+    AFTER UPDATE OF location ON scenes
+    WHEN old.location IS NOT new.location BEGIN
+        UPDATE scenes SET stale_reason = 'Location changed'
+        WHERE id = new.id
+            AND (video_url <> '' OR EXISTS (SELECT 1 FROM video_jobs j WHERE j.scene_id = scenes.id));
+    END;
+    CREATE TRIGGER IF NOT EXISTS stale_style_presets_au
+    AFTER UPDATE OF guidance, keywords, negative_prompt ON style_presets
+    WHEN old.guidance IS NOT new.guidance OR old.keywords IS NOT new.keywords
+        OR old.negative_prompt IS NOT new.negative_prompt BEGIN
+        UPDATE scenes SET stale_reason = 'Style preset ' || new.name || ' changed'
+        WHERE project_id IN (SELECT id FROM projects WHERE default_style_preset_id = new.id)
+            AND (video_url <> '' OR EXISTS (SELECT 1 FROM video_jobs j WHERE j.scene_id = scenes.id));
+    END;
+    CREATE TRIGGER IF NOT EXISTS stale_projects_preset_au
+    AFTER UPDATE OF default_style_preset_id ON projects
+    WHEN old.default_style_preset_id IS NOT new.default_style_preset_id BEGIN
+        UPDATE scenes SET stale_reason = 'Style preset changed'
+        WHERE project_id = new.id
+            AND (video_url <> '' OR EXISTS (SELECT 1 FROM video_jobs j WHERE j.scene_id = scenes.id));
+    END;
+    CREATE INDEX IF NOT EXISTS idx_scenes_stale ON scenes(project_id) WHERE stale_reason <> '';
+    ",
+    // 35: raw provider requests and responses per job, for debugging
+    "
+    CREATE TABLE IF NOT EXISTS job_logs (
+        id INTEGER PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        phase TEXT NOT NULL,
+        method TEXT NOT NULL,
+        url TEXT NOT NULL,
+        request_json TEXT,
+        status INTEGER,
+        response TEXT NOT NULL DEFAULT '',
+        error TEXT NOT NULL DEFAULT '',
+        created_at TEXT NOT NULL,
+        FOREIGN KEY (job_id) REFERENCES video_jobs(id) ON DELETE CASCADE
+    );
+    CREATE INDEX IF NOT EXISTS idx_job_logs_job ON job_logs(job_id);
+    ",
+    // 36: why a job failed (`failure_kind`), how many times it has been
+    // retried, and when it may next be submitted
+    "
+    ALTER TABLE video_jobs ADD COLUMN failure_kind TEXT NOT NULL DEFAULT '';
+    ALTER TABLE video_jobs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE video_jobs ADD COLUMN retry_at TEXT;
+    ",
+    // 37: last known credit or quota left per provider account
+    "
+    CREATE TABLE IF NOT EXISTS provider_balances (
+        provider TEXT PRIMARY KEY,
+        remaining REAL,
+        quota_limit REAL,
+        unit TEXT NOT NULL,
+        error TEXT NOT NULL DEFAULT '',
+        fetched_at TEXT NOT NULL
+    );
+    ",
+    // 38: local user profiles on a shared machine, each with its own
+    // settings and recent projects
+    "
+    CREATE TABLE IF NOT EXISTS user_profiles (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        avatar TEXT NOT NULL DEFAULT '',
+        created_at TEXT DEFAULT (datetime('now')),
+        last_used_at TEXT
+    );
+    CREATE TABLE IF NOT EXISTS user_settings (
+        user_id TEXT NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (user_id, key)
+    );
+    CREATE TABLE IF NOT EXISTS user_projects (
+        user_id TEXT NOT NULL REFERENCES user_profiles(id) ON DELETE CASCADE,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        opened_at TEXT,
+        is_pinned INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (user_id, project_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_user_projects_project ON user_projects(project_id);
+    ",
+    // 39: read-only lock on delivered projects
+    "
+    ALTER TABLE projects ADD COLUMN locked_at TEXT;
+    ",
+    // 40: autosaved drafts of text being edited, kept until saved or
+    // discarded so they survive a crash
+    "
+    CREATE TABLE IF NOT EXISTS drafts (
+        entity_type TEXT NOT NULL,
+        entity_id TEXT NOT NULL,
+        field TEXT NOT NULL,
+        user_id TEXT NOT NULL DEFAULT '',
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        text TEXT NOT NULL,
+        base_version INTEGER,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (entity_type, entity_id, field, user_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_drafts_project ON drafts(project_id);
+    ",
+    // 41: per-project screenplay conventions for script exports
+    "
+    ALTER TABLE projects ADD COLUMN screenplay_json TEXT NOT NULL DEFAULT '{}';
+    ",
+    // 42: idea inbox filled by the quick-capture window
+    "
+    CREATE TABLE IF NOT EXISTS inbox (
+        id TEXT PRIMARY KEY,
+        text TEXT NOT NULL,
+        source TEXT NOT NULL DEFAULT '',
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_inbox_created ON inbox(created_at);
+    ",
+    // 43: outbound Notion and Airtable connectors: each project's target
+    // and field mapping, and the remote page or record of each scene
+    "
+    CREATE TABLE IF NOT EXISTS integration_links (
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        connector TEXT NOT NULL,
+        target TEXT NOT NULL,
+        target_table TEXT NOT NULL DEFAULT '',
+        fields_json TEXT NOT NULL DEFAULT '{}',
+        last_synced_at TEXT,
+        PRIMARY KEY (project_id, connector)
+    );
+    CREATE TABLE IF NOT EXISTS integration_records (
+        connector TEXT NOT NULL,
+        scene_id TEXT NOT NULL REFERENCES scenes(id) ON DELETE CASCADE,
+        remote_id TEXT NOT NULL,
+        PRIMARY KEY (connector, scene_id)
+    );
+    ",
+    // 44: review platform links on takes
+    "
+    ALTER TABLE video_jobs ADD COLUMN review_url TEXT NOT NULL DEFAULT '';
+    ",
+    // 45: finished renders, and where each has been published
+    "
+    CREATE TABLE IF NOT EXISTS renders (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        path TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_renders_project ON renders(project_id);
+    CREATE TABLE IF NOT EXISTS render_publications (
+        render_id TEXT NOT NULL REFERENCES renders(id) ON DELETE CASCADE,
+        target TEXT NOT NULL,
+        video_id TEXT NOT NULL,
+        url TEXT NOT NULL,
+        published_at TEXT NOT NULL,
+        PRIMARY KEY (render_id, target)
+    );
+    ",
+    // 46: per-scene focal point for reframing social cuts, as fractions of
+    // the frame's width and height
+    "
+    CREATE TABLE IF NOT EXISTS scene_focus (
+        scene_id TEXT PRIMARY KEY REFERENCES scenes(id) ON DELETE CASCADE,
+        x REAL NOT NULL,
+        y REAL NOT NULL
+    );
+    ",
+    // 47: export presets for review copies; `watermark_json` is null when
+    // the preset burns in no watermark
+    "
+    CREATE TABLE IF NOT EXISTS export_presets (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        watermark_json TEXT,
+        created_at TEXT NOT NULL
+    );
+    ",
+    // 48: export presets that burn in scene numbers and running timecode
+    "
+    ALTER TABLE export_presets ADD COLUMN timecode INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 49: archived projects
+    "
+    ALTER TABLE projects ADD COLUMN archived_at TEXT;
+    ",
+    // 50: projects saved as .aidc files
+    "
+    ALTER TABLE projects ADD COLUMN file_path TEXT;
+    ",
+    // 51: plain-text project mirrors
+    "
+    CREATE TABLE IF NOT EXISTS project_mirrors (
+        project_id TEXT PRIMARY KEY REFERENCES projects(id) ON DELETE CASCADE,
+        dir TEXT NOT NULL,
+        last_change INTEGER NOT NULL DEFAULT 0,
+        mirrored_at TEXT
+    );
+    ",
+    // 52: project roles
+    "
+    CREATE TABLE IF NOT EXISTS project_members (
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL CHECK (kind IN ('user', 'device')),
+        member_id TEXT NOT NULL,
+        name TEXT NOT NULL DEFAULT '',
+        role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'commenter', 'viewer')),
+        created_at TEXT NOT NULL,
+        PRIMARY KEY (project_id, kind, member_id)
+    );
+    ",
+    // 53: invite tokens for LAN session guests; only a hash of each is kept
+    "
+    CREATE TABLE IF NOT EXISTS invite_tokens (
+        id TEXT PRIMARY KEY,
+        project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        role TEXT NOT NULL CHECK (role IN ('editor', 'commenter', 'viewer')),
+        token_hash TEXT NOT NULL UNIQUE,
+        created_at TEXT NOT NULL,
+        expires_at TEXT,
+        last_used_at TEXT,
+        last_device_id TEXT,
+        revoked_at TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_invite_tokens_project ON invite_tokens(project_id);
+    ",
+    // 54: scene prompts marked confidential, left out of support exports
+    "
+    ALTER TABLE scenes ADD COLUMN prompt_confidential INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 55: confidential projects, kept on this machine
+    "
+    ALTER TABLE projects ADD COLUMN confidential INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 56: cached answers to repeatable provider calls
+    "
+    CREATE TABLE IF NOT EXISTS provider_cache (
+        key TEXT PRIMARY KEY,
+        provider TEXT NOT NULL,
+        value TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        expires_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_provider_cache_expires ON provider_cache(expires_at);
+    ",
+    // 57: order_key index (26 reused idx_scenes_order, so it was skipped)
+    "
+    CREATE INDEX IF NOT EXISTS idx_scenes_order_key ON scenes(project_id, order_key);
+    ",
+];
+
+fn run_migrations(conn: &Connection, target: usize) -> Result<(), rusqlite::Error> {
+    let current: usize = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().take(target).skip(current) {
+        let version = i + 1;
+        let batch = format!(
+            "BEGIN;\n{}\nPRAGMA user_version = {};\nCOMMIT;",
+            sql, version
+        );
+        if let Err(e) = conn.execute_batch(&batch) {
+            conn.execute_batch("ROLLBACK;").ok();
+            return Err(e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{App, AppHandle, Manager, State};
+use tauri_plugin_deep_link::DeepLinkExt;
+use ts_rs::TS;
+
+/// Registered in `tauri.conf.json` under `plugins.deep-link`.
+pub const SCHEME: &str = "aidc";
+
+/// Where an `aidc://` link points, e.g.
+/// `aidc://project/<id>/scene/<id>` or `aidc://project/<id>/character/<id>`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DeepLink {
+    pub url: String,
+    pub project_id: String,
+    pub scene_id: Option<String>,
+    pub character_id: Option<String>,
+}
+
+/// The last link that arrived, kept until the frontend asks for it so a
+/// link that launched the app isn't lost before the UI is listening.
+#[derive(Default)]
+pub struct PendingLink(Mutex<Option<DeepLink>>);
+
+pub fn parse(url: &str) -> AppResult<DeepLink> {
+    let invalid = || AppError::Invalid(format!("not an {} link: {}", SCHEME, url));
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|r| r.strip_prefix("://"))
+        .ok_or_else(invalid)?;
+    let path = rest.split(['?', '#']).next().unwrap_or_default();
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    let (project_id, scene_id, character_id) = match parts.as_slice() {
+        ["project", project] => (project, None, None),
+        ["project", project, "scene", scene] => (project, Some(scene), None),
+        ["project", project, "character", character] => (project, None, Some(character)),
+        _ => return Err(invalid()),
+    };
+    Ok(DeepLink {
+        url: url.to_string(),
+        project_id: project_id.to_string(),
+        scene_id: scene_id.map(|s| s.to_string()),
+        character_id: character_id.map(|s| s.to_string()),
+    })
+}
+
+pub fn project_link(project_id: &str) -> String {
+    format!("{}://project/{}", SCHEME, project_id)
+}
+
+pub fn scene_link(project_id: &str, scene_id: &str) -> String {
+    format!("{}/scene/{}", project_link(project_id), scene_id)
+}
+
+/// Bring the main window forward and tell the frontend where to go.
+/// Emits `deep-link-opened`.
+fn open(app: &AppHandle, urls: impl IntoIterator<Item = String>) {
+    let Some(link) = urls.into_iter().filter_map(|u| parse(&u).ok()).last() else {
+        return;
+    };
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    *app.state::<PendingLink>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(link.clone());
+    AppEvent::DeepLinkOpened(link).emit(app);
+}
+
+/// Hook up link handling. Links opened while the app runs arrive through
+/// `on_open_url` (forwarded by the single-instance plugin on Windows and
+/// Linux); a link that launched the app is read once at startup.
+pub fn setup(app: &App) {
+    app.manage(PendingLink::default());
+    // Installed bundles register the scheme; dev builds need to do it here.
+    #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+    if let Err(e) = app.deep_link().register_all() {
+        tracing::warn!(scheme = SCHEME, error = %e, "failed to register deep link scheme");
+    }
+    let handle = app.handle().clone();
+    app.deep_link().on_open_url(move |event| {
+        open(&handle, event.urls().into_iter().map(|u| u.to_string()));
+    });
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        open(app.handle(), urls.into_iter().map(|u| u.to_string()));
+    }
+}
+
+/// The link that opened or last focused the app, if the frontend hasn't
+/// handled it yet.
+#[tauri::command]
+pub fn take_pending_deep_link(pending: State<'_, PendingLink>) -> Option<DeepLink> {
+    pending.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// Build a shareable link to a project or one of its scenes.
+#[tauri::command]
+pub fn get_deep_link(project_id: String, scene_id: Option<String>) -> String {
+    match scene_id {
+        Some(scene_id) => scene_link(&project_id, &scene_id),
+        None => project_link(&project_id),
+    }
+}
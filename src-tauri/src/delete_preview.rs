@@ -0,0 +1,209 @@
+//! What deleting a project or character would take with it, shown before
+//! the user confirms. Most tables hang off projects and characters with
+//! `ON DELETE CASCADE`, so the rows are found by walking the schema's
+//! foreign keys rather than a hand-kept list that would drift as tables
+//! are added.
+
+use crate::archive;
+use crate::characters;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::State;
+use ts_rs::TS;
+
+/// Entities that can be previewed, with their table.
+const ENTITY_TABLES: &[(&str, &str)] = &[("project", "projects"), ("character", "characters")];
+/// How many foreign keys deep to follow; the schema is at most three.
+const MAX_DEPTH: usize = 5;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct CascadeCount {
+    pub table: String,
+    #[ts(type = "number")]
+    pub rows: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DeletePreview {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub name: String,
+    /// Scenes deleted with a project, or that a character is cast in and
+    /// would be removed from.
+    #[ts(type = "number")]
+    pub scenes: i64,
+    #[ts(type = "number")]
+    pub takes: i64,
+    /// Media files only this entity uses, which the delete leaves orphaned.
+    pub media_files: usize,
+    #[ts(type = "number")]
+    pub media_bytes: u64,
+    /// Every row the delete removes, by table, the entity's own included.
+    pub rows: Vec<CascadeCount>,
+}
+
+/// A cascading foreign key: rows of `child` whose `from` matches `to` in
+/// `parent` go when the parent row does.
+struct Cascade {
+    child: String,
+    from: String,
+    parent: String,
+    to: String,
+}
+
+fn cascades(conn: &Connection) -> AppResult<Vec<Cascade>> {
+    let tables: Vec<String> = {
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    let mut cascades = Vec::new();
+    for child in tables {
+        let mut stmt = conn.prepare(&format!("PRAGMA foreign_key_list(\"{}\")", child))?;
+        let keys = stmt
+            .query_map([], |r| {
+                Ok((
+                    r.get::<_, String>(2)?,
+                    r.get::<_, String>(3)?,
+                    r.get::<_, Option<String>>(4)?,
+                    r.get::<_, String>(6)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (parent, from, to, on_delete) in keys {
+            if on_delete.eq_ignore_ascii_case("CASCADE") {
+                cascades.push(Cascade {
+                    child: child.clone(),
+                    from,
+                    parent,
+                    // Our keys always name the column; `id` is the key anyway.
+                    to: to.unwrap_or_else(|| "id".into()),
+                });
+            }
+        }
+    }
+    Ok(cascades)
+}
+
+/// Add the conditions selecting rows that cascade from `table` rows
+/// matching `condition` (in terms of `?1`), by child table.
+fn collect(
+    cascades: &[Cascade],
+    table: &str,
+    condition: &str,
+    depth: usize,
+    found: &mut BTreeMap<String, Vec<String>>,
+) {
+    if depth == MAX_DEPTH {
+        return;
+    }
+    for cascade in cascades.iter().filter(|c| c.parent == table) {
+        let child_condition = format!(
+            "\"{}\" IN (SELECT \"{}\" FROM \"{}\" WHERE {})",
+            cascade.from, cascade.to, table, condition
+        );
+        collect(cascades, &cascade.child, &child_condition, depth + 1, found);
+        found
+            .entry(cascade.child.clone())
+            .or_default()
+            .push(child_condition);
+    }
+}
+
+/// Rows removed by deleting the `table` row with id `id`, by table.
+fn cascade_counts(conn: &Connection, table: &str, id: &str) -> AppResult<Vec<CascadeCount>> {
+    let mut found = BTreeMap::new();
+    found.insert(table.to_string(), vec!["id = ?1".to_string()]);
+    collect(&cascades(conn)?, table, "id = ?1", 0, &mut found);
+    let mut counts = Vec::new();
+    for (table, conditions) in found {
+        // A row reached along two paths is still one row.
+        let rows: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM \"{}\" WHERE ({})",
+                table,
+                conditions.join(") OR (")
+            ),
+            [id],
+            |r| r.get(0),
+        )?;
+        if rows > 0 {
+            counts.push(CascadeCount { table, rows });
+        }
+    }
+    Ok(counts)
+}
+
+fn file_sizes<'a>(root: &Path, files: impl IntoIterator<Item = &'a Path>) -> u64 {
+    files
+        .into_iter()
+        .filter_map(|f| std::fs::metadata(root.join(f)).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Report what deleting a project or character would remove, so the
+/// confirmation can say so.
+pub fn preview(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+    root: &Path,
+) -> AppResult<DeletePreview> {
+    let table = ENTITY_TABLES
+        .iter()
+        .find(|(t, _)| *t == entity_type)
+        .map(|(_, table)| *table)
+        .ok_or_else(|| AppError::Invalid(format!("cannot preview deleting '{}'", entity_type)))?;
+    let rows = cascade_counts(conn, table, entity_id)?;
+    let count = |table: &str| rows.iter().find(|c| c.table == table).map_or(0, |c| c.rows);
+    let (name, scenes, files) = if table == "projects" {
+        let project = projects::get_project(conn, entity_id)?;
+        let files = archive::project_files(conn, entity_id, root)?;
+        (project.name, count("scenes"), files)
+    } else {
+        let character = characters::get_character(conn, entity_id)?;
+        let mut stmt =
+            conn.prepare("SELECT file_path FROM voice_samples WHERE character_id = ?1")?;
+        let samples = stmt
+            .query_map([&entity_id], |r| r.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let media = root.join("media");
+        let files = samples
+            .iter()
+            .map(Path::new)
+            .filter(|p| p.starts_with(&media))
+            .filter_map(|p| p.strip_prefix(root).ok().map(Path::to_path_buf))
+            .collect();
+        (character.name, count("scene_characters"), files)
+    };
+    Ok(DeletePreview {
+        entity_type: entity_type.to_string(),
+        entity_id: entity_id.to_string(),
+        name,
+        scenes,
+        takes: count("video_jobs"),
+        media_files: files.len(),
+        media_bytes: file_sizes(root, files.iter().map(|f| f.as_path())),
+        rows,
+    })
+}
+
+#[tauri::command]
+pub fn preview_delete(
+    db: State<'_, Database>,
+    entity_type: String,
+    entity_id: String,
+) -> AppResult<DeletePreview> {
+    preview(&db.conn(), &entity_type, &entity_id, &db::app_dir())
+}
@@ -0,0 +1,271 @@
+//! A quick health check of everything generation depends on: database
+//! latency, media folder throughput, ffmpeg, and how long each configured
+//! provider takes to answer. The latest report is kept next to the
+//! database so `export_support_bundle` can include it.
+
+use crate::clock;
+use crate::db::{self, Database};
+use crate::error::AppResult;
+use crate::{frames, llm, providers, settings, voices};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use ts_rs::TS;
+
+/// Queries timed for the database read and write figures.
+const DB_ROUNDS: u32 = 20;
+/// Size of the file written and read back in the media folder.
+const PROBE_BYTES: usize = 8 * 1024 * 1024;
+/// Provider pings slower than this count as unreachable.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+pub const PROBE_SETTING: &str = "diagnostics.probe";
+const REPORT_FILE: &str = "diagnostics.json";
+
+/// Providers pinged, with the setting that holds each one's key.
+const PROVIDER_HOSTS: &[(&str, &str, &str)] = &[
+    ("fal", providers::FAL_KEY_SETTING, "https://queue.fal.run"),
+    (
+        "gemini",
+        llm::GEMINI_KEY_SETTING,
+        "https://generativelanguage.googleapis.com",
+    ),
+    (
+        "elevenlabs",
+        voices::ELEVENLABS_KEY_SETTING,
+        "https://api.elevenlabs.io",
+    ),
+];
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseCheck {
+    /// Mean time for one indexed read, in milliseconds.
+    pub read_ms: f64,
+    /// Mean time for one committed write, in milliseconds.
+    pub write_ms: f64,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskCheck {
+    pub path: String,
+    pub write_mb_per_sec: f64,
+    pub read_mb_per_sec: f64,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegCheck {
+    pub path: String,
+    pub available: bool,
+    /// First line of `ffmpeg -version`.
+    pub version: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCheck {
+    pub provider: String,
+    pub url: String,
+    /// Providers without a key (or account) aren't pinged.
+    pub configured: bool,
+    /// Time to the response headers, in milliseconds.
+    pub latency_ms: Option<f64>,
+    /// Any status counts as reachable; this is only for reference.
+    pub status: Option<u16>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DiagnosticsReport {
+    pub ran_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub database: DatabaseCheck,
+    pub media: DiskCheck,
+    pub ffmpeg: FfmpegCheck,
+    pub providers: Vec<ProviderCheck>,
+}
+
+fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
+}
+
+fn mb_per_sec(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+fn check_database(conn: &Connection) -> AppResult<DatabaseCheck> {
+    let started = Instant::now();
+    for _ in 0..DB_ROUNDS {
+        settings::get(conn, PROBE_SETTING)?;
+    }
+    let read = started.elapsed();
+    let started = Instant::now();
+    for round in 0..DB_ROUNDS {
+        settings::set(conn, PROBE_SETTING, &round.to_string())?;
+    }
+    let write = started.elapsed();
+    conn.execute(
+        "DELETE FROM settings WHERE key = ?1",
+        params![PROBE_SETTING],
+    )?;
+    Ok(DatabaseCheck {
+        read_ms: millis(read) / f64::from(DB_ROUNDS),
+        write_ms: millis(write) / f64::from(DB_ROUNDS),
+        error: String::new(),
+    })
+}
+
+async fn check_disk() -> AppResult<DiskCheck> {
+    let dir = db::media_dir("diagnostics")?;
+    let path = dir.join(format!("probe-{}.bin", uuid::Uuid::new_v4().simple()));
+    let data = vec![0x5a_u8; PROBE_BYTES];
+
+    let started = Instant::now();
+    let mut file = tokio::fs::File::create(&path).await?;
+    file.write_all(&data).await?;
+    file.sync_all().await?;
+    let write = started.elapsed();
+    drop(file);
+
+    let started = Instant::now();
+    let mut back = Vec::with_capacity(PROBE_BYTES);
+    tokio::fs::File::open(&path)
+        .await?
+        .read_to_end(&mut back)
+        .await?;
+    let read = started.elapsed();
+    let _ = tokio::fs::remove_file(&path).await;
+
+    Ok(DiskCheck {
+        path: dir.to_string_lossy().to_string(),
+        write_mb_per_sec: mb_per_sec(PROBE_BYTES, write),
+        read_mb_per_sec: mb_per_sec(back.len(), read),
+        error: String::new(),
+    })
+}
+
+async fn check_ffmpeg(binary: PathBuf) -> FfmpegCheck {
+    let mut check = FfmpegCheck {
+        path: binary.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    match tokio::process::Command::new(&binary)
+        .arg("-version")
+        .output()
+        .await
+    {
+        Ok(out) if out.status.success() => {
+            check.available = true;
+            check.version = String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+        }
+        Ok(out) => check.error = String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        Err(e) => check.error = e.to_string(),
+    }
+    check
+}
+
+async fn ping(mut check: ProviderCheck) -> ProviderCheck {
+    let started = Instant::now();
+    match providers::client()
+        .head(&check.url)
+        .timeout(PING_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(res) => {
+            check.latency_ms = Some(millis(started.elapsed()));
+            check.status = Some(res.status().as_u16());
+        }
+        Err(e) => check.error = e.to_string(),
+    }
+    check
+}
+
+fn report_path() -> PathBuf {
+    db::app_dir().join(REPORT_FILE)
+}
+
+/// The last report `run_diagnostics` saved, if any.
+pub fn last_report() -> Option<serde_json::Value> {
+    let text = std::fs::read_to_string(report_path()).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// The checks that only need the database: its timings, the ffmpeg binary
+/// to probe, and which providers have a key and so get pinged.
+pub fn local_checks(conn: &Connection) -> AppResult<(DatabaseCheck, PathBuf, Vec<ProviderCheck>)> {
+    let database = check_database(conn).unwrap_or_else(|e| DatabaseCheck {
+        error: e.to_string(),
+        ..Default::default()
+    });
+    let mut targets = Vec::new();
+    for &(provider, key_setting, url) in PROVIDER_HOSTS {
+        let configured = if provider == "gemini" {
+            llm::credential(conn, None).is_ok()
+        } else {
+            settings::get(conn, key_setting)?.is_some_and(|k| !k.is_empty())
+        };
+        targets.push(ProviderCheck {
+            provider: provider.to_string(),
+            url: url.to_string(),
+            configured,
+            ..Default::default()
+        });
+    }
+    Ok((database, frames::ffmpeg_binary(conn)?, targets))
+}
+
+/// Measure the database, media folder, ffmpeg and provider connections,
+/// save the report for the support bundle, and return it. Failed checks
+/// carry their error instead of failing the whole run.
+#[tauri::command]
+pub async fn run_diagnostics(app: AppHandle) -> AppResult<DiagnosticsReport> {
+    let (database, ffmpeg, targets) = local_checks(&app.state::<Database>().conn())?;
+
+    let media = check_disk().await.unwrap_or_else(|e| DiskCheck {
+        error: e.to_string(),
+        ..Default::default()
+    });
+    let ffmpeg = check_ffmpeg(ffmpeg).await;
+    let mut checks = Vec::new();
+    for target in targets {
+        checks.push(if target.configured {
+            ping(target).await
+        } else {
+            target
+        });
+    }
+
+    let report = DiagnosticsReport {
+        ran_at: clock::now(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        database,
+        media,
+        ffmpeg,
+        providers: checks,
+    };
+    std::fs::write(report_path(), serde_json::to_string_pretty(&report)?)?;
+    tracing::info!(
+        db_write_ms = report.database.write_ms,
+        ffmpeg = report.ffmpeg.available,
+        "diagnostics run"
+    );
+    Ok(report)
+}
@@ -0,0 +1,223 @@
+//! Word-level text diffs for the version-history view, so the UI can mark
+//! exactly which words of a prompt changed between two iterations. A scene
+//! "version" is an entry in its activity log: the scene's text as it stood
+//! right after that edit.
+
+use crate::activity::FieldChange;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::scenes::{self, Scene};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use similar::{Algorithm, ChangeTag};
+use std::time::Duration;
+use tauri::State;
+use ts_rs::TS;
+
+/// How long Myers gets to find the smallest diff. Past it, the rest of the
+/// texts is diffed coarsely, so even a huge paste comes back at once.
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Scene text fields the activity log tracks, by their activity field name.
+const TEXT_FIELDS: &[&str] = &[
+    "title",
+    "description",
+    "prompt",
+    "dialog",
+    "location",
+    "cameraAngle",
+    "lighting",
+];
+
+pub mod op {
+    pub const EQUAL: &str = "equal";
+    pub const INSERT: &str = "insert";
+    pub const DELETE: &str = "delete";
+}
+
+/// A run of text that is unchanged, added or removed. Concatenating the
+/// `equal` and `delete` hunks gives the old text; `equal` and `insert`
+/// give the new one.
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub op: &'static str,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct TextDiff {
+    pub hunks: Vec<DiffHunk>,
+    /// Words added and removed, for a "+12 −3" summary.
+    pub inserted: usize,
+    pub deleted: usize,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+    pub diff: TextDiff,
+}
+
+fn is_word(token: &str) -> bool {
+    !token.chars().all(char::is_whitespace)
+}
+
+fn push(hunks: &mut Vec<DiffHunk>, op: &'static str, token: &str) {
+    match hunks.last_mut() {
+        Some(last) if last.op == op => last.text.push_str(token),
+        _ => hunks.push(DiffHunk {
+            op,
+            text: token.to_string(),
+        }),
+    }
+}
+
+/// Diff two texts word by word, over words and the whitespace runs
+/// between them (Myers, in space linear in the texts' length).
+pub fn diff_words(old: &str, new: &str) -> TextDiff {
+    let changes = similar::TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .timeout(DIFF_TIMEOUT)
+        .diff_words(old, new);
+    let mut diff = TextDiff {
+        hunks: Vec::new(),
+        inserted: 0,
+        deleted: 0,
+    };
+    for change in changes.iter_all_changes() {
+        let token = change.value();
+        let kind = match change.tag() {
+            ChangeTag::Equal => op::EQUAL,
+            ChangeTag::Insert => op::INSERT,
+            ChangeTag::Delete => op::DELETE,
+        };
+        if is_word(token) {
+            match kind {
+                op::INSERT => diff.inserted += 1,
+                op::DELETE => diff.deleted += 1,
+                _ => {}
+            }
+        }
+        push(&mut diff.hunks, kind, token);
+    }
+    diff
+}
+
+fn current_value(scene: &Scene, field: &str) -> String {
+    match field {
+        "title" => scene.title.clone(),
+        "description" => scene.description.clone(),
+        "prompt" => scene.prompt.clone(),
+        "dialog" => scene.dialog.clone(),
+        "location" => scene.location.clone(),
+        "cameraAngle" => scene.camera_angle.clone(),
+        "lighting" => scene.lighting.clone(),
+        _ => String::new(),
+    }
+}
+
+/// The "before" value of the first edit to `field` of a scene after
+/// activity entry `after`.
+fn next_old_value(
+    conn: &Connection,
+    scene_id: &str,
+    after: i64,
+    field: &str,
+) -> AppResult<Option<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT changes_json FROM activity_log
+         WHERE entity_type = 'scene' AND entity_id = ?1 AND id > ?2 ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map(params![scene_id, after], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for json in rows {
+        let changes: Vec<FieldChange> = serde_json::from_str(&json).unwrap_or_default();
+        if let Some(change) = changes.into_iter().find(|c| c.field == field) {
+            return Ok(Some(change.old));
+        }
+    }
+    Ok(None)
+}
+
+/// A text field as it stood right after activity entry `version`: the
+/// "before" of the next edit to it, or the current value if it hasn't been
+/// edited since.
+fn value_at(conn: &Connection, scene: &Scene, version: i64, field: &str) -> AppResult<String> {
+    Ok(next_old_value(conn, &scene.id, version, field)?
+        .unwrap_or_else(|| current_value(scene, field)))
+}
+
+fn ensure_version(conn: &Connection, scene_id: &str, version: i64) -> AppResult<()> {
+    conn.query_row(
+        "SELECT 1 FROM activity_log WHERE id = ?1 AND entity_type = 'scene' AND entity_id = ?2",
+        params![version, scene_id],
+        |_| Ok(()),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("version {} of scene {}", version, scene_id)))
+}
+
+#[tauri::command]
+pub fn diff_text(old: String, new: String) -> TextDiff {
+    diff_words(&old, &new)
+}
+
+/// Word diffs of a scene's text fields between two versions (ids from its
+/// activity log); `to` of `None` means the scene as it is now. Fields that
+/// didn't change are left out.
+pub fn scene_versions(
+    conn: &Connection,
+    scene_id: &str,
+    from: i64,
+    to: Option<i64>,
+    fields: Vec<String>,
+) -> AppResult<Vec<FieldDiff>> {
+    if let Some(unknown) = fields.iter().find(|f| !TEXT_FIELDS.contains(&f.as_str())) {
+        return Err(AppError::Invalid(format!(
+            "can't diff scene field '{}'",
+            unknown
+        )));
+    }
+    let scene = scenes::get_scene(conn, scene_id)?;
+    ensure_version(conn, scene_id, from)?;
+    if let Some(to) = to {
+        ensure_version(conn, scene_id, to)?;
+    }
+    let mut diffs = Vec::new();
+    for field in fields {
+        let old = value_at(conn, &scene, from, &field)?;
+        let new = match to {
+            Some(to) => value_at(conn, &scene, to, &field)?,
+            None => current_value(&scene, &field),
+        };
+        if old != new {
+            diffs.push(FieldDiff {
+                diff: diff_words(&old, &new),
+                field,
+                old,
+                new,
+            });
+        }
+    }
+    Ok(diffs)
+}
+
+#[tauri::command]
+pub fn diff_scene_versions(
+    db: State<'_, Database>,
+    scene_id: String,
+    from: i64,
+    to: Option<i64>,
+    fields: Option<Vec<String>>,
+) -> AppResult<Vec<FieldDiff>> {
+    let fields = fields.unwrap_or_else(|| vec!["prompt".to_string()]);
+    scene_versions(&db.conn(), &scene_id, from, to, fields)
+}
@@ -0,0 +1,259 @@
+//! Autosaved drafts of text being edited. The editor checkpoints a prompt,
+//! dialog or description every few seconds with `save_draft`; the draft is
+//! dropped once the edit is saved or abandoned. Drafts still around after
+//! a crash or forced quit show up in the recovery report so they can be
+//! restored.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::validation::{Validator, MAX_TEXT_LEN};
+use crate::{projects, users};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Entity types with a draftable text field, with their table and fields.
+const EDITABLE: &[(&str, &str, &[&str])] = &[
+    (
+        "scene",
+        "scenes",
+        &["title", "description", "prompt", "dialog", "location"],
+    ),
+    ("character", "characters", &["name", "description"]),
+    ("project", "projects", &["name", "synopsis"]),
+];
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct DraftInput {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub field: String,
+    pub text: String,
+    /// Version of the entity when editing started; the current one if
+    /// omitted.
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub base_version: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Draft {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub field: String,
+    pub user_id: String,
+    pub project_id: String,
+    pub text: String,
+    /// The field's saved value, to show next to the draft.
+    pub saved_text: String,
+    #[ts(type = "number | null")]
+    pub base_version: Option<i64>,
+    #[ts(type = "number")]
+    pub current_version: i64,
+    /// The entity was saved since the draft started, so restoring the
+    /// draft would overwrite that change.
+    pub conflict: bool,
+    pub updated_at: String,
+}
+
+fn table_for(entity_type: &str) -> Option<(&'static str, &'static [&'static str])> {
+    EDITABLE
+        .iter()
+        .find(|(t, _, _)| *t == entity_type)
+        .map(|&(_, table, fields)| (table, fields))
+}
+
+fn validate(input: &DraftInput) -> AppResult<&'static str> {
+    let mut v = Validator::new();
+    v.uuid("entityId", &input.entity_id)
+        .max_len("text", &input.text, MAX_TEXT_LEN);
+    let table = match table_for(&input.entity_type) {
+        Some((table, fields)) => {
+            v.one_of("field", &input.field, fields);
+            table
+        }
+        None => {
+            let types: Vec<&str> = EDITABLE.iter().map(|(t, _, _)| *t).collect();
+            v.one_of("entityType", &input.entity_type, &types);
+            ""
+        }
+    };
+    v.finish()?;
+    Ok(table)
+}
+
+/// The owning project and current version of an entity, if it exists.
+fn entity(conn: &Connection, table: &str, id: &str) -> AppResult<Option<(String, i64)>> {
+    let project = if table == "projects" {
+        "id"
+    } else {
+        "project_id"
+    };
+    Ok(conn
+        .query_row(
+            &format!("SELECT {}, version FROM {} WHERE id = ?1", project, table),
+            [id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?)
+}
+
+/// Checkpoint a field being edited. Cheap enough to call every few
+/// seconds: one lookup and one upsert. The draft keeps the version it
+/// started from until it is discarded.
+pub fn save(conn: &Connection, input: &DraftInput) -> AppResult<()> {
+    let table = validate(input)?;
+    let (project_id, version) = entity(conn, table, &input.entity_id)?
+        .ok_or_else(|| AppError::NotFound(format!("{} {}", input.entity_type, input.entity_id)))?;
+    projects::ensure_unlocked(conn, &project_id)?;
+    conn.execute(
+        "INSERT INTO drafts
+            (entity_type, entity_id, field, user_id, project_id, text, base_version, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(entity_type, entity_id, field, user_id) DO UPDATE SET
+            text = excluded.text,
+            updated_at = excluded.updated_at",
+        params![
+            input.entity_type,
+            input.entity_id,
+            input.field,
+            users::active().unwrap_or_default(),
+            project_id,
+            input.text,
+            input.base_version.unwrap_or(version),
+            clock::now()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Drafts matching the filters, newest first. Drafts whose entity is gone
+/// or whose text matches what is saved are dropped on the way.
+pub fn list(
+    conn: &Connection,
+    project_id: Option<&str>,
+    user_id: Option<&str>,
+) -> AppResult<Vec<Draft>> {
+    let mut stmt = conn.prepare(
+        "SELECT entity_type, entity_id, field, user_id, project_id, text, base_version, updated_at
+         FROM drafts
+         WHERE (?1 IS NULL OR project_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+         ORDER BY updated_at DESC",
+    )?;
+    let rows = stmt
+        .query_map(params![project_id, user_id], |r| {
+            Ok(Draft {
+                entity_type: r.get(0)?,
+                entity_id: r.get(1)?,
+                field: r.get(2)?,
+                user_id: r.get(3)?,
+                project_id: r.get(4)?,
+                text: r.get(5)?,
+                saved_text: String::new(),
+                base_version: r.get(6)?,
+                current_version: 0,
+                conflict: false,
+                updated_at: r.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut drafts = Vec::new();
+    for mut draft in rows {
+        let saved = table_for(&draft.entity_type)
+            .filter(|(_, fields)| fields.contains(&draft.field.as_str()))
+            .map(|(table, _)| {
+                conn.query_row(
+                    &format!(
+                        "SELECT coalesce({}, ''), version FROM {} WHERE id = ?1",
+                        draft.field, table
+                    ),
+                    [&draft.entity_id],
+                    |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)),
+                )
+                .optional()
+            })
+            .transpose()?
+            .flatten();
+        match saved {
+            Some((text, _)) if text == draft.text => {}
+            Some((text, version)) => {
+                draft.saved_text = text;
+                draft.current_version = version;
+                draft.conflict = draft.base_version.is_some_and(|base| version > base);
+                drafts.push(draft);
+                continue;
+            }
+            None => {}
+        }
+        discard(
+            conn,
+            &draft.entity_type,
+            &draft.entity_id,
+            &draft.field,
+            &draft.user_id,
+        )?;
+    }
+    Ok(drafts)
+}
+
+/// Every user's unsaved drafts, for the crash recovery report.
+pub fn unsaved(conn: &Connection) -> AppResult<Vec<Draft>> {
+    list(conn, None, None)
+}
+
+/// Drop one user's draft of a field.
+pub fn discard(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+    field: &str,
+    user_id: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM drafts
+         WHERE entity_type = ?1 AND entity_id = ?2 AND field = ?3 AND user_id = ?4",
+        params![entity_type, entity_id, field, user_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn save_draft(db: State<'_, Database>, input: DraftInput) -> AppResult<()> {
+    save(&db.conn(), &input)
+}
+
+/// The active user's drafts, optionally for one project.
+#[tauri::command]
+pub fn list_drafts(db: State<'_, Database>, project_id: Option<String>) -> AppResult<Vec<Draft>> {
+    list(
+        &db.conn(),
+        project_id.as_deref(),
+        Some(&users::active().unwrap_or_default()),
+    )
+}
+
+/// Drop a draft, after the field is saved or the user declines to restore
+/// it. Pass `user_id` from a recovered draft to drop another user's.
+#[tauri::command]
+pub fn discard_draft(
+    db: State<'_, Database>,
+    entity_type: String,
+    entity_id: String,
+    field: String,
+    user_id: Option<String>,
+) -> AppResult<()> {
+    discard(
+        &db.conn(),
+        &entity_type,
+        &entity_id,
+        &field,
+        &user_id.or_else(users::active).unwrap_or_default(),
+    )
+}
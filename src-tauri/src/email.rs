@@ -0,0 +1,245 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::notifications::{Notification, NotifyEvent};
+use crate::secrets;
+use crate::settings;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const ENABLED_SETTING: &str = "email.enabled";
+const HOST_SETTING: &str = "email.smtp_host";
+const PORT_SETTING: &str = "email.smtp_port";
+const SECURITY_SETTING: &str = "email.smtp_security";
+const USERNAME_SETTING: &str = "email.smtp_username";
+const FROM_SETTING: &str = "email.from";
+const TO_SETTING: &str = "email.to";
+const EVENTS_SETTING: &str = "email.events";
+const PASSWORD_SECRET: &str = "email.smtp_password";
+
+/// `tls` is implicit TLS (usually port 465), `starttls` upgrades a plain
+/// connection (usually 587), `none` is for local relays only.
+pub const SECURITY_MODES: &[&str] = &["starttls", "tls", "none"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct EmailSettings {
+    pub enabled: bool,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: String,
+    pub smtp_username: String,
+    pub from: String,
+    /// Comma-separated recipients.
+    pub to: String,
+    /// Which notifications are emailed. Defaults to the long-running ones.
+    pub events: Vec<NotifyEvent>,
+    /// Only in responses; the password itself is never returned.
+    #[serde(default)]
+    pub has_password: bool,
+    /// Only in requests; omit to keep the stored password, `""` to clear.
+    #[serde(default, skip_serializing)]
+    pub smtp_password: Option<String>,
+}
+
+pub fn load(conn: &Connection) -> AppResult<EmailSettings> {
+    let get = |key| -> AppResult<String> { Ok(settings::get(conn, key)?.unwrap_or_default()) };
+    let events = settings::get(conn, EVENTS_SETTING)?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_else(|| vec![NotifyEvent::BatchCompleted, NotifyEvent::RenderFinished]);
+    let security = get(SECURITY_SETTING)?;
+    Ok(EmailSettings {
+        enabled: settings::get_bool(conn, ENABLED_SETTING)?,
+        smtp_host: get(HOST_SETTING)?,
+        smtp_port: get(PORT_SETTING)?.parse().unwrap_or(587),
+        smtp_security: if security.is_empty() {
+            "starttls".into()
+        } else {
+            security
+        },
+        smtp_username: get(USERNAME_SETTING)?,
+        from: get(FROM_SETTING)?,
+        to: get(TO_SETTING)?,
+        events,
+        has_password: secrets::get(conn, PASSWORD_SECRET)?.is_some(),
+        smtp_password: None,
+    })
+}
+
+fn mailbox(address: &str) -> AppResult<Mailbox> {
+    address
+        .trim()
+        .parse()
+        .map_err(|e| AppError::Invalid(format!("bad email address '{}': {}", address.trim(), e)))
+}
+
+fn build_message(config: &EmailSettings, subject: &str, body: String) -> AppResult<Message> {
+    let mut builder = Message::builder()
+        .from(mailbox(&config.from)?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for to in config.to.split(',').filter(|a| !a.trim().is_empty()) {
+        builder = builder.to(mailbox(to)?);
+    }
+    builder
+        .body(body)
+        .map_err(|e| AppError::Invalid(format!("email: {}", e)))
+}
+
+async fn send(config: &EmailSettings, password: Option<String>, message: Message) -> AppResult<()> {
+    let smtp_err = |e: lettre::transport::smtp::Error| AppError::Network(format!("smtp: {}", e));
+    let builder = match config.smtp_security.as_str() {
+        "tls" => {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host).map_err(smtp_err)?
+        }
+        "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+        _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+            .map_err(smtp_err)?,
+    };
+    let mut builder = builder
+        .port(config.smtp_port)
+        .timeout(Some(Duration::from_secs(30)));
+    if !config.smtp_username.is_empty() {
+        builder = builder.credentials(Credentials::new(
+            config.smtp_username.clone(),
+            password.unwrap_or_default(),
+        ));
+    }
+    builder.build().send(message).await.map_err(smtp_err)?;
+    Ok(())
+}
+
+fn subject(event: NotifyEvent) -> &'static str {
+    match event {
+        NotifyEvent::JobCompleted => "Generation finished",
+        NotifyEvent::BatchCompleted => "Batch finished",
+        NotifyEvent::RenderFinished => "Render finished",
+        NotifyEvent::BudgetAlert => "Budget alert",
+    }
+}
+
+/// Email a notification if SMTP is set up and the event is one the user
+/// asked for. Sends in the background; failures are logged.
+pub fn notify(app: &AppHandle, notification: &Notification) {
+    let loaded = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        load(&conn).and_then(|c| Ok((c, secrets::get(&conn, PASSWORD_SECRET)?)))
+    };
+    let (config, password) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            tracing::error!(error = %e, "failed to load email settings");
+            return;
+        }
+    };
+    if !config.enabled
+        || config.smtp_host.is_empty()
+        || !config.events.contains(&notification.event)
+    {
+        return;
+    }
+    let body = notification.render("");
+    let message = match build_message(
+        &config,
+        &format!("AIDC: {}", subject(notification.event)),
+        body,
+    ) {
+        Ok(m) => m,
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to build notification email");
+            return;
+        }
+    };
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = send(&config, password, message).await {
+            tracing::warn!(error = %e, "notification email failed");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_email_settings(db: State<'_, Database>) -> AppResult<EmailSettings> {
+    load(&db.conn())
+}
+
+/// Check and store the SMTP settings. The password is kept with the other
+/// secrets and only replaced when `smtp_password` is given.
+pub fn save(conn: &Connection, config: &EmailSettings) -> AppResult<EmailSettings> {
+    if !SECURITY_MODES.contains(&config.smtp_security.as_str()) {
+        return Err(AppError::Invalid(format!(
+            "unknown SMTP security '{}'",
+            config.smtp_security
+        )));
+    }
+    if config.enabled {
+        if config.smtp_host.trim().is_empty() {
+            return Err(AppError::Invalid("SMTP server is required".into()));
+        }
+        mailbox(&config.from)?;
+        if config.to.split(',').all(|a| a.trim().is_empty()) {
+            return Err(AppError::Invalid("add at least one recipient".into()));
+        }
+        for to in config.to.split(',').filter(|a| !a.trim().is_empty()) {
+            mailbox(to)?;
+        }
+    }
+    settings::set(
+        conn,
+        ENABLED_SETTING,
+        if config.enabled { "true" } else { "false" },
+    )?;
+    settings::set(conn, HOST_SETTING, config.smtp_host.trim())?;
+    settings::set(conn, PORT_SETTING, &config.smtp_port.to_string())?;
+    settings::set(conn, SECURITY_SETTING, &config.smtp_security)?;
+    settings::set(conn, USERNAME_SETTING, config.smtp_username.trim())?;
+    settings::set(conn, FROM_SETTING, config.from.trim())?;
+    settings::set(conn, TO_SETTING, config.to.trim())?;
+    settings::set(
+        conn,
+        EVENTS_SETTING,
+        &serde_json::to_string(&config.events)?,
+    )?;
+    match config.smtp_password.as_deref() {
+        Some("") => secrets::delete(conn, PASSWORD_SECRET)?,
+        Some(password) => secrets::set(conn, PASSWORD_SECRET, password)?,
+        None => {}
+    }
+    load(conn)
+}
+
+#[tauri::command]
+pub fn save_email_settings(
+    db: State<'_, Database>,
+    config: EmailSettings,
+) -> AppResult<EmailSettings> {
+    save(&db.conn(), &config)
+}
+
+/// The saved settings, password and message for a test email, built even
+/// if email notifications are switched off.
+pub fn test_message(conn: &Connection) -> AppResult<(EmailSettings, Option<String>, Message)> {
+    let config = load(conn)?;
+    if config.smtp_host.is_empty() {
+        return Err(AppError::Invalid("SMTP server is not configured".into()));
+    }
+    let message = build_message(
+        &config,
+        "AIDC: test email",
+        "Email notifications from AI Director's Chair are working.".into(),
+    )?;
+    Ok((config, secrets::get(conn, PASSWORD_SECRET)?, message))
+}
+
+#[tauri::command]
+pub async fn send_test_email(app: AppHandle) -> AppResult<()> {
+    let (config, password, message) = test_message(&app.state::<Database>().conn())?;
+    send(&config, password, message).await
+}
@@ -0,0 +1,215 @@
+//! Optional encryption at rest via SQLCipher. Each database file's
+//! passphrase lives in the OS keyring, never on disk next to the data.
+//! Turning encryption on or off exports the database to a new file with
+//! `sqlcipher_export` and swaps it in, so an existing plaintext database
+//! is migrated in place.
+
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+use ts_rs::TS;
+
+pub const KEYRING_SERVICE: &str = "ai-directors-chair";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+fn entry(db_path: &Path) -> keyring::Result<keyring::Entry> {
+    let file = db_path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    keyring::Entry::new(KEYRING_SERVICE, &format!("database:{}", file))
+}
+
+fn keyring_err(e: keyring::Error) -> AppError {
+    AppError::Io(format!("OS keyring: {}", e))
+}
+
+/// The stored passphrase for a database file, if it is encrypted.
+fn passphrase(db_path: &Path) -> AppResult<Option<String>> {
+    match entry(db_path).and_then(|e| e.get_password()) {
+        Ok(p) => Ok(Some(p)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keyring_err(e)),
+    }
+}
+
+fn store_passphrase(db_path: &Path, pass: &str) -> AppResult<()> {
+    entry(db_path)
+        .and_then(|e| e.set_password(pass))
+        .map_err(keyring_err)
+}
+
+fn forget_passphrase(db_path: &Path) -> AppResult<()> {
+    match entry(db_path).and_then(|e| e.delete_credential()) {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keyring_err(e)),
+    }
+}
+
+/// Key a freshly opened connection if its file is encrypted. Must run
+/// before anything else touches the database.
+pub fn unlock(conn: &Connection, db_path: &Path) -> rusqlite::Result<()> {
+    match passphrase(db_path) {
+        Ok(Some(pass)) => conn.pragma_update(None, "key", pass),
+        Ok(None) => Ok(()),
+        Err(e) => {
+            tracing::warn!(error = %e, "couldn't read database key; opening unencrypted");
+            Ok(())
+        }
+    }
+}
+
+/// Copy the open database into a new file keyed with `key` (empty for
+/// plaintext), preserving the schema version.
+fn export(conn: &Connection, dest: &Path, key: &str) -> AppResult<()> {
+    std::fs::remove_file(dest).ok();
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS exported KEY ?2",
+        [dest.to_string_lossy().as_ref(), key],
+    )?;
+    let result = conn
+        .query_row("SELECT sqlcipher_export('exported')", [], |_| Ok(()))
+        .and_then(|_| conn.execute_batch(&format!("PRAGMA exported.user_version = {};", version)));
+    conn.execute_batch("DETACH DATABASE exported;")?;
+    result?;
+    Ok(())
+}
+
+/// Check an exported file opens with `key` and is intact.
+fn verify(path: &Path, key: &str) -> AppResult<()> {
+    let conn = Connection::open(path)?;
+    if !key.is_empty() {
+        conn.pragma_update(None, "key", key)?;
+    }
+    let check: String = conn.query_row("PRAGMA integrity_check", [], |r| r.get(0))?;
+    if check != "ok" {
+        return Err(AppError::Database(format!(
+            "re-encrypted copy failed its integrity check: {}",
+            check
+        )));
+    }
+    Ok(())
+}
+
+/// Write the open database to `dest` keyed with `key` (empty for
+/// plaintext), and check the copy opens with it.
+pub fn rekeyed_copy(conn: &Connection, dest: &Path, key: &str) -> AppResult<()> {
+    export(conn, dest, key)?;
+    verify(dest, key)
+}
+
+pub fn check_passphrase(pass: &str) -> AppResult<()> {
+    if pass.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(AppError::Invalid(format!(
+            "passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub cipher_version: String,
+}
+
+fn status(conn: &Connection, db_path: &Path) -> AppResult<EncryptionStatus> {
+    let cipher_version: String = conn
+        .query_row("PRAGMA cipher_version", [], |r| r.get(0))
+        .unwrap_or_default();
+    Ok(EncryptionStatus {
+        enabled: passphrase(db_path)?.is_some(),
+        cipher_version,
+    })
+}
+
+/// The user's passphrase once checked, or a random one if they gave none.
+pub fn new_passphrase(passphrase: Option<String>) -> AppResult<String> {
+    match passphrase {
+        Some(p) => {
+            check_passphrase(&p)?;
+            Ok(p)
+        }
+        None => Ok(format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        )),
+    }
+}
+
+/// Whether the active workspace's database is encrypted.
+#[tauri::command]
+pub fn get_encryption_status(db: State<'_, Database>) -> AppResult<EncryptionStatus> {
+    status(&db.conn(), &db::get_db_path())
+}
+
+/// Encrypt the active workspace's database. Without a passphrase a random
+/// one is generated; either way it is kept in the OS keyring.
+#[tauri::command]
+pub fn enable_encryption(
+    db: State<'_, Database>,
+    passphrase: Option<String>,
+) -> AppResult<EncryptionStatus> {
+    let db_path = db::get_db_path();
+    if self::passphrase(&db_path)?.is_some() {
+        return Err(AppError::Invalid("database is already encrypted".into()));
+    }
+    let pass = new_passphrase(passphrase)?;
+
+    let mut conn = db.conn();
+    let tmp = db::with_suffix(&db_path, "encrypting");
+    rekeyed_copy(&conn, &tmp, &pass)?;
+    store_passphrase(&db_path, &pass)?;
+    if let Err(e) = db::swap_file(&mut conn, &db_path, &tmp) {
+        forget_passphrase(&db_path).ok();
+        std::fs::remove_file(&tmp).ok();
+        return Err(e);
+    }
+    tracing::info!("database encrypted");
+    status(&conn, &db_path)
+}
+
+/// Decrypt the active workspace's database back to plain SQLite and drop
+/// its keyring entry.
+#[tauri::command]
+pub fn disable_encryption(db: State<'_, Database>) -> AppResult<EncryptionStatus> {
+    let db_path = db::get_db_path();
+    if passphrase(&db_path)?.is_none() {
+        return Err(AppError::Invalid("database is not encrypted".into()));
+    }
+    let mut conn = db.conn();
+    let tmp = db::with_suffix(&db_path, "decrypting");
+    rekeyed_copy(&conn, &tmp, "")?;
+    // Reconnecting must not apply the old key to the plaintext file.
+    forget_passphrase(&db_path)?;
+    db::swap_file(&mut conn, &db_path, &tmp)?;
+    tracing::info!("database decrypted");
+    status(&conn, &db_path)
+}
+
+#[tauri::command]
+pub fn change_encryption_passphrase(
+    db: State<'_, Database>,
+    passphrase: String,
+) -> AppResult<EncryptionStatus> {
+    check_passphrase(&passphrase)?;
+    let db_path = db::get_db_path();
+    let Some(old) = self::passphrase(&db_path)? else {
+        return Err(AppError::Invalid("database is not encrypted".into()));
+    };
+    let conn = db.conn();
+    conn.pragma_update(None, "rekey", &passphrase)?;
+    if let Err(e) = store_passphrase(&db_path, &passphrase) {
+        conn.pragma_update(None, "rekey", &old)?;
+        return Err(e);
+    }
+    status(&conn, &db_path)
+}
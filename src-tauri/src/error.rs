@@ -0,0 +1,106 @@
+use crate::validation::FieldError;
+use serde::Serialize;
+use std::fmt;
+
+/// Error returned from every command. Serialized as `{ kind, message }` so
+/// the frontend can branch on `kind` without parsing message text.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum AppError {
+    Database(String),
+    NotFound(String),
+    Invalid(String),
+    Network(String),
+    Provider(String),
+    /// Prompt rejected by the moderation pre-check; message lists categories.
+    Flagged(String),
+    Io(String),
+    /// The row changed since the client read it; nothing was written.
+    Conflict(Box<EditConflict>),
+    /// Input failed validation; nothing was written. Lists every problem.
+    Validation(Vec<FieldError>),
+    /// The project is read-only until unlocked; see `projects::lock_project`.
+    Locked(String),
+    /// The current role on the project doesn't allow this; see `roles`.
+    Forbidden(String),
+}
+
+/// Both sides of a rejected edit, so the UI can show them side by side.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditConflict {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub expected_version: i64,
+    pub current_version: i64,
+    /// What the client tried to save.
+    pub yours: serde_json::Value,
+    /// What is stored now.
+    pub theirs: serde_json::Value,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Database(m) => write!(f, "database error: {}", m),
+            AppError::NotFound(m) => write!(f, "not found: {}", m),
+            AppError::Invalid(m) => write!(f, "invalid input: {}", m),
+            AppError::Network(m) => write!(f, "network error: {}", m),
+            AppError::Provider(m) => write!(f, "provider error: {}", m),
+            AppError::Flagged(m) => write!(f, "prompt flagged by moderation: {}", m),
+            AppError::Io(m) => write!(f, "io error: {}", m),
+            AppError::Conflict(c) => write!(
+                f,
+                "{} {} was changed elsewhere (version {}, expected {})",
+                c.entity_type, c.entity_id, c.current_version, c.expected_version
+            ),
+            AppError::Validation(errors) => {
+                write!(f, "invalid input: ")?;
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{} {}", e.field, e.message)?;
+                }
+                Ok(())
+            }
+            AppError::Locked(m) => write!(f, "locked: {}", m),
+            AppError::Forbidden(m) => write!(f, "not allowed: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound("no matching row".into()),
+            other => AppError::Database(other.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Invalid(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() {
+            AppError::Network(e.to_string())
+        } else {
+            AppError::Provider(e.to_string())
+        }
+    }
+}
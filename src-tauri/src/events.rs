@@ -0,0 +1,113 @@
+//! Every event the backend sends the frontend, in one catalog. Emit with
+//! `AppEvent::emit`; each goes out under its kebab-case name (`JobUpdated`
+//! is `job-updated`) with the variant's value as the payload.
+//!
+//! The enum is exported to TypeScript with ts-rs (`cargo test` writes the
+//! bindings), as a union of `{ event, payload }`, so the frontend can
+//! type its listeners from it instead of from strings.
+
+use crate::backup::{BackupReport, RestoreReport};
+use crate::characters::Character;
+use crate::clips::ReferenceClip;
+use crate::cloud_sync::CloudProvider;
+use crate::deep_link::DeepLink;
+use crate::external_editor::ExternalSave;
+use crate::jobs::VideoJob;
+use crate::lan::LanSession;
+use crate::network::NetworkStatus;
+use crate::publishing::PublishTarget;
+use crate::recovery::RecoveryReport;
+use crate::updater::{UpdateInfo, UpdateProgress};
+use crate::users::UserProfile;
+use crate::watch_folder::SkippedFile;
+use crate::windows::EntityChanged;
+use crate::workflow::StatusChange;
+use crate::workspaces::Workspace;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+use ts_rs::TS;
+
+/// Why signing in to `provider` failed.
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct SignInFailed<T> {
+    pub provider: T,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "event", content = "payload", rename_all = "kebab-case")]
+#[ts(export)]
+pub enum AppEvent {
+    // Jobs and scenes
+    /// A take or lip-sync job changed: status, progress, result or error.
+    JobUpdated(VideoJob),
+    /// Deferred jobs are going out, this many.
+    OfflineQueueFlushing(usize),
+    NetworkStatusChanged(NetworkStatus),
+    SceneStatusChanged(StatusChange),
+    /// A file in the watch folder couldn't be imported as a take.
+    WatchFolderSkipped(SkippedFile),
+    /// A reference clip finished downloading; analysis listens for this.
+    ReferenceClipReady(ReferenceClip),
+    ReferenceClipUpdated(ReferenceClip),
+    /// A character's voice clone finished.
+    CharacterUpdated(Character),
+    ExternalEditSaved(ExternalSave),
+    /// `error` is set.
+    ExternalEditFailed(ExternalSave),
+    /// A row changed, from any window; see `windows`.
+    EntityChanged(EntityChanged),
+    /// An idea was captured; the idea's id.
+    InboxChanged(String),
+
+    // Sync, accounts and backups
+    /// A newer bundle was pulled from cloud storage; the project's id.
+    ProjectSynced(String),
+    CloudStorageConnected(CloudProvider),
+    CloudStorageFailed(SignInFailed<CloudProvider>),
+    PublishAccountConnected(PublishTarget),
+    PublishAccountFailed(SignInFailed<PublishTarget>),
+    /// Signed in to an AI provider; the provider's name.
+    ProviderAccountConnected(String),
+    ProviderAccountFailed(SignInFailed<String>),
+    BackupCompleted(BackupReport),
+    /// A scheduled backup failed; the error.
+    BackupFailed(String),
+    /// A snapshot replaced the workspace; reload everything.
+    BackupRestored(RestoreReport),
+    /// Someone joined or left the LAN session, or it ended (None).
+    LanSessionChanged(Option<LanSession>),
+
+    // The app
+    DeepLinkOpened(DeepLink),
+    UpdateAvailable(UpdateInfo),
+    UpdateProgress(UpdateProgress),
+    /// The version installed.
+    UpdateInstalled(String),
+    /// The active profile; None when signed out.
+    UserChanged(Option<UserProfile>),
+    WorkspaceSwitched(Workspace),
+    RecoveredSession(RecoveryReport),
+    /// The database was restored from this pre-migration backup.
+    DatabaseRolledBack(String),
+}
+
+impl AppEvent {
+    /// Send the event to every window. Failures only mean no window is
+    /// listening, so they are logged and dropped.
+    pub fn emit(&self, app: &AppHandle) {
+        let Ok(Value::Object(mut event)) = serde_json::to_value(self) else {
+            return;
+        };
+        let name = event
+            .get("event")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let payload = event.remove("payload").unwrap_or(Value::Null);
+        if let Err(e) = app.emit(&name, payload) {
+            tracing::debug!(event = %name, error = %e, "event not delivered");
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use crate::analytics;
+use crate::characters::{self, Character};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::i18n::{self, Locale};
+use crate::providers;
+use crate::scenes::{self, Scene};
+use base64::Engine as _;
+use rusqlite::{Connection, OptionalExtension};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Project metadata shared by the exporters.
+pub struct ProjectInfo {
+    pub name: String,
+    pub genre: String,
+    pub synopsis: String,
+    pub tone: String,
+}
+
+pub fn load_project(conn: &Connection, project_id: &str) -> AppResult<ProjectInfo> {
+    conn.query_row(
+        "SELECT name, genre, synopsis, tone FROM projects WHERE id = ?1",
+        [project_id],
+        |r| {
+            Ok(ProjectInfo {
+                name: r.get(0)?,
+                genre: r.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                synopsis: r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                tone: r.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("project {}", project_id)))
+}
+
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn image_mime(path: &Path) -> Option<&'static str> {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("png") => Some("image/png"),
+        Some("jpg") | Some("jpeg") => Some("image/jpeg"),
+        Some("webp") => Some("image/webp"),
+        Some("gif") => Some("image/gif"),
+        _ => None,
+    }
+}
+
+/// One CSV line, quoting fields that need it.
+pub fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| {
+            let f = f.as_ref();
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Turn a stored image reference (media-folder path, data URI or URL) into
+/// something an `<img src>` can use without the app. Local files are
+/// inlined; remote URLs are left as they are.
+pub fn embeddable_image(image: &str) -> Option<String> {
+    if image.is_empty() {
+        None
+    } else if image.starts_with("data:image/") || image.starts_with("http") {
+        Some(image.to_string())
+    } else {
+        let path = Path::new(image);
+        let mime = image_mime(path)?;
+        let bytes = std::fs::read(path).ok()?;
+        Some(format!(
+            "data:{};base64,{}",
+            mime,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ))
+    }
+}
+
+/// Raw bytes for a stored image reference, fetching remote URLs. `None` if
+/// there is no image or it can't be read.
+pub async fn image_bytes(image: &str) -> Option<Vec<u8>> {
+    if image.is_empty() {
+        None
+    } else if let Some(rest) = image.strip_prefix("data:") {
+        let (_, data) = rest.split_once(";base64,")?;
+        base64::engine::general_purpose::STANDARD.decode(data).ok()
+    } else if image.starts_with("http") {
+        let res = providers::client().get(image).send().await.ok()?;
+        if !res.status().is_success() {
+            return None;
+        }
+        res.bytes().await.ok().map(|b| b.to_vec())
+    } else {
+        tokio::fs::read(image).await.ok()
+    }
+}
+
+/// `path` may name the output file or a folder to write `default_name` into.
+pub fn resolve_output(path: &str, default_name: &str) -> AppResult<PathBuf> {
+    let path = PathBuf::from(path);
+    let path = if path.is_dir() {
+        path.join(default_name)
+    } else {
+        path
+    };
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+const STORYBOARD_CSS: &str = "
+body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;margin:0;background:#111;color:#eee}
+header{padding:32px 40px;border-bottom:1px solid #333}
+header h1{margin:0 0 8px}
+header p{color:#aaa;max-width:70ch}
+main{display:grid;grid-template-columns:repeat(auto-fill,minmax(320px,1fr));gap:24px;padding:32px 40px}
+article{background:#1b1b1b;border-radius:8px;overflow:hidden}
+.frame{aspect-ratio:16/9;background:#000;display:flex;align-items:center;justify-content:center;color:#555}
+.frame img{width:100%;height:100%;object-fit:cover}
+.body{padding:16px}
+.body h2{font-size:16px;margin:0 0 8px}
+.meta{font-size:12px;color:#888;margin-bottom:8px}
+.prompt{font-size:13px;color:#ccc;white-space:pre-wrap}
+.dialog{font-size:13px;font-style:italic;border-left:2px solid #555;padding-left:8px;margin-top:8px;white-space:pre-wrap}
+.cast{font-size:12px;color:#aaa;margin-top:8px}
+a{color:#7ab7ff}
+footer{padding:16px 40px;color:#666;font-size:12px}
+";
+
+fn render_storyboard(
+    locale: Locale,
+    project: &ProjectInfo,
+    exported_on: &str,
+    scenes: &[Scene],
+    characters: &[Character],
+    include_video_links: bool,
+) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width,initial-scale=1\">\n\
+         <title>{page_title}</title>\n<style>{css}</style>\n</head>\n<body>\n\
+         <header><h1>{title}</h1><div class=\"meta\">{genre} · {tone} · {count}</div><p>{synopsis}</p></header>\n<main>\n",
+        lang = locale.code(),
+        page_title = escape_html(
+            &locale.tf("storyboard.title", &[("project", &project.name)])
+        ),
+        title = escape_html(&project.name),
+        css = STORYBOARD_CSS,
+        genre = escape_html(&project.genre),
+        tone = escape_html(&project.tone),
+        count = escape_html(&locale.tf(
+            "storyboard.sceneCount",
+            &[("count", &locale.integer(scenes.len() as i64))]
+        )),
+        synopsis = escape_html(&project.synopsis),
+    );
+
+    for scene in scenes {
+        let frame = match embeddable_image(&scene.conditioning_image) {
+            Some(src) => format!("<img src=\"{}\" alt=\"\">", escape_html(&src)),
+            None => escape_html(locale.t("storyboard.noFrame")),
+        };
+        let cast: Vec<&str> = scene
+            .characters
+            .iter()
+            .map(|id| {
+                characters
+                    .iter()
+                    .find(|c| &c.id == id)
+                    .map_or(id.as_str(), |c| c.name.as_str())
+            })
+            .collect();
+
+        let _ = write!(
+            html,
+            "<article><div class=\"frame\">{frame}</div><div class=\"body\">\
+             <h2>{number}. {title}</h2>\
+             <div class=\"meta\">{camera} · {lighting} · {duration}</div>\
+             <div class=\"prompt\">{prompt}</div>",
+            frame = frame,
+            number = scene.scene_number,
+            title = escape_html(&scene.title),
+            camera = escape_html(&scene.camera_angle),
+            lighting = escape_html(&scene.lighting),
+            duration = escape_html(&locale.seconds(scene.duration)),
+            prompt = escape_html(&scene.prompt),
+        );
+        if !scene.dialog.trim().is_empty() {
+            let _ = write!(
+                html,
+                "<div class=\"dialog\">{}</div>",
+                escape_html(&scene.dialog)
+            );
+        }
+        if !cast.is_empty() {
+            let _ = write!(
+                html,
+                "<div class=\"cast\">{}</div>",
+                escape_html(&cast.join(", "))
+            );
+        }
+        if include_video_links && scene.video_url.starts_with("http") {
+            let _ = write!(
+                html,
+                "<div class=\"meta\"><a href=\"{}\" target=\"_blank\" rel=\"noopener\">{}</a></div>",
+                escape_html(&scene.video_url),
+                escape_html(locale.t("storyboard.watchTake"))
+            );
+        }
+        html.push_str("</div></article>\n");
+    }
+
+    let _ = write!(
+        html,
+        "</main>\n<footer>{}</footer>\n</body>\n</html>\n",
+        escape_html(&locale.tf("export.footer", &[("date", &locale.date(exported_on))]))
+    );
+    html
+}
+
+/// Write a read-only storyboard as a single self-contained HTML file, with
+/// frames inlined so it opens anywhere. Remote take links are included only
+/// when `include_video_links` is set. Returns the written path.
+pub fn html(
+    conn: &Connection,
+    project_id: &str,
+    path: &str,
+    include_video_links: bool,
+) -> AppResult<String> {
+    let project = load_project(conn, project_id)?;
+    let scenes = scenes::list_for_project(conn, project_id)?;
+    let characters = characters::list_for_project(conn, project_id)?;
+    let today: String = conn.query_row("SELECT date('now')", [], |r| r.get(0))?;
+    analytics::track(conn, project_id, "export", "html");
+    let html = render_storyboard(
+        i18n::current(conn)?,
+        &project,
+        &today,
+        &scenes,
+        &characters,
+        include_video_links,
+    );
+    let out = resolve_output(path, "index.html")?;
+    std::fs::write(&out, html)?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_html(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+    include_video_links: Option<bool>,
+) -> AppResult<String> {
+    html(
+        &db.conn(),
+        &project_id,
+        &path,
+        include_video_links.unwrap_or(false),
+    )
+}
@@ -0,0 +1,319 @@
+//! Presets for review copies: what gets burned into a take, render or
+//! preview before it leaves for review. A preset's watermark can show the
+//! project name, a WORK IN PROGRESS banner, the export time and who it is
+//! for. Its timecode option adds the scene number and the running time, so
+//! notes like "fix the shot at 01:23" lead straight back to a scene.
+//!
+//! Presets only ever apply to the copy being sent (see `burn_in`); takes
+//! and renders on disk, the masters, are never touched.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::validation::{Validator, MAX_NAME_LEN};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use ts_rs::TS;
+
+const DEFAULT_OPACITY: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Watermark {
+    #[serde(default)]
+    pub project_name: bool,
+    #[serde(default)]
+    pub work_in_progress: bool,
+    /// When the copy was exported.
+    #[serde(default)]
+    pub timestamp: bool,
+    /// The recipient named at export time, if any.
+    #[serde(default)]
+    pub recipient: bool,
+    /// 0.1 (faint) to 1.
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+}
+
+fn default_opacity() -> f64 {
+    DEFAULT_OPACITY
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ExportPreset {
+    pub id: String,
+    pub name: String,
+    pub watermark: Option<Watermark>,
+    /// Burn in the scene number and running timecode.
+    pub timecode: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ExportPresetInput {
+    pub name: String,
+    #[serde(default)]
+    pub watermark: Option<Watermark>,
+    #[serde(default)]
+    pub timecode: bool,
+}
+
+/// What gets burned in, filled in at export time.
+pub struct Stamp<'a> {
+    pub project: &'a str,
+    pub recipient: &'a str,
+    /// Scene labels by start time in seconds, for the timecode overlay.
+    pub scenes: Vec<(f64, String)>,
+}
+
+fn validate(input: &ExportPresetInput) -> AppResult<()> {
+    let mut v = Validator::new();
+    v.required("name", &input.name)
+        .max_len("name", &input.name, MAX_NAME_LEN);
+    if let Some(w) = &input.watermark {
+        if !(0.1..=1.0).contains(&w.opacity) {
+            v.error("watermark.opacity", "must be between 0.1 and 1");
+        }
+    }
+    v.finish()
+}
+
+fn row_to_preset(row: &Row) -> rusqlite::Result<ExportPreset> {
+    let watermark: Option<String> = row.get(2)?;
+    Ok(ExportPreset {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        watermark: watermark.and_then(|w| serde_json::from_str(&w).ok()),
+        timecode: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+pub fn get_preset(conn: &Connection, id: &str) -> AppResult<ExportPreset> {
+    conn.query_row(
+        "SELECT id, name, watermark_json, timecode, created_at FROM export_presets WHERE id = ?1",
+        [id],
+        row_to_preset,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(format!("export preset {}", id)),
+        e => e.into(),
+    })
+}
+
+/// Text safe inside a quoted drawtext value.
+fn drawtext_escape(text: &str) -> String {
+    text.replace('\\', "/").replace('\'', "\u{2019}")
+}
+
+/// The ffmpeg filter that burns `watermark` in, or None if it shows
+/// nothing.
+pub fn watermark_filter(watermark: &Watermark, stamp: &Stamp) -> Option<String> {
+    let opacity = watermark.opacity.clamp(0.1, 1.0);
+    let mut filters = Vec::new();
+    if watermark.work_in_progress {
+        filters.push(format!(
+            "drawtext=text='WORK IN PROGRESS':expansion=none:fontsize=h/10:\
+             fontcolor=white@{o:.2}:borderw=2:bordercolor=black@{o:.2}:\
+             x=(w-text_w)/2:y=(h-text_h)/2",
+            o = opacity
+        ));
+    }
+    let mut parts = Vec::new();
+    if watermark.project_name && !stamp.project.is_empty() {
+        parts.push(stamp.project.to_string());
+    }
+    if watermark.recipient && !stamp.recipient.trim().is_empty() {
+        parts.push(format!("For {}", stamp.recipient.trim()));
+    }
+    if watermark.timestamp {
+        let now = clock::now();
+        parts.push(now.get(..16).unwrap_or(&now).replace('T', " "));
+    }
+    if !parts.is_empty() {
+        filters.push(format!(
+            "drawtext=text='{t}':expansion=none:fontsize=h/28:fontcolor=white@{o:.2}:\
+             box=1:boxcolor=black@{b:.2}:boxborderw=8:x=w/40:y=h-text_h-h/40",
+            t = drawtext_escape(&parts.join("  \u{00b7}  ")),
+            o = opacity,
+            b = opacity * 0.6
+        ));
+    }
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+/// The filter that burns in the running timecode (top right) and the
+/// label of the scene playing (top left).
+pub fn timecode_filter(scenes: &[(f64, String)]) -> String {
+    let style = "fontsize=h/24:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=6:y=h/40";
+    let mut filters = vec![format!(
+        "drawtext=text='%{{pts\\:hms}}':{}:x=w-text_w-w/40",
+        style
+    )];
+    for (i, (start, label)) in scenes.iter().enumerate() {
+        let enable = match scenes.get(i + 1) {
+            Some((end, _)) => format!("between(t,{:.3},{:.3})", start, end),
+            None => format!("gte(t,{:.3})", start),
+        };
+        filters.push(format!(
+            "drawtext=text='{}':expansion=none:{}:x=w/40:enable='{}'",
+            drawtext_escape(label),
+            style,
+            enable
+        ));
+    }
+    filters.join(",")
+}
+
+/// The preset's burn-in filter for `stamp`, if it has anything to burn in.
+pub fn filter_for(
+    conn: &Connection,
+    preset_id: Option<&str>,
+    stamp: &Stamp,
+) -> AppResult<Option<String>> {
+    let Some(id) = preset_id else {
+        return Ok(None);
+    };
+    let preset = get_preset(conn, id)?;
+    let filters: Vec<String> = [
+        preset.watermark.and_then(|w| watermark_filter(&w, stamp)),
+        preset.timecode.then(|| timecode_filter(&stamp.scenes)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    Ok((!filters.is_empty()).then(|| filters.join(",")))
+}
+
+/// Write a copy of `input` (a path or URL) with `filter` burned in, and
+/// tagged with `metadata` (see `provenance`), to a temp file and return
+/// it. The caller removes it once sent.
+pub async fn burn_in(
+    binary: &Path,
+    input: &str,
+    filter: &str,
+    metadata: &[String],
+) -> AppResult<PathBuf> {
+    let dir = std::env::temp_dir()
+        .join("ai-directors-chair")
+        .join("review");
+    std::fs::create_dir_all(&dir)?;
+    let out = dir.join(format!("{}.mp4", uuid::Uuid::new_v4()));
+    let output = tokio::process::Command::new(binary)
+        .args(["-v", "error", "-y", "-i", input, "-vf", filter])
+        .args(["-c:v", "libx264", "-preset", "medium", "-crf", "20"])
+        .args(["-pix_fmt", "yuv420p", "-c:a", "copy"])
+        .args(metadata)
+        .args(["-movflags", "+faststart+use_metadata_tags"])
+        .arg(&out)
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not run ffmpeg ({}): {}",
+                binary.display(),
+                e
+            ))
+        })?;
+    if !output.status.success() || !out.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("ffmpeg produced no video");
+        std::fs::remove_file(&out).ok();
+        return Err(AppError::Io(format!("watermarking failed: {}", last)));
+    }
+    Ok(out)
+}
+
+pub fn list(conn: &Connection) -> AppResult<Vec<ExportPreset>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, watermark_json, timecode, created_at FROM export_presets ORDER BY name COLLATE NOCASE",
+    )?;
+    let presets = stmt
+        .query_map([], row_to_preset)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(presets)
+}
+
+#[tauri::command]
+pub fn list_export_presets(db: State<'_, Database>) -> AppResult<Vec<ExportPreset>> {
+    list(&db.conn())
+}
+
+pub fn create(conn: &Connection, input: &ExportPresetInput) -> AppResult<ExportPreset> {
+    validate(input)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let watermark = input
+        .watermark
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    conn.execute(
+        "INSERT INTO export_presets (id, name, watermark_json, timecode, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            input.name.trim(),
+            watermark,
+            input.timecode,
+            clock::now()
+        ],
+    )?;
+    get_preset(conn, &id)
+}
+
+#[tauri::command]
+pub fn create_export_preset(
+    db: State<'_, Database>,
+    input: ExportPresetInput,
+) -> AppResult<ExportPreset> {
+    create(&db.conn(), &input)
+}
+
+pub fn update(conn: &Connection, id: &str, input: &ExportPresetInput) -> AppResult<ExportPreset> {
+    validate(input)?;
+    let watermark = input
+        .watermark
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    let changed = conn.execute(
+        "UPDATE export_presets SET name = ?2, watermark_json = ?3, timecode = ?4 WHERE id = ?1",
+        params![id, input.name.trim(), watermark, input.timecode],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("export preset {}", id)));
+    }
+    get_preset(conn, id)
+}
+
+#[tauri::command]
+pub fn update_export_preset(
+    db: State<'_, Database>,
+    id: String,
+    input: ExportPresetInput,
+) -> AppResult<ExportPreset> {
+    update(&db.conn(), &id, &input)
+}
+
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let changed = conn.execute("DELETE FROM export_presets WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("export preset {}", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_export_preset(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
@@ -0,0 +1,324 @@
+//! Editing a scene's long text in the user's own editor. The field is
+//! written to a temp file and opened; every save there is written back to
+//! the scene as an ordinary edit, so it lands in the activity log and the
+//! version history like one made in the app. Watching stops when the
+//! frontend calls `close_external_editor`, or the app quits.
+//!
+//! The editor is the `tools.external_editor` command line, or the OS's
+//! default text editor.
+
+use crate::activity::{self, FieldChange};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::scenes;
+use crate::settings;
+use crate::validation::{Validator, MAX_TEXT_LEN};
+use crate::workflow;
+use notify::{RecursiveMode, Watcher as _};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+use ts_rs::TS;
+
+/// Settings key for the editor command, e.g. `code --wait` or a path to
+/// an executable; the file is passed as the last argument.
+const EDITOR_SETTING: &str = "tools.external_editor";
+/// Scene fields worth a full editor.
+const FIELDS: &[&str] = &["description", "prompt", "dialog"];
+/// Editors often write a file in several steps; wait for them to finish.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ExternalEdit {
+    pub scene_id: String,
+    pub field: String,
+    /// The temp file the editor has open.
+    pub path: String,
+}
+
+/// Emitted as `external-edit-saved` when a save changed the field, or
+/// `external-edit-failed` with `error` set.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalSave {
+    pub scene_id: String,
+    pub field: String,
+    #[ts(type = "number | null")]
+    pub version: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Open sessions by `scene_id:field`, each holding its watcher.
+#[derive(Default)]
+pub struct ExternalEdits(Mutex<HashMap<String, notify::RecommendedWatcher>>);
+
+fn session_key(scene_id: &str, field: &str) -> String {
+    format!("{}:{}", scene_id, field)
+}
+
+fn scene_field(scene: &scenes::Scene, field: &str) -> String {
+    match field {
+        "description" => scene.description.clone(),
+        "prompt" => scene.prompt.clone(),
+        _ => scene.dialog.clone(),
+    }
+}
+
+/// Text as saved by the editor, without the final newline most editors
+/// add unless the field had one.
+fn from_file(saved: &str, original: &str) -> String {
+    if original.ends_with('\n') {
+        return saved.to_string();
+    }
+    saved
+        .strip_suffix("\r\n")
+        .or_else(|| saved.strip_suffix('\n'))
+        .unwrap_or(saved)
+        .to_string()
+}
+
+/// Write `text` into the scene's field as an edit. Returns the new version,
+/// or None if the text is unchanged.
+pub fn apply(conn: &Connection, scene_id: &str, field: &str, text: &str) -> AppResult<Option<i64>> {
+    let mut v = Validator::new();
+    v.one_of("field", field, FIELDS)
+        .max_len(field, text, MAX_TEXT_LEN);
+    v.finish()?;
+    workflow::ensure_editable(conn, scene_id)?;
+    let before = scenes::get_scene(conn, scene_id)?;
+    let old = scene_field(&before, field);
+    if old == text {
+        return Ok(None);
+    }
+    // `field` is one of `FIELDS`, so safe to splice in.
+    conn.execute(
+        &format!("UPDATE scenes SET {} = ?2 WHERE id = ?1", field),
+        params![scene_id, text],
+    )?;
+    activity::record(
+        conn,
+        &before.project_id,
+        "scene",
+        scene_id,
+        "updated",
+        &format!("Edited scene {} in an external editor", before.scene_number),
+        &[FieldChange {
+            field: field.to_string(),
+            old,
+            new: text.to_string(),
+        }],
+    )?;
+    Ok(Some(scenes::get_scene(conn, scene_id)?.version))
+}
+
+/// The editor command line from settings, or the OS default.
+fn editor_command(conn: &Connection) -> AppResult<(PathBuf, Vec<String>)> {
+    let configured = settings::get(conn, EDITOR_SETTING)?.unwrap_or_default();
+    let configured = configured.trim();
+    if !configured.is_empty() {
+        // A path with spaces in it names the whole command.
+        if Path::new(configured).is_file() {
+            return Ok((PathBuf::from(configured), Vec::new()));
+        }
+        let mut parts = configured.split_whitespace().map(str::to_string);
+        let program = parts.next().unwrap_or_default();
+        return Ok((PathBuf::from(program), parts.collect()));
+    }
+    Ok(if cfg!(target_os = "macos") {
+        (PathBuf::from("open"), vec!["-t".to_string()])
+    } else if cfg!(windows) {
+        (PathBuf::from("notepad"), Vec::new())
+    } else {
+        (PathBuf::from("xdg-open"), Vec::new())
+    })
+}
+
+/// Write back every settled save of `file` until the session's watcher is
+/// dropped.
+async fn run(
+    app: AppHandle,
+    scene_id: String,
+    field: String,
+    file: PathBuf,
+    mut original: String,
+    mut events: mpsc::UnboundedReceiver<()>,
+) {
+    let mut synced = original.clone();
+    while events.recv().await.is_some() {
+        // Let the burst of events from one save pass.
+        while let Ok(Some(())) = tokio::time::timeout(DEBOUNCE, events.recv()).await {}
+        let Ok(saved) = tokio::fs::read_to_string(&file).await else {
+            continue;
+        };
+        let text = from_file(&saved, &original);
+        if text == synced {
+            continue;
+        }
+        let result = {
+            let db = app.state::<Database>();
+            let conn = db.conn();
+            apply(&conn, &scene_id, &field, &text)
+        };
+        let save = |version, error| ExternalSave {
+            scene_id: scene_id.clone(),
+            field: field.clone(),
+            version,
+            error,
+        };
+        let event = match result {
+            Ok(version) => {
+                synced = text;
+                original = synced.clone();
+                if version.is_none() {
+                    continue;
+                }
+                AppEvent::ExternalEditSaved(save(version, None))
+            }
+            Err(e) => AppEvent::ExternalEditFailed(save(None, Some(e.to_string()))),
+        };
+        event.emit(&app);
+    }
+}
+
+/// Where the temp files for open fields are written.
+fn temp_root() -> PathBuf {
+    std::env::temp_dir().join("ai-directors-chair")
+}
+
+/// Write a scene's description, prompt or dialog to a file of its own
+/// under `root` for the editor to open. Returns the file and the text.
+pub fn stage(
+    conn: &Connection,
+    scene_id: &str,
+    field: &str,
+    root: &Path,
+) -> AppResult<(PathBuf, String)> {
+    let mut v = Validator::new();
+    v.uuid("sceneId", scene_id).one_of("field", field, FIELDS);
+    v.finish()?;
+    workflow::ensure_editable(conn, scene_id)?;
+    let scene = scenes::get_scene(conn, scene_id)?;
+    let text = scene_field(&scene, field);
+    let dir = root.join(scene_id);
+    std::fs::create_dir_all(&dir)?;
+    let file = dir.join(format!(
+        "scene-{}{}-{}.txt",
+        scene.scene_number, scene.scene_suffix, field
+    ));
+    std::fs::write(&file, &text)?;
+    Ok((file, text))
+}
+
+/// Open a scene's description, prompt or dialog in the external editor
+/// and write its saves back. Opening a field that is already open writes
+/// the current text to the file again and brings the editor back.
+#[tauri::command]
+pub async fn open_in_external_editor(
+    app: AppHandle,
+    scene_id: String,
+    field: String,
+) -> AppResult<ExternalEdit> {
+    let (file, text, (program, args)) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let (file, text) = stage(&conn, &scene_id, &field, &temp_root())?;
+        (file, text, editor_command(&conn)?)
+    };
+    let dir = temp_root().join(&scene_id);
+
+    let key = session_key(&scene_id, &field);
+    let open = app.state::<ExternalEdits>();
+    let already_open = open
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains_key(&key);
+    if !already_open {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let watched = file.clone();
+        // Editors often save by replacing the file, so watch its folder.
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if event.is_ok_and(|e| e.paths.iter().any(|p| p == &watched)) {
+                    let _ = tx.send(());
+                }
+            })
+            .map_err(|e| AppError::Io(format!("could not watch {}: {}", file.display(), e)))?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| AppError::Io(format!("could not watch {}: {}", file.display(), e)))?;
+        open.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, watcher);
+        tauri::async_runtime::spawn(run(
+            app.clone(),
+            scene_id.clone(),
+            field.clone(),
+            file.clone(),
+            text,
+            rx,
+        ));
+    }
+
+    let mut child = tokio::process::Command::new(&program)
+        .args(&args)
+        .arg(&file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not start the editor ({}): {}; set it in Settings",
+                program.display(),
+                e
+            ))
+        })?;
+    tauri::async_runtime::spawn(async move {
+        let _ = child.wait().await;
+    });
+    Ok(ExternalEdit {
+        scene_id,
+        field,
+        path: file.to_string_lossy().into_owned(),
+    })
+}
+
+/// Stop writing back saves from the external editor and remove the temp
+/// file.
+#[tauri::command]
+pub fn close_external_editor(app: AppHandle, scene_id: String, field: String) -> AppResult<()> {
+    let watcher = app
+        .state::<ExternalEdits>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&session_key(&scene_id, &field));
+    if watcher.is_none() {
+        return Err(AppError::NotFound(format!(
+            "external edit of {} for scene {}",
+            field, scene_id
+        )));
+    }
+    let dir = temp_root().join(&scene_id);
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        let suffix = format!("-{}.txt", field);
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().ends_with(&suffix) {
+                std::fs::remove_file(entry.path()).ok();
+            }
+        }
+    }
+    std::fs::remove_dir(&dir).ok();
+    Ok(())
+}
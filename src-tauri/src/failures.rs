@@ -0,0 +1,204 @@
+//! What happens to a job the provider turned down, by the kind
+//! `providers::classify_failure` gives it. Transient failures go back in
+//! the queue with a growing delay; quota failures go back too but pause
+//! the queue until the user resumes it; content-policy failures flag the
+//! scene's prompt for editing; anything else just fails.
+
+use crate::activity;
+use crate::clock;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::jobs::{self, kind, status};
+use crate::providers::{self, failure};
+use crate::settings;
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Transient failures are retried this many times before the job fails.
+const MAX_ATTEMPTS: i64 = 3;
+/// First retry delay; doubled for each later attempt.
+const RETRY_DELAY_SECS: i64 = 30;
+/// Why the queue is paused; empty or missing when it isn't.
+pub const QUEUE_PAUSED_SETTING: &str = "queue_paused";
+
+/// What `handle` did with a failed job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Back in the queue until `retry_at`.
+    Retrying,
+    /// Back in the queue, which is now paused.
+    Paused,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureCount {
+    pub kind: String,
+    #[ts(type = "number")]
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct FailureSummary {
+    /// Jobs that have hit each kind of failure, including ones retried
+    /// since.
+    pub counts: Vec<FailureCount>,
+    /// Jobs waiting to be retried.
+    #[ts(type = "number")]
+    pub retrying: i64,
+    /// Why the queue is paused, if it is.
+    pub queue_paused: Option<String>,
+    /// Scenes whose latest take was refused on content policy.
+    pub needs_prompt_edit: Vec<String>,
+}
+
+pub fn queue_paused(conn: &Connection) -> AppResult<Option<String>> {
+    Ok(settings::get(conn, QUEUE_PAUSED_SETTING)?.filter(|r| !r.is_empty()))
+}
+
+/// Apply the retry policy to a job that just failed with `error`.
+/// `http_status` is that of the provider's last response, if any.
+pub fn handle(
+    conn: &Connection,
+    job_id: &str,
+    error: &str,
+    http_status: Option<u16>,
+) -> AppResult<Outcome> {
+    let failure_kind = providers::classify_failure(http_status, error);
+    let (attempts, scene_id): (i64, String) = conn.query_row(
+        "SELECT attempts, scene_id FROM video_jobs WHERE id = ?1",
+        [job_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+
+    if failure_kind == failure::TRANSIENT && attempts < MAX_ATTEMPTS {
+        let delay = RETRY_DELAY_SECS << attempts;
+        conn.execute(
+            "UPDATE video_jobs SET status = ?2, job_id = '', error = ?3, failure_kind = ?4,
+                attempts = attempts + 1, retry_at = ?5
+             WHERE id = ?1",
+            params![
+                job_id,
+                status::OFFLINE,
+                error,
+                failure_kind,
+                clock::format(Utc::now() + Duration::seconds(delay))
+            ],
+        )?;
+        activity::record_for_scene(
+            conn,
+            &scene_id,
+            "take",
+            job_id,
+            "retrying",
+            &format!("Take failed ({}); retrying in {}s", error, delay),
+            &[],
+        )?;
+        return Ok(Outcome::Retrying);
+    }
+
+    if failure_kind == failure::QUOTA {
+        conn.execute(
+            "UPDATE video_jobs SET status = ?2, job_id = '', error = ?3, failure_kind = ?4,
+                retry_at = NULL
+             WHERE id = ?1",
+            params![job_id, status::OFFLINE, error, failure_kind],
+        )?;
+        settings::set(conn, QUEUE_PAUSED_SETTING, error)?;
+        return Ok(Outcome::Paused);
+    }
+
+    conn.execute(
+        "UPDATE video_jobs SET status = ?2, error = ?3, failure_kind = ?4, completed_at = ?5
+         WHERE id = ?1",
+        params![job_id, status::FAILED, error, failure_kind, clock::now()],
+    )?;
+    if failure_kind == failure::CONTENT_POLICY {
+        activity::record_for_scene(
+            conn,
+            &scene_id,
+            "scene",
+            &scene_id,
+            "flagged",
+            "Prompt refused on content policy; edit it before generating again",
+            &[],
+        )?;
+    }
+    Ok(Outcome::Failed)
+}
+
+/// Failure counts for a project's jobs, whether the queue is paused, and
+/// which scenes need their prompt edited.
+pub fn summary(conn: &Connection, project_id: &str) -> AppResult<FailureSummary> {
+    let mut stmt = conn.prepare(
+        "SELECT j.failure_kind, COUNT(*) FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+         WHERE s.project_id = ?1 AND j.failure_kind <> ''
+         GROUP BY j.failure_kind ORDER BY COUNT(*) DESC, j.failure_kind",
+    )?;
+    let counts = stmt
+        .query_map([project_id], |r| {
+            Ok(FailureCount {
+                kind: r.get(0)?,
+                count: r.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let retrying = conn.query_row(
+        "SELECT COUNT(*) FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+         WHERE s.project_id = ?1 AND j.status = ?2 AND j.retry_at IS NOT NULL",
+        params![project_id, status::OFFLINE],
+        |r| r.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT s.id FROM scenes s
+         WHERE s.project_id = ?1 AND (
+            SELECT j.status || ':' || j.failure_kind FROM video_jobs j
+            WHERE j.scene_id = s.id AND j.kind = ?2
+            ORDER BY j.started_at DESC, j.rowid DESC LIMIT 1) = ?3 || ':' || ?4
+         ORDER BY s.order_key, s.sort_order, s.scene_number",
+    )?;
+    let needs_prompt_edit = stmt
+        .query_map(
+            params![
+                project_id,
+                kind::VIDEO,
+                status::FAILED,
+                failure::CONTENT_POLICY
+            ],
+            |r| r.get(0),
+        )?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(FailureSummary {
+        counts,
+        retrying,
+        queue_paused: queue_paused(conn)?,
+        needs_prompt_edit,
+    })
+}
+
+#[tauri::command]
+pub fn get_failure_summary(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<FailureSummary> {
+    summary(&db.conn(), &project_id)
+}
+
+/// Unpause the queue after a quota failure.
+pub fn resume(conn: &Connection) -> AppResult<()> {
+    settings::set(conn, QUEUE_PAUSED_SETTING, "")
+}
+
+/// Unpause the queue and submit what's waiting.
+#[tauri::command]
+pub async fn resume_queue(app: AppHandle) -> AppResult<()> {
+    resume(&app.state::<Database>().conn())?;
+    jobs::flush_deferred(&app).await;
+    Ok(())
+}
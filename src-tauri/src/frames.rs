@@ -0,0 +1,188 @@
+//! Still frames pulled out of finished takes with ffmpeg, used as start
+//! images for the takes that continue from them.
+
+use crate::activity::{self, FieldChange};
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::providers;
+use crate::scene_links;
+use crate::scenes::{self, Scene};
+use crate::settings;
+use crate::workflow;
+use base64::Engine as _;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Settings key for an explicit ffmpeg binary; otherwise we look for the
+/// bundled sidecar next to the executable, then `PATH`.
+const FFMPEG_PATH_SETTING: &str = "tools.ffmpeg_path";
+
+pub fn ffmpeg_binary(conn: &Connection) -> AppResult<PathBuf> {
+    if let Some(path) = settings::get(conn, FFMPEG_PATH_SETTING)?.filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    let exe_name = if cfg!(windows) {
+        "ffmpeg.exe"
+    } else {
+        "ffmpeg"
+    };
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(PathBuf::from))
+    {
+        let sidecar = dir.join(exe_name);
+        if sidecar.exists() {
+            return Ok(sidecar);
+        }
+    }
+    Ok(PathBuf::from(exe_name))
+}
+
+/// Save the last frame of a video (local path or URL) as a JPEG in the
+/// frames media dir and return its path.
+pub async fn last_frame(binary: &Path, video: &str) -> AppResult<PathBuf> {
+    if video.is_empty() {
+        return Err(AppError::Invalid("take has no video".into()));
+    }
+    let out = db::media_dir("frames")?.join(format!("{}.jpg", uuid::Uuid::new_v4()));
+    // Seek to the final second and keep overwriting one image, so the file
+    // ends up holding the very last decoded frame.
+    let output = tokio::process::Command::new(binary)
+        .args(["-v", "error", "-y", "-sseof", "-1", "-i", video])
+        .args(["-update", "1", "-q:v", "2"])
+        .arg(&out)
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not run ffmpeg ({}): {}",
+                binary.display(),
+                e
+            ))
+        })?;
+    if !output.status.success() || !out.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("ffmpeg produced no frame");
+        return Err(AppError::Io(format!("frame extraction failed: {}", last)));
+    }
+    Ok(out)
+}
+
+/// A JPEG on disk as a `data:` URL, which providers accept wherever they
+/// take an image URL.
+pub fn data_url(path: &Path) -> AppResult<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(format!(
+        "data:image/jpeg;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// The scene after `scene` on the board, if any.
+fn next_scene(conn: &Connection, scene: &Scene) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM scenes WHERE project_id = ?1 AND id != ?2 AND order_key > ?3
+             ORDER BY order_key, sort_order, scene_number LIMIT 1",
+            params![scene.project_id, scene.id, scene.order_key],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// The scene to chain from `scene_id`, the scene that will start from its
+/// end (`next_scene_id`, or the next one on the board) and the take to
+/// take the frame from.
+pub fn chain(
+    conn: &Connection,
+    scene_id: &str,
+    next_scene_id: Option<&str>,
+) -> AppResult<(Scene, Scene, String)> {
+    let scene = scenes::get_scene(conn, scene_id)?;
+    let next_id = match next_scene_id {
+        Some(id) => id.to_string(),
+        None => next_scene(conn, &scene)?.ok_or_else(|| {
+            AppError::Invalid(format!("scene {} is the last scene", scene.scene_number))
+        })?,
+    };
+    let next = scenes::get_scene(conn, &next_id)?;
+    if next.project_id != scene.project_id {
+        return Err(AppError::Invalid(
+            "scenes belong to different projects".into(),
+        ));
+    }
+    workflow::ensure_editable(conn, &next.id)?;
+    let video = scene_links::finished_take(conn, &scene.id)?.ok_or_else(|| {
+        AppError::Invalid(format!(
+            "scene {} has no finished take yet",
+            scene.scene_number
+        ))
+    })?;
+    Ok((scene, next, video))
+}
+
+/// Make `frame`, taken from the end of `scene`, the start image of `next`.
+/// Returns the updated next scene.
+pub fn start_from(conn: &Connection, scene: &Scene, next: &Scene, frame: &str) -> AppResult<Scene> {
+    conn.execute(
+        "UPDATE scenes SET conditioning_image = ?2 WHERE id = ?1",
+        params![next.id, frame],
+    )?;
+    activity::record(
+        conn,
+        &next.project_id,
+        "scene",
+        &next.id,
+        "updated",
+        &format!(
+            "Scene {} starts from the end of scene {}",
+            next.scene_number, scene.scene_number
+        ),
+        &[FieldChange {
+            field: "conditioning_image".into(),
+            old: next.conditioning_image.clone(),
+            new: frame.to_string(),
+        }],
+    )?;
+    scenes::get_scene(conn, &next.id)
+}
+
+/// Extract the last frame of a scene's finished take and make it the start
+/// image of the next scene on the board (or `next_scene_id`). When
+/// `provider` is given it must be able to start from an image. Returns the
+/// updated next scene.
+#[tauri::command]
+pub async fn chain_end_frame(
+    app: AppHandle,
+    scene_id: String,
+    next_scene_id: Option<String>,
+    provider: Option<String>,
+) -> AppResult<Scene> {
+    if let Some(provider) = &provider {
+        if !providers::model(provider)?.supports_image() {
+            return Err(AppError::Invalid(format!(
+                "{} can't start from an image",
+                provider
+            )));
+        }
+    }
+    let (scene, next, video, binary) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let (scene, next, video) = chain(&conn, &scene_id, next_scene_id.as_deref())?;
+        (scene, next, video, ffmpeg_binary(&conn)?)
+    };
+
+    let frame = last_frame(&binary, &video).await?;
+    start_from(
+        &app.state::<Database>().conn(),
+        &scene,
+        &next,
+        &frame.to_string_lossy(),
+    )
+}
@@ -0,0 +1,263 @@
+//! Generation settings: project defaults, which individual scenes can
+//! override field by field (`generation_json` on both tables). Settings are
+//! checked against the chosen model's capabilities in `providers::MODELS`,
+//! so a scene can't ask a model for a resolution or length it can't do.
+
+use crate::activity::{self, FieldChange};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::providers::{self, ModelSpec};
+use crate::scenes::{self, Scene};
+use crate::validation::{Validate, Validator};
+use crate::workflow;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Generation parameters; a field left out of a scene's overrides falls
+/// through to the project default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct GenerationSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// A `providers::MODELS` key, e.g. `"veo2"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Clip length in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+    /// 0–1; only for models with a motion control.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion_strength: Option<f64>,
+}
+
+impl GenerationSettings {
+    /// `self` with anything it leaves out taken from `fallback`.
+    pub fn or(&self, fallback: &GenerationSettings) -> GenerationSettings {
+        GenerationSettings {
+            provider: self.provider.clone().or_else(|| fallback.provider.clone()),
+            model: self.model.clone().or_else(|| fallback.model.clone()),
+            duration: self.duration.or(fallback.duration),
+            resolution: self
+                .resolution
+                .clone()
+                .or_else(|| fallback.resolution.clone()),
+            motion_strength: self.motion_strength.or(fallback.motion_strength),
+        }
+    }
+
+    pub fn from_json(json: &str) -> GenerationSettings {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    /// Check the settings against what `spec` can do.
+    pub fn check_model(&self, spec: &ModelSpec) -> AppResult<()> {
+        let mut v = Validator::new();
+        self.validate_for(&mut v, Some(spec));
+        v.finish()
+    }
+
+    fn validate_for(&self, v: &mut Validator, spec: Option<&ModelSpec>) {
+        if let Some(provider) = &self.provider {
+            v.one_of("provider", provider, providers::PROVIDERS);
+        }
+        if let Some(motion) = self.motion_strength {
+            v.range("motionStrength", motion, 0.0, 1.0);
+        }
+        let Some(spec) = spec else {
+            if let Some(duration) = self.duration {
+                v.range("duration", duration, 1, providers::max_duration());
+            }
+            if self.resolution.is_some() || self.motion_strength.is_some() {
+                v.error(
+                    "model",
+                    "choose a model before setting resolution or motion strength",
+                );
+            }
+            return;
+        };
+        if let Some(duration) = self.duration {
+            v.range("duration", duration, 1, spec.max_duration);
+        }
+        if let Some(resolution) = &self.resolution {
+            if spec.resolutions.is_empty() {
+                v.error("resolution", format!("{} has a fixed resolution", spec.key));
+            } else {
+                v.one_of("resolution", resolution, spec.resolutions);
+            }
+        }
+        if self.motion_strength.is_some() && spec.motion_param.is_none() {
+            v.error(
+                "motionStrength",
+                format!("{} has no motion strength control", spec.key),
+            );
+        }
+    }
+}
+
+impl Validate for GenerationSettings {
+    fn validate(&self, v: &mut Validator) {
+        let model = self.model.as_deref();
+        let spec = model.and_then(|m| providers::model(m).ok());
+        if let (Some(model), None) = (model, spec) {
+            v.error("model", format!("unknown model '{}'", model));
+            return;
+        }
+        self.validate_for(v, spec);
+    }
+}
+
+pub fn project_defaults(conn: &Connection, project_id: &str) -> AppResult<GenerationSettings> {
+    let json: String = conn.query_row(
+        "SELECT generation_json FROM projects WHERE id = ?1",
+        [project_id],
+        |r| r.get(0),
+    )?;
+    Ok(GenerationSettings::from_json(&json))
+}
+
+/// What a scene will be generated with: its overrides over the project
+/// defaults.
+pub fn effective(conn: &Connection, scene: &Scene) -> AppResult<GenerationSettings> {
+    Ok(scene
+        .generation
+        .or(&project_defaults(conn, &scene.project_id)?))
+}
+
+fn describe(settings: &GenerationSettings) -> String {
+    serde_json::to_string(settings).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_project_generation_defaults(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<GenerationSettings> {
+    let conn = db.conn();
+    projects::get_project(&conn, &project_id)?;
+    project_defaults(&conn, &project_id)
+}
+
+/// Replace a project's generation defaults.
+pub fn set_project_defaults(
+    conn: &Connection,
+    project_id: &str,
+    settings: &GenerationSettings,
+) -> AppResult<GenerationSettings> {
+    settings.check()?;
+    projects::ensure_unlocked(conn, project_id)?;
+    let before = project_defaults(conn, project_id)?;
+    conn.execute(
+        "UPDATE projects SET generation_json = ?2 WHERE id = ?1",
+        params![project_id, serde_json::to_string(settings)?],
+    )?;
+    if &before != settings {
+        activity::record(
+            conn,
+            project_id,
+            "project",
+            project_id,
+            "updated",
+            "Changed generation defaults",
+            &[FieldChange {
+                field: "generation".into(),
+                old: describe(&before),
+                new: describe(settings),
+            }],
+        )?;
+    }
+    Ok(settings.clone())
+}
+
+#[tauri::command]
+pub fn set_project_generation_defaults(
+    db: State<'_, Database>,
+    project_id: String,
+    settings: GenerationSettings,
+) -> AppResult<GenerationSettings> {
+    set_project_defaults(&db.conn(), &project_id, &settings)
+}
+
+/// Replace a scene's overrides; an empty object clears them. Checked
+/// together with the project defaults they fall back to.
+pub fn set_scene_overrides(
+    conn: &Connection,
+    scene_id: &str,
+    overrides: &GenerationSettings,
+) -> AppResult<Scene> {
+    workflow::ensure_editable(conn, scene_id)?;
+    let scene = scenes::get_scene(conn, scene_id)?;
+    overrides
+        .or(&project_defaults(conn, &scene.project_id)?)
+        .check()?;
+    if &scene.generation == overrides {
+        return Ok(scene);
+    }
+    conn.execute(
+        "UPDATE scenes SET generation_json = ?2 WHERE id = ?1",
+        params![scene_id, serde_json::to_string(overrides)?],
+    )?;
+    activity::record(
+        conn,
+        &scene.project_id,
+        "scene",
+        scene_id,
+        "updated",
+        &format!(
+            "Changed generation settings for scene {}",
+            scene.scene_number
+        ),
+        &[FieldChange {
+            field: "generation".into(),
+            old: describe(&scene.generation),
+            new: describe(overrides),
+        }],
+    )?;
+    scenes::get_scene(conn, scene_id)
+}
+
+#[tauri::command]
+pub fn set_scene_generation_overrides(
+    db: State<'_, Database>,
+    scene_id: String,
+    overrides: GenerationSettings,
+) -> AppResult<Scene> {
+    set_scene_overrides(&db.conn(), &scene_id, &overrides)
+}
+
+/// The settings a scene will actually be generated with.
+#[tauri::command]
+pub fn get_effective_generation_settings(
+    db: State<'_, Database>,
+    scene_id: String,
+) -> AppResult<GenerationSettings> {
+    let conn = db.conn();
+    let scene = scenes::get_scene(&conn, &scene_id)?;
+    effective(&conn, &scene)
+}
+
+/// The model for a scene: its own override, else the one the caller asked
+/// for, else the project default.
+pub fn resolve_model(
+    overrides: &GenerationSettings,
+    requested: Option<&str>,
+    defaults: &GenerationSettings,
+) -> AppResult<&'static ModelSpec> {
+    let key = overrides
+        .model
+        .as_deref()
+        .or(requested)
+        .or(defaults.model.as_deref());
+    match key {
+        Some(key) => providers::model(key),
+        None => Err(AppError::Invalid(
+            "no model chosen for this scene or project".into(),
+        )),
+    }
+}
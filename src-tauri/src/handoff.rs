@@ -0,0 +1,186 @@
+//! Encrypted project handoff: a whole `.aidc` project file (see
+//! `project_file`), media included, sealed with a passphrase so it can go
+//! over email, chat or a shared drive and only someone told the passphrase
+//! can open it.
+//!
+//! Bundles are age files encrypted to a passphrase (scrypt), so the `age`
+//! tool opens them too. age seals the payload in numbered 64 KiB
+//! ChaCha20-Poly1305 chunks with the last one marked, so chunks can't be
+//! reordered, dropped or cut off without the bundle failing to open. The
+//! zip is streamed straight into the encryptor and read back straight out
+//! of the decryptor: the project never touches the disk unencrypted,
+//! except for its media as it is unpacked into the media folder.
+
+use crate::db::Database;
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::project_file;
+use crate::projects::{self, ProjectSummary};
+use age::secrecy::SecretString;
+use std::cell::Cell;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+
+pub const EXTENSION: &str = "aidcx";
+
+fn wrong_passphrase() -> AppError {
+    AppError::Invalid("wrong passphrase or damaged project bundle".into())
+}
+
+/// The decrypted bundle, noting when reading it failed: a chunk that
+/// doesn't authenticate reaches the zip reader as a plain I/O error.
+struct Tracked<R> {
+    inner: R,
+    failed: Rc<Cell<bool>>,
+}
+
+impl<R> Tracked<R> {
+    fn note<T>(&self, result: std::io::Result<T>) -> std::io::Result<T> {
+        if result.is_err() {
+            self.failed.set(true);
+        }
+        result
+    }
+}
+
+impl<R: Read> Read for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let result = self.inner.read(buf);
+        self.note(result)
+    }
+}
+
+impl<R: Seek> Seek for Tracked<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let result = self.inner.seek(pos);
+        self.note(result)
+    }
+}
+
+fn seal(db: &Database, project_id: &str, passphrase: &str, out: &Path) -> AppResult<usize> {
+    let file = BufWriter::new(std::fs::File::create(out)?);
+    let sealed = age::Encryptor::with_user_passphrase(SecretString::from(passphrase.to_owned()))
+        .wrap_output(file)?;
+    let (sealed, files) = project_file::write_to(&db.conn(), project_id, sealed)?;
+    sealed.finish()?.flush()?;
+    Ok(files)
+}
+
+/// Write the project, media included, to `out` sealed with `passphrase`.
+pub fn export(db: &Database, project_id: &str, passphrase: &str, out: &Path) -> AppResult<()> {
+    encryption::check_passphrase(passphrase)?;
+    let partial = project_file::appended(out, "partial");
+    let files = match seal(db, project_id, passphrase, &partial) {
+        Ok(files) => files,
+        Err(e) => {
+            std::fs::remove_file(&partial).ok();
+            return Err(e);
+        }
+    };
+    std::fs::rename(&partial, out)?;
+    tracing::info!(project_id, path = %out.display(), files, "exported encrypted bundle");
+    Ok(())
+}
+
+/// Open the sealed bundle at `path` and load its project, creating or
+/// updating it. The project is only saved once everything it needs from
+/// the bundle has decrypted.
+pub fn import(db: &Database, path: &Path, passphrase: &str) -> AppResult<ProjectSummary> {
+    let not_a_bundle = || {
+        AppError::Invalid(format!(
+            "{} is not an encrypted project bundle",
+            path.display()
+        ))
+    };
+    let decryptor = age::Decryptor::new_buffered(BufReader::new(std::fs::File::open(path)?))
+        .map_err(|_| not_a_bundle())?;
+    if !decryptor.is_scrypt() {
+        return Err(not_a_bundle());
+    }
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_owned()));
+    let plain = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|e| match e {
+            age::DecryptError::ExcessiveWork { .. } => AppError::Invalid(
+                "this bundle's passphrase would take too long to check on this device".into(),
+            ),
+            _ => wrong_passphrase(),
+        })?;
+    let failed = Rc::new(Cell::new(false));
+    let plain = Tracked {
+        inner: plain,
+        failed: failed.clone(),
+    };
+    let conn = db.conn();
+    let project = project_file::load_from(&conn, plain, path)
+        .and_then(|project_id| projects::get_project(&conn, &project_id))
+        .map_err(|e| if failed.get() { wrong_passphrase() } else { e })?;
+    tracing::info!(project_id = %project.id, path = %path.display(), "imported encrypted bundle");
+    Ok(project)
+}
+
+/// Seal the project with `passphrase` into a file picked in a save
+/// dialog. Returns where it was written, or None if the dialog was
+/// cancelled. The passphrase has to reach the recipient some other way.
+#[tauri::command]
+pub async fn export_encrypted_bundle(
+    app: AppHandle,
+    db: State<'_, Database>,
+    project_id: String,
+    passphrase: String,
+) -> AppResult<Option<String>> {
+    encryption::check_passphrase(&passphrase)?;
+    let project = projects::get_project(&db.conn(), &project_id)?;
+    let chosen = app
+        .dialog()
+        .file()
+        .set_title("Export Encrypted Project")
+        .add_filter("Encrypted AI Director's Chair project", &[EXTENSION])
+        .set_file_name(project_file::suggested_name(&project.name, EXTENSION))
+        .blocking_save_file();
+    let Some(chosen) = chosen else {
+        return Ok(None);
+    };
+    let mut path = chosen
+        .into_path()
+        .map_err(|e| AppError::Invalid(format!("cannot save there: {}", e)))?;
+    if !path.extension().is_some_and(|e| e == EXTENSION) {
+        path = project_file::appended(&path, EXTENSION);
+    }
+    export(&db, &project_id, &passphrase, &path)?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+/// Open an encrypted bundle with the passphrase the user was prompted
+/// for: `path` if given (a dropped file), else one picked in an open
+/// dialog. Returns None if the dialog was cancelled; a wrong passphrase is
+/// an `Invalid` error.
+#[tauri::command]
+pub async fn import_encrypted_bundle(
+    app: AppHandle,
+    db: State<'_, Database>,
+    passphrase: String,
+    path: Option<String>,
+) -> AppResult<Option<ProjectSummary>> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let chosen = app
+                .dialog()
+                .file()
+                .set_title("Import Encrypted Project")
+                .add_filter("Encrypted AI Director's Chair project", &[EXTENSION])
+                .blocking_pick_file();
+            let Some(chosen) = chosen else {
+                return Ok(None);
+            };
+            chosen
+                .into_path()
+                .map_err(|e| AppError::Invalid(format!("cannot open that file: {}", e)))?
+        }
+    };
+    import(&db, &path, &passphrase).map(Some)
+}
@@ -0,0 +1,302 @@
+//! Translations and locale-aware formatting. Catalogs are flat JSON maps
+//! bundled from `locales/`, one per locale; a regional catalog such as
+//! `en-GB` only holds what differs from its language, and every catalog
+//! falls back to English for keys it lacks. Besides UI strings, each
+//! catalog carries its `format.*` conventions (date and time patterns,
+//! separators, currency and percent layout), which the exporters use
+//! through `Locale`.
+//!
+//! The locale is the `ui.locale` setting, or the OS locale when that is
+//! empty.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::settings;
+use chrono::{NaiveDate, NaiveTime};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use tauri::State;
+use ts_rs::TS;
+
+pub const LOCALE_SETTING: &str = "ui.locale";
+const FALLBACK: &str = "en";
+
+/// Bundled catalogs. Keep `FALLBACK` first.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("en-GB", include_str!("../locales/en-GB.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("fr", include_str!("../locales/fr.json")),
+    ("es", include_str!("../locales/es.json")),
+];
+
+type Catalog = HashMap<String, String>;
+
+/// Every catalog with its fallbacks merged in, parsed on first use.
+fn catalogs() -> &'static HashMap<&'static str, Catalog> {
+    static LOADED: OnceLock<HashMap<&'static str, Catalog>> = OnceLock::new();
+    LOADED.get_or_init(|| {
+        let parsed: HashMap<&str, Catalog> = CATALOGS
+            .iter()
+            .map(|&(code, json)| {
+                let catalog = serde_json::from_str(json).unwrap_or_else(|e| {
+                    tracing::error!(locale = code, error = %e, "bundled catalog is invalid");
+                    Catalog::new()
+                });
+                (code, catalog)
+            })
+            .collect();
+        CATALOGS
+            .iter()
+            .map(|&(code, _)| {
+                let mut merged = parsed[FALLBACK].clone();
+                if let Some((language, _)) = code.split_once('-') {
+                    merged.extend(parsed.get(language).cloned().unwrap_or_default());
+                }
+                merged.extend(parsed[code].clone());
+                (code, merged)
+            })
+            .collect()
+    })
+}
+
+/// The bundled locale best matching a tag such as `de_AT.UTF-8`, `en-gb`
+/// or `fr`: the exact region if there is a catalog for it, else the
+/// language.
+pub fn resolve(tag: &str) -> Option<&'static str> {
+    let tag = tag
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default();
+    let find = |wanted: &str| {
+        CATALOGS
+            .iter()
+            .map(|&(code, _)| code)
+            .find(|code| code.eq_ignore_ascii_case(wanted))
+    };
+    find(&tag).or_else(|| find(language))
+}
+
+/// The OS's preferred locale tag, looked up once per run.
+pub fn os_locale() -> Option<&'static str> {
+    static DETECTED: OnceLock<Option<String>> = OnceLock::new();
+    DETECTED.get_or_init(detect_os_locale).as_deref()
+}
+
+fn detect_os_locale() -> Option<String> {
+    for var in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+        if let Ok(value) = std::env::var(var) {
+            if !value.is_empty() && value != "C" && value != "POSIX" {
+                return Some(value);
+            }
+        }
+    }
+    let output = if cfg!(target_os = "macos") {
+        std::process::Command::new("defaults")
+            .args(["read", "-g", "AppleLocale"])
+            .output()
+    } else if cfg!(windows) {
+        std::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", "(Get-Culture).Name"])
+            .output()
+    } else {
+        return None;
+    };
+    output
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// A locale's catalog and formatting conventions.
+#[derive(Clone, Copy)]
+pub struct Locale {
+    code: &'static str,
+    catalog: &'static Catalog,
+}
+
+impl Locale {
+    pub fn get(code: &str) -> Option<Self> {
+        let (&code, catalog) = catalogs().get_key_value(resolve(code)?)?;
+        Some(Locale { code, catalog })
+    }
+
+    pub fn fallback() -> Self {
+        Self::get(FALLBACK).expect("the fallback catalog is bundled")
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// The translation of `key`, or the key itself if no catalog has it.
+    pub fn t<'a>(&self, key: &'a str) -> &'a str {
+        self.catalog.get(key).map_or(key, String::as_str)
+    }
+
+    /// `t` with `{name}` placeholders filled in.
+    pub fn tf(&self, key: &str, args: &[(&str, &str)]) -> String {
+        fill(self.t(key), args)
+    }
+
+    /// A stored `YYYY-MM-DD` date or timestamp as a local date; anything
+    /// else is returned as it is.
+    pub fn date(&self, value: &str) -> String {
+        match NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d") {
+            Ok(date) => date.format(self.t("format.date")).to_string(),
+            Err(_) => value.to_string(),
+        }
+    }
+
+    /// A stored `HH:MM` time of day; anything else is returned as it is.
+    pub fn time(&self, value: &str) -> String {
+        match NaiveTime::parse_from_str(value, "%H:%M") {
+            Ok(time) => time.format(self.t("format.time")).to_string(),
+            Err(_) => value.to_string(),
+        }
+    }
+
+    pub fn integer(&self, n: i64) -> String {
+        let digits = n.unsigned_abs().to_string();
+        let group = self.t("format.group");
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                out.push_str(group);
+            }
+            out.push(c);
+        }
+        if n < 0 {
+            out.insert(0, '-');
+        }
+        out
+    }
+
+    pub fn decimal(&self, n: f64, places: usize) -> String {
+        let fixed = format!("{:.*}", places, n.abs());
+        let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let mut out = self.integer(whole.parse().unwrap_or(0));
+        if n < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.insert(0, '-');
+        }
+        if !fraction.is_empty() {
+            out.push_str(self.t("format.decimal"));
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    /// A US dollar amount, the currency providers bill in.
+    pub fn money(&self, usd: f64) -> String {
+        fill(self.t("format.money"), &[("amount", &self.decimal(usd, 2))])
+    }
+
+    /// A 0–1 ratio as a whole percentage.
+    pub fn percent(&self, ratio: f64) -> String {
+        fill(
+            self.t("format.percent"),
+            &[("value", &self.decimal(ratio * 100.0, 0))],
+        )
+    }
+
+    pub fn seconds(&self, seconds: i64) -> String {
+        fill(
+            self.t("format.seconds"),
+            &[("value", &self.integer(seconds))],
+        )
+    }
+}
+
+fn fill(template: &str, args: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{}}}", name), value);
+    }
+    out
+}
+
+/// The locale exports and the UI use: the setting, else the OS locale,
+/// else English.
+pub fn current(conn: &Connection) -> AppResult<Locale> {
+    let chosen = settings::get(conn, LOCALE_SETTING)?.and_then(|tag| Locale::get(&tag));
+    Ok(chosen
+        .or_else(|| os_locale().and_then(Locale::get))
+        .unwrap_or_else(Locale::fallback))
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Translations {
+    pub locale: String,
+    pub catalog: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleOption {
+    pub code: String,
+    /// The locale's name in its own language.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LocaleList {
+    pub current: String,
+    /// The bundled locale matching the OS, if any.
+    pub detected: Option<String>,
+    pub available: Vec<LocaleOption>,
+}
+
+/// The full catalog for `locale` (the current locale if omitted), with
+/// English filling any gaps.
+pub fn translations(conn: &Connection, locale: Option<&str>) -> AppResult<Translations> {
+    let locale = match locale {
+        Some(tag) => Locale::get(tag)
+            .ok_or_else(|| AppError::NotFound(format!("translations for {}", tag)))?,
+        None => current(conn)?,
+    };
+    Ok(Translations {
+        locale: locale.code().to_string(),
+        catalog: locale
+            .catalog
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+    })
+}
+
+#[tauri::command]
+pub fn get_translations(
+    db: State<'_, Database>,
+    locale: Option<String>,
+) -> AppResult<Translations> {
+    translations(&db.conn(), locale.as_deref())
+}
+
+pub fn locales(conn: &Connection) -> AppResult<LocaleList> {
+    Ok(LocaleList {
+        current: current(conn)?.code().to_string(),
+        detected: os_locale().and_then(resolve).map(str::to_string),
+        available: CATALOGS
+            .iter()
+            .filter_map(|&(code, _)| Locale::get(code))
+            .map(|l| LocaleOption {
+                code: l.code().to_string(),
+                name: l.t("locale.name").to_string(),
+            })
+            .collect(),
+    })
+}
+
+#[tauri::command]
+pub fn list_locales(db: State<'_, Database>) -> AppResult<LocaleList> {
+    locales(&db.conn())
+}
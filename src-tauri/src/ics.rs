@@ -0,0 +1,342 @@
+//! Minimal iCalendar (RFC 5545) writer for exporting schedules and
+//! deadlines, and a reader for importing them back. Times are written as
+//! floating local time so events land at the wall-clock time the user
+//! typed, wherever the calendar is. Read back, times in a named zone keep
+//! their wall-clock time too, and UTC times become local time.
+
+use chrono::{Local, NaiveDateTime};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum When {
+    /// `YYYY-MM-DD`.
+    AllDay(String),
+    /// `YYYY-MM-DD` plus `HH:MM` start and optional end. An end earlier
+    /// than the start runs past midnight; no end means `default_minutes`.
+    Timed {
+        date: String,
+        start: String,
+        end: Option<String>,
+        default_minutes: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub uid: String,
+    pub when: When,
+    pub summary: String,
+    pub description: String,
+    pub location: String,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line at 75 octets without splitting a UTF-8 sequence.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn compact_date(date: &str) -> String {
+    date.replace('-', "")
+}
+
+fn minutes(time: &str) -> Option<u32> {
+    let (h, m) = time.split_once(':')?;
+    Some(h.parse::<u32>().ok()? * 60 + m.parse::<u32>().ok()?)
+}
+
+/// Current UTC time as an iCalendar timestamp, e.g. `20260101T120000Z`.
+pub fn utc_stamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Render a VCALENDAR containing `events`.
+pub fn calendar(name: &str, events: &[Event]) -> String {
+    let stamp = utc_stamp();
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//AI Director's Chair//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+    for event in events {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}", event.uid));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        match &event.when {
+            When::AllDay(date) => {
+                push_line(
+                    &mut out,
+                    &format!("DTSTART;VALUE=DATE:{}", compact_date(date)),
+                );
+                push_line(&mut out, "DURATION:P1D");
+            }
+            When::Timed {
+                date,
+                start,
+                end,
+                default_minutes,
+            } => {
+                let from = minutes(start).unwrap_or(0);
+                let length = end
+                    .as_deref()
+                    .and_then(minutes)
+                    .map(|to| (to + 24 * 60 - from) % (24 * 60))
+                    .filter(|m| *m > 0)
+                    .unwrap_or(*default_minutes);
+                push_line(
+                    &mut out,
+                    &format!(
+                        "DTSTART:{}T{:02}{:02}00",
+                        compact_date(date),
+                        from / 60,
+                        from % 60
+                    ),
+                );
+                let mut duration = String::from("DURATION:PT");
+                if length >= 60 {
+                    let _ = write!(duration, "{}H", length / 60);
+                }
+                if length % 60 > 0 {
+                    let _ = write!(duration, "{}M", length % 60);
+                }
+                push_line(&mut out, &duration);
+            }
+        }
+        push_line(&mut out, &format!("SUMMARY:{}", escape(&event.summary)));
+        if !event.description.is_empty() {
+            push_line(
+                &mut out,
+                &format!("DESCRIPTION:{}", escape(&event.description)),
+            );
+        }
+        if !event.location.is_empty() {
+            push_line(&mut out, &format!("LOCATION:{}", escape(&event.location)));
+        }
+        push_line(&mut out, "END:VEVENT");
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Join folded lines back into content lines.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if !line.is_empty() => lines.push(line.to_string()),
+            _ => {}
+        }
+    }
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n' | 'N') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => {}
+            },
+            (c, false) => out.push(c),
+        }
+    }
+    out
+}
+
+/// A content line's parameters and value.
+struct Property {
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+/// A content line's name and property. The value starts at the first
+/// colon outside a quoted parameter.
+fn split_property(line: &str) -> Option<(String, Property)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        .collect();
+    let value = line[colon + 1..].to_string();
+    Some((name, Property { params, value }))
+}
+
+/// `YYYYMMDD` as `YYYY-MM-DD`.
+fn dashed_date(date: &str) -> Option<String> {
+    (date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]))
+}
+
+/// A DATE-TIME value as a local `YYYY-MM-DD` date and `HH:MM` time.
+fn local_time(value: &str) -> Option<(String, String)> {
+    let (value, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(v) => (v, true),
+        None => (value, false),
+    };
+    let mut time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    if utc {
+        time = time.and_utc().with_timezone(&Local).naive_local();
+    }
+    Some((
+        time.format("%Y-%m-%d").to_string(),
+        time.format("%H:%M").to_string(),
+    ))
+}
+
+/// A DURATION in minutes, e.g. `PT1H30M` or `P1D`.
+fn duration_minutes(value: &str) -> Option<u32> {
+    let value = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut minutes = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: u32 = number.parse().ok()?;
+                number.clear();
+                minutes += n * match unit {
+                    'W' => 7 * 24 * 60,
+                    'D' => 24 * 60,
+                    'H' => 60,
+                    'M' => 1,
+                    'S' => 0,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(minutes)
+}
+
+fn clock(minutes: u32) -> String {
+    let minutes = minutes % (24 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// The events of an iCalendar file. Events without a start the reader
+/// understands are skipped; recurrence rules and alarms are ignored.
+pub fn parse(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut current: Option<HashMap<String, Property>> = None;
+    // Depth inside components nested in an event, such as VALARM.
+    let mut nested = 0;
+    for line in unfold(text) {
+        let Some((name, property)) = split_property(&line) else {
+            continue;
+        };
+        match (name.as_str(), property.value.to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(HashMap::new()),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", _) if nested > 0 => nested -= 1,
+            _ if nested > 0 => {}
+            ("END", "VEVENT") => {
+                if let Some(event) = current.take().and_then(|props| event(&props)) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let Some(props) = current.as_mut() {
+                    props.entry(name).or_insert(property);
+                }
+            }
+        }
+    }
+    events
+}
+
+fn event(props: &HashMap<String, Property>) -> Option<Event> {
+    let text = |name: &str| {
+        props
+            .get(name)
+            .map(|p| unescape(&p.value))
+            .unwrap_or_default()
+    };
+    let start = props.get("DTSTART")?;
+    let all_day = start
+        .params
+        .iter()
+        .any(|(k, v)| k == "VALUE" && v == "DATE")
+        || start.value.len() == 8;
+    let when = if all_day {
+        When::AllDay(dashed_date(&start.value)?)
+    } else {
+        let (date, start) = local_time(&start.value)?;
+        let end = match (props.get("DTEND"), props.get("DURATION")) {
+            (Some(end), _) => local_time(&end.value).map(|(_, end)| end),
+            (None, Some(length)) => duration_minutes(&length.value)
+                .map(|length| clock(minutes(&start).unwrap_or(0) + length)),
+            (None, None) => None,
+        };
+        When::Timed {
+            date,
+            start,
+            end,
+            default_minutes: 0,
+        }
+    };
+    Some(Event {
+        uid: text("UID"),
+        when,
+        summary: text("SUMMARY"),
+        description: text("DESCRIPTION"),
+        location: text("LOCATION"),
+    })
+}
@@ -0,0 +1,228 @@
+//! The idea inbox: loose notes that belong to no project yet, typed into
+//! the quick-capture window (see `quick_capture`) or the inbox itself.
+//! An idea leaves the inbox by being deleted or turned into a scene or a
+//! project; its first line becomes the title and the whole note the
+//! description.
+
+use crate::activity;
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::projects::{self, ProjectSummary};
+use crate::scenes::{self, Scene, SceneInput};
+use crate::validation::{Validator, MAX_TEXT_LEN};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Longest title taken from an idea's first line.
+const TITLE_LEN: usize = 80;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Idea {
+    pub id: String,
+    pub text: String,
+    /// Where it was typed: `"quick-capture"` or `"inbox"`.
+    pub source: String,
+    pub created_at: String,
+}
+
+fn validate_text(text: &str) -> AppResult<()> {
+    let mut v = Validator::new();
+    v.required("text", text).max_len("text", text, MAX_TEXT_LEN);
+    v.finish()
+}
+
+fn row_to_idea(row: &Row) -> rusqlite::Result<Idea> {
+    Ok(Idea {
+        id: row.get(0)?,
+        text: row.get(1)?,
+        source: row.get(2)?,
+        created_at: row.get(3)?,
+    })
+}
+
+pub fn get(conn: &Connection, id: &str) -> AppResult<Idea> {
+    conn.query_row(
+        "SELECT id, text, source, created_at FROM inbox WHERE id = ?1",
+        [id],
+        row_to_idea,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(format!("idea {}", id)),
+        e => e.into(),
+    })
+}
+
+/// Add a note to the inbox.
+pub fn append(conn: &Connection, text: &str, source: &str) -> AppResult<Idea> {
+    let text = text.trim();
+    validate_text(text)?;
+    let idea = Idea {
+        id: uuid::Uuid::new_v4().to_string(),
+        text: text.to_string(),
+        source: source.to_string(),
+        created_at: clock::now(),
+    };
+    conn.execute(
+        "INSERT INTO inbox (id, text, source, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![idea.id, idea.text, idea.source, idea.created_at],
+    )?;
+    Ok(idea)
+}
+
+/// Save a note from the quick-capture window. Emits `inbox-changed` so an
+/// open inbox picks it up.
+#[tauri::command]
+pub fn capture_idea(app: AppHandle, text: String) -> AppResult<Idea> {
+    let idea = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        append(&conn, &text, "quick-capture")?
+    };
+    AppEvent::InboxChanged(idea.id.clone()).emit(&app);
+    Ok(idea)
+}
+
+/// The idea's first line, shortened to a title.
+fn title(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    if line.chars().count() <= TITLE_LEN {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(TITLE_LEN - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Make the idea the project's next scene and drop it from the inbox.
+pub fn convert_to_scene(conn: &Connection, id: &str, project_id: &str) -> AppResult<Scene> {
+    let idea = get(conn, id)?;
+    let existing = scenes::list_for_project(conn, project_id)?;
+    let number = existing.iter().map(|s| s.scene_number).max().unwrap_or(0) + 1;
+    let sort_order = existing.iter().map(|s| s.sort_order).max().unwrap_or(0) + 1;
+    let tx = conn.unchecked_transaction()?;
+    let scene = scenes::save(
+        &tx,
+        &SceneInput {
+            id: None,
+            project_id: project_id.to_string(),
+            scene_number: number.min(9999),
+            title: title(&idea.text),
+            description: idea.text.clone(),
+            prompt: String::new(),
+            camera_angle: None,
+            lighting: None,
+            duration: 5,
+            dialog: String::new(),
+            characters: Vec::new(),
+            sort_order,
+            location: String::new(),
+            expected_version: None,
+        },
+    )?;
+    tx.execute("DELETE FROM inbox WHERE id = ?1", [id])?;
+    tx.commit()?;
+    Ok(scene)
+}
+
+/// Start a project from the idea and drop it from the inbox.
+pub fn convert_to_project(conn: &Connection, id: &str) -> AppResult<ProjectSummary> {
+    let idea = get(conn, id)?;
+    let name = title(&idea.text);
+    let project_id = uuid::Uuid::new_v4().to_string();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO projects (id, name, synopsis, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?4)",
+        params![project_id, name, idea.text, clock::now()],
+    )?;
+    activity::record(
+        &tx,
+        &project_id,
+        "project",
+        &project_id,
+        "created",
+        &format!("Started project {} from an idea", name),
+        &[],
+    )?;
+    tx.execute("DELETE FROM inbox WHERE id = ?1", [id])?;
+    tx.commit()?;
+    projects::get_project(conn, &project_id)
+}
+
+/// The inbox, newest first.
+pub fn list(conn: &Connection) -> AppResult<Vec<Idea>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, text, source, created_at FROM inbox ORDER BY created_at DESC, rowid DESC",
+    )?;
+    let ideas = stmt
+        .query_map([], row_to_idea)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(ideas)
+}
+
+#[tauri::command]
+pub fn list_ideas(db: State<'_, Database>) -> AppResult<Vec<Idea>> {
+    list(&db.conn())
+}
+
+#[tauri::command]
+pub fn add_idea(db: State<'_, Database>, text: String) -> AppResult<Idea> {
+    append(&db.conn(), &text, "inbox")
+}
+
+pub fn update(conn: &Connection, id: &str, text: &str) -> AppResult<Idea> {
+    let text = text.trim();
+    validate_text(text)?;
+    let changed = conn.execute(
+        "UPDATE inbox SET text = ?2 WHERE id = ?1",
+        params![id, text],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("idea {}", id)));
+    }
+    get(conn, id)
+}
+
+#[tauri::command]
+pub fn update_idea(db: State<'_, Database>, id: String, text: String) -> AppResult<Idea> {
+    update(&db.conn(), &id, &text)
+}
+
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let changed = conn.execute("DELETE FROM inbox WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("idea {}", id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_idea(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
+
+#[tauri::command]
+pub fn convert_idea_to_scene(
+    db: State<'_, Database>,
+    idea_id: String,
+    project_id: String,
+) -> AppResult<Scene> {
+    convert_to_scene(&db.conn(), &idea_id, &project_id)
+}
+
+#[tauri::command]
+pub fn convert_idea_to_project(
+    db: State<'_, Database>,
+    idea_id: String,
+) -> AppResult<ProjectSummary> {
+    convert_to_project(&db.conn(), &idea_id)
+}
@@ -0,0 +1,703 @@
+//! Outbound connectors that keep a Notion database or an Airtable table
+//! listing a project's scenes and their status, for people who track the
+//! production there. Syncing is one-way and on demand: `sync_integration`
+//! creates a page or record for each new scene and updates the ones made
+//! before. Rows of deleted scenes are left for the user to remove.
+//!
+//! Each connector's API token is a secret; the target and which scene
+//! field goes into which remote property are stored per project.
+
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::i18n;
+use crate::projects;
+use crate::provider_cache;
+use crate::providers;
+use crate::scene_characters;
+use crate::scenes::{self, Scene};
+use crate::{clock, numbering, secrets};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const NOTION_API: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+const AIRTABLE_API: &str = "https://api.airtable.com/v0";
+/// Notion allows about three requests a second.
+const NOTION_PACE: Duration = Duration::from_millis(350);
+/// How long a database's schema is reused between syncs.
+const NOTION_SCHEMA_CACHE: Duration = Duration::from_secs(10 * 60);
+/// Airtable takes at most ten records per request.
+const AIRTABLE_BATCH: usize = 10;
+/// Longest text Notion accepts in one rich-text run.
+const NOTION_TEXT_LIMIT: usize = 2000;
+
+/// Scene fields that can be sent, by the names used in a field mapping.
+pub const SCENE_FIELDS: &[&str] = &[
+    "scene",
+    "title",
+    "status",
+    "description",
+    "prompt",
+    "location",
+    "duration",
+    "characters",
+    "sceneId",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum Connector {
+    Notion,
+    Airtable,
+}
+
+impl Connector {
+    fn as_str(self) -> &'static str {
+        match self {
+            Connector::Notion => "notion",
+            Connector::Airtable => "airtable",
+        }
+    }
+
+    fn token_secret(self) -> String {
+        format!("integrations.{}_token", self.as_str())
+    }
+
+    fn token(self, conn: &Connection) -> AppResult<String> {
+        secrets::get(conn, &self.token_secret())?
+            .ok_or_else(|| AppError::Invalid(format!("add a {} API token first", self.as_str())))
+    }
+
+    /// The mapping a new link starts with: remote property by scene field.
+    fn default_fields(self) -> BTreeMap<String, String> {
+        [("title", "Name"), ("scene", "Scene"), ("status", "Status")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+}
+
+/// Where a project's scenes go.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct IntegrationLink {
+    pub connector: Connector,
+    /// A Notion database id (or its URL), or an Airtable base id.
+    pub target: String,
+    /// The Airtable table name or id; unused for Notion.
+    #[serde(default)]
+    pub table: String,
+    /// Remote property name by scene field (see `SCENE_FIELDS`).
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Only in responses.
+    #[serde(default)]
+    pub last_synced_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct IntegrationStatus {
+    pub connector: Connector,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct IntegrationSyncReport {
+    pub created: usize,
+    pub updated: usize,
+    /// Scenes that couldn't be sent, with why.
+    pub failed: Vec<String>,
+    /// Mapped properties the remote side doesn't have or can't take.
+    pub ignored_fields: Vec<String>,
+}
+
+/// A scene field's value before it's shaped for a remote property type.
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    List(Vec<String>),
+}
+
+impl FieldValue {
+    fn text(&self) -> String {
+        match self {
+            FieldValue::Text(s) => s.clone(),
+            FieldValue::Number(n) => n.to_string(),
+            FieldValue::List(items) => items.join(", "),
+        }
+    }
+}
+
+/// The 32-hex-digit id in a Notion database id or URL, dashed.
+fn notion_id(input: &str) -> Option<String> {
+    let path = input.split(['?', '#']).next().unwrap_or_default();
+    let hex: String = path
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| *c != '-')
+        .collect();
+    let hex = hex.get(hex.len().checked_sub(32)?..)?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+fn get_link(
+    conn: &Connection,
+    project_id: &str,
+    connector: Connector,
+) -> AppResult<Option<IntegrationLink>> {
+    Ok(conn
+        .query_row(
+            "SELECT target, target_table, fields_json, last_synced_at FROM integration_links
+             WHERE project_id = ?1 AND connector = ?2",
+            params![project_id, connector.as_str()],
+            |r| {
+                Ok(IntegrationLink {
+                    connector,
+                    target: r.get(0)?,
+                    table: r.get(1)?,
+                    fields: serde_json::from_str(&r.get::<_, String>(2)?).unwrap_or_default(),
+                    last_synced_at: r.get(3)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+fn remote_id(conn: &Connection, connector: Connector, scene_id: &str) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT remote_id FROM integration_records WHERE connector = ?1 AND scene_id = ?2",
+            params![connector.as_str(), scene_id],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+fn values(
+    conn: &Connection,
+    locale: i18n::Locale,
+    scene: &Scene,
+) -> AppResult<BTreeMap<&'static str, FieldValue>> {
+    let status = locale.t(&format!("status.{}", scene.status)).to_string();
+    Ok(BTreeMap::from([
+        (
+            "scene",
+            FieldValue::Text(numbering::label(scene.scene_number, &scene.scene_suffix)),
+        ),
+        ("title", FieldValue::Text(scene.title.clone())),
+        ("status", FieldValue::Text(status)),
+        ("description", FieldValue::Text(scene.description.clone())),
+        ("prompt", FieldValue::Text(scene.prompt.clone())),
+        ("location", FieldValue::Text(scene.location.clone())),
+        ("duration", FieldValue::Number(scene.duration as f64)),
+        (
+            "characters",
+            FieldValue::List(scene_characters::names(conn, &scene.characters)?),
+        ),
+        ("sceneId", FieldValue::Text(scene.id.clone())),
+    ]))
+}
+
+/// A value shaped for a Notion property of type `kind`, or None if the
+/// type can't take it.
+fn notion_value(kind: &str, value: &FieldValue) -> Option<Value> {
+    let text: String = value.text().chars().take(NOTION_TEXT_LIMIT).collect();
+    let option = |name: &str| {
+        // Commas aren't allowed in option names.
+        let name = name.replace(',', " ");
+        if name.trim().is_empty() {
+            Value::Null
+        } else {
+            json!({ "name": name })
+        }
+    };
+    Some(match kind {
+        "title" => json!({ "title": [{ "text": { "content": text } }] }),
+        "rich_text" => json!({ "rich_text": [{ "text": { "content": text } }] }),
+        "number" => json!({ "number": match value {
+            FieldValue::Number(n) => Some(*n),
+            other => other.text().parse::<f64>().ok(),
+        } }),
+        "select" => json!({ "select": option(&text) }),
+        "status" => json!({ "status": option(&text) }),
+        "multi_select" => {
+            let items = match value {
+                FieldValue::List(items) => items.clone(),
+                other => vec![other.text()],
+            };
+            json!({ "multi_select": items
+                .iter()
+                .filter(|i| !i.trim().is_empty())
+                .map(|i| option(i))
+                .collect::<Vec<_>>() })
+        }
+        "url" => json!({ "url": Some(text).filter(|t| !t.is_empty()) }),
+        _ => return None,
+    })
+}
+
+async fn check(res: reqwest::Response, what: &str) -> AppResult<Value> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res.json().await?);
+    }
+    let body = res.text().await.unwrap_or_default();
+    Err(AppError::Provider(format!(
+        "{} ({}): {}",
+        what, status, body
+    )))
+}
+
+/// Property types of a Notion database, by property name.
+async fn notion_schema(token: &str, database: &str) -> AppResult<BTreeMap<String, String>> {
+    let res = providers::client()
+        .get(format!("{}/databases/{}", NOTION_API, database))
+        .bearer_auth(token)
+        .header("Notion-Version", NOTION_VERSION)
+        .send()
+        .await?;
+    let body = check(res, "Notion database").await?;
+    Ok(body["properties"]
+        .as_object()
+        .map(|props| {
+            props
+                .iter()
+                .map(|(name, p)| (name.clone(), p["type"].as_str().unwrap_or("").to_string()))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Create or update one Notion page. Returns the page id and whether it
+/// was created.
+async fn notion_upsert(
+    token: &str,
+    database: &str,
+    existing: Option<&str>,
+    properties: Value,
+) -> AppResult<(String, bool)> {
+    if let Some(page) = existing {
+        let res = providers::client()
+            .patch(format!("{}/pages/{}", NOTION_API, page))
+            .bearer_auth(token)
+            .header("Notion-Version", NOTION_VERSION)
+            .json(&json!({ "properties": properties }))
+            .send()
+            .await?;
+        // A page deleted on the Notion side is made again.
+        if res.status() != reqwest::StatusCode::NOT_FOUND {
+            check(res, "Notion page update").await?;
+            return Ok((page.to_string(), false));
+        }
+    }
+    let res = providers::client()
+        .post(format!("{}/pages", NOTION_API))
+        .bearer_auth(token)
+        .header("Notion-Version", NOTION_VERSION)
+        .json(&json!({ "parent": { "database_id": database }, "properties": properties }))
+        .send()
+        .await?;
+    let body = check(res, "Notion page").await?;
+    let id = body["id"]
+        .as_str()
+        .ok_or_else(|| AppError::Provider("Notion returned no page id".into()))?;
+    Ok((id.to_string(), true))
+}
+
+/// Send one batch of Airtable records; `existing` pairs each with its
+/// record id when it has one. Returns the record ids in order.
+async fn airtable_batch(
+    token: &str,
+    url: &str,
+    fields: &[Map<String, Value>],
+    existing: &[Option<String>],
+    update: bool,
+) -> AppResult<Vec<String>> {
+    let records: Vec<Value> = fields
+        .iter()
+        .zip(existing)
+        .map(|(f, id)| match id {
+            Some(id) if update => json!({ "id": id, "fields": f }),
+            _ => json!({ "fields": f }),
+        })
+        .collect();
+    let request = if update {
+        providers::client().patch(url)
+    } else {
+        providers::client().post(url)
+    };
+    let res = request
+        .bearer_auth(token)
+        .json(&json!({ "records": records, "typecast": true }))
+        .send()
+        .await?;
+    let body = check(res, "Airtable records").await?;
+    Ok(body["records"]
+        .as_array()
+        .map(|rs| {
+            rs.iter()
+                .filter_map(|r| r["id"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn remember(
+    conn: &Connection,
+    connector: Connector,
+    scene_id: &str,
+    remote: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO integration_records (connector, scene_id, remote_id) VALUES (?1, ?2, ?3)
+         ON CONFLICT(connector, scene_id) DO UPDATE SET remote_id = excluded.remote_id",
+        params![connector.as_str(), scene_id, remote],
+    )?;
+    Ok(())
+}
+
+/// Per scene: its id, its values by remote property, and its remote id.
+pub type Outgoing = Vec<(String, BTreeMap<String, FieldValue>, Option<String>)>;
+
+fn outgoing(conn: &Connection, project_id: &str, link: &IntegrationLink) -> AppResult<Outgoing> {
+    let locale = i18n::current(conn)?;
+    scenes::list_for_project(conn, project_id)?
+        .iter()
+        .map(|scene| {
+            let mut values = values(conn, locale, scene)?;
+            let mapped = link
+                .fields
+                .iter()
+                .filter_map(|(field, property)| {
+                    values.remove(field.as_str()).map(|v| (property.clone(), v))
+                })
+                .collect();
+            Ok((
+                scene.id.clone(),
+                mapped,
+                remote_id(conn, link.connector, &scene.id)?,
+            ))
+        })
+        .collect()
+}
+
+async fn sync_notion(
+    app: &AppHandle,
+    token: &str,
+    link: &IntegrationLink,
+    scenes: Outgoing,
+) -> AppResult<IntegrationSyncReport> {
+    let database = notion_id(&link.target)
+        .ok_or_else(|| AppError::Invalid("not a Notion database id or link".into()))?;
+    let db = app.state::<Database>();
+    let key = provider_cache::key("notion", &[token, &database]);
+    let fetch = || notion_schema(token, &database);
+    let mut schema =
+        provider_cache::get_or_fetch(&db, "notion", &key, NOTION_SCHEMA_CACHE, fetch).await?;
+    if link.fields.values().any(|p| !schema.contains_key(p)) {
+        // The property may have been added since the schema was cached.
+        provider_cache::forget(&db.conn(), &key)?;
+        schema =
+            provider_cache::get_or_fetch(&db, "notion", &key, NOTION_SCHEMA_CACHE, fetch).await?;
+    }
+    let mut report = IntegrationSyncReport::default();
+    for property in link.fields.values() {
+        if !schema.contains_key(property) {
+            report.ignored_fields.push(property.clone());
+        }
+    }
+    for (scene_id, values, existing) in scenes {
+        let mut properties = Map::new();
+        for (property, value) in &values {
+            match schema
+                .get(property)
+                .and_then(|kind| notion_value(kind, value))
+            {
+                Some(v) => {
+                    properties.insert(property.clone(), v);
+                }
+                None if schema.contains_key(property)
+                    && !report.ignored_fields.contains(property) =>
+                {
+                    report.ignored_fields.push(property.clone())
+                }
+                None => {}
+            }
+        }
+        match notion_upsert(
+            token,
+            &database,
+            existing.as_deref(),
+            Value::Object(properties),
+        )
+        .await
+        {
+            Ok((page, created)) => {
+                remember(&db.conn(), Connector::Notion, &scene_id, &page)?;
+                if created {
+                    report.created += 1;
+                } else {
+                    report.updated += 1;
+                }
+            }
+            Err(e @ AppError::Network(_)) => return Err(e),
+            Err(e) => report.failed.push(format!("{}: {}", scene_id, e)),
+        }
+        tokio::time::sleep(NOTION_PACE).await;
+    }
+    Ok(report)
+}
+
+async fn sync_airtable(
+    app: &AppHandle,
+    token: &str,
+    link: &IntegrationLink,
+    scenes: Outgoing,
+) -> AppResult<IntegrationSyncReport> {
+    if link.table.trim().is_empty() {
+        return Err(AppError::Invalid(
+            "choose the Airtable table to sync to".into(),
+        ));
+    }
+    let url = format!(
+        "{}/{}/{}",
+        AIRTABLE_API,
+        link.target.trim(),
+        urlencode(link.table.trim())
+    );
+    let mut report = IntegrationSyncReport::default();
+    let (updates, creates): (Vec<_>, Vec<_>) = scenes
+        .into_iter()
+        .partition(|(_, _, existing)| existing.is_some());
+    for (batch, update) in updates
+        .chunks(AIRTABLE_BATCH)
+        .map(|b| (b, true))
+        .chain(creates.chunks(AIRTABLE_BATCH).map(|b| (b, false)))
+    {
+        let fields: Vec<Map<String, Value>> = batch
+            .iter()
+            .map(|(_, values, _)| {
+                values
+                    .iter()
+                    .map(|(property, value)| {
+                        let v = match value {
+                            FieldValue::Number(n) => json!(n),
+                            FieldValue::List(items) => json!(items),
+                            FieldValue::Text(s) => json!(s),
+                        };
+                        (property.clone(), v)
+                    })
+                    .collect()
+            })
+            .collect();
+        let existing: Vec<Option<String>> = batch.iter().map(|(_, _, e)| e.clone()).collect();
+        match airtable_batch(token, &url, &fields, &existing, update).await {
+            Ok(ids) => {
+                let db = app.state::<Database>();
+                let conn = db.conn();
+                for ((scene_id, _, _), id) in batch.iter().zip(&ids) {
+                    remember(&conn, Connector::Airtable, scene_id, id)?;
+                }
+                if update {
+                    report.updated += ids.len();
+                } else {
+                    report.created += ids.len();
+                }
+            }
+            Err(e @ AppError::Network(_)) => return Err(e),
+            Err(e) => report.failed.extend(
+                batch
+                    .iter()
+                    .map(|(scene_id, _, _)| format!("{}: {}", scene_id, e)),
+            ),
+        }
+    }
+    Ok(report)
+}
+
+fn urlencode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Store an API token (a Notion internal integration secret or an
+/// Airtable personal access token); empty removes it.
+pub fn set_token(conn: &Connection, connector: Connector, token: &str) -> AppResult<()> {
+    match token.trim() {
+        "" => secrets::delete(conn, &connector.token_secret()),
+        token => secrets::set(conn, &connector.token_secret(), token),
+    }
+}
+
+#[tauri::command]
+pub fn set_integration_token(
+    db: State<'_, Database>,
+    connector: Connector,
+    token: String,
+) -> AppResult<()> {
+    set_token(&db.conn(), connector, &token)
+}
+
+pub fn status(conn: &Connection) -> AppResult<Vec<IntegrationStatus>> {
+    [Connector::Notion, Connector::Airtable]
+        .into_iter()
+        .map(|connector| {
+            Ok(IntegrationStatus {
+                connector,
+                connected: secrets::get(conn, &connector.token_secret())?.is_some(),
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_integration_status(db: State<'_, Database>) -> AppResult<Vec<IntegrationStatus>> {
+    status(&db.conn())
+}
+
+/// The project's link to `connector`, or a blank one with the default
+/// field mapping.
+pub fn link(
+    conn: &Connection,
+    project_id: &str,
+    connector: Connector,
+) -> AppResult<IntegrationLink> {
+    projects::get_project(conn, project_id)?;
+    Ok(
+        get_link(conn, project_id, connector)?.unwrap_or_else(|| IntegrationLink {
+            connector,
+            target: String::new(),
+            table: String::new(),
+            fields: connector.default_fields(),
+            last_synced_at: None,
+        }),
+    )
+}
+
+#[tauri::command]
+pub fn get_integration_link(
+    db: State<'_, Database>,
+    project_id: String,
+    connector: Connector,
+) -> AppResult<IntegrationLink> {
+    link(&db.conn(), &project_id, connector)
+}
+
+pub fn save_link(
+    conn: &Connection,
+    project_id: &str,
+    link: &IntegrationLink,
+) -> AppResult<IntegrationLink> {
+    projects::get_project(conn, project_id)?;
+    if let Some(field) = link
+        .fields
+        .keys()
+        .find(|f| !SCENE_FIELDS.contains(&f.as_str()))
+    {
+        return Err(AppError::Invalid(format!("unknown scene field: {}", field)));
+    }
+    if link.connector == Connector::Notion && notion_id(&link.target).is_none() {
+        return Err(AppError::Invalid("not a Notion database id or link".into()));
+    }
+    conn.execute(
+        "INSERT INTO integration_links (project_id, connector, target, target_table, fields_json)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(project_id, connector) DO UPDATE SET target = excluded.target,
+            target_table = excluded.target_table, fields_json = excluded.fields_json",
+        params![
+            project_id,
+            link.connector.as_str(),
+            link.target.trim(),
+            link.table.trim(),
+            serde_json::to_string(&link.fields)?
+        ],
+    )?;
+    get_link(conn, project_id, link.connector)?
+        .ok_or_else(|| AppError::NotFound(format!("integration for project {}", project_id)))
+}
+
+#[tauri::command]
+pub fn set_integration_link(
+    db: State<'_, Database>,
+    project_id: String,
+    link: IntegrationLink,
+) -> AppResult<IntegrationLink> {
+    save_link(&db.conn(), &project_id, &link)
+}
+
+/// What a sync of the project to `connector` sends: the API token, the
+/// link and the scenes' values.
+pub fn prepare(
+    conn: &Connection,
+    project_id: &str,
+    connector: Connector,
+) -> AppResult<(String, IntegrationLink, Outgoing)> {
+    confidential::ensure_remote_allowed(conn, project_id, connector.as_str())?;
+    let link = get_link(conn, project_id, connector)?.ok_or_else(|| {
+        AppError::Invalid(format!("link this project to {} first", connector.as_str()))
+    })?;
+    let scenes = outgoing(conn, project_id, &link)?;
+    Ok((connector.token(conn)?, link, scenes))
+}
+
+pub fn mark_synced(conn: &Connection, project_id: &str, connector: Connector) -> AppResult<()> {
+    conn.execute(
+        "UPDATE integration_links SET last_synced_at = ?3 WHERE project_id = ?1 AND connector = ?2",
+        params![project_id, connector.as_str(), clock::now()],
+    )?;
+    Ok(())
+}
+
+/// Push the project's scenes to the linked Notion database or Airtable
+/// table now.
+#[tauri::command]
+pub async fn sync_integration(
+    app: AppHandle,
+    project_id: String,
+    connector: Connector,
+) -> AppResult<IntegrationSyncReport> {
+    let (token, link, scenes) = prepare(&app.state::<Database>().conn(), &project_id, connector)?;
+    let report = match connector {
+        Connector::Notion => sync_notion(&app, &token, &link, scenes).await?,
+        Connector::Airtable => sync_airtable(&app, &token, &link, scenes).await?,
+    };
+    mark_synced(&app.state::<Database>().conn(), &project_id, connector)?;
+    tracing::info!(
+        connector = connector.as_str(),
+        created = report.created,
+        updated = report.updated,
+        failed = report.failed.len(),
+        "integration sync finished"
+    );
+    Ok(report)
+}
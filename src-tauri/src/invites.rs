@@ -0,0 +1,252 @@
+//! Invite tokens for the LAN session server (see `lan`). An owner issues
+//! one per collaborator, scoped to a project and a role and optionally
+//! expiring; the guest passes it when joining. Once a project has any
+//! live token, joining it needs one, and the token's role is what the
+//! guest gets.
+//!
+//! Only a SHA-256 hash of each token is stored, so the token itself is
+//! shown once, when issued. Each use records when and from which device,
+//! and revoking a token also drops a guest connected with it.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::lan::{self, LanState};
+use crate::projects;
+use crate::roles::{self, Role};
+use crate::validation::{Validate, Validator};
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::State;
+use ts_rs::TS;
+
+const TOKEN_PREFIX: &str = "aidc_";
+
+/// An issued token, without the token itself.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct InviteToken {
+    pub id: String,
+    pub project_id: String,
+    /// Who it was issued to.
+    pub name: String,
+    pub role: Role,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub last_device_id: Option<String>,
+    pub revoked_at: Option<String>,
+    /// A guest is in the LAN session with it right now.
+    pub connected: bool,
+}
+
+/// A new token; `token` is shown this once and never stored.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct IssuedToken {
+    pub invite: InviteToken,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct InviteInput {
+    pub project_id: String,
+    pub name: String,
+    /// Editor, commenter or viewer; ownership can't be handed out.
+    pub role: Role,
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+}
+
+impl Validate for InviteInput {
+    fn validate(&self, v: &mut Validator) {
+        v.uuid("projectId", &self.project_id)
+            .name("name", &self.name)
+            .one_of(
+                "role",
+                self.role.as_str(),
+                &["editor", "commenter", "viewer"],
+            );
+    }
+}
+
+const TOKEN_COLUMNS: &str = "id, project_id, name, role, created_at, expires_at, last_used_at,
+    last_device_id, revoked_at";
+
+fn row_to_token(row: &Row) -> rusqlite::Result<InviteToken> {
+    Ok(InviteToken {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        role: Role::parse(&row.get::<_, String>(3)?),
+        created_at: row.get(4)?,
+        expires_at: row.get(5)?,
+        last_used_at: row.get(6)?,
+        last_device_id: row.get(7)?,
+        revoked_at: row.get(8)?,
+        connected: false,
+    })
+}
+
+fn hash(token: &str) -> String {
+    Sha256::digest(token.trim().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn get_token(conn: &Connection, id: &str) -> AppResult<InviteToken> {
+    conn.query_row(
+        &format!("SELECT {} FROM invite_tokens WHERE id = ?1", TOKEN_COLUMNS),
+        [id],
+        row_to_token,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("invite token {}", id)))
+}
+
+/// Whether joining the project needs a token: it has one neither revoked
+/// nor expired.
+pub fn required(conn: &Connection, project_id: &str) -> AppResult<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invite_tokens WHERE project_id = ?1 AND revoked_at IS NULL
+            AND (expires_at IS NULL OR expires_at > ?2))",
+        params![project_id, clock::now()],
+        |r| r.get(0),
+    )?)
+}
+
+/// Check a guest's token for the project, recording its use. Returns the
+/// token's id and role.
+pub fn redeem(
+    conn: &Connection,
+    project_id: &str,
+    token: &str,
+    device_id: &str,
+) -> AppResult<(String, Role)> {
+    let now = clock::now();
+    let found: Option<(String, String)> = conn
+        .query_row(
+            "SELECT id, role FROM invite_tokens WHERE token_hash = ?1 AND project_id = ?2
+                AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?3)",
+            params![hash(token), project_id, now],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    let Some((id, role)) = found else {
+        return Err(AppError::Forbidden(
+            "the invite token is wrong, expired or revoked".into(),
+        ));
+    };
+    conn.execute(
+        "UPDATE invite_tokens SET last_used_at = ?2, last_device_id = ?3 WHERE id = ?1",
+        params![id, now, device_id],
+    )?;
+    Ok((id, Role::parse(&role)))
+}
+
+/// Whether a redeemed token still lets its guest in.
+pub fn is_live(conn: &Connection, id: &str) -> AppResult<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM invite_tokens WHERE id = ?1 AND revoked_at IS NULL
+            AND (expires_at IS NULL OR expires_at > ?2))",
+        params![id, clock::now()],
+        |r| r.get(0),
+    )?)
+}
+
+/// Create a token for a collaborator; owners only.
+pub fn issue(conn: &Connection, input: &InviteInput) -> AppResult<IssuedToken> {
+    input.check()?;
+    projects::get_project(conn, &input.project_id)?;
+    roles::ensure_role(conn, &input.project_id, Role::Owner)?;
+    let token = format!(
+        "{}{}{}",
+        TOKEN_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let id = uuid::Uuid::new_v4().to_string();
+    let expires_at = input
+        .expires_in_days
+        .map(|days| clock::format(Utc::now() + Duration::days(days.into())));
+    conn.execute(
+        "INSERT INTO invite_tokens (id, project_id, name, role, token_hash, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            id,
+            input.project_id,
+            input.name.trim(),
+            input.role.as_str(),
+            hash(&token),
+            clock::now(),
+            expires_at
+        ],
+    )?;
+    tracing::info!(project_id = %input.project_id, token_id = %id, role = input.role.as_str(), "issued invite token");
+    Ok(IssuedToken {
+        invite: get_token(conn, &id)?,
+        token,
+    })
+}
+
+#[tauri::command]
+pub fn issue_invite_token(db: State<'_, Database>, input: InviteInput) -> AppResult<IssuedToken> {
+    issue(&db.conn(), &input)
+}
+
+/// Issued tokens, newest first, with when each was last used and whether
+/// a guest is connected with it (its id is in `connected`). Revoked ones
+/// are included.
+pub fn list(
+    conn: &Connection,
+    project_id: &str,
+    connected: &[String],
+) -> AppResult<Vec<InviteToken>> {
+    roles::ensure_role(conn, project_id, Role::Owner)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM invite_tokens WHERE project_id = ?1 ORDER BY created_at DESC",
+        TOKEN_COLUMNS
+    ))?;
+    let mut tokens = stmt
+        .query_map([project_id], row_to_token)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for token in &mut tokens {
+        token.connected = connected.contains(&token.id);
+    }
+    Ok(tokens)
+}
+
+#[tauri::command]
+pub fn list_sessions(
+    db: State<'_, Database>,
+    lan: State<'_, LanState>,
+    project_id: String,
+) -> AppResult<Vec<InviteToken>> {
+    list(&db.conn(), &project_id, &lan::connected_tokens(&lan))
+}
+
+/// Revoke a token. A guest connected with it is dropped within a moment.
+pub fn revoke(conn: &Connection, id: &str) -> AppResult<InviteToken> {
+    let token = get_token(conn, id)?;
+    roles::ensure_role(conn, &token.project_id, Role::Owner)?;
+    if token.revoked_at.is_none() {
+        conn.execute(
+            "UPDATE invite_tokens SET revoked_at = ?2 WHERE id = ?1",
+            params![id, clock::now()],
+        )?;
+        tracing::info!(token_id = %id, "revoked invite token");
+    }
+    get_token(conn, id)
+}
+
+#[tauri::command]
+pub fn revoke_token(db: State<'_, Database>, id: String) -> AppResult<InviteToken> {
+    revoke(&db.conn(), &id)
+}
@@ -0,0 +1,185 @@
+//! The HTTP exchanges behind each generation job, kept so a failed take
+//! shows what the provider actually said. Provider calls collect
+//! `Exchange`s as they go; the job runner stores them in `job_logs` with
+//! the API key and inline media scrubbed out.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::jobs::{self, VideoJob};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+use ts_rs::TS;
+
+/// Which step of a job an exchange belongs to, stored in `job_logs.phase`.
+pub mod phase {
+    pub const SUBMIT: &str = "submit";
+    pub const POLL: &str = "poll";
+}
+
+/// Responses are cut to this many characters.
+const MAX_RESPONSE_CHARS: usize = 64 * 1024;
+/// Strings in a request longer than this that are data URIs (audio, images)
+/// are stored as a placeholder.
+const MAX_INLINE_CHARS: usize = 256;
+
+/// One request to a provider and what came back. `status` is None when no
+/// response arrived.
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub method: &'static str,
+    pub url: String,
+    pub request: Option<Value>,
+    pub status: Option<u16>,
+    pub response: String,
+    pub error: String,
+}
+
+impl Exchange {
+    pub fn new(method: &'static str, url: &str, request: Option<&Value>) -> Self {
+        Self {
+            method,
+            url: url.to_string(),
+            request: request.cloned(),
+            status: None,
+            response: String::new(),
+            error: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct JobLogEntry {
+    #[ts(type = "number")]
+    pub id: i64,
+    pub phase: String,
+    pub method: String,
+    pub url: String,
+    pub request: Option<Value>,
+    pub status: Option<u16>,
+    pub response: String,
+    pub error: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct JobDebugInfo {
+    pub job: VideoJob,
+    /// The request as the app stored it, before provider formatting.
+    pub request: Option<Value>,
+    /// Every exchange with the provider, oldest first.
+    pub logs: Vec<JobLogEntry>,
+}
+
+fn redact_text(text: &str, secrets: &[&str]) -> String {
+    secrets
+        .iter()
+        .filter(|s| !s.is_empty())
+        .fold(text.to_string(), |acc, s| acc.replace(s, "[redacted]"))
+}
+
+fn redact_value(value: &mut Value, secrets: &[&str]) {
+    match value {
+        Value::String(s) if s.starts_with("data:") && s.len() > MAX_INLINE_CHARS => {
+            let mime = s[5..].split([';', ',']).next().unwrap_or_default();
+            *s = format!("[{} data, {} bytes]", mime, s.len());
+        }
+        Value::String(s) => *s = redact_text(s, secrets),
+        Value::Array(items) => items.iter_mut().for_each(|v| redact_value(v, secrets)),
+        Value::Object(map) => map.values_mut().for_each(|v| redact_value(v, secrets)),
+        _ => {}
+    }
+}
+
+/// Store a job's exchanges, scrubbing `secrets` (API keys) from every part.
+pub fn record(
+    conn: &Connection,
+    job_id: &str,
+    job_phase: &str,
+    exchanges: &[Exchange],
+    secrets: &[&str],
+) -> AppResult<()> {
+    let now = clock::now();
+    for e in exchanges {
+        let request = e.request.clone().map(|mut r| {
+            redact_value(&mut r, secrets);
+            r.to_string()
+        });
+        let response: String = redact_text(&e.response, secrets)
+            .chars()
+            .take(MAX_RESPONSE_CHARS)
+            .collect();
+        conn.execute(
+            "INSERT INTO job_logs (job_id, phase, method, url, request_json, status, response,
+                error, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                job_id,
+                job_phase,
+                e.method,
+                redact_text(&e.url, secrets),
+                request,
+                e.status,
+                response,
+                redact_text(&e.error, secrets),
+                now
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn list_for_job(conn: &Connection, job_id: &str) -> AppResult<Vec<JobLogEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, phase, method, url, request_json, status, response, error, created_at
+         FROM job_logs WHERE job_id = ?1 ORDER BY id",
+    )?;
+    let logs = stmt
+        .query_map([job_id], |r| {
+            Ok(JobLogEntry {
+                id: r.get(0)?,
+                phase: r.get(1)?,
+                method: r.get(2)?,
+                url: r.get(3)?,
+                request: r
+                    .get::<_, Option<String>>(4)?
+                    .and_then(|j| serde_json::from_str(&j).ok()),
+                status: r.get(5)?,
+                response: r.get(6)?,
+                error: r.get(7)?,
+                created_at: r.get(8)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(logs)
+}
+
+/// A job with its stored request and every provider exchange, for the
+/// "why did this fail" view.
+pub fn debug_info(conn: &Connection, job_id: &str) -> AppResult<JobDebugInfo> {
+    let job = jobs::get_job(conn, job_id)?;
+    let request_json: String = conn.query_row(
+        "SELECT request_json FROM video_jobs WHERE id = ?1",
+        [job_id],
+        |r| r.get(0),
+    )?;
+    let mut request: Option<Value> = serde_json::from_str(&request_json).ok();
+    if let Some(r) = &mut request {
+        redact_value(r, &[]);
+    }
+    Ok(JobDebugInfo {
+        job,
+        request,
+        logs: list_for_job(conn, job_id)?,
+    })
+}
+
+#[tauri::command]
+pub fn get_job_debug_info(db: State<'_, Database>, job_id: String) -> AppResult<JobDebugInfo> {
+    debug_info(&db.conn(), &job_id)
+}
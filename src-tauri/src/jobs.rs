@@ -0,0 +1,752 @@
+use crate::activity::{self, FieldChange};
+use crate::analytics;
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::failures::{self, Outcome};
+use crate::frames;
+use crate::job_logs;
+use crate::lipsync::{self, LipSyncRequest};
+use crate::mock;
+use crate::moderation;
+use crate::network::NetworkMonitor;
+use crate::notifications;
+use crate::projects;
+use crate::providers::{self, GenerationRequest, QueueState};
+use crate::scene_links;
+use crate::settings;
+use crate::validation::{Validator, MAX_TEXT_LEN};
+use crate::workflow::{self, SceneStatus};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+/// Job lifecycle values stored in `video_jobs.status`.
+pub mod status {
+    /// Held locally because we were offline; submitted when connectivity returns.
+    pub const OFFLINE: &str = "offline";
+    /// Being sent to the provider right now.
+    pub const PENDING: &str = "pending";
+    /// Accepted by the provider and waiting in its queue.
+    pub const QUEUED: &str = "queued";
+    pub const COMPLETED: &str = "completed";
+    pub const FAILED: &str = "failed";
+    /// Held until the jobs it waits on (`video_job_waits`) have completed;
+    /// then it joins the offline queue.
+    pub const WAITING: &str = "waiting";
+}
+
+/// What a job produces, stored in `video_jobs.kind`. Each kind has its own
+/// provider list and request shape.
+pub mod kind {
+    /// Text/image-to-video via `providers::MODELS`.
+    pub const VIDEO: &str = "video";
+    /// Portrait + audio talking head via `lipsync::LIPSYNC_MODELS`.
+    pub const LIPSYNC: &str = "lipsync";
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct VideoJob {
+    pub id: String,
+    pub scene_id: String,
+    pub provider: String,
+    pub kind: String,
+    pub job_id: String,
+    pub status: String,
+    pub video_url: String,
+    pub cost: f64,
+    pub started_at: String,
+    pub completed_at: Option<String>,
+    pub error: String,
+    /// 1–5 from review; None until rated.
+    #[ts(type = "number | null")]
+    pub rating: Option<i64>,
+    pub note: String,
+    /// A `providers::failure` kind once the job has failed, even if it was
+    /// retried since.
+    pub failure_kind: String,
+    /// Automatic retries so far.
+    #[ts(type = "number")]
+    pub attempts: i64,
+    /// Link to the take on the review platform; empty until uploaded.
+    pub review_url: String,
+}
+
+const JOB_COLUMNS: &str = "id, scene_id, provider, kind, job_id, status, video_url, cost, started_at, completed_at, error, rating, note, failure_kind, attempts, review_url";
+
+fn row_to_job(row: &Row) -> rusqlite::Result<VideoJob> {
+    Ok(VideoJob {
+        id: row.get(0)?,
+        scene_id: row.get(1)?,
+        provider: row.get(2)?,
+        kind: row.get(3)?,
+        job_id: row.get(4)?,
+        status: row.get(5)?,
+        video_url: row.get(6)?,
+        cost: row.get(7)?,
+        started_at: row.get(8)?,
+        completed_at: row.get(9)?,
+        error: row.get(10)?,
+        rating: row.get(11)?,
+        note: row.get(12)?,
+        failure_kind: row.get(13)?,
+        attempts: row.get(14)?,
+        review_url: row.get(15)?,
+    })
+}
+
+pub fn get_job(conn: &Connection, id: &str) -> AppResult<VideoJob> {
+    conn.query_row(
+        &format!("SELECT {} FROM video_jobs WHERE id = ?1", JOB_COLUMNS),
+        [id],
+        row_to_job,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(format!("job {}", id)),
+        other => other.into(),
+    })
+}
+
+fn set_status(
+    conn: &Connection,
+    id: &str,
+    status: &str,
+    job_id: &str,
+    error: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "UPDATE video_jobs SET status = ?2, job_id = ?3, error = ?4 WHERE id = ?1",
+        params![id, status, job_id, error],
+    )?;
+    Ok(())
+}
+
+pub fn count_deferred(conn: &Connection) -> AppResult<i64> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM video_jobs WHERE status = ?1",
+        [status::OFFLINE],
+        |r| r.get(0),
+    )?)
+}
+
+pub fn emit_job(app: &AppHandle, id: &str) {
+    let db = app.state::<Database>();
+    let job = get_job(&db.conn(), id);
+    if let Ok(job) = job {
+        AppEvent::JobUpdated(job).emit(app);
+    }
+}
+
+/// Move an offline job to pending so exactly one caller submits it.
+/// Returns its provider, kind and request, or None when the job isn't
+/// offline any more because a flush or another dispatch got there first.
+pub fn claim(conn: &Connection, id: &str) -> AppResult<Option<(String, String, String)>> {
+    let claimed = conn.execute(
+        "UPDATE video_jobs SET status = ?2, job_id = '', error = '' WHERE id = ?1 AND status = ?3",
+        params![id, status::PENDING, status::OFFLINE],
+    )?;
+    if claimed != 1 {
+        return Ok(None);
+    }
+    Ok(Some(conn.query_row(
+        "SELECT provider, kind, request_json FROM video_jobs WHERE id = ?1",
+        [id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+    )?))
+}
+
+/// Send one stored job to its provider, if it is still offline and nobody
+/// else has claimed it. Network failures put the job back into the offline
+/// queue instead of failing it; anything else is final. Returns false when
+/// the network dropped out.
+async fn submit_stored(app: &AppHandle, id: &str) -> AppResult<bool> {
+    let (provider, job_kind, request_json, api_key) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let Some((provider, job_kind, request_json)) = claim(&conn, id)? else {
+            return Ok(true);
+        };
+        let api_key = settings::get(&conn, providers::FAL_KEY_SETTING)?.unwrap_or_default();
+        (provider, job_kind, request_json, api_key)
+    };
+    emit_job(app, id);
+
+    let mut log = Vec::new();
+    let result = match job_kind.as_str() {
+        kind::LIPSYNC => match serde_json::from_str::<LipSyncRequest>(&request_json) {
+            Ok(request) => lipsync::submit(&api_key, &provider, &request, &mut log).await,
+            Err(e) => Err(e.into()),
+        },
+        _ => match serde_json::from_str::<GenerationRequest>(&request_json) {
+            Ok(request) if provider == mock::MODEL => {
+                let db = app.state::<Database>();
+                let conn = db.conn();
+                mock::submit(&conn, &request, &mut log)
+            }
+            Ok(request) => providers::submit(&api_key, &provider, &request, &mut log).await,
+            Err(e) => Err(e.into()),
+        },
+    };
+    let mut failed = Vec::new();
+    let online = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        job_logs::record(&conn, id, job_logs::phase::SUBMIT, &log, &[&api_key])?;
+        match &result {
+            Ok(external_id) => {
+                set_status(&conn, id, status::QUEUED, external_id, "")?;
+                true
+            }
+            Err(AppError::Network(_)) => {
+                app.state::<NetworkMonitor>().mark_offline();
+                set_status(&conn, id, status::OFFLINE, "", "")?;
+                false
+            }
+            Err(e) => {
+                let http_status = log.last().and_then(|x| x.status);
+                match failures::handle(&conn, id, &e.to_string(), http_status)? {
+                    Outcome::Retrying => true,
+                    Outcome::Paused => false,
+                    Outcome::Failed => {
+                        failed = fail_waiting(&conn, id)?;
+                        true
+                    }
+                }
+            }
+        }
+    };
+    for job_id in std::iter::once(id).chain(failed.iter().map(String::as_str)) {
+        emit_job(app, job_id);
+    }
+    Ok(online)
+}
+
+/// Whether a quota failure has paused the queue; see `failures`.
+fn is_paused(app: &AppHandle) -> bool {
+    let db = app.state::<Database>();
+    let paused = failures::queue_paused(&db.conn());
+    matches!(paused, Ok(Some(_)))
+}
+
+/// Submit every deferred job, oldest first, except those waiting out a
+/// retry delay. Does nothing while the queue is paused. Emits `offline-queue-flushing`
+/// with the number of jobs before the first one goes out.
+pub async fn flush_deferred(app: &AppHandle) {
+    let net = app.state::<NetworkMonitor>();
+    if !net.can_submit() || is_paused(app) || !net.begin_flush() {
+        return;
+    }
+
+    let ids: AppResult<Vec<String>> = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        conn.prepare(
+            "SELECT id FROM video_jobs WHERE status = ?1 AND (retry_at IS NULL OR retry_at <= ?2)
+             ORDER BY started_at, rowid",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map(params![status::OFFLINE, clock::now()], |r| r.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()
+        })
+        .map_err(AppError::from)
+    };
+
+    match ids {
+        Ok(ids) if !ids.is_empty() => {
+            AppEvent::OfflineQueueFlushing(ids.len()).emit(app);
+            for id in ids {
+                match submit_stored(app, &id).await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        tracing::error!(job_id = %id, error = %e, "failed to submit deferred job")
+                    }
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!(error = %e, "failed to read deferred jobs"),
+    }
+
+    net.end_flush();
+}
+
+/// Store a new job in the offline queue, where `flush_deferred` or the
+/// caller picks it up. Returns the job's id.
+pub fn insert_job(
+    conn: &Connection,
+    scene_id: &str,
+    provider: &str,
+    job_kind: &str,
+    request_json: &str,
+) -> AppResult<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO video_jobs (id, scene_id, provider, kind, job_id, status, request_json)
+         VALUES (?1, ?2, ?3, ?4, '', ?5, ?6)",
+        params![
+            id,
+            scene_id,
+            provider,
+            job_kind,
+            status::OFFLINE,
+            request_json
+        ],
+    )?;
+    if job_kind == kind::VIDEO {
+        // A new take is made from the scene as it is now.
+        conn.execute(
+            "UPDATE scenes SET stale_reason = '' WHERE id = ?1",
+            [scene_id],
+        )?;
+    }
+    notifications::batch_started(conn, scene_id)?;
+    activity::record_for_scene(
+        conn,
+        scene_id,
+        "take",
+        &id,
+        "queued",
+        &format!("Queued {} {} job", provider, job_kind),
+        &[],
+    )?;
+    let project_id: String = conn.query_row(
+        "SELECT project_id FROM scenes WHERE id = ?1",
+        [scene_id],
+        |r| r.get(0),
+    )?;
+    analytics::track(conn, &project_id, job_kind, provider);
+    Ok(id)
+}
+
+/// Hold a just-inserted job until every one of `upstream` has completed.
+pub fn hold(conn: &Connection, id: &str, upstream: &[String]) -> AppResult<()> {
+    if upstream.is_empty() {
+        return Ok(());
+    }
+    for upstream_id in upstream {
+        conn.execute(
+            "INSERT OR IGNORE INTO video_job_waits (job_id, upstream_job_id) VALUES (?1, ?2)",
+            params![id, upstream_id],
+        )?;
+    }
+    set_status(conn, id, status::WAITING, "", "")
+}
+
+/// Fail every job waiting on `id`, which failed, and everything waiting on
+/// those in turn. Returns the jobs failed.
+fn fail_waiting(conn: &Connection, id: &str) -> AppResult<Vec<String>> {
+    let mut failed = Vec::new();
+    let mut upstream = vec![id.to_string()];
+    while let Some(up) = upstream.pop() {
+        let scene_number: i64 = conn.query_row(
+            "SELECT s.scene_number FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+             WHERE j.id = ?1",
+            [&up],
+            |r| r.get(0),
+        )?;
+        let mut stmt = conn.prepare(
+            "SELECT w.job_id FROM video_job_waits w JOIN video_jobs j ON j.id = w.job_id
+             WHERE w.upstream_job_id = ?1 AND j.status = ?2",
+        )?;
+        let waiting = stmt
+            .query_map([up.as_str(), status::WAITING], |r| r.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        for job_id in waiting {
+            conn.execute("DELETE FROM video_job_waits WHERE job_id = ?1", [&job_id])?;
+            set_status(
+                conn,
+                &job_id,
+                status::FAILED,
+                "",
+                &format!("the take for scene {} it depends on failed", scene_number),
+            )?;
+            upstream.push(job_id.clone());
+            failed.push(job_id);
+        }
+    }
+    Ok(failed)
+}
+
+/// After `id` finishes, move on the jobs waiting on it: a failure fails
+/// them too; a completed take hands its end frame to linked scenes, and
+/// jobs with nothing left to wait on join the offline queue.
+async fn release_waiting(app: &AppHandle, id: &str) -> AppResult<()> {
+    let (job, waiting) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let job = get_job(&conn, id)?;
+        if job.status == status::FAILED {
+            let failed = fail_waiting(&conn, id)?;
+            drop(conn);
+            for job_id in failed {
+                emit_job(app, &job_id);
+            }
+            return Ok(());
+        }
+        let mut stmt = conn.prepare(
+            "SELECT j.id, j.scene_id FROM video_job_waits w JOIN video_jobs j ON j.id = w.job_id
+             WHERE w.upstream_job_id = ?1 AND j.status = ?2",
+        )?;
+        let waiting = stmt
+            .query_map([id, status::WAITING], |r| {
+                Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        (job, waiting)
+    };
+
+    let mut released = false;
+    for (waiting_id, scene_id) in waiting {
+        let frame = scene_links::end_frame_for(app, &scene_id, &job.scene_id, &job.video_url).await;
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let mut changed = vec![waiting_id.clone()];
+        match frame {
+            Ok(frame) => {
+                if let Some(frame) = frame {
+                    let request_json: String = conn.query_row(
+                        "SELECT request_json FROM video_jobs WHERE id = ?1",
+                        [&waiting_id],
+                        |r| r.get(0),
+                    )?;
+                    let mut request: GenerationRequest = serde_json::from_str(&request_json)?;
+                    request.image_url = Some(frame);
+                    conn.execute(
+                        "UPDATE video_jobs SET request_json = ?2 WHERE id = ?1",
+                        params![waiting_id, serde_json::to_string(&request)?],
+                    )?;
+                }
+                conn.execute(
+                    "DELETE FROM video_job_waits WHERE job_id = ?1 AND upstream_job_id = ?2",
+                    params![waiting_id, id],
+                )?;
+                let remaining: i64 = conn.query_row(
+                    "SELECT COUNT(*) FROM video_job_waits WHERE job_id = ?1",
+                    [&waiting_id],
+                    |r| r.get(0),
+                )?;
+                if remaining == 0 {
+                    set_status(&conn, &waiting_id, status::OFFLINE, "", "")?;
+                    released = true;
+                }
+            }
+            Err(e) => {
+                conn.execute(
+                    "DELETE FROM video_job_waits WHERE job_id = ?1",
+                    [&waiting_id],
+                )?;
+                set_status(&conn, &waiting_id, status::FAILED, "", &e.to_string())?;
+                changed.extend(fail_waiting(&conn, &waiting_id)?);
+            }
+        }
+        drop(conn);
+        for job_id in changed {
+            emit_job(app, &job_id);
+        }
+    }
+    if released && app.state::<NetworkMonitor>().can_submit() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { flush_deferred(&app).await });
+    }
+    Ok(())
+}
+
+/// Store a new job and submit it straight away, or leave it in the offline
+/// queue if we can't reach the provider right now.
+pub async fn create_and_dispatch(
+    app: &AppHandle,
+    scene_id: &str,
+    provider: &str,
+    job_kind: &str,
+    request_json: String,
+) -> AppResult<VideoJob> {
+    let online = app.state::<NetworkMonitor>().can_submit();
+    let id = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        projects::ensure_scene_unlocked(&conn, scene_id)?;
+        insert_job(&conn, scene_id, provider, job_kind, &request_json)?
+    };
+
+    workflow::advance_if_allowed(app, scene_id, SceneStatus::Generating);
+    if online && !is_paused(app) {
+        submit_stored(app, &id).await?;
+    } else {
+        emit_job(app, &id);
+    }
+
+    let db = app.state::<Database>();
+    let job = get_job(&db.conn(), &id);
+    job
+}
+
+/// Record a generation request for a scene and submit it, or hold it in the
+/// offline queue if we can't reach the provider right now.
+#[tauri::command]
+pub async fn queue_generation(
+    app: AppHandle,
+    scene_id: String,
+    provider: String,
+    request: GenerationRequest,
+) -> AppResult<VideoJob> {
+    providers::model(&provider)?;
+    moderation::precheck(&app, &scene_id, &request.prompt).await?;
+    create_and_dispatch(
+        &app,
+        &scene_id,
+        &provider,
+        kind::VIDEO,
+        serde_json::to_string(&request)?,
+    )
+    .await
+}
+
+/// Record a take the provider finished.
+pub fn complete(conn: &Connection, job: &VideoJob, video_url: &str) -> AppResult<()> {
+    conn.execute(
+        "UPDATE video_jobs SET status = ?2, video_url = ?3, completed_at = ?4
+         WHERE id = ?1",
+        params![job.id, status::COMPLETED, video_url, clock::now()],
+    )?;
+    activity::record_for_scene(
+        conn,
+        &job.scene_id,
+        "take",
+        &job.id,
+        "completed",
+        &format!("{} take finished", job.provider),
+        &[],
+    )
+}
+
+/// Record a take the provider turned down; see `failures::handle` for
+/// what happens to it.
+pub fn fail(
+    conn: &Connection,
+    job: &VideoJob,
+    error: &str,
+    http_status: Option<u16>,
+) -> AppResult<Outcome> {
+    let outcome = failures::handle(conn, &job.id, error, http_status)?;
+    if outcome == Outcome::Failed {
+        activity::record_for_scene(
+            conn,
+            &job.scene_id,
+            "take",
+            &job.id,
+            "failed",
+            &format!("{} take failed: {}", job.provider, error),
+            &[],
+        )?;
+    }
+    Ok(outcome)
+}
+
+/// Ask the provider how a queued job is doing and record the outcome.
+/// Emits `job-updated` when the status changes.
+#[tauri::command]
+pub async fn refresh_job(app: AppHandle, id: String) -> AppResult<VideoJob> {
+    let (job, api_key, ffmpeg) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let job = get_job(&conn, &id)?;
+        let api_key = settings::get(&conn, providers::FAL_KEY_SETTING)?.unwrap_or_default();
+        (job, api_key, frames::ffmpeg_binary(&conn)?)
+    };
+    if job.status != status::QUEUED {
+        return Ok(job);
+    }
+    let endpoint = match job.kind.as_str() {
+        kind::LIPSYNC => lipsync::model(&job.provider)?.endpoint,
+        _ => providers::model(&job.provider)?.text_endpoint,
+    };
+
+    let mut log = Vec::new();
+    let state = if job.provider == mock::MODEL {
+        mock::poll(&ffmpeg, &job.job_id, &mut log).await
+    } else {
+        providers::poll_fal(&api_key, endpoint, &job.job_id, &mut log).await
+    };
+    let http_status = log.last().and_then(|x| x.status);
+    // Polls that find the job still waiting aren't worth keeping.
+    if !matches!(state, Ok(QueueState::Waiting)) {
+        let db = app.state::<Database>();
+        job_logs::record(&db.conn(), &id, job_logs::phase::POLL, &log, &[&api_key])?;
+    }
+    match state? {
+        QueueState::Waiting => return Ok(job),
+        QueueState::Completed { video_url } => {
+            complete(&app.state::<Database>().conn(), &job, &video_url)?;
+            workflow::advance_if_allowed(&app, &job.scene_id, SceneStatus::Review);
+        }
+        QueueState::Failed(error) => {
+            let outcome = fail(&app.state::<Database>().conn(), &job, &error, http_status)?;
+            if outcome != Outcome::Failed {
+                // Back in the queue; nothing downstream has finished.
+                emit_job(&app, &id);
+                let db = app.state::<Database>();
+                let job = get_job(&db.conn(), &id);
+                return job;
+            }
+        }
+    }
+    if let Err(e) = release_waiting(&app, &id).await {
+        tracing::error!(job_id = %id, error = %e, "failed to release dependent jobs");
+    }
+    notifications::job_finished(&app, &id);
+    emit_job(&app, &id);
+    let db = app.state::<Database>();
+    let job = get_job(&db.conn(), &id);
+    job
+}
+
+/// How `list_scene_jobs` orders takes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum TakeSort {
+    #[default]
+    Newest,
+    /// Best rated first, unrated last; newest first within a rating.
+    Rating,
+}
+
+/// Every job for a scene across all job kinds, newest first unless sorted
+/// by rating. `min_rating` leaves out takes rated lower or not at all.
+pub fn list_for_scene(
+    conn: &Connection,
+    scene_id: &str,
+    min_rating: Option<i64>,
+    sort: TakeSort,
+) -> AppResult<Vec<VideoJob>> {
+    let order = match sort {
+        TakeSort::Newest => "started_at DESC, rowid DESC",
+        TakeSort::Rating => "rating IS NULL, rating DESC, started_at DESC, rowid DESC",
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM video_jobs WHERE scene_id = ?1 AND (?2 IS NULL OR rating >= ?2)
+         ORDER BY {}",
+        JOB_COLUMNS, order
+    ))?;
+    let jobs = stmt
+        .query_map(params![scene_id, min_rating], row_to_job)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+#[tauri::command]
+pub fn list_scene_jobs(
+    db: tauri::State<'_, Database>,
+    scene_id: String,
+    min_rating: Option<i64>,
+    sort: Option<TakeSort>,
+) -> AppResult<Vec<VideoJob>> {
+    list_for_scene(&db.conn(), &scene_id, min_rating, sort.unwrap_or_default())
+}
+
+/// Rate a take 1–5, or clear its rating with None.
+pub fn rate(conn: &Connection, id: &str, rating: Option<i64>) -> AppResult<VideoJob> {
+    let mut v = Validator::new();
+    v.uuid("id", id);
+    if let Some(rating) = rating {
+        v.range("rating", rating, 1, 5);
+    }
+    v.finish()?;
+    let job = get_job(conn, id)?;
+    projects::ensure_scene_unlocked(conn, &job.scene_id)?;
+    if job.rating == rating {
+        return Ok(job);
+    }
+    conn.execute(
+        "UPDATE video_jobs SET rating = ?2 WHERE id = ?1",
+        params![id, rating],
+    )?;
+    let shown = |r: Option<i64>| r.map(|r| r.to_string()).unwrap_or_default();
+    activity::record_for_scene(
+        conn,
+        &job.scene_id,
+        "take",
+        id,
+        "rated",
+        &match rating {
+            Some(r) => format!("Rated {} take {}/5", job.provider, r),
+            None => format!("Cleared rating on {} take", job.provider),
+        },
+        &[FieldChange {
+            field: "rating".into(),
+            old: shown(job.rating),
+            new: shown(rating),
+        }],
+    )?;
+    get_job(conn, id)
+}
+
+#[tauri::command]
+pub fn set_take_rating(
+    db: tauri::State<'_, Database>,
+    id: String,
+    rating: Option<i64>,
+) -> AppResult<VideoJob> {
+    rate(&db.conn(), &id, rating)
+}
+
+pub fn set_note(conn: &Connection, id: &str, note: &str) -> AppResult<VideoJob> {
+    let mut v = Validator::new();
+    v.uuid("id", id).max_len("note", note, MAX_TEXT_LEN);
+    v.finish()?;
+    let job = get_job(conn, id)?;
+    projects::ensure_scene_unlocked(conn, &job.scene_id)?;
+    let note = note.trim();
+    if job.note == note {
+        return Ok(job);
+    }
+    conn.execute(
+        "UPDATE video_jobs SET note = ?2 WHERE id = ?1",
+        params![id, note],
+    )?;
+    activity::record_for_scene(
+        conn,
+        &job.scene_id,
+        "take",
+        id,
+        "updated",
+        &format!("Edited note on {} take", job.provider),
+        &[FieldChange {
+            field: "note".into(),
+            old: job.note.clone(),
+            new: note.to_string(),
+        }],
+    )?;
+    get_job(conn, id)
+}
+
+#[tauri::command]
+pub fn set_take_note(
+    db: tauri::State<'_, Database>,
+    id: String,
+    note: String,
+) -> AppResult<VideoJob> {
+    set_note(&db.conn(), &id, &note)
+}
+
+/// Jobs that have not reached a provider yet (offline or mid-submit).
+pub fn deferred(conn: &Connection) -> AppResult<Vec<VideoJob>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM video_jobs WHERE status IN (?1, ?2) ORDER BY started_at, rowid",
+        JOB_COLUMNS
+    ))?;
+    let jobs = stmt
+        .query_map([status::OFFLINE, status::PENDING], row_to_job)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(jobs)
+}
+
+#[tauri::command]
+pub fn list_deferred_jobs(db: tauri::State<'_, Database>) -> AppResult<Vec<VideoJob>> {
+    deferred(&db.conn())
+}
@@ -0,0 +1,642 @@
+//! Live collaboration on the local network. One instance hosts a project:
+//! it listens for websockets and advertises itself over mDNS as
+//! `_aidc-collab._tcp`, so others on the LAN can find and join it.
+//!
+//! Joining loads the host's copy of the project in place of any local
+//! one. From then on each side streams its change-log rows (see `sync`)
+//! to the other as they are written and merges what it receives, last
+//! writer wins, so both ends converge without anyone stopping to resolve
+//! conflicts. The host relays between its guests. Merged rows go through
+//! the usual change log, so open windows refresh through `entity-changed`.
+//!
+//! Without invite tokens anyone on the network who can see the session
+//! can join it, much as with a shared folder, but only to watch. An
+//! invite token (see `invites`) decides what a guest may do, lowered to
+//! its device's role if the project lists the device (see `roles`). The
+//! host ignores edits from guests below editor, and tells each guest its
+//! role so its own commands refuse them up front. Projects with live
+//! tokens need one to join at all.
+
+use crate::bundle::{self, ProjectBundle};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::invites;
+use crate::projects::{self, ProjectSummary};
+use crate::roles::{self, Role};
+use crate::sync::{self, Changeset, Merge};
+use crate::users;
+use futures_util::{SinkExt, StreamExt};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message as Frame;
+use tokio_tungstenite::WebSocketStream;
+use ts_rs::TS;
+
+const SERVICE_TYPE: &str = "_aidc-collab._tcp.local.";
+/// Bumped when the messages below change incompatibly.
+const PROTOCOL: u32 = 2;
+/// How often local edits are sent to peers.
+const PUSH_INTERVAL: Duration = Duration::from_millis(300);
+const DEFAULT_DISCOVERY: Duration = Duration::from_secs(3);
+
+/// What peers send each other, one JSON text frame each.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// First from a guest: who it is and which project it wants.
+    Hello {
+        protocol: u32,
+        device_id: String,
+        name: String,
+        project_id: String,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// The host's answer: its copy of the project to start from, and what
+    /// the guest may do to it.
+    Welcome {
+        device_id: String,
+        name: String,
+        role: Role,
+        bundle: Box<ProjectBundle>,
+    },
+    Refused {
+        reason: String,
+    },
+    Changes {
+        changeset: Changeset,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct LanPeer {
+    pub device_id: String,
+    pub name: String,
+    /// The guest's role; for a guest's view of the host, its own role as
+    /// the host granted it.
+    pub role: Role,
+    /// The invite token the guest joined with; see `invites`.
+    pub token_id: Option<String>,
+}
+
+/// The session this instance is hosting or has joined.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LanSession {
+    /// `"host"` or `"guest"`.
+    pub role: String,
+    pub project_id: String,
+    /// `host:port` guests connect to.
+    pub address: String,
+    /// Everyone else in the session; a guest sees only the host.
+    pub peers: Vec<LanPeer>,
+}
+
+/// A hosted session found on the network.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LanHost {
+    pub name: String,
+    pub project_id: String,
+    pub project_name: String,
+    pub address: String,
+}
+
+struct Active {
+    session: LanSession,
+    /// Set to true to end the session's tasks.
+    stop: watch::Sender<bool>,
+    /// The advertisement, while hosting.
+    mdns: Option<ServiceDaemon>,
+}
+
+/// The current session, if any; registered as Tauri state.
+#[derive(Default)]
+pub struct LanState(Mutex<Option<Active>>);
+
+impl LanState {
+    fn with<T>(&self, f: impl FnOnce(&mut Option<Active>) -> T) -> T {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn session(&self) -> Option<LanSession> {
+        self.with(|active| active.as_ref().map(|a| a.session.clone()))
+    }
+}
+
+fn ws_err(e: tokio_tungstenite::tungstenite::Error) -> AppError {
+    AppError::Network(format!("LAN session: {}", e))
+}
+
+fn mdns_err(e: mdns_sd::Error) -> AppError {
+    AppError::Network(format!("LAN discovery: {}", e))
+}
+
+/// How this instance introduces itself: the active profile's name, else
+/// the OS user's.
+fn display_name(conn: &Connection) -> String {
+    users::active()
+        .and_then(|id| users::get_user(conn, &id).ok())
+        .map(|u| u.name)
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "Guest".into())
+}
+
+/// The address other machines reach this one on. Connecting a UDP socket
+/// sends nothing; it only picks the outgoing interface.
+fn local_ip() -> std::net::IpAddr {
+    std::net::UdpSocket::bind("0.0.0.0:0")
+        .and_then(|s| s.connect("192.0.2.1:9").map(|_| s))
+        .and_then(|s| s.local_addr())
+        .map(|a| a.ip())
+        .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST))
+}
+
+fn publish(app: &AppHandle) {
+    AppEvent::LanSessionChanged(app.state::<LanState>().session()).emit(app);
+}
+
+async fn send<S>(ws: &mut S, message: &Message) -> AppResult<()>
+where
+    S: SinkExt<Frame, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    ws.send(Frame::Text(serde_json::to_string(message)?))
+        .await
+        .map_err(ws_err)
+}
+
+async fn receive<S>(ws: &mut WebSocketStream<S>) -> AppResult<Option<Message>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    while let Some(frame) = ws.next().await {
+        match frame.map_err(ws_err)? {
+            Frame::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Frame::Close(_) => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Trade changes with one peer until either side leaves or the session
+/// stops. `cursor` is the last local log row the peer already has; the
+/// peer's edits are dropped unless it `may_edit`.
+async fn exchange<S>(
+    app: &AppHandle,
+    ws: WebSocketStream<S>,
+    project_id: &str,
+    peer: &LanPeer,
+    may_edit: bool,
+    mut cursor: i64,
+    mut stop: watch::Receiver<bool>,
+) -> AppResult<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut outgoing, mut incoming) = ws.split();
+    let mut tick = tokio::time::interval(PUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = stop.changed() => {
+                outgoing.close().await.ok();
+                return Ok(());
+            }
+            _ = tick.tick() => {
+                let (live, mut changeset) = {
+                    let db = app.state::<Database>();
+                    let conn = db.conn();
+                    let live = match &peer.token_id {
+                        Some(token_id) => invites::is_live(&conn, token_id)?,
+                        None => true,
+                    };
+                    let (changeset, last) = sync::changes_after(&conn, project_id, cursor)?;
+                    cursor = last;
+                    (live, changeset)
+                };
+                if !live {
+                    tracing::info!(peer = %peer.name, "dropped LAN guest whose token was revoked or expired");
+                    outgoing.close().await.ok();
+                    return Ok(());
+                }
+                // The peer already has its own edits.
+                changeset.changes.retain(|c| c.origin_device != peer.device_id);
+                if !changeset.changes.is_empty() {
+                    send(&mut outgoing, &Message::Changes { changeset }).await?;
+                }
+            }
+            frame = incoming.next() => {
+                let text = match frame {
+                    None => return Ok(()),
+                    Some(frame) => match frame.map_err(ws_err)? {
+                        Frame::Text(text) => text,
+                        Frame::Close(_) => return Ok(()),
+                        _ => continue,
+                    },
+                };
+                if let Message::Changes { changeset } = serde_json::from_str(&text)? {
+                    if changeset.project_id != project_id {
+                        continue;
+                    }
+                    if !may_edit {
+                        tracing::warn!(peer = %peer.name, "ignored edits from a LAN guest without edit access");
+                        continue;
+                    }
+                    let merged = {
+                        let db = app.state::<Database>();
+                        let conn = db.conn();
+                        sync::merge_changes(&conn, &changeset, Merge::LastWriter)
+                    };
+                    match merged {
+                        Ok(report) => {
+                            tracing::debug!(from = %peer.device_id, applied = report.applied, "merged LAN changes");
+                        }
+                        Err(AppError::Locked(reason)) => {
+                            tracing::warn!(peer = %peer.name, %reason, "ignored LAN changes to a locked project");
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Greet a guest and keep it in sync until it leaves.
+async fn serve(
+    app: AppHandle,
+    stream: tokio::net::TcpStream,
+    project_id: String,
+    stop: watch::Receiver<bool>,
+) -> AppResult<()> {
+    let mut ws = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(ws_err)?;
+    let Some(Message::Hello {
+        protocol,
+        device_id,
+        name,
+        project_id: wanted,
+        token,
+    }) = receive(&mut ws).await?
+    else {
+        return Ok(());
+    };
+    let refusal = if protocol != PROTOCOL {
+        Some("this session runs a different version of the app".to_string())
+    } else if wanted != project_id {
+        Some("this session is for a different project".to_string())
+    } else {
+        None
+    };
+    if let Some(reason) = refusal {
+        return send(&mut ws, &Message::Refused { reason }).await;
+    }
+
+    let admitted = admit(
+        &app.state::<Database>().conn(),
+        &project_id,
+        token.as_deref(),
+        &device_id,
+    );
+    let (token_id, role) = match admitted {
+        Ok(admitted) => admitted,
+        Err(AppError::Forbidden(reason)) => {
+            return send(&mut ws, &Message::Refused { reason }).await;
+        }
+        Err(e) => return Err(e),
+    };
+    let (welcome, cursor) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let (device_id, name) = identity(&conn)?;
+        let welcome = Message::Welcome {
+            device_id,
+            name,
+            role,
+            bundle: Box::new(bundle::export_bundle(&conn, &project_id)?),
+        };
+        (welcome, sync::latest_change(&conn)?)
+    };
+    send(&mut ws, &welcome).await?;
+    let peer = LanPeer {
+        device_id: device_id.clone(),
+        name,
+        role,
+        token_id,
+    };
+    tracing::info!(project_id = %project_id, peer = %peer.name, "guest joined LAN session");
+    let state = app.state::<LanState>();
+    state.with(|active| {
+        if let Some(active) = active {
+            active.session.peers.push(peer.clone());
+        }
+    });
+    publish(&app);
+
+    let may_edit = role >= Role::Editor;
+    let result = exchange(&app, ws, &project_id, &peer, may_edit, cursor, stop).await;
+    state.with(|active| {
+        if let Some(active) = active {
+            active.session.peers.retain(|p| p.device_id != device_id);
+        }
+    });
+    publish(&app);
+    result
+}
+
+/// Whether a guest asking to join `project_id` is let in, and as what:
+/// the token's id and role, capped by any role listed for the device.
+/// Forbidden when the token is wrong, or missing and the project needs one.
+pub fn admit(
+    conn: &Connection,
+    project_id: &str,
+    token: Option<&str>,
+    device_id: &str,
+) -> AppResult<(Option<String>, Role)> {
+    match token {
+        Some(token) => {
+            let (id, role) = invites::redeem(conn, project_id, token, device_id)?;
+            let listed = roles::listed_role(conn, project_id, "device", device_id)?;
+            Ok((Some(id), listed.map_or(role, |listed| role.min(listed))))
+        }
+        None if invites::required(conn, project_id)? => Err(AppError::Forbidden(
+            "this session needs an invite token".into(),
+        )),
+        // The device id is only the guest's word, so it grants nothing.
+        None => Ok((None, Role::Viewer)),
+    }
+}
+
+/// This instance's device id and the name it introduces itself by.
+pub fn identity(conn: &Connection) -> AppResult<(String, String)> {
+    Ok((sync::device_id(conn)?, display_name(conn)))
+}
+
+/// The project to host and this instance's `identity`; owners only.
+pub fn hosting(conn: &Connection, project_id: &str) -> AppResult<(ProjectSummary, String, String)> {
+    roles::ensure_role(conn, project_id, Role::Owner)?;
+    let project = projects::get_project(conn, project_id)?;
+    let (device_id, name) = identity(conn)?;
+    Ok((project, device_id, name))
+}
+
+/// Replace the local copy of the project with the host's, without
+/// recording it as local changes. Returns the change cursor to exchange
+/// edits from.
+pub fn take_host_copy(conn: &Connection, bundle: &ProjectBundle) -> AppResult<i64> {
+    sync::uncaptured(conn, || bundle::import_bundle(conn, bundle))?;
+    sync::latest_change(conn)
+}
+
+fn ensure_idle(state: &LanState) -> AppResult<()> {
+    if state.session().is_some() {
+        return Err(AppError::Invalid(
+            "already in a LAN session; leave it first".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Host the project for others on the network to join.
+#[tauri::command]
+pub async fn host_lan_session(
+    app: AppHandle,
+    state: State<'_, LanState>,
+    project_id: String,
+) -> AppResult<LanSession> {
+    ensure_idle(&state)?;
+    let (project, device_id, name) = hosting(&app.state::<Database>().conn(), &project_id)?;
+    let listener = TcpListener::bind("0.0.0.0:0").await?;
+    let port = listener.local_addr()?.port();
+
+    let mdns = ServiceDaemon::new().map_err(mdns_err)?;
+    let properties = HashMap::from([
+        ("name".to_string(), name),
+        ("project_id".to_string(), project.id.clone()),
+        ("project".to_string(), project.name.clone()),
+    ]);
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &device_id,
+        &format!("aidc-{}.local.", device_id),
+        (),
+        port,
+        properties,
+    )
+    .map_err(mdns_err)?
+    .enable_addr_auto();
+    mdns.register(service).map_err(mdns_err)?;
+
+    let (stop, stopped) = watch::channel(false);
+    let session = LanSession {
+        role: "host".into(),
+        project_id: project_id.clone(),
+        address: format!("{}:{}", local_ip(), port),
+        peers: Vec::new(),
+    };
+    state.with(|active| {
+        *active = Some(Active {
+            session: session.clone(),
+            stop,
+            mdns: Some(mdns),
+        })
+    });
+    tracing::info!(project_id = %project_id, address = %session.address, "hosting LAN session");
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut stop = stopped.clone();
+        loop {
+            tokio::select! {
+                _ = stop.changed() => break,
+                accepted = listener.accept() => {
+                    let Ok((stream, _)) = accepted else { continue };
+                    let app = handle.clone();
+                    let project_id = project_id.clone();
+                    let stopped = stopped.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = serve(app, stream, project_id, stopped).await {
+                            tracing::warn!(error = %e, "LAN guest connection ended");
+                        }
+                    });
+                }
+            }
+        }
+    });
+    publish(&app);
+    Ok(session)
+}
+
+/// Look for hosted sessions on the network for `timeout_ms` (default 3s).
+#[tauri::command]
+pub async fn discover_lan_sessions(timeout_ms: Option<u64>) -> AppResult<Vec<LanHost>> {
+    let mdns = ServiceDaemon::new().map_err(mdns_err)?;
+    let events = mdns.browse(SERVICE_TYPE).map_err(mdns_err)?;
+    let deadline =
+        tokio::time::Instant::now() + timeout_ms.map_or(DEFAULT_DISCOVERY, Duration::from_millis);
+    let mut hosts: Vec<LanHost> = Vec::new();
+    while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, events.recv_async()).await {
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        let Some(ip) = info
+            .get_addresses()
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .or_else(|| info.get_addresses().iter().next())
+        else {
+            continue;
+        };
+        let address = match ip {
+            std::net::IpAddr::V6(ip) => format!("[{}]:{}", ip, info.get_port()),
+            ip => format!("{}:{}", ip, info.get_port()),
+        };
+        let property = |key| {
+            info.get_property_val_str(key)
+                .unwrap_or_default()
+                .to_string()
+        };
+        if !hosts.iter().any(|h| h.address == address) {
+            hosts.push(LanHost {
+                name: property("name"),
+                project_id: property("project_id"),
+                project_name: property("project"),
+                address,
+            });
+        }
+    }
+    mdns.shutdown().ok();
+    Ok(hosts)
+}
+
+/// Join the session at `address` (from `discover_lan_sessions`), with
+/// the invite token the host gave if it needs one. The host's copy of the
+/// project replaces the local one.
+#[tauri::command]
+pub async fn join_lan_session(
+    app: AppHandle,
+    state: State<'_, LanState>,
+    address: String,
+    project_id: String,
+    token: Option<String>,
+) -> AppResult<LanSession> {
+    ensure_idle(&state)?;
+    let (device_id, name) = identity(&app.state::<Database>().conn())?;
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", address))
+        .await
+        .map_err(ws_err)?;
+    send(
+        &mut ws,
+        &Message::Hello {
+            protocol: PROTOCOL,
+            device_id,
+            name,
+            project_id: project_id.clone(),
+            token,
+        },
+    )
+    .await?;
+    let (host, cursor) = match receive(&mut ws).await? {
+        Some(Message::Welcome {
+            device_id,
+            name,
+            role,
+            bundle,
+        }) if bundle.project.id == project_id => {
+            let cursor = take_host_copy(&app.state::<Database>().conn(), &bundle)?;
+            roles::set_session_grant(Some((project_id.clone(), role)));
+            (
+                LanPeer {
+                    device_id,
+                    name,
+                    role,
+                    token_id: None,
+                },
+                cursor,
+            )
+        }
+        Some(Message::Refused { reason }) => {
+            return Err(AppError::Invalid(format!("the host refused: {}", reason)))
+        }
+        _ => {
+            return Err(AppError::Network(
+                "the host closed the connection before sending the project".into(),
+            ))
+        }
+    };
+
+    let (stop, stopped) = watch::channel(false);
+    let session = LanSession {
+        role: "guest".into(),
+        project_id: project_id.clone(),
+        address,
+        peers: vec![host.clone()],
+    };
+    state.with(|active| {
+        *active = Some(Active {
+            session: session.clone(),
+            stop,
+            mdns: None,
+        })
+    });
+    tracing::info!(project_id = %project_id, host = %host.name, "joined LAN session");
+
+    let handle = app.clone();
+    let left = stopped.clone();
+    tauri::async_runtime::spawn(async move {
+        // The host enforces the guest's role; what it sends is always kept.
+        if let Err(e) = exchange(&handle, ws, &project_id, &host, true, cursor, stopped).await {
+            tracing::warn!(error = %e, "LAN session ended");
+        }
+        // Unless we left, the host did or the link dropped; either way the
+        // session is over.
+        if !*left.borrow() {
+            roles::set_session_grant(None);
+            handle.state::<LanState>().with(|active| active.take());
+            publish(&handle);
+        }
+    });
+    publish(&app);
+    Ok(session)
+}
+
+/// Invite tokens guests of the hosted session joined with.
+pub fn connected_tokens(state: &LanState) -> Vec<String> {
+    state
+        .session()
+        .map(|s| s.peers.into_iter().filter_map(|p| p.token_id).collect())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_lan_session(state: State<'_, LanState>) -> Option<LanSession> {
+    state.session()
+}
+
+/// Stop hosting, or leave the joined session. The project stays as it is.
+#[tauri::command]
+pub fn leave_lan_session(app: AppHandle, state: State<'_, LanState>) {
+    let Some(active) = state.with(|active| active.take()) else {
+        return;
+    };
+    roles::set_session_grant(None);
+    active.stop.send(true).ok();
+    if let Some(mdns) = active.mdns {
+        mdns.shutdown().ok();
+    }
+    publish(&app);
+}
@@ -0,0 +1,485 @@
+//! The app as a library: every module, plus `run` to start the Tauri
+//! application. `main.rs` just calls `run`; integration tests in `tests/`
+//! use the modules directly against an in-memory database.
+
+pub mod activity;
+pub mod analytics;
+pub mod archive;
+pub mod backup;
+pub mod balances;
+pub mod bulk;
+pub mod bundle;
+pub mod character_fragments;
+pub mod character_packs;
+pub mod characters;
+pub mod clips;
+pub mod clock;
+pub mod cloud_sync;
+pub mod comments;
+pub mod confidential;
+pub mod contact_sheet;
+pub mod continuity;
+pub mod data_dir;
+pub mod db;
+pub mod deep_link;
+pub mod delete_preview;
+pub mod diagnostics;
+pub mod diff;
+pub mod drafts;
+pub mod email;
+pub mod encryption;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod export_presets;
+pub mod external_editor;
+pub mod failures;
+pub mod frames;
+pub mod generation;
+pub mod handoff;
+pub mod i18n;
+pub mod ics;
+pub mod inbox;
+pub mod integrations;
+pub mod invites;
+pub mod job_logs;
+pub mod jobs;
+pub mod lan;
+pub mod library;
+pub mod lipsync;
+pub mod llm;
+pub mod logging;
+pub mod migration_assistant;
+pub mod milestones;
+pub mod mirror;
+pub mod mock;
+pub mod moderation;
+pub mod moodboards;
+pub mod network;
+pub mod notifications;
+pub mod numbering;
+pub mod oauth;
+pub mod ordering;
+pub mod paging;
+pub mod pdf;
+pub mod pitch_deck;
+pub mod presets;
+pub mod previews;
+pub mod project_file;
+pub mod projects;
+pub mod prompts;
+pub mod props;
+pub mod provenance;
+pub mod provider_cache;
+pub mod providers;
+pub mod publishing;
+pub mod quick_capture;
+pub mod recovery;
+pub mod redaction;
+pub mod references;
+pub mod reports;
+pub mod review_upload;
+pub mod roles;
+pub mod runtime;
+pub mod scene_characters;
+pub mod scene_graph;
+pub mod scene_import;
+pub mod scene_links;
+pub mod scenes;
+pub mod schedule;
+pub mod screenplay;
+pub mod search;
+pub mod secrets;
+pub mod settings;
+pub mod settings_profiles;
+pub mod social_cuts;
+pub mod spellcheck;
+pub mod split_merge;
+pub mod stale;
+pub mod stats;
+pub mod sync;
+pub mod tags;
+pub mod updater;
+pub mod users;
+pub mod validation;
+pub mod versioning;
+pub mod vocabulary;
+pub mod voices;
+pub mod watch_folder;
+pub mod windows;
+pub mod workflow;
+pub mod workspaces;
+
+use db::{get_db_path, Database};
+use network::NetworkMonitor;
+use tauri::Manager;
+
+#[tauri::command]
+fn get_app_data_dir() -> String {
+    db::app_dir().to_string_lossy().to_string()
+}
+
+/// Open the active workspace database and run the app until it exits.
+pub fn run() {
+    let log_guard = logging::init();
+    let db_path = get_db_path();
+
+    let database = match Database::open(&db_path) {
+        Ok(db) => {
+            tracing::info!(path = ?db_path, "database initialized");
+            db
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "failed to initialize database");
+            drop(log_guard);
+            std::process::exit(1);
+        }
+    };
+    let network = NetworkMonitor::new(network::load_forced_offline(&database));
+
+    tauri::Builder::default()
+        // Must come first so a second launch hands its link to this one.
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.set_focus();
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(quick_capture::on_shortcut)
+                .build(),
+        )
+        .manage(database)
+        .manage(network)
+        .manage(external_editor::ExternalEdits::default())
+        .manage(spellcheck::Dictionaries::default())
+        .manage(lan::LanState::default())
+        .setup(|app| {
+            #[cfg(debug_assertions)]
+            {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.open_devtools();
+                }
+            }
+            recovery::setup(app);
+            network::spawn_monitor(app.handle().clone());
+            backup::spawn_scheduler(app.handle().clone());
+            windows::spawn_broadcaster(app.handle().clone());
+            ordering::spawn_rebalancer(app.handle().clone());
+            mirror::spawn_writer(app.handle().clone());
+            deep_link::setup(app);
+            updater::setup(app);
+            quick_capture::setup(app);
+            watch_folder::setup(app);
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            get_app_data_dir,
+            settings::get_setting,
+            settings::set_setting,
+            network::get_network_status,
+            network::set_offline_mode,
+            jobs::queue_generation,
+            jobs::list_deferred_jobs,
+            jobs::refresh_job,
+            jobs::list_scene_jobs,
+            moderation::moderate_scenes,
+            moderation::moderate_prompt,
+            continuity::check_continuity,
+            continuity::list_continuity_notes,
+            continuity::resolve_continuity_note,
+            presets::list_style_presets,
+            presets::create_style_preset,
+            presets::update_style_preset,
+            presets::delete_style_preset,
+            presets::set_project_style_preset,
+            prompts::enhance_prompt,
+            scenes::list_scenes,
+            scenes::get_scene_by_id,
+            scenes::save_scene,
+            scenes::delete_scene,
+            vocabulary::get_vocabulary,
+            vocabulary::save_vocabulary_term,
+            vocabulary::delete_vocabulary_term,
+            characters::list_characters,
+            characters::save_character,
+            characters::delete_character,
+            characters::set_character_voice,
+            voices::list_voice_samples,
+            voices::add_voice_sample,
+            voices::remove_voice_sample,
+            voices::clone_character_voice,
+            voices::synthesize_character_speech,
+            lipsync::queue_lipsync,
+            moodboards::list_moodboards,
+            moodboards::create_moodboard,
+            moodboards::update_moodboard,
+            moodboards::delete_moodboard,
+            moodboards::list_moodboard_items,
+            moodboards::add_moodboard_item,
+            moodboards::update_moodboard_item,
+            moodboards::reorder_moodboard_items,
+            moodboards::delete_moodboard_item,
+            moodboards::set_scene_conditioning_from_moodboard,
+            references::fetch_reference,
+            clips::get_ytdlp_status,
+            clips::acknowledge_reference_notice,
+            clips::import_reference_clip,
+            clips::list_reference_clips,
+            clips::delete_reference_clip,
+            comments::list_comments,
+            comments::add_comment,
+            comments::edit_comment,
+            comments::resolve_comment,
+            comments::delete_comment,
+            workflow::get_scene_workflow,
+            workflow::transition_scene,
+            workflow::bulk_transition_scenes,
+            activity::get_activity,
+            stats::get_project_stats,
+            export::export_html,
+            contact_sheet::export_contact_sheet,
+            pitch_deck::export_pitch_deck,
+            reports::get_character_breakdown,
+            reports::get_location_breakdown,
+            reports::get_status_breakdown,
+            reports::export_breakdown_csv,
+            schedule::list_shooting_days,
+            schedule::save_shooting_day,
+            schedule::delete_shooting_day,
+            schedule::set_shooting_day_scenes,
+            schedule::export_call_sheet,
+            schedule::export_shooting_day_ics,
+            schedule::import_shooting_days_ics,
+            milestones::list_milestones,
+            milestones::save_milestone,
+            milestones::delete_milestone,
+            milestones::export_milestones_ics,
+            notifications::list_webhooks,
+            notifications::save_webhook,
+            notifications::delete_webhook,
+            notifications::test_webhook,
+            notifications::notify_render_finished,
+            email::get_email_settings,
+            email::save_email_settings,
+            email::send_test_email,
+            deep_link::take_pending_deep_link,
+            deep_link::get_deep_link,
+            updater::get_update_channel,
+            updater::set_update_channel,
+            updater::check_for_update,
+            updater::install_update,
+            updater::restart_app,
+            logging::get_recent_logs,
+            logging::export_support_bundle,
+            analytics::get_analytics_settings,
+            analytics::save_analytics_settings,
+            analytics::get_usage_summary,
+            analytics::purge_usage_data,
+            analytics::send_usage_report,
+            recovery::take_recovery_report,
+            search::global_search,
+            projects::get_recent_projects,
+            projects::mark_project_opened,
+            projects::set_project_pinned,
+            workspaces::list_workspaces,
+            workspaces::create_workspace,
+            workspaces::rename_workspace,
+            workspaces::switch_workspace,
+            workspaces::delete_workspace,
+            data_dir::get_data_dir,
+            data_dir::move_data_dir,
+            encryption::get_encryption_status,
+            encryption::enable_encryption,
+            encryption::disable_encryption,
+            encryption::change_encryption_passphrase,
+            backup::get_backup_settings,
+            backup::save_backup_settings,
+            backup::run_backup,
+            backup::list_backups,
+            backup::restore_backup,
+            cloud_sync::get_cloud_storage_status,
+            cloud_sync::set_cloud_folder,
+            cloud_sync::connect_cloud_storage,
+            cloud_sync::disconnect_cloud_storage,
+            cloud_sync::get_project_sync_status,
+            cloud_sync::push_project_bundle,
+            cloud_sync::pull_project_bundle,
+            sync::get_sync_device,
+            sync::export_sync_changes,
+            sync::import_sync_changes,
+            sync::list_sync_conflicts,
+            sync::resolve_sync_conflict,
+            character_packs::export_character_pack,
+            character_packs::read_character_pack,
+            character_packs::import_character_pack,
+            library::list_library_assets,
+            library::add_library_asset,
+            library::update_library_asset,
+            library::delete_library_asset,
+            library::link_library_asset,
+            library::unlink_library_asset,
+            library::list_project_assets,
+            windows::open_project_window,
+            scenes::list_scenes_page,
+            characters::list_characters_page,
+            bulk::bulk_delete_scenes,
+            bulk::bulk_assign_characters,
+            bulk::bulk_queue_generation,
+            projects::update_project,
+            scene_graph::save_scene_graph,
+            runtime::estimate_runtime,
+            runtime::get_runtime_settings,
+            runtime::save_runtime_settings,
+            numbering::renumber_scenes,
+            ordering::move_scene,
+            scene_links::list_scene_links,
+            scene_links::create_scene_link,
+            scene_links::delete_scene_link,
+            frames::chain_end_frame,
+            jobs::set_take_rating,
+            jobs::set_take_note,
+            generation::get_project_generation_defaults,
+            generation::set_project_generation_defaults,
+            generation::set_scene_generation_overrides,
+            generation::get_effective_generation_settings,
+            tags::list_scene_tags,
+            tags::set_scene_tags,
+            tags::auto_tag_scenes,
+            diff::diff_text,
+            diff::diff_scene_versions,
+            split_merge::split_scene,
+            split_merge::merge_scenes,
+            scene_characters::list_scene_cast,
+            scene_characters::set_scene_character,
+            scene_characters::remove_scene_character,
+            scene_characters::list_scenes_featuring,
+            props::list_props,
+            props::save_prop,
+            props::delete_prop,
+            props::list_prop_links,
+            props::attach_prop,
+            props::detach_prop,
+            props::list_scene_details,
+            character_fragments::list_character_fragments,
+            character_fragments::list_fragment_versions,
+            character_fragments::save_character_fragment,
+            character_fragments::delete_character_fragment,
+            stale::list_stale_scenes,
+            stale::dismiss_stale_scene,
+            stale::regenerate_stale_scenes,
+            job_logs::get_job_debug_info,
+            failures::get_failure_summary,
+            failures::resume_queue,
+            balances::get_provider_balances,
+            oauth::get_provider_accounts,
+            oauth::connect_provider_account,
+            oauth::disconnect_provider_account,
+            mock::get_mock_settings,
+            mock::save_mock_settings,
+            diagnostics::run_diagnostics,
+            migration_assistant::preview_migration,
+            migration_assistant::list_migration_backups,
+            migration_assistant::rollback_migration,
+            settings_profiles::export_settings_profile,
+            settings_profiles::read_settings_profile,
+            settings_profiles::import_settings_profile,
+            users::list_users,
+            users::save_user,
+            users::select_user,
+            users::delete_user,
+            projects::lock_project,
+            projects::unlock_project,
+            drafts::save_draft,
+            drafts::list_drafts,
+            drafts::discard_draft,
+            i18n::get_translations,
+            i18n::list_locales,
+            screenplay::list_screenplay_conventions,
+            screenplay::get_screenplay_format,
+            screenplay::set_screenplay_format,
+            screenplay::export_screenplay_pdf,
+            screenplay::export_fountain,
+            spellcheck::check_text,
+            spellcheck::list_dictionary_words,
+            spellcheck::add_dictionary_word,
+            spellcheck::remove_dictionary_word,
+            inbox::capture_idea,
+            quick_capture::get_quick_capture_shortcut,
+            quick_capture::set_quick_capture_shortcut,
+            inbox::list_ideas,
+            inbox::add_idea,
+            inbox::update_idea,
+            inbox::delete_idea,
+            inbox::convert_idea_to_scene,
+            inbox::convert_idea_to_project,
+            watch_folder::get_watch_folder,
+            watch_folder::set_watch_folder,
+            external_editor::open_in_external_editor,
+            external_editor::close_external_editor,
+            prompts::copy_scene_prompt,
+            prompts::export_project_prompts,
+            scene_import::preview_scene_import,
+            scene_import::import_scenes,
+            integrations::set_integration_token,
+            integrations::get_integration_status,
+            integrations::get_integration_link,
+            integrations::set_integration_link,
+            integrations::sync_integration,
+            review_upload::get_review_settings,
+            review_upload::set_review_settings,
+            review_upload::upload_takes_for_review,
+            review_upload::upload_render_for_review,
+            publishing::connect_publish_account,
+            publishing::get_publish_accounts,
+            publishing::disconnect_publish_account,
+            publishing::list_renders,
+            publishing::publish_render,
+            social_cuts::get_scene_focus,
+            social_cuts::set_scene_focus,
+            social_cuts::generate_social_cut,
+            previews::export_scene_gif,
+            previews::export_scene_clip,
+            export_presets::list_export_presets,
+            export_presets::create_export_preset,
+            export_presets::update_export_preset,
+            export_presets::delete_export_preset,
+            review_upload::export_review_copy,
+            archive::archive_project,
+            archive::unarchive_project,
+            delete_preview::preview_delete,
+            project_file::save_project_file,
+            project_file::open_project_file,
+            mirror::get_project_mirror,
+            mirror::set_project_mirror,
+            lan::host_lan_session,
+            lan::discover_lan_sessions,
+            lan::join_lan_session,
+            lan::get_lan_session,
+            lan::leave_lan_session,
+            roles::list_project_members,
+            roles::get_my_role,
+            roles::set_project_member,
+            invites::issue_invite_token,
+            invites::list_sessions,
+            invites::revoke_token,
+            handoff::export_encrypted_bundle,
+            handoff::import_encrypted_bundle,
+            redaction::get_redaction_policy,
+            redaction::set_redaction_policy,
+            redaction::set_prompt_confidential,
+            confidential::set_project_confidential,
+            provider_cache::clear_provider_cache,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_, event| {
+            if let tauri::RunEvent::Exit = event {
+                recovery::end_session();
+            }
+        });
+}
@@ -0,0 +1,415 @@
+//! The shared asset library: reference images, LUTs, music and sound
+//! effects kept once in the app data dir and linked into any number of
+//! projects, in any workspace.
+//!
+//! Assets are content-addressed; an asset's id is the SHA-256 of its file,
+//! so the same file added twice is stored once and a project link made on
+//! one machine resolves on another that has the same file. Links live in
+//! each workspace's `project_assets` table and are carried in bundles by id.
+
+use crate::backup;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::ics;
+use crate::projects;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+use ts_rs::TS;
+
+const INDEX_FILE: &str = "index.json";
+
+/// Serializes read-modify-write of the index.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum AssetKind {
+    ReferenceImage,
+    Lut,
+    Music,
+    Sfx,
+}
+
+impl AssetKind {
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            AssetKind::ReferenceImage => &["png", "jpg", "jpeg", "webp", "gif"],
+            AssetKind::Lut => &["cube", "3dl"],
+            AssetKind::Music | AssetKind::Sfx => &["mp3", "wav", "m4a", "ogg", "flac", "aac"],
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssetKind::ReferenceImage => "reference_image",
+            AssetKind::Lut => "lut",
+            AssetKind::Music => "music",
+            AssetKind::Sfx => "sfx",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LibraryAsset {
+    /// SHA-256 of the file, hex.
+    pub id: String,
+    pub kind: AssetKind,
+    pub name: String,
+    /// Name of the file it was added from.
+    pub file_name: String,
+    pub ext: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub added_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Index {
+    assets: Vec<LibraryAsset>,
+}
+
+/// A project's link to a library asset. `asset` and `path` are missing
+/// when this machine's library doesn't have the file.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LinkedAsset {
+    pub asset_id: String,
+    pub kind: String,
+    pub name: String,
+    pub linked_at: String,
+    pub asset: Option<LibraryAsset>,
+    pub path: Option<String>,
+}
+
+/// A project's link as carried in a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetLink {
+    pub asset_id: String,
+    pub kind: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+pub fn library_dir() -> PathBuf {
+    db::app_dir().join("library")
+}
+
+fn index_path(dir: &Path) -> PathBuf {
+    dir.join(INDEX_FILE)
+}
+
+fn load(dir: &Path) -> Index {
+    std::fs::read_to_string(index_path(dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Write via a temp file and rename so a crash can't leave half an index.
+fn save(dir: &Path, index: &Index) -> AppResult<()> {
+    let path = index_path(dir);
+    std::fs::create_dir_all(dir)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(index)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+fn file_path(dir: &Path, asset: &LibraryAsset) -> PathBuf {
+    dir.join("files")
+        .join(format!("{}.{}", asset.id, asset.ext))
+}
+
+pub fn get_asset(dir: &Path, id: &str) -> AppResult<LibraryAsset> {
+    load(dir)
+        .assets
+        .into_iter()
+        .find(|a| a.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("library asset {}", id)))
+}
+
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !out.contains(&tag) {
+            out.push(tag);
+        }
+    }
+    out
+}
+
+pub fn links_for_project(conn: &Connection, project_id: &str) -> AppResult<Vec<AssetLink>> {
+    let mut stmt = conn.prepare(
+        "SELECT asset_id, kind, name FROM project_assets WHERE project_id = ?1
+         ORDER BY linked_at, asset_id",
+    )?;
+    let links = stmt
+        .query_map([project_id], |r| {
+            Ok(AssetLink {
+                asset_id: r.get(0)?,
+                kind: r.get(1)?,
+                name: r.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(links)
+}
+
+/// Replace a project's links with `links`, e.g. from an imported bundle.
+pub fn replace_links(conn: &Connection, project_id: &str, links: &[AssetLink]) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM project_assets WHERE project_id = ?1",
+        [project_id],
+    )?;
+    for link in links {
+        conn.execute(
+            "INSERT OR IGNORE INTO project_assets (project_id, asset_id, kind, name)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![project_id, link.asset_id, link.kind, link.name],
+        )?;
+    }
+    Ok(())
+}
+
+/// Library assets, newest first, optionally of one kind and matching
+/// `query` against name and tags.
+/// The library in `dir` (see `library_dir`), newest first, optionally of
+/// one kind and matching `query` by name or tag.
+pub fn list(dir: &Path, kind: Option<AssetKind>, query: &str) -> Vec<LibraryAsset> {
+    let query = query.trim().to_lowercase();
+    let mut assets: Vec<LibraryAsset> = load(dir)
+        .assets
+        .into_iter()
+        .filter(|a| kind.map_or(true, |k| a.kind == k))
+        .filter(|a| {
+            query.is_empty()
+                || a.name.to_lowercase().contains(&query)
+                || a.tags.iter().any(|t| t.contains(&query))
+        })
+        .collect();
+    assets.sort_by(|a, b| b.added_at.cmp(&a.added_at));
+    assets
+}
+
+#[tauri::command]
+pub fn list_library_assets(kind: Option<AssetKind>, query: Option<String>) -> Vec<LibraryAsset> {
+    list(&library_dir(), kind, query.as_deref().unwrap_or_default())
+}
+
+/// Copy a file into the library. Adding a file that is already there
+/// returns the existing asset unchanged.
+pub fn add(
+    dir: &Path,
+    source: &Path,
+    kind: AssetKind,
+    name: Option<&str>,
+    tags: Vec<String>,
+) -> AppResult<LibraryAsset> {
+    let ext = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    if !kind.extensions().contains(&ext.as_str()) {
+        return Err(AppError::Invalid(format!(
+            "'{}' files can't be added as {}",
+            ext,
+            kind.as_str()
+        )));
+    }
+    let bytes = std::fs::read(source)?;
+    let id = backup::sha256_hex(&bytes);
+
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut index = load(dir);
+    if let Some(existing) = index.assets.iter().find(|a| a.id == id) {
+        return Ok(existing.clone());
+    }
+    let file_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let asset = LibraryAsset {
+        id,
+        kind,
+        name: name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| {
+                source
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            }),
+        file_name,
+        ext,
+        size: bytes.len() as u64,
+        tags: clean_tags(tags),
+        added_at: ics::utc_stamp(),
+    };
+    let dest = file_path(dir, &asset);
+    std::fs::create_dir_all(dir.join("files"))?;
+    std::fs::write(&dest, &bytes)?;
+    index.assets.push(asset.clone());
+    save(dir, &index)?;
+    Ok(asset)
+}
+
+#[tauri::command]
+pub fn add_library_asset(
+    source_path: String,
+    kind: AssetKind,
+    name: Option<String>,
+    tags: Option<Vec<String>>,
+) -> AppResult<LibraryAsset> {
+    add(
+        &library_dir(),
+        Path::new(&source_path),
+        kind,
+        name.as_deref(),
+        tags.unwrap_or_default(),
+    )
+}
+
+pub fn update(dir: &Path, id: &str, name: &str, tags: Vec<String>) -> AppResult<LibraryAsset> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Invalid("asset name is required".into()));
+    }
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut index = load(dir);
+    let asset = index
+        .assets
+        .iter_mut()
+        .find(|a| a.id == id)
+        .ok_or_else(|| AppError::NotFound(format!("library asset {}", id)))?;
+    asset.name = name.to_string();
+    asset.tags = clean_tags(tags);
+    let asset = asset.clone();
+    save(dir, &index)?;
+    Ok(asset)
+}
+
+#[tauri::command]
+pub fn update_library_asset(
+    id: String,
+    name: String,
+    tags: Vec<String>,
+) -> AppResult<LibraryAsset> {
+    update(&library_dir(), &id, &name, tags)
+}
+
+/// Remove an asset from the library and unlink it from this workspace's
+/// projects. Links in other workspaces show it as missing.
+pub fn delete(conn: &Connection, dir: &Path, id: &str) -> AppResult<()> {
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut index = load(dir);
+    let Some(pos) = index.assets.iter().position(|a| a.id == id) else {
+        return Err(AppError::NotFound(format!("library asset {}", id)));
+    };
+    let asset = index.assets.remove(pos);
+    save(dir, &index)?;
+    let _ = std::fs::remove_file(file_path(dir, &asset));
+    conn.execute("DELETE FROM project_assets WHERE asset_id = ?1", [id])?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_library_asset(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &library_dir(), &id)
+}
+
+pub fn link(conn: &Connection, dir: &Path, project_id: &str, asset_id: &str) -> AppResult<()> {
+    let asset = get_asset(dir, asset_id)?;
+    projects::ensure_unlocked(conn, project_id)?;
+    conn.execute(
+        "INSERT INTO project_assets (project_id, asset_id, kind, name) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_id, asset_id) DO UPDATE SET kind = excluded.kind,
+            name = excluded.name",
+        params![project_id, asset.id, asset.kind.as_str(), asset.name],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn link_library_asset(
+    db: State<'_, Database>,
+    project_id: String,
+    asset_id: String,
+) -> AppResult<()> {
+    link(&db.conn(), &library_dir(), &project_id, &asset_id)
+}
+
+pub fn unlink(conn: &Connection, project_id: &str, asset_id: &str) -> AppResult<()> {
+    projects::ensure_unlocked(conn, project_id)?;
+    conn.execute(
+        "DELETE FROM project_assets WHERE project_id = ?1 AND asset_id = ?2",
+        params![project_id, asset_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unlink_library_asset(
+    db: State<'_, Database>,
+    project_id: String,
+    asset_id: String,
+) -> AppResult<()> {
+    unlink(&db.conn(), &project_id, &asset_id)
+}
+
+/// A project's linked assets with their library details and file paths.
+pub fn list_for_project(
+    conn: &Connection,
+    dir: &Path,
+    project_id: &str,
+) -> AppResult<Vec<LinkedAsset>> {
+    let mut stmt = conn.prepare(
+        "SELECT asset_id, kind, name, linked_at FROM project_assets WHERE project_id = ?1
+         ORDER BY linked_at, asset_id",
+    )?;
+    let rows = stmt
+        .query_map([project_id], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(String, String, String, String)>>>()?;
+    let index = load(dir);
+    Ok(rows
+        .into_iter()
+        .map(|(asset_id, kind, name, linked_at)| {
+            let asset = index.assets.iter().find(|a| a.id == asset_id).cloned();
+            let path = asset
+                .as_ref()
+                .map(|a| file_path(dir, a))
+                .filter(|p| p.exists())
+                .map(|p| p.to_string_lossy().to_string());
+            LinkedAsset {
+                asset_id,
+                kind,
+                name,
+                linked_at,
+                asset,
+                path,
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn list_project_assets(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<LinkedAsset>> {
+    list_for_project(&db.conn(), &library_dir(), &project_id)
+}
@@ -0,0 +1,151 @@
+use crate::characters;
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::job_logs::Exchange;
+use crate::jobs::{self, VideoJob};
+use crate::providers;
+use crate::voices;
+use base64::Engine as _;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+/// Talking-head models: a still portrait plus an audio track in, a video of
+/// the portrait speaking out.
+pub struct LipSyncSpec {
+    pub key: &'static str,
+    pub endpoint: &'static str,
+    image_field: &'static str,
+    audio_field: &'static str,
+}
+
+pub const LIPSYNC_MODELS: &[LipSyncSpec] = &[
+    LipSyncSpec {
+        key: "sadtalker",
+        endpoint: "fal-ai/sadtalker",
+        image_field: "source_image_url",
+        audio_field: "driven_audio_url",
+    },
+    LipSyncSpec {
+        key: "omnihuman-lipsync",
+        endpoint: "fal-ai/omnihuman",
+        image_field: "image_url",
+        audio_field: "audio_url",
+    },
+];
+
+pub fn model(key: &str) -> AppResult<&'static LipSyncSpec> {
+    LIPSYNC_MODELS
+        .iter()
+        .find(|m| m.key == key)
+        .ok_or_else(|| AppError::Invalid(format!("unknown lip-sync provider '{}'", key)))
+}
+
+/// Stored in `video_jobs.request_json` for lip-sync jobs. The audio stays a
+/// local path until submit so offline-queued jobs don't carry the payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LipSyncRequest {
+    pub character_id: String,
+    /// Portrait as an http(s) URL or data URI.
+    pub image_url: String,
+    pub audio_path: String,
+}
+
+fn audio_data_uri(path: &str) -> AppResult<String> {
+    let mime = match Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("wav") => "audio/wav",
+        Some("m4a") => "audio/mp4",
+        Some("ogg") => "audio/ogg",
+        _ => "audio/mpeg",
+    };
+    let bytes = std::fs::read(path)?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+pub async fn submit(
+    api_key: &str,
+    provider: &str,
+    req: &LipSyncRequest,
+    log: &mut Vec<Exchange>,
+) -> AppResult<String> {
+    let spec = model(provider)?;
+    let mut body = json!({});
+    body[spec.image_field] = Value::String(req.image_url.clone());
+    body[spec.audio_field] = Value::String(audio_data_uri(&req.audio_path)?);
+    providers::submit_fal(api_key, provider, spec.endpoint, &body, log).await
+}
+
+/// The character's reference photo and the scene's dialog, for a
+/// lip-sync take of the scene.
+pub fn sources(
+    conn: &Connection,
+    scene_id: &str,
+    character_id: &str,
+) -> AppResult<(String, String)> {
+    let character = characters::get_character(conn, character_id)?;
+    let (project_id, dialog): (String, String) = conn.query_row(
+        "SELECT project_id, dialog FROM scenes WHERE id = ?1",
+        [scene_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+    confidential::ensure_remote_allowed(conn, &project_id, "lip-sync providers")?;
+    if character.photo_data.is_empty() {
+        return Err(AppError::Invalid(
+            "character needs a reference photo for lip-sync".into(),
+        ));
+    }
+    Ok((character.photo_data, dialog))
+}
+
+/// Queue a talking-head clip for a scene: the character's reference photo
+/// lip-synced to either an existing audio file or freshly synthesized TTS
+/// of `text` (defaulting to the scene's dialog) in the character's voice.
+#[tauri::command]
+pub async fn queue_lipsync(
+    app: AppHandle,
+    scene_id: String,
+    character_id: String,
+    provider: String,
+    text: Option<String>,
+    audio_path: Option<String>,
+) -> AppResult<VideoJob> {
+    model(&provider)?;
+    let (photo, dialog) = sources(&app.state::<Database>().conn(), &scene_id, &character_id)?;
+
+    let audio_path = match audio_path {
+        Some(path) => path,
+        None => {
+            let text = text.unwrap_or(dialog);
+            voices::synthesize(&app, &character_id, &text)
+                .await?
+                .file_path
+        }
+    };
+
+    let request = LipSyncRequest {
+        character_id,
+        image_url: photo,
+        audio_path,
+    };
+    jobs::create_and_dispatch(
+        &app,
+        &scene_id,
+        &provider,
+        jobs::kind::LIPSYNC,
+        serde_json::to_string(&request)?,
+    )
+    .await
+}
@@ -0,0 +1,252 @@
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::oauth::{self, OAuthClient};
+use crate::provider_cache;
+use crate::providers;
+use crate::settings;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const GEMINI_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+const GEMINI_MODEL: &str = "gemini-2.0-flash";
+
+/// Settings key holding the Gemini API key.
+pub const GEMINI_KEY_SETTING: &str = "api_key.gemini";
+/// Settings keys for a model served on this machine by Ollama, e.g.
+/// `http://127.0.0.1:11434` and `llama3.1`.
+pub const LOCAL_URL_SETTING: &str = "llm.local_url";
+pub const LOCAL_MODEL_SETTING: &str = "llm.local_model";
+
+/// The seed for analysis passes (continuity, tagging), so the same
+/// project state gets the same findings.
+pub const ANALYSIS_SEED: u32 = 7;
+/// How long the answer to a seeded request is reused.
+const SEEDED_CACHE: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Which LLM a request goes to and how it is authorized: Gemini with a
+/// pasted API key or a Google account connected through `oauth`, or the
+/// local model.
+#[derive(Debug, Clone)]
+pub enum Credential {
+    ApiKey(String),
+    OAuth(OAuthClient),
+    Local { url: String, model: String },
+}
+
+/// Sign-in client for a Google account, from settings
+/// (`oauth.gemini.client_id`) or baked in at build time.
+pub fn oauth_client(conn: &Connection) -> AppResult<OAuthClient> {
+    let client_id = settings::get(conn, "oauth.gemini.client_id")?
+        .filter(|v| !v.is_empty())
+        .or(option_env!("AIDC_GOOGLE_CLIENT_ID").map(str::to_string))
+        .ok_or_else(|| AppError::Invalid("no OAuth client configured for gemini".into()))?;
+    Ok(OAuthClient {
+        account: "provider.gemini".into(),
+        auth_url: "https://accounts.google.com/o/oauth2/v2/auth",
+        token_url: "https://oauth2.googleapis.com/token",
+        client_id,
+        client_secret: option_env!("AIDC_GOOGLE_CLIENT_SECRET"),
+        extra_auth_params: &[
+            (
+                "scope",
+                "https://www.googleapis.com/auth/cloud-platform https://www.googleapis.com/auth/generative-language.retriever",
+            ),
+            ("access_type", "offline"),
+            ("prompt", "consent"),
+        ],
+    })
+}
+
+/// The local model, if one is configured at a loopback address.
+fn local(conn: &Connection) -> AppResult<Option<Credential>> {
+    let Some(url) = settings::get(conn, LOCAL_URL_SETTING)?.filter(|u| !u.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let parsed = reqwest::Url::parse(url.trim())
+        .map_err(|e| AppError::Invalid(format!("local LLM address: {}", e)))?;
+    let loopback = match parsed.host_str() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    };
+    if !loopback {
+        return Err(AppError::Invalid(format!(
+            "local LLM address must be on this machine: {}",
+            url
+        )));
+    }
+    let model = settings::get(conn, LOCAL_MODEL_SETTING)?
+        .filter(|m| !m.trim().is_empty())
+        .ok_or_else(|| AppError::Invalid("no local LLM model configured".into()))?;
+    Ok(Some(Credential::Local {
+        url: url.trim().trim_end_matches('/').to_string(),
+        model: model.trim().to_string(),
+    }))
+}
+
+/// Read the credential for a request about `project_id` up front so
+/// callers can drop the DB lock before the request goes out. An API key
+/// wins over a connected account, and either over the local model. A
+/// confidential project only ever gets the local model.
+pub fn credential(conn: &Connection, project_id: Option<&str>) -> AppResult<Credential> {
+    if let Some(project_id) = project_id {
+        if confidential::is_confidential(conn, project_id)? {
+            return local(conn)?.ok_or_else(|| {
+                AppError::Forbidden(
+                    "this project is confidential; set up a local LLM to use AI features".into(),
+                )
+            });
+        }
+    }
+    if let Some(key) = settings::get(conn, GEMINI_KEY_SETTING)?.filter(|k| !k.is_empty()) {
+        return Ok(Credential::ApiKey(key));
+    }
+    match oauth_client(conn) {
+        Ok(client) if oauth::is_connected(&client.account)? => Ok(Credential::OAuth(client)),
+        _ => local(conn)?.ok_or_else(|| AppError::Invalid("Gemini API key not configured".into())),
+    }
+}
+
+/// Ask the local model for JSON through Ollama's generate endpoint.
+async fn generate_local(
+    url: &str,
+    model: &str,
+    prompt: &str,
+    temperature: f32,
+    seed: Option<u32>,
+) -> AppResult<String> {
+    let res = providers::client()
+        .post(format!("{}/api/generate", url))
+        .json(&json!({
+            "model": model,
+            "prompt": prompt,
+            "format": "json",
+            "stream": false,
+            "options": { "temperature": temperature, "seed": seed },
+        }))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "local LLM ({}): {}",
+            status, text
+        )));
+    }
+    let data: Value = res.json().await?;
+    data["response"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Provider("empty response from the local LLM".into()))
+}
+
+/// Send a prompt to the LLM in JSON mode and parse the reply. Code fences
+/// are stripped the same way the frontend storyboard generator does.
+pub async fn generate_json(
+    credential: &Credential,
+    prompt: &str,
+    temperature: f32,
+) -> AppResult<Value> {
+    request_json(credential, prompt, temperature, None).await
+}
+
+/// `generate_json` with a fixed seed, for analysis of `project_id` whose
+/// answer should repeat for the same input. The same prompt to the same
+/// model gets the cached reply for a week instead of another request,
+/// unless the project is confidential.
+pub async fn generate_json_seeded(
+    db: &Database,
+    project_id: &str,
+    credential: &Credential,
+    prompt: &str,
+    temperature: f32,
+    seed: u32,
+) -> AppResult<Value> {
+    let model = match credential {
+        Credential::ApiKey(_) | Credential::OAuth(_) => GEMINI_MODEL.to_string(),
+        Credential::Local { url, model } => format!("{} {}", url, model),
+    };
+    let key = provider_cache::key(
+        "llm",
+        &[&model, prompt, &temperature.to_string(), &seed.to_string()],
+    );
+    provider_cache::get_or_fetch_for_project(db, project_id, "llm", &key, SEEDED_CACHE, || {
+        request_json(credential, prompt, temperature, Some(seed))
+    })
+    .await
+}
+
+async fn request_json(
+    credential: &Credential,
+    prompt: &str,
+    temperature: f32,
+    seed: Option<u32>,
+) -> AppResult<Value> {
+    let url = format!("{}/{}:generateContent", GEMINI_BASE, GEMINI_MODEL);
+    let req = match credential {
+        Credential::ApiKey(key) => providers::client().post(url).query(&[("key", key)]),
+        Credential::OAuth(client) => providers::client()
+            .post(url)
+            .bearer_auth(oauth::access_token(client).await?),
+        Credential::Local { url, model } => {
+            let text = generate_local(url, model, prompt, temperature, seed).await?;
+            return parse_reply(&text);
+        }
+    };
+    let res = req
+        .json(&json!({
+            "contents": [{ "parts": [{ "text": prompt }] }],
+            "generationConfig": {
+                "temperature": temperature,
+                "responseMimeType": "application/json",
+                "seed": seed,
+            },
+        }))
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!("gemini ({}): {}", status, text)));
+    }
+
+    let data: Value = res.json().await?;
+    let text = data["candidates"][0]["content"]["parts"][0]["text"]
+        .as_str()
+        .ok_or_else(|| AppError::Provider("empty response from Gemini".into()))?;
+    parse_reply(text)
+}
+
+fn parse_reply(text: &str) -> AppResult<Value> {
+    let cleaned = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    serde_json::from_str(cleaned)
+        .map_err(|e| AppError::Provider(format!("the LLM returned invalid JSON: {}", e)))
+}
+
+/// Unwrap the array Gemini was asked for, tolerating the common habit of
+/// wrapping it in a single-key object.
+pub fn into_array(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        Value::Object(map) => map
+            .into_iter()
+            .find_map(|(_, v)| match v {
+                Value::Array(items) => Some(items),
+                _ => None,
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,243 @@
+use crate::db::{self, Database};
+use crate::diagnostics;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::redaction::Redactor;
+use crate::secrets;
+use rusqlite::Connection;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+use ts_rs::TS;
+
+/// Overrides the default `info` level, e.g. `AIDC_LOG=debug`.
+const FILTER_ENV: &str = "AIDC_LOG";
+const FILE_PREFIX: &str = "aidc";
+/// Daily files kept before the oldest is deleted.
+const KEEP_FILES: usize = 14;
+const DEFAULT_LOG_LIMIT: usize = 200;
+
+/// Settings whose values never go into a support bundle.
+const REDACTED_MARKERS: &[&str] = &["api_key", "password", "token", "secret"];
+
+pub fn log_dir() -> PathBuf {
+    db::app_dir().join("logs")
+}
+
+/// Send `tracing` output to daily-rotated JSON files under the app data
+/// dir (and to stderr in debug builds). Keep the guard alive for the life
+/// of the app or buffered lines are lost.
+pub fn init() -> Option<WorkerGuard> {
+    let filter = EnvFilter::try_from_env(FILTER_ENV).unwrap_or_else(|_| EnvFilter::new("info"));
+    let file = std::fs::create_dir_all(log_dir()).ok().and_then(|_| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(FILE_PREFIX)
+            .filename_suffix("log")
+            .max_log_files(KEEP_FILES)
+            .build(log_dir())
+            .ok()
+    });
+    let (writer, guard) = match file {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(writer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let console = cfg!(debug_assertions).then(|| fmt::layer().with_writer(std::io::stderr));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(writer.map(|w| fmt::layer().json().with_writer(w)))
+        .with(console)
+        .init();
+    guard
+}
+
+/// Log files in `dir` (see `log_dir`), newest first.
+fn log_files(dir: &Path) -> AppResult<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(FILE_PREFIX) && n.ends_with(".log"))
+            })
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    // Names embed the date, so they sort chronologically.
+    files.sort();
+    files.reverse();
+    Ok(files)
+}
+
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LogFilter {
+    /// Minimum level: `error`, `warn`, `info`, `debug` or `trace`.
+    #[serde(default)]
+    pub level: Option<String>,
+    /// Case-insensitive text that must appear somewhere in the entry.
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_lowercase().as_str() {
+        "error" => 0,
+        "warn" => 1,
+        "info" => 2,
+        "debug" => 3,
+        _ => 4,
+    }
+}
+
+/// Most recent log entries, newest first, as the JSON objects written by
+/// the file layer.
+pub fn recent(dir: &Path, filter: &LogFilter) -> AppResult<Vec<Value>> {
+    let max_rank = filter.level.as_deref().map_or(4, level_rank);
+    let needle = filter.contains.as_ref().map(|c| c.to_lowercase());
+    let limit = filter.limit.unwrap_or(DEFAULT_LOG_LIMIT);
+
+    let mut entries = Vec::new();
+    for path in log_files(dir)? {
+        let file = std::fs::File::open(&path)?;
+        let mut lines: Vec<String> = BufReader::new(file).lines().map_while(Result::ok).collect();
+        lines.reverse();
+        for line in lines {
+            if needle
+                .as_ref()
+                .is_some_and(|n| !line.to_lowercase().contains(n.as_str()))
+            {
+                continue;
+            }
+            let Ok(entry) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if level_rank(entry["level"].as_str().unwrap_or("trace")) > max_rank {
+                continue;
+            }
+            entries.push(entry);
+            if entries.len() >= limit {
+                return Ok(entries);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn get_recent_logs(filter: Option<LogFilter>) -> AppResult<Vec<Value>> {
+    recent(&log_dir(), &filter.unwrap_or_default())
+}
+
+fn redacted_settings(conn: &Connection) -> AppResult<Value> {
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Value::Object(
+        rows.into_iter()
+            .filter(|(key, _)| !key.starts_with(secrets::PREFIX))
+            .map(|(key, value)| {
+                let hide = REDACTED_MARKERS.iter().any(|m| key.contains(m)) && !value.is_empty();
+                let value = if hide { "[redacted]".into() } else { value };
+                (key, Value::String(value))
+            })
+            .collect(),
+    ))
+}
+
+fn db_stats(conn: &Connection) -> AppResult<Value> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let tables = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut counts = serde_json::Map::new();
+    for table in tables {
+        let count: i64 =
+            conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |r| {
+                r.get(0)
+            })?;
+        counts.insert(table, json!(count));
+    }
+    let size = std::fs::metadata(db::get_db_path())
+        .map(|m| m.len())
+        .unwrap_or(0);
+    Ok(json!({
+        "schemaVersion": version,
+        "fileBytes": size,
+        "rows": counts,
+    }))
+}
+
+fn zip_err(e: zip::result::ZipError) -> AppError {
+    AppError::Io(format!("writing support bundle: {}", e))
+}
+
+/// Zip up recent logs, settings with credentials redacted, database stats,
+/// basic system info and the last diagnostics report for attaching to a
+/// bug report, all scrubbed per the redaction policy (see `redaction`).
+/// Returns the path.
+pub fn support_bundle(conn: &Connection, logs: &Path, path: &str) -> AppResult<String> {
+    let settings = redacted_settings(conn)?;
+    let stats = db_stats(conn)?;
+    let redactor = Redactor::new(conn)?;
+    let system = json!({
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+    });
+
+    let out = export::resolve_output(path, "aidc-support.zip")?;
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&out)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut files = vec![
+        ("settings.json", settings),
+        ("database.json", stats),
+        ("system.json", system),
+    ];
+    if let Some(report) = diagnostics::last_report() {
+        files.push(("diagnostics.json", report));
+    }
+    for (name, mut value) in files {
+        redactor.value(&mut value);
+        zip.start_file(name, options).map_err(zip_err)?;
+        zip.write_all(serde_json::to_string_pretty(&value)?.as_bytes())?;
+    }
+    for file in log_files(logs)?.into_iter().take(7) {
+        let Some(name) = file.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        zip.start_file(format!("logs/{}", name), options)
+            .map_err(zip_err)?;
+        for line in BufReader::new(std::fs::File::open(&file)?).lines() {
+            zip.write_all(redactor.line(&line?).as_bytes())?;
+            zip.write_all(b"\n")?;
+        }
+    }
+    zip.finish().map_err(zip_err)?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_support_bundle(db: State<'_, Database>, path: String) -> AppResult<String> {
+    support_bundle(&db.conn(), &log_dir(), &path)
+}
@@ -0,0 +1,314 @@
+//! Safety net around schema migrations for databases made by older
+//! versions. Before a database with data is migrated, a copy is kept next
+//! to it as `<file>.pre-v<version>.bak`. A dry run migrates a throwaway
+//! copy and reports what would change, and a rollback puts a pre-migration
+//! copy back.
+
+use crate::db::{self, Database};
+use crate::encryption;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::workspaces;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+/// How long the app waits after a rollback before quitting, so the
+/// command's reply gets through.
+const EXIT_DELAY: Duration = Duration::from_millis(500);
+
+fn user_version(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.query_row("PRAGMA user_version", [], |r| r.get(0))
+}
+
+fn backup_path(db_path: &Path, version: usize) -> PathBuf {
+    db::with_suffix(db_path, &format!("pre-v{}.bak", version))
+}
+
+/// Copy a database that is about to be migrated, unless it is new or
+/// already current. An existing copy for the same version is kept: it is
+/// the older, safer one. Returns where the copy is.
+pub fn backup_before_migrating(
+    conn: &Connection,
+    db_path: &Path,
+) -> rusqlite::Result<Option<PathBuf>> {
+    let version = user_version(conn)?;
+    if version >= db::schema_version() {
+        return Ok(None);
+    }
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master
+            WHERE type = 'table' AND name NOT LIKE 'sqlite_%')",
+        [],
+        |r| r.get(0),
+    )?;
+    if !has_tables {
+        return Ok(None);
+    }
+    let backup = backup_path(db_path, version);
+    if !backup.exists() {
+        conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+        tracing::info!(
+            from_version = version,
+            to_version = db::schema_version(),
+            backup = ?backup,
+            "backed up database before migrating"
+        );
+    }
+    Ok(Some(backup))
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MigrationBackup {
+    pub path: String,
+    /// Schema version of the database when it was copied.
+    pub version: usize,
+    #[ts(type = "number")]
+    pub bytes: u64,
+}
+
+/// Pre-migration copies of the database at `db_path`, newest schema
+/// first.
+pub fn backups(db_path: &Path) -> AppResult<Vec<MigrationBackup>> {
+    let (Some(dir), Some(file)) = (db_path.parent(), db_path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}.pre-v", file.to_string_lossy());
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(version) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .and_then(|v| v.parse().ok())
+        else {
+            continue;
+        };
+        backups.push(MigrationBackup {
+            path: entry.path().to_string_lossy().to_string(),
+            version,
+            bytes: entry.metadata()?.len(),
+        });
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.version));
+    Ok(backups)
+}
+
+/// Tables with their columns and row counts, plus other schema objects.
+#[derive(Default)]
+struct Shape {
+    tables: BTreeMap<String, (Vec<String>, i64)>,
+    objects: Vec<String>,
+}
+
+fn shape(conn: &Connection) -> rusqlite::Result<Shape> {
+    let mut stmt = conn.prepare(
+        "SELECT type, name FROM sqlite_master WHERE name NOT LIKE 'sqlite_%' ORDER BY type, name",
+    )?;
+    let entries = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut shape = Shape::default();
+    for (kind, name) in entries {
+        if kind != "table" {
+            shape.objects.push(format!("{} {}", kind, name));
+            continue;
+        }
+        let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", name))?;
+        let columns = stmt
+            .query_map([], |r| r.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        let rows = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name), [], |r| {
+            r.get(0)
+        })?;
+        shape.tables.insert(name, (columns, rows));
+    }
+    Ok(shape)
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RowChange {
+    pub table: String,
+    #[ts(type = "number")]
+    pub before: i64,
+    #[ts(type = "number")]
+    pub after: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MigrationPreview {
+    pub path: String,
+    pub from_version: usize,
+    pub to_version: usize,
+    /// Migrations that would run; 0 means the database is current.
+    pub pending: usize,
+    pub tables_added: Vec<String>,
+    /// As `table.column`, for tables that already existed.
+    pub columns_added: Vec<String>,
+    /// Indexes, triggers and views, as e.g. `index idx_scenes_stale`.
+    pub objects_added: Vec<String>,
+    /// Tables whose row count the migrations change.
+    pub row_changes: Vec<RowChange>,
+    /// Where the pre-migration copy will be written.
+    pub backup_path: String,
+    /// Why the migrations failed on the copy; they would fail for real too.
+    pub error: String,
+}
+
+fn compare(before: &Shape, after: &Shape, preview: &mut MigrationPreview) {
+    for (table, (columns, rows)) in &after.tables {
+        let Some((old_columns, old_rows)) = before.tables.get(table) else {
+            preview.tables_added.push(table.clone());
+            continue;
+        };
+        preview.columns_added.extend(
+            columns
+                .iter()
+                .filter(|c| !old_columns.contains(c))
+                .map(|c| format!("{}.{}", table, c)),
+        );
+        if rows != old_rows {
+            preview.row_changes.push(RowChange {
+                table: table.clone(),
+                before: *old_rows,
+                after: *rows,
+            });
+        }
+    }
+    preview.objects_added = after
+        .objects
+        .iter()
+        .filter(|o| !before.objects.contains(o))
+        .cloned()
+        .collect();
+}
+
+/// What migrating a workspace's database (the active one by default) would
+/// do, found by migrating a temporary copy. The database itself is only
+/// read.
+pub fn preview(db_path: &Path) -> AppResult<MigrationPreview> {
+    if !db_path.exists() {
+        return Err(AppError::NotFound(format!(
+            "database {}",
+            db_path.display()
+        )));
+    }
+    let source = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    encryption::unlock(&source, db_path)?;
+    let from_version = user_version(&source)?;
+    let mut preview = MigrationPreview {
+        path: db_path.to_string_lossy().to_string(),
+        from_version,
+        to_version: db::schema_version(),
+        pending: db::schema_version().saturating_sub(from_version),
+        backup_path: backup_path(db_path, from_version)
+            .to_string_lossy()
+            .to_string(),
+        ..Default::default()
+    };
+    if preview.pending == 0 {
+        return Ok(preview);
+    }
+
+    let before = shape(&source)?;
+    let scratch = db::with_suffix(db_path, "dry-run");
+    std::fs::remove_file(&scratch).ok();
+    source.execute("VACUUM INTO ?1", [scratch.to_string_lossy()])?;
+    drop(source);
+    let result = Connection::open(&scratch).and_then(|copy| {
+        // The copy is keyed like the original if that is encrypted.
+        encryption::unlock(&copy, db_path)?;
+        copy.execute_batch("PRAGMA foreign_keys = ON;")?;
+        db::migrate(&copy)?;
+        shape(&copy)
+    });
+    std::fs::remove_file(&scratch).ok();
+    match result {
+        Ok(after) => compare(&before, &after, &mut preview),
+        Err(e) => preview.error = e.to_string(),
+    }
+    Ok(preview)
+}
+
+#[tauri::command]
+pub fn preview_migration(workspace_id: Option<String>) -> AppResult<MigrationPreview> {
+    preview(&workspaces::db_path_for(workspace_id.as_deref())?)
+}
+
+#[tauri::command]
+pub fn list_migration_backups(workspace_id: Option<String>) -> AppResult<Vec<MigrationBackup>> {
+    backups(&workspaces::db_path_for(workspace_id.as_deref())?)
+}
+
+/// The pre-migration copy of `db_path` at `backup`, if it is one and
+/// passes an integrity check.
+pub fn verified_backup(db_path: &Path, backup: &str) -> AppResult<MigrationBackup> {
+    let chosen = backups(db_path)?
+        .into_iter()
+        .find(|b| b.path == backup)
+        .ok_or_else(|| AppError::NotFound(format!("migration backup {}", backup)))?;
+    let check = Connection::open_with_flags(&chosen.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|conn| {
+            encryption::unlock(&conn, db_path)?;
+            conn.query_row("PRAGMA integrity_check", [], |r| r.get::<_, String>(0))
+        });
+    match check {
+        Ok(ref c) if c == "ok" => Ok(chosen),
+        other => Err(AppError::Database(format!(
+            "migration backup failed its integrity check: {}",
+            other.map_err(|e| e.to_string()).unwrap_or_else(|e| e)
+        ))),
+    }
+}
+
+/// Put a pre-migration copy back in place of a workspace's database. For
+/// the active workspace the app then quits, since this version can't run
+/// on the older schema: reinstall the version that made the copy before
+/// opening it again, or it will simply be migrated (and backed up) anew.
+/// Emits `database-rolled-back` with the restored path first.
+#[tauri::command]
+pub fn rollback_migration(
+    app: AppHandle,
+    backup: String,
+    workspace_id: Option<String>,
+) -> AppResult<()> {
+    let db_path = workspaces::db_path_for(workspace_id.as_deref())?;
+    let chosen = verified_backup(&db_path, &backup)?;
+    let active = db_path == db::get_db_path();
+
+    let tmp = db::with_suffix(&db_path, "rollback");
+    std::fs::copy(&chosen.path, &tmp)?;
+    if active {
+        let db = app.state::<Database>();
+        let mut conn = db.conn();
+        // Close the live file; nothing may write to it again this run.
+        *conn = Connection::open_in_memory()?;
+        std::fs::rename(&tmp, &db_path)?;
+    } else {
+        std::fs::rename(&tmp, &db_path)?;
+    }
+    tracing::warn!(
+        path = ?db_path,
+        version = chosen.version,
+        "rolled database back to pre-migration backup"
+    );
+    AppEvent::DatabaseRolledBack(chosen.path.clone()).emit(&app);
+    if active {
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(EXIT_DELAY).await;
+            handle.exit(0);
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::ics::{self, When};
+use crate::projects;
+use crate::schedule;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Standard milestones, with the title used when none is given. `custom`
+/// requires a title.
+pub const MILESTONE_KINDS: &[(&str, &str)] = &[
+    ("script_locked", "Script locked"),
+    ("previz_complete", "Previz complete"),
+    ("picture_lock", "Picture lock"),
+    ("delivery", "Delivery"),
+    ("custom", ""),
+];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Milestone {
+    pub id: String,
+    pub project_id: String,
+    pub kind: String,
+    pub title: String,
+    pub due_date: String,
+    pub notes: String,
+    pub completed: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MilestoneInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub project_id: String,
+    pub kind: String,
+    #[serde(default)]
+    pub title: String,
+    /// `YYYY-MM-DD`.
+    pub due_date: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub completed: bool,
+}
+
+const MILESTONE_COLUMNS: &str =
+    "id, project_id, kind, title, due_date, notes, completed, created_at";
+
+fn row_to_milestone(row: &Row) -> rusqlite::Result<Milestone> {
+    Ok(Milestone {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        kind: row.get(2)?,
+        title: row.get(3)?,
+        due_date: row.get(4)?,
+        notes: row.get(5)?,
+        completed: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+fn get_milestone(conn: &Connection, id: &str) -> AppResult<Milestone> {
+    conn.query_row(
+        &format!("SELECT {} FROM milestones WHERE id = ?1", MILESTONE_COLUMNS),
+        [id],
+        row_to_milestone,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("milestone {}", id)))
+}
+
+pub fn list_for_project(conn: &Connection, project_id: &str) -> AppResult<Vec<Milestone>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM milestones WHERE project_id = ?1 ORDER BY due_date, rowid",
+        MILESTONE_COLUMNS
+    ))?;
+    let milestones = stmt
+        .query_map([project_id], row_to_milestone)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(milestones)
+}
+
+#[tauri::command]
+pub fn list_milestones(db: State<'_, Database>, project_id: String) -> AppResult<Vec<Milestone>> {
+    list_for_project(&db.conn(), &project_id)
+}
+
+pub fn save(conn: &Connection, input: &MilestoneInput) -> AppResult<Milestone> {
+    let default_title = MILESTONE_KINDS
+        .iter()
+        .find(|(k, _)| *k == input.kind)
+        .map(|(_, title)| *title)
+        .ok_or_else(|| AppError::Invalid(format!("unknown milestone kind '{}'", input.kind)))?;
+    let title = match input.title.trim() {
+        "" if default_title.is_empty() => {
+            return Err(AppError::Invalid("custom milestones need a title".into()))
+        }
+        "" => default_title,
+        t => t,
+    };
+    schedule::validate_date(&input.due_date)?;
+    projects::ensure_unlocked(conn, &input.project_id)?;
+    let id = match &input.id {
+        Some(id) => {
+            let changed = conn.execute(
+                "UPDATE milestones SET kind = ?2, title = ?3, due_date = ?4, notes = ?5, completed = ?6
+                 WHERE id = ?1 AND project_id = ?7",
+                params![
+                    id,
+                    input.kind,
+                    title,
+                    input.due_date,
+                    input.notes,
+                    input.completed,
+                    input.project_id
+                ],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("milestone {}", id)));
+            }
+            id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO milestones (id, project_id, kind, title, due_date, notes, completed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id,
+                    input.project_id,
+                    input.kind,
+                    title,
+                    input.due_date,
+                    input.notes,
+                    input.completed
+                ],
+            )?;
+            id
+        }
+    };
+    get_milestone(conn, &id)
+}
+
+#[tauri::command]
+pub fn save_milestone(db: State<'_, Database>, input: MilestoneInput) -> AppResult<Milestone> {
+    save(&db.conn(), &input)
+}
+
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let milestone = get_milestone(conn, id)?;
+    projects::ensure_unlocked(conn, &milestone.project_id)?;
+    conn.execute("DELETE FROM milestones WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_milestone(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
+
+/// The project's milestones as all-day events. Completed milestones are
+/// left out unless `include_completed` is set.
+pub fn calendar(conn: &Connection, project_id: &str, include_completed: bool) -> AppResult<String> {
+    let project = export::load_project(conn, project_id)?;
+    let events: Vec<ics::Event> = list_for_project(conn, project_id)?
+        .into_iter()
+        .filter(|m| include_completed || !m.completed)
+        .map(|m| ics::Event {
+            uid: format!("milestone-{}@aidc", m.id),
+            when: When::AllDay(m.due_date),
+            summary: format!("{}: {}", project.name, m.title),
+            description: m.notes,
+            location: String::new(),
+        })
+        .collect();
+    Ok(ics::calendar(&project.name, &events))
+}
+
+/// Write the project's milestones as all-day events in an `.ics` file.
+/// Completed milestones are left out unless `include_completed` is set.
+#[tauri::command]
+pub fn export_milestones_ics(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+    include_completed: Option<bool>,
+) -> AppResult<String> {
+    let calendar = calendar(&db.conn(), &project_id, include_completed.unwrap_or(false))?;
+    let out = export::resolve_output(&path, "milestones.ics")?;
+    std::fs::write(&out, calendar)?;
+    Ok(out.to_string_lossy().to_string())
+}
@@ -0,0 +1,237 @@
+//! A project's script and scene data mirrored into a folder of plain JSON
+//! files, so writers can keep it in Git. The backend rewrites the folder
+//! whenever the project changes; media stays out of it.
+//!
+//! Layout, one file per entity and named by id so renames and reordering
+//! show up as edits rather than moves:
+//!
+//! ```text
+//! project.json
+//! assets.json
+//! characters/<character id>.json
+//! scenes/<scene id>.json
+//! ```
+//!
+//! Files are pretty-printed with fields in a fixed order and only written
+//! when their content changed, so unchanged entities never show in a diff.
+
+use crate::bundle::{self, ProjectBundle};
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// How often to look for changed projects.
+const MIRROR_CHECK: Duration = Duration::from_secs(5);
+/// Folders holding one file per entity; stale files in them are removed.
+const ENTITY_DIRS: &[&str] = &["characters", "scenes"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProjectMirror {
+    pub project_id: String,
+    pub dir: String,
+    pub mirrored_at: Option<String>,
+}
+
+pub fn get(conn: &Connection, project_id: &str) -> AppResult<Option<ProjectMirror>> {
+    Ok(conn
+        .query_row(
+            "SELECT project_id, dir, mirrored_at FROM project_mirrors WHERE project_id = ?1",
+            [project_id],
+            |r| {
+                Ok(ProjectMirror {
+                    project_id: r.get(0)?,
+                    dir: r.get(1)?,
+                    mirrored_at: r.get(2)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// The newest change-log row for the project; see `sync`.
+fn last_change(conn: &Connection, project_id: &str) -> AppResult<i64> {
+    Ok(conn.query_row(
+        "SELECT coalesce(max(id), 0) FROM sync_changes WHERE project_id = ?1",
+        [project_id],
+        |r| r.get(0),
+    )?)
+}
+
+/// Mirrors whose project changed since they were last written, with the
+/// change they will be brought up to.
+fn stale(conn: &Connection) -> AppResult<Vec<(String, PathBuf, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT m.project_id, m.dir, coalesce(max(c.id), 0) AS latest
+         FROM project_mirrors m LEFT JOIN sync_changes c ON c.project_id = m.project_id
+         GROUP BY m.project_id HAVING latest <> m.last_change OR m.mirrored_at IS NULL",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        Ok((r.get(0)?, PathBuf::from(r.get::<_, String>(1)?), r.get(2)?))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn mark_written(conn: &Connection, project_id: &str, change: i64) -> AppResult<()> {
+    conn.execute(
+        "UPDATE project_mirrors SET last_change = ?2, mirrored_at = ?3 WHERE project_id = ?1",
+        params![project_id, change, clock::now()],
+    )?;
+    Ok(())
+}
+
+/// Write `value` to `path` unless it already holds exactly that.
+fn write_json(path: &Path, value: &impl Serialize) -> AppResult<bool> {
+    let mut text = serde_json::to_string_pretty(value)?;
+    text.push('\n');
+    if std::fs::read_to_string(path).ok().as_deref() == Some(text.as_str()) {
+        return Ok(false);
+    }
+    std::fs::write(path, text)?;
+    Ok(true)
+}
+
+/// Bring the folder in line with the bundle. Returns how many files were
+/// written or removed.
+fn write_mirror(dir: &Path, bundle: &ProjectBundle) -> AppResult<usize> {
+    for sub in ENTITY_DIRS {
+        std::fs::create_dir_all(dir.join(sub))?;
+    }
+    let mut changed = 0;
+    let mut keep = BTreeSet::new();
+    changed += write_json(&dir.join("project.json"), &bundle.project)? as usize;
+    changed += write_json(&dir.join("assets.json"), &bundle.assets)? as usize;
+    for character in &bundle.characters {
+        // Photos are media; they stay in the database.
+        let mut value = serde_json::to_value(character)?;
+        if let Value::Object(fields) = &mut value {
+            fields.remove("photoData");
+        }
+        let path = dir
+            .join("characters")
+            .join(format!("{}.json", character.id));
+        changed += write_json(&path, &value)? as usize;
+        keep.insert(path);
+    }
+    for scene in &bundle.scenes {
+        let path = dir.join("scenes").join(format!("{}.json", scene.id));
+        changed += write_json(&path, scene)? as usize;
+        keep.insert(path);
+    }
+    for sub in ENTITY_DIRS {
+        for entry in std::fs::read_dir(dir.join(sub))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") && !keep.contains(&path) {
+                std::fs::remove_file(&path)?;
+                changed += 1;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Rewrite one project's mirror now.
+fn refresh(conn: &Connection, project_id: &str, dir: &Path) -> AppResult<()> {
+    let change = last_change(conn, project_id)?;
+    let bundle = bundle::export_bundle(conn, project_id)?;
+    let changed = write_mirror(dir, &bundle)?;
+    mark_written(conn, project_id, change)?;
+    tracing::debug!(project_id, changed, "mirrored project");
+    Ok(())
+}
+
+/// Start the task that keeps mirror folders up to date with their
+/// projects.
+pub fn spawn_writer(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let due = {
+                let db = app.state::<Database>();
+                let conn = db.conn();
+                stale(&conn)
+            };
+            match due {
+                Ok(due) => {
+                    for (project_id, dir, change) in due {
+                        let bundle = {
+                            let db = app.state::<Database>();
+                            let conn = db.conn();
+                            bundle::export_bundle(&conn, &project_id)
+                        };
+                        // Write outside the lock; the folder may be slow.
+                        let result = bundle
+                            .and_then(|bundle| write_mirror(&dir, &bundle))
+                            .and_then(|_| {
+                                mark_written(&app.state::<Database>().conn(), &project_id, change)
+                            });
+                        if let Err(e) = result {
+                            tracing::warn!(project_id = %project_id, dir = %dir.display(), error = %e, "failed to mirror project");
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "failed to check project mirrors"),
+            }
+            tokio::time::sleep(MIRROR_CHECK).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_project_mirror(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Option<ProjectMirror>> {
+    get(&db.conn(), &project_id)
+}
+
+/// Mirror the project into `dir`, writing it straight away, or stop
+/// mirroring it when `dir` is None. The folder's files are left in place
+/// when mirroring stops.
+pub fn set(
+    conn: &Connection,
+    project_id: &str,
+    dir: Option<&str>,
+) -> AppResult<Option<ProjectMirror>> {
+    projects::get_project(conn, project_id)?;
+    let Some(dir) = dir else {
+        conn.execute(
+            "DELETE FROM project_mirrors WHERE project_id = ?1",
+            [project_id],
+        )?;
+        return Ok(None);
+    };
+    let path = PathBuf::from(dir);
+    if !path.is_absolute() {
+        return Err(AppError::Invalid(format!(
+            "mirror folder must be an absolute path: {}",
+            dir
+        )));
+    }
+    conn.execute(
+        "INSERT INTO project_mirrors (project_id, dir) VALUES (?1, ?2)
+         ON CONFLICT(project_id) DO UPDATE SET dir = excluded.dir, last_change = 0,
+            mirrored_at = NULL",
+        params![project_id, dir],
+    )?;
+    refresh(conn, project_id, &path)?;
+    get(conn, project_id)
+}
+
+#[tauri::command]
+pub fn set_project_mirror(
+    db: State<'_, Database>,
+    project_id: String,
+    dir: Option<String>,
+) -> AppResult<Option<ProjectMirror>> {
+    set(&db.conn(), &project_id, dir.as_deref())
+}
@@ -0,0 +1,227 @@
+//! A built-in `mock` model that walks jobs through the same queue, polling
+//! and completion steps as a real provider without calling out or spending
+//! anything. Each submission gets a request id that records when it will be
+//! ready and whether it will fail, so jobs survive a restart just like
+//! Fal.ai's do. Finished takes are placeholder test-pattern clips rendered
+//! once per size and length with the bundled ffmpeg.
+
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::job_logs::Exchange;
+use crate::providers::{GenerationRequest, QueueState};
+use crate::settings;
+use crate::validation::Validator;
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use ts_rs::TS;
+
+/// The `providers::MODELS` key (and job provider) of the mock.
+pub const MODEL: &str = "mock";
+
+const DELAY_SETTING: &str = "mock.delay_secs";
+const FAILURE_RATE_SETTING: &str = "mock.failure_rate";
+const DEFAULT_DELAY_SECS: u32 = 5;
+const MAX_DELAY_SECS: u32 = 600;
+
+/// Errors a failing mock job reports, one for each `providers::failure`
+/// kind the retry policy treats differently.
+const FAILURES: &[&str] = &[
+    "mock provider temporarily unavailable, try again",
+    "mock provider: insufficient credits",
+    "mock provider: prompt flagged by safety filter",
+    "mock provider: unsupported duration",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MockSettings {
+    /// How long a job stays queued before it finishes.
+    pub delay_secs: u32,
+    /// 0–1; the share of jobs that fail instead of finishing.
+    pub failure_rate: f64,
+}
+
+fn load(conn: &Connection) -> AppResult<MockSettings> {
+    Ok(MockSettings {
+        delay_secs: settings::get(conn, DELAY_SETTING)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_DELAY_SECS),
+        failure_rate: settings::get(conn, FAILURE_RATE_SETTING)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0.0),
+    })
+}
+
+/// A number in [0, 1) from a fresh v4 uuid.
+fn roll() -> f64 {
+    (uuid::Uuid::new_v4().as_u128() >> 75) as f64 / (1u64 << 53) as f64
+}
+
+/// Output size for an aspect ratio, 720 pixels on the short side.
+fn frame_size(aspect_ratio: &str) -> (u32, u32) {
+    match aspect_ratio {
+        "9:16" => (720, 1280),
+        "1:1" => (720, 720),
+        "4:3" => (960, 720),
+        "3:4" => (720, 960),
+        "21:9" => (1680, 720),
+        _ => (1280, 720),
+    }
+}
+
+/// Accept a job and return its request id:
+/// `mock-<ready at, ms>-<failure index or x>-<seconds>-<width>x<height>-<uuid>`.
+pub fn submit(
+    conn: &Connection,
+    req: &GenerationRequest,
+    log: &mut Vec<Exchange>,
+) -> AppResult<String> {
+    let config = load(conn)?;
+    let ready_at = Utc::now().timestamp_millis() + i64::from(config.delay_secs) * 1000;
+    let failure = if roll() < config.failure_rate {
+        ((roll() * FAILURES.len() as f64) as usize).to_string()
+    } else {
+        "x".into()
+    };
+    let (width, height) = frame_size(&req.aspect_ratio);
+    let request_id = format!(
+        "mock-{}-{}-{}-{}x{}-{}",
+        ready_at,
+        failure,
+        req.duration.max(1),
+        width,
+        height,
+        uuid::Uuid::new_v4().simple()
+    );
+    let body = serde_json::to_value(req)?;
+    let mut exchange = Exchange::new("POST", "mock://queue", Some(&body));
+    exchange.status = Some(200);
+    exchange.response = json!({ "request_id": request_id }).to_string();
+    log.push(exchange);
+    Ok(request_id)
+}
+
+struct MockJob {
+    ready_at: i64,
+    failure: Option<usize>,
+    duration: u32,
+    size: (u32, u32),
+}
+
+fn parse(request_id: &str) -> Option<MockJob> {
+    let mut parts = request_id.strip_prefix("mock-")?.split('-');
+    let ready_at = parts.next()?.parse().ok()?;
+    let failure = match parts.next()? {
+        "x" => None,
+        n => Some(n.parse::<usize>().ok()?.min(FAILURES.len() - 1)),
+    };
+    let duration = parts.next()?.parse().ok()?;
+    let (width, height) = parts.next()?.split_once('x')?;
+    Some(MockJob {
+        ready_at,
+        failure,
+        duration,
+        size: (width.parse().ok()?, height.parse().ok()?),
+    })
+}
+
+/// The placeholder clip for a size and length, rendering it on first use.
+async fn placeholder(binary: &Path, job: &MockJob) -> AppResult<PathBuf> {
+    let (width, height) = job.size;
+    let out = db::media_dir("mock")?.join(format!(
+        "placeholder-{}x{}-{}s.mp4",
+        width, height, job.duration
+    ));
+    if out.exists() {
+        return Ok(out);
+    }
+    let source = format!(
+        "testsrc2=size={}x{}:rate=24:duration={}",
+        width, height, job.duration
+    );
+    let output = tokio::process::Command::new(binary)
+        .args(["-v", "error", "-y", "-f", "lavfi", "-i", &source])
+        .args(["-pix_fmt", "yuv420p", "-movflags", "+faststart"])
+        .arg(&out)
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not run ffmpeg ({}): {}",
+                binary.display(),
+                e
+            ))
+        })?;
+    if !output.status.success() || !out.exists() {
+        let _ = std::fs::remove_file(&out);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("ffmpeg produced no video");
+        return Err(AppError::Io(format!("placeholder render failed: {}", last)));
+    }
+    Ok(out)
+}
+
+/// Where a mock job has got to, finishing it with a placeholder clip once
+/// its delay has passed.
+pub async fn poll(
+    ffmpeg: &Path,
+    request_id: &str,
+    log: &mut Vec<Exchange>,
+) -> AppResult<QueueState> {
+    let job = parse(request_id)
+        .ok_or_else(|| AppError::Invalid(format!("not a mock request id: {}", request_id)))?;
+    let mut exchange = Exchange::new("GET", &format!("mock://requests/{}", request_id), None);
+    exchange.status = Some(200);
+    if Utc::now().timestamp_millis() < job.ready_at {
+        exchange.response = json!({ "status": "IN_QUEUE" }).to_string();
+        log.push(exchange);
+        return Ok(QueueState::Waiting);
+    }
+    let state = match job.failure {
+        Some(i) => QueueState::Failed(FAILURES[i].to_string()),
+        None => QueueState::Completed {
+            video_url: placeholder(ffmpeg, &job)
+                .await?
+                .to_string_lossy()
+                .to_string(),
+        },
+    };
+    exchange.response = match &state {
+        QueueState::Completed { video_url } => json!({ "video": { "url": video_url } }),
+        QueueState::Failed(detail) => json!({ "detail": detail }),
+        QueueState::Waiting => json!({}),
+    }
+    .to_string();
+    log.push(exchange);
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn get_mock_settings(db: State<'_, Database>) -> AppResult<MockSettings> {
+    load(&db.conn())
+}
+
+/// Check and store the mock's settings.
+pub fn save(conn: &Connection, mock: &MockSettings) -> AppResult<MockSettings> {
+    let mut v = Validator::new();
+    v.range("delaySecs", mock.delay_secs, 0, MAX_DELAY_SECS)
+        .range("failureRate", mock.failure_rate, 0.0, 1.0);
+    v.finish()?;
+    settings::set(conn, DELAY_SETTING, &mock.delay_secs.to_string())?;
+    settings::set(conn, FAILURE_RATE_SETTING, &mock.failure_rate.to_string())?;
+    load(conn)
+}
+
+#[tauri::command]
+pub fn save_mock_settings(db: State<'_, Database>, mock: MockSettings) -> AppResult<MockSettings> {
+    save(&db.conn(), &mock)
+}
@@ -0,0 +1,275 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::{confidential, providers, scenes, settings};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+const OPENAI_MODERATION_URL: &str = "https://api.openai.com/v1/moderations";
+const OPENAI_MODERATION_MODEL: &str = "omni-moderation-latest";
+
+/// Settings keys. `moderation.engine` is `"local"` (default) or `"openai"`.
+const ENABLED_SETTING: &str = "moderation.enabled";
+const ENGINE_SETTING: &str = "moderation.engine";
+pub const OPENAI_KEY_SETTING: &str = "api_key.openai";
+
+/// Terms that commonly trip provider safety filters, grouped by the
+/// category we report. Matched as whole words, case-insensitively.
+const LOCAL_RULES: &[(&str, &[&str])] = &[
+    (
+        "sexual",
+        &[
+            "nude", "naked", "nsfw", "explicit", "porn", "sex", "topless", "erotic",
+        ],
+    ),
+    (
+        "violence/graphic",
+        &[
+            "gore",
+            "decapitated",
+            "dismembered",
+            "mutilated",
+            "disembowel",
+            "bloodbath",
+        ],
+    ),
+    ("self-harm", &["suicide", "self-harm", "overdose"]),
+    ("hate", &["nazi", "swastika", "lynching"]),
+    ("minors", &["underage", "preteen"]),
+    ("public-figures", &["deepfake", "impersonate"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    Local,
+    OpenAi,
+}
+
+impl Engine {
+    fn as_str(self) -> &'static str {
+        match self {
+            Engine::Local => "local",
+            Engine::OpenAi => "openai",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ModerationResult {
+    pub scene_id: Option<String>,
+    pub flagged: bool,
+    pub categories: Vec<String>,
+    pub engine: String,
+}
+
+pub struct Config {
+    enabled: bool,
+    engine: Engine,
+    openai_key: String,
+}
+
+/// The moderation settings; prompts of a confidential project are only
+/// ever checked locally.
+pub fn load_config(conn: &Connection, project_id: Option<&str>) -> AppResult<Config> {
+    let confidential = match project_id {
+        Some(project_id) => confidential::is_confidential(conn, project_id)?,
+        None => false,
+    };
+    let engine = match settings::get(conn, ENGINE_SETTING)?.as_deref() {
+        Some("openai") if !confidential => Engine::OpenAi,
+        _ => Engine::Local,
+    };
+    Ok(Config {
+        enabled: settings::get_bool(conn, ENABLED_SETTING)?,
+        engine,
+        openai_key: settings::get(conn, OPENAI_KEY_SETTING)?.unwrap_or_default(),
+    })
+}
+
+fn classify_local(text: &str) -> Vec<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    LOCAL_RULES
+        .iter()
+        .filter(|(_, terms)| terms.iter().any(|t| words.iter().any(|w| w == t)))
+        .map(|(category, _)| category.to_string())
+        .collect()
+}
+
+/// One round trip for the whole batch; results come back in input order.
+async fn classify_openai(api_key: &str, inputs: &[String]) -> AppResult<Vec<Vec<String>>> {
+    if api_key.is_empty() {
+        return Err(AppError::Invalid("OpenAI API key not configured".into()));
+    }
+    let res = providers::client()
+        .post(OPENAI_MODERATION_URL)
+        .bearer_auth(api_key)
+        .json(&json!({ "model": OPENAI_MODERATION_MODEL, "input": inputs }))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "moderation ({}): {}",
+            status, text
+        )));
+    }
+
+    let data: Value = res.json().await?;
+    let results = data["results"]
+        .as_array()
+        .filter(|r| r.len() == inputs.len())
+        .ok_or_else(|| AppError::Provider("moderation returned malformed results".into()))?;
+    Ok(results
+        .iter()
+        .map(|r| {
+            r["categories"]
+                .as_object()
+                .map(|cats| {
+                    cats.iter()
+                        .filter(|(_, v)| v.as_bool() == Some(true))
+                        .map(|(k, _)| k.clone())
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+/// Classify a batch of prompts with the configured engine. An unreachable
+/// OpenAI endpoint falls back to the local rules so offline queuing still
+/// gets a check.
+async fn classify(config: &Config, inputs: &[String]) -> AppResult<(Engine, Vec<Vec<String>>)> {
+    if config.engine == Engine::OpenAi {
+        match classify_openai(&config.openai_key, inputs).await {
+            Ok(categories) => return Ok((Engine::OpenAi, categories)),
+            Err(AppError::Network(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((
+        Engine::Local,
+        inputs.iter().map(|text| classify_local(text)).collect(),
+    ))
+}
+
+/// Gate used before a scene's prompt goes to a provider. No-op unless
+/// moderation is switched on in settings.
+pub async fn precheck(app: &AppHandle, scene_id: &str, prompt: &str) -> AppResult<()> {
+    let config = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let project_id = scenes::get_scene(&conn, scene_id)?.project_id;
+        load_config(&conn, Some(&project_id))?
+    };
+    if !config.enabled {
+        return Ok(());
+    }
+    let (_, mut categories) = classify(&config, &[prompt.to_string()]).await?;
+    let categories = categories.pop().unwrap_or_default();
+    if categories.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Flagged(categories.join(", ")))
+    }
+}
+
+/// The text to check for each of the project's scenes (or just
+/// `scene_ids`), in board order: the prompt, or the description of scenes
+/// without one.
+pub fn scene_texts(
+    conn: &Connection,
+    project_id: &str,
+    scene_ids: Option<&[String]>,
+) -> AppResult<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, CASE WHEN prompt != '' THEN prompt ELSE description END
+         FROM scenes WHERE project_id = ?1 ORDER BY order_key, sort_order, scene_number",
+    )?;
+    let scenes = stmt
+        .query_map([project_id], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(match scene_ids {
+        Some(ids) => scenes
+            .into_iter()
+            .filter(|(id, _)| ids.contains(id))
+            .collect(),
+        None => scenes,
+    })
+}
+
+/// Classify scene texts from `scene_texts`.
+pub async fn check_scenes(
+    config: &Config,
+    scenes: Vec<(String, String)>,
+) -> AppResult<Vec<ModerationResult>> {
+    if scenes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let inputs: Vec<String> = scenes.iter().map(|(_, text)| text.clone()).collect();
+    let (engine, categories) = classify(config, &inputs).await?;
+    Ok(scenes
+        .into_iter()
+        .zip(categories)
+        .map(|((id, _), categories)| ModerationResult {
+            scene_id: Some(id),
+            flagged: !categories.is_empty(),
+            categories,
+            engine: engine.as_str().into(),
+        })
+        .collect())
+}
+
+/// Check scene prompts before a batch submit. Runs whether or not the
+/// submit-time gate is enabled so the UI can always ask. Scenes without a
+/// prompt are checked on their description instead.
+#[tauri::command]
+pub async fn moderate_scenes(
+    app: AppHandle,
+    project_id: String,
+    scene_ids: Option<Vec<String>>,
+) -> AppResult<Vec<ModerationResult>> {
+    let (config, scenes) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        (
+            load_config(&conn, Some(&project_id))?,
+            scene_texts(&conn, &project_id, scene_ids.as_deref())?,
+        )
+    };
+    check_scenes(&config, scenes).await
+}
+
+/// Classify one free-form prompt.
+pub async fn check_prompt(config: &Config, prompt: String) -> AppResult<ModerationResult> {
+    let (engine, mut categories) = classify(config, &[prompt]).await?;
+    let categories = categories.pop().unwrap_or_default();
+    Ok(ModerationResult {
+        scene_id: None,
+        flagged: !categories.is_empty(),
+        categories,
+        engine: engine.as_str().into(),
+    })
+}
+
+/// Check a single free-form prompt, e.g. while the user is still typing
+/// it, for the project it belongs to if any.
+#[tauri::command]
+pub async fn moderate_prompt(
+    app: AppHandle,
+    prompt: String,
+    project_id: Option<String>,
+) -> AppResult<ModerationResult> {
+    let config = load_config(&app.state::<Database>().conn(), project_id.as_deref())?;
+    check_prompt(&config, prompt).await
+}
@@ -0,0 +1,485 @@
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::scenes::{self, Scene};
+use crate::validation::{Validator, MAX_TEXT_LEN};
+use crate::versioning;
+use crate::workflow;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+use ts_rs::TS;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif"];
+
+/// Item sources whose image is a file we own in the media folder.
+const LOCAL_SOURCES: &[&str] = &["imported", "web"];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Moodboard {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub description: String,
+    #[ts(type = "number")]
+    pub sort_order: i64,
+    pub created_at: String,
+    #[ts(type = "number")]
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MoodboardItem {
+    pub id: String,
+    pub moodboard_id: String,
+    /// Local file path for imported images, URL for remote/generated ones.
+    pub image: String,
+    /// `"imported"`, `"generated"`, `"url"` or `"web"` (fetched from a page).
+    pub source: String,
+    pub caption: String,
+    /// Page the image was found on, for `"web"` items.
+    pub source_url: String,
+    pub include_in_prompt: bool,
+    #[ts(type = "number")]
+    pub sort_order: i64,
+    pub created_at: String,
+    #[ts(type = "number")]
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MoodboardItemInput {
+    /// Local file to copy in. Exactly one of `source_path`/`url` is required.
+    #[serde(default)]
+    pub source_path: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Set when `url` points at one of our own generations.
+    #[serde(default)]
+    pub generated: bool,
+    #[serde(default)]
+    pub caption: String,
+}
+
+const BOARD_COLUMNS: &str = "id, project_id, name, description, sort_order, created_at, version";
+const ITEM_COLUMNS: &str = "id, moodboard_id, image, source, caption, source_url, include_in_prompt, sort_order, created_at, version";
+
+fn row_to_board(row: &Row) -> rusqlite::Result<Moodboard> {
+    Ok(Moodboard {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        sort_order: row.get(4)?,
+        created_at: row.get(5)?,
+        version: row.get(6)?,
+    })
+}
+
+fn row_to_item(row: &Row) -> rusqlite::Result<MoodboardItem> {
+    Ok(MoodboardItem {
+        id: row.get(0)?,
+        moodboard_id: row.get(1)?,
+        image: row.get(2)?,
+        source: row.get(3)?,
+        caption: row.get(4)?,
+        source_url: row.get(5)?,
+        include_in_prompt: row.get(6)?,
+        sort_order: row.get(7)?,
+        created_at: row.get(8)?,
+        version: row.get(9)?,
+    })
+}
+
+pub fn get_board(conn: &Connection, id: &str) -> AppResult<Moodboard> {
+    conn.query_row(
+        &format!("SELECT {} FROM moodboards WHERE id = ?1", BOARD_COLUMNS),
+        [id],
+        row_to_board,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("moodboard {}", id)))
+}
+
+fn get_item(conn: &Connection, id: &str) -> AppResult<MoodboardItem> {
+    conn.query_row(
+        &format!("SELECT {} FROM moodboard_items WHERE id = ?1", ITEM_COLUMNS),
+        [id],
+        row_to_item,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("moodboard item {}", id)))
+}
+
+/// The board, unless its project is locked.
+fn editable_board(conn: &Connection, id: &str) -> AppResult<Moodboard> {
+    let board = get_board(conn, id)?;
+    projects::ensure_unlocked(conn, &board.project_id)?;
+    Ok(board)
+}
+
+/// Append an item to the end of a board.
+pub fn insert_item(
+    conn: &Connection,
+    id: &str,
+    moodboard_id: &str,
+    image: &str,
+    source: &str,
+    caption: &str,
+    source_url: &str,
+) -> AppResult<MoodboardItem> {
+    conn.execute(
+        "INSERT INTO moodboard_items (id, moodboard_id, image, source, caption, source_url, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6,
+            (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM moodboard_items WHERE moodboard_id = ?2))",
+        params![id, moodboard_id, image, source, caption, source_url],
+    )?;
+    get_item(conn, id)
+}
+
+/// Captions of items flagged for prompt inclusion across all of a
+/// project's boards, in board then item order.
+pub fn prompt_references(conn: &Connection, project_id: &str) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.caption FROM moodboard_items i
+         JOIN moodboards b ON b.id = i.moodboard_id
+         WHERE b.project_id = ?1 AND i.include_in_prompt = 1 AND i.caption != ''
+         ORDER BY b.sort_order, i.sort_order",
+    )?;
+    let captions = stmt
+        .query_map([project_id], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(captions)
+}
+
+pub fn list(conn: &Connection, project_id: &str) -> AppResult<Vec<Moodboard>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM moodboards WHERE project_id = ?1 ORDER BY sort_order, created_at",
+        BOARD_COLUMNS
+    ))?;
+    let boards = stmt
+        .query_map([project_id], row_to_board)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(boards)
+}
+
+#[tauri::command]
+pub fn list_moodboards(db: State<'_, Database>, project_id: String) -> AppResult<Vec<Moodboard>> {
+    list(&db.conn(), &project_id)
+}
+
+pub fn create(
+    conn: &Connection,
+    project_id: &str,
+    name: &str,
+    description: &str,
+) -> AppResult<Moodboard> {
+    let mut v = Validator::new();
+    v.uuid("projectId", project_id).name("name", name).max_len(
+        "description",
+        description,
+        MAX_TEXT_LEN,
+    );
+    v.finish()?;
+    let id = uuid::Uuid::new_v4().to_string();
+    projects::ensure_unlocked(conn, project_id)?;
+    conn.execute(
+        "INSERT INTO moodboards (id, project_id, name, description, sort_order)
+         VALUES (?1, ?2, ?3, ?4,
+            (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM moodboards WHERE project_id = ?2))",
+        params![id, project_id, name.trim(), description],
+    )?;
+    get_board(conn, &id)
+}
+
+#[tauri::command]
+pub fn create_moodboard(
+    db: State<'_, Database>,
+    project_id: String,
+    name: String,
+    description: Option<String>,
+) -> AppResult<Moodboard> {
+    create(
+        &db.conn(),
+        &project_id,
+        &name,
+        &description.unwrap_or_default(),
+    )
+}
+
+pub fn update(
+    conn: &Connection,
+    id: &str,
+    name: &str,
+    description: &str,
+    expected_version: Option<i64>,
+) -> AppResult<Moodboard> {
+    let mut v = Validator::new();
+    v.uuid("id", id)
+        .name("name", name)
+        .max_len("description", description, MAX_TEXT_LEN);
+    v.finish()?;
+    let before = editable_board(conn, id)?;
+    versioning::check(
+        "moodboard",
+        id,
+        expected_version,
+        before.version,
+        &before,
+        &serde_json::json!({ "name": name, "description": description }),
+    )?;
+    let changed = conn.execute(
+        "UPDATE moodboards SET name = ?2, description = ?3 WHERE id = ?1",
+        params![id, name.trim(), description],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("moodboard {}", id)));
+    }
+    get_board(conn, id)
+}
+
+#[tauri::command]
+pub fn update_moodboard(
+    db: State<'_, Database>,
+    id: String,
+    name: String,
+    description: String,
+    expected_version: Option<i64>,
+) -> AppResult<Moodboard> {
+    update(&db.conn(), &id, &name, &description, expected_version)
+}
+
+/// Delete a board and its items, removing any images we copied in.
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    editable_board(conn, id)?;
+    let mut stmt =
+        conn.prepare("SELECT image, source FROM moodboard_items WHERE moodboard_id = ?1")?;
+    let files = stmt
+        .query_map([id], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|(_, source)| LOCAL_SOURCES.contains(&source.as_str()))
+        .map(|(image, _)| image)
+        .collect::<Vec<_>>();
+    let changed = conn.execute("DELETE FROM moodboards WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("moodboard {}", id)));
+    }
+    for file in files {
+        std::fs::remove_file(file).ok();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_moodboard(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
+
+pub fn items(conn: &Connection, moodboard_id: &str) -> AppResult<Vec<MoodboardItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM moodboard_items WHERE moodboard_id = ?1 ORDER BY sort_order, created_at",
+        ITEM_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map([moodboard_id], row_to_item)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+#[tauri::command]
+pub fn list_moodboard_items(
+    db: State<'_, Database>,
+    moodboard_id: String,
+) -> AppResult<Vec<MoodboardItem>> {
+    items(&db.conn(), &moodboard_id)
+}
+
+/// Add an image to the end of a board, either by copying a local file into
+/// the media folder or by referencing a URL.
+pub fn add_item(
+    conn: &Connection,
+    media_dir: &Path,
+    moodboard_id: &str,
+    input: &MoodboardItemInput,
+) -> AppResult<MoodboardItem> {
+    editable_board(conn, moodboard_id)?;
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let (image, source) = match (input.source_path.as_deref(), input.url.as_deref()) {
+        (Some(path), None) => {
+            let path = Path::new(path);
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_lowercase)
+                .unwrap_or_default();
+            if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+                return Err(AppError::Invalid(format!(
+                    "unsupported image format '{}'",
+                    ext
+                )));
+            }
+            let dest = media_dir.join(format!("{}.{}", id, ext));
+            std::fs::copy(path, &dest)?;
+            (dest.to_string_lossy().to_string(), "imported")
+        }
+        (None, Some(url)) if url.starts_with("http") || url.starts_with("data:image/") => (
+            url.to_string(),
+            if input.generated { "generated" } else { "url" },
+        ),
+        _ => {
+            return Err(AppError::Invalid(
+                "provide either a local image path or an image URL".into(),
+            ))
+        }
+    };
+
+    insert_item(conn, &id, moodboard_id, &image, source, &input.caption, "")
+}
+
+#[tauri::command]
+pub fn add_moodboard_item(
+    db: State<'_, Database>,
+    moodboard_id: String,
+    input: MoodboardItemInput,
+) -> AppResult<MoodboardItem> {
+    add_item(
+        &db.conn(),
+        &db::media_dir("moodboards")?,
+        &moodboard_id,
+        &input,
+    )
+}
+
+pub fn update_item(
+    conn: &Connection,
+    id: &str,
+    caption: &str,
+    include_in_prompt: bool,
+    expected_version: Option<i64>,
+) -> AppResult<MoodboardItem> {
+    let mut v = Validator::new();
+    v.uuid("id", id).max_len("caption", caption, MAX_TEXT_LEN);
+    v.finish()?;
+    let before = get_item(conn, id)?;
+    editable_board(conn, &before.moodboard_id)?;
+    versioning::check(
+        "moodboard_item",
+        id,
+        expected_version,
+        before.version,
+        &before,
+        &serde_json::json!({ "caption": caption, "includeInPrompt": include_in_prompt }),
+    )?;
+    let changed = conn.execute(
+        "UPDATE moodboard_items SET caption = ?2, include_in_prompt = ?3 WHERE id = ?1",
+        params![id, caption, include_in_prompt],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("moodboard item {}", id)));
+    }
+    get_item(conn, id)
+}
+
+#[tauri::command]
+pub fn update_moodboard_item(
+    db: State<'_, Database>,
+    id: String,
+    caption: String,
+    include_in_prompt: bool,
+    expected_version: Option<i64>,
+) -> AppResult<MoodboardItem> {
+    update_item(
+        &db.conn(),
+        &id,
+        &caption,
+        include_in_prompt,
+        expected_version,
+    )
+}
+
+/// Rewrite item order to match `item_ids`. Items not listed keep their
+/// relative order after the listed ones.
+pub fn reorder_items(conn: &Connection, moodboard_id: &str, item_ids: &[String]) -> AppResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    editable_board(&tx, moodboard_id)?;
+    let offset = item_ids.len() as i64;
+    tx.execute(
+        "UPDATE moodboard_items SET sort_order = sort_order + ?2 WHERE moodboard_id = ?1",
+        params![moodboard_id, offset],
+    )?;
+    for (position, item_id) in item_ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE moodboard_items SET sort_order = ?3 WHERE id = ?1 AND moodboard_id = ?2",
+            params![item_id, moodboard_id, position as i64],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn reorder_moodboard_items(
+    db: State<'_, Database>,
+    moodboard_id: String,
+    item_ids: Vec<String>,
+) -> AppResult<()> {
+    reorder_items(&db.conn(), &moodboard_id, &item_ids)
+}
+
+pub fn delete_item(conn: &Connection, id: &str) -> AppResult<()> {
+    let item = get_item(conn, id)?;
+    editable_board(conn, &item.moodboard_id)?;
+    conn.execute("DELETE FROM moodboard_items WHERE id = ?1", [id])?;
+    if LOCAL_SOURCES.contains(&item.source.as_str()) {
+        std::fs::remove_file(item.image).ok();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_moodboard_item(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete_item(&db.conn(), &id)
+}
+
+/// Use a moodboard image as the conditioning (image-to-video) frame for a
+/// scene in the same project.
+pub fn set_scene_conditioning(
+    conn: &Connection,
+    item_id: &str,
+    scene_id: &str,
+) -> AppResult<Scene> {
+    let item = get_item(conn, item_id)?;
+    let board = get_board(conn, &item.moodboard_id)?;
+    workflow::ensure_editable(conn, scene_id)?;
+    let scene = scenes::get_scene(conn, scene_id)?;
+    if scene.project_id != board.project_id {
+        return Err(AppError::Invalid(
+            "moodboard and scene belong to different projects".into(),
+        ));
+    }
+    conn.execute(
+        "UPDATE scenes SET conditioning_image = ?2 WHERE id = ?1",
+        params![scene_id, item.image],
+    )?;
+    scenes::get_scene(conn, scene_id)
+}
+
+#[tauri::command]
+pub fn set_scene_conditioning_from_moodboard(
+    db: State<'_, Database>,
+    item_id: String,
+    scene_id: String,
+) -> AppResult<Scene> {
+    set_scene_conditioning(&db.conn(), &item_id, &scene_id)
+}
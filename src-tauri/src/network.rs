@@ -0,0 +1,153 @@
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::events::AppEvent;
+use crate::{jobs, providers, settings};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Settings key for the user-forced offline switch.
+const OFFLINE_MODE_SETTING: &str = "offline_mode";
+
+/// Connectivity as seen by the backend. `online` is the last probe result;
+/// `forced_offline` is the user's offline-mode switch, which wins over it.
+pub struct NetworkMonitor {
+    online: AtomicBool,
+    forced_offline: AtomicBool,
+    flushing: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct NetworkStatus {
+    pub online: bool,
+    pub forced_offline: bool,
+    #[ts(type = "number")]
+    pub deferred_jobs: i64,
+}
+
+impl NetworkMonitor {
+    pub fn new(forced_offline: bool) -> Self {
+        Self {
+            // Optimistic until the first probe lands; a failed submit will
+            // defer the job anyway.
+            online: AtomicBool::new(true),
+            forced_offline: AtomicBool::new(forced_offline),
+            flushing: AtomicBool::new(false),
+        }
+    }
+
+    pub fn can_submit(&self) -> bool {
+        self.online.load(Ordering::SeqCst) && !self.forced_offline.load(Ordering::SeqCst)
+    }
+
+    pub fn mark_offline(&self) {
+        self.online.store(false, Ordering::SeqCst);
+    }
+
+    /// Claim the flush slot; returns false if a flush is already running.
+    pub fn begin_flush(&self) -> bool {
+        !self.flushing.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn end_flush(&self) {
+        self.flushing.store(false, Ordering::SeqCst);
+    }
+
+    /// Re-read the offline switch after the database changed underneath us.
+    pub fn reload(&self, db: &Database) {
+        self.forced_offline
+            .store(load_forced_offline(db), Ordering::SeqCst);
+    }
+
+    pub fn status(&self, conn: &Connection) -> AppResult<NetworkStatus> {
+        Ok(NetworkStatus {
+            online: self.online.load(Ordering::SeqCst),
+            forced_offline: self.forced_offline.load(Ordering::SeqCst),
+            deferred_jobs: jobs::count_deferred(conn)?,
+        })
+    }
+
+    /// Switch offline mode on or off and remember it.
+    pub fn set_forced_offline(&self, conn: &Connection, enabled: bool) -> AppResult<()> {
+        settings::set(
+            conn,
+            OFFLINE_MODE_SETTING,
+            if enabled { "true" } else { "false" },
+        )?;
+        self.forced_offline.store(enabled, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+pub fn load_forced_offline(db: &Database) -> bool {
+    settings::get_bool(&db.conn(), OFFLINE_MODE_SETTING).unwrap_or(false)
+}
+
+/// Cheap reachability check: can we open a TCP connection to the provider queue?
+async fn probe() -> bool {
+    matches!(
+        tokio::time::timeout(
+            PROBE_TIMEOUT,
+            tokio::net::TcpStream::connect((providers::FAL_QUEUE_HOST, 443)),
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+fn emit_status(app: &AppHandle) {
+    let net = app.state::<NetworkMonitor>();
+    let status = net.status(&app.state::<Database>().conn());
+    if let Ok(status) = status {
+        AppEvent::NetworkStatusChanged(status).emit(app);
+    }
+}
+
+/// Poll connectivity in the background, emitting `network-status-changed`
+/// on every transition and flushing deferred jobs when we come back online.
+pub fn spawn_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let up = probe().await;
+            let was_up = {
+                let net = app.state::<NetworkMonitor>();
+                net.online.swap(up, Ordering::SeqCst)
+            };
+            if up != was_up {
+                emit_status(&app);
+            }
+            if up {
+                // Also covers jobs deferred before the app last quit.
+                jobs::flush_deferred(&app).await;
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_network_status(
+    db: State<'_, Database>,
+    net: State<'_, NetworkMonitor>,
+) -> AppResult<NetworkStatus> {
+    net.status(&db.conn())
+}
+
+#[tauri::command]
+pub async fn set_offline_mode(app: AppHandle, enabled: bool) -> AppResult<NetworkStatus> {
+    let net = app.state::<NetworkMonitor>();
+    net.set_forced_offline(&app.state::<Database>().conn(), enabled)?;
+    emit_status(&app);
+    if !enabled {
+        jobs::flush_deferred(&app).await;
+    }
+    net.status(&app.state::<Database>().conn())
+}
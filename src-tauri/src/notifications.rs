@@ -0,0 +1,510 @@
+use crate::clock;
+use crate::confidential;
+use crate::db::Database;
+use crate::deep_link;
+use crate::email;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::jobs::{self, status};
+use crate::providers;
+use crate::publishing;
+use crate::scenes;
+use crate::secrets;
+use crate::settings;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Settings key: alert once a project's recorded spend reaches this many
+/// dollars. Unset or `0` disables budget alerts.
+pub const BUDGET_LIMIT_SETTING: &str = "notifications.budget_limit";
+
+pub const WEBHOOK_KINDS: &[&str] = &["slack", "discord", "generic"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    JobCompleted,
+    BatchCompleted,
+    RenderFinished,
+    BudgetAlert,
+}
+
+impl NotifyEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotifyEvent::JobCompleted => "job_completed",
+            NotifyEvent::BatchCompleted => "batch_completed",
+            NotifyEvent::RenderFinished => "render_finished",
+            NotifyEvent::BudgetAlert => "budget_alert",
+        }
+    }
+
+    /// Message used when a webhook has no template of its own.
+    fn default_template(self) -> &'static str {
+        match self {
+            NotifyEvent::JobCompleted => {
+                "{{project}}: scene {{scene}} take from {{provider}} {{status}}"
+            }
+            NotifyEvent::BatchCompleted => {
+                "{{project}}: batch finished, {{succeeded}}/{{total}} succeeded"
+            }
+            NotifyEvent::RenderFinished => "{{project}}: render finished ({{output}})",
+            NotifyEvent::BudgetAlert => {
+                "{{project}}: spend ${{spend}} has reached the ${{limit}} budget"
+            }
+        }
+    }
+}
+
+/// Something worth telling the team about. `fields` fill `{{name}}`
+/// placeholders in templates and go out as-is in generic payloads.
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub project_id: String,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl Notification {
+    pub fn render(&self, template: &str) -> String {
+        let template = if template.trim().is_empty() {
+            self.event.default_template()
+        } else {
+            template
+        };
+        self.fields
+            .iter()
+            .fold(template.to_string(), |text, (key, value)| {
+                text.replace(&format!("{{{{{}}}}}", key), value)
+            })
+    }
+
+    fn fields_json(&self) -> Value {
+        Value::Object(
+            self.fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::String(v.clone())))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Webhook {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub events: Vec<NotifyEvent>,
+    pub template: String,
+    pub enabled: bool,
+    /// Whether a URL is stored. The URL itself carries the token, so it
+    /// never leaves the secrets store.
+    pub has_url: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct WebhookInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    pub kind: String,
+    pub events: Vec<NotifyEvent>,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// New URL; omit to keep the stored one.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn url_secret(id: &str) -> String {
+    format!("webhook.{}.url", id)
+}
+
+const WEBHOOK_COLUMNS: &str = "id, name, kind, events_json, template, enabled, created_at";
+
+fn row_to_webhook(row: &Row) -> rusqlite::Result<Webhook> {
+    let events_json: String = row.get(3)?;
+    Ok(Webhook {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: row.get(2)?,
+        events: serde_json::from_str(&events_json).unwrap_or_default(),
+        template: row.get(4)?,
+        enabled: row.get(5)?,
+        has_url: false,
+        created_at: row.get(6)?,
+    })
+}
+
+fn get_webhook(conn: &Connection, id: &str) -> AppResult<Webhook> {
+    let mut hook = conn
+        .query_row(
+            &format!("SELECT {} FROM webhooks WHERE id = ?1", WEBHOOK_COLUMNS),
+            [id],
+            row_to_webhook,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("webhook {}", id)))?;
+    hook.has_url = secrets::get(conn, &url_secret(id))?.is_some();
+    Ok(hook)
+}
+
+fn payload(kind: &str, notification: &Notification, text: &str) -> Value {
+    match kind {
+        "slack" => json!({ "text": text }),
+        "discord" => json!({ "content": text }),
+        _ => json!({
+            "event": notification.event.as_str(),
+            "projectId": notification.project_id,
+            "message": text,
+            "fields": notification.fields_json(),
+        }),
+    }
+}
+
+async fn deliver(url: &str, body: &Value) -> AppResult<()> {
+    let res = providers::client().post(url).json(body).send().await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "webhook ({}): {}",
+            status, text
+        )));
+    }
+    Ok(())
+}
+
+/// Send a notification to every enabled webhook subscribed to its event,
+/// and by email if configured. Delivery happens in the background;
+/// failures are logged, not surfaced. Confidential projects send nothing.
+pub fn notify(app: &AppHandle, notification: Notification) {
+    let confidential =
+        confidential::is_confidential(&app.state::<Database>().conn(), &notification.project_id);
+    if !matches!(confidential, Ok(false)) {
+        return;
+    }
+    email::notify(app, &notification);
+    let targets: AppResult<Vec<(String, Value)>> = (|| {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM webhooks WHERE enabled = 1",
+            WEBHOOK_COLUMNS
+        ))?;
+        let hooks = stmt
+            .query_map([], row_to_webhook)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut targets = Vec::new();
+        for hook in hooks
+            .into_iter()
+            .filter(|h| h.events.contains(&notification.event))
+        {
+            if let Some(url) = secrets::get(&conn, &url_secret(&hook.id))? {
+                let text = notification.render(&hook.template);
+                targets.push((url, payload(&hook.kind, &notification, &text)));
+            }
+        }
+        Ok(targets)
+    })();
+
+    match targets {
+        Ok(targets) => {
+            for (url, body) in targets {
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = deliver(&url, &body).await {
+                        tracing::warn!(error = %e, "webhook delivery failed");
+                    }
+                });
+            }
+        }
+        Err(e) => tracing::error!(error = %e, "failed to load webhooks"),
+    }
+}
+
+fn batch_key(project_id: &str) -> String {
+    format!("notifications.batch.{}", project_id)
+}
+
+fn project_for_scene(conn: &Connection, scene_id: &str) -> AppResult<String> {
+    Ok(scenes::get_scene(conn, scene_id)?.project_id)
+}
+
+/// Note when a project goes from idle to having jobs in flight, so the
+/// batch can be summed up when the last one finishes.
+pub fn batch_started(conn: &Connection, scene_id: &str) -> AppResult<()> {
+    let project_id = project_for_scene(conn, scene_id)?;
+    if settings::get(conn, &batch_key(&project_id))?.is_none() {
+        settings::set(conn, &batch_key(&project_id), &clock::now())?;
+    }
+    Ok(())
+}
+
+/// Fire job, batch and budget notifications for a job that just finished.
+pub fn job_finished(app: &AppHandle, job_id: &str) {
+    let result: AppResult<Vec<Notification>> = (|| {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let job = jobs::get_job(&conn, job_id)?;
+        let scene = scenes::get_scene(&conn, &job.scene_id)?;
+        let project = export::load_project(&conn, &scene.project_id)?;
+        let mut out = vec![Notification {
+            event: NotifyEvent::JobCompleted,
+            project_id: scene.project_id.clone(),
+            fields: vec![
+                ("project", project.name.clone()),
+                ("scene", scene.scene_number.to_string()),
+                ("provider", job.provider.clone()),
+                ("status", job.status.clone()),
+                ("error", job.error.clone()),
+                ("videoUrl", job.video_url.clone()),
+                ("link", deep_link::scene_link(&scene.project_id, &scene.id)),
+            ],
+        }];
+
+        let outstanding: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+             WHERE s.project_id = ?1 AND j.status IN (?2, ?3, ?4, ?5)",
+            params![
+                scene.project_id,
+                status::OFFLINE,
+                status::PENDING,
+                status::QUEUED,
+                status::WAITING
+            ],
+            |r| r.get(0),
+        )?;
+        if outstanding == 0 {
+            if let Some(since) = settings::get(&conn, &batch_key(&scene.project_id))? {
+                conn.execute(
+                    "DELETE FROM settings WHERE key = ?1",
+                    [batch_key(&scene.project_id)],
+                )?;
+                let (total, succeeded): (i64, i64) = conn.query_row(
+                    "SELECT COUNT(*), COALESCE(SUM(j.status = ?3), 0)
+                     FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+                     WHERE s.project_id = ?1 AND j.started_at >= ?2",
+                    params![scene.project_id, since, status::COMPLETED],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )?;
+                if total > 1 {
+                    out.push(Notification {
+                        event: NotifyEvent::BatchCompleted,
+                        project_id: scene.project_id.clone(),
+                        fields: vec![
+                            ("project", project.name.clone()),
+                            ("succeeded", succeeded.to_string()),
+                            ("failed", (total - succeeded).to_string()),
+                            ("total", total.to_string()),
+                            ("link", deep_link::project_link(&scene.project_id)),
+                        ],
+                    });
+                }
+            }
+        }
+
+        let limit = settings::get(&conn, BUDGET_LIMIT_SETTING)?
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|l| *l > 0.0);
+        if let Some(limit) = limit {
+            let spend: f64 = conn.query_row(
+                "SELECT COALESCE(SUM(j.cost), 0) FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+                 WHERE s.project_id = ?1",
+                [&scene.project_id],
+                |r| r.get(0),
+            )?;
+            // Only on the job that crosses the line, not every one after.
+            if spend >= limit && spend - job.cost < limit {
+                out.push(Notification {
+                    event: NotifyEvent::BudgetAlert,
+                    project_id: scene.project_id.clone(),
+                    fields: vec![
+                        ("project", project.name),
+                        ("spend", format!("{:.2}", spend)),
+                        ("limit", format!("{:.2}", limit)),
+                    ],
+                });
+            }
+        }
+        Ok(out)
+    })();
+
+    match result {
+        Ok(notifications) => {
+            for notification in notifications {
+                notify(app, notification);
+            }
+        }
+        Err(e) => tracing::error!(job_id, error = %e, "failed to build job notifications"),
+    }
+}
+
+pub fn list(conn: &Connection) -> AppResult<Vec<Webhook>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM webhooks ORDER BY created_at, rowid",
+        WEBHOOK_COLUMNS
+    ))?;
+    let mut hooks = stmt
+        .query_map([], row_to_webhook)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for hook in &mut hooks {
+        hook.has_url = secrets::get(conn, &url_secret(&hook.id))?.is_some();
+    }
+    Ok(hooks)
+}
+
+#[tauri::command]
+pub fn list_webhooks(db: State<'_, Database>) -> AppResult<Vec<Webhook>> {
+    list(&db.conn())
+}
+
+pub fn save(conn: &Connection, input: &WebhookInput) -> AppResult<Webhook> {
+    if input.name.trim().is_empty() {
+        return Err(AppError::Invalid("webhook name is required".into()));
+    }
+    if !WEBHOOK_KINDS.contains(&input.kind.as_str()) {
+        return Err(AppError::Invalid(format!(
+            "unknown webhook kind '{}'",
+            input.kind
+        )));
+    }
+    if let Some(url) = &input.url {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(AppError::Invalid("webhook URL must be http(s)".into()));
+        }
+    }
+    let events_json = serde_json::to_string(&input.events)?;
+    let id = match &input.id {
+        Some(id) => {
+            let changed = conn.execute(
+                "UPDATE webhooks SET name = ?2, kind = ?3, events_json = ?4, template = ?5, enabled = ?6
+                 WHERE id = ?1",
+                params![
+                    id,
+                    input.name.trim(),
+                    input.kind,
+                    events_json,
+                    input.template,
+                    input.enabled
+                ],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("webhook {}", id)));
+            }
+            id.clone()
+        }
+        None => {
+            if input.url.is_none() {
+                return Err(AppError::Invalid("webhook URL is required".into()));
+            }
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO webhooks (id, name, kind, events_json, template, enabled)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    id,
+                    input.name.trim(),
+                    input.kind,
+                    events_json,
+                    input.template,
+                    input.enabled
+                ],
+            )?;
+            id
+        }
+    };
+    if let Some(url) = &input.url {
+        secrets::set(conn, &url_secret(&id), url)?;
+    }
+    get_webhook(conn, &id)
+}
+
+#[tauri::command]
+pub fn save_webhook(db: State<'_, Database>, input: WebhookInput) -> AppResult<Webhook> {
+    save(&db.conn(), &input)
+}
+
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let changed = conn.execute("DELETE FROM webhooks WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("webhook {}", id)));
+    }
+    secrets::delete(conn, &url_secret(id))
+}
+
+#[tauri::command]
+pub fn delete_webhook(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
+
+/// Build the sample message `test_webhook` sends: the hook's URL and the
+/// payload for its kind.
+pub fn test_delivery(conn: &Connection, id: &str) -> AppResult<(String, Value)> {
+    let hook = get_webhook(conn, id)?;
+    let url = secrets::get(conn, &url_secret(id))?
+        .ok_or_else(|| AppError::Invalid("webhook has no URL".into()))?;
+    let sample = Notification {
+        event: NotifyEvent::JobCompleted,
+        project_id: String::new(),
+        fields: vec![
+            ("project", "Test project".into()),
+            ("scene", "1".into()),
+            ("provider", "kling".into()),
+            ("status", status::COMPLETED.into()),
+        ],
+    };
+    let text = sample.render(&hook.template);
+    Ok((url, payload(&hook.kind, &sample, &text)))
+}
+
+/// Send a sample message through one webhook and report any error.
+#[tauri::command]
+pub async fn test_webhook(app: AppHandle, id: String) -> AppResult<()> {
+    let (url, body) = test_delivery(&app.state::<Database>().conn(), &id)?;
+    deliver(&url, &body).await
+}
+
+/// Renders are assembled in the frontend; it calls this when one is done.
+/// The render is recorded so it can be published later (see `publishing`),
+/// and the notification to send for it is returned alongside.
+pub fn render_finished(
+    conn: &Connection,
+    project_id: &str,
+    output: &str,
+) -> AppResult<(publishing::Render, Notification)> {
+    let project = export::load_project(conn, project_id)?;
+    let render = publishing::record_render(conn, project_id, output)?;
+    Ok((
+        render,
+        Notification {
+            event: NotifyEvent::RenderFinished,
+            project_id: project_id.to_string(),
+            fields: vec![("project", project.name), ("output", output.to_string())],
+        },
+    ))
+}
+
+#[tauri::command]
+pub fn notify_render_finished(
+    app: AppHandle,
+    project_id: String,
+    output: String,
+) -> AppResult<publishing::Render> {
+    let (render, notification) =
+        render_finished(&app.state::<Database>().conn(), &project_id, &output)?;
+    notify(&app, notification);
+    Ok(render)
+}
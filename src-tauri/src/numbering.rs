@@ -0,0 +1,194 @@
+//! Scene numbering. Numbers drift as scenes are inserted, moved and
+//! deleted; `renumber_scenes` makes them follow board order again, either
+//! from 1 or in the production style that keeps existing numbers and
+//! letters the scenes inserted between them (12, 12A, 12B, 13).
+//!
+//! Numbering is structure rather than content, so locked scenes are
+//! renumbered too. Each change goes through the sync change log like any
+//! other edit, and the whole mapping is recorded in the activity feed.
+
+use crate::activity::{self, FieldChange};
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::projects;
+use crate::scenes::{self, Scene};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum RenumberStrategy {
+    /// 1, 2, 3… in board order, dropping any letters.
+    #[default]
+    Sequential,
+    /// Keep the longest run of numbers already in order; every other scene
+    /// takes the number before it plus a letter. Scenes ahead of the first
+    /// kept number are lettered from 0 (0A, 0B).
+    InsertStyle,
+}
+
+impl RenumberStrategy {
+    fn as_str(self) -> &'static str {
+        match self {
+            RenumberStrategy::Sequential => "sequential",
+            RenumberStrategy::InsertStyle => "insert-style",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Renumbered {
+    pub scene_id: String,
+    pub old_label: String,
+    pub new_label: String,
+}
+
+pub fn label(number: i64, suffix: &str) -> String {
+    format!("{}{}", number, suffix)
+}
+
+/// `A`…`Z`, then `AA`, `AB`… for the `n`th (from 0) inserted scene.
+fn letters(mut n: usize) -> String {
+    let mut out = Vec::new();
+    loop {
+        out.push(b'A' + (n % 26) as u8);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    out.reverse();
+    String::from_utf8(out).expect("ASCII letters")
+}
+
+/// Indexes of the longest strictly increasing run of scene numbers, among
+/// scenes without a letter.
+fn kept_numbers(scenes: &[Scene]) -> Vec<bool> {
+    let candidates: Vec<usize> = (0..scenes.len())
+        .filter(|&i| scenes[i].scene_suffix.is_empty() && scenes[i].scene_number > 0)
+        .collect();
+    // Patience sorting: tails[k] is the candidate ending the best run of
+    // length k + 1; prev links runs back together.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = vec![None; scenes.len()];
+    for &i in &candidates {
+        let n = scenes[i].scene_number;
+        let k = tails.partition_point(|&t| scenes[t].scene_number < n);
+        prev[i] = k.checked_sub(1).map(|p| tails[p]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+    let mut kept = vec![false; scenes.len()];
+    let mut cur = tails.last().copied();
+    while let Some(i) = cur {
+        kept[i] = true;
+        cur = prev[i];
+    }
+    kept
+}
+
+fn plan(scenes: &[Scene], strategy: RenumberStrategy) -> Vec<(i64, String)> {
+    match strategy {
+        RenumberStrategy::Sequential => (1..=scenes.len() as i64)
+            .map(|n| (n, String::new()))
+            .collect(),
+        RenumberStrategy::InsertStyle => {
+            let kept = kept_numbers(scenes);
+            let mut base = 0;
+            let mut inserted = 0;
+            scenes
+                .iter()
+                .zip(kept)
+                .map(|(s, kept)| {
+                    if kept {
+                        base = s.scene_number;
+                        inserted = 0;
+                        (base, String::new())
+                    } else {
+                        inserted += 1;
+                        (base, letters(inserted - 1))
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+pub fn renumber(
+    conn: &Connection,
+    project_id: &str,
+    strategy: RenumberStrategy,
+) -> AppResult<Vec<Renumbered>> {
+    projects::get_project(conn, project_id)?;
+    let scenes = scenes::list_for_project(conn, project_id)?;
+    let mut mapping = Vec::new();
+    for (scene, (number, suffix)) in scenes.iter().zip(plan(&scenes, strategy)) {
+        if number == scene.scene_number && suffix == scene.scene_suffix {
+            continue;
+        }
+        conn.execute(
+            "UPDATE scenes SET scene_number = ?2, scene_suffix = ?3 WHERE id = ?1",
+            params![scene.id, number, suffix],
+        )?;
+        mapping.push(Renumbered {
+            scene_id: scene.id.clone(),
+            old_label: label(scene.scene_number, &scene.scene_suffix),
+            new_label: label(number, &suffix),
+        });
+    }
+    if !mapping.is_empty() {
+        let changes: Vec<FieldChange> = mapping
+            .iter()
+            .map(|m| FieldChange {
+                field: m.scene_id.clone(),
+                old: m.old_label.clone(),
+                new: m.new_label.clone(),
+            })
+            .collect();
+        activity::record(
+            conn,
+            project_id,
+            "project",
+            project_id,
+            "renumbered",
+            &format!(
+                "Renumbered {} scenes ({})",
+                mapping.len(),
+                strategy.as_str()
+            ),
+            &changes,
+        )?;
+    }
+    Ok(mapping)
+}
+
+/// Renumber a project's scenes in board order. Returns the scenes whose
+/// number changed, old and new.
+pub fn renumber_project(
+    conn: &Connection,
+    project_id: &str,
+    strategy: RenumberStrategy,
+) -> AppResult<Vec<Renumbered>> {
+    let tx = conn.unchecked_transaction()?;
+    projects::ensure_unlocked(&tx, project_id)?;
+    let mapping = renumber(&tx, project_id, strategy)?;
+    tx.commit()?;
+    Ok(mapping)
+}
+
+#[tauri::command]
+pub fn renumber_scenes(
+    db: State<'_, Database>,
+    project_id: String,
+    strategy: Option<RenumberStrategy>,
+) -> AppResult<Vec<Renumbered>> {
+    renumber_project(&db.conn(), &project_id, strategy.unwrap_or_default())
+}
@@ -0,0 +1,367 @@
+//! OAuth sign-in for services that don't hand out API keys: authorization
+//! code with PKCE through a loopback redirect, token refresh, and token
+//! storage in the OS keyring. Services opt in by describing themselves
+//! with an `OAuthClient`; see `cloud_sync` and the AI providers listed in
+//! `PROVIDERS`.
+
+use crate::clock;
+use crate::db::Database;
+use crate::encryption::KEYRING_SERVICE;
+use crate::error::{AppError, AppResult};
+use crate::events::{AppEvent, SignInFailed};
+use crate::{llm, providers};
+use base64::Engine as _;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use ts_rs::TS;
+
+/// How long to wait for the user to finish signing in.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Access tokens this close to expiry are refreshed before use.
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// AI providers that can be connected with OAuth instead of an API key.
+pub const PROVIDERS: &[&str] = &["gemini"];
+
+/// How to sign in to one service.
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    /// Names the tokens in the keyring, e.g. `cloud.dropbox`.
+    pub account: String,
+    pub auth_url: &'static str,
+    pub token_url: &'static str,
+    pub client_id: String,
+    /// For providers that want their (non-confidential) desktop client
+    /// secret sent too.
+    pub client_secret: Option<&'static str>,
+    /// Scopes and provider-specific switches for the sign-in URL.
+    pub extra_auth_params: &'static [(&'static str, &'static str)],
+}
+
+/// What we keep in the keyring for a connected account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Tokens {
+    refresh_token: String,
+    #[serde(default)]
+    access_token: String,
+    #[serde(default)]
+    expires_at: Option<String>,
+}
+
+fn entry(account: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("oauth:{}", account))
+}
+
+fn keyring_err(e: keyring::Error) -> AppError {
+    AppError::Io(format!("OS keyring: {}", e))
+}
+
+fn load(account: &str) -> AppResult<Option<Tokens>> {
+    match entry(account).and_then(|e| e.get_password()) {
+        Ok(json) => Ok(serde_json::from_str(&json).ok()),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keyring_err(e)),
+    }
+}
+
+fn store(account: &str, tokens: &Tokens) -> AppResult<()> {
+    let json = serde_json::to_string(tokens)?;
+    entry(account)
+        .and_then(|e| e.set_password(&json))
+        .map_err(keyring_err)
+}
+
+/// Keep a refresh token obtained elsewhere, e.g. one saved by an older
+/// version of the app.
+pub fn store_refresh_token(account: &str, refresh_token: &str) -> AppResult<()> {
+    store(
+        account,
+        &Tokens {
+            refresh_token: refresh_token.to_string(),
+            ..Default::default()
+        },
+    )
+}
+
+pub fn is_connected(account: &str) -> AppResult<bool> {
+    Ok(load(account)?.is_some())
+}
+
+/// Forget an account's tokens. Nothing is revoked with the provider.
+pub fn disconnect(account: &str) -> AppResult<()> {
+    match entry(account).and_then(|e| e.delete_credential()) {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keyring_err(e)),
+    }
+}
+
+fn pkce_pair() -> (String, String) {
+    let verifier = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(Sha256::digest(verifier.as_bytes()));
+    (verifier, challenge)
+}
+
+async fn token_request(client: &OAuthClient, mut form: Vec<(&str, String)>) -> AppResult<Tokens> {
+    form.push(("client_id", client.client_id.clone()));
+    if let Some(secret) = client.client_secret {
+        form.push(("client_secret", secret.to_string()));
+    }
+    let res = providers::client()
+        .post(client.token_url)
+        .form(&form)
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "{} sign-in ({}): {}",
+            client.account, status, body
+        )));
+    }
+    let body: Value = res.json().await?;
+    let access_token = body["access_token"]
+        .as_str()
+        .ok_or_else(|| AppError::Provider("sign-in response had no access token".into()))?;
+    Ok(Tokens {
+        refresh_token: body["refresh_token"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        access_token: access_token.to_string(),
+        expires_at: body["expires_in"]
+            .as_i64()
+            .map(|secs| clock::format(Utc::now() + ChronoDuration::seconds(secs))),
+    })
+}
+
+/// A current access token for a connected account, refreshing (and
+/// storing) it when it is missing or about to expire.
+pub async fn access_token(client: &OAuthClient) -> AppResult<String> {
+    let tokens = load(&client.account)?
+        .ok_or_else(|| AppError::Invalid(format!("{} is not connected", client.account)))?;
+    let fresh = tokens
+        .expires_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .is_some_and(|t| t > Utc::now() + ChronoDuration::seconds(EXPIRY_MARGIN_SECS));
+    if fresh && !tokens.access_token.is_empty() {
+        return Ok(tokens.access_token);
+    }
+    let mut refreshed = token_request(
+        client,
+        vec![
+            ("grant_type", "refresh_token".into()),
+            ("refresh_token", tokens.refresh_token.clone()),
+        ],
+    )
+    .await?;
+    // Most providers keep the refresh token; some rotate it.
+    if refreshed.refresh_token.is_empty() {
+        refreshed.refresh_token = tokens.refresh_token;
+    }
+    store(&client.account, &refreshed)?;
+    Ok(refreshed.access_token)
+}
+
+/// Wait for the browser to hit the loopback redirect and return the
+/// authorization code.
+async fn await_redirect(listener: tokio::net::TcpListener, state: &str) -> AppResult<String> {
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let mut buf = vec![0u8; 8192];
+        let n = socket.read(&mut buf).await?;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        let Some(target) = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+        else {
+            continue;
+        };
+        let Ok(url) = reqwest::Url::parse(&format!("http://127.0.0.1{}", target)) else {
+            continue;
+        };
+        if url.path() != "/callback" {
+            // Browsers also ask for /favicon.ico and the like.
+            let _ = socket
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .await;
+            continue;
+        }
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_string())
+        };
+        let result = match (param("code"), param("state"), param("error")) {
+            (_, _, Some(error)) => Err(AppError::Provider(format!("sign-in refused: {}", error))),
+            (Some(code), Some(s), None) if s == state => Ok(code),
+            _ => Err(AppError::Invalid("unexpected sign-in redirect".into())),
+        };
+        let message = if result.is_ok() {
+            "Signed in. You can close this window and return to AI Director's Chair."
+        } else {
+            "Sign-in failed. Return to AI Director's Chair and try again."
+        };
+        let page = format!(
+            "<!doctype html><html><body><p>{}</p></body></html>",
+            message
+        );
+        let _ = socket
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                )
+                .as_bytes(),
+            )
+            .await;
+        return result;
+    }
+}
+
+/// Start signing in to `client`'s service. Returns the URL for the
+/// frontend to open in the browser; a background task waits for the
+/// redirect, stores the tokens, and emits `event(None)`, or
+/// `event(Some(error))` if signing in failed.
+pub async fn connect(
+    app: &AppHandle,
+    client: OAuthClient,
+    event: impl FnOnce(Option<String>) -> AppEvent + Send + 'static,
+) -> AppResult<String> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+    let redirect_uri = format!(
+        "http://127.0.0.1:{}/callback",
+        listener.local_addr()?.port()
+    );
+    let (verifier, challenge) = pkce_pair();
+    let state = uuid::Uuid::new_v4().simple().to_string();
+
+    let mut query = vec![
+        ("client_id", client.client_id.as_str()),
+        ("response_type", "code"),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("code_challenge", challenge.as_str()),
+        ("code_challenge_method", "S256"),
+        ("state", state.as_str()),
+    ];
+    query.extend_from_slice(client.extra_auth_params);
+    let auth_url = reqwest::Url::parse_with_params(client.auth_url, &query)
+        .map_err(|e| AppError::Invalid(e.to_string()))?
+        .to_string();
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = async {
+            let code = tokio::time::timeout(AUTH_TIMEOUT, await_redirect(listener, &state))
+                .await
+                .map_err(|_| AppError::Network("timed out waiting for sign-in".into()))??;
+            let tokens = token_request(
+                &client,
+                vec![
+                    ("grant_type", "authorization_code".into()),
+                    ("code", code),
+                    ("redirect_uri", redirect_uri),
+                    ("code_verifier", verifier),
+                ],
+            )
+            .await?;
+            if tokens.refresh_token.is_empty() {
+                return Err(AppError::Provider(
+                    "sign-in response had no refresh token".into(),
+                ));
+            }
+            store(&client.account, &tokens)
+        }
+        .await;
+        let error = result.err().map(|e| {
+            tracing::warn!(account = %client.account, error = %e, "OAuth sign-in failed");
+            e.to_string()
+        });
+        event(error).emit(&handle);
+    });
+    Ok(auth_url)
+}
+
+/// The sign-in client for an AI provider in `PROVIDERS`.
+/// The sign-in client for an AI provider, if it supports OAuth and one is
+/// configured.
+pub fn provider_client(conn: &Connection, provider: &str) -> AppResult<OAuthClient> {
+    match provider {
+        "gemini" => llm::oauth_client(conn),
+        other => Err(AppError::Invalid(format!(
+            "{} doesn't support OAuth sign-in",
+            other
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProviderAccount {
+    pub provider: String,
+    /// An OAuth client is configured for this provider.
+    pub available: bool,
+    pub connected: bool,
+}
+
+/// AI providers that can sign in with OAuth, and which are connected.
+pub fn accounts(conn: &Connection) -> AppResult<Vec<ProviderAccount>> {
+    PROVIDERS
+        .iter()
+        .map(|&provider| {
+            let client = provider_client(conn, provider);
+            Ok(ProviderAccount {
+                provider: provider.to_string(),
+                available: client.is_ok(),
+                connected: match client {
+                    Ok(c) => is_connected(&c.account)?,
+                    Err(_) => false,
+                },
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_provider_accounts(db: State<'_, Database>) -> AppResult<Vec<ProviderAccount>> {
+    accounts(&db.conn())
+}
+
+/// Start signing in to an AI provider instead of pasting a key. Returns
+/// the sign-in URL; completion is reported with
+/// `provider-account-connected` or `provider-account-failed`.
+#[tauri::command]
+pub async fn connect_provider_account(app: AppHandle, provider: String) -> AppResult<String> {
+    let client = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        provider_client(&conn, &provider)?
+    };
+    connect(&app, client, move |error| match error {
+        None => AppEvent::ProviderAccountConnected(provider),
+        Some(error) => AppEvent::ProviderAccountFailed(SignInFailed { provider, error }),
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn disconnect_provider_account(db: State<'_, Database>, provider: String) -> AppResult<()> {
+    let client = provider_client(&db.conn(), &provider)?;
+    disconnect(&client.account)
+}
@@ -0,0 +1,258 @@
+//! Fractional ordering keys for the scene board.
+//!
+//! A scene's `order_key` is a string of base-62 digits read as a fraction
+//! (`"V"` is about 0.5), and the board is sorted by it as plain text. A key
+//! can always be made between any two others, so moving or inserting a
+//! scene writes only that scene. Keys grow a digit each time the same gap
+//! is split; a background job rebalances a project to short, evenly spaced
+//! keys once they get long, or when scenes arrive without one (bundles and
+//! changesets from older versions).
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::scenes::{self, Scene};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// In ASCII order, so keys compare correctly as text.
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE: usize = 62;
+
+/// Keys longer than this are due for a rebalance.
+const REBALANCE_LEN: i64 = 24;
+const REBALANCE_CHECK: Duration = Duration::from_secs(60 * 60);
+
+fn digit(c: u8) -> Option<usize> {
+    DIGITS.iter().position(|&d| d == c)
+}
+
+fn valid(key: &str) -> bool {
+    !key.is_empty() && !key.ends_with('0') && key.bytes().all(|c| digit(c).is_some())
+}
+
+/// The shortest key strictly between `a` (or 0 when empty) and `b` (or 1).
+/// Both must be valid keys with `a < b`.
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> Vec<u8> {
+    if let Some(b) = b {
+        // Copy the common prefix, reading a missing digit of `a` as 0.
+        let mut n = 0;
+        while n < b.len() && a.get(n).copied().unwrap_or(b'0') == b[n] {
+            n += 1;
+        }
+        if n > 0 {
+            let mut out = b[..n].to_vec();
+            out.extend(midpoint(a.get(n..).unwrap_or(&[]), Some(&b[n..])));
+            return out;
+        }
+    }
+    let da = a.first().and_then(|&c| digit(c)).unwrap_or(0);
+    let db = b.and_then(|b| digit(b[0])).unwrap_or(BASE);
+    if db - da > 1 {
+        vec![DIGITS[(da + db) / 2]]
+    } else if let Some(b) = b.filter(|b| b.len() > 1) {
+        vec![b[0]]
+    } else {
+        let mut out = vec![DIGITS[da]];
+        out.extend(midpoint(a.get(1..).unwrap_or(&[]), None));
+        out
+    }
+}
+
+/// The shortest key after `a` with nothing required after it: bump the
+/// first digit that isn't already the last one.
+fn successor(a: &[u8]) -> Vec<u8> {
+    match a.iter().position(|&c| c != DIGITS[BASE - 1]) {
+        Some(i) => {
+            let mut out = a[..=i].to_vec();
+            out[i] = DIGITS[digit(a[i]).unwrap_or(0) + 1];
+            out
+        }
+        None => {
+            let mut out = a.to_vec();
+            out.extend(midpoint(&[], None));
+            out
+        }
+    }
+}
+
+/// A key that sorts after `before` and before `after`; None means the start
+/// or end of the list.
+pub fn key_between(before: Option<&str>, after: Option<&str>) -> AppResult<String> {
+    let bad = |k: &str| AppError::Invalid(format!("invalid ordering key '{}'", k));
+    if let Some(k) = before.filter(|k| !valid(k)) {
+        return Err(bad(k));
+    }
+    if let Some(k) = after.filter(|k| !valid(k)) {
+        return Err(bad(k));
+    }
+    if let (Some(a), Some(b)) = (before, after) {
+        if a >= b {
+            return Err(AppError::Invalid(format!(
+                "ordering key '{}' is not before '{}'",
+                a, b
+            )));
+        }
+    }
+    let key = match (before, after) {
+        (Some(a), None) => successor(a.as_bytes()),
+        _ => midpoint(before.unwrap_or("").as_bytes(), after.map(str::as_bytes)),
+    };
+    Ok(String::from_utf8(key).expect("keys are ASCII"))
+}
+
+/// `n` short keys, evenly spread, in order.
+pub fn spread(n: usize) -> Vec<String> {
+    let mut width = 1u32;
+    while (BASE as u128).pow(width) <= n as u128 + 1 {
+        width += 1;
+    }
+    let step = (BASE as u128).pow(width) / (n as u128 + 1);
+    (1..=n as u128)
+        .map(|i| {
+            let mut v = i * step;
+            let mut key = vec![b'0'; width as usize];
+            for slot in key.iter_mut().rev() {
+                *slot = DIGITS[(v % BASE as u128) as usize];
+                v /= BASE as u128;
+            }
+            while key.last() == Some(&b'0') {
+                key.pop();
+            }
+            String::from_utf8(key).expect("keys are ASCII")
+        })
+        .collect()
+}
+
+/// Key for a scene added at the end of a project's board.
+pub fn next_key(conn: &Connection, project_id: &str) -> AppResult<String> {
+    let last: Option<String> = conn.query_row(
+        "SELECT MAX(order_key) FROM scenes WHERE project_id = ?1 AND order_key != ''",
+        [project_id],
+        |r| r.get(0),
+    )?;
+    match last.filter(|k| valid(k)) {
+        Some(last) if last.len() as i64 > REBALANCE_LEN => {
+            rebalance(conn, project_id)?;
+            next_key(conn, project_id)
+        }
+        Some(last) => key_between(Some(&last), None),
+        None => key_between(None, None),
+    }
+}
+
+/// Give every scene in a project a fresh, evenly spaced key, keeping the
+/// current board order. Returns how many scenes were rewritten.
+pub fn rebalance(conn: &Connection, project_id: &str) -> AppResult<usize> {
+    let scenes = scenes::list_for_project(conn, project_id)?;
+    let mut changed = 0;
+    for (scene, key) in scenes.iter().zip(spread(scenes.len())) {
+        if scene.order_key != key {
+            conn.execute(
+                "UPDATE scenes SET order_key = ?2 WHERE id = ?1",
+                params![scene.id, key],
+            )?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn needs_rebalance(conn: &Connection) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT project_id FROM scenes GROUP BY project_id
+         HAVING MAX(length(order_key)) > ?1 OR MIN(order_key) = ''",
+    )?;
+    let ids = stmt
+        .query_map([REBALANCE_LEN], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Rebalance projects whose keys have grown long, now and then hourly.
+pub fn spawn_rebalancer(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let result = {
+                let db = app.state::<Database>();
+                let mut conn = db.conn();
+                (|| -> AppResult<usize> {
+                    let tx = conn.transaction()?;
+                    let mut changed = 0;
+                    for project_id in needs_rebalance(&tx)? {
+                        changed += rebalance(&tx, &project_id)?;
+                    }
+                    tx.commit()?;
+                    Ok(changed)
+                })()
+            };
+            match result {
+                Ok(0) => {}
+                Ok(changed) => tracing::info!(changed, "rebalanced scene order keys"),
+                Err(e) => tracing::warn!(error = %e, "failed to rebalance scene order"),
+            }
+            tokio::time::sleep(REBALANCE_CHECK).await;
+        }
+    });
+}
+
+/// Move a scene to just after `after_id`, or to the start of the board
+/// when it is None. Only the moved scene is written.
+pub fn move_after(conn: &Connection, scene_id: &str, after_id: Option<&str>) -> AppResult<Scene> {
+    let tx = conn.unchecked_transaction()?;
+    let scene = scenes::get_scene(&tx, scene_id)?;
+    projects::ensure_unlocked(&tx, &scene.project_id)?;
+
+    let neighbours = |conn: &Connection| -> AppResult<(Option<String>, Option<String>)> {
+        let before = match after_id {
+            Some(id) => {
+                let after = scenes::get_scene(conn, id)?;
+                if after.project_id != scene.project_id {
+                    return Err(AppError::Invalid(
+                        "scenes belong to different projects".into(),
+                    ));
+                }
+                Some(after.order_key)
+            }
+            None => None,
+        };
+        let next: Option<String> = conn
+            .query_row(
+                "SELECT MIN(order_key) FROM scenes
+                 WHERE project_id = ?1 AND id != ?2 AND (?3 IS NULL OR order_key > ?3)",
+                params![scene.project_id, scene_id, before],
+                |r| r.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok((before, next))
+    };
+
+    let (before, next) = neighbours(&tx)?;
+    let key = match key_between(before.as_deref(), next.as_deref()) {
+        Ok(key) if key.len() as i64 <= REBALANCE_LEN => key,
+        // Missing, colliding or overlong keys: respace the board and retry.
+        _ => {
+            rebalance(&tx, &scene.project_id)?;
+            let (before, next) = neighbours(&tx)?;
+            key_between(before.as_deref(), next.as_deref())?
+        }
+    };
+    tx.execute(
+        "UPDATE scenes SET order_key = ?2 WHERE id = ?1",
+        params![scene_id, key],
+    )?;
+    let moved = scenes::get_scene(&tx, scene_id)?;
+    tx.commit()?;
+    Ok(moved)
+}
+
+#[tauri::command]
+pub fn move_scene(
+    db: State<'_, Database>,
+    scene_id: String,
+    after_id: Option<String>,
+) -> AppResult<Scene> {
+    move_after(&db.conn(), &scene_id, after_id.as_deref())
+}
@@ -0,0 +1,335 @@
+//! Just enough PDF to lay out exports: the two standard Helvetica faces
+//! and Courier for screenplays, filled rectangles and JPEG images. Coordinates are in points from the
+//! top-left corner of the page.
+
+use image::codecs::jpeg::JpegEncoder;
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+    /// Courier, the monospaced screenplay face.
+    Mono,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+}
+
+/// Helvetica advance widths for ASCII 32–126, in 1/1000 em.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Approximate rendered width of `text` in points.
+pub fn text_width(text: &str, size: f32, font: Font) -> f32 {
+    if font == Font::Mono {
+        return text.chars().count() as f32 * 600.0 * size / 1000.0;
+    }
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            32..=126 => u32::from(HELVETICA_WIDTHS[c as usize - 32]),
+            _ => 556,
+        })
+        .sum();
+    let bold = if font == Font::Bold { 1.06 } else { 1.0 };
+    units as f32 * size / 1000.0 * bold
+}
+
+/// Split `text` into lines no wider than `width`, keeping paragraph breaks.
+pub fn wrap(text: &str, width: f32, size: f32, font: Font) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && text_width(&candidate, size, font) > width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Encode text as a PDF literal string in WinAnsi, dropping what it can't
+/// represent. Hungarian double-acute letters are drawn with umlauts, the
+/// usual fallback.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        let byte = match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+                continue;
+            }
+            '\u{2014}' => 0x97,
+            '\u{2013}' => 0x96,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201C}' => 0x93,
+            '\u{201D}' => 0x94,
+            '\u{2026}' => 0x85,
+            '\u{2022}' => 0x95,
+            '\u{0150}' => 0xD6,
+            '\u{0151}' => 0xF6,
+            '\u{0170}' => 0xDC,
+            '\u{0171}' => 0xFC,
+            c if (' '..='~').contains(&c) => {
+                out.push(c);
+                continue;
+            }
+            c if ('\u{A0}'..='\u{FF}').contains(&c) => c as u32 as u8,
+            _ => b'?',
+        };
+        let _ = write!(out, "\\{:03o}", byte);
+    }
+    out.push(')');
+    out
+}
+
+/// A JPEG ready to embed. Any format the `image` crate reads is re-encoded.
+pub struct JpegImage {
+    pub width: u32,
+    pub height: u32,
+    data: Vec<u8>,
+}
+
+impl JpegImage {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rgb = image::load_from_memory(bytes).ok()?.to_rgb8();
+        let mut data = Vec::new();
+        JpegEncoder::new_with_quality(&mut data, 85)
+            .encode_image(&rgb)
+            .ok()?;
+        Some(JpegImage {
+            width: rgb.width(),
+            height: rgb.height(),
+            data,
+        })
+    }
+}
+
+pub struct Page<'a> {
+    height: f32,
+    ops: String,
+    images: Vec<&'a JpegImage>,
+}
+
+impl<'a> Page<'a> {
+    pub fn text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        let _ = writeln!(
+            self.ops,
+            "BT /{} {:.1} Tf {:.2} {:.2} Td {} Tj ET",
+            font.resource(),
+            size,
+            x,
+            self.height - y - size,
+            pdf_string(text)
+        );
+    }
+
+    /// Draw wrapped text from `y` downwards and return the y below it.
+    /// Lines past `max_y` are dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paragraph(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        max_y: f32,
+        size: f32,
+        font: Font,
+        text: &str,
+    ) -> f32 {
+        let leading = size * 1.35;
+        let mut y = y;
+        for line in wrap(text, width, size, font) {
+            if y + leading > max_y {
+                break;
+            }
+            self.text(x, y, size, font, &line);
+            y += leading;
+        }
+        y
+    }
+
+    pub fn color(&mut self, (r, g, b): (f32, f32, f32)) {
+        let _ = writeln!(self.ops, "{:.3} {:.3} {:.3} rg", r, g, b);
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        let _ = writeln!(
+            self.ops,
+            "{:.2} {:.2} {:.2} {:.2} re f",
+            x,
+            self.height - y - h,
+            w,
+            h
+        );
+    }
+
+    /// Draw `img` scaled to fit inside the box, centred.
+    pub fn image_fit(&mut self, img: &'a JpegImage, x: f32, y: f32, w: f32, h: f32) {
+        let scale = (w / img.width as f32).min(h / img.height as f32);
+        let (dw, dh) = (img.width as f32 * scale, img.height as f32 * scale);
+        let (dx, dy) = (x + (w - dw) / 2.0, y + (h - dh) / 2.0);
+        let name = format!("Im{}", self.images.len());
+        self.images.push(img);
+        let _ = writeln!(
+            self.ops,
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /{} Do Q",
+            dw,
+            dh,
+            dx,
+            self.height - dy - dh,
+            name
+        );
+    }
+}
+
+pub struct Document<'a> {
+    pub width: f32,
+    pub height: f32,
+    pages: Vec<Page<'a>>,
+    /// An XMP packet for the document catalog.
+    metadata: Option<String>,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(width: f32, height: f32) -> Self {
+        Document {
+            width,
+            height,
+            pages: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    pub fn set_metadata(&mut self, xmp: String) {
+        self.metadata = Some(xmp);
+    }
+
+    pub fn page(&self) -> Page<'a> {
+        Page {
+            height: self.height,
+            ops: String::new(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, page: Page<'a>) {
+        self.pages.push(page);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        // Objects are numbered from 1: catalog, page tree, three fonts, then
+        // each page followed by its content stream and images.
+        let mut objects: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            Vec::new(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        ];
+        let mut kids = Vec::new();
+        for page in &self.pages {
+            let page_id = objects.len() + 1;
+            let content_id = page_id + 1;
+            kids.push(format!("{} 0 R", page_id));
+
+            let mut xobjects = String::new();
+            for (i, _) in page.images.iter().enumerate() {
+                let _ = write!(xobjects, "/Im{} {} 0 R ", i, content_id + 1 + i);
+            }
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.0} {:.0}] \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> /XObject << {}>> >> \
+                     /Contents {} 0 R >>",
+                    self.width, self.height, xobjects, content_id
+                )
+                .into_bytes(),
+            );
+            objects.push(stream("", page.ops.as_bytes()));
+            for img in &page.images {
+                objects.push(stream(
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                         /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode",
+                        img.width, img.height
+                    ),
+                    &img.data,
+                ));
+            }
+        }
+        objects[0] = match &self.metadata {
+            Some(xmp) => {
+                objects.push(stream("/Type /Metadata /Subtype /XML", xmp.as_bytes()));
+                format!(
+                    "<< /Type /Catalog /Pages 2 0 R /Metadata {} 0 R >>",
+                    objects.len()
+                )
+                .into_bytes()
+            }
+            None => b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        };
+        objects[1] = format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        )
+        .into_bytes();
+
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, body) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+    out.extend_from_slice(data);
+    out.extend_from_slice(b"\nendstream");
+    out
+}
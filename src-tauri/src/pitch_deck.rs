@@ -0,0 +1,328 @@
+use crate::analytics;
+use crate::characters::{self, Character};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export::{self, ProjectInfo};
+use crate::i18n::{self, Locale};
+use crate::pdf::{Document, Font, JpegImage, Page};
+use crate::provenance::{self, Provenance};
+use crate::scenes::{self, Scene};
+use crate::stats::{self, ProjectStats};
+use crate::workflow::SceneStatus;
+use rusqlite::Connection;
+use tauri::{AppHandle, Manager};
+
+/// US Letter, landscape.
+const PAGE_W: f32 = 792.0;
+const PAGE_H: f32 = 612.0;
+const MARGIN: f32 = 54.0;
+/// Key frames picked automatically when the caller doesn't choose any.
+const AUTO_KEY_FRAMES: usize = 8;
+
+const DARK: (f32, f32, f32) = (0.08, 0.08, 0.09);
+const LIGHT: (f32, f32, f32) = (0.96, 0.96, 0.94);
+const MUTED: (f32, f32, f32) = (0.45, 0.45, 0.45);
+const INK: (f32, f32, f32) = (0.1, 0.1, 0.1);
+
+fn format_runtime(seconds: i64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Scenes to show as key frames: the caller's picks in their order, or
+/// approved/locked scenes with a frame, topped up with any framed scene.
+fn pick_key_scenes(scenes: &[Scene], chosen: Option<&[String]>) -> Vec<Scene> {
+    match chosen {
+        Some(ids) => ids
+            .iter()
+            .filter_map(|id| scenes.iter().find(|s| &s.id == id).cloned())
+            .collect(),
+        None => {
+            let signed_off = |s: &Scene| {
+                matches!(
+                    SceneStatus::parse(&s.status),
+                    Ok(SceneStatus::Approved | SceneStatus::Locked)
+                )
+            };
+            let framed = scenes.iter().filter(|s| !s.conditioning_image.is_empty());
+            framed
+                .clone()
+                .filter(|s| signed_off(s))
+                .chain(framed.filter(|s| !signed_off(s)))
+                .take(AUTO_KEY_FRAMES)
+                .cloned()
+                .collect()
+        }
+    }
+}
+
+fn heading<'a>(doc: &Document<'a>, title: &str) -> Page<'a> {
+    let mut page = doc.page();
+    page.color(LIGHT);
+    page.fill_rect(0.0, 0.0, PAGE_W, PAGE_H);
+    page.color(INK);
+    page.text(MARGIN, MARGIN, 26.0, Font::Bold, title);
+    page.color(MUTED);
+    page.fill_rect(MARGIN, MARGIN + 38.0, 60.0, 3.0);
+    page
+}
+
+fn cover<'a>(doc: &Document<'a>, project: &ProjectInfo, frame: Option<&'a JpegImage>) -> Page<'a> {
+    let mut page = doc.page();
+    page.color(DARK);
+    page.fill_rect(0.0, 0.0, PAGE_W, PAGE_H);
+    if let Some(img) = frame {
+        page.image_fit(img, 0.0, 0.0, PAGE_W, PAGE_H - 170.0);
+    }
+    page.color((1.0, 1.0, 1.0));
+    page.text(MARGIN, PAGE_H - 150.0, 40.0, Font::Bold, &project.name);
+    let tagline = [project.genre.as_str(), project.tone.as_str()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_uppercase())
+        .collect::<Vec<_>>()
+        .join("  \u{2022}  ");
+    page.color((0.7, 0.7, 0.7));
+    page.text(MARGIN, PAGE_H - 95.0, 14.0, Font::Regular, &tagline);
+    page
+}
+
+fn character_page<'a>(
+    doc: &Document<'a>,
+    character: &Character,
+    photo: Option<&'a JpegImage>,
+) -> Page<'a> {
+    let mut page = heading(doc, &character.name);
+    let top = MARGIN + 64.0;
+    let photo_w = 280.0;
+    let text_x = match photo {
+        Some(img) => {
+            page.image_fit(img, MARGIN, top, photo_w, PAGE_H - top - MARGIN);
+            MARGIN + photo_w + 32.0
+        }
+        None => MARGIN,
+    };
+    page.color(INK);
+    page.paragraph(
+        text_x,
+        top,
+        PAGE_W - text_x - MARGIN,
+        PAGE_H - MARGIN,
+        13.0,
+        Font::Regular,
+        &character.description,
+    );
+    page
+}
+
+/// Two-by-two grid of key frames with scene captions.
+fn key_frame_page<'a>(
+    doc: &Document<'a>,
+    locale: Locale,
+    frames: &[(&Scene, &'a JpegImage)],
+) -> Page<'a> {
+    let mut page = heading(doc, locale.t("pitchDeck.keyFrames"));
+    let top = MARGIN + 64.0;
+    let gap = 24.0;
+    let cell_w = (PAGE_W - 2.0 * MARGIN - gap) / 2.0;
+    let cell_h = (PAGE_H - top - MARGIN - gap) / 2.0;
+    let frame_h = cell_h - 22.0;
+    for (i, (scene, img)) in frames.iter().enumerate() {
+        let x = MARGIN + (i % 2) as f32 * (cell_w + gap);
+        let y = top + (i / 2) as f32 * (cell_h + gap);
+        page.color(DARK);
+        page.fill_rect(x, y, cell_w, frame_h);
+        page.image_fit(img, x, y, cell_w, frame_h);
+        page.color(INK);
+        let number = locale.tf(
+            "pitchDeck.scene",
+            &[("number", &locale.integer(scene.scene_number))],
+        );
+        let caption = if scene.title.is_empty() {
+            number
+        } else {
+            format!("{} \u{2014} {}", number, scene.title)
+        };
+        page.text(x, y + frame_h + 6.0, 11.0, Font::Bold, &caption);
+    }
+    page
+}
+
+fn summary_page<'a>(
+    doc: &Document<'a>,
+    locale: Locale,
+    stats: &ProjectStats,
+    characters: usize,
+) -> Page<'a> {
+    let mut page = heading(doc, locale.t("pitchDeck.summary"));
+    let mut y = MARGIN + 72.0;
+    let mut row = |page: &mut Page<'a>, label: &str, value: String| {
+        page.color(MUTED);
+        page.text(MARGIN, y, 13.0, Font::Regular, label);
+        page.color(INK);
+        page.text(MARGIN + 260.0, y, 13.0, Font::Bold, &value);
+        y += 26.0;
+    };
+    row(
+        &mut page,
+        locale.t("pitchDeck.runtime"),
+        format_runtime(stats.estimated_runtime),
+    );
+    row(
+        &mut page,
+        locale.t("pitchDeck.scenes"),
+        locale.integer(stats.scene_count),
+    );
+    row(
+        &mut page,
+        locale.t("pitchDeck.characters"),
+        locale.integer(characters as i64),
+    );
+    row(
+        &mut page,
+        locale.t("pitchDeck.spend"),
+        locale.money(stats.total_spend),
+    );
+    for provider in &stats.spend_by_provider {
+        let label = locale.tf(
+            "pitchDeck.providerSpend",
+            &[
+                ("provider", &provider.provider),
+                ("count", &locale.integer(provider.jobs)),
+            ],
+        );
+        row(
+            &mut page,
+            &format!("    {}", label),
+            locale.money(provider.spend),
+        );
+    }
+    if let Some(rate) = stats.success_rate {
+        row(
+            &mut page,
+            locale.t("pitchDeck.successRate"),
+            locale.percent(rate),
+        );
+    }
+    page
+}
+
+/// What goes into a pitch deck, read up front so the database isn't held
+/// while images are fetched.
+pub struct DeckSource {
+    locale: Locale,
+    project: ProjectInfo,
+    key_scenes: Vec<Scene>,
+    characters: Vec<Character>,
+    stats: ProjectStats,
+    provenance: Provenance,
+}
+
+/// Load a project's deck contents. `key_scene_ids` picks the frames;
+/// without it approved scenes with frames are used.
+pub fn load(
+    conn: &Connection,
+    project_id: &str,
+    key_scene_ids: Option<&[String]>,
+) -> AppResult<DeckSource> {
+    let locale = i18n::current(conn)?;
+    let project = export::load_project(conn, project_id)?;
+    let scenes = scenes::list_for_project(conn, project_id)?;
+    let characters = characters::list_for_project(conn, project_id)?;
+    let stats = stats::project_stats(conn, project_id)?;
+    analytics::track(conn, project_id, "export", "pitch_deck");
+    let key_scenes = pick_key_scenes(&scenes, key_scene_ids);
+    let provenance = provenance::for_scenes(conn, project_id, &key_scenes)?;
+    Ok(DeckSource {
+        locale,
+        project,
+        key_scenes,
+        characters,
+        stats,
+        provenance,
+    })
+}
+
+/// Write a pitch deck PDF: cover, synopsis, one page per character, key
+/// frames and a runtime/budget summary. Returns the written path.
+pub async fn write(source: DeckSource, path: &str) -> AppResult<String> {
+    let DeckSource {
+        locale,
+        project,
+        key_scenes,
+        characters,
+        stats,
+        provenance,
+    } = source;
+    let mut frames = Vec::new();
+    for scene in &key_scenes {
+        if let Some(img) = export::image_bytes(&scene.conditioning_image)
+            .await
+            .and_then(|b| JpegImage::from_bytes(&b))
+        {
+            frames.push((scene, img));
+        }
+    }
+    let mut photos = Vec::new();
+    for character in &characters {
+        let img = export::image_bytes(&character.photo_data)
+            .await
+            .and_then(|b| JpegImage::from_bytes(&b));
+        photos.push(img);
+    }
+
+    let mut doc = Document::new(PAGE_W, PAGE_H);
+    doc.set_metadata(provenance.xmp());
+    let cover_page = cover(&doc, &project, frames.first().map(|(_, img)| img));
+    doc.push(cover_page);
+
+    if !project.synopsis.trim().is_empty() {
+        let mut page = heading(&doc, locale.t("pitchDeck.synopsis"));
+        page.color(INK);
+        page.paragraph(
+            MARGIN,
+            MARGIN + 64.0,
+            PAGE_W - 2.0 * MARGIN,
+            PAGE_H - MARGIN,
+            14.0,
+            Font::Regular,
+            &project.synopsis,
+        );
+        doc.push(page);
+    }
+
+    for (character, photo) in characters.iter().zip(&photos) {
+        let page = character_page(&doc, character, photo.as_ref());
+        doc.push(page);
+    }
+
+    let frame_refs: Vec<(&Scene, &JpegImage)> =
+        frames.iter().map(|(scene, img)| (*scene, img)).collect();
+    for chunk in frame_refs.chunks(4) {
+        let page = key_frame_page(&doc, locale, chunk);
+        doc.push(page);
+    }
+
+    let page = summary_page(&doc, locale, &stats, characters.len());
+    doc.push(page);
+
+    let out = export::resolve_output(path, "pitch-deck.pdf")?;
+    tokio::fs::write(&out, doc.finish())
+        .await
+        .map_err(|e| AppError::Io(format!("writing pitch deck: {}", e)))?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn export_pitch_deck(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+    key_scene_ids: Option<Vec<String>>,
+) -> AppResult<String> {
+    let source = load(
+        &app.state::<Database>().conn(),
+        &project_id,
+        key_scene_ids.as_deref(),
+    )?;
+    write(source, &path).await
+}
@@ -0,0 +1,228 @@
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::versioning;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct StylePreset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Direction handed to the LLM enhancer (lens, movement, grade, texture).
+    pub guidance: String,
+    /// Keywords appended verbatim if the LLM is unavailable.
+    pub keywords: String,
+    pub negative_prompt: String,
+    pub builtin: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct StylePresetInput {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub guidance: String,
+    #[serde(default)]
+    pub keywords: String,
+    #[serde(default)]
+    pub negative_prompt: String,
+}
+
+const PRESET_COLUMNS: &str =
+    "id, name, description, guidance, keywords, negative_prompt, builtin, created_at";
+
+fn row_to_preset(row: &Row) -> rusqlite::Result<StylePreset> {
+    Ok(StylePreset {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        guidance: row.get(3)?,
+        keywords: row.get(4)?,
+        negative_prompt: row.get(5)?,
+        builtin: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+pub fn get_preset(conn: &Connection, id: &str) -> AppResult<StylePreset> {
+    conn.query_row(
+        &format!("SELECT {} FROM style_presets WHERE id = ?1", PRESET_COLUMNS),
+        [id],
+        row_to_preset,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("style preset {}", id)))
+}
+
+/// The preset a project falls back to when the caller doesn't pick one.
+pub fn project_default(conn: &Connection, project_id: &str) -> AppResult<Option<StylePreset>> {
+    let id: Option<String> = conn
+        .query_row(
+            "SELECT default_style_preset_id FROM projects WHERE id = ?1",
+            [project_id],
+            |r| r.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("project {}", project_id)))?;
+    id.map(|id| get_preset(conn, &id)).transpose()
+}
+
+fn validate(input: &StylePresetInput) -> AppResult<()> {
+    if input.name.trim().is_empty() {
+        return Err(AppError::Invalid("preset name is required".into()));
+    }
+    if input.guidance.trim().is_empty() && input.keywords.trim().is_empty() {
+        return Err(AppError::Invalid(
+            "preset needs guidance or keywords".into(),
+        ));
+    }
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> AppResult<Vec<StylePreset>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM style_presets ORDER BY builtin DESC, name COLLATE NOCASE",
+        PRESET_COLUMNS
+    ))?;
+    let presets = stmt
+        .query_map([], row_to_preset)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(presets)
+}
+
+#[tauri::command]
+pub fn list_style_presets(db: State<'_, Database>) -> AppResult<Vec<StylePreset>> {
+    list(&db.conn())
+}
+
+pub fn create(conn: &Connection, input: &StylePresetInput) -> AppResult<StylePreset> {
+    validate(input)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO style_presets (id, name, description, guidance, keywords, negative_prompt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            input.name.trim(),
+            input.description,
+            input.guidance,
+            input.keywords,
+            input.negative_prompt
+        ],
+    )?;
+    get_preset(conn, &id)
+}
+
+#[tauri::command]
+pub fn create_style_preset(
+    db: State<'_, Database>,
+    input: StylePresetInput,
+) -> AppResult<StylePreset> {
+    create(&db.conn(), &input)
+}
+
+pub fn update(conn: &Connection, id: &str, input: &StylePresetInput) -> AppResult<StylePreset> {
+    validate(input)?;
+    let changed = conn.execute(
+        "UPDATE style_presets
+         SET name = ?2, description = ?3, guidance = ?4, keywords = ?5, negative_prompt = ?6
+         WHERE id = ?1",
+        params![
+            id,
+            input.name.trim(),
+            input.description,
+            input.guidance,
+            input.keywords,
+            input.negative_prompt
+        ],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("style preset {}", id)));
+    }
+    get_preset(conn, id)
+}
+
+#[tauri::command]
+pub fn update_style_preset(
+    db: State<'_, Database>,
+    id: String,
+    input: StylePresetInput,
+) -> AppResult<StylePreset> {
+    update(&db.conn(), &id, &input)
+}
+
+/// Built-in presets can be edited but not removed. Projects using a deleted
+/// preset fall back to no default.
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    if get_preset(conn, id)?.builtin {
+        return Err(AppError::Invalid(
+            "built-in presets cannot be deleted".into(),
+        ));
+    }
+    conn.execute(
+        "UPDATE projects SET default_style_preset_id = NULL WHERE default_style_preset_id = ?1",
+        [id],
+    )?;
+    conn.execute("DELETE FROM style_presets WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_style_preset(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
+
+pub fn set_project_default(
+    conn: &Connection,
+    project_id: &str,
+    preset_id: Option<&str>,
+    expected_version: Option<i64>,
+) -> AppResult<()> {
+    if let Some(id) = preset_id {
+        get_preset(conn, id)?;
+    }
+    let project = projects::get_project(conn, project_id)?;
+    projects::ensure_unlocked(conn, project_id)?;
+    versioning::check(
+        "project",
+        project_id,
+        expected_version,
+        project.version,
+        &project,
+        &serde_json::json!({ "defaultStylePresetId": preset_id }),
+    )?;
+    let changed = conn.execute(
+        "UPDATE projects SET default_style_preset_id = ?2, updated_at = ?3 WHERE id = ?1",
+        params![project_id, preset_id, clock::now()],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("project {}", project_id)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_project_style_preset(
+    db: State<'_, Database>,
+    project_id: String,
+    preset_id: Option<String>,
+    expected_version: Option<i64>,
+) -> AppResult<()> {
+    set_project_default(
+        &db.conn(),
+        &project_id,
+        preset_id.as_deref(),
+        expected_version,
+    )
+}
@@ -0,0 +1,320 @@
+//! Small previews of a scene's take for pasting into docs and messages: a
+//! looping GIF or a short, silent MP4. The take is the scene's chosen
+//! video, else its newest finished one (see `scene_links::finished_take`).
+//!
+//! Both are capped in size. A preview that comes out too big is made again
+//! smaller, down to the last step in its ladder, and fails if even that is
+//! over the cap.
+//!
+//! An export preset can have a watermark burned in (see `export_presets`).
+
+use crate::analytics;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::export_presets::{self, Stamp};
+use crate::frames;
+use crate::numbering;
+use crate::projects;
+use crate::provenance;
+use crate::scene_links;
+use crate::scenes;
+use crate::validation::Validator;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+/// Most chat apps and issue trackers inline files up to about this size.
+const GIF_MAX_BYTES: u64 = 8 * 1024 * 1024;
+const CLIP_MAX_BYTES: u64 = 4 * 1024 * 1024;
+/// Longest preview, in seconds.
+const MAX_SECONDS: f64 = 10.0;
+const DEFAULT_SECONDS: f64 = 3.0;
+/// Width and frame rate for each GIF attempt, best first.
+const GIF_LADDER: &[(u32, u32)] = &[(480, 12), (360, 10), (240, 8)];
+/// Width and CRF for each MP4 attempt, best first.
+const CLIP_LADDER: &[(u32, u32)] = &[(640, 26), (480, 30), (320, 34)];
+
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PreviewOptions {
+    /// Where in the take to start, in seconds.
+    #[serde(default)]
+    pub start: f64,
+    /// Length in seconds; 3 unless set.
+    #[serde(default)]
+    pub seconds: Option<f64>,
+    /// An export preset whose watermark is burned in.
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// Who the preview is for, for watermarks that name the recipient.
+    #[serde(default)]
+    pub recipient: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Preview {
+    pub path: String,
+    #[ts(type = "number")]
+    pub bytes: u64,
+    pub width: u32,
+}
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Gif,
+    Clip,
+}
+
+impl Kind {
+    fn extension(self) -> &'static str {
+        match self {
+            Kind::Gif => "gif",
+            Kind::Clip => "mp4",
+        }
+    }
+
+    fn max_bytes(self) -> u64 {
+        match self {
+            Kind::Gif => GIF_MAX_BYTES,
+            Kind::Clip => CLIP_MAX_BYTES,
+        }
+    }
+
+    fn ladder(self) -> &'static [(u32, u32)] {
+        match self {
+            Kind::Gif => GIF_LADDER,
+            Kind::Clip => CLIP_LADDER,
+        }
+    }
+
+    /// Output arguments for one rung of the ladder, after the burn-in
+    /// `filter` if there is one. MP4s are tagged with `metadata`.
+    fn args(
+        self,
+        width: u32,
+        setting: u32,
+        filter: Option<&str>,
+        metadata: &[String],
+    ) -> Vec<String> {
+        let filter = filter.map(|f| format!("{},", f)).unwrap_or_default();
+        match self {
+            // One palette per GIF keeps colours right at 128 entries.
+            Kind::Gif => vec![
+                "-vf".into(),
+                format!(
+                    "{}fps={},scale={}:-2:flags=lanczos,split[a][b];\
+                     [a]palettegen=max_colors=128[p];[b][p]paletteuse=dither=bayer",
+                    filter, setting, width
+                ),
+                "-loop".into(),
+                "0".into(),
+            ],
+            Kind::Clip => [
+                "-vf".into(),
+                format!("{}scale={}:-2", filter, width),
+                "-an".into(),
+                "-c:v".into(),
+                "libx264".into(),
+                "-crf".into(),
+                setting.to_string(),
+                "-pix_fmt".into(),
+                "yuv420p".into(),
+                "-movflags".into(),
+                "+faststart+use_metadata_tags".into(),
+            ]
+            .into_iter()
+            .chain(metadata.iter().cloned())
+            .collect(),
+        }
+    }
+}
+
+async fn run_ffmpeg(binary: &Path, args: &[String]) -> AppResult<()> {
+    let output = tokio::process::Command::new(binary)
+        .args(["-v", "error", "-y"])
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not run ffmpeg ({}): {}",
+                binary.display(),
+                e
+            ))
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("ffmpeg failed");
+        return Err(AppError::Io(format!("preview export failed: {}", last)));
+    }
+    Ok(())
+}
+
+/// Everything an export needs from the database, read up front so it
+/// isn't held while ffmpeg runs.
+pub struct PreviewSource {
+    kind: Kind,
+    start: f64,
+    seconds: f64,
+    video: String,
+    binary: PathBuf,
+    filter: Option<String>,
+    metadata: Vec<String>,
+    out: PathBuf,
+}
+
+/// Check `options` and find the scene's finished take, the watermark to
+/// burn in and where to write the preview.
+pub fn load(
+    conn: &Connection,
+    scene_id: &str,
+    path: &str,
+    options: &PreviewOptions,
+    kind: Kind,
+) -> AppResult<PreviewSource> {
+    let seconds = options.seconds.unwrap_or(DEFAULT_SECONDS);
+    let mut v = Validator::new();
+    v.uuid("sceneId", scene_id);
+    if !(seconds > 0.0 && seconds <= MAX_SECONDS) {
+        v.error("seconds", "must be more than 0 and at most 10");
+    }
+    if options.start.is_nan() || options.start < 0.0 {
+        v.error("start", "can't be negative");
+    }
+    v.finish()?;
+    let scene = scenes::get_scene(conn, scene_id)?;
+    let video = scene_links::finished_take(conn, scene_id)?
+        .ok_or_else(|| AppError::Invalid("scene has no finished take".into()))?;
+    let filter = match options.preset_id.as_deref() {
+        Some(preset_id) => {
+            let project = projects::get_project(conn, &scene.project_id)?;
+            export_presets::filter_for(
+                conn,
+                Some(preset_id),
+                &Stamp {
+                    project: &project.name,
+                    recipient: &options.recipient,
+                    scenes: vec![(
+                        0.0,
+                        format!(
+                            "Scene {}",
+                            numbering::label(scene.scene_number, &scene.scene_suffix)
+                        ),
+                    )],
+                },
+            )?
+        }
+        None => None,
+    };
+    analytics::track(
+        conn,
+        &scene.project_id,
+        "export",
+        &format!("scene_{}", kind.extension()),
+    );
+    let metadata = provenance::for_scene(conn, &scene)?.ffmpeg_args();
+    let out = export::resolve_output(
+        path,
+        &format!(
+            "scene-{}.{}",
+            numbering::label(scene.scene_number, &scene.scene_suffix),
+            kind.extension()
+        ),
+    )?;
+    Ok(PreviewSource {
+        kind,
+        start: options.start,
+        seconds,
+        video,
+        binary: frames::ffmpeg_binary(conn)?,
+        filter,
+        metadata,
+        out,
+    })
+}
+
+/// Encode the preview, stepping down the size ladder until it fits.
+pub async fn render(source: PreviewSource) -> AppResult<Preview> {
+    let PreviewSource {
+        kind,
+        start,
+        seconds,
+        video,
+        binary,
+        filter,
+        metadata,
+        out,
+    } = source;
+    for &(width, setting) in kind.ladder() {
+        let mut args = vec![
+            "-ss".to_string(),
+            format!("{:.3}", start),
+            "-t".to_string(),
+            format!("{:.3}", seconds),
+            "-i".to_string(),
+            video.clone(),
+        ];
+        args.extend(kind.args(width, setting, filter.as_deref(), &metadata));
+        args.push(out.to_string_lossy().into_owned());
+        run_ffmpeg(&binary, &args).await?;
+        let bytes = std::fs::metadata(&out)?.len();
+        if bytes <= kind.max_bytes() {
+            return Ok(Preview {
+                path: out.to_string_lossy().into_owned(),
+                bytes,
+                width,
+            });
+        }
+    }
+    std::fs::remove_file(&out).ok();
+    Err(AppError::Invalid(format!(
+        "the preview is over {} MB even at its smallest; try a shorter one",
+        kind.max_bytes() / (1024 * 1024)
+    )))
+}
+
+/// Export part of the scene's take as a looping GIF, at most 8 MB.
+#[tauri::command]
+pub async fn export_scene_gif(
+    app: AppHandle,
+    scene_id: String,
+    path: String,
+    options: Option<PreviewOptions>,
+) -> AppResult<Preview> {
+    let source = load(
+        &app.state::<Database>().conn(),
+        &scene_id,
+        &path,
+        &options.unwrap_or_default(),
+        Kind::Gif,
+    )?;
+    render(source).await
+}
+
+/// Export part of the scene's take as a silent MP4 clip, at most 4 MB.
+#[tauri::command]
+pub async fn export_scene_clip(
+    app: AppHandle,
+    scene_id: String,
+    path: String,
+    options: Option<PreviewOptions>,
+) -> AppResult<Preview> {
+    let source = load(
+        &app.state::<Database>().conn(),
+        &scene_id,
+        &path,
+        &options.unwrap_or_default(),
+        Kind::Clip,
+    )?;
+    render(source).await
+}
@@ -0,0 +1,303 @@
+//! Projects as `.aidc` files the user keeps wherever they like, next to
+//! the central database. An `.aidc` file is a zip of `project.json` (the
+//! project's bundle, pretty-printed so it diffs well in Git) and the media
+//! its scenes point at under `media/`.
+//!
+//! Opening a file loads it into the database like a pulled bundle, keyed
+//! by the project's id, so reopening a file updates the same project. The
+//! file's path is remembered on the project, and saving again writes back
+//! to it without asking.
+
+use crate::bundle::{self, ProjectBundle};
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::projects::{self, ProjectSummary};
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+
+pub const EXTENSION: &str = "aidc";
+const PROJECT_JSON: &str = "project.json";
+/// Where media from outside the app's media folder goes, in the file and
+/// when it is opened.
+const OTHER_MEDIA: &str = "media/files";
+
+fn zip_err(e: zip::result::ZipError) -> AppError {
+    AppError::Io(format!("project file: {}", e))
+}
+
+/// The name a local media file gets inside the `.aidc` file. Files in the
+/// media folder keep their place there; others are named by a hash of
+/// their path so two `take.mp4`s from different folders don't collide.
+fn entry_name(root: &Path, path: &Path) -> String {
+    if let Ok(rel) = path.strip_prefix(root) {
+        if rel.starts_with("media") {
+            return rel.to_string_lossy().replace('\\', "/");
+        }
+    }
+    let hash: String = Sha256::digest(path.to_string_lossy().as_bytes())[..4]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!("{}/{}-{}", OTHER_MEDIA, hash, name)
+}
+
+/// Point the bundle's media fields at entries in the file, returning the
+/// files to pack by entry name. Remote URLs and missing files are left as
+/// they are.
+fn pack_media(bundle: &mut ProjectBundle, root: &Path) -> BTreeMap<String, PathBuf> {
+    let mut files = BTreeMap::new();
+    for scene in &mut bundle.scenes {
+        for field in [&mut scene.video_url, &mut scene.conditioning_image] {
+            let path = PathBuf::from(field.as_str());
+            if field.is_empty() || !path.is_file() {
+                continue;
+            }
+            let name = entry_name(root, &path);
+            *field = name.clone();
+            files.insert(name, path);
+        }
+    }
+    files
+}
+
+/// Write the zip into `out`, which needn't be seekable so it can go
+/// straight into an encryptor (see `handoff`). Returns `out`.
+fn write_zip<W: Write>(
+    out: W,
+    bundle: &ProjectBundle,
+    files: &BTreeMap<String, PathBuf>,
+) -> AppResult<W> {
+    let mut zip = zip::ZipWriter::new_stream(out);
+    let stored =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(PROJECT_JSON, deflated).map_err(zip_err)?;
+    zip.write_all(&serde_json::to_vec_pretty(bundle)?)?;
+    for (name, path) in files {
+        zip.start_file(name.as_str(), stored).map_err(zip_err)?;
+        std::io::copy(&mut std::fs::File::open(path)?, &mut zip)?;
+    }
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+/// Write the project and its media into `out` as an `.aidc` file. Returns
+/// `out` and how many media files were packed.
+pub fn write_to<W: Write>(conn: &Connection, project_id: &str, out: W) -> AppResult<(W, usize)> {
+    let root = db::app_dir();
+    let mut bundle = bundle::export_bundle(conn, project_id)?;
+    let files = pack_media(&mut bundle, &root);
+    let out = write_zip(out, &bundle, &files)?;
+    Ok((out, files.len()))
+}
+
+/// Write the project and its media to `path` as an `.aidc` file. Returns
+/// how many media files were packed.
+pub fn write(conn: &Connection, project_id: &str, path: &Path) -> AppResult<usize> {
+    let partial = appended(path, "partial");
+    let file = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+    let (mut file, files) = write_to(conn, project_id, file)?;
+    file.flush()?;
+    drop(file);
+    std::fs::rename(&partial, path)?;
+    Ok(files)
+}
+
+/// Write the project to `path` and remember it as the project's file.
+pub fn save(conn: &Connection, project_id: &str, path: &Path) -> AppResult<ProjectSummary> {
+    let files = write(conn, project_id, path)?;
+    conn.execute(
+        "UPDATE projects SET file_path = ?2 WHERE id = ?1",
+        params![project_id, path.to_string_lossy()],
+    )?;
+    tracing::info!(project_id, path = %path.display(), files, "saved project file");
+    projects::get_project(conn, project_id)
+}
+
+/// Load the `.aidc` file at `path` into the database, creating or
+/// updating its project. Returns the project's id.
+pub fn load(conn: &Connection, path: &Path) -> AppResult<String> {
+    load_from(conn, std::fs::File::open(path)?, path)
+}
+
+/// `load` from any seekable reader, such as a decryptor (see `handoff`);
+/// `path` is only for messages.
+pub fn load_from<R: Read + Seek>(conn: &Connection, input: R, path: &Path) -> AppResult<String> {
+    let root = db::app_dir();
+    let mut zip = zip::ZipArchive::new(input).map_err(|e| {
+        AppError::Invalid(format!("{} is not a project file ({})", path.display(), e))
+    })?;
+    let mut bundle: ProjectBundle = {
+        let mut json = String::new();
+        zip.by_name(PROJECT_JSON)
+            .map_err(zip_err)?
+            .read_to_string(&mut json)?;
+        serde_json::from_str(&json)?
+    };
+    if projects::get_project(conn, &bundle.project.id).is_ok() {
+        projects::ensure_unlocked(conn, &bundle.project.id)?;
+    }
+
+    let mut media = BTreeSet::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(zip_err)?;
+        // Only ever write under the media folder.
+        let Some(rel) = entry.enclosed_name().filter(|p| p.starts_with("media")) else {
+            continue;
+        };
+        let dest = root.join(&rel);
+        media.insert(entry.name().to_string());
+        // Media names are unique, so a file already there is this one.
+        if dest.is_file() {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let partial = appended(&dest, "partial");
+        let copied = std::io::copy(&mut entry, &mut std::fs::File::create(&partial)?);
+        if let Err(e) = copied {
+            std::fs::remove_file(&partial).ok();
+            return Err(e.into());
+        }
+        std::fs::rename(&partial, &dest)?;
+    }
+    for scene in &mut bundle.scenes {
+        for field in [&mut scene.video_url, &mut scene.conditioning_image] {
+            if media.contains(field.as_str()) {
+                *field = root.join(field.as_str()).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    bundle::import_bundle(conn, &bundle)?;
+    Ok(bundle.project.id)
+}
+
+/// Load the `.aidc` file at `path` and remember it as its project's file.
+pub fn open(conn: &Connection, path: &Path) -> AppResult<ProjectSummary> {
+    let project_id = load(conn, path)?;
+    conn.execute(
+        "UPDATE projects SET file_path = ?2 WHERE id = ?1",
+        params![project_id, path.to_string_lossy()],
+    )?;
+    tracing::info!(project_id = %project_id, path = %path.display(), "opened project file");
+    projects::get_project(conn, &project_id)
+}
+
+/// `path` with `.suffix` added after any extension it has.
+pub fn appended(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    path.into()
+}
+
+/// Windows device names, which can't be file names with any extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+const MAX_NAME_CHARS: usize = 120;
+
+/// A file name for a project to suggest in a save dialog. Project names
+/// can come from files other people sent, so separators and characters
+/// no platform allows become `_`.
+pub fn suggested_name(project_name: &str, extension: &str) -> String {
+    let clean: String = project_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let clean = clean.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    let stem = if clean.is_empty() || RESERVED_NAMES.iter().any(|r| clean.eq_ignore_ascii_case(r)) {
+        format!("Project {}", clean).trim().to_string()
+    } else {
+        clean.to_string()
+    };
+    format!("{}.{}", stem, extension)
+}
+
+fn with_extension(path: PathBuf) -> PathBuf {
+    if path.extension().is_some_and(|e| e == EXTENSION) {
+        path
+    } else {
+        appended(&path, EXTENSION)
+    }
+}
+
+/// Save the project to its `.aidc` file, asking where with a save dialog
+/// the first time or with `save_as`. Returns None if the dialog was
+/// cancelled.
+#[tauri::command]
+pub async fn save_project_file(
+    app: AppHandle,
+    db: State<'_, Database>,
+    project_id: String,
+    save_as: Option<bool>,
+) -> AppResult<Option<ProjectSummary>> {
+    let project = projects::get_project(&db.conn(), &project_id)?;
+    let path = match project.file_path.filter(|_| !save_as.unwrap_or(false)) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let chosen = app
+                .dialog()
+                .file()
+                .set_title("Save Project")
+                .add_filter("AI Director's Chair project", &[EXTENSION])
+                .set_file_name(suggested_name(&project.name, EXTENSION))
+                .blocking_save_file();
+            let Some(chosen) = chosen else {
+                return Ok(None);
+            };
+            with_extension(
+                chosen
+                    .into_path()
+                    .map_err(|e| AppError::Invalid(format!("cannot save there: {}", e)))?,
+            )
+        }
+    };
+    save(&db.conn(), &project_id, &path).map(Some)
+}
+
+/// Open an `.aidc` file: `path` if given (a dropped or double-clicked
+/// file), else one picked in an open dialog. Returns None if the dialog
+/// was cancelled.
+#[tauri::command]
+pub async fn open_project_file(
+    app: AppHandle,
+    db: State<'_, Database>,
+    path: Option<String>,
+) -> AppResult<Option<ProjectSummary>> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let chosen = app
+                .dialog()
+                .file()
+                .set_title("Open Project")
+                .add_filter("AI Director's Chair project", &[EXTENSION])
+                .blocking_pick_file();
+            let Some(chosen) = chosen else {
+                return Ok(None);
+            };
+            chosen
+                .into_path()
+                .map_err(|e| AppError::Invalid(format!("cannot open that file: {}", e)))?
+        }
+    };
+    open(&db.conn(), &path).map(Some)
+}
@@ -0,0 +1,409 @@
+use crate::activity;
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::roles::{self, Role};
+use crate::users;
+use crate::validation::{Validate, Validator, MAX_NAME_LEN, MAX_TEXT_LEN};
+use crate::versioning;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+const DEFAULT_RECENT_LIMIT: usize = 10;
+
+/// A project as the launcher shows it.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProjectSummary {
+    pub id: String,
+    pub name: String,
+    pub genre: String,
+    pub synopsis: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_opened_at: Option<String>,
+    pub pinned: bool,
+    /// When the project was made read-only; see `lock_project`.
+    pub locked_at: Option<String>,
+    /// When the project was archived; see `archive::archive_project`.
+    pub archived_at: Option<String>,
+    /// The `.aidc` file the project was last opened from or saved to; see
+    /// `project_file`.
+    pub file_path: Option<String>,
+    /// Kept on this machine: no cloud LLM or voice, notifications,
+    /// analytics or cloud backup; see `confidential`.
+    pub confidential: bool,
+    #[ts(type = "number")]
+    pub scene_count: i64,
+    /// Pass back as `expected_version` when saving; see `versioning`.
+    #[ts(type = "number")]
+    pub version: i64,
+}
+
+/// Project fields the editor can write.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProjectInput {
+    pub name: String,
+    #[serde(default)]
+    pub genre: String,
+    #[serde(default)]
+    pub synopsis: String,
+    #[serde(default)]
+    pub tone: String,
+    /// The `version` the editor loaded; a stale one is rejected.
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub expected_version: Option<i64>,
+}
+
+impl Validate for ProjectInput {
+    fn validate(&self, v: &mut Validator) {
+        v.name("name", &self.name)
+            .max_len("genre", &self.genre, MAX_NAME_LEN)
+            .max_len("synopsis", &self.synopsis, MAX_TEXT_LEN)
+            .max_len("tone", &self.tone, MAX_NAME_LEN);
+    }
+}
+
+const PROJECT_COLUMNS: &str = "id, name, coalesce(genre, ''), coalesce(synopsis, ''), created_at, updated_at, last_opened_at, pinned,
+    (SELECT COUNT(*) FROM scenes s WHERE s.project_id = projects.id), version, locked_at, archived_at, file_path,
+    confidential";
+
+/// `PROJECT_COLUMNS` with the active user's opened and pinned state in
+/// place of the shared one; needs `user_projects u` joined.
+const USER_PROJECT_COLUMNS: &str = "id, name, coalesce(genre, ''), coalesce(synopsis, ''), created_at, updated_at, u.opened_at, coalesce(u.is_pinned, 0),
+    (SELECT COUNT(*) FROM scenes s WHERE s.project_id = projects.id), version, locked_at, archived_at, file_path,
+    confidential";
+
+fn row_to_project(row: &Row) -> rusqlite::Result<ProjectSummary> {
+    Ok(ProjectSummary {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        genre: row.get(2)?,
+        synopsis: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+        last_opened_at: row.get(6)?,
+        pinned: row.get(7)?,
+        scene_count: row.get(8)?,
+        version: row.get(9)?,
+        locked_at: row.get(10)?,
+        archived_at: row.get(11)?,
+        file_path: row.get(12)?,
+        confidential: row.get(13)?,
+    })
+}
+
+pub fn get_project(conn: &Connection, id: &str) -> AppResult<ProjectSummary> {
+    conn.query_row(
+        &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+        [id],
+        row_to_project,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("project {}", id)))
+}
+
+/// Refuse edits to a locked project. Call before writing anything that
+/// belongs to it: the project itself, its scenes, cast, props, boards,
+/// schedule and generations. Archived projects are read-only too, and
+/// only editors may edit shared ones; see `roles`.
+pub fn ensure_unlocked(conn: &Connection, project_id: &str) -> AppResult<()> {
+    get_project(conn, project_id)?;
+    roles::ensure_role(conn, project_id, Role::Editor)?;
+    ensure_writable(conn, project_id)
+}
+
+/// `ensure_unlocked` without the role check, for edits that aren't this
+/// user's: changes merged in from other devices (see `sync`).
+pub fn ensure_writable(conn: &Connection, project_id: &str) -> AppResult<()> {
+    let project = get_project(conn, project_id)?;
+    if project.archived_at.is_some() {
+        return Err(AppError::Locked(format!(
+            "project {} is archived; restore it before editing",
+            project.name
+        )));
+    }
+    if project.locked_at.is_some() {
+        return Err(AppError::Locked(format!(
+            "project {} is locked; unlock it before editing",
+            project.name
+        )));
+    }
+    Ok(())
+}
+
+/// `ensure_unlocked` for the project a scene belongs to.
+pub fn ensure_scene_unlocked(conn: &Connection, scene_id: &str) -> AppResult<()> {
+    let project_id: String = conn
+        .query_row(
+            "SELECT project_id FROM scenes WHERE id = ?1",
+            [scene_id],
+            |r| r.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("scene {}", scene_id)))?;
+    ensure_unlocked(conn, &project_id)
+}
+
+/// Update a project's details, recording the edit in the activity feed.
+pub fn update(conn: &Connection, id: &str, input: &ProjectInput) -> AppResult<ProjectSummary> {
+    let mut v = Validator::new();
+    v.uuid("id", id);
+    input.validate(&mut v);
+    v.finish()?;
+    ensure_unlocked(conn, id)?;
+    let name = input.name.trim();
+    let current = get_project(conn, id)?;
+    versioning::check(
+        "project",
+        id,
+        input.expected_version,
+        current.version,
+        &current,
+        input,
+    )?;
+    let before = export::load_project(conn, id)?;
+    conn.execute(
+        "UPDATE projects SET name = ?2, genre = ?3, synopsis = ?4, tone = ?5,
+            updated_at = ?6
+         WHERE id = ?1",
+        params![
+            id,
+            name,
+            input.genre,
+            input.synopsis,
+            input.tone,
+            clock::now()
+        ],
+    )?;
+    let mut changes = Vec::new();
+    activity::diff(&mut changes, "name", &before.name, name);
+    activity::diff(&mut changes, "genre", &before.genre, &input.genre);
+    activity::diff(&mut changes, "synopsis", &before.synopsis, &input.synopsis);
+    activity::diff(&mut changes, "tone", &before.tone, &input.tone);
+    activity::record(
+        conn,
+        id,
+        "project",
+        id,
+        "updated",
+        &format!("Edited project {}", name),
+        &changes,
+    )?;
+    get_project(conn, id)
+}
+
+/// A project as the given user sees it in the launcher.
+fn get_for_user(conn: &Connection, id: &str, user_id: &str) -> AppResult<ProjectSummary> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM projects
+             LEFT JOIN user_projects u ON u.project_id = projects.id AND u.user_id = ?2
+             WHERE id = ?1",
+            USER_PROJECT_COLUMNS
+        ),
+        params![id, user_id],
+        row_to_project,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("project {}", id)))
+}
+
+/// Pinned projects first, then the rest by when they were last opened
+/// (never-opened projects by creation date). With a user profile active,
+/// pins and opens are that user's own. Archived projects are left out
+/// unless `include_archived` is set.
+pub fn recent(
+    conn: &Connection,
+    limit: usize,
+    include_archived: bool,
+) -> AppResult<Vec<ProjectSummary>> {
+    let limit = limit as i64;
+    let archived = include_archived;
+    let projects = match users::active() {
+        Some(user_id) => {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM projects
+                 LEFT JOIN user_projects u ON u.project_id = projects.id AND u.user_id = ?2
+                 WHERE ?3 OR archived_at IS NULL
+                 ORDER BY coalesce(u.is_pinned, 0) DESC, coalesce(u.opened_at, created_at) DESC, name
+                 LIMIT ?1",
+                USER_PROJECT_COLUMNS
+            ))?;
+            let rows = stmt.query_map(params![limit, user_id, archived], row_to_project)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        }
+        None => {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM projects
+                 WHERE ?2 OR archived_at IS NULL
+                 ORDER BY pinned DESC, coalesce(last_opened_at, created_at) DESC, name
+                 LIMIT ?1",
+                PROJECT_COLUMNS
+            ))?;
+            let rows = stmt.query_map(params![limit, archived], row_to_project)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        }
+    };
+    Ok(projects)
+}
+
+#[tauri::command]
+pub fn get_recent_projects(
+    db: State<'_, Database>,
+    limit: Option<usize>,
+    include_archived: Option<bool>,
+) -> AppResult<Vec<ProjectSummary>> {
+    recent(
+        &db.conn(),
+        limit.unwrap_or(DEFAULT_RECENT_LIMIT),
+        include_archived.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub fn update_project(
+    db: State<'_, Database>,
+    id: String,
+    input: ProjectInput,
+) -> AppResult<ProjectSummary> {
+    update(&db.conn(), &id, &input)
+}
+
+/// Record that the user opened a project. Call when the editor loads it.
+pub fn mark_opened(conn: &Connection, id: &str) -> AppResult<ProjectSummary> {
+    let changed = conn.execute(
+        "UPDATE projects SET last_opened_at = ?2 WHERE id = ?1",
+        params![id, clock::now()],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("project {}", id)));
+    }
+    match users::active() {
+        Some(user_id) => {
+            conn.execute(
+                "INSERT INTO user_projects (user_id, project_id, opened_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(user_id, project_id) DO UPDATE SET opened_at = excluded.opened_at",
+                params![user_id, id, clock::now()],
+            )?;
+            get_for_user(conn, id, &user_id)
+        }
+        None => get_project(conn, id),
+    }
+}
+
+#[tauri::command]
+pub fn mark_project_opened(db: State<'_, Database>, id: String) -> AppResult<ProjectSummary> {
+    mark_opened(&db.conn(), &id)
+}
+
+pub fn set_pinned(conn: &Connection, id: &str, pinned: bool) -> AppResult<ProjectSummary> {
+    if let Some(user_id) = users::active() {
+        get_project(conn, id)?;
+        conn.execute(
+            "INSERT INTO user_projects (user_id, project_id, is_pinned) VALUES (?1, ?2, ?3)
+             ON CONFLICT(user_id, project_id) DO UPDATE SET is_pinned = excluded.is_pinned",
+            params![user_id, id, pinned],
+        )?;
+        return get_for_user(conn, id, &user_id);
+    }
+    let changed = conn.execute(
+        "UPDATE projects SET pinned = ?2 WHERE id = ?1",
+        params![id, pinned],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("project {}", id)));
+    }
+    get_project(conn, id)
+}
+
+#[tauri::command]
+pub fn set_project_pinned(
+    db: State<'_, Database>,
+    id: String,
+    pinned: bool,
+) -> AppResult<ProjectSummary> {
+    set_pinned(&db.conn(), &id, pinned)
+}
+
+/// Make a project read-only. Every command that edits it, its scenes or
+/// its assets fails with `AppError::Locked` until `unlock`.
+/// Comments stay open so a delivered project can still be reviewed.
+pub fn lock(conn: &Connection, project_id: &str) -> AppResult<ProjectSummary> {
+    let project = get_project(conn, project_id)?;
+    roles::ensure_role(conn, project_id, Role::Owner)?;
+    if project.locked_at.is_some() {
+        return Ok(project);
+    }
+    conn.execute(
+        "UPDATE projects SET locked_at = ?2 WHERE id = ?1",
+        params![project_id, clock::now()],
+    )?;
+    activity::record(
+        conn,
+        project_id,
+        "project",
+        project_id,
+        "locked",
+        &format!("Locked project {}", project.name),
+        &[],
+    )?;
+    get_project(conn, project_id)
+}
+
+#[tauri::command]
+pub fn lock_project(db: State<'_, Database>, project_id: String) -> AppResult<ProjectSummary> {
+    lock(&db.conn(), &project_id)
+}
+
+/// Make a locked project editable again. `confirm_name` must repeat the
+/// project's name, so an unlock is always a deliberate act.
+pub fn unlock(
+    conn: &Connection,
+    project_id: &str,
+    confirm_name: &str,
+) -> AppResult<ProjectSummary> {
+    let project = get_project(conn, project_id)?;
+    roles::ensure_role(conn, project_id, Role::Owner)?;
+    if project.locked_at.is_none() {
+        return Ok(project);
+    }
+    if !confirm_name
+        .trim()
+        .eq_ignore_ascii_case(project.name.trim())
+    {
+        return Err(AppError::Invalid(
+            "type the project's name to confirm unlocking it".into(),
+        ));
+    }
+    conn.execute(
+        "UPDATE projects SET locked_at = NULL WHERE id = ?1",
+        [project_id],
+    )?;
+    activity::record(
+        conn,
+        project_id,
+        "project",
+        project_id,
+        "unlocked",
+        &format!("Unlocked project {}", project.name),
+        &[],
+    )?;
+    get_project(conn, project_id)
+}
+
+#[tauri::command]
+pub fn unlock_project(
+    db: State<'_, Database>,
+    project_id: String,
+    confirm_name: String,
+) -> AppResult<ProjectSummary> {
+    unlock(&db.conn(), &project_id, &confirm_name)
+}
@@ -0,0 +1,423 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, Credential};
+use crate::presets::{self, StylePreset};
+use crate::providers::{self, ModelSpec};
+use crate::scenes::{self, Scene};
+use crate::vocabulary::{self, VocabularyKind};
+use crate::{analytics, clock, export, generation, numbering};
+use crate::{character_fragments, moodboards, props};
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct EnhancedPrompt {
+    /// Output of the LLM stage, before provider formatting.
+    pub enhanced: String,
+    /// Final text to send to `provider`.
+    pub prompt: String,
+    pub negative_prompt: Option<String>,
+    pub preset_id: Option<String>,
+    pub provider: String,
+    /// False when the LLM stage was skipped and preset keywords were used.
+    pub llm_enhanced: bool,
+}
+
+/// Everything besides the description that feeds prompt assembly.
+#[derive(Default)]
+struct PromptContext {
+    preset: Option<StylePreset>,
+    /// Camera angle and lighting vocabulary fragments.
+    shot: Vec<String>,
+    /// Captions of moodboard items marked for prompt inclusion.
+    references: Vec<String>,
+    /// The scene's props and wardrobe, e.g. "Mara wearing the red leather
+    /// jacket".
+    details: Vec<String>,
+    /// The cast's appearance and mannerism fragments, one per character.
+    characters: Vec<String>,
+    /// The cast's negative fragments.
+    negative: Vec<String>,
+}
+
+fn enhancement_prompt(description: &str, ctx: &PromptContext) -> String {
+    let mut style = match &ctx.preset {
+        Some(p) => format!(
+            "Style preset: {}\nDirection: {}\nKeywords to honour: {}",
+            p.name, p.guidance, p.keywords
+        ),
+        None => "Style: grounded, cinematic, naturalistic.".to_string(),
+    };
+    if !ctx.shot.is_empty() {
+        style.push_str(&format!("\nShot and lighting: {}", ctx.shot.join(", ")));
+    }
+    if !ctx.references.is_empty() {
+        style.push_str(&format!(
+            "\nVisual references from the moodboard: {}",
+            ctx.references.join("; ")
+        ));
+    }
+    if !ctx.characters.is_empty() {
+        style.push_str(&format!(
+            "\nCharacters, to be described exactly as written: {}",
+            ctx.characters.join(" | ")
+        ));
+    }
+    if !ctx.details.is_empty() {
+        style.push_str(&format!(
+            "\nProps and wardrobe to show exactly as written: {}",
+            ctx.details.join("; ")
+        ));
+    }
+    format!(
+        "You are a cinematographer writing a prompt for an AI video generator.
+
+Scene description:
+{}
+
+{}
+
+Rewrite the scene as a single vivid paragraph (under 80 words) describing subject, action, setting, camera movement, lens, lighting and colour. Keep every story fact from the description. Do not add dialog or on-screen text.
+
+Return JSON: {{\"prompt\": \"...\"}}",
+        description, style
+    )
+}
+
+/// Keyword fallback used when no Gemini key is configured.
+fn append_keywords(description: &str, ctx: &PromptContext) -> String {
+    let mut parts = vec![description.trim_end_matches('.').to_string()];
+    parts.extend(ctx.characters.iter().cloned());
+    parts.extend(ctx.details.iter().cloned());
+    parts.extend(ctx.shot.iter().cloned());
+    if let Some(keywords) = ctx
+        .preset
+        .as_ref()
+        .map(|p| p.keywords.trim())
+        .filter(|k| !k.is_empty())
+    {
+        parts.push(keywords.to_string());
+    }
+    parts.join(", ")
+}
+
+/// A description ready for `enhance`, from `prepare`.
+pub struct Enhancement {
+    description: String,
+    provider: String,
+    spec: &'static ModelSpec,
+    ctx: PromptContext,
+    credential: Option<Credential>,
+}
+
+/// Two-stage prompt pipeline: the description is rewritten by the LLM under a
+/// style preset, then shaped for the target provider. `preset_id` wins over
+/// the project's default preset; with neither, only the house style applies.
+/// Camera angle and lighting keys pull their vocabulary prompt fragments in,
+/// and a project's flagged moodboard captions are passed as references.
+/// With `scene_id`, the cast's character fragments and the scene's props and
+/// wardrobe are written in too; negative fragments join the preset's
+/// negative prompt.
+/// Everything is read here, so the database isn't held during the LLM
+/// call in `enhance`.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare(
+    conn: &Connection,
+    description: &str,
+    provider: &str,
+    project_id: Option<&str>,
+    preset_id: Option<&str>,
+    camera_angle: Option<&str>,
+    lighting: Option<&str>,
+    scene_id: Option<&str>,
+) -> AppResult<Enhancement> {
+    let description = description.trim().to_string();
+    if description.is_empty() {
+        return Err(AppError::Invalid("description is empty".into()));
+    }
+    let spec = providers::model(provider)?;
+    let preset = match (preset_id, project_id) {
+        (Some(id), _) => Some(presets::get_preset(conn, id)?),
+        (None, Some(project)) => presets::project_default(conn, project)?,
+        (None, None) => None,
+    };
+    let mut shot = Vec::new();
+    for (kind, key) in [
+        (VocabularyKind::CameraAngle, camera_angle),
+        (VocabularyKind::Lighting, lighting),
+    ] {
+        if let Some(fragment) = key
+            .map(|k| vocabulary::prompt_fragment(conn, kind, k))
+            .transpose()?
+            .flatten()
+        {
+            shot.push(fragment);
+        }
+    }
+    let references = match project_id {
+        Some(project) => moodboards::prompt_references(conn, project)?,
+        None => Vec::new(),
+    };
+    let (details, fragments) = match scene_id {
+        Some(scene) => (
+            props::prompt_fragments(conn, scene)?,
+            character_fragments::for_scene(conn, scene)?,
+        ),
+        None => Default::default(),
+    };
+    let ctx = PromptContext {
+        preset,
+        shot,
+        references,
+        details,
+        characters: fragments.characters,
+        negative: fragments.negative,
+    };
+    // A confidential project without a local model falls back to
+    // keywords, same as having no LLM at all.
+    let project = match (project_id, scene_id) {
+        (Some(project), _) => Some(project.to_string()),
+        (None, Some(scene)) => Some(scenes::get_scene(conn, scene)?.project_id),
+        (None, None) => None,
+    };
+    Ok(Enhancement {
+        description,
+        provider: provider.to_string(),
+        spec,
+        credential: llm::credential(conn, project.as_deref()).ok(),
+        ctx,
+    })
+}
+
+/// Run a prepared description through both stages, falling back to
+/// keywords when no LLM is usable.
+pub async fn enhance(enhancement: Enhancement) -> AppResult<EnhancedPrompt> {
+    let Enhancement {
+        description,
+        provider,
+        spec,
+        ctx,
+        credential,
+    } = enhancement;
+    let (enhanced, llm_enhanced) = match credential {
+        Some(credential) => {
+            let reply =
+                llm::generate_json(&credential, &enhancement_prompt(&description, &ctx), 0.7)
+                    .await?;
+            match reply["prompt"]
+                .as_str()
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+            {
+                Some(p) => (p.to_string(), true),
+                None => (append_keywords(&description, &ctx), false),
+            }
+        }
+        None => (append_keywords(&description, &ctx), false),
+    };
+
+    let negative_prompt = ctx
+        .preset
+        .as_ref()
+        .map(|p| p.negative_prompt.trim().to_string())
+        .into_iter()
+        .chain(ctx.negative.iter().cloned())
+        .filter(|n| !n.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let negative_prompt =
+        Some(negative_prompt).filter(|n| !n.is_empty() && spec.supports_negative_prompt);
+
+    Ok(EnhancedPrompt {
+        prompt: providers::format_prompt(spec, &enhanced),
+        enhanced,
+        negative_prompt,
+        preset_id: ctx.preset.map(|p| p.id),
+        provider,
+        llm_enhanced,
+    })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn enhance_prompt(
+    app: AppHandle,
+    description: String,
+    provider: String,
+    project_id: Option<String>,
+    preset_id: Option<String>,
+    camera_angle: Option<String>,
+    lighting: Option<String>,
+    scene_id: Option<String>,
+) -> AppResult<EnhancedPrompt> {
+    let enhancement = prepare(
+        &app.state::<Database>().conn(),
+        &description,
+        &provider,
+        project_id.as_deref(),
+        preset_id.as_deref(),
+        camera_angle.as_deref(),
+        lighting.as_deref(),
+        scene_id.as_deref(),
+    )?;
+    enhance(enhancement).await
+}
+
+/// A scene's prompt as it would be sent, for use outside the app.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AssembledPrompt {
+    pub scene_id: String,
+    /// Scene number with its letter, e.g. `12A`.
+    pub scene: String,
+    pub title: String,
+    /// The model the scene would be generated with, if one is chosen.
+    pub model: Option<String>,
+    pub prompt: String,
+    pub negative_prompt: Option<String>,
+}
+
+/// The scene's prompt (its description if it has none) with the style
+/// preset's keywords, the cast's character fragments, props, wardrobe and
+/// shot vocabulary expanded into it, shaped for the scene's model. This is
+/// the keyword assembly `enhance_prompt` falls back on, so it needs no LLM
+/// and gives the same text every time.
+pub fn assemble(conn: &Connection, scene: &Scene) -> AppResult<AssembledPrompt> {
+    let mut shot = Vec::new();
+    for (kind, key) in [
+        (VocabularyKind::CameraAngle, &scene.camera_angle),
+        (VocabularyKind::Lighting, &scene.lighting),
+    ] {
+        if let Some(fragment) = vocabulary::prompt_fragment(conn, kind, key)? {
+            shot.push(fragment);
+        }
+    }
+    let fragments = character_fragments::for_scene(conn, &scene.id)?;
+    let ctx = PromptContext {
+        preset: presets::project_default(conn, &scene.project_id)?,
+        shot,
+        references: Vec::new(),
+        details: props::prompt_fragments(conn, &scene.id)?,
+        characters: fragments.characters,
+        negative: fragments.negative,
+    };
+    let base = if scene.prompt.trim().is_empty() {
+        &scene.description
+    } else {
+        &scene.prompt
+    };
+    let text = append_keywords(base.trim(), &ctx);
+    let spec = generation::effective(conn, scene)?
+        .model
+        .and_then(|key| providers::model(&key).ok());
+
+    let negative = ctx
+        .preset
+        .as_ref()
+        .map(|p| p.negative_prompt.trim().to_string())
+        .into_iter()
+        .chain(ctx.negative.iter().cloned())
+        .filter(|n| !n.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(AssembledPrompt {
+        scene_id: scene.id.clone(),
+        scene: numbering::label(scene.scene_number, &scene.scene_suffix),
+        title: scene.title.clone(),
+        model: spec.map(|s| s.key.to_string()),
+        prompt: match spec {
+            Some(spec) => providers::format_prompt(spec, &text),
+            None => text,
+        },
+        negative_prompt: Some(negative)
+            .filter(|n| !n.is_empty() && spec.map_or(true, |s| s.supports_negative_prompt)),
+    })
+}
+
+/// `assemble` for the scene with this id.
+pub fn for_scene(conn: &Connection, scene_id: &str) -> AppResult<AssembledPrompt> {
+    assemble(conn, &scenes::get_scene(conn, scene_id)?)
+}
+
+/// Copy a scene's assembled prompt to the clipboard, returning it too.
+#[tauri::command]
+pub fn copy_scene_prompt(app: AppHandle, scene_id: String) -> AppResult<AssembledPrompt> {
+    let assembled = for_scene(&app.state::<Database>().conn(), &scene_id)?;
+    app.clipboard()
+        .write_text(assembled.prompt.as_str())
+        .map_err(|e| AppError::Io(format!("could not copy to the clipboard: {}", e)))?;
+    Ok(assembled)
+}
+
+fn prompts_text(project: &str, prompts: &[AssembledPrompt]) -> String {
+    let mut out = format!("{}\n\n", project);
+    for p in prompts {
+        out.push_str(&format!("## Scene {}", p.scene));
+        if !p.title.is_empty() {
+            out.push_str(&format!(" - {}", p.title));
+        }
+        out.push('\n');
+        if let Some(model) = &p.model {
+            out.push_str(&format!("Model: {}\n", model));
+        }
+        out.push_str(&format!("\n{}\n", p.prompt));
+        if let Some(negative) = &p.negative_prompt {
+            out.push_str(&format!("\nNegative: {}\n", negative));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Write every scene's assembled prompt, in board order, to one file:
+/// `format` is `"text"` (headed sections) or `"json"`. Returns the written
+/// path.
+pub fn export_all(
+    conn: &Connection,
+    project_id: &str,
+    path: &str,
+    format: &str,
+) -> AppResult<String> {
+    let project = export::load_project(conn, project_id)?;
+    let prompts = scenes::list_for_project(conn, project_id)?
+        .iter()
+        .map(|scene| assemble(conn, scene))
+        .collect::<AppResult<Vec<_>>>()?;
+    let (contents, extension) = match format {
+        "text" => (prompts_text(&project.name, &prompts), "txt"),
+        "json" => (
+            serde_json::to_string_pretty(&serde_json::json!({
+                "project": project.name,
+                "exportedAt": clock::now(),
+                "prompts": prompts,
+            }))?,
+            "json",
+        ),
+        other => {
+            return Err(AppError::Invalid(format!(
+                "unknown prompt export format: {}",
+                other
+            )))
+        }
+    };
+    let out = export::resolve_output(path, &format!("prompts.{}", extension))?;
+    std::fs::write(&out, contents)?;
+    analytics::track(conn, project_id, "export", &format!("prompts_{}", format));
+    Ok(out.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub fn export_project_prompts(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+    format: String,
+) -> AppResult<String> {
+    export_all(&db.conn(), &project_id, &path, &format)
+}
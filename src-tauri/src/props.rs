@@ -0,0 +1,490 @@
+//! Props and wardrobe, tracked so visual details carry over between
+//! generations. An item is attached to a scene, to a character (worn or
+//! carried wherever they appear), or to a character in one scene; a
+//! character's wardrobe in a scene replaces what they usually wear. A
+//! scene's items are spliced into its prompt ("Mara wearing the red leather
+//! jacket") and listed for the continuity check.
+
+use crate::characters;
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::scenes;
+use crate::validation::{Validate, Validator, MAX_TEXT_LEN};
+use crate::workflow;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum PropKind {
+    Prop,
+    Wardrobe,
+}
+
+impl PropKind {
+    const ALL: [PropKind; 2] = [PropKind::Wardrobe, PropKind::Prop];
+
+    fn table(self) -> &'static str {
+        match self {
+            PropKind::Prop => "props",
+            PropKind::Wardrobe => "wardrobe",
+        }
+    }
+
+    fn links_table(self) -> &'static str {
+        match self {
+            PropKind::Prop => "prop_links",
+            PropKind::Wardrobe => "wardrobe_links",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PropKind::Prop => "prop",
+            PropKind::Wardrobe => "wardrobe item",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Prop {
+    pub id: String,
+    pub project_id: String,
+    pub kind: PropKind,
+    pub name: String,
+    pub description: String,
+    /// How the item reads in a prompt, e.g. "the red leather jacket";
+    /// empty uses the name.
+    pub prompt_fragment: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PropInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub project_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub prompt_fragment: String,
+}
+
+impl Validate for PropInput {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_uuid("id", self.id.as_deref())
+            .uuid("projectId", &self.project_id)
+            .name("name", &self.name)
+            .max_len("description", &self.description, MAX_TEXT_LEN)
+            .max_len("promptFragment", &self.prompt_fragment, MAX_TEXT_LEN);
+    }
+}
+
+/// Where an item is attached. At least one of `scene_id` and
+/// `character_id` is set.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PropLink {
+    #[ts(type = "number")]
+    pub id: i64,
+    pub kind: PropKind,
+    pub item_id: String,
+    pub scene_id: Option<String>,
+    pub character_id: Option<String>,
+    pub created_at: String,
+}
+
+/// An item in effect in a scene, for prompts and the continuity check.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneDetail {
+    pub kind: PropKind,
+    pub item_id: String,
+    pub name: String,
+    pub fragment: String,
+    pub character_id: Option<String>,
+    pub character_name: Option<String>,
+    /// False when it comes from the character rather than this scene.
+    pub scene_specific: bool,
+}
+
+const PROP_COLUMNS: &str = "id, project_id, name, description, prompt_fragment, created_at";
+
+fn row_to_prop(kind: PropKind) -> impl Fn(&Row) -> rusqlite::Result<Prop> {
+    move |row| {
+        Ok(Prop {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            kind,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            prompt_fragment: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+}
+
+fn row_to_link(kind: PropKind) -> impl Fn(&Row) -> rusqlite::Result<PropLink> {
+    move |row| {
+        Ok(PropLink {
+            id: row.get(0)?,
+            kind,
+            item_id: row.get(1)?,
+            scene_id: row.get(2)?,
+            character_id: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }
+}
+
+pub fn get_prop(conn: &Connection, kind: PropKind, id: &str) -> AppResult<Prop> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM {} WHERE id = ?1",
+            PROP_COLUMNS,
+            kind.table()
+        ),
+        [id],
+        row_to_prop(kind),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("{} {}", kind.label(), id)))
+}
+
+pub fn list_for_project(
+    conn: &Connection,
+    project_id: &str,
+    kind: PropKind,
+) -> AppResult<Vec<Prop>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE project_id = ?1 ORDER BY name COLLATE NOCASE, created_at",
+        PROP_COLUMNS,
+        kind.table()
+    ))?;
+    let props = stmt
+        .query_map([project_id], row_to_prop(kind))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(props)
+}
+
+/// Props and wardrobe in effect in a scene: those attached to it, plus what
+/// its cast wear and carry everywhere. Someone given wardrobe for this
+/// scene doesn't also get their usual wardrobe. Ordered by billing.
+pub fn scene_details(conn: &Connection, scene_id: &str) -> AppResult<Vec<SceneDetail>> {
+    let mut details = Vec::new();
+    for kind in PropKind::ALL {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT i.id, i.name,
+                CASE WHEN trim(i.prompt_fragment) = '' THEN i.name ELSE i.prompt_fragment END,
+                l.character_id, c.name, l.scene_id IS NOT NULL
+             FROM {} l JOIN {} i ON i.id = l.item_id
+             LEFT JOIN characters c ON c.id = l.character_id
+             LEFT JOIN scene_characters sc ON sc.scene_id = ?1 AND sc.character_id = l.character_id
+             WHERE l.scene_id = ?1 OR (l.scene_id IS NULL AND sc.character_id IS NOT NULL)
+             ORDER BY l.character_id IS NULL, sc.position, c.name, i.name COLLATE NOCASE",
+            kind.links_table(),
+            kind.table()
+        ))?;
+        let rows = stmt
+            .query_map([scene_id], |r| {
+                Ok(SceneDetail {
+                    kind,
+                    item_id: r.get(0)?,
+                    name: r.get(1)?,
+                    fragment: r.get(2)?,
+                    character_id: r.get(3)?,
+                    character_name: r.get(4)?,
+                    scene_specific: r.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let costumed: Vec<Option<String>> = rows
+            .iter()
+            .filter(|d| d.scene_specific && d.character_id.is_some())
+            .map(|d| d.character_id.clone())
+            .collect();
+        details.extend(rows.into_iter().filter(|d| {
+            kind != PropKind::Wardrobe || d.scene_specific || !costumed.contains(&d.character_id)
+        }));
+    }
+    Ok(details)
+}
+
+/// A scene's props and wardrobe as prompt phrases: one per character
+/// ("Mara wearing the red leather jacket, carrying a brass compass"), then
+/// one per item attached to the scene alone.
+pub fn prompt_fragments(conn: &Connection, scene_id: &str) -> AppResult<Vec<String>> {
+    let details = scene_details(conn, scene_id)?;
+    let mut people: Vec<(&str, Vec<&str>, Vec<&str>)> = Vec::new();
+    let mut loose = Vec::new();
+    for d in &details {
+        let Some(name) = d.character_name.as_deref() else {
+            loose.push(d.fragment.clone());
+            continue;
+        };
+        let i = match people.iter().position(|(n, _, _)| *n == name) {
+            Some(i) => i,
+            None => {
+                people.push((name, Vec::new(), Vec::new()));
+                people.len() - 1
+            }
+        };
+        match d.kind {
+            PropKind::Wardrobe => people[i].1.push(&d.fragment),
+            PropKind::Prop => people[i].2.push(&d.fragment),
+        }
+    }
+    let mut fragments: Vec<String> = people
+        .into_iter()
+        .map(|(name, worn, carried)| {
+            let mut phrase = name.to_string();
+            if !worn.is_empty() {
+                phrase.push_str(&format!(" wearing {}", worn.join(" and ")));
+            }
+            if !carried.is_empty() {
+                if !worn.is_empty() {
+                    phrase.push(',');
+                }
+                phrase.push_str(&format!(" carrying {}", carried.join(" and ")));
+            }
+            phrase
+        })
+        .collect();
+    fragments.extend(loose);
+    Ok(fragments)
+}
+
+pub fn list(conn: &Connection, project_id: &str, kind: Option<PropKind>) -> AppResult<Vec<Prop>> {
+    let mut props = Vec::new();
+    for k in PropKind::ALL
+        .into_iter()
+        .filter(|k| kind.map_or(true, |kind| kind == *k))
+    {
+        props.extend(list_for_project(conn, project_id, k)?);
+    }
+    Ok(props)
+}
+
+#[tauri::command]
+pub fn list_props(
+    db: State<'_, Database>,
+    project_id: String,
+    kind: Option<PropKind>,
+) -> AppResult<Vec<Prop>> {
+    list(&db.conn(), &project_id, kind)
+}
+
+pub fn save(conn: &Connection, kind: PropKind, input: &PropInput) -> AppResult<Prop> {
+    input.check()?;
+    projects::ensure_unlocked(conn, &input.project_id)?;
+    let id = match &input.id {
+        Some(id) => {
+            let changed = conn.execute(
+                &format!(
+                    "UPDATE {} SET name = ?2, description = ?3, prompt_fragment = ?4
+                     WHERE id = ?1 AND project_id = ?5",
+                    kind.table()
+                ),
+                params![
+                    id,
+                    input.name.trim(),
+                    input.description,
+                    input.prompt_fragment.trim(),
+                    input.project_id
+                ],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("{} {}", kind.label(), id)));
+            }
+            id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                &format!(
+                    "INSERT INTO {} (id, project_id, name, description, prompt_fragment, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    kind.table()
+                ),
+                params![
+                    id,
+                    input.project_id,
+                    input.name.trim(),
+                    input.description,
+                    input.prompt_fragment.trim(),
+                    clock::now()
+                ],
+            )?;
+            id
+        }
+    };
+    get_prop(conn, kind, &id)
+}
+
+#[tauri::command]
+pub fn save_prop(db: State<'_, Database>, kind: PropKind, input: PropInput) -> AppResult<Prop> {
+    save(&db.conn(), kind, &input)
+}
+
+/// Delete an item and everywhere it's attached.
+pub fn delete(conn: &Connection, kind: PropKind, id: &str) -> AppResult<()> {
+    let item = get_prop(conn, kind, id)?;
+    projects::ensure_unlocked(conn, &item.project_id)?;
+    conn.execute(&format!("DELETE FROM {} WHERE id = ?1", kind.table()), [id])?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_prop(db: State<'_, Database>, kind: PropKind, id: String) -> AppResult<()> {
+    delete(&db.conn(), kind, &id)
+}
+
+pub fn links(conn: &Connection, project_id: &str) -> AppResult<Vec<PropLink>> {
+    let mut links = Vec::new();
+    for kind in PropKind::ALL {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT l.id, l.item_id, l.scene_id, l.character_id, l.created_at
+             FROM {} l JOIN {} i ON i.id = l.item_id
+             WHERE i.project_id = ?1 ORDER BY l.id",
+            kind.links_table(),
+            kind.table()
+        ))?;
+        links.extend(
+            stmt.query_map([project_id], row_to_link(kind))?
+                .collect::<rusqlite::Result<Vec<_>>>()?,
+        );
+    }
+    Ok(links)
+}
+
+#[tauri::command]
+pub fn list_prop_links(db: State<'_, Database>, project_id: String) -> AppResult<Vec<PropLink>> {
+    links(&db.conn(), &project_id)
+}
+
+/// Attach an item to a scene, a character, or a character in one scene.
+/// Attaching it somewhere it already is returns the existing link.
+pub fn attach(
+    conn: &Connection,
+    kind: PropKind,
+    item_id: &str,
+    scene_id: Option<&str>,
+    character_id: Option<&str>,
+) -> AppResult<PropLink> {
+    if scene_id.is_none() && character_id.is_none() {
+        return Err(AppError::Invalid(format!(
+            "attach the {} to a scene or a character",
+            kind.label()
+        )));
+    }
+    let item = get_prop(conn, kind, item_id)?;
+    projects::ensure_unlocked(conn, &item.project_id)?;
+    if let Some(scene_id) = scene_id {
+        workflow::ensure_editable(conn, scene_id)?;
+        if scenes::get_scene(conn, scene_id)?.project_id != item.project_id {
+            return Err(AppError::Invalid(format!(
+                "scene {} belongs to another project",
+                scene_id
+            )));
+        }
+    }
+    if let Some(character_id) = character_id {
+        let character = characters::get_character(conn, character_id)?;
+        if character.project_id != item.project_id {
+            return Err(AppError::Invalid(format!(
+                "character {} belongs to another project",
+                character.name
+            )));
+        }
+    }
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO {} (item_id, scene_id, character_id, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            kind.links_table()
+        ),
+        params![item_id, scene_id, character_id, clock::now()],
+    )?;
+    Ok(conn.query_row(
+        &format!(
+            "SELECT id, item_id, scene_id, character_id, created_at FROM {}
+             WHERE item_id = ?1 AND scene_id IS ?2 AND character_id IS ?3",
+            kind.links_table()
+        ),
+        params![item_id, scene_id, character_id],
+        row_to_link(kind),
+    )?)
+}
+
+#[tauri::command]
+pub fn attach_prop(
+    db: State<'_, Database>,
+    kind: PropKind,
+    item_id: String,
+    scene_id: Option<String>,
+    character_id: Option<String>,
+) -> AppResult<PropLink> {
+    attach(
+        &db.conn(),
+        kind,
+        &item_id,
+        scene_id.as_deref(),
+        character_id.as_deref(),
+    )
+}
+
+pub fn detach(conn: &Connection, kind: PropKind, link_id: i64) -> AppResult<()> {
+    let (scene_id, project_id): (Option<String>, String) = conn
+        .query_row(
+            &format!(
+                "SELECT l.scene_id, i.project_id FROM {} l JOIN {} i ON i.id = l.item_id
+                 WHERE l.id = ?1",
+                kind.links_table(),
+                kind.table()
+            ),
+            [link_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("{} link {}", kind.label(), link_id)))?;
+    projects::ensure_unlocked(conn, &project_id)?;
+    if let Some(scene_id) = &scene_id {
+        workflow::ensure_editable(conn, scene_id)?;
+    }
+    conn.execute(
+        &format!("DELETE FROM {} WHERE id = ?1", kind.links_table()),
+        [link_id],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn detach_prop(db: State<'_, Database>, kind: PropKind, link_id: i64) -> AppResult<()> {
+    detach(&db.conn(), kind, link_id)
+}
+
+/// The props and wardrobe in effect in a scene; see `scene_details`.
+pub fn details_for_scene(conn: &Connection, scene_id: &str) -> AppResult<Vec<SceneDetail>> {
+    scenes::get_scene(conn, scene_id)?;
+    scene_details(conn, scene_id)
+}
+
+#[tauri::command]
+pub fn list_scene_details(
+    db: State<'_, Database>,
+    scene_id: String,
+) -> AppResult<Vec<SceneDetail>> {
+    details_for_scene(&db.conn(), &scene_id)
+}
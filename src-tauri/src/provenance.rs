@@ -0,0 +1,187 @@
+//! Where an exported file came from, written into the file itself so a
+//! clip or PDF forwarded around by email can be traced back to its
+//! project and scenes: MP4 metadata tags for videos and an XMP packet for
+//! PNGs and PDFs.
+//!
+//! Each scene is identified by its id and a hash of its prompt, so a file
+//! shows whether the scene has been rewritten since it was exported.
+
+use crate::error::AppResult;
+use crate::numbering;
+use crate::projects;
+use crate::scenes::{self, Scene};
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// XMP namespace for our own properties.
+const NAMESPACE: &str = "https://aidc.app/ns/provenance/1.0/";
+/// Hex digits of the prompt hash kept; enough to tell prompts apart.
+const HASH_LEN: usize = 16;
+
+pub struct SceneMark {
+    pub id: String,
+    pub label: String,
+    pub title: String,
+    pub prompt_hash: String,
+}
+
+pub struct Provenance {
+    pub project_id: String,
+    pub project: String,
+    pub scenes: Vec<SceneMark>,
+}
+
+pub fn prompt_hash(prompt: &str) -> String {
+    Sha256::digest(prompt.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()[..HASH_LEN]
+        .to_string()
+}
+
+fn mark(scene: &Scene) -> SceneMark {
+    SceneMark {
+        id: scene.id.clone(),
+        label: numbering::label(scene.scene_number, &scene.scene_suffix),
+        title: scene.title.clone(),
+        prompt_hash: prompt_hash(&scene.prompt),
+    }
+}
+
+/// A file showing some of a project's scenes.
+pub fn for_scenes<'a>(
+    conn: &Connection,
+    project_id: &str,
+    scenes: impl IntoIterator<Item = &'a Scene>,
+) -> AppResult<Provenance> {
+    let project = projects::get_project(conn, project_id)?;
+    Ok(Provenance {
+        project_id: project.id,
+        project: project.name,
+        scenes: scenes.into_iter().map(mark).collect(),
+    })
+}
+
+/// A file showing the whole project.
+pub fn for_project(conn: &Connection, project_id: &str) -> AppResult<Provenance> {
+    for_scenes(
+        conn,
+        project_id,
+        &scenes::list_for_project(conn, project_id)?,
+    )
+}
+
+/// A file showing one scene.
+pub fn for_scene(conn: &Connection, scene: &Scene) -> AppResult<Provenance> {
+    for_scenes(conn, &scene.project_id, [scene])
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Provenance {
+    /// A human-readable title: the scene's for a one-scene file, else the
+    /// project's.
+    pub fn title(&self) -> String {
+        match self.scenes.as_slice() {
+            [scene] => format!(
+                "{} \u{2013} Scene {}: {}",
+                self.project, scene.label, scene.title
+            ),
+            _ => self.project.clone(),
+        }
+    }
+
+    /// `-metadata` arguments for ffmpeg. The `aidc_*` keys need
+    /// `use_metadata_tags` in `-movflags` to be kept in an MP4.
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let mut tags = vec![
+            ("title", self.title()),
+            (
+                "comment",
+                format!(
+                    "AI Director's Chair project {}{}",
+                    self.project_id,
+                    self.scenes
+                        .iter()
+                        .map(|s| format!(", scene {} ({})", s.id, s.prompt_hash))
+                        .collect::<String>()
+                ),
+            ),
+            ("aidc_project_id", self.project_id.clone()),
+        ];
+        if let [scene] = self.scenes.as_slice() {
+            tags.push(("aidc_scene_id", scene.id.clone()));
+            tags.push(("aidc_prompt_hash", scene.prompt_hash.clone()));
+        }
+        tags.into_iter()
+            .flat_map(|(key, value)| ["-metadata".to_string(), format!("{}={}", key, value)])
+            .collect()
+    }
+
+    /// An XMP packet with the title and our own properties.
+    pub fn xmp(&self) -> String {
+        let scenes: String = self
+            .scenes
+            .iter()
+            .map(|s| {
+                format!(
+                    "<rdf:li rdf:parseType=\"Resource\"><aidc:sceneId>{}</aidc:sceneId>\
+                     <aidc:label>{}</aidc:label><aidc:promptHash>{}</aidc:promptHash></rdf:li>",
+                    s.id,
+                    xml_escape(&s.label),
+                    s.prompt_hash
+                )
+            })
+            .collect();
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+             <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+             xmlns:aidc=\"{ns}\">\
+             <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{title}</rdf:li></rdf:Alt></dc:title>\
+             <aidc:projectId>{project_id}</aidc:projectId>\
+             <aidc:scenes><rdf:Bag>{scenes}</rdf:Bag></aidc:scenes>\
+             </rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end=\"r\"?>",
+            ns = NAMESPACE,
+            title = xml_escape(&self.title()),
+            project_id = self.project_id,
+            scenes = scenes
+        )
+    }
+
+    /// Add the XMP packet to a PNG as an `iTXt` chunk, just before its end.
+    pub fn embed_png(&self, path: &Path) -> AppResult<()> {
+        let png = std::fs::read(path)?;
+        let Some(end) = png
+            .len()
+            .checked_sub(12)
+            .filter(|&i| &png[i + 4..i + 8] == b"IEND")
+        else {
+            // Not a PNG we wrote; leave it alone.
+            return Ok(());
+        };
+        // Keyword, then compression flag and method, language and
+        // translated keyword (both empty), then the text.
+        let mut data = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+        data.extend_from_slice(self.xmp().as_bytes());
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(b"iTXt");
+        hasher.update(&data);
+        chunk.extend_from_slice(b"iTXt");
+        chunk.extend_from_slice(&data);
+        chunk.extend_from_slice(&hasher.finalize().to_be_bytes());
+        let mut out = png[..end].to_vec();
+        out.extend_from_slice(&chunk);
+        out.extend_from_slice(&png[end..]);
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! Answers from provider calls that come out the same for the same
+//! inputs, kept in `provider_cache` for a while so opening a project or
+//! re-running a check doesn't send the same requests again: lookups such
+//! as a Notion database's schema or a Frame.io project's root folder, and
+//! LLM calls made with a fixed seed (see `llm::generate_json_seeded`).
+//!
+//! Entries are keyed by a hash of everything that shapes the answer,
+//! including which account asked where that matters, so no credential or
+//! prompt is stored in the key. Expired entries are dropped as new ones
+//! are written. Answers derived from a confidential project's content are
+//! never kept, since cloud backups carry this table (see `confidential`).
+//! Balances have their own cache; see `balances`.
+
+use crate::clock;
+use crate::confidential;
+use crate::db::Database;
+use crate::error::AppResult;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::time::Duration;
+use tauri::State;
+
+/// The cache key for a call to `provider` shaped by `parts`.
+pub fn key(provider: &str, parts: &[&str]) -> String {
+    let mut hash = Sha256::new();
+    hash.update(provider.as_bytes());
+    for part in parts {
+        hash.update([0]);
+        hash.update(part.as_bytes());
+    }
+    hash.finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn get(conn: &Connection, key: &str) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM provider_cache WHERE key = ?1 AND expires_at > ?2",
+            params![key, clock::now()],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+fn put(conn: &Connection, provider: &str, key: &str, value: &str, ttl: Duration) -> AppResult<()> {
+    let now = clock::now();
+    let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::days(365));
+    conn.execute("DELETE FROM provider_cache WHERE expires_at <= ?1", [&now])?;
+    conn.execute(
+        "INSERT INTO provider_cache (key, provider, value, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value,
+            created_at = excluded.created_at, expires_at = excluded.expires_at",
+        params![key, provider, value, now, clock::format(Utc::now() + ttl)],
+    )?;
+    Ok(())
+}
+
+/// Drop one entry, e.g. when its answer turned out to be stale.
+pub fn forget(conn: &Connection, key: &str) -> AppResult<()> {
+    conn.execute("DELETE FROM provider_cache WHERE key = ?1", [key])?;
+    Ok(())
+}
+
+/// The cached answer under `key`, or `fetch`'s, kept for `ttl`. The
+/// database is only locked to read and write the entry, not during the
+/// call. Errors aren't cached.
+pub async fn get_or_fetch<T, F, Fut>(
+    db: &Database,
+    provider: &str,
+    key: &str,
+    ttl: Duration,
+    fetch: F,
+) -> AppResult<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = AppResult<T>>,
+{
+    let hit = get(&db.conn(), key)?;
+    if let Some(value) = hit.and_then(|v| serde_json::from_str(&v).ok()) {
+        tracing::debug!(provider, "provider cache hit");
+        return Ok(value);
+    }
+    let value = fetch().await?;
+    put(
+        &db.conn(),
+        provider,
+        key,
+        &serde_json::to_string(&value)?,
+        ttl,
+    )?;
+    Ok(value)
+}
+
+/// `get_or_fetch` for a call made with `project_id`'s content, which
+/// skips the cache altogether for confidential projects.
+pub async fn get_or_fetch_for_project<T, F, Fut>(
+    db: &Database,
+    project_id: &str,
+    provider: &str,
+    key: &str,
+    ttl: Duration,
+    fetch: F,
+) -> AppResult<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = AppResult<T>>,
+{
+    let confidential = confidential::is_confidential(&db.conn(), project_id)?;
+    if confidential {
+        return fetch().await;
+    }
+    get_or_fetch(db, provider, key, ttl, fetch).await
+}
+
+/// Empty the cache, or just one provider's entries. Returns how many were
+/// removed.
+pub fn clear(conn: &Connection, provider: Option<&str>) -> AppResult<usize> {
+    Ok(match provider {
+        Some(provider) => {
+            conn.execute("DELETE FROM provider_cache WHERE provider = ?1", [provider])?
+        }
+        None => conn.execute("DELETE FROM provider_cache", [])?,
+    })
+}
+
+#[tauri::command]
+pub fn clear_provider_cache(db: State<'_, Database>, provider: Option<String>) -> AppResult<usize> {
+    clear(&db.conn(), provider.as_deref())
+}
@@ -0,0 +1,512 @@
+use crate::error::{AppError, AppResult};
+use crate::job_logs::Exchange;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+use std::time::Duration;
+use ts_rs::TS;
+
+pub const FAL_QUEUE_HOST: &str = "queue.fal.run";
+const FAL_BASE: &str = "https://queue.fal.run";
+const FAL_REST_BASE: &str = "https://rest.alpha.fal.ai";
+const DEFAULT_NEGATIVE: &str = "blurry, low quality, distorted anatomy, watermark, text";
+
+/// Services models are generated through: every real model runs on
+/// Fal.ai, and `mock` runs locally.
+pub const PROVIDERS: &[&str] = &["fal", "mock"];
+
+/// Settings key holding the Fal.ai API key.
+pub const FAL_KEY_SETTING: &str = "api_key.fal";
+
+/// What the frontend asks us to generate. Mirrors `VideoGenerationParams`
+/// on the TypeScript side; style/camera enhancement has already been applied
+/// to `prompt` by the time it gets here.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct GenerationRequest {
+    pub prompt: String,
+    #[serde(default)]
+    pub negative_prompt: Option<String>,
+    #[serde(default)]
+    pub image_url: Option<String>,
+    pub duration: u32,
+    #[serde(default = "default_aspect_ratio")]
+    pub aspect_ratio: String,
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub seed: Option<u64>,
+    /// One of the model's `resolutions`; its default when None.
+    #[serde(default)]
+    pub resolution: Option<String>,
+    /// 0–1, for models with a `motion_param`.
+    #[serde(default)]
+    pub motion_strength: Option<f64>,
+}
+
+fn default_aspect_ratio() -> String {
+    "16:9".into()
+}
+
+/// Static per-model facts needed to build a request body.
+pub struct ModelSpec {
+    pub key: &'static str,
+    pub text_endpoint: &'static str,
+    pub image_endpoint: &'static str,
+    pub max_duration: u32,
+    pub supports_negative_prompt: bool,
+    /// Output resolutions the model offers; empty when it has just one.
+    pub resolutions: &'static [&'static str],
+    /// Request field taking a 0–1 motion strength, for models that have one.
+    pub motion_param: Option<&'static str>,
+    /// Rough list price in USD per second of output, for estimates only.
+    pub usd_per_second: f64,
+}
+
+pub const MODELS: &[ModelSpec] = &[
+    ModelSpec {
+        key: "kling",
+        text_endpoint: "fal-ai/kling-video/v1.6/pro/text-to-video",
+        image_endpoint: "fal-ai/kling-video/v1.6/pro/text-to-video",
+        max_duration: 10,
+        supports_negative_prompt: true,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.095,
+    },
+    ModelSpec {
+        key: "kling-o1",
+        text_endpoint: "fal-ai/kling-video/v2.1/master/text-to-video",
+        image_endpoint: "fal-ai/kling-video/v2.1/master/text-to-video",
+        max_duration: 10,
+        supports_negative_prompt: true,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.28,
+    },
+    ModelSpec {
+        key: "minimax",
+        text_endpoint: "fal-ai/minimax-video",
+        image_endpoint: "fal-ai/minimax-video",
+        max_duration: 6,
+        supports_negative_prompt: false,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.08,
+    },
+    ModelSpec {
+        key: "wan",
+        text_endpoint: "fal-ai/wan/v2.1/text-to-video",
+        image_endpoint: "fal-ai/wan/v2.1/image-to-video",
+        max_duration: 10,
+        supports_negative_prompt: false,
+        resolutions: &["480p", "720p"],
+        motion_param: None,
+        usd_per_second: 0.08,
+    },
+    ModelSpec {
+        key: "omni-human",
+        text_endpoint: "fal-ai/omnihuman-v1",
+        image_endpoint: "fal-ai/omnihuman-v1",
+        max_duration: 10,
+        supports_negative_prompt: false,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.14,
+    },
+    ModelSpec {
+        key: "veo2",
+        text_endpoint: "fal-ai/veo2",
+        image_endpoint: "fal-ai/veo2/image-to-video",
+        max_duration: 8,
+        supports_negative_prompt: false,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.5,
+    },
+    ModelSpec {
+        key: "ltx",
+        text_endpoint: "fal-ai/ltx-video",
+        image_endpoint: "fal-ai/ltx-video/image-to-video",
+        max_duration: 5,
+        supports_negative_prompt: true,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.02,
+    },
+    ModelSpec {
+        key: "pixverse",
+        text_endpoint: "fal-ai/pixverse/v3.5/text-to-video",
+        image_endpoint: "fal-ai/pixverse/v3.5/image-to-video",
+        max_duration: 8,
+        supports_negative_prompt: true,
+        resolutions: &["360p", "540p", "720p", "1080p"],
+        motion_param: None,
+        usd_per_second: 0.1,
+    },
+    ModelSpec {
+        key: "runway",
+        text_endpoint: "fal-ai/runway-gen3/turbo/text-to-video",
+        image_endpoint: "fal-ai/runway-gen3/turbo/image-to-video",
+        max_duration: 10,
+        supports_negative_prompt: false,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.05,
+    },
+    // Handled by `mock`; the endpoints only name the request kind.
+    ModelSpec {
+        key: "mock",
+        text_endpoint: "mock/text-to-video",
+        image_endpoint: "mock/image-to-video",
+        max_duration: 10,
+        supports_negative_prompt: true,
+        resolutions: &[],
+        motion_param: None,
+        usd_per_second: 0.0,
+    },
+];
+
+impl ModelSpec {
+    /// Whether the model can start from an image; models without an
+    /// image-to-video endpoint ignore one.
+    pub fn supports_image(&self) -> bool {
+        self.image_endpoint != self.text_endpoint
+    }
+
+    /// Roughly what a clip of `duration` seconds costs, in USD.
+    pub fn estimate_cost(&self, duration: u32) -> f64 {
+        f64::from(duration.clamp(1, self.max_duration)) * self.usd_per_second
+    }
+}
+
+/// Longest clip any model can make.
+pub fn max_duration() -> u32 {
+    MODELS.iter().map(|m| m.max_duration).max().unwrap_or(1)
+}
+
+pub fn model(key: &str) -> AppResult<&'static ModelSpec> {
+    MODELS
+        .iter()
+        .find(|m| m.key == key)
+        .ok_or_else(|| AppError::Invalid(format!("unknown provider '{}'", key)))
+}
+
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .expect("failed to build HTTP client")
+    })
+}
+
+/// Longest prompt we send; some models silently truncate, others reject.
+const MAX_PROMPT_CHARS: usize = 1500;
+
+/// Provider-specific prompt shaping, applied after style enhancement.
+pub fn format_prompt(spec: &ModelSpec, prompt: &str) -> String {
+    let mut out = match spec.key {
+        // MiniMax does better with concise prompts and explicit motion cues
+        "minimax" => {
+            let stripped = ["cinematic", "high quality", "8k", "detailed"]
+                .iter()
+                .fold(prompt.to_string(), |acc, word| {
+                    replace_ignore_case(&acc, word, "")
+                });
+            format!(
+                "{}, high motion, smooth camera",
+                stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+            )
+        }
+        "wan" if !prompt.to_lowercase().contains("motion") => format!(
+            "{}, dynamic motion, fluid movement, physics-based animation",
+            prompt
+        ),
+        _ => prompt.to_string(),
+    };
+    if out.chars().count() > MAX_PROMPT_CHARS {
+        let cut: String = out.chars().take(MAX_PROMPT_CHARS).collect();
+        // Prefer ending on a sentence or clause rather than mid-word
+        let end = cut.rfind(['.', ',']).unwrap_or(cut.len());
+        out = cut[..end].to_string();
+    }
+    out
+}
+
+fn replace_ignore_case(haystack: &str, needle: &str, with: &str) -> String {
+    // ASCII-only folding keeps byte offsets identical between the two strings
+    let lower = haystack.to_ascii_lowercase();
+    let mut out = String::with_capacity(haystack.len());
+    let mut last = 0;
+    for (idx, _) in lower.match_indices(&needle.to_ascii_lowercase()) {
+        out.push_str(&haystack[last..idx]);
+        out.push_str(with);
+        last = idx + needle.len();
+    }
+    out.push_str(&haystack[last..]);
+    out
+}
+
+fn request_body(spec: &ModelSpec, req: &GenerationRequest) -> Value {
+    let duration = req.duration.clamp(1, spec.max_duration);
+    let mut body = json!({
+        "prompt": req.prompt,
+        "aspect_ratio": req.aspect_ratio,
+    });
+    if spec.key == "ltx" {
+        // LTX takes frames at 24fps rather than seconds
+        body["num_frames"] = json!(duration * 24);
+    } else if spec.key.starts_with("kling") {
+        body["duration"] = json!(duration.to_string());
+    } else {
+        body["duration"] = json!(duration);
+    }
+    if spec.supports_negative_prompt {
+        body["negative_prompt"] = json!(req.negative_prompt.as_deref().unwrap_or(DEFAULT_NEGATIVE));
+    }
+    if let Some(url) = &req.image_url {
+        body["image_url"] = json!(url);
+    }
+    if let Some(seed) = req.seed {
+        body["seed"] = json!(seed);
+    }
+    if let Some(resolution) = req
+        .resolution
+        .as_deref()
+        .filter(|r| spec.resolutions.contains(r))
+    {
+        body["resolution"] = json!(resolution);
+    }
+    if let (Some(param), Some(strength)) = (spec.motion_param, req.motion_strength) {
+        body[param] = json!(strength.clamp(0.0, 1.0));
+    }
+    body
+}
+
+/// Submit a generation to the Fal.ai queue and return its request id. The
+/// HTTP exchange is appended to `log`.
+pub async fn submit(
+    api_key: &str,
+    provider: &str,
+    req: &GenerationRequest,
+    log: &mut Vec<Exchange>,
+) -> AppResult<String> {
+    let spec = model(provider)?;
+    let endpoint = if req.image_url.is_some() {
+        spec.image_endpoint
+    } else {
+        spec.text_endpoint
+    };
+    submit_fal(api_key, provider, endpoint, &request_body(spec, req), log).await
+}
+
+/// Send a request, noting it and the response in `log`. Returns the status
+/// and body text.
+async fn send_logged(
+    request: reqwest::RequestBuilder,
+    mut exchange: Exchange,
+    log: &mut Vec<Exchange>,
+) -> AppResult<(reqwest::StatusCode, String)> {
+    let res = match request.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            exchange.error = e.to_string();
+            log.push(exchange);
+            return Err(e.into());
+        }
+    };
+    let status = res.status();
+    exchange.status = Some(status.as_u16());
+    let text = match res.text().await {
+        Ok(text) => text,
+        Err(e) => {
+            exchange.error = e.to_string();
+            log.push(exchange);
+            return Err(e.into());
+        }
+    };
+    exchange.response = text.clone();
+    log.push(exchange);
+    Ok((status, text))
+}
+
+/// POST a request body to a Fal.ai queue endpoint and return its request id.
+/// `label` names the provider in error messages.
+pub async fn submit_fal(
+    api_key: &str,
+    label: &str,
+    endpoint: &str,
+    body: &Value,
+    log: &mut Vec<Exchange>,
+) -> AppResult<String> {
+    if api_key.is_empty() {
+        return Err(AppError::Invalid("Fal.ai API key not configured".into()));
+    }
+
+    let url = format!("{}/{}", FAL_BASE, endpoint);
+    let request = client()
+        .post(&url)
+        .header("Authorization", format!("Key {}", api_key))
+        .json(body);
+    let (status, text) = send_logged(request, Exchange::new("POST", &url, Some(body)), log).await?;
+
+    if !status.is_success() {
+        return Err(AppError::Provider(format!(
+            "{} ({}): {}",
+            label, status, text
+        )));
+    }
+
+    let data: Value = serde_json::from_str(&text)?;
+    data["request_id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Provider(format!("{} returned no request_id", label)))
+}
+
+/// The account's remaining Fal.ai credit, in USD.
+pub async fn fal_balance(api_key: &str) -> AppResult<f64> {
+    if api_key.is_empty() {
+        return Err(AppError::Invalid("Fal.ai API key not configured".into()));
+    }
+    let res = client()
+        .get(format!("{}/billing/user_balance", FAL_REST_BASE))
+        .header("Authorization", format!("Key {}", api_key))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "balance ({}): {}",
+            status, text
+        )));
+    }
+    let data: Value = res.json().await?;
+    data.as_f64()
+        .or_else(|| data["balance"].as_f64())
+        .ok_or_else(|| AppError::Provider("Fal.ai returned no balance".into()))
+}
+
+/// Why a provider turned a job down, stored in `video_jobs.failure_kind`.
+pub mod failure {
+    /// The prompt or image tripped the provider's safety filter.
+    pub const CONTENT_POLICY: &str = "content_policy";
+    /// Out of credits or rate limited.
+    pub const QUOTA: &str = "quota";
+    /// Timeouts and server errors that may go away on their own.
+    pub const TRANSIENT: &str = "transient";
+    /// The request itself was rejected, e.g. an unsupported duration.
+    pub const INVALID_PARAMS: &str = "invalid_params";
+    pub const UNKNOWN: &str = "unknown";
+}
+
+/// Sort a provider failure into a `failure` kind from the HTTP status of
+/// the last response, if there was one, and the error text.
+pub fn classify_failure(status: Option<u16>, message: &str) -> &'static str {
+    let text = message.to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|w| text.contains(w));
+    if mentions(&[
+        "content policy",
+        "content_policy",
+        "safety",
+        "nsfw",
+        "moderation",
+        "flagged",
+        "violat",
+    ]) {
+        return failure::CONTENT_POLICY;
+    }
+    if matches!(status, Some(402 | 429))
+        || mentions(&[
+            "quota",
+            "rate limit",
+            "too many requests",
+            "insufficient",
+            "balance",
+            "credits",
+            "exhausted",
+        ])
+    {
+        return failure::QUOTA;
+    }
+    match status {
+        Some(400 | 404 | 413 | 422) => failure::INVALID_PARAMS,
+        Some(408 | 425) | Some(500..=599) => failure::TRANSIENT,
+        _ if mentions(&[
+            "timed out",
+            "timeout",
+            "temporarily",
+            "unavailable",
+            "try again",
+        ]) =>
+        {
+            failure::TRANSIENT
+        }
+        _ if mentions(&["invalid", "validation", "must be", "unsupported"]) => {
+            failure::INVALID_PARAMS
+        }
+        _ => failure::UNKNOWN,
+    }
+}
+
+/// Where a queued Fal.ai request has got to.
+pub enum QueueState {
+    Waiting,
+    Completed { video_url: String },
+    Failed(String),
+}
+
+/// Fal.ai addresses queued requests by app id (`owner/app`) without the
+/// model sub-path, so text and image endpoints of one model share it.
+fn fal_app_id(endpoint: &str) -> String {
+    endpoint.split('/').take(2).collect::<Vec<_>>().join("/")
+}
+
+/// Check a queued request and fetch its output once it has finished. The
+/// HTTP exchanges are appended to `log`.
+pub async fn poll_fal(
+    api_key: &str,
+    endpoint: &str,
+    request_id: &str,
+    log: &mut Vec<Exchange>,
+) -> AppResult<QueueState> {
+    let base = format!(
+        "{}/{}/requests/{}",
+        FAL_BASE,
+        fal_app_id(endpoint),
+        request_id
+    );
+    let auth = format!("Key {}", api_key);
+
+    let url = format!("{}/status", base);
+    let request = client().get(&url).header("Authorization", &auth);
+    let (status, text) = send_logged(request, Exchange::new("GET", &url, None), log).await?;
+    if !status.is_success() {
+        return Err(AppError::Provider(format!("status ({}): {}", status, text)));
+    }
+    let status: Value = serde_json::from_str(&text)?;
+    if status["status"].as_str() != Some("COMPLETED") {
+        return Ok(QueueState::Waiting);
+    }
+
+    let request = client().get(&base).header("Authorization", &auth);
+    let (status, text) = send_logged(request, Exchange::new("GET", &base, None), log).await?;
+    if !status.is_success() {
+        return Ok(QueueState::Failed(text));
+    }
+    let data: Value = serde_json::from_str(&text)?;
+    match data["video"]["url"].as_str() {
+        Some(url) => Ok(QueueState::Completed {
+            video_url: url.to_string(),
+        }),
+        None => Ok(QueueState::Failed(
+            data["detail"]
+                .as_str()
+                .unwrap_or("no video in provider response")
+                .to_string(),
+        )),
+    }
+}
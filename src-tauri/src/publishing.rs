@@ -0,0 +1,547 @@
+//! Publishing finished renders to YouTube or Vimeo. Renders are assembled
+//! in the frontend, which reports each with `notify_render_finished`; that
+//! records it here so it can be published later. Accounts are connected
+//! with OAuth (see `oauth`).
+//!
+//! The title and description are templates filled from the project:
+//! `{{project}}`, `{{synopsis}}`, `{{genre}}`, `{{scenes}}` and `{{date}}`.
+//! The published URL is stored with the render.
+
+use crate::activity;
+use crate::clock;
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::{AppEvent, SignInFailed};
+use crate::oauth::{self, OAuthClient};
+use crate::projects::{self, ProjectSummary};
+use crate::providers;
+use crate::scenes::{self, Scene};
+use crate::settings;
+use crate::validation::{Validator, MAX_NAME_LEN};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Uploads can be large; the shared client's timeout is meant for API calls.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_TITLE: &str = "{{project}}";
+const DEFAULT_DESCRIPTION: &str = "{{synopsis}}";
+const YOUTUBE_TITLE_LIMIT: usize = 100;
+const YOUTUBE_DESCRIPTION_LIMIT: usize = 5000;
+const VIMEO_ACCEPT: &str = "application/vnd.vimeo.*+json;version=3.4";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum PublishTarget {
+    Youtube,
+    Vimeo,
+}
+
+impl PublishTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PublishTarget::Youtube => "youtube",
+            PublishTarget::Vimeo => "vimeo",
+        }
+    }
+
+    /// OAuth client id, from settings (`publish.<target>.client_id`) or
+    /// baked in at build time.
+    fn client_id(self, conn: &Connection) -> AppResult<String> {
+        let built_in = match self {
+            PublishTarget::Youtube => option_env!("AIDC_GOOGLE_CLIENT_ID"),
+            PublishTarget::Vimeo => option_env!("AIDC_VIMEO_CLIENT_ID"),
+        };
+        settings::get(conn, &format!("publish.{}.client_id", self.as_str()))?
+            .filter(|v| !v.is_empty())
+            .or(built_in.map(str::to_string))
+            .ok_or_else(|| {
+                AppError::Invalid(format!("no OAuth client configured for {}", self.as_str()))
+            })
+    }
+
+    /// How to sign in to the target's account.
+    pub fn oauth_client(self, conn: &Connection) -> AppResult<OAuthClient> {
+        let (auth_url, token_url, client_secret, extra_auth_params): (
+            _,
+            _,
+            _,
+            &'static [(&'static str, &'static str)],
+        ) = match self {
+            PublishTarget::Youtube => (
+                "https://accounts.google.com/o/oauth2/v2/auth",
+                "https://oauth2.googleapis.com/token",
+                option_env!("AIDC_GOOGLE_CLIENT_SECRET"),
+                &[
+                    ("scope", "https://www.googleapis.com/auth/youtube.upload"),
+                    ("access_type", "offline"),
+                    ("prompt", "consent"),
+                ],
+            ),
+            PublishTarget::Vimeo => (
+                "https://api.vimeo.com/oauth/authorize",
+                "https://api.vimeo.com/oauth/access_token",
+                option_env!("AIDC_VIMEO_CLIENT_SECRET"),
+                &[("scope", "public private upload")],
+            ),
+        };
+        Ok(OAuthClient {
+            account: format!("publish.{}", self.as_str()),
+            auth_url,
+            token_url,
+            client_id: self.client_id(conn)?,
+            client_secret,
+            extra_auth_params,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Publication {
+    pub target: PublishTarget,
+    pub url: String,
+    pub published_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Render {
+    pub id: String,
+    pub project_id: String,
+    pub path: String,
+    pub created_at: String,
+    pub publications: Vec<Publication>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PublishMetadata {
+    /// Template; defaults to the project name.
+    #[serde(default)]
+    pub title: String,
+    /// Template; defaults to the synopsis.
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `private` (the default), `unlisted` or `public`.
+    #[serde(default)]
+    pub privacy: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct PublishAccount {
+    pub target: PublishTarget,
+    /// An OAuth client is configured for this target.
+    pub available: bool,
+    pub connected: bool,
+}
+
+const PRIVACY: &[&str] = &["private", "unlisted", "public"];
+
+fn row_to_render(row: &Row) -> rusqlite::Result<Render> {
+    Ok(Render {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        path: row.get(2)?,
+        created_at: row.get(3)?,
+        publications: Vec::new(),
+    })
+}
+
+fn with_publications(conn: &Connection, mut render: Render) -> AppResult<Render> {
+    let mut stmt = conn.prepare(
+        "SELECT target, url, published_at FROM render_publications
+         WHERE render_id = ?1 ORDER BY published_at",
+    )?;
+    render.publications = stmt
+        .query_map([&render.id], |r| {
+            let target: String = r.get(0)?;
+            Ok(Publication {
+                target: if target == "vimeo" {
+                    PublishTarget::Vimeo
+                } else {
+                    PublishTarget::Youtube
+                },
+                url: r.get(1)?,
+                published_at: r.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(render)
+}
+
+pub fn get_render(conn: &Connection, id: &str) -> AppResult<Render> {
+    let render = conn
+        .query_row(
+            "SELECT id, project_id, path, created_at FROM renders WHERE id = ?1",
+            [id],
+            row_to_render,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(format!("render {}", id)),
+            e => e.into(),
+        })?;
+    with_publications(conn, render)
+}
+
+/// Remember a finished render.
+pub fn record_render(conn: &Connection, project_id: &str, path: &str) -> AppResult<Render> {
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO renders (id, project_id, path, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![id, project_id, path, clock::now()],
+    )?;
+    get_render(conn, &id)
+}
+
+/// The project's scenes with the time each starts at in a render, in
+/// seconds. Renders hold the scenes in order at their planned durations,
+/// which is how the frontend assembles them.
+pub fn scene_timeline(conn: &Connection, project_id: &str) -> AppResult<Vec<(f64, Scene)>> {
+    let mut start = 0.0;
+    Ok(scenes::list_for_project(conn, project_id)?
+        .into_iter()
+        .map(|scene| {
+            let at = start;
+            start += scene.duration as f64;
+            (at, scene)
+        })
+        .collect())
+}
+
+/// Fill `{{name}}` placeholders from the project.
+pub fn fill(template: &str, project: &ProjectSummary) -> String {
+    let fields = [
+        ("project", project.name.clone()),
+        ("synopsis", project.synopsis.clone()),
+        ("genre", project.genre.clone()),
+        ("scenes", project.scene_count.to_string()),
+        ("date", clock::now().chars().take(10).collect()),
+    ];
+    fields
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{{{}}}}}", key), value)
+        })
+        .trim()
+        .to_string()
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    text.chars().take(limit).collect()
+}
+
+async fn check(res: reqwest::Response, what: &str) -> AppResult<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    Err(AppError::Provider(format!(
+        "{} ({}): {}",
+        what, status, body
+    )))
+}
+
+/// Resumable upload: the metadata first, then the file to the session URL.
+/// Returns the video id and watch URL.
+async fn upload_youtube(
+    token: &str,
+    bytes: Vec<u8>,
+    title: &str,
+    description: &str,
+    metadata: &PublishMetadata,
+    privacy: &str,
+) -> AppResult<(String, String)> {
+    // YouTube rejects angle brackets in titles and descriptions.
+    let clean = |s: &str| s.replace(['<', '>'], "");
+    let res = providers::client()
+        .post("https://www.googleapis.com/upload/youtube/v3/videos")
+        .bearer_auth(token)
+        .query(&[("uploadType", "resumable"), ("part", "snippet,status")])
+        .header("X-Upload-Content-Type", "video/*")
+        .header("X-Upload-Content-Length", bytes.len())
+        .json(&json!({
+            "snippet": {
+                "title": truncate(&clean(title), YOUTUBE_TITLE_LIMIT),
+                "description": truncate(&clean(description), YOUTUBE_DESCRIPTION_LIMIT),
+                "tags": metadata.tags,
+            },
+            "status": { "privacyStatus": privacy },
+        }))
+        .send()
+        .await?;
+    let res = check(res, "YouTube upload").await?;
+    let session = res
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| AppError::Provider("YouTube returned no upload URL".into()))?
+        .to_string();
+    let res = providers::client()
+        .put(session)
+        .timeout(UPLOAD_TIMEOUT)
+        .bearer_auth(token)
+        .header("Content-Type", "video/*")
+        .body(bytes)
+        .send()
+        .await?;
+    let body: Value = check(res, "YouTube upload").await?.json().await?;
+    let id = body["id"]
+        .as_str()
+        .ok_or_else(|| AppError::Provider("YouTube returned no video id".into()))?;
+    Ok((id.to_string(), format!("https://youtu.be/{}", id)))
+}
+
+/// tus upload: create the video with its metadata, then send the file.
+/// Returns the video URI and page URL.
+async fn upload_vimeo(
+    token: &str,
+    bytes: Vec<u8>,
+    title: &str,
+    description: &str,
+    privacy: &str,
+) -> AppResult<(String, String)> {
+    let view = match privacy {
+        "public" => "anybody",
+        "unlisted" => "unlisted",
+        _ => "nobody",
+    };
+    let res = providers::client()
+        .post("https://api.vimeo.com/me/videos")
+        .bearer_auth(token)
+        .header("Accept", VIMEO_ACCEPT)
+        .json(&json!({
+            "upload": { "approach": "tus", "size": bytes.len() },
+            "name": title,
+            "description": description,
+            "privacy": { "view": view },
+        }))
+        .send()
+        .await?;
+    let body: Value = check(res, "Vimeo upload").await?.json().await?;
+    let (Some(upload_link), Some(uri), Some(link)) = (
+        body["upload"]["upload_link"].as_str(),
+        body["uri"].as_str(),
+        body["link"].as_str(),
+    ) else {
+        return Err(AppError::Provider(
+            "Vimeo returned an incomplete upload".into(),
+        ));
+    };
+    let res = providers::client()
+        .patch(upload_link)
+        .timeout(UPLOAD_TIMEOUT)
+        .header("Tus-Resumable", "1.0.0")
+        .header("Upload-Offset", "0")
+        .header("Content-Type", "application/offset+octet-stream")
+        .body(bytes)
+        .send()
+        .await?;
+    check(res, "Vimeo upload").await?;
+    Ok((uri.to_string(), link.to_string()))
+}
+
+/// Start connecting a YouTube or Vimeo account. Returns the sign-in URL;
+/// completion is reported with `publish-account-connected` or
+/// `publish-account-failed`.
+#[tauri::command]
+pub async fn connect_publish_account(app: AppHandle, target: PublishTarget) -> AppResult<String> {
+    let client = target.oauth_client(&app.state::<Database>().conn())?;
+    oauth::connect(&app, client, move |error| match error {
+        None => AppEvent::PublishAccountConnected(target),
+        Some(error) => AppEvent::PublishAccountFailed(SignInFailed {
+            provider: target,
+            error,
+        }),
+    })
+    .await
+}
+
+pub fn accounts(conn: &Connection) -> AppResult<Vec<PublishAccount>> {
+    [PublishTarget::Youtube, PublishTarget::Vimeo]
+        .into_iter()
+        .map(|target| {
+            Ok(PublishAccount {
+                target,
+                available: target.client_id(conn).is_ok(),
+                connected: oauth::is_connected(&format!("publish.{}", target.as_str()))?,
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_publish_accounts(db: State<'_, Database>) -> AppResult<Vec<PublishAccount>> {
+    accounts(&db.conn())
+}
+
+#[tauri::command]
+pub fn disconnect_publish_account(target: PublishTarget) -> AppResult<()> {
+    oauth::disconnect(&format!("publish.{}", target.as_str()))
+}
+
+/// The project's renders, newest first, with where each was published.
+pub fn renders(conn: &Connection, project_id: &str) -> AppResult<Vec<Render>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, path, created_at FROM renders
+         WHERE project_id = ?1 ORDER BY created_at DESC, rowid DESC",
+    )?;
+    let renders = stmt
+        .query_map([project_id], row_to_render)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    renders
+        .into_iter()
+        .map(|r| with_publications(conn, r))
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_renders(db: State<'_, Database>, project_id: String) -> AppResult<Vec<Render>> {
+    renders(&db.conn(), &project_id)
+}
+
+/// A render checked and ready to upload, from `prepare_upload`.
+pub struct Upload {
+    render: Render,
+    client: OAuthClient,
+    title: String,
+    description: String,
+    privacy: String,
+}
+
+/// Check `metadata`, fill its title and description templates from the
+/// render's project, and make sure the render file is still there.
+pub fn prepare_upload(
+    conn: &Connection,
+    render_id: &str,
+    target: PublishTarget,
+    metadata: &PublishMetadata,
+) -> AppResult<Upload> {
+    let privacy = if metadata.privacy.is_empty() {
+        "private"
+    } else {
+        metadata.privacy.as_str()
+    };
+    let mut v = Validator::new();
+    v.uuid("renderId", render_id)
+        .one_of("privacy", privacy, PRIVACY);
+    for tag in &metadata.tags {
+        v.max_len("tags", tag, MAX_NAME_LEN);
+    }
+    v.finish()?;
+    let render = get_render(conn, render_id)?;
+    let project = projects::get_project(conn, &render.project_id)?;
+    confidential::ensure_remote_allowed(conn, &project.id, target.as_str())?;
+    let client = target.oauth_client(conn)?;
+    let template =
+        |t: &str, default: &str| fill(if t.trim().is_empty() { default } else { t }, &project);
+    let title = template(&metadata.title, DEFAULT_TITLE);
+    let title = if title.is_empty() {
+        project.name.clone()
+    } else {
+        title
+    };
+    let description = template(&metadata.description, DEFAULT_DESCRIPTION);
+    if !Path::new(&render.path).is_file() {
+        return Err(AppError::NotFound(format!("render file {}", render.path)));
+    }
+    Ok(Upload {
+        render,
+        client,
+        title,
+        description,
+        privacy: privacy.to_string(),
+    })
+}
+
+/// Store where a render was published. Publishing the same render to the
+/// same target again replaces the stored URL.
+pub fn record_publication(
+    conn: &Connection,
+    render_id: &str,
+    target: PublishTarget,
+    video_id: &str,
+    url: &str,
+    title: &str,
+) -> AppResult<Render> {
+    let render = get_render(conn, render_id)?;
+    conn.execute(
+        "INSERT INTO render_publications (render_id, target, video_id, url, published_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(render_id, target) DO UPDATE SET video_id = excluded.video_id,
+            url = excluded.url, published_at = excluded.published_at",
+        params![render_id, target.as_str(), video_id, url, clock::now()],
+    )?;
+    activity::record(
+        conn,
+        &render.project_id,
+        "render",
+        render_id,
+        "published",
+        &format!("Published \"{}\" to {}: {}", title, target.as_str(), url),
+        &[],
+    )?;
+    get_render(conn, render_id)
+}
+
+/// Upload a render to YouTube or Vimeo and store the video's URL with it.
+#[tauri::command]
+pub async fn publish_render(
+    app: AppHandle,
+    render_id: String,
+    target: PublishTarget,
+    metadata: PublishMetadata,
+) -> AppResult<Render> {
+    let upload = prepare_upload(
+        &app.state::<Database>().conn(),
+        &render_id,
+        target,
+        &metadata,
+    )?;
+    let token = oauth::access_token(&upload.client).await?;
+    let bytes = tokio::fs::read(&upload.render.path).await?;
+    let (video_id, url) = match target {
+        PublishTarget::Youtube => {
+            upload_youtube(
+                &token,
+                bytes,
+                &upload.title,
+                &upload.description,
+                &metadata,
+                &upload.privacy,
+            )
+            .await?
+        }
+        PublishTarget::Vimeo => {
+            upload_vimeo(
+                &token,
+                bytes,
+                &upload.title,
+                &upload.description,
+                &upload.privacy,
+            )
+            .await?
+        }
+    };
+    record_publication(
+        &app.state::<Database>().conn(),
+        &render_id,
+        target,
+        &video_id,
+        &url,
+        &upload.title,
+    )
+}
@@ -0,0 +1,146 @@
+//! A global shortcut that pops up a small always-on-top window for jotting
+//! down an idea from anywhere, even with the app in the background. The
+//! window saves the note with `inbox::capture_idea` and hides itself; the
+//! shortcut is a setting and can be changed or turned off.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::settings;
+use tauri::{App, AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+/// The shortcut as an accelerator string, e.g. `CmdOrCtrl+Shift+I`. Empty
+/// turns quick capture off.
+pub const SHORTCUT_SETTING: &str = "shortcuts.quick_capture";
+pub const DEFAULT_SHORTCUT: &str = "CmdOrCtrl+Shift+Space";
+const WINDOW_LABEL: &str = "quick-capture";
+
+/// The saved shortcut, or the default if none was ever set.
+pub fn shortcut(conn: &rusqlite::Connection) -> AppResult<String> {
+    Ok(settings::get(conn, SHORTCUT_SETTING)?.unwrap_or_else(|| DEFAULT_SHORTCUT.to_string()))
+}
+
+fn parse(accelerator: &str) -> AppResult<Shortcut> {
+    accelerator
+        .parse()
+        .map_err(|e| AppError::Invalid(format!("not a shortcut: {}: {}", accelerator, e)))
+}
+
+/// Show the capture window, creating it the first time.
+fn show(app: &AppHandle) -> AppResult<()> {
+    let window = match app.get_webview_window(WINDOW_LABEL) {
+        Some(window) => window,
+        None => WebviewWindowBuilder::new(
+            app,
+            WINDOW_LABEL,
+            WebviewUrl::App("index.html?capture=1".into()),
+        )
+        .title("Capture an idea")
+        .inner_size(520.0, 180.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .build()
+        .map_err(|e| AppError::Invalid(format!("could not open window: {}", e)))?,
+    };
+    window
+        .show()
+        .and_then(|_| window.set_focus())
+        .map_err(|e| AppError::Invalid(e.to_string()))
+}
+
+/// The global shortcut plugin's handler. Quick capture is the only
+/// shortcut the app registers.
+pub fn on_shortcut(app: &AppHandle, _shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() == ShortcutState::Pressed {
+        if let Err(e) = show(app) {
+            tracing::warn!(error = %e, "could not show quick capture");
+        }
+    }
+}
+
+/// Register the saved shortcut. Another app may already own it, which is
+/// logged rather than stopping startup.
+pub fn setup(app: &App) {
+    let accelerator = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        shortcut(&conn).unwrap_or_else(|_| DEFAULT_SHORTCUT.to_string())
+    };
+    if accelerator.is_empty() {
+        return;
+    }
+    if let Err(e) = parse(&accelerator).and_then(|s| {
+        app.global_shortcut()
+            .register(s)
+            .map_err(|e| AppError::Invalid(e.to_string()))
+    }) {
+        tracing::warn!(shortcut = %accelerator, error = %e, "could not register quick capture shortcut");
+    }
+}
+
+#[tauri::command]
+pub fn get_quick_capture_shortcut(db: State<'_, Database>) -> AppResult<String> {
+    shortcut(&db.conn())
+}
+
+/// Switching from the saved shortcut to a new one: the trimmed accelerator
+/// to save, and the shortcuts to register and release.
+pub struct ShortcutChange {
+    pub accelerator: String,
+    pub register: Option<Shortcut>,
+    pub release: Option<Shortcut>,
+}
+
+/// Work out a switch to `shortcut`; an empty one turns quick capture off.
+/// None when it is the saved shortcut already.
+pub fn change(conn: &rusqlite::Connection, shortcut: &str) -> AppResult<Option<ShortcutChange>> {
+    let accelerator = shortcut.trim().to_string();
+    let new = match accelerator.as_str() {
+        "" => None,
+        a => Some(parse(a)?),
+    };
+    let old = self::shortcut(conn)?;
+    let old = if old.is_empty() {
+        None
+    } else {
+        parse(&old).ok()
+    };
+    if old == new {
+        return Ok(None);
+    }
+    Ok(Some(ShortcutChange {
+        accelerator,
+        register: new,
+        release: old,
+    }))
+}
+
+/// Switch to a new shortcut. The setting only changes once the OS has
+/// accepted the shortcut, so a combination taken by another app leaves the
+/// old one working.
+#[tauri::command]
+pub fn set_quick_capture_shortcut(
+    app: AppHandle,
+    db: State<'_, Database>,
+    shortcut: String,
+) -> AppResult<String> {
+    let Some(change) = change(&db.conn(), &shortcut)? else {
+        return Ok(shortcut.trim().to_string());
+    };
+    let global = app.global_shortcut();
+    if let Some(new) = change.register {
+        global.register(new).map_err(|e| {
+            AppError::Invalid(format!("{} is not available: {}", change.accelerator, e))
+        })?;
+    }
+    if let Some(old) = change.release {
+        if let Err(e) = global.unregister(old) {
+            tracing::warn!(error = %e, "could not release the old quick capture shortcut");
+        }
+    }
+    settings::set(&db.conn(), SHORTCUT_SETTING, &change.accelerator)?;
+    Ok(change.accelerator)
+}
@@ -0,0 +1,214 @@
+//! Dirty-shutdown detection. A sentinel file is written when the app starts
+//! and removed on a clean exit; finding it at startup means the last
+//! session crashed or was killed, so work it left half done is tidied up.
+
+use crate::clips;
+use crate::db::{self, Database};
+use crate::drafts::{self, Draft};
+use crate::error::AppResult;
+use crate::events::AppEvent;
+use crate::jobs::{self, status};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{App, AppHandle, Manager, State};
+use ts_rs::TS;
+
+const INTERRUPTED: &str = "interrupted by an unclean shutdown";
+
+fn sentinel_path() -> PathBuf {
+    db::app_dir().join("session.lock")
+}
+
+/// What recovery found and fixed, sent with `recovered-session`.
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RecoveryReport {
+    /// When the interrupted session started, as written in the sentinel.
+    pub session_started: String,
+    /// Jobs caught mid-submit, put back in the offline queue.
+    pub requeued_jobs: Vec<String>,
+    /// Jobs that were waiting on a provider, with the status after polling.
+    pub reconciled_jobs: Vec<ReconciledJob>,
+    pub failed_clips: Vec<String>,
+    /// Characters whose voice clone never came back.
+    pub failed_voice_clones: Vec<String>,
+    /// Text edits autosaved but never saved, offered for restoring.
+    pub unsaved_drafts: Vec<Draft>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciledJob {
+    pub id: String,
+    pub status: String,
+    pub error: String,
+}
+
+/// The last recovery report, kept until the frontend asks for it.
+#[derive(Default)]
+pub struct PendingRecovery(Mutex<Option<RecoveryReport>>);
+
+/// Returns the previous session's start time if it didn't shut down
+/// cleanly, then marks this session as running.
+fn begin_session() -> Option<String> {
+    let path = sentinel_path();
+    let previous = std::fs::read_to_string(&path).ok();
+    let started = unix_now();
+    if let Err(e) = std::fs::write(&path, &started) {
+        tracing::warn!(path = %path.display(), error = %e, "failed to write session sentinel");
+    }
+    previous.map(|s| s.trim().to_string())
+}
+
+/// Seconds since the Unix epoch; the frontend formats it.
+fn unix_now() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_default()
+}
+
+/// Remove the sentinel. Call once the app is exiting normally.
+pub fn end_session() {
+    let _ = std::fs::remove_file(sentinel_path());
+}
+
+fn ids(conn: &Connection, sql: &str, param: &str) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let ids = stmt
+        .query_map([param], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// Local fixes that need no network: half-submitted jobs go back to the
+/// offline queue, and downloads and voice clones that were running are
+/// failed so they can be retried.
+fn repair_local(conn: &Connection, report: &mut RecoveryReport) -> AppResult<()> {
+    report.requeued_jobs = ids(
+        conn,
+        "SELECT id FROM video_jobs WHERE status = ?1",
+        status::PENDING,
+    )?;
+    conn.execute(
+        "UPDATE video_jobs SET status = ?2 WHERE status = ?1",
+        [status::PENDING, status::OFFLINE],
+    )?;
+
+    report.failed_clips = ids(
+        conn,
+        "SELECT id FROM reference_clips WHERE status = ?1",
+        clips::status::DOWNLOADING,
+    )?;
+    conn.execute(
+        "UPDATE reference_clips SET status = ?2, error = ?3 WHERE status = ?1",
+        [
+            clips::status::DOWNLOADING,
+            clips::status::FAILED,
+            INTERRUPTED,
+        ],
+    )?;
+
+    report.failed_voice_clones = ids(
+        conn,
+        "SELECT id FROM characters WHERE voice_clone_status = ?1",
+        "pending",
+    )?;
+    conn.execute(
+        "UPDATE characters SET voice_clone_status = 'failed' WHERE voice_clone_status = ?1",
+        ["pending"],
+    )?;
+    Ok(())
+}
+
+/// Poll the provider for every job that was waiting in its queue.
+async fn reconcile_jobs(app: &AppHandle, report: &mut RecoveryReport) -> AppResult<()> {
+    let queued = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        ids(
+            &conn,
+            "SELECT id FROM video_jobs WHERE status = ?1",
+            status::QUEUED,
+        )?
+    };
+    for id in queued {
+        match jobs::refresh_job(app.clone(), id.clone()).await {
+            Ok(job) => report.reconciled_jobs.push(ReconciledJob {
+                id,
+                status: job.status,
+                error: job.error,
+            }),
+            Err(e) => {
+                tracing::warn!(job_id = %id, error = %e, "failed to reconcile job after crash");
+                report.reconciled_jobs.push(ReconciledJob {
+                    id,
+                    status: status::QUEUED.to_string(),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the session sentinel and, if the last session ended uncleanly,
+/// repair what it left behind. Local fixes happen before anything else
+/// touches the database; provider polling runs in the background and ends
+/// with a `recovered-session` event.
+pub fn setup(app: &App) {
+    app.manage(PendingRecovery::default());
+    let Some(session_started) = begin_session() else {
+        return;
+    };
+    tracing::warn!(
+        session_started,
+        "previous session did not shut down cleanly"
+    );
+
+    let mut report = RecoveryReport {
+        session_started,
+        ..Default::default()
+    };
+    {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        if let Err(e) = repair_local(&conn, &mut report) {
+            tracing::error!(error = %e, "crash recovery failed");
+        }
+        match drafts::unsaved(&conn) {
+            Ok(found) => report.unsaved_drafts = found,
+            Err(e) => tracing::error!(error = %e, "failed to collect unsaved drafts"),
+        }
+    }
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = reconcile_jobs(&handle, &mut report).await {
+            tracing::error!(error = %e, "failed to reconcile jobs after crash");
+        }
+        tracing::info!(
+            requeued = report.requeued_jobs.len(),
+            reconciled = report.reconciled_jobs.len(),
+            failed_clips = report.failed_clips.len(),
+            failed_voice_clones = report.failed_voice_clones.len(),
+            unsaved_drafts = report.unsaved_drafts.len(),
+            "session recovered"
+        );
+        *handle
+            .state::<PendingRecovery>()
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(report.clone());
+        AppEvent::RecoveredSession(report).emit(&handle);
+    });
+}
+
+/// The recovery report from this launch, if the frontend hasn't shown it.
+#[tauri::command]
+pub fn take_recovery_report(pending: State<'_, PendingRecovery>) -> Option<RecoveryReport> {
+    pending.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
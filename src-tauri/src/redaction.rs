@@ -0,0 +1,229 @@
+//! What support bundles and exported logs leave out, so debug info can be
+//! sent to someone without sending the production along with it. The
+//! policy picks the kinds of data to scrub and is on for all of them
+//! until turned off:
+//!
+//! - API keys and other stored credentials
+//! - prompts of scenes marked confidential
+//! - character photos, and any inline image data
+//!
+//! A `Redactor` is built from the policy and the database's current values,
+//! then run over every string that leaves, JSON or plain text.
+
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::projects;
+use crate::scenes::{self, Scene};
+use crate::settings;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+use ts_rs::TS;
+
+const API_KEYS_SETTING: &str = "redaction.api_keys";
+const CONFIDENTIAL_PROMPTS_SETTING: &str = "redaction.confidential_prompts";
+const CHARACTER_PHOTOS_SETTING: &str = "redaction.character_photos";
+
+/// Values shorter than this aren't matched inside other text; they would
+/// scrub ordinary words along with them.
+const MIN_MATCH_CHARS: usize = 6;
+/// Field names whose values are always hidden, whatever they hold.
+const SECRET_FIELDS: &[&str] = &["api_key", "apikey", "password", "token", "secret"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RedactionPolicy {
+    pub api_keys: bool,
+    pub confidential_prompts: bool,
+    pub character_photos: bool,
+}
+
+pub fn policy(conn: &Connection) -> AppResult<RedactionPolicy> {
+    let on = |key| -> AppResult<bool> {
+        Ok(settings::get(conn, key)?.map_or(true, |v| v != "false" && v != "0"))
+    };
+    Ok(RedactionPolicy {
+        api_keys: on(API_KEYS_SETTING)?,
+        confidential_prompts: on(CONFIDENTIAL_PROMPTS_SETTING)?,
+        character_photos: on(CHARACTER_PHOTOS_SETTING)?,
+    })
+}
+
+fn column(conn: &Connection, sql: &str) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let values = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(values)
+}
+
+/// Scrubs the values the policy covers out of strings and JSON.
+pub struct Redactor {
+    /// Values to replace and what replaces them, longest first so a value
+    /// containing another is replaced whole.
+    values: Vec<(String, &'static str)>,
+    api_keys: bool,
+    images: bool,
+}
+
+impl Redactor {
+    /// A redactor for the policy in effect, with the values it covers as
+    /// they are now.
+    pub fn new(conn: &Connection) -> AppResult<Self> {
+        let policy = policy(conn)?;
+        let mut values = Vec::new();
+        if policy.api_keys {
+            // Credentials are never per-user, so they live in `settings`.
+            let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+            let credentials = stmt
+                .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for (_, value) in credentials
+                .into_iter()
+                .filter(|(key, _)| settings::is_credential(key))
+            {
+                values.push((value, "[redacted credential]"));
+            }
+        }
+        if policy.confidential_prompts {
+            for prompt in column(
+                conn,
+                "SELECT prompt FROM scenes WHERE prompt_confidential = 1 AND prompt <> ''",
+            )? {
+                values.push((prompt, "[confidential prompt]"));
+            }
+        }
+        if policy.character_photos {
+            for photo in column(
+                conn,
+                "SELECT photo_data FROM characters WHERE photo_data <> ''",
+            )? {
+                values.push((photo, "[character photo]"));
+            }
+        }
+        values.retain(|(value, _)| value.trim().chars().count() >= MIN_MATCH_CHARS);
+        values.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        values.dedup_by(|a, b| a.0 == b.0);
+        Ok(Self {
+            values,
+            api_keys: policy.api_keys,
+            images: policy.character_photos,
+        })
+    }
+
+    pub fn text(&self, text: &str) -> String {
+        let mut text = self
+            .values
+            .iter()
+            .filter(|(value, _)| text.contains(value.as_str()))
+            .fold(text.to_string(), |acc, (value, placeholder)| {
+                acc.replace(value.as_str(), placeholder)
+            });
+        if self.images && text.contains("data:image/") {
+            text = strip_images(&text);
+        }
+        text
+    }
+
+    pub fn value(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => *s = self.text(s),
+            Value::Array(items) => items.iter_mut().for_each(|v| self.value(v)),
+            Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    let key = key.to_ascii_lowercase();
+                    let secret = SECRET_FIELDS.iter().any(|f| key.contains(f));
+                    if self.api_keys && secret && v.as_str().is_some_and(|s| !s.is_empty()) {
+                        *v = Value::String("[redacted]".into());
+                    } else {
+                        self.value(v);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// One line of a log file: a JSON entry as written by the file layer,
+    /// or plain text.
+    pub fn line(&self, line: &str) -> String {
+        match serde_json::from_str::<Value>(line) {
+            Ok(mut entry) if entry.is_object() => {
+                self.value(&mut entry);
+                entry.to_string()
+            }
+            _ => self.text(line),
+        }
+    }
+}
+
+/// Replace inline `data:image/...` URIs with a placeholder.
+fn strip_images(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("data:image/") {
+        out.push_str(&rest[..start]);
+        let uri = &rest[start..];
+        let end = uri
+            .find(|c: char| c == '"' || c == '\'' || c == ')' || c.is_whitespace())
+            .unwrap_or(uri.len());
+        out.push_str("[image data]");
+        rest = &uri[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[tauri::command]
+pub fn get_redaction_policy(db: State<'_, Database>) -> AppResult<RedactionPolicy> {
+    policy(&db.conn())
+}
+
+pub fn set_policy(conn: &Connection, policy: &RedactionPolicy) -> AppResult<RedactionPolicy> {
+    settings::set(conn, API_KEYS_SETTING, &policy.api_keys.to_string())?;
+    settings::set(
+        conn,
+        CONFIDENTIAL_PROMPTS_SETTING,
+        &policy.confidential_prompts.to_string(),
+    )?;
+    settings::set(
+        conn,
+        CHARACTER_PHOTOS_SETTING,
+        &policy.character_photos.to_string(),
+    )?;
+    self::policy(conn)
+}
+
+#[tauri::command]
+pub fn set_redaction_policy(
+    db: State<'_, Database>,
+    policy: RedactionPolicy,
+) -> AppResult<RedactionPolicy> {
+    set_policy(&db.conn(), &policy)
+}
+
+/// Mark a scene's prompt confidential, or clear the mark.
+pub fn mark_prompt_confidential(
+    conn: &Connection,
+    scene_id: &str,
+    confidential: bool,
+) -> AppResult<Scene> {
+    let scene = scenes::get_scene(conn, scene_id)?;
+    projects::ensure_unlocked(conn, &scene.project_id)?;
+    conn.execute(
+        "UPDATE scenes SET prompt_confidential = ?2 WHERE id = ?1",
+        params![scene_id, confidential],
+    )?;
+    scenes::get_scene(conn, scene_id)
+}
+
+#[tauri::command]
+pub fn set_prompt_confidential(
+    db: State<'_, Database>,
+    scene_id: String,
+    confidential: bool,
+) -> AppResult<Scene> {
+    mark_prompt_confidential(&db.conn(), &scene_id, confidential)
+}
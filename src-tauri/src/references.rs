@@ -0,0 +1,251 @@
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::moodboards::{self, MoodboardItem};
+use crate::providers;
+use reqwest::Url;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+/// Only the start of a page is parsed; the metadata we want is in `<head>`.
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+const MAX_IMAGE_BYTES: usize = 15 * 1024 * 1024;
+
+/// Stream a response body, giving up as soon as it passes `limit` bytes
+/// rather than trusting `Content-Length`.
+async fn download_limited(
+    url: &Url,
+    limit: usize,
+    accept: fn(&str) -> bool,
+) -> AppResult<(Vec<u8>, String)> {
+    let mut res = providers::client().get(url.clone()).send().await?;
+    if !res.status().is_success() {
+        return Err(AppError::Provider(format!(
+            "{} returned {}",
+            url,
+            res.status()
+        )));
+    }
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    if !accept(&content_type) {
+        return Err(AppError::Invalid(format!(
+            "{} is {}, not a supported type",
+            url,
+            if content_type.is_empty() {
+                "untyped"
+            } else {
+                &content_type
+            }
+        )));
+    }
+    if res.content_length().is_some_and(|len| len as usize > limit) {
+        return Err(AppError::Invalid(format!("{} is too large", url)));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() > limit {
+            return Err(AppError::Invalid(format!("{} is too large", url)));
+        }
+    }
+    Ok((body, content_type))
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Pull `name="value"` / `name='value'` pairs out of a single tag.
+fn tag_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = tag;
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq]
+            .rsplit(|c: char| c.is_whitespace())
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        let after = rest[eq + 1..].trim_start();
+        let (value, remainder) = match after.chars().next() {
+            Some(q @ ('"' | '\'')) => match after[1..].find(q) {
+                Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                None => break,
+            },
+            _ => {
+                let end = after
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if !name.is_empty() {
+            attrs.insert(name, decode_entities(value.trim()));
+        }
+        rest = remainder;
+    }
+    attrs
+}
+
+#[derive(Default)]
+struct PageMeta {
+    title: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+}
+
+fn parse_meta(html: &str) -> PageMeta {
+    let mut meta = PageMeta::default();
+    let lower = html.to_ascii_lowercase();
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta") {
+        let start = pos + start;
+        let end = lower[start..].find('>').map_or(html.len(), |e| start + e);
+        let attrs = tag_attributes(&html[start + 5..end]);
+        pos = end;
+
+        let key = attrs
+            .get("property")
+            .or_else(|| attrs.get("name"))
+            .map(|k| k.to_lowercase());
+        let Some(content) = attrs.get("content").filter(|c| !c.is_empty()) else {
+            continue;
+        };
+        match key.as_deref() {
+            Some("og:title") => meta.title = Some(content.clone()),
+            Some("twitter:title") if meta.title.is_none() => meta.title = Some(content.clone()),
+            Some("og:description") => meta.description = Some(content.clone()),
+            Some("description" | "twitter:description") if meta.description.is_none() => {
+                meta.description = Some(content.clone())
+            }
+            Some("og:image" | "og:image:url" | "og:image:secure_url") if meta.image.is_none() => {
+                meta.image = Some(content.clone())
+            }
+            Some("twitter:image") if meta.image.is_none() => meta.image = Some(content.clone()),
+            _ => {}
+        }
+    }
+    if meta.title.is_none() {
+        if let (Some(s), Some(e)) = (lower.find("<title"), lower.find("</title>")) {
+            if let Some(gt) = lower[s..e].find('>') {
+                let title = decode_entities(html[s + gt + 1..e].trim());
+                if !title.is_empty() {
+                    meta.title = Some(title);
+                }
+            }
+        }
+    }
+    meta
+}
+
+fn image_extension(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => "jpg",
+    }
+}
+
+/// Check a pasted link can be fetched into the moodboard.
+pub fn parse_link(conn: &Connection, moodboard_id: &str, url: &str) -> AppResult<Url> {
+    let page_url =
+        Url::parse(url.trim()).map_err(|e| AppError::Invalid(format!("invalid URL: {}", e)))?;
+    if !matches!(page_url.scheme(), "http" | "https") {
+        return Err(AppError::Invalid(
+            "only http(s) links can be fetched".into(),
+        ));
+    }
+    moodboards::get_board(conn, moodboard_id)?;
+    Ok(page_url)
+}
+
+/// Save a fetched image into `media_dir` and add it to the moodboard,
+/// captioned and linked back to the page it came from.
+pub fn store(
+    conn: &Connection,
+    media_dir: &Path,
+    moodboard_id: &str,
+    page_url: &Url,
+    image: &[u8],
+    content_type: &str,
+    caption: &str,
+) -> AppResult<MoodboardItem> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let dest = media_dir.join(format!("{}.{}", id, image_extension(content_type)));
+    std::fs::write(&dest, image)?;
+    moodboards::insert_item(
+        conn,
+        &id,
+        moodboard_id,
+        &dest.to_string_lossy(),
+        "web",
+        caption,
+        page_url.as_str(),
+    )
+}
+
+/// Turn a pasted link into a moodboard item: read the page's og:image,
+/// title and description, download the image into the media folder and
+/// caption it with the page's title and description. A direct image URL
+/// is stored as-is.
+#[tauri::command]
+pub async fn fetch_reference(
+    app: AppHandle,
+    moodboard_id: String,
+    url: String,
+) -> AppResult<MoodboardItem> {
+    let page_url = parse_link(&app.state::<Database>().conn(), &moodboard_id, &url)?;
+
+    let (body, content_type) = download_limited(&page_url, MAX_IMAGE_BYTES, |ct| {
+        ct.starts_with("text/html")
+            || ct.starts_with("application/xhtml")
+            || ct.starts_with("image/")
+    })
+    .await?;
+
+    let (image_bytes, image_type, caption) = if content_type.starts_with("image/") {
+        (body, content_type, String::new())
+    } else {
+        let html = String::from_utf8_lossy(&body[..body.len().min(MAX_PAGE_BYTES)]);
+        let meta = parse_meta(&html);
+        let image = meta
+            .image
+            .ok_or_else(|| AppError::NotFound("page has no preview image".into()))?;
+        let image_url = page_url
+            .join(&image)
+            .map_err(|e| AppError::Invalid(format!("bad preview image URL: {}", e)))?;
+        let (bytes, image_type) =
+            download_limited(&image_url, MAX_IMAGE_BYTES, |ct| ct.starts_with("image/")).await?;
+        let caption = [meta.title, meta.description]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" — ");
+        (bytes, image_type, caption)
+    };
+
+    store(
+        &app.state::<Database>().conn(),
+        &db::media_dir("moodboards")?,
+        &moodboard_id,
+        &page_url,
+        &image_bytes,
+        &image_type,
+        &caption,
+    )
+}
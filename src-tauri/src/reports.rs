@@ -0,0 +1,293 @@
+use crate::characters::{self, Character};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::i18n::{self, Locale};
+use crate::scenes::{self, Scene};
+use crate::workflow::SceneStatus;
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::State;
+use ts_rs::TS;
+
+const UNSPECIFIED_LOCATION: &str = "Unspecified";
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CharacterBreakdown {
+    /// `None` for names used in scenes that have no character record.
+    pub character_id: Option<String>,
+    pub name: String,
+    pub scene_ids: Vec<String>,
+    #[ts(type = "Array<number>")]
+    pub scene_numbers: Vec<i64>,
+    /// Sum of the durations of the scenes they appear in, in seconds.
+    #[ts(type = "number")]
+    pub screen_time: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct LocationBreakdown {
+    pub location: String,
+    pub scene_ids: Vec<String>,
+    #[ts(type = "Array<number>")]
+    pub scene_numbers: Vec<i64>,
+    #[ts(type = "number")]
+    pub total_duration: i64,
+    pub characters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct StatusBreakdown {
+    pub status: SceneStatus,
+    pub scene_ids: Vec<String>,
+    #[ts(type = "Array<number>")]
+    pub scene_numbers: Vec<i64>,
+    #[ts(type = "number")]
+    pub total_duration: i64,
+}
+
+/// Scenes reference cast by character id or, from older storyboards, by
+/// name. Resolve either to a display name.
+fn cast_name<'a>(characters: &'a [Character], entry: &'a str) -> (Option<&'a Character>, &'a str) {
+    match characters
+        .iter()
+        .find(|c| c.id == entry || c.name.eq_ignore_ascii_case(entry))
+    {
+        Some(c) => (Some(c), c.name.as_str()),
+        None => (None, entry),
+    }
+}
+
+/// A project's scenes and characters, which every breakdown is built from.
+pub fn load(conn: &Connection, project_id: &str) -> AppResult<(Vec<Scene>, Vec<Character>)> {
+    export::load_project(conn, project_id)?;
+    Ok((
+        scenes::list_for_project(conn, project_id)?,
+        characters::list_for_project(conn, project_id)?,
+    ))
+}
+
+pub fn by_character(scenes: &[Scene], characters: &[Character]) -> Vec<CharacterBreakdown> {
+    let mut rows: Vec<CharacterBreakdown> = characters
+        .iter()
+        .map(|c| CharacterBreakdown {
+            character_id: Some(c.id.clone()),
+            name: c.name.clone(),
+            scene_ids: Vec::new(),
+            scene_numbers: Vec::new(),
+            screen_time: 0,
+        })
+        .collect();
+    for scene in scenes {
+        for entry in &scene.characters {
+            let (character, name) = cast_name(characters, entry);
+            let index = match rows.iter().position(|r| r.name == name) {
+                Some(i) => i,
+                None => {
+                    rows.push(CharacterBreakdown {
+                        character_id: character.map(|c| c.id.clone()),
+                        name: name.to_string(),
+                        scene_ids: Vec::new(),
+                        scene_numbers: Vec::new(),
+                        screen_time: 0,
+                    });
+                    rows.len() - 1
+                }
+            };
+            let row = &mut rows[index];
+            if !row.scene_ids.contains(&scene.id) {
+                row.scene_ids.push(scene.id.clone());
+                row.scene_numbers.push(scene.scene_number);
+                row.screen_time += scene.duration;
+            }
+        }
+    }
+    rows.sort_by(|a, b| b.screen_time.cmp(&a.screen_time).then(a.name.cmp(&b.name)));
+    rows
+}
+
+pub fn by_location(scenes: &[Scene], characters: &[Character]) -> Vec<LocationBreakdown> {
+    let mut rows: Vec<LocationBreakdown> = Vec::new();
+    for scene in scenes {
+        let location = match scene.location.trim() {
+            "" => UNSPECIFIED_LOCATION,
+            l => l,
+        };
+        let index = match rows
+            .iter()
+            .position(|r| r.location.eq_ignore_ascii_case(location))
+        {
+            Some(i) => i,
+            None => {
+                rows.push(LocationBreakdown {
+                    location: location.to_string(),
+                    scene_ids: Vec::new(),
+                    scene_numbers: Vec::new(),
+                    total_duration: 0,
+                    characters: Vec::new(),
+                });
+                rows.len() - 1
+            }
+        };
+        let row = &mut rows[index];
+        row.scene_ids.push(scene.id.clone());
+        row.scene_numbers.push(scene.scene_number);
+        row.total_duration += scene.duration;
+        for entry in &scene.characters {
+            let name = cast_name(characters, entry).1.to_string();
+            if !row.characters.contains(&name) {
+                row.characters.push(name);
+            }
+        }
+    }
+    rows
+}
+
+pub fn by_status(scenes: &[Scene]) -> Vec<StatusBreakdown> {
+    SceneStatus::ALL
+        .into_iter()
+        .map(|status| {
+            let matching: Vec<&Scene> = scenes
+                .iter()
+                .filter(|s| s.status == status.as_str())
+                .collect();
+            StatusBreakdown {
+                status,
+                scene_ids: matching.iter().map(|s| s.id.clone()).collect(),
+                scene_numbers: matching.iter().map(|s| s.scene_number).collect(),
+                total_duration: matching.iter().map(|s| s.duration).sum(),
+            }
+        })
+        .collect()
+}
+
+fn numbers(locale: Locale, list: &[i64]) -> String {
+    list.iter()
+        .map(|&n| locale.integer(n))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[tauri::command]
+pub fn get_character_breakdown(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<CharacterBreakdown>> {
+    let (scenes, characters) = load(&db.conn(), &project_id)?;
+    Ok(by_character(&scenes, &characters))
+}
+
+#[tauri::command]
+pub fn get_location_breakdown(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<LocationBreakdown>> {
+    let (scenes, characters) = load(&db.conn(), &project_id)?;
+    Ok(by_location(&scenes, &characters))
+}
+
+#[tauri::command]
+pub fn get_status_breakdown(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<StatusBreakdown>> {
+    let (scenes, _) = load(&db.conn(), &project_id)?;
+    Ok(by_status(&scenes))
+}
+
+/// Write one breakdown (`"character"`, `"location"` or `"status"`) as CSV.
+/// Returns the written path.
+pub fn export_csv(
+    conn: &Connection,
+    project_id: &str,
+    report: &str,
+    path: &str,
+) -> AppResult<String> {
+    let locale = i18n::current(conn)?;
+    let (scenes, characters) = load(conn, project_id)?;
+    let mut csv = String::new();
+    match report {
+        "character" => {
+            csv.push_str(&export::csv_row(&[
+                locale.t("breakdown.character"),
+                locale.t("breakdown.scenes"),
+                locale.t("breakdown.sceneCount"),
+                locale.t("breakdown.screenTime"),
+            ]));
+            for row in by_character(&scenes, &characters) {
+                csv.push_str(&export::csv_row(&[
+                    row.name,
+                    numbers(locale, &row.scene_numbers),
+                    locale.integer(row.scene_numbers.len() as i64),
+                    locale.integer(row.screen_time),
+                ]));
+            }
+        }
+        "location" => {
+            csv.push_str(&export::csv_row(&[
+                locale.t("breakdown.location"),
+                locale.t("breakdown.scenes"),
+                locale.t("breakdown.sceneCount"),
+                locale.t("breakdown.duration"),
+                locale.t("breakdown.characters"),
+            ]));
+            for row in by_location(&scenes, &characters) {
+                let location = if row.location == UNSPECIFIED_LOCATION {
+                    locale.t("breakdown.unspecified").to_string()
+                } else {
+                    row.location
+                };
+                csv.push_str(&export::csv_row(&[
+                    location,
+                    numbers(locale, &row.scene_numbers),
+                    locale.integer(row.scene_numbers.len() as i64),
+                    locale.integer(row.total_duration),
+                    row.characters.join("; "),
+                ]));
+            }
+        }
+        "status" => {
+            csv.push_str(&export::csv_row(&[
+                locale.t("breakdown.status"),
+                locale.t("breakdown.scenes"),
+                locale.t("breakdown.sceneCount"),
+                locale.t("breakdown.duration"),
+            ]));
+            for row in by_status(&scenes) {
+                csv.push_str(&export::csv_row(&[
+                    locale
+                        .t(&format!("status.{}", row.status.as_str()))
+                        .to_string(),
+                    numbers(locale, &row.scene_numbers),
+                    locale.integer(row.scene_numbers.len() as i64),
+                    locale.integer(row.total_duration),
+                ]));
+            }
+        }
+        other => {
+            return Err(AppError::Invalid(format!(
+                "unknown breakdown report '{}'",
+                other
+            )))
+        }
+    }
+    let out = export::resolve_output(path, &format!("breakdown-{}.csv", report))?;
+    std::fs::write(&out, csv)?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_breakdown_csv(
+    db: State<'_, Database>,
+    project_id: String,
+    report: String,
+    path: String,
+) -> AppResult<String> {
+    export_csv(&db.conn(), &project_id, &report, &path)
+}
@@ -0,0 +1,753 @@
+//! Uploading takes and finished renders to a review platform, so clients
+//! can watch and comment without anyone moving files by hand. Frame.io is
+//! supported directly; any other platform can be reached through a
+//! generic endpoint (see `upload_generic`).
+//!
+//! Each upload gets its own review link. A take's link is stored on the
+//! take; a render's is returned and kept in the activity log. An export
+//! preset can have a watermark burned into the uploaded copy (see
+//! `export_presets`).
+
+use crate::activity;
+use crate::confidential;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::export_presets::{self, Stamp};
+use crate::frames;
+use crate::jobs::{self, status, VideoJob};
+use crate::numbering;
+use crate::projects;
+use crate::provenance;
+use crate::provider_cache;
+use crate::providers;
+use crate::publishing;
+use crate::scenes::{self, Scene};
+use crate::secrets;
+use crate::settings;
+use crate::validation::Validator;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use ts_rs::TS;
+
+const PLATFORM_SETTING: &str = "review.platform";
+/// The Frame.io project uploads go into.
+const FRAMEIO_PROJECT_SETTING: &str = "review.frameio_project";
+/// The generic platform's upload endpoint.
+const GENERIC_URL_SETTING: &str = "review.generic_url";
+const FRAMEIO_API: &str = "https://api.frame.io/v2";
+/// Uploads can be large; the shared client's timeout is meant for API calls.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// A project's root folder doesn't change; look it up once a day.
+const FRAMEIO_ROOT_CACHE: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewPlatform {
+    #[serde(rename = "frameio")]
+    FrameIo,
+    Generic,
+}
+
+impl ReviewPlatform {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReviewPlatform::FrameIo => "frameio",
+            ReviewPlatform::Generic => "generic",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ReviewPlatform::FrameIo => "Frame.io",
+            ReviewPlatform::Generic => "the review platform",
+        }
+    }
+
+    fn token_secret(self) -> String {
+        format!("review.{}_token", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ReviewSettings {
+    pub platform: ReviewPlatform,
+    pub frameio_project: String,
+    pub generic_url: String,
+    /// Whether the selected platform has a token.
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ReviewSettingsInput {
+    pub platform: ReviewPlatform,
+    #[serde(default)]
+    pub frameio_project: String,
+    #[serde(default)]
+    pub generic_url: String,
+    /// Replaces the platform's token when set; empty removes it.
+    pub token: Option<String>,
+}
+
+/// One file's outcome.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ReviewUpload {
+    /// None for a render.
+    pub take_id: Option<String>,
+    pub name: String,
+    pub review_url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// How the uploaded copy is prepared.
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ReviewCopy {
+    /// An export preset whose watermark is burned in.
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// Who the copy is for, shown by watermarks that name the recipient.
+    #[serde(default)]
+    pub recipient: String,
+}
+
+/// What to upload: a local file or a URL the platform can fetch itself.
+struct Source {
+    name: String,
+    location: String,
+}
+
+impl Source {
+    fn is_remote(&self) -> bool {
+        self.location.starts_with("http://") || self.location.starts_with("https://")
+    }
+}
+
+/// The review settings, and whether the selected platform has a token.
+pub fn load(conn: &Connection) -> AppResult<ReviewSettings> {
+    let platform = match settings::get(conn, PLATFORM_SETTING)?.as_deref() {
+        Some("generic") => ReviewPlatform::Generic,
+        _ => ReviewPlatform::FrameIo,
+    };
+    Ok(ReviewSettings {
+        platform,
+        frameio_project: settings::get(conn, FRAMEIO_PROJECT_SETTING)?.unwrap_or_default(),
+        generic_url: settings::get(conn, GENERIC_URL_SETTING)?.unwrap_or_default(),
+        connected: secrets::get(conn, &platform.token_secret())?.is_some(),
+    })
+}
+
+fn content_type(name: &str) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        _ => "application/octet-stream",
+    }
+}
+
+fn extension(location: &str) -> &str {
+    let path = location.split(['?', '#']).next().unwrap_or_default();
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.len() <= 4)
+        .unwrap_or("mp4")
+}
+
+async fn check(res: reqwest::Response, what: &str) -> AppResult<Value> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res.json().await.unwrap_or(Value::Null));
+    }
+    let body = res.text().await.unwrap_or_default();
+    Err(AppError::Provider(format!(
+        "{} ({}): {}",
+        what, status, body
+    )))
+}
+
+fn frameio_str(body: &Value, key: &str, what: &str) -> AppResult<String> {
+    body[key]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Provider(format!("Frame.io returned no {}", what)))
+}
+
+/// Write a local file to Frame.io's upload URLs, one equal part each.
+async fn frameio_put_parts(file: &Path, size: u64, urls: &[Value]) -> AppResult<()> {
+    if urls.is_empty() {
+        return Err(AppError::Provider(
+            "Frame.io returned no upload URLs".into(),
+        ));
+    }
+    let parts = urls.len() as u64;
+    let part = (size + parts - 1) / parts;
+    let mut reader = tokio::fs::File::open(file).await?;
+    for (i, url) in urls.iter().enumerate() {
+        let url = url
+            .as_str()
+            .ok_or_else(|| AppError::Provider("Frame.io returned a bad upload URL".into()))?;
+        let start = part * i as u64;
+        let len = part.min(size.saturating_sub(start));
+        let mut chunk = vec![0; len as usize];
+        reader.seek(std::io::SeekFrom::Start(start)).await?;
+        reader.read_exact(&mut chunk).await?;
+        let res = providers::client()
+            .put(url)
+            .timeout(UPLOAD_TIMEOUT)
+            .header("Content-Type", content_type(&file.to_string_lossy()))
+            .header("x-amz-acl", "private")
+            .body(chunk)
+            .send()
+            .await?;
+        check(res, "Frame.io upload").await?;
+    }
+    Ok(())
+}
+
+/// The Frame.io project's root folder.
+async fn frameio_root(token: &str, project: &str) -> AppResult<String> {
+    let res = providers::client()
+        .get(format!("{}/projects/{}", FRAMEIO_API, project))
+        .bearer_auth(token)
+        .send()
+        .await?;
+    frameio_str(
+        &check(res, "Frame.io project").await?,
+        "root_asset_id",
+        "root folder",
+    )
+}
+
+/// Upload to the Frame.io project and share it on a new review link.
+async fn upload_frameio(
+    app: &AppHandle,
+    token: &str,
+    project: &str,
+    source: &Source,
+) -> AppResult<String> {
+    let key = provider_cache::key("frameio", &[token, project]);
+    let root = provider_cache::get_or_fetch(
+        &app.state::<Database>(),
+        "frameio",
+        &key,
+        FRAMEIO_ROOT_CACHE,
+        || frameio_root(token, project),
+    )
+    .await?;
+
+    let mut asset = json!({
+        "type": "file",
+        "name": source.name,
+        "filetype": content_type(&source.name),
+    });
+    let local = (!source.is_remote()).then(|| Path::new(&source.location));
+    let size = match local {
+        Some(path) => {
+            let size = tokio::fs::metadata(path).await?.len();
+            asset["filesize"] = json!(size);
+            size
+        }
+        None => {
+            // Frame.io fetches URLs itself.
+            asset["source"] = json!({ "url": source.location });
+            0
+        }
+    };
+    let res = providers::client()
+        .post(format!("{}/assets/{}/children", FRAMEIO_API, root))
+        .bearer_auth(token)
+        .json(&asset)
+        .send()
+        .await?;
+    let created = check(res, "Frame.io asset").await?;
+    let asset_id = frameio_str(&created, "id", "asset id")?;
+    if let Some(path) = local {
+        let urls = created["upload_urls"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        frameio_put_parts(path, size, &urls).await?;
+    }
+
+    let res = providers::client()
+        .post(format!("{}/projects/{}/review_links", FRAMEIO_API, project))
+        .bearer_auth(token)
+        .json(&json!({ "name": source.name }))
+        .send()
+        .await?;
+    let link = check(res, "Frame.io review link").await?;
+    let link_id = frameio_str(&link, "id", "review link id")?;
+    let res = providers::client()
+        .post(format!("{}/review_links/{}/assets", FRAMEIO_API, link_id))
+        .bearer_auth(token)
+        .json(&json!({ "asset_ids": [asset_id] }))
+        .send()
+        .await?;
+    check(res, "Frame.io review link").await?;
+    frameio_str(&link, "short_url", "review link URL")
+}
+
+/// Upload to a generic endpoint. A local file is POSTed as multipart form
+/// field `file` with a `name` field; a remote one as JSON `{name, url}`.
+/// The response must be JSON with the review link in `url`, `reviewUrl`
+/// or `review_url`.
+async fn upload_generic(token: &str, endpoint: &str, source: &Source) -> AppResult<String> {
+    let request = providers::client()
+        .post(endpoint)
+        .timeout(UPLOAD_TIMEOUT)
+        .bearer_auth(token);
+    let request = if source.is_remote() {
+        request.json(&json!({ "name": source.name, "url": source.location }))
+    } else {
+        let bytes = tokio::fs::read(&source.location).await?;
+        let part = reqwest::multipart::Part::bytes(bytes)
+            .file_name(source.name.clone())
+            .mime_str(content_type(&source.name))?;
+        request.multipart(
+            reqwest::multipart::Form::new()
+                .text("name", source.name.clone())
+                .part("file", part),
+        )
+    };
+    let body = check(request.send().await?, "Review upload").await?;
+    ["url", "reviewUrl", "review_url"]
+        .iter()
+        .find_map(|k| body[*k].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Provider("the review platform returned no link".into()))
+}
+
+/// The platform, its token and where uploads go, checked up front.
+pub fn destination(conn: &Connection) -> AppResult<(ReviewPlatform, String, String)> {
+    let config = load(conn)?;
+    let platform = config.platform;
+    let token = secrets::get(conn, &platform.token_secret())?.ok_or_else(|| {
+        AppError::Invalid(format!("add an API token for {} first", platform.label()))
+    })?;
+    let target = match platform {
+        ReviewPlatform::FrameIo => config.frameio_project,
+        ReviewPlatform::Generic => config.generic_url,
+    };
+    if target.is_empty() {
+        return Err(AppError::Invalid(format!(
+            "choose where uploads to {} go first",
+            platform.label()
+        )));
+    }
+    Ok((platform, token, target))
+}
+
+async fn upload(
+    app: &AppHandle,
+    platform: ReviewPlatform,
+    token: &str,
+    target: &str,
+    source: &Source,
+) -> AppResult<String> {
+    match platform {
+        ReviewPlatform::FrameIo => upload_frameio(app, token, target, source).await,
+        ReviewPlatform::Generic => upload_generic(token, target, source).await,
+    }
+}
+
+/// The burn-in filter for a copy of something in `project_id` showing
+/// `scenes` (labels by start time), if any.
+fn copy_filter(
+    conn: &Connection,
+    project_id: &str,
+    scenes: Vec<(f64, String)>,
+    copy: &ReviewCopy,
+) -> AppResult<Option<String>> {
+    if copy.preset_id.is_none() {
+        return Ok(None);
+    }
+    let project = projects::get_project(conn, project_id)?;
+    export_presets::filter_for(
+        conn,
+        copy.preset_id.as_deref(),
+        &Stamp {
+            project: &project.name,
+            recipient: &copy.recipient,
+            scenes,
+        },
+    )
+}
+
+/// Upload `source`, or a copy of it with `filter` burned in and tagged
+/// with `metadata`. The copy is removed afterwards.
+async fn send(
+    app: &AppHandle,
+    (platform, token, target): (ReviewPlatform, &str, &str),
+    source: &Source,
+    filter: Option<&str>,
+    metadata: &[String],
+) -> AppResult<String> {
+    let Some(filter) = filter else {
+        return upload(app, platform, token, target, source).await;
+    };
+    let binary = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        frames::ffmpeg_binary(&conn)?
+    };
+    let marked = export_presets::burn_in(&binary, &source.location, filter, metadata).await?;
+    let stem = source
+        .name
+        .rsplit_once('.')
+        .map_or(source.name.as_str(), |(stem, _)| stem);
+    let copy = Source {
+        name: format!("{}.mp4", stem),
+        location: marked.to_string_lossy().into_owned(),
+    };
+    let result = upload(app, platform, token, target, &copy).await;
+    std::fs::remove_file(&marked).ok();
+    result
+}
+
+fn scene_label(scene: &Scene) -> String {
+    format!(
+        "Scene {}",
+        numbering::label(scene.scene_number, &scene.scene_suffix)
+    )
+}
+
+/// Labels of the scenes in a render of `project_id`, by start time.
+fn render_scenes(conn: &Connection, project_id: &str) -> AppResult<Vec<(f64, String)>> {
+    Ok(publishing::scene_timeline(conn, project_id)?
+        .iter()
+        .map(|(start, scene)| (*start, scene_label(scene)))
+        .collect())
+}
+
+fn take_source(conn: &Connection, take: &VideoJob) -> AppResult<Source> {
+    if take.status != status::COMPLETED || take.video_url.is_empty() {
+        return Err(AppError::Invalid(
+            "only finished takes can be uploaded".into(),
+        ));
+    }
+    let scene = scenes::get_scene(conn, &take.scene_id)?;
+    Ok(Source {
+        name: format!(
+            "Scene {} - {} ({}).{}",
+            numbering::label(scene.scene_number, &scene.scene_suffix),
+            scene.title,
+            &take.id[..8.min(take.id.len())],
+            extension(&take.video_url)
+        ),
+        location: take.video_url.clone(),
+    })
+}
+
+/// A file checked and ready to upload for review, with the burn-in filter
+/// and provenance tags for its copy.
+pub struct PreparedUpload {
+    source: Source,
+    filter: Option<String>,
+    metadata: Vec<String>,
+}
+
+impl PreparedUpload {
+    /// The file name the platform will show.
+    pub fn name(&self) -> &str {
+        &self.source.name
+    }
+}
+
+/// Check a take can go to `platform` and prepare its upload.
+pub fn prepare_take(
+    conn: &Connection,
+    take_id: &str,
+    platform: ReviewPlatform,
+    copy: &ReviewCopy,
+) -> AppResult<PreparedUpload> {
+    let take = jobs::get_job(conn, take_id)?;
+    let source = take_source(conn, &take)?;
+    let scene = scenes::get_scene(conn, &take.scene_id)?;
+    confidential::ensure_remote_allowed(conn, &scene.project_id, platform.label())?;
+    let filter = copy_filter(
+        conn,
+        &scene.project_id,
+        vec![(0.0, scene_label(&scene))],
+        copy,
+    )?;
+    let metadata = provenance::for_scene(conn, &scene)?.ffmpeg_args();
+    Ok(PreparedUpload {
+        source,
+        filter,
+        metadata,
+    })
+}
+
+/// Store a take's review link and note the upload in its activity.
+pub fn record_take_upload(
+    conn: &Connection,
+    take_id: &str,
+    name: &str,
+    platform: ReviewPlatform,
+    url: &str,
+) -> AppResult<()> {
+    let take = jobs::get_job(conn, take_id)?;
+    conn.execute(
+        "UPDATE video_jobs SET review_url = ?2 WHERE id = ?1",
+        params![take_id, url],
+    )?;
+    activity::record_for_scene(
+        conn,
+        &take.scene_id,
+        "take",
+        take_id,
+        "shared",
+        &format!("Uploaded {} to {} for review", name, platform.label()),
+        &[],
+    )?;
+    Ok(())
+}
+
+/// Check a render of `project_id` at `path` can go to `platform` and
+/// prepare its upload.
+pub fn prepare_render(
+    conn: &Connection,
+    project_id: &str,
+    path: &str,
+    platform: ReviewPlatform,
+    copy: &ReviewCopy,
+) -> AppResult<PreparedUpload> {
+    if !Path::new(path).is_file() {
+        return Err(AppError::NotFound(format!("render {}", path)));
+    }
+    let project = projects::get_project(conn, project_id)?;
+    confidential::ensure_remote_allowed(conn, project_id, platform.label())?;
+    let filter = copy_filter(conn, project_id, render_scenes(conn, project_id)?, copy)?;
+    let metadata = provenance::for_project(conn, project_id)?.ffmpeg_args();
+    Ok(PreparedUpload {
+        source: Source {
+            name: format!("{}.{}", project.name, extension(path)),
+            location: path.to_string(),
+        },
+        filter,
+        metadata,
+    })
+}
+
+/// Note a render's review upload in the project's activity.
+pub fn record_render_upload(
+    conn: &Connection,
+    project_id: &str,
+    platform: ReviewPlatform,
+    url: &str,
+) -> AppResult<()> {
+    activity::record(
+        conn,
+        project_id,
+        "project",
+        project_id,
+        "shared",
+        &format!(
+            "Uploaded the render to {} for review: {}",
+            platform.label(),
+            url
+        ),
+        &[],
+    )?;
+    Ok(())
+}
+
+/// A render ready to be copied with burn-ins, from `prepare_copy`.
+pub struct CopyJob {
+    render_path: String,
+    filter: String,
+    metadata: Vec<String>,
+    binary: PathBuf,
+}
+
+/// Check a render can be copied with `copy`'s preset: it must burn
+/// something in, and the render file must still be there.
+pub fn prepare_copy(conn: &Connection, render_id: &str, copy: &ReviewCopy) -> AppResult<CopyJob> {
+    let render = publishing::get_render(conn, render_id)?;
+    let filter = copy_filter(
+        conn,
+        &render.project_id,
+        render_scenes(conn, &render.project_id)?,
+        copy,
+    )?
+    .ok_or_else(|| AppError::Invalid("choose a preset that burns something in".into()))?;
+    let metadata = provenance::for_project(conn, &render.project_id)?.ffmpeg_args();
+    let binary = frames::ffmpeg_binary(conn)?;
+    if !Path::new(&render.path).is_file() {
+        return Err(AppError::NotFound(format!("render file {}", render.path)));
+    }
+    Ok(CopyJob {
+        render_path: render.path,
+        filter,
+        metadata,
+        binary,
+    })
+}
+
+/// Burn the copy in and write it to `path`. Returns the written path.
+pub async fn write_copy(job: CopyJob, path: &str) -> AppResult<String> {
+    let out = export::resolve_output(path, "review.mp4")?;
+    let marked =
+        export_presets::burn_in(&job.binary, &job.render_path, &job.filter, &job.metadata).await?;
+    let moved = std::fs::copy(&marked, &out);
+    std::fs::remove_file(&marked).ok();
+    moved?;
+    Ok(out.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub fn get_review_settings(db: State<'_, Database>) -> AppResult<ReviewSettings> {
+    load(&db.conn())
+}
+
+/// Save the review settings. A token replaces the platform's stored one;
+/// an empty token removes it.
+pub fn save_settings(conn: &Connection, input: &ReviewSettingsInput) -> AppResult<ReviewSettings> {
+    let generic_url = input.generic_url.trim();
+    let mut v = Validator::new();
+    if !generic_url.is_empty() && !generic_url.starts_with("https://") {
+        v.error("genericUrl", "must be an https:// URL");
+    }
+    v.finish()?;
+    settings::set(conn, PLATFORM_SETTING, input.platform.as_str())?;
+    settings::set(conn, FRAMEIO_PROJECT_SETTING, input.frameio_project.trim())?;
+    settings::set(conn, GENERIC_URL_SETTING, generic_url)?;
+    match input.token.as_deref().map(str::trim) {
+        Some("") => secrets::delete(conn, &input.platform.token_secret())?,
+        Some(token) => secrets::set(conn, &input.platform.token_secret(), token)?,
+        None => {}
+    }
+    load(conn)
+}
+
+#[tauri::command]
+pub fn set_review_settings(
+    db: State<'_, Database>,
+    input: ReviewSettingsInput,
+) -> AppResult<ReviewSettings> {
+    save_settings(&db.conn(), &input)
+}
+
+/// Upload finished takes for review, one link each, and store the links
+/// on the takes. A take that fails doesn't stop the rest.
+#[tauri::command]
+pub async fn upload_takes_for_review(
+    app: AppHandle,
+    take_ids: Vec<String>,
+    copy: Option<ReviewCopy>,
+) -> AppResult<Vec<ReviewUpload>> {
+    let copy = copy.unwrap_or_default();
+    let (platform, token, target) = destination(&app.state::<Database>().conn())?;
+    let mut results = Vec::new();
+    for take_id in take_ids {
+        let prepared = prepare_take(&app.state::<Database>().conn(), &take_id, platform, &copy);
+        let (name, outcome) = match prepared {
+            Ok(prepared) => (
+                prepared.source.name.clone(),
+                send(
+                    &app,
+                    (platform, &token, &target),
+                    &prepared.source,
+                    prepared.filter.as_deref(),
+                    &prepared.metadata,
+                )
+                .await,
+            ),
+            Err(e) => (String::new(), Err(e)),
+        };
+        let outcome = outcome.and_then(|url| {
+            record_take_upload(
+                &app.state::<Database>().conn(),
+                &take_id,
+                &name,
+                platform,
+                &url,
+            )?;
+            Ok(url)
+        });
+        if outcome.is_ok() {
+            jobs::emit_job(&app, &take_id);
+        }
+        if let Err(e) = &outcome {
+            tracing::warn!(take_id = %take_id, error = %e, "review upload failed");
+        }
+        results.push(ReviewUpload {
+            take_id: Some(take_id),
+            name,
+            review_url: outcome.as_ref().ok().cloned(),
+            error: outcome.err().map(|e| e.to_string()),
+        });
+    }
+    Ok(results)
+}
+
+/// Upload a finished render (assembled by the frontend) for review.
+#[tauri::command]
+pub async fn upload_render_for_review(
+    app: AppHandle,
+    project_id: String,
+    path: String,
+    copy: Option<ReviewCopy>,
+) -> AppResult<ReviewUpload> {
+    let (platform, token, target, prepared) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let (platform, token, target) = destination(&conn)?;
+        let prepared = prepare_render(
+            &conn,
+            &project_id,
+            &path,
+            platform,
+            &copy.unwrap_or_default(),
+        )?;
+        (platform, token, target, prepared)
+    };
+    let url = send(
+        &app,
+        (platform, &token, &target),
+        &prepared.source,
+        prepared.filter.as_deref(),
+        &prepared.metadata,
+    )
+    .await?;
+    record_render_upload(&app.state::<Database>().conn(), &project_id, platform, &url)?;
+    Ok(ReviewUpload {
+        take_id: None,
+        name: prepared.source.name,
+        review_url: Some(url),
+        error: None,
+    })
+}
+
+/// Write a copy of a render with the preset's burn-ins (watermark,
+/// timecode) to `path`, for sending some other way. Returns the path.
+#[tauri::command]
+pub async fn export_review_copy(
+    app: AppHandle,
+    render_id: String,
+    copy: ReviewCopy,
+    path: String,
+) -> AppResult<String> {
+    let job = prepare_copy(&app.state::<Database>().conn(), &render_id, &copy)?;
+    write_copy(job, &path).await
+}
@@ -0,0 +1,291 @@
+//! Who may do what to a shared project. A member is a local profile (see
+//! `users`) or a LAN guest's device (see `lan`), with one of four roles:
+//! owners manage members and lock or archive the project, editors change
+//! it and run generations, commenters only comment, viewers only look.
+//!
+//! A project with no members isn't shared: this machine owns it. Once it
+//! has members, anyone not listed is a viewer.
+//!
+//! LAN guests get more than viewing only through an invite token (see
+//! `invites`). Device ids aren't secret, since every relayed change
+//! carries one, so listing a device can lower what its token grants but
+//! never raise it.
+//! With no profile signed in the machine itself acts, as owner; profiles
+//! are for telling people apart, not for locking the machine's owner out.
+//!
+//! Checks live in the command layer: `projects::ensure_unlocked` asks for
+//! an editor, so every command that edits a project or spends money on it
+//! does too.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::users;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::State;
+use ts_rs::TS;
+
+/// The role the host of a joined LAN session granted, by project.
+static SESSION_GRANT: RwLock<Option<(String, Role)>> = RwLock::new(None);
+
+/// In increasing order of what they allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum Role {
+    Viewer,
+    Commenter,
+    Editor,
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Commenter => "commenter",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+
+    pub fn parse(role: &str) -> Role {
+        match role {
+            "owner" => Role::Owner,
+            "editor" => Role::Editor,
+            "commenter" => Role::Commenter,
+            _ => Role::Viewer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProjectMember {
+    pub project_id: String,
+    /// `"user"` for a local profile, `"device"` for a LAN guest.
+    pub kind: String,
+    pub member_id: String,
+    pub name: String,
+    pub role: Role,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MemberInput {
+    pub kind: String,
+    pub member_id: String,
+    #[serde(default)]
+    pub name: String,
+    /// None removes the member.
+    pub role: Option<Role>,
+}
+
+/// Record what the host of the joined LAN session allows here, or clear
+/// it when the session ends.
+pub fn set_session_grant(grant: Option<(String, Role)>) {
+    if let Ok(mut current) = SESSION_GRANT.write() {
+        *current = grant;
+    }
+}
+
+fn session_grant(project_id: &str) -> Option<Role> {
+    SESSION_GRANT
+        .read()
+        .ok()?
+        .as_ref()
+        .filter(|(id, _)| id == project_id)
+        .map(|(_, role)| *role)
+}
+
+fn list(conn: &Connection, project_id: &str) -> AppResult<Vec<ProjectMember>> {
+    let mut stmt = conn.prepare(
+        "SELECT project_id, kind, member_id, name, role, created_at FROM project_members
+         WHERE project_id = ?1 ORDER BY created_at, name",
+    )?;
+    let rows = stmt.query_map([project_id], |r| {
+        Ok(ProjectMember {
+            project_id: r.get(0)?,
+            kind: r.get(1)?,
+            member_id: r.get(2)?,
+            name: r.get(3)?,
+            role: Role::parse(&r.get::<_, String>(4)?),
+            created_at: r.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn is_shared(conn: &Connection, project_id: &str) -> AppResult<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM project_members WHERE project_id = ?1)",
+        [project_id],
+        |r| r.get(0),
+    )?)
+}
+
+/// The role the project lists for a member, if it lists them; `kind` is
+/// `"user"` or `"device"`.
+pub fn listed_role(
+    conn: &Connection,
+    project_id: &str,
+    kind: &str,
+    member_id: &str,
+) -> AppResult<Option<Role>> {
+    let role: Option<String> = conn
+        .query_row(
+            "SELECT role FROM project_members
+             WHERE project_id = ?1 AND kind = ?2 AND member_id = ?3",
+            params![project_id, kind, member_id],
+            |r| r.get(0),
+        )
+        .optional()?;
+    Ok(role.map(|r| Role::parse(&r)))
+}
+
+/// A member's role on the project; `kind` is `"user"` or `"device"`.
+/// Devices are viewers unless listed; see `lan` for what guests get.
+pub fn role_of(
+    conn: &Connection,
+    project_id: &str,
+    kind: &str,
+    member_id: &str,
+) -> AppResult<Role> {
+    if !is_shared(conn, project_id)? {
+        return Ok(if kind == "device" {
+            Role::Viewer
+        } else {
+            Role::Owner
+        });
+    }
+    Ok(listed_role(conn, project_id, kind, member_id)?.unwrap_or(Role::Viewer))
+}
+
+/// The role of whoever is using this instance: the LAN host's grant for
+/// a joined session's project, else the signed-in profile's.
+pub fn current_role(conn: &Connection, project_id: &str) -> AppResult<Role> {
+    if let Some(role) = session_grant(project_id) {
+        return Ok(role);
+    }
+    match users::active() {
+        Some(user_id) => role_of(conn, project_id, "user", &user_id),
+        None => Ok(Role::Owner),
+    }
+}
+
+/// Refuse unless the current role is at least `needed`.
+pub fn ensure_role(conn: &Connection, project_id: &str, needed: Role) -> AppResult<()> {
+    let role = current_role(conn, project_id)?;
+    if role < needed {
+        return Err(AppError::Forbidden(format!(
+            "this needs {} access to the project; you are a {}",
+            needed.as_str(),
+            role.as_str()
+        )));
+    }
+    Ok(())
+}
+
+/// A project's members, in the order they were added.
+pub fn members(conn: &Connection, project_id: &str) -> AppResult<Vec<ProjectMember>> {
+    projects::get_project(conn, project_id)?;
+    list(conn, project_id)
+}
+
+#[tauri::command]
+pub fn list_project_members(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<ProjectMember>> {
+    members(&db.conn(), &project_id)
+}
+
+/// The current role on a project that exists.
+pub fn my_role(conn: &Connection, project_id: &str) -> AppResult<Role> {
+    projects::get_project(conn, project_id)?;
+    current_role(conn, project_id)
+}
+
+#[tauri::command]
+pub fn get_my_role(db: State<'_, Database>, project_id: String) -> AppResult<Role> {
+    my_role(&db.conn(), &project_id)
+}
+
+/// Add, change or remove a member; owners only. Sharing a project for the
+/// first time makes the signed-in profile its owner, so it can't lock
+/// itself out, and once profiles are members the last owner can't be
+/// removed or demoted.
+pub fn set_member(
+    conn: &Connection,
+    project_id: &str,
+    input: &MemberInput,
+) -> AppResult<Vec<ProjectMember>> {
+    if !["user", "device"].contains(&input.kind.as_str()) {
+        return Err(AppError::Invalid(format!(
+            "unknown member kind '{}'",
+            input.kind
+        )));
+    }
+    projects::get_project(conn, project_id)?;
+    ensure_role(conn, project_id, Role::Owner)?;
+    let tx = conn.unchecked_transaction()?;
+    if !is_shared(&tx, project_id)? {
+        if let Some(user_id) = users::active() {
+            let user = users::get_user(&tx, &user_id)?;
+            tx.execute(
+                "INSERT INTO project_members (project_id, kind, member_id, name, role, created_at)
+                 VALUES (?1, 'user', ?2, ?3, 'owner', ?4)",
+                params![project_id, user_id, user.name, clock::now()],
+            )?;
+        }
+    }
+    match input.role {
+        Some(role) => {
+            tx.execute(
+                "INSERT INTO project_members (project_id, kind, member_id, name, role, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(project_id, kind, member_id) DO UPDATE SET
+                    name = CASE WHEN excluded.name = '' THEN name ELSE excluded.name END,
+                    role = excluded.role",
+                params![
+                    project_id,
+                    input.kind,
+                    input.member_id,
+                    input.name.trim(),
+                    role.as_str(),
+                    clock::now()
+                ],
+            )?;
+        }
+        None => {
+            tx.execute(
+                "DELETE FROM project_members WHERE project_id = ?1 AND kind = ?2 AND member_id = ?3",
+                params![project_id, input.kind, input.member_id],
+            )?;
+        }
+    }
+    let members = list(&tx, project_id)?;
+    let has_users = members.iter().any(|m| m.kind == "user");
+    if has_users && !members.iter().any(|m| m.role == Role::Owner) {
+        return Err(AppError::Invalid(
+            "a shared project needs at least one owner".into(),
+        ));
+    }
+    tx.commit()?;
+    Ok(members)
+}
+
+#[tauri::command]
+pub fn set_project_member(
+    db: State<'_, Database>,
+    project_id: String,
+    input: MemberInput,
+) -> AppResult<Vec<ProjectMember>> {
+    set_member(&db.conn(), &project_id, &input)
+}
@@ -0,0 +1,211 @@
+//! Runtime estimates from the script alone, long before any video exists.
+//!
+//! A scene runs for its set duration or as long as its dialog takes to
+//! speak, whichever is longer. Dialog time comes from the word count at the
+//! configured words per minute, stretched or tightened by the project
+//! genre's pacing multiplier (a drama breathes more than an action film).
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export;
+use crate::scenes;
+use crate::settings;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::State;
+use ts_rs::TS;
+
+const WPM_SETTING: &str = "runtime.wpm";
+const PACING_SETTING: &str = "runtime.pacing";
+
+/// Typical screen dialog delivery.
+pub const DEFAULT_WPM: f64 = 150.0;
+
+/// Built-in pacing per genre, overridable in settings.
+const DEFAULT_PACING: &[(&str, f64)] = &[
+    ("action", 0.9),
+    ("comedy", 0.95),
+    ("thriller", 0.95),
+    ("sci-fi", 1.0),
+    ("documentary", 1.0),
+    ("horror", 1.1),
+    ("drama", 1.15),
+    ("romance", 1.15),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RuntimeSettings {
+    pub words_per_minute: f64,
+    /// Lower-case genre to multiplier applied to dialog time.
+    pub pacing: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneRuntime {
+    pub scene_id: String,
+    #[ts(type = "number")]
+    pub scene_number: i64,
+    pub title: String,
+    /// The scene's set duration, in seconds.
+    #[ts(type = "number")]
+    pub duration: i64,
+    pub dialog_words: usize,
+    /// Time to speak the dialog at the project's pacing, in seconds.
+    pub dialog_seconds: f64,
+    pub estimated_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RuntimeEstimate {
+    pub project_id: String,
+    pub words_per_minute: f64,
+    /// Multiplier used for the project's genre; 1.0 when it has none.
+    pub pacing: f64,
+    pub scenes: Vec<SceneRuntime>,
+    pub total_seconds: f64,
+}
+
+pub fn load_settings(conn: &Connection) -> AppResult<RuntimeSettings> {
+    let words_per_minute = settings::get(conn, WPM_SETTING)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_WPM);
+    let pacing = match settings::get(conn, PACING_SETTING)? {
+        Some(json) => serde_json::from_str(&json)?,
+        None => DEFAULT_PACING
+            .iter()
+            .map(|(g, m)| (g.to_string(), *m))
+            .collect(),
+    };
+    Ok(RuntimeSettings {
+        words_per_minute,
+        pacing,
+    })
+}
+
+/// Words actually spoken: a leading `NAME:` speaker tag and bracketed
+/// directions like `(beat)` are not counted.
+pub fn spoken_words(dialog: &str) -> usize {
+    dialog
+        .lines()
+        .map(|line| {
+            let line = match line.split_once(':') {
+                Some((speaker, rest)) if !speaker.trim().is_empty() && speaker.len() <= 40 => rest,
+                _ => line,
+            };
+            let mut depth = 0usize;
+            let mut spoken = String::with_capacity(line.len());
+            for c in line.chars() {
+                match c {
+                    '(' | '[' => depth += 1,
+                    ')' | ']' => depth = depth.saturating_sub(1),
+                    _ if depth == 0 => spoken.push(c),
+                    _ => {}
+                }
+            }
+            spoken
+                .split_whitespace()
+                .filter(|w| w.chars().any(char::is_alphanumeric))
+                .count()
+        })
+        .sum()
+}
+
+fn round_tenth(seconds: f64) -> f64 {
+    (seconds * 10.0).round() / 10.0
+}
+
+pub fn estimate(
+    conn: &Connection,
+    project_id: &str,
+    words_per_minute: Option<f64>,
+) -> AppResult<RuntimeEstimate> {
+    let project = export::load_project(conn, project_id)?;
+    let config = load_settings(conn)?;
+    let words_per_minute = words_per_minute.unwrap_or(config.words_per_minute);
+    if words_per_minute <= 0.0 {
+        return Err(AppError::Invalid(
+            "words per minute must be positive".into(),
+        ));
+    }
+    let pacing = config
+        .pacing
+        .get(project.genre.trim().to_lowercase().as_str())
+        .copied()
+        .unwrap_or(1.0);
+
+    let scenes: Vec<SceneRuntime> = scenes::list_for_project(conn, project_id)?
+        .into_iter()
+        .map(|s| {
+            let dialog_words = spoken_words(&s.dialog);
+            let dialog_seconds = dialog_words as f64 / words_per_minute * 60.0 * pacing;
+            SceneRuntime {
+                estimated_seconds: round_tenth(dialog_seconds.max(s.duration as f64)),
+                dialog_seconds: round_tenth(dialog_seconds),
+                scene_id: s.id,
+                scene_number: s.scene_number,
+                title: s.title,
+                duration: s.duration,
+                dialog_words,
+            }
+        })
+        .collect();
+    let total_seconds = round_tenth(scenes.iter().map(|s| s.estimated_seconds).sum());
+    Ok(RuntimeEstimate {
+        project_id: project_id.to_string(),
+        words_per_minute,
+        pacing,
+        scenes,
+        total_seconds,
+    })
+}
+
+/// Per-scene and total runtime for a project. `words_per_minute` overrides
+/// the saved setting for this estimate only.
+#[tauri::command]
+pub fn estimate_runtime(
+    db: State<'_, Database>,
+    project_id: String,
+    words_per_minute: Option<f64>,
+) -> AppResult<RuntimeEstimate> {
+    estimate(&db.conn(), &project_id, words_per_minute)
+}
+
+#[tauri::command]
+pub fn get_runtime_settings(db: State<'_, Database>) -> AppResult<RuntimeSettings> {
+    load_settings(&db.conn())
+}
+
+/// Save the reading speed and per-genre pacing. Genres are matched
+/// case-insensitively; blank ones are dropped.
+pub fn save_settings(conn: &Connection, input: &RuntimeSettings) -> AppResult<()> {
+    if !(60.0..=400.0).contains(&input.words_per_minute) {
+        return Err(AppError::Invalid("words per minute must be 60–400".into()));
+    }
+    let mut pacing = BTreeMap::new();
+    for (genre, multiplier) in &input.pacing {
+        let genre = genre.trim().to_lowercase();
+        if genre.is_empty() {
+            continue;
+        }
+        if !(0.25..=4.0).contains(multiplier) {
+            return Err(AppError::Invalid(format!(
+                "pacing for {} must be 0.25–4.0",
+                genre
+            )));
+        }
+        pacing.insert(genre, *multiplier);
+    }
+    settings::set(conn, WPM_SETTING, &input.words_per_minute.to_string())?;
+    settings::set(conn, PACING_SETTING, &serde_json::to_string(&pacing)?)
+}
+
+#[tauri::command]
+pub fn save_runtime_settings(db: State<'_, Database>, input: RuntimeSettings) -> AppResult<()> {
+    save_settings(&db.conn(), &input)
+}
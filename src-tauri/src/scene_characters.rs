@@ -0,0 +1,369 @@
+//! Who appears in each scene (`scene_characters`, migration 31), with the
+//! part they play there and how prominent they are. Replaces the old
+//! `characters_json` list, so "every scene featuring X" is a plain query.
+//! A scene's cast is also on `Scene::cast`, in billing order.
+
+use crate::activity::{self, FieldChange};
+use crate::characters::{self, CharacterInput};
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::scenes::{self, Scene, SCENE_COLUMNS};
+use crate::validation::{Validate, Validator, MAX_NAME_LEN};
+use crate::workflow;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// How prominent a character is in a scene, stored in
+/// `scene_characters.importance`.
+pub mod importance {
+    pub const LEAD: &str = "lead";
+    pub const SUPPORTING: &str = "supporting";
+    pub const BACKGROUND: &str = "background";
+
+    pub const ALL: &[&str] = &[LEAD, SUPPORTING, BACKGROUND];
+}
+
+/// One character in a scene's cast.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CastMember {
+    pub character_id: String,
+    /// The part they play in this scene, e.g. "witness"; free text.
+    #[serde(default)]
+    pub role: String,
+    #[serde(default = "default_importance")]
+    pub importance: String,
+}
+
+fn default_importance() -> String {
+    importance::SUPPORTING.to_string()
+}
+
+impl CastMember {
+    pub fn new(character_id: String) -> Self {
+        Self {
+            character_id,
+            role: String::new(),
+            importance: default_importance(),
+        }
+    }
+}
+
+impl Validate for CastMember {
+    fn validate(&self, v: &mut Validator) {
+        v.max_len("role", self.role.trim(), MAX_NAME_LEN).one_of(
+            "importance",
+            &self.importance,
+            importance::ALL,
+        );
+    }
+}
+
+/// A cast entry with the character's name, for listing.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneCharacter {
+    pub scene_id: String,
+    pub character_id: String,
+    pub name: String,
+    pub role: String,
+    pub importance: String,
+    #[ts(type = "number")]
+    pub position: i64,
+}
+
+fn row_to_entry(row: &Row) -> rusqlite::Result<SceneCharacter> {
+    Ok(SceneCharacter {
+        scene_id: row.get(0)?,
+        character_id: row.get(1)?,
+        name: row.get(2)?,
+        role: row.get(3)?,
+        importance: row.get(4)?,
+        position: row.get(5)?,
+    })
+}
+
+pub fn list_for_scene(conn: &Connection, scene_id: &str) -> AppResult<Vec<SceneCharacter>> {
+    let mut stmt = conn.prepare(
+        "SELECT sc.scene_id, sc.character_id, c.name, sc.role, sc.importance, sc.position
+         FROM scene_characters sc JOIN characters c ON c.id = sc.character_id
+         WHERE sc.scene_id = ?1 ORDER BY sc.position",
+    )?;
+    let cast = stmt
+        .query_map([scene_id], row_to_entry)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(cast)
+}
+
+/// A character in the project with `entry` as its id, else its name (any
+/// case).
+pub fn find(conn: &Connection, project_id: &str, entry: &str) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM characters WHERE project_id = ?1
+                AND (id = ?2 OR name = ?2 COLLATE NOCASE)
+             ORDER BY id = ?2 DESC, created_at, rowid LIMIT 1",
+            params![project_id, entry.trim()],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// The character a cast entry names (see `find`), or a new character by
+/// that name.
+pub fn resolve(conn: &Connection, project_id: &str, entry: &str) -> AppResult<String> {
+    if let Some(id) = find(conn, project_id, entry)? {
+        return Ok(id);
+    }
+    Ok(characters::save(
+        conn,
+        &CharacterInput {
+            id: None,
+            project_id: project_id.to_string(),
+            name: entry.trim().to_string(),
+            description: String::new(),
+            photo_data: String::new(),
+            expected_version: None,
+        },
+    )?
+    .id)
+}
+
+/// Make `members` a scene's whole cast, in that order. Rows that don't
+/// change are left alone, so they don't bump the scene's version or sync.
+pub fn replace(conn: &Connection, scene: &Scene, members: &[CastMember]) -> AppResult<()> {
+    let mut v = Validator::new();
+    for (i, m) in members.iter().enumerate() {
+        v.nested(&format!("cast.{}", i), m);
+    }
+    v.finish()?;
+    let keep: Vec<&str> = members.iter().map(|m| m.character_id.as_str()).collect();
+    conn.execute(
+        "DELETE FROM scene_characters WHERE scene_id = ?1
+         AND character_id NOT IN (SELECT value FROM json_each(?2))",
+        params![scene.id, serde_json::to_string(&keep)?],
+    )?;
+    for (position, m) in members.iter().enumerate() {
+        let character = characters::get_character(conn, &m.character_id)?;
+        if character.project_id != scene.project_id {
+            return Err(AppError::Invalid(format!(
+                "character {} belongs to another project",
+                character.name
+            )));
+        }
+        conn.execute(
+            "INSERT INTO scene_characters (id, project_id, scene_id, character_id, role,
+                importance, position, created_at)
+             VALUES (?1 || ':' || ?2, ?3, ?1, ?2, ?4, ?5, ?6, ?7)
+             ON CONFLICT(scene_id, character_id) DO UPDATE SET role = excluded.role,
+                importance = excluded.importance, position = excluded.position
+             WHERE role IS NOT excluded.role OR importance IS NOT excluded.importance
+                OR position IS NOT excluded.position",
+            params![
+                scene.id,
+                m.character_id,
+                scene.project_id,
+                m.role.trim(),
+                m.importance,
+                position as i64,
+                clock::now()
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Set a scene's cast from character ids or names (see `resolve`), keeping
+/// the role and importance of anyone already in it.
+pub fn set_cast(conn: &Connection, scene: &Scene, entries: &[String]) -> AppResult<()> {
+    let mut members: Vec<CastMember> = Vec::new();
+    for entry in entries.iter().filter(|e| !e.trim().is_empty()) {
+        let id = resolve(conn, &scene.project_id, entry)?;
+        if members.iter().any(|m| m.character_id == id) {
+            continue;
+        }
+        members.push(
+            scene
+                .cast
+                .iter()
+                .find(|m| m.character_id == id)
+                .cloned()
+                .unwrap_or_else(|| CastMember::new(id)),
+        );
+    }
+    replace(conn, scene, &members)
+}
+
+/// Character names for a list of ids, for activity entries and prompts.
+pub fn names(conn: &Connection, ids: &[String]) -> AppResult<Vec<String>> {
+    ids.iter()
+        .map(|id| {
+            Ok(conn
+                .query_row("SELECT name FROM characters WHERE id = ?1", [id], |r| {
+                    r.get(0)
+                })
+                .optional()?
+                .unwrap_or_else(|| id.clone()))
+        })
+        .collect()
+}
+
+/// A scene's cast, in billing order.
+pub fn cast(conn: &Connection, scene_id: &str) -> AppResult<Vec<SceneCharacter>> {
+    scenes::get_scene(conn, scene_id)?;
+    list_for_scene(conn, scene_id)
+}
+
+#[tauri::command]
+pub fn list_scene_cast(
+    db: State<'_, Database>,
+    scene_id: String,
+) -> AppResult<Vec<SceneCharacter>> {
+    cast(&db.conn(), &scene_id)
+}
+
+/// Add a character to a scene, or change their role or importance there.
+/// New cast members go to the end of the billing.
+pub fn set_member(
+    conn: &Connection,
+    scene_id: &str,
+    member: &CastMember,
+) -> AppResult<Vec<SceneCharacter>> {
+    let tx = conn.unchecked_transaction()?;
+    workflow::ensure_editable(&tx, scene_id)?;
+    let scene = scenes::get_scene(&tx, scene_id)?;
+    let character = characters::get_character(&tx, &member.character_id)?;
+    let mut cast = scene.cast.clone();
+    let before = cast
+        .iter()
+        .find(|m| m.character_id == member.character_id)
+        .cloned();
+    match cast
+        .iter_mut()
+        .find(|m| m.character_id == member.character_id)
+    {
+        Some(m) => *m = member.clone(),
+        None => cast.push(member.clone()),
+    }
+    replace(&tx, &scene, &cast)?;
+    let describe = |m: &CastMember| match m.role.trim() {
+        "" => m.importance.clone(),
+        role => format!("{} ({})", role, m.importance),
+    };
+    activity::record(
+        &tx,
+        &scene.project_id,
+        "scene",
+        &scene.id,
+        "updated",
+        &format!("Cast {} in scene {}", character.name, scene.scene_number),
+        &[FieldChange {
+            field: format!("cast.{}", character.name),
+            old: before.as_ref().map(describe).unwrap_or_default(),
+            new: describe(member),
+        }],
+    )?;
+    let cast = list_for_scene(&tx, &scene.id)?;
+    tx.commit()?;
+    Ok(cast)
+}
+
+#[tauri::command]
+pub fn set_scene_character(
+    db: State<'_, Database>,
+    scene_id: String,
+    member: CastMember,
+) -> AppResult<Vec<SceneCharacter>> {
+    set_member(&db.conn(), &scene_id, &member)
+}
+
+pub fn remove(
+    conn: &Connection,
+    scene_id: &str,
+    character_id: &str,
+) -> AppResult<Vec<SceneCharacter>> {
+    let tx = conn.unchecked_transaction()?;
+    workflow::ensure_editable(&tx, scene_id)?;
+    let scene = scenes::get_scene(&tx, scene_id)?;
+    let cast: Vec<CastMember> = scene
+        .cast
+        .iter()
+        .filter(|m| m.character_id != character_id)
+        .cloned()
+        .collect();
+    if cast.len() == scene.cast.len() {
+        return Err(AppError::NotFound(format!(
+            "character {} in scene {}",
+            character_id, scene.scene_number
+        )));
+    }
+    replace(&tx, &scene, &cast)?;
+    let name = names(&tx, &[character_id.to_string()])?.remove(0);
+    activity::record(
+        &tx,
+        &scene.project_id,
+        "scene",
+        &scene.id,
+        "updated",
+        &format!("Removed {} from scene {}", name, scene.scene_number),
+        &[FieldChange {
+            field: "characters".into(),
+            old: names(&tx, &scene.characters)?.join(", "),
+            new: names(
+                &tx,
+                &cast
+                    .iter()
+                    .map(|m| m.character_id.clone())
+                    .collect::<Vec<_>>(),
+            )?
+            .join(", "),
+        }],
+    )?;
+    let cast = list_for_scene(&tx, &scene.id)?;
+    tx.commit()?;
+    Ok(cast)
+}
+
+#[tauri::command]
+pub fn remove_scene_character(
+    db: State<'_, Database>,
+    scene_id: String,
+    character_id: String,
+) -> AppResult<Vec<SceneCharacter>> {
+    remove(&db.conn(), &scene_id, &character_id)
+}
+
+/// Every scene a character appears in, in board order; `importance`
+/// narrows it to e.g. the scenes they lead.
+pub fn featuring(
+    conn: &Connection,
+    character_id: &str,
+    importance: Option<&str>,
+) -> AppResult<Vec<Scene>> {
+    characters::get_character(conn, character_id)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scenes WHERE id IN (
+            SELECT scene_id FROM scene_characters
+            WHERE character_id = ?1 AND (?2 IS NULL OR importance = ?2))
+         ORDER BY order_key, sort_order, scene_number",
+        SCENE_COLUMNS
+    ))?;
+    let scenes = stmt
+        .query_map(params![character_id, importance], scenes::row_to_scene)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(scenes)
+}
+
+#[tauri::command]
+pub fn list_scenes_featuring(
+    db: State<'_, Database>,
+    character_id: String,
+    importance: Option<String>,
+) -> AppResult<Vec<Scene>> {
+    featuring(&db.conn(), &character_id, importance.as_deref())
+}
@@ -0,0 +1,184 @@
+//! Save a project together with related scenes and characters in one
+//! transaction, so an editor session that touched several of them is
+//! stored completely or not at all.
+//!
+//! New scenes and characters may carry a client `key` in place of an id;
+//! `links` can name either, which lets a new character be cast in a new
+//! scene in the same save.
+
+use crate::characters::{self, Character, CharacterInput};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects::{self, ProjectInput, ProjectSummary};
+use crate::scene_characters;
+use crate::scenes::{self, Scene, SceneInput};
+use crate::workflow;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphScene {
+    /// Client-side handle for a scene without an id yet.
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(flatten)]
+    pub input: SceneInput,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphCharacter {
+    /// Client-side handle for a character without an id yet.
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(flatten)]
+    pub input: CharacterInput,
+}
+
+/// Casts a character in a scene. Each side is an id or a `key` from the
+/// same save. Every scene named in `links` gets exactly the linked cast.
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphLink {
+    pub scene: String,
+    pub character: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneGraph {
+    #[serde(default)]
+    pub project: Option<ProjectInput>,
+    #[serde(default)]
+    pub characters: Vec<GraphCharacter>,
+    #[serde(default)]
+    pub scenes: Vec<GraphScene>,
+    #[serde(default)]
+    pub links: Vec<GraphLink>,
+    #[serde(default)]
+    pub deleted_scene_ids: Vec<String>,
+    #[serde(default)]
+    pub deleted_character_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SavedGraph {
+    pub project: ProjectSummary,
+    pub characters: Vec<Character>,
+    pub scenes: Vec<Scene>,
+    /// Client key to the id it was saved under.
+    pub keys: HashMap<String, String>,
+}
+
+fn resolve<'a>(keys: &'a HashMap<String, String>, handle: &'a str) -> &'a str {
+    keys.get(handle).map(String::as_str).unwrap_or(handle)
+}
+
+fn save_graph(conn: &Connection, project_id: &str, graph: &SceneGraph) -> AppResult<SavedGraph> {
+    let mut project = projects::get_project(conn, project_id)?;
+    if let Some(input) = &graph.project {
+        project = projects::update(conn, project_id, input)?;
+    }
+
+    let mut keys = HashMap::new();
+    let mut saved_characters = Vec::new();
+    for c in &graph.characters {
+        if c.input.project_id != project_id {
+            return Err(AppError::Invalid(format!(
+                "character {} belongs to another project",
+                c.input.name
+            )));
+        }
+        let saved = characters::save(conn, &c.input)?;
+        if let Some(key) = &c.key {
+            keys.insert(key.clone(), saved.id.clone());
+        }
+        saved_characters.push(saved);
+    }
+
+    let mut saved_scenes = Vec::new();
+    for s in &graph.scenes {
+        if s.input.project_id != project_id {
+            return Err(AppError::Invalid(format!(
+                "scene {} belongs to another project",
+                s.input.scene_number
+            )));
+        }
+        let saved = scenes::save(conn, &s.input)?;
+        if let Some(key) = &s.key {
+            keys.insert(key.clone(), saved.id.clone());
+        }
+        saved_scenes.push(saved);
+    }
+
+    let mut casts: Vec<(String, Vec<String>)> = Vec::new();
+    for link in &graph.links {
+        let scene_id = resolve(&keys, &link.scene).to_string();
+        let character_id = resolve(&keys, &link.character).to_string();
+        let character = characters::get_character(conn, &character_id)?;
+        if character.project_id != project_id {
+            return Err(AppError::Invalid(format!(
+                "character {} belongs to another project",
+                character.name
+            )));
+        }
+        match casts.iter_mut().find(|(id, _)| *id == scene_id) {
+            Some((_, cast)) if !cast.contains(&character_id) => cast.push(character_id),
+            Some(_) => {}
+            None => casts.push((scene_id, vec![character_id])),
+        }
+    }
+    for (scene_id, cast) in casts {
+        workflow::ensure_editable(conn, &scene_id)?;
+        let scene = scenes::get_scene(conn, &scene_id)?;
+        if scene.project_id != project_id {
+            return Err(AppError::NotFound(format!("scene {}", scene_id)));
+        }
+        scene_characters::set_cast(conn, &scene, &cast)?;
+        if let Some(s) = saved_scenes.iter_mut().find(|s| s.id == scene_id) {
+            *s = scenes::get_scene(conn, &scene_id)?;
+        }
+    }
+
+    for id in &graph.deleted_scene_ids {
+        scenes::delete(conn, id)?;
+        saved_scenes.retain(|s| s.id != *id);
+    }
+    for id in &graph.deleted_character_ids {
+        characters::delete(conn, id)?;
+        saved_characters.retain(|c| c.id != *id);
+    }
+
+    Ok(SavedGraph {
+        project,
+        characters: saved_characters,
+        scenes: saved_scenes,
+        keys,
+    })
+}
+
+/// Apply every change in `graph` to the project atomically. Any failure,
+/// including a stale `expectedVersion`, rolls the whole save back.
+pub fn save(conn: &Connection, project_id: &str, graph: &SceneGraph) -> AppResult<SavedGraph> {
+    let tx = conn.unchecked_transaction()?;
+    projects::ensure_unlocked(&tx, project_id)?;
+    let saved = save_graph(&tx, project_id, graph)?;
+    tx.commit()?;
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn save_scene_graph(
+    db: State<'_, Database>,
+    project_id: String,
+    graph: SceneGraph,
+) -> AppResult<SavedGraph> {
+    save(&db.conn(), &project_id, &graph)
+}
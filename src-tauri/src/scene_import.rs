@@ -0,0 +1,448 @@
+//! Importing scene lists made in other tools: a CSV from a spreadsheet, or
+//! JSON exported from Notion, Airtable or a script. Nothing about the
+//! source's shape is assumed. `preview_scene_import` reads the file into
+//! rows and columns and suggests which column feeds which scene field;
+//! the user adjusts that mapping and `import_scenes` applies it. Rows that
+//! don't make a valid scene are skipped and reported, not fatal.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use crate::scenes::{self, SceneInput};
+use crate::validation::MAX_SCENE_DURATION;
+use crate::vocabulary::{self, VocabularyKind};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::State;
+use ts_rs::TS;
+
+/// Scene fields a column can feed, with column names that suggest them.
+const TARGETS: &[(&str, &[&str])] = &[
+    (
+        "sceneNumber",
+        &["scene", "scenenumber", "number", "no", "num", "sc"],
+    ),
+    ("title", &["title", "name", "heading", "scenetitle"]),
+    (
+        "description",
+        &["description", "summary", "action", "synopsis", "notes"],
+    ),
+    ("prompt", &["prompt", "videoprompt", "shotprompt"]),
+    ("dialog", &["dialog", "dialogue", "lines", "script"]),
+    ("location", &["location", "setting", "slugline", "place"]),
+    ("duration", &["duration", "length", "seconds", "runtime"]),
+    (
+        "cameraAngle",
+        &["cameraangle", "camera", "shot", "shottype", "angle"],
+    ),
+    ("lighting", &["lighting", "light", "mood"]),
+    ("characters", &["characters", "cast", "who"]),
+];
+/// Rows returned by a preview.
+const PREVIEW_ROWS: usize = 20;
+
+/// A source file as rows under named columns.
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Which source column feeds each scene field, by the field names in
+/// `TARGETS`. Unmapped fields take their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ImportMapping {
+    pub fields: BTreeMap<String, String>,
+    /// What separates names in the characters column; `,` and `;` if
+    /// empty.
+    #[serde(default)]
+    pub character_separator: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ImportPreview {
+    pub table: Table,
+    /// Total rows; `table` holds the first few.
+    pub row_count: usize,
+    pub suggested: ImportMapping,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    /// 1-based, not counting the header.
+    pub row: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ImportReport {
+    pub created: Vec<String>,
+    pub skipped: Vec<SkippedRow>,
+    /// Values that couldn't be used but didn't sink the row, such as an
+    /// unknown camera angle.
+    pub warnings: Vec<String>,
+}
+
+/// Split CSV text into records, with quoted fields, doubled quotes and
+/// line breaks inside quotes.
+fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+        } else if c == '"' && field.is_empty() {
+            quoted = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            records.push(std::mem::take(&mut record));
+        } else {
+            field.push(c);
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// A JSON value as cell text: lists are joined, and objects such as
+/// Notion's `{"name": ...}` selects give their name.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(cell)
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Object(map) => ["name", "title", "plain_text", "content", "value"]
+            .iter()
+            .find_map(|k| map.get(*k))
+            .map(cell)
+            .unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+/// The records in a JSON export: a top-level array, or the first array
+/// under an object (`records`, `results`, `scenes`…). Airtable's `fields`
+/// and Notion's `properties` are unwrapped.
+fn json_table(text: &str) -> AppResult<Table> {
+    let root: Value = serde_json::from_str(text)
+        .map_err(|e| AppError::Invalid(format!("not valid JSON: {}", e)))?;
+    let records = match &root {
+        Value::Array(items) => items.clone(),
+        Value::Object(map) => map
+            .values()
+            .find_map(|v| v.as_array().cloned())
+            .ok_or_else(|| AppError::Invalid("the JSON holds no list of scenes".into()))?,
+        _ => return Err(AppError::Invalid("the JSON holds no list of scenes".into())),
+    };
+    let objects: Vec<serde_json::Map<String, Value>> = records
+        .into_iter()
+        .filter_map(|r| match r {
+            Value::Object(mut map) => Some(
+                match map.remove("fields").or_else(|| map.remove("properties")) {
+                    Some(Value::Object(inner)) => inner,
+                    _ => map,
+                },
+            ),
+            _ => None,
+        })
+        .collect();
+    let mut table = Table::default();
+    for object in &objects {
+        for key in object.keys() {
+            if !table.columns.contains(key) {
+                table.columns.push(key.clone());
+            }
+        }
+    }
+    table.rows = objects
+        .iter()
+        .map(|o| {
+            table
+                .columns
+                .iter()
+                .map(|c| o.get(c).map(cell).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    Ok(table)
+}
+
+impl Table {
+    /// Read CSV (comma, semicolon or tab separated) or JSON, whichever the
+    /// text is.
+    pub fn parse(text: &str) -> AppResult<Table> {
+        let text = text.trim_start_matches('\u{feff}');
+        if matches!(text.trim_start().chars().next(), Some('[' | '{')) {
+            return json_table(text);
+        }
+        let header = text.lines().next().unwrap_or_default();
+        let delimiter = [',', ';', '\t']
+            .into_iter()
+            .max_by_key(|d| header.matches(*d).count())
+            .unwrap_or(',');
+        let mut records = parse_csv(text, delimiter).into_iter();
+        let columns: Vec<String> = records
+            .next()
+            .ok_or_else(|| AppError::Invalid("the file is empty".into()))?
+            .into_iter()
+            .map(|c| c.trim().to_string())
+            .collect();
+        let rows = records
+            .map(|mut r| {
+                r.resize(columns.len(), String::new());
+                r
+            })
+            .collect();
+        Ok(Table { columns, rows })
+    }
+
+    fn value<'a>(&self, row: &'a [String], column: Option<&String>) -> &'a str {
+        column
+            .and_then(|c| self.columns.iter().position(|x| x == c))
+            .and_then(|i| row.get(i))
+            .map_or("", |v| v.trim())
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Map each scene field to the first unused column whose name matches one
+/// of its synonyms.
+pub fn suggest(columns: &[String]) -> ImportMapping {
+    let mut mapping = ImportMapping::default();
+    for (field, synonyms) in TARGETS {
+        let found = columns.iter().find(|c| {
+            synonyms.contains(&normalize(c).as_str()) && !mapping.fields.values().any(|v| v == *c)
+        });
+        if let Some(column) = found {
+            mapping.fields.insert(field.to_string(), column.clone());
+        }
+    }
+    mapping
+}
+
+/// Seconds from `5`, `5s`, `5.5` or `0:05`.
+fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim().trim_end_matches(['s', 'S']).trim();
+    if let Some((minutes, seconds)) = value.split_once(':') {
+        return Some(
+            minutes.trim().parse::<i64>().ok()? * 60 + seconds.trim().parse::<i64>().ok()?,
+        );
+    }
+    value.parse::<f64>().ok().map(|s| s.round() as i64)
+}
+
+/// A vocabulary key for a term given by key or label, ignoring case.
+fn term(conn: &Connection, kind: VocabularyKind, value: &str) -> AppResult<Option<String>> {
+    let wanted = normalize(value);
+    Ok(vocabulary::list_terms(conn, kind)?
+        .into_iter()
+        .find(|t| normalize(&t.key) == wanted || normalize(&t.label) == wanted)
+        .map(|t| t.key))
+}
+
+/// Create a scene in `project_id` for every usable row of `table`. Rows
+/// without a scene number are numbered on from the project's last scene.
+pub fn import(
+    conn: &Connection,
+    project_id: &str,
+    table: &Table,
+    mapping: &ImportMapping,
+) -> AppResult<ImportReport> {
+    projects::ensure_unlocked(conn, project_id)?;
+    for (field, column) in &mapping.fields {
+        if !TARGETS.iter().any(|(t, _)| t == field) {
+            return Err(AppError::Invalid(format!("unknown scene field: {}", field)));
+        }
+        if !table.columns.contains(column) {
+            return Err(AppError::Invalid(format!("no column named {}", column)));
+        }
+    }
+    let column = |field: &str| mapping.fields.get(field);
+    let separators: Vec<char> = if mapping.character_separator.is_empty() {
+        vec![',', ';']
+    } else {
+        mapping.character_separator.chars().collect()
+    };
+    let existing = scenes::list_for_project(conn, project_id)?;
+    let mut next_number = existing.iter().map(|s| s.scene_number).max().unwrap_or(0) + 1;
+    let mut sort_order = existing.iter().map(|s| s.sort_order).max().unwrap_or(0);
+
+    let mut report = ImportReport::default();
+    let tx = conn.unchecked_transaction()?;
+    for (i, row) in table.rows.iter().enumerate() {
+        let get = |field: &str| table.value(row, column(field));
+        let number_text = get("sceneNumber");
+        let scene_number = if number_text.is_empty() {
+            next_number
+        } else {
+            let digits: String = number_text
+                .trim_start_matches(|c: char| !c.is_ascii_digit())
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect();
+            match digits.parse() {
+                Ok(n) => n,
+                Err(_) => {
+                    report.skipped.push(SkippedRow {
+                        row: i + 1,
+                        reason: format!("\"{}\" is not a scene number", number_text),
+                    });
+                    continue;
+                }
+            }
+        };
+        let duration = match get("duration") {
+            "" => 5,
+            text => match parse_duration(text) {
+                Some(d) => d.clamp(1, MAX_SCENE_DURATION),
+                None => {
+                    report
+                        .warnings
+                        .push(format!("row {}: duration \"{}\" ignored", i + 1, text));
+                    5
+                }
+            },
+        };
+        let mut vocab = |kind, field: &str| -> AppResult<Option<String>> {
+            let value = get(field);
+            if value.is_empty() {
+                return Ok(None);
+            }
+            let key = term(&tx, kind, value)?;
+            if key.is_none() {
+                report.warnings.push(format!(
+                    "row {}: {} \"{}\" is not in the vocabulary",
+                    i + 1,
+                    field,
+                    value
+                ));
+            }
+            Ok(key)
+        };
+        let camera_angle = vocab(VocabularyKind::CameraAngle, "cameraAngle")?;
+        let lighting = vocab(VocabularyKind::Lighting, "lighting")?;
+        let description = get("description");
+        let prompt = get("prompt");
+        let title = get("title");
+        if title.is_empty() && description.is_empty() && prompt.is_empty() {
+            report.skipped.push(SkippedRow {
+                row: i + 1,
+                reason: "no title, description or prompt".into(),
+            });
+            continue;
+        }
+        sort_order += 1;
+        let input = SceneInput {
+            id: None,
+            project_id: project_id.to_string(),
+            scene_number,
+            title: title.to_string(),
+            description: description.to_string(),
+            prompt: prompt.to_string(),
+            camera_angle,
+            lighting,
+            duration,
+            dialog: get("dialog").to_string(),
+            characters: get("characters")
+                .split(separators.as_slice())
+                .map(str::trim)
+                .filter(|c| !c.is_empty())
+                .map(str::to_string)
+                .collect(),
+            sort_order,
+            location: get("location").to_string(),
+            expected_version: None,
+        };
+        match scenes::save(&tx, &input) {
+            Ok(scene) => {
+                next_number = next_number.max(scene_number + 1);
+                report.created.push(scene.id);
+            }
+            Err(AppError::Validation(errors)) => report.skipped.push(SkippedRow {
+                row: i + 1,
+                reason: errors
+                    .iter()
+                    .map(|e| format!("{} {}", e.field, e.message))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            }),
+            Err(e) => return Err(e),
+        }
+    }
+    tx.commit()?;
+    Ok(report)
+}
+
+fn read(path: &str) -> AppResult<Table> {
+    let bytes = std::fs::read(path)?;
+    Table::parse(&String::from_utf8_lossy(&bytes))
+}
+
+/// Read a CSV or JSON file and suggest a column mapping for it.
+pub fn preview(path: &str) -> AppResult<ImportPreview> {
+    let mut table = read(path)?;
+    let row_count = table.rows.len();
+    table.rows.truncate(PREVIEW_ROWS);
+    Ok(ImportPreview {
+        suggested: suggest(&table.columns),
+        table,
+        row_count,
+    })
+}
+
+#[tauri::command]
+pub fn preview_scene_import(path: String) -> AppResult<ImportPreview> {
+    preview(&path)
+}
+
+/// Import the file's rows as new scenes using `mapping`.
+#[tauri::command]
+pub fn import_scenes(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+    mapping: ImportMapping,
+) -> AppResult<ImportReport> {
+    let table = read(&path)?;
+    import(&db.conn(), &project_id, &table, &mapping)
+}
@@ -0,0 +1,285 @@
+//! Dependencies between scenes: "shot 14 continues from the end frame of
+//! shot 13". Batch generation queues upstream scenes first and holds each
+//! dependent job until the takes it depends on have finished; an
+//! `end_frame` link then hands the upstream take's last frame to the
+//! dependent as its start image.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::frames;
+use crate::jobs::{self, status};
+use crate::projects;
+use crate::scenes;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// How a dependent scene uses its upstream scene, stored in
+/// `scene_links.kind`.
+pub mod kind {
+    /// Start from the last frame of the upstream take.
+    pub const END_FRAME: &str = "end_frame";
+    /// Only generate after the upstream scene.
+    pub const AFTER: &str = "after";
+
+    pub const ALL: &[&str] = &[END_FRAME, AFTER];
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneLink {
+    pub id: String,
+    pub project_id: String,
+    pub upstream_id: String,
+    pub downstream_id: String,
+    pub kind: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneLinkInput {
+    pub upstream_id: String,
+    pub downstream_id: String,
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+const LINK_COLUMNS: &str = "id, project_id, upstream_id, downstream_id, kind, created_at";
+
+fn row_to_link(row: &Row) -> rusqlite::Result<SceneLink> {
+    Ok(SceneLink {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        upstream_id: row.get(2)?,
+        downstream_id: row.get(3)?,
+        kind: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+fn get_link(conn: &Connection, id: &str) -> AppResult<SceneLink> {
+    conn.query_row(
+        &format!("SELECT {} FROM scene_links WHERE id = ?1", LINK_COLUMNS),
+        [id],
+        row_to_link,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("scene link {}", id)))
+}
+
+/// Links whose downstream scene is `scene_id`.
+pub fn upstream_of(conn: &Connection, scene_id: &str) -> AppResult<Vec<SceneLink>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scene_links WHERE downstream_id = ?1 ORDER BY created_at, rowid",
+        LINK_COLUMNS
+    ))?;
+    let links = stmt
+        .query_map([scene_id], row_to_link)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(links)
+}
+
+/// Whether `ancestor` is `scene_id` or anything it depends on, directly or
+/// not.
+fn depends_on(conn: &Connection, scene_id: &str, ancestor: &str) -> AppResult<bool> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![scene_id.to_string()];
+    while let Some(id) = stack.pop() {
+        if id == ancestor {
+            return Ok(true);
+        }
+        if seen.insert(id.clone()) {
+            stack.extend(upstream_of(conn, &id)?.into_iter().map(|l| l.upstream_id));
+        }
+    }
+    Ok(false)
+}
+
+/// `scene_ids`, without repeats, reordered so every scene comes after the
+/// scenes it depends on within the batch. Otherwise the given order is kept.
+pub fn generation_order(conn: &Connection, scene_ids: &[String]) -> AppResult<Vec<String>> {
+    let mut batch: HashSet<&str> = HashSet::new();
+    let ids: Vec<&str> = scene_ids
+        .iter()
+        .map(String::as_str)
+        .filter(|id| batch.insert(id))
+        .collect();
+    let mut pending: HashMap<&str, usize> = HashMap::new();
+    let mut dependents: HashMap<String, Vec<&str>> = HashMap::new();
+    for &id in &ids {
+        let upstream: Vec<String> = upstream_of(conn, id)?
+            .into_iter()
+            .map(|l| l.upstream_id)
+            .filter(|u| batch.contains(u.as_str()))
+            .collect();
+        pending.insert(id, upstream.len());
+        for u in upstream {
+            dependents.entry(u).or_default().push(id);
+        }
+    }
+
+    let mut order = Vec::with_capacity(ids.len());
+    let mut done = HashSet::new();
+    // Links can't form cycles, so each pass places at least one scene.
+    while order.len() < ids.len() {
+        let ready: Vec<&str> = ids
+            .iter()
+            .copied()
+            .filter(|id| !done.contains(id) && pending[id] == 0)
+            .collect();
+        if ready.is_empty() {
+            return Err(AppError::Invalid("scene dependencies form a cycle".into()));
+        }
+        for id in ready {
+            done.insert(id);
+            order.push(id.to_string());
+            for d in dependents.get(id).into_iter().flatten() {
+                if let Some(n) = pending.get_mut(d) {
+                    *n -= 1;
+                }
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// The take a dependent continues from when its upstream scene isn't being
+/// generated with it: the scene's chosen video, else its newest finished
+/// take.
+pub fn finished_take(conn: &Connection, scene_id: &str) -> AppResult<Option<String>> {
+    let scene = scenes::get_scene(conn, scene_id)?;
+    if !scene.video_url.is_empty() {
+        return Ok(Some(scene.video_url));
+    }
+    Ok(conn
+        .query_row(
+            "SELECT video_url FROM video_jobs
+             WHERE scene_id = ?1 AND kind = ?2 AND status = ?3 AND video_url != ''
+             ORDER BY completed_at DESC, rowid DESC LIMIT 1",
+            params![scene_id, jobs::kind::VIDEO, status::COMPLETED],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+/// Start image for `downstream_id` taken from `video`, a take of
+/// `upstream_id`, if they are linked by end frame.
+pub async fn end_frame_for(
+    app: &AppHandle,
+    downstream_id: &str,
+    upstream_id: &str,
+    video: &str,
+) -> AppResult<Option<String>> {
+    let (linked, binary) = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        let linked = upstream_of(&conn, downstream_id)?
+            .iter()
+            .any(|l| l.upstream_id == upstream_id && l.kind == kind::END_FRAME);
+        (linked, frames::ffmpeg_binary(&conn)?)
+    };
+    if !linked {
+        return Ok(None);
+    }
+    let path = frames::last_frame(&binary, video).await?;
+    frames::data_url(&path).map(Some)
+}
+
+/// A project's scene links, oldest first.
+pub fn list(conn: &Connection, project_id: &str) -> AppResult<Vec<SceneLink>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scene_links WHERE project_id = ?1 ORDER BY created_at, rowid",
+        LINK_COLUMNS
+    ))?;
+    let links = stmt
+        .query_map([project_id], row_to_link)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(links)
+}
+
+#[tauri::command]
+pub fn list_scene_links(db: State<'_, Database>, project_id: String) -> AppResult<Vec<SceneLink>> {
+    list(&db.conn(), &project_id)
+}
+
+/// Make `downstream_id` depend on `upstream_id`. Both scenes must be in the
+/// same project, the link may not close a loop, and a scene can continue
+/// from only one end frame.
+pub fn create(conn: &Connection, input: &SceneLinkInput) -> AppResult<SceneLink> {
+    let link_kind = input.kind.as_deref().unwrap_or(kind::END_FRAME);
+    if !kind::ALL.contains(&link_kind) {
+        return Err(AppError::Invalid(format!(
+            "unknown link kind '{}'",
+            link_kind
+        )));
+    }
+    if input.upstream_id == input.downstream_id {
+        return Err(AppError::Invalid("a scene can't depend on itself".into()));
+    }
+    let upstream = scenes::get_scene(conn, &input.upstream_id)?;
+    let downstream = scenes::get_scene(conn, &input.downstream_id)?;
+    if upstream.project_id != downstream.project_id {
+        return Err(AppError::Invalid(
+            "scenes belong to different projects".into(),
+        ));
+    }
+    projects::ensure_unlocked(conn, &upstream.project_id)?;
+    if depends_on(conn, &upstream.id, &downstream.id)? {
+        return Err(AppError::Invalid(format!(
+            "scene {} already depends on scene {}",
+            upstream.scene_number, downstream.scene_number
+        )));
+    }
+    let existing = upstream_of(conn, &downstream.id)?;
+    if existing.iter().any(|l| l.upstream_id == upstream.id) {
+        return Err(AppError::Invalid(format!(
+            "scene {} already depends on scene {}",
+            downstream.scene_number, upstream.scene_number
+        )));
+    }
+    if link_kind == kind::END_FRAME && existing.iter().any(|l| l.kind == kind::END_FRAME) {
+        return Err(AppError::Invalid(format!(
+            "scene {} already continues from another scene's end frame",
+            downstream.scene_number
+        )));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO scene_links (id, project_id, upstream_id, downstream_id, kind, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            upstream.project_id,
+            upstream.id,
+            downstream.id,
+            link_kind,
+            clock::now()
+        ],
+    )?;
+    get_link(conn, &id)
+}
+
+#[tauri::command]
+pub fn create_scene_link(db: State<'_, Database>, input: SceneLinkInput) -> AppResult<SceneLink> {
+    create(&db.conn(), &input)
+}
+
+/// Remove a link, unless its project is locked.
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let link = get_link(conn, id)?;
+    projects::ensure_unlocked(conn, &link.project_id)?;
+    conn.execute("DELETE FROM scene_links WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_scene_link(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
@@ -0,0 +1,541 @@
+use crate::activity::{self, FieldChange};
+use crate::clock::{self, DateRange};
+use crate::comments;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::generation::GenerationSettings;
+use crate::ordering;
+use crate::paging::{self, Direction, Page};
+use crate::projects;
+use crate::scene_characters::{self, CastMember};
+use crate::validation::{Validate, Validator, MAX_NAME_LEN, MAX_SCENE_DURATION, MAX_TEXT_LEN};
+use crate::versioning;
+use crate::vocabulary::{self, VocabularyKind};
+use crate::workflow::{self, SceneStatus};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Scene {
+    pub id: String,
+    pub project_id: String,
+    #[ts(type = "number")]
+    pub scene_number: i64,
+    pub title: String,
+    pub description: String,
+    pub prompt: String,
+    pub camera_angle: String,
+    pub lighting: String,
+    #[ts(type = "number")]
+    pub duration: i64,
+    pub dialog: String,
+    /// Character ids in billing order; the same people as `cast`.
+    pub characters: Vec<String>,
+    pub status: String,
+    pub video_url: String,
+    #[ts(type = "number")]
+    pub sort_order: i64,
+    pub created_at: String,
+    /// Image used to condition image-to-video generation, if any.
+    pub conditioning_image: String,
+    /// Where the scene takes place, e.g. "INT. DINER - NIGHT".
+    pub location: String,
+    #[ts(type = "number")]
+    pub comment_count: i64,
+    #[ts(type = "number")]
+    pub open_comment_count: i64,
+    /// Pass back as `expected_version` when saving; see `versioning`.
+    #[ts(type = "number")]
+    pub version: i64,
+    /// Letter after the number for scenes inserted later, e.g. `"A"` in
+    /// 12A. Set by `renumber_scenes`.
+    pub scene_suffix: String,
+    /// Board position; see `ordering`. Move scenes with `move_scene`.
+    pub order_key: String,
+    /// Overrides of the project's generation defaults; see `generation`.
+    pub generation: GenerationSettings,
+    /// Why the scene's takes are out of date, e.g. "Mara's appearance
+    /// changed"; empty when they're current. Cleared by queueing a take.
+    pub stale_reason: String,
+    /// Who appears, with their role and importance; see
+    /// `scene_characters`.
+    pub cast: Vec<CastMember>,
+    /// Keep the prompt out of support bundles and exported logs; see
+    /// `redaction`.
+    pub prompt_confidential: bool,
+}
+
+/// Scene fields the editor can write. Omitting `id` creates a new scene;
+/// omitting camera angle or lighting uses the vocabulary default.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub project_id: String,
+    #[ts(type = "number")]
+    pub scene_number: i64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub camera_angle: Option<String>,
+    #[serde(default)]
+    pub lighting: Option<String>,
+    #[serde(default = "default_duration")]
+    #[ts(type = "number")]
+    pub duration: i64,
+    #[serde(default)]
+    pub dialog: String,
+    /// Character ids or names; unknown names become new characters. Roles
+    /// and importance are kept for anyone already cast.
+    #[serde(default)]
+    pub characters: Vec<String>,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub sort_order: i64,
+    #[serde(default)]
+    pub location: String,
+    /// The `version` the editor loaded; a stale one is rejected.
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub expected_version: Option<i64>,
+}
+
+fn default_duration() -> i64 {
+    5
+}
+
+impl Validate for SceneInput {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_uuid("id", self.id.as_deref())
+            .uuid("projectId", &self.project_id)
+            .range("sceneNumber", self.scene_number, 0, 9999)
+            .max_len("title", &self.title, MAX_NAME_LEN)
+            .max_len("description", &self.description, MAX_TEXT_LEN)
+            .max_len("prompt", &self.prompt, MAX_TEXT_LEN)
+            .max_len("dialog", &self.dialog, MAX_TEXT_LEN)
+            .max_len("location", self.location.trim(), MAX_NAME_LEN)
+            .range("duration", self.duration, 1, MAX_SCENE_DURATION);
+        for (i, c) in self.characters.iter().enumerate() {
+            v.name(&format!("characters.{}", i), c);
+        }
+    }
+}
+
+pub const SCENE_COLUMNS: &str = "id, project_id, scene_number, title, description, prompt, camera_angle, lighting, duration, dialog,
+    (SELECT json_group_array(json_object('characterId', character_id, 'role', role, 'importance', importance))
+        FROM (SELECT character_id, role, importance FROM scene_characters sc
+              WHERE sc.scene_id = scenes.id ORDER BY position)),
+    status, video_url, sort_order, created_at, conditioning_image, location,
+    (SELECT COUNT(*) FROM comments c WHERE c.entity_type = 'scene' AND c.entity_id = scenes.id),
+    (SELECT COUNT(*) FROM comments c WHERE c.entity_type = 'scene' AND c.entity_id = scenes.id AND c.resolved = 0),
+    version, scene_suffix, order_key, generation_json, stale_reason,
+    prompt_confidential";
+
+pub fn row_to_scene(row: &Row) -> rusqlite::Result<Scene> {
+    let cast: Vec<CastMember> =
+        serde_json::from_str(&row.get::<_, String>(10)?).unwrap_or_default();
+    Ok(Scene {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        scene_number: row.get(2)?,
+        title: row.get(3)?,
+        description: row.get(4)?,
+        prompt: row.get(5)?,
+        camera_angle: row.get(6)?,
+        lighting: row.get(7)?,
+        duration: row.get(8)?,
+        dialog: row.get(9)?,
+        characters: cast.iter().map(|m| m.character_id.clone()).collect(),
+        status: row.get(11)?,
+        video_url: row.get(12)?,
+        sort_order: row.get(13)?,
+        created_at: row.get(14)?,
+        conditioning_image: row.get(15)?,
+        location: row.get(16)?,
+        comment_count: row.get(17)?,
+        open_comment_count: row.get(18)?,
+        version: row.get(19)?,
+        scene_suffix: row.get(20)?,
+        order_key: row.get(21)?,
+        generation: GenerationSettings::from_json(&row.get::<_, String>(22)?),
+        stale_reason: row.get(23)?,
+        cast,
+        prompt_confidential: row.get(24)?,
+    })
+}
+
+pub fn get_scene(conn: &Connection, id: &str) -> AppResult<Scene> {
+    conn.query_row(
+        &format!("SELECT {} FROM scenes WHERE id = ?1", SCENE_COLUMNS),
+        [id],
+        row_to_scene,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("scene {}", id)))
+}
+
+pub fn list_for_project(conn: &Connection, project_id: &str) -> AppResult<Vec<Scene>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scenes WHERE project_id = ?1 ORDER BY order_key, sort_order, scene_number",
+        SCENE_COLUMNS
+    ))?;
+    let scenes = stmt
+        .query_map([project_id], row_to_scene)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(scenes)
+}
+
+fn resolve_term(conn: &Connection, kind: VocabularyKind, value: Option<&str>) -> AppResult<String> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(key) => {
+            vocabulary::validate(conn, kind, key)?;
+            Ok(key.to_string())
+        }
+        None => vocabulary::default_key(conn, kind),
+    }
+}
+
+/// Fields worth showing in the activity feed when a scene is edited.
+fn scene_changes(conn: &Connection, before: &Scene, after: &Scene) -> AppResult<Vec<FieldChange>> {
+    let mut changes = Vec::new();
+    activity::diff(
+        &mut changes,
+        "sceneNumber",
+        before.scene_number,
+        after.scene_number,
+    );
+    activity::diff(&mut changes, "title", &before.title, &after.title);
+    activity::diff(
+        &mut changes,
+        "description",
+        &before.description,
+        &after.description,
+    );
+    activity::diff(&mut changes, "prompt", &before.prompt, &after.prompt);
+    activity::diff(
+        &mut changes,
+        "cameraAngle",
+        &before.camera_angle,
+        &after.camera_angle,
+    );
+    activity::diff(&mut changes, "lighting", &before.lighting, &after.lighting);
+    activity::diff(&mut changes, "duration", before.duration, after.duration);
+    activity::diff(&mut changes, "dialog", &before.dialog, &after.dialog);
+    activity::diff(&mut changes, "location", &before.location, &after.location);
+    activity::diff(
+        &mut changes,
+        "characters",
+        scene_characters::names(conn, &before.characters)?.join(", "),
+        scene_characters::names(conn, &after.characters)?.join(", "),
+    );
+    Ok(changes)
+}
+
+/// Narrowing for `list_scenes_page`; every field is optional.
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneFilter {
+    /// Only scenes in one of these statuses.
+    #[serde(default)]
+    pub statuses: Option<Vec<String>>,
+    /// Case-insensitive match against title, description, prompt, dialog
+    /// and location.
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Only scenes featuring this character entry.
+    #[serde(default)]
+    pub character: Option<String>,
+    /// Only scenes created in this span.
+    #[serde(default)]
+    pub created: Option<DateRange>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneSortField {
+    /// Board order, as `list_scenes` returns it.
+    #[default]
+    Order,
+    SceneNumber,
+    Title,
+    Status,
+    Duration,
+    CreatedAt,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneSort {
+    #[serde(default)]
+    pub field: SceneSortField,
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+impl SceneSort {
+    fn order_by(self) -> String {
+        let column = match self.field {
+            SceneSortField::Order => "order_key",
+            SceneSortField::SceneNumber => "scene_number",
+            SceneSortField::Title => "title COLLATE NOCASE",
+            SceneSortField::Status => "status",
+            SceneSortField::Duration => "duration",
+            SceneSortField::CreatedAt => "created_at",
+        };
+        format!("{} {}, scene_number, rowid", column, self.direction.sql())
+    }
+}
+
+const SCENE_FILTER: &str = "project_id = ?1
+    AND (?2 IS NULL OR status IN (SELECT value FROM json_each(?2)))
+    AND (?3 IS NULL OR instr(lower(title || ' ' || description || ' ' || prompt || ' '
+        || dialog || ' ' || location), lower(?3)) > 0)
+    AND (?4 IS NULL OR location = ?4 COLLATE NOCASE)
+    AND (?5 IS NULL OR EXISTS (SELECT 1 FROM scene_characters sc
+        JOIN characters c ON c.id = sc.character_id
+        WHERE sc.scene_id = scenes.id AND (c.id = ?5 OR c.name = ?5 COLLATE NOCASE)))
+    AND (?6 IS NULL OR created_at >= ?6)
+    AND (?7 IS NULL OR created_at < ?7)";
+
+#[tauri::command]
+pub fn list_scenes(db: State<'_, Database>, project_id: String) -> AppResult<Vec<Scene>> {
+    list_for_project(&db.conn(), &project_id)
+}
+
+/// One page of a project's scenes, filtered and sorted in the database,
+/// with the total number of matches.
+pub fn list_page(
+    conn: &Connection,
+    project_id: &str,
+    filter: &SceneFilter,
+    sort: SceneSort,
+    offset: Option<i64>,
+    limit: Option<i64>,
+) -> AppResult<Page<Scene>> {
+    let (offset, limit) = paging::window(offset, limit);
+    let statuses = filter
+        .statuses
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+    let query = filter
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty());
+    let location = filter
+        .location
+        .as_deref()
+        .map(str::trim)
+        .filter(|l| !l.is_empty());
+    let character = filter.character.as_deref().filter(|c| !c.is_empty());
+    let (created_from, created_to) = filter.created.clone().unwrap_or_default().bounds()?;
+
+    let total = conn.query_row(
+        &format!("SELECT COUNT(*) FROM scenes WHERE {}", SCENE_FILTER),
+        params![
+            project_id,
+            statuses,
+            query,
+            location,
+            character,
+            created_from,
+            created_to
+        ],
+        |r| r.get(0),
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scenes WHERE {} ORDER BY {} LIMIT ?8 OFFSET ?9",
+        SCENE_COLUMNS,
+        SCENE_FILTER,
+        sort.order_by()
+    ))?;
+    let items = stmt
+        .query_map(
+            params![
+                project_id,
+                statuses,
+                query,
+                location,
+                character,
+                created_from,
+                created_to,
+                limit,
+                offset
+            ],
+            row_to_scene,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Page {
+        items,
+        total,
+        offset,
+        limit,
+    })
+}
+
+#[tauri::command]
+pub fn list_scenes_page(
+    db: State<'_, Database>,
+    project_id: String,
+    filter: Option<SceneFilter>,
+    sort: Option<SceneSort>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+) -> AppResult<Page<Scene>> {
+    list_page(
+        &db.conn(),
+        &project_id,
+        &filter.unwrap_or_default(),
+        sort.unwrap_or_default(),
+        offset,
+        limit,
+    )
+}
+
+#[tauri::command]
+pub fn get_scene_by_id(db: State<'_, Database>, id: String) -> AppResult<Scene> {
+    get_scene(&db.conn(), &id)
+}
+
+/// Create or update a scene. Camera angle and lighting must come from the
+/// managed vocabulary. Status is not editable here; see `transition_scene`.
+pub fn save(conn: &Connection, input: &SceneInput) -> AppResult<Scene> {
+    input.check()?;
+    projects::ensure_unlocked(conn, &input.project_id)?;
+    let camera_angle = resolve_term(
+        conn,
+        VocabularyKind::CameraAngle,
+        input.camera_angle.as_deref(),
+    )?;
+    let lighting = resolve_term(conn, VocabularyKind::Lighting, input.lighting.as_deref())?;
+
+    let id = match &input.id {
+        Some(id) => {
+            workflow::ensure_editable(conn, id)?;
+            let before = get_scene(conn, id)?;
+            versioning::check(
+                "scene",
+                id,
+                input.expected_version,
+                before.version,
+                &before,
+                input,
+            )?;
+            let changed = conn.execute(
+                "UPDATE scenes SET scene_number = ?2, title = ?3, description = ?4, prompt = ?5,
+                    camera_angle = ?6, lighting = ?7, duration = ?8, dialog = ?9,
+                    sort_order = ?10, location = ?12
+                 WHERE id = ?1 AND project_id = ?11",
+                params![
+                    id,
+                    input.scene_number,
+                    input.title,
+                    input.description,
+                    input.prompt,
+                    camera_angle,
+                    lighting,
+                    input.duration,
+                    input.dialog,
+                    input.sort_order,
+                    input.project_id,
+                    input.location.trim()
+                ],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("scene {}", id)));
+            }
+            scene_characters::set_cast(conn, &before, &input.characters)?;
+            let after = get_scene(conn, id)?;
+            activity::record(
+                conn,
+                &after.project_id,
+                "scene",
+                id,
+                "updated",
+                &format!("Edited scene {}", after.scene_number),
+                &scene_changes(conn, &before, &after)?,
+            )?;
+            id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO scenes (id, project_id, scene_number, title, description, prompt,
+                    camera_angle, lighting, duration, dialog, sort_order, status, location,
+                    created_at, order_key)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    id,
+                    input.project_id,
+                    input.scene_number,
+                    input.title,
+                    input.description,
+                    input.prompt,
+                    camera_angle,
+                    lighting,
+                    input.duration,
+                    input.dialog,
+                    input.sort_order,
+                    SceneStatus::Draft.as_str(),
+                    input.location.trim(),
+                    clock::now(),
+                    ordering::next_key(conn, &input.project_id)?
+                ],
+            )?;
+            activity::record(
+                conn,
+                &input.project_id,
+                "scene",
+                &id,
+                "created",
+                &format!("Added scene {}", input.scene_number),
+                &[],
+            )?;
+            scene_characters::set_cast(conn, &get_scene(conn, &id)?, &input.characters)?;
+            id
+        }
+    };
+    get_scene(conn, &id)
+}
+
+#[tauri::command]
+pub fn save_scene(db: State<'_, Database>, input: SceneInput) -> AppResult<Scene> {
+    save(&db.conn(), &input)
+}
+
+/// Delete an unlocked scene and its comments.
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    workflow::ensure_editable(conn, id)?;
+    let scene = get_scene(conn, id)?;
+    conn.execute("DELETE FROM scenes WHERE id = ?1", [id])?;
+    activity::record(
+        conn,
+        &scene.project_id,
+        "scene",
+        id,
+        "deleted",
+        &format!("Deleted scene {} ({})", scene.scene_number, scene.title),
+        &[],
+    )?;
+    comments::delete_for_entity(conn, "scene", id)
+}
+
+#[tauri::command]
+pub fn delete_scene(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
@@ -0,0 +1,604 @@
+use crate::characters;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::export::{self, ProjectInfo};
+use crate::i18n::{self, Locale};
+use crate::ics::{self, When};
+use crate::pdf::{Document, Font, Page};
+use crate::projects;
+use crate::provenance::{self, Provenance};
+use crate::reports;
+use crate::scenes::{self, Scene};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Shooting days without a wrap time are assumed to run this long.
+const DEFAULT_DAY_MINUTES: u32 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ShootingDay {
+    pub id: String,
+    pub project_id: String,
+    /// 1-based position in date order.
+    #[ts(type = "number")]
+    pub day_number: i64,
+    pub shoot_date: String,
+    pub call_time: String,
+    pub wrap_time: String,
+    pub location: String,
+    pub notes: String,
+    pub scene_ids: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ShootingDayInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub project_id: String,
+    /// `YYYY-MM-DD`.
+    pub shoot_date: String,
+    /// `HH:MM`, 24-hour.
+    #[serde(default)]
+    pub call_time: String,
+    #[serde(default)]
+    pub wrap_time: String,
+    #[serde(default)]
+    pub location: String,
+    #[serde(default)]
+    pub notes: String,
+}
+
+const DAY_COLUMNS: &str = "id, project_id,
+    (SELECT COUNT(*) FROM shooting_days d2 WHERE d2.project_id = shooting_days.project_id
+        AND (d2.shoot_date < shooting_days.shoot_date
+            OR (d2.shoot_date = shooting_days.shoot_date AND d2.rowid <= shooting_days.rowid))),
+    shoot_date, call_time, wrap_time, location, notes, created_at";
+
+fn row_to_day(row: &Row) -> rusqlite::Result<ShootingDay> {
+    Ok(ShootingDay {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        day_number: row.get(2)?,
+        shoot_date: row.get(3)?,
+        call_time: row.get(4)?,
+        wrap_time: row.get(5)?,
+        location: row.get(6)?,
+        notes: row.get(7)?,
+        scene_ids: Vec::new(),
+        created_at: row.get(8)?,
+    })
+}
+
+fn day_scene_ids(conn: &Connection, day_id: &str) -> AppResult<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT scene_id FROM shooting_day_scenes WHERE day_id = ?1 ORDER BY position")?;
+    let ids = stmt
+        .query_map([day_id], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+pub fn get_day(conn: &Connection, id: &str) -> AppResult<ShootingDay> {
+    let mut day = conn
+        .query_row(
+            &format!("SELECT {} FROM shooting_days WHERE id = ?1", DAY_COLUMNS),
+            [id],
+            row_to_day,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("shooting day {}", id)))?;
+    day.scene_ids = day_scene_ids(conn, id)?;
+    Ok(day)
+}
+
+pub fn validate_date(date: &str) -> AppResult<()> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let ok = matches!(parts.as_slice(), [y, m, d]
+        if y.len() == 4 && m.len() == 2 && d.len() == 2
+            && y.parse::<u32>().is_ok()
+            && m.parse::<u32>().is_ok_and(|m| (1..=12).contains(&m))
+            && d.parse::<u32>().is_ok_and(|d| (1..=31).contains(&d)));
+    if ok {
+        Ok(())
+    } else {
+        Err(AppError::Invalid(format!(
+            "'{}' is not a YYYY-MM-DD date",
+            date
+        )))
+    }
+}
+
+fn validate_time(time: &str) -> AppResult<()> {
+    if time.is_empty() {
+        return Ok(());
+    }
+    let ok = matches!(time.split_once(':'), Some((h, m))
+        if h.len() == 2 && m.len() == 2
+            && h.parse::<u32>().is_ok_and(|h| h < 24)
+            && m.parse::<u32>().is_ok_and(|m| m < 60));
+    if ok {
+        Ok(())
+    } else {
+        Err(AppError::Invalid(format!(
+            "'{}' is not an HH:MM time",
+            time
+        )))
+    }
+}
+
+/// A project's shooting days by date, each with its scenes in order.
+pub fn list_days(conn: &Connection, project_id: &str) -> AppResult<Vec<ShootingDay>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM shooting_days WHERE project_id = ?1 ORDER BY shoot_date, rowid",
+        DAY_COLUMNS
+    ))?;
+    let mut days = stmt
+        .query_map([project_id], row_to_day)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for day in &mut days {
+        day.scene_ids = day_scene_ids(conn, &day.id)?;
+    }
+    Ok(days)
+}
+
+#[tauri::command]
+pub fn list_shooting_days(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<Vec<ShootingDay>> {
+    list_days(&db.conn(), &project_id)
+}
+
+pub fn save_day(conn: &Connection, input: &ShootingDayInput) -> AppResult<ShootingDay> {
+    validate_date(&input.shoot_date)?;
+    validate_time(&input.call_time)?;
+    validate_time(&input.wrap_time)?;
+    projects::ensure_unlocked(conn, &input.project_id)?;
+    let id = match &input.id {
+        Some(id) => {
+            let changed = conn.execute(
+                "UPDATE shooting_days SET shoot_date = ?2, call_time = ?3, wrap_time = ?4,
+                    location = ?5, notes = ?6
+                 WHERE id = ?1 AND project_id = ?7",
+                params![
+                    id,
+                    input.shoot_date,
+                    input.call_time,
+                    input.wrap_time,
+                    input.location.trim(),
+                    input.notes,
+                    input.project_id
+                ],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("shooting day {}", id)));
+            }
+            id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO shooting_days (id, project_id, shoot_date, call_time, wrap_time, location, notes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id,
+                    input.project_id,
+                    input.shoot_date,
+                    input.call_time,
+                    input.wrap_time,
+                    input.location.trim(),
+                    input.notes
+                ],
+            )?;
+            id
+        }
+    };
+    get_day(conn, &id)
+}
+
+#[tauri::command]
+pub fn save_shooting_day(
+    db: State<'_, Database>,
+    input: ShootingDayInput,
+) -> AppResult<ShootingDay> {
+    save_day(&db.conn(), &input)
+}
+
+/// Remove a shooting day, unless its project is locked.
+pub fn delete_day(conn: &Connection, id: &str) -> AppResult<()> {
+    let day = get_day(conn, id)?;
+    projects::ensure_unlocked(conn, &day.project_id)?;
+    conn.execute("DELETE FROM shooting_days WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_shooting_day(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete_day(&db.conn(), &id)
+}
+
+/// Set which scenes are shot on a day, in shooting order. A scene belongs
+/// to at most one day, so listed scenes are moved off any other day.
+pub fn set_day_scenes(
+    conn: &Connection,
+    day_id: &str,
+    scene_ids: &[String],
+) -> AppResult<ShootingDay> {
+    let tx = conn.unchecked_transaction()?;
+    let day = get_day(&tx, day_id)?;
+    projects::ensure_unlocked(&tx, &day.project_id)?;
+    for scene_id in scene_ids {
+        let scene = scenes::get_scene(&tx, scene_id)?;
+        if scene.project_id != day.project_id {
+            return Err(AppError::Invalid(format!(
+                "scene {} belongs to another project",
+                scene.scene_number
+            )));
+        }
+    }
+    tx.execute(
+        "DELETE FROM shooting_day_scenes WHERE day_id = ?1",
+        [day_id],
+    )?;
+    for (position, scene_id) in scene_ids.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO shooting_day_scenes (day_id, scene_id, position) VALUES (?1, ?2, ?3)
+             ON CONFLICT(scene_id) DO UPDATE SET day_id = excluded.day_id, position = excluded.position",
+            params![day_id, scene_id, position as i64],
+        )?;
+    }
+    tx.commit()?;
+    get_day(conn, day_id)
+}
+
+#[tauri::command]
+pub fn set_shooting_day_scenes(
+    db: State<'_, Database>,
+    day_id: String,
+    scene_ids: Vec<String>,
+) -> AppResult<ShootingDay> {
+    set_day_scenes(&db.conn(), &day_id, &scene_ids)
+}
+
+struct DaySheet {
+    locale: Locale,
+    project: ProjectInfo,
+    day: ShootingDay,
+    scenes: Vec<Scene>,
+    /// Cast names in order of first appearance.
+    cast: Vec<String>,
+}
+
+fn load_sheet(conn: &Connection, day_id: &str) -> AppResult<DaySheet> {
+    let day = get_day(conn, day_id)?;
+    let project = export::load_project(conn, &day.project_id)?;
+    let scenes = day
+        .scene_ids
+        .iter()
+        .map(|id| scenes::get_scene(conn, id))
+        .collect::<AppResult<Vec<_>>>()?;
+    let characters = characters::list_for_project(conn, &day.project_id)?;
+    let cast = reports::by_character(&scenes, &characters)
+        .into_iter()
+        .filter(|c| !c.scene_ids.is_empty())
+        .map(|c| c.name)
+        .collect();
+    Ok(DaySheet {
+        locale: i18n::current(conn)?,
+        project,
+        day,
+        scenes,
+        cast,
+    })
+}
+
+fn day_title(sheet: &DaySheet) -> String {
+    let locale = sheet.locale;
+    locale.tf(
+        "callSheet.dayTitle",
+        &[
+            ("project", &sheet.project.name),
+            ("day", &locale.integer(sheet.day.day_number)),
+            ("date", &locale.date(&sheet.day.shoot_date)),
+        ],
+    )
+}
+
+fn render_call_sheet(sheet: &DaySheet, provenance: &Provenance) -> Vec<u8> {
+    // US Letter, portrait.
+    let (w, h, margin) = (612.0, 792.0, 48.0);
+    let ink = (0.1, 0.1, 0.1);
+    let muted = (0.45, 0.45, 0.45);
+    let locale = sheet.locale;
+    let mut doc = Document::new(w, h);
+    doc.set_metadata(provenance.xmp());
+    let mut page = doc.page();
+
+    page.color(ink);
+    page.text(
+        margin,
+        margin,
+        22.0,
+        Font::Bold,
+        locale.t("callSheet.title"),
+    );
+    page.text(margin, margin + 30.0, 14.0, Font::Bold, &sheet.project.name);
+    let mut y = margin + 60.0;
+    let field = |page: &mut Page, y: f32, label: &str, value: &str| {
+        page.color(muted);
+        page.text(margin, y, 10.0, Font::Regular, label);
+        page.color(ink);
+        page.text(margin + 90.0, y, 11.0, Font::Bold, value);
+    };
+    field(
+        &mut page,
+        y,
+        locale.t("callSheet.shootDay"),
+        &locale.integer(sheet.day.day_number),
+    );
+    y += 18.0;
+    field(
+        &mut page,
+        y,
+        locale.t("callSheet.date"),
+        &locale.date(&sheet.day.shoot_date),
+    );
+    y += 18.0;
+    let call = if sheet.day.call_time.is_empty() {
+        locale.t("callSheet.tbc").to_string()
+    } else {
+        locale.time(&sheet.day.call_time)
+    };
+    field(&mut page, y, locale.t("callSheet.generalCall"), &call);
+    y += 18.0;
+    if !sheet.day.wrap_time.is_empty() {
+        field(
+            &mut page,
+            y,
+            locale.t("callSheet.wrap"),
+            &locale.time(&sheet.day.wrap_time),
+        );
+        y += 18.0;
+    }
+    if !sheet.day.location.is_empty() {
+        field(
+            &mut page,
+            y,
+            locale.t("callSheet.location"),
+            &sheet.day.location,
+        );
+        y += 18.0;
+    }
+    if !sheet.day.notes.trim().is_empty() {
+        page.color(ink);
+        y = page.paragraph(
+            margin,
+            y + 6.0,
+            w - 2.0 * margin,
+            y + 90.0,
+            10.0,
+            Font::Regular,
+            &sheet.day.notes,
+        );
+    }
+
+    y += 18.0;
+    page.color(ink);
+    page.text(margin, y, 13.0, Font::Bold, locale.t("callSheet.scenes"));
+    y += 22.0;
+    let cols = [margin, margin + 40.0, margin + 250.0, margin + 420.0];
+    page.color(muted);
+    let labels = [
+        "#",
+        locale.t("callSheet.scene"),
+        locale.t("callSheet.location"),
+        locale.t("callSheet.length"),
+    ];
+    for (x, label) in cols.iter().zip(labels) {
+        page.text(*x, y, 9.0, Font::Bold, label);
+    }
+    y += 16.0;
+    let total: i64 = sheet.scenes.iter().map(|s| s.duration).sum();
+    for scene in &sheet.scenes {
+        if y > h - 200.0 {
+            break;
+        }
+        page.color(ink);
+        page.text(
+            cols[0],
+            y,
+            10.0,
+            Font::Regular,
+            &locale.integer(scene.scene_number),
+        );
+        page.text(cols[1], y, 10.0, Font::Regular, &scene.title);
+        page.text(cols[2], y, 10.0, Font::Regular, &scene.location);
+        page.text(
+            cols[3],
+            y,
+            10.0,
+            Font::Regular,
+            &locale.seconds(scene.duration),
+        );
+        y += 15.0;
+    }
+    page.color(muted);
+    page.text(
+        cols[2],
+        y + 4.0,
+        10.0,
+        Font::Bold,
+        &locale.tf(
+            "callSheet.total",
+            &[
+                ("count", &locale.integer(sheet.scenes.len() as i64)),
+                ("duration", &locale.seconds(total)),
+            ],
+        ),
+    );
+
+    y += 36.0;
+    page.color(ink);
+    page.text(margin, y, 13.0, Font::Bold, locale.t("callSheet.cast"));
+    y += 22.0;
+    for name in &sheet.cast {
+        if y > h - margin {
+            break;
+        }
+        page.text(margin, y, 10.0, Font::Regular, name);
+        page.text(margin + 250.0, y, 10.0, Font::Regular, &call);
+        y += 15.0;
+    }
+
+    doc.push(page);
+    doc.finish()
+}
+
+/// Write a one-page call sheet PDF for a shooting day. Returns the path.
+pub fn write_call_sheet(conn: &Connection, day_id: &str, path: &str) -> AppResult<String> {
+    let sheet = load_sheet(conn, day_id)?;
+    let provenance = provenance::for_scenes(conn, &sheet.day.project_id, &sheet.scenes)?;
+    let out = export::resolve_output(
+        path,
+        &format!("call-sheet-day-{}.pdf", sheet.day.day_number),
+    )?;
+    std::fs::write(&out, render_call_sheet(&sheet, &provenance))?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_call_sheet(
+    db: State<'_, Database>,
+    day_id: String,
+    path: String,
+) -> AppResult<String> {
+    write_call_sheet(&db.conn(), &day_id, &path)
+}
+
+/// The shooting day as a calendar with one event.
+pub fn day_calendar(conn: &Connection, day_id: &str) -> AppResult<String> {
+    let sheet = load_sheet(conn, day_id)?;
+    let when = if sheet.day.call_time.is_empty() {
+        When::AllDay(sheet.day.shoot_date.clone())
+    } else {
+        When::Timed {
+            date: sheet.day.shoot_date.clone(),
+            start: sheet.day.call_time.clone(),
+            end: Some(sheet.day.wrap_time.clone()).filter(|t| !t.is_empty()),
+            default_minutes: DEFAULT_DAY_MINUTES,
+        }
+    };
+    let scene_list = sheet
+        .scenes
+        .iter()
+        .map(|s| format!("{}. {}", s.scene_number, s.title))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let locale = sheet.locale;
+    let mut description = format!("{}:\n{}", locale.t("callSheet.scenes"), scene_list);
+    if !sheet.cast.is_empty() {
+        description.push_str(&format!(
+            "\n\n{}: {}",
+            locale.t("callSheet.cast"),
+            sheet.cast.join(", ")
+        ));
+    }
+    if !sheet.day.notes.trim().is_empty() {
+        description.push_str(&format!("\n\n{}", sheet.day.notes.trim()));
+    }
+    let event = ics::Event {
+        uid: format!("shooting-day-{}@aidc", sheet.day.id),
+        when,
+        summary: day_title(&sheet),
+        description,
+        location: sheet.day.location.clone(),
+    };
+    Ok(ics::calendar(&sheet.project.name, &[event]))
+}
+
+/// Write an `.ics` file with the shooting day as a calendar event.
+pub fn write_day_ics(conn: &Connection, day_id: &str, path: &str) -> AppResult<String> {
+    let calendar = day_calendar(conn, day_id)?;
+    let day_number = get_day(conn, day_id)?.day_number;
+    let out = export::resolve_output(path, &format!("shooting-day-{}.ics", day_number))?;
+    std::fs::write(&out, calendar)?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_shooting_day_ics(
+    db: State<'_, Database>,
+    day_id: String,
+    path: String,
+) -> AppResult<String> {
+    write_day_ics(&db.conn(), &day_id, &path)
+}
+
+/// Shooting days from a calendar, one per event: its date, start and end
+/// as call and wrap times, location, and description as notes. An event
+/// exported from one of the project's days moves that day rather than
+/// adding another, keeping its notes. Returns the days saved.
+pub fn import_calendar(
+    conn: &Connection,
+    project_id: &str,
+    calendar: &str,
+) -> AppResult<Vec<ShootingDay>> {
+    projects::ensure_unlocked(conn, project_id)?;
+    let mut days = Vec::new();
+    for event in ics::parse(calendar) {
+        let (shoot_date, call_time, wrap_time) = match event.when {
+            When::AllDay(date) => (date, String::new(), String::new()),
+            When::Timed {
+                date, start, end, ..
+            } => (date, start, end.unwrap_or_default()),
+        };
+        let existing = event
+            .uid
+            .strip_prefix("shooting-day-")
+            .and_then(|rest| rest.strip_suffix("@aidc"))
+            .and_then(|id| get_day(conn, id).ok())
+            .filter(|day| day.project_id == project_id);
+        let notes = match &existing {
+            Some(day) => day.notes.clone(),
+            None => event.description,
+        };
+        days.push(save_day(
+            conn,
+            &ShootingDayInput {
+                id: existing.map(|day| day.id),
+                project_id: project_id.to_string(),
+                shoot_date,
+                call_time,
+                wrap_time,
+                location: event.location,
+                notes,
+            },
+        )?);
+    }
+    Ok(days)
+}
+
+/// Add shooting days from the events in the `.ics` file at `path`.
+pub fn import_ics(conn: &Connection, project_id: &str, path: &str) -> AppResult<Vec<ShootingDay>> {
+    let calendar = std::fs::read_to_string(path)?;
+    let tx = conn.unchecked_transaction()?;
+    let days = import_calendar(&tx, project_id, &calendar)?;
+    tx.commit()?;
+    Ok(days)
+}
+
+#[tauri::command]
+pub fn import_shooting_days_ics(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+) -> AppResult<Vec<ShootingDay>> {
+    import_ics(&db.conn(), &project_id, &path)
+}
@@ -0,0 +1,677 @@
+//! Screenplay exports, as PDF and as Fountain plain text, built from each
+//! scene's location, description and dialog. Layout follows a convention
+//! (US, UK, French, German, Hungarian or Spanish) for the scene heading
+//! keywords, scene numbering, page size and element margins. A project
+//! picks one and can override any of its fields (`screenplay_json`); with
+//! none picked, the convention follows the app language.
+
+use crate::activity::{self, FieldChange};
+use crate::analytics;
+use crate::db::Database;
+use crate::error::AppResult;
+use crate::export::{self, ProjectInfo};
+use crate::numbering;
+use crate::pdf::{self, Document, Font, Page};
+use crate::provenance::{self, Provenance};
+use crate::scenes::{self, Scene};
+use crate::validation::{Validate, Validator};
+use crate::{i18n, projects};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use tauri::State;
+use ts_rs::TS;
+
+const POINTS_PER_INCH: f32 = 72.0;
+const FONT_SIZE: f32 = 12.0;
+/// Courier 12 sets six lines to the inch.
+const LEADING: f32 = 12.0;
+const PAGE_SIZES: &[&str] = &["letter", "a4"];
+/// Longest speaker name recognised in `NAME: line` dialog, as in
+/// `runtime::spoken_words`.
+const MAX_SPEAKER_LEN: usize = 40;
+
+/// A screenplay convention. Margins are left edges in inches from the
+/// edge of the page; the right margin is always one inch.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Convention {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub page_size: &'static str,
+    pub interior: &'static str,
+    pub exterior: &'static str,
+    pub interior_exterior: &'static str,
+    pub day: &'static str,
+    pub night: &'static str,
+    pub numbered_headings: bool,
+    pub action_left: f32,
+    pub character_left: f32,
+    pub parenthetical_left: f32,
+    pub dialogue_left: f32,
+    pub dialogue_width: f32,
+}
+
+const US: Convention = Convention {
+    key: "us",
+    name: "US (Hollywood)",
+    page_size: "letter",
+    interior: "INT.",
+    exterior: "EXT.",
+    interior_exterior: "INT./EXT.",
+    day: "DAY",
+    night: "NIGHT",
+    numbered_headings: false,
+    action_left: 1.5,
+    character_left: 3.7,
+    parenthetical_left: 3.1,
+    dialogue_left: 2.5,
+    dialogue_width: 3.5,
+};
+
+/// European conventions share A4 and a narrower, more centred dialogue
+/// column, and number their scenes.
+const EUROPEAN: Convention = Convention {
+    key: "",
+    name: "",
+    page_size: "a4",
+    numbered_headings: true,
+    action_left: 1.2,
+    character_left: 3.4,
+    parenthetical_left: 2.9,
+    dialogue_left: 2.4,
+    dialogue_width: 3.3,
+    ..US
+};
+
+pub const CONVENTIONS: &[Convention] = &[
+    US,
+    Convention {
+        key: "uk",
+        name: "UK",
+        page_size: "a4",
+        ..US
+    },
+    Convention {
+        key: "fr",
+        name: "Français",
+        day: "JOUR",
+        night: "NUIT",
+        ..EUROPEAN
+    },
+    Convention {
+        key: "de",
+        name: "Deutsch",
+        interior: "INNEN",
+        exterior: "AUSSEN",
+        interior_exterior: "INNEN/AUSSEN",
+        day: "TAG",
+        night: "NACHT",
+        ..EUROPEAN
+    },
+    Convention {
+        key: "hu",
+        name: "Magyar",
+        interior: "BELSŐ",
+        exterior: "KÜLSŐ",
+        interior_exterior: "BELSŐ/KÜLSŐ",
+        day: "NAPPAL",
+        night: "ÉJSZAKA",
+        ..EUROPEAN
+    },
+    Convention {
+        key: "es",
+        name: "Español",
+        day: "DÍA",
+        night: "NOCHE",
+        ..EUROPEAN
+    },
+];
+
+fn convention(key: &str) -> Option<&'static Convention> {
+    CONVENTIONS.iter().find(|c| c.key == key)
+}
+
+/// A project's screenplay settings. Fields left out come from the
+/// convention.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ScreenplayFormat {
+    /// A `CONVENTIONS` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub convention: Option<String>,
+    /// `"letter"` or `"a4"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interior: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exterior: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interior_exterior: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub night: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numbered_headings: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action_left: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character_left: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parenthetical_left: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialogue_left: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialogue_width: Option<f32>,
+}
+
+impl Validate for ScreenplayFormat {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(key) = &self.convention {
+            let keys: Vec<&str> = CONVENTIONS.iter().map(|c| c.key).collect();
+            v.one_of("convention", key, &keys);
+        }
+        if let Some(size) = &self.page_size {
+            v.one_of("pageSize", size, PAGE_SIZES);
+        }
+        for (field, keyword) in [
+            ("interior", &self.interior),
+            ("exterior", &self.exterior),
+            ("interiorExterior", &self.interior_exterior),
+            ("day", &self.day),
+            ("night", &self.night),
+        ] {
+            if let Some(keyword) = keyword {
+                v.required(field, keyword).max_len(field, keyword, 40);
+            }
+        }
+        for (field, inches) in [
+            ("actionLeft", self.action_left),
+            ("characterLeft", self.character_left),
+            ("parentheticalLeft", self.parenthetical_left),
+            ("dialogueLeft", self.dialogue_left),
+        ] {
+            if let Some(inches) = inches {
+                v.range(field, inches, 0.5, 5.0);
+            }
+        }
+        if let Some(width) = self.dialogue_width {
+            v.range("dialogueWidth", width, 1.5, 6.0);
+        }
+    }
+}
+
+/// Everything the renderers need, with the overrides applied.
+struct Layout {
+    width: f32,
+    height: f32,
+    interior: String,
+    exterior: String,
+    interior_exterior: String,
+    day: String,
+    night: String,
+    numbered_headings: bool,
+    action_left: f32,
+    character_left: f32,
+    parenthetical_left: f32,
+    dialogue_left: f32,
+    dialogue_width: f32,
+}
+
+impl Layout {
+    fn new(format: &ScreenplayFormat, base: &Convention) -> Layout {
+        let text = |value: &Option<String>, fallback: &str| {
+            value.clone().unwrap_or_else(|| fallback.to_string())
+        };
+        let (width, height) = match format.page_size.as_deref().unwrap_or(base.page_size) {
+            "a4" => (595.28, 841.89),
+            _ => (612.0, 792.0),
+        };
+        Layout {
+            width,
+            height,
+            interior: text(&format.interior, base.interior),
+            exterior: text(&format.exterior, base.exterior),
+            interior_exterior: text(&format.interior_exterior, base.interior_exterior),
+            day: text(&format.day, base.day),
+            night: text(&format.night, base.night),
+            numbered_headings: format.numbered_headings.unwrap_or(base.numbered_headings),
+            action_left: format.action_left.unwrap_or(base.action_left),
+            character_left: format.character_left.unwrap_or(base.character_left),
+            parenthetical_left: format.parenthetical_left.unwrap_or(base.parenthetical_left),
+            dialogue_left: format.dialogue_left.unwrap_or(base.dialogue_left),
+            dialogue_width: format.dialogue_width.unwrap_or(base.dialogue_width),
+        }
+    }
+}
+
+pub fn project_format(conn: &Connection, project_id: &str) -> AppResult<ScreenplayFormat> {
+    let json: String = conn.query_row(
+        "SELECT screenplay_json FROM projects WHERE id = ?1",
+        [project_id],
+        |r| r.get(0),
+    )?;
+    Ok(serde_json::from_str(&json).unwrap_or_default())
+}
+
+/// The convention for a project that hasn't picked one.
+fn default_convention(conn: &Connection) -> AppResult<&'static Convention> {
+    let key = match i18n::current(conn)?.code() {
+        "en-GB" => "uk",
+        "de" => "de",
+        "fr" => "fr",
+        "es" => "es",
+        _ => "us",
+    };
+    Ok(convention(key).unwrap_or(&US))
+}
+
+fn load_layout(conn: &Connection, project_id: &str) -> AppResult<Layout> {
+    let format = project_format(conn, project_id)?;
+    let base = match format.convention.as_deref().and_then(convention) {
+        Some(base) => base,
+        None => default_convention(conn)?,
+    };
+    Ok(Layout::new(&format, base))
+}
+
+enum Element {
+    /// The heading text and the scene's number label.
+    Heading(String, String),
+    Action(String),
+    Character(String),
+    Parenthetical(String),
+    Dialogue(String),
+}
+
+#[derive(Clone, Copy)]
+enum Setting {
+    Interior,
+    Exterior,
+    Both,
+}
+
+/// Strip a setting keyword in any convention's language (or a common
+/// variant such as `I/E`) from the start of a heading.
+fn split_setting(heading: &str) -> (Option<Setting>, &str) {
+    let (first, rest) = heading.split_once(' ').unwrap_or((heading, ""));
+    let word = first.trim_end_matches('.');
+    let known = CONVENTIONS
+        .iter()
+        .flat_map(|c| {
+            [
+                (c.interior_exterior, Setting::Both),
+                (c.interior, Setting::Interior),
+                (c.exterior, Setting::Exterior),
+            ]
+        })
+        .chain([
+            ("I/E", Setting::Both),
+            ("EXT./INT.", Setting::Both),
+            ("INT", Setting::Interior),
+            ("EXT", Setting::Exterior),
+        ]);
+    for (keyword, setting) in known {
+        if keyword.trim_end_matches('.') == word {
+            return (Some(setting), rest.trim_start());
+        }
+    }
+    (None, heading)
+}
+
+/// Strip a trailing `- DAY`/`- NIGHT` in any convention's language.
+/// Other times of day (`DUSK`, `LATER`) are left in place.
+fn split_time(place: &str) -> (&str, Option<bool>) {
+    for separator in [" - ", " \u{2013} ", " \u{2014} "] {
+        if let Some((rest, time)) = place.rsplit_once(separator) {
+            let time = time.trim();
+            if CONVENTIONS.iter().any(|c| c.day == time) {
+                return (rest.trim_end(), Some(true));
+            }
+            if CONVENTIONS.iter().any(|c| c.night == time) {
+                return (rest.trim_end(), Some(false));
+            }
+        }
+    }
+    (place, None)
+}
+
+/// A scene heading in the layout's keywords, from a location such as
+/// `INT. DINER - NIGHT`. Scenes without a location use their title.
+fn heading(layout: &Layout, scene: &Scene) -> String {
+    let location = scene.location.trim().to_uppercase();
+    if location.is_empty() {
+        return scene.title.trim().to_uppercase();
+    }
+    let (setting, place) = split_setting(&location);
+    let (place, day) = split_time(place);
+    let mut out = String::new();
+    if let Some(setting) = setting {
+        out.push_str(match setting {
+            Setting::Interior => &layout.interior,
+            Setting::Exterior => &layout.exterior,
+            Setting::Both => &layout.interior_exterior,
+        });
+        out.push(' ');
+    }
+    out.push_str(place);
+    if let Some(day) = day {
+        out.push_str(" - ");
+        out.push_str(if day { &layout.day } else { &layout.night });
+    }
+    out
+}
+
+/// Dialog is written as `NAME: line`, optionally starting with a
+/// `(parenthetical)`. Lines without a speaker continue the last one's
+/// speech until a blank line; before any speaker they are action.
+fn push_dialog(elements: &mut Vec<Element>, dialog: &str) {
+    let mut speaking = false;
+    for line in dialog.lines().map(str::trim) {
+        if line.is_empty() {
+            speaking = false;
+            continue;
+        }
+        let speech = match line.split_once(':') {
+            Some((name, rest))
+                if !name.trim().is_empty()
+                    && name.len() <= MAX_SPEAKER_LEN
+                    && !name.contains('(') =>
+            {
+                elements.push(Element::Character(name.trim().to_uppercase()));
+                speaking = true;
+                rest.trim()
+            }
+            _ if speaking => line,
+            _ => {
+                elements.push(Element::Action(line.to_string()));
+                continue;
+            }
+        };
+        let speech = match speech.strip_prefix('(').and_then(|s| s.split_once(')')) {
+            Some((direction, rest)) => {
+                elements.push(Element::Parenthetical(format!("({})", direction.trim())));
+                rest.trim()
+            }
+            None => speech,
+        };
+        if !speech.is_empty() {
+            elements.push(Element::Dialogue(speech.to_string()));
+        }
+    }
+}
+
+fn script(layout: &Layout, scenes: &[Scene]) -> Vec<Element> {
+    let mut elements = Vec::new();
+    for scene in scenes {
+        elements.push(Element::Heading(
+            heading(layout, scene),
+            numbering::label(scene.scene_number, &scene.scene_suffix),
+        ));
+        for paragraph in scene.description.split("\n\n") {
+            if !paragraph.trim().is_empty() {
+                elements.push(Element::Action(paragraph.trim().to_string()));
+            }
+        }
+        push_dialog(&mut elements, &scene.dialog);
+    }
+    elements
+}
+
+/// Fountain only recognises English setting keywords; other headings are
+/// forced with a leading `.`.
+fn fountain_heading(heading: &str) -> String {
+    let english = ["INT", "EXT", "EST", "INT./EXT", "INT/EXT", "I/E"]
+        .iter()
+        .any(|k| {
+            heading
+                .strip_prefix(k)
+                .is_some_and(|rest| rest.starts_with(['.', ' ']))
+        });
+    if english {
+        heading.to_string()
+    } else {
+        format!(".{}", heading)
+    }
+}
+
+fn render_fountain(project: &ProjectInfo, layout: &Layout, elements: &[Element]) -> String {
+    let mut out = format!("Title: {}\n", project.name);
+    for element in elements {
+        match element {
+            Element::Heading(text, number) => {
+                let _ = write!(out, "\n{}", fountain_heading(text));
+                if layout.numbered_headings {
+                    let _ = write!(out, " #{}#", number);
+                }
+                out.push('\n');
+            }
+            Element::Action(text) => {
+                // An all-caps line would read as a character cue.
+                let force = text.lines().count() == 1
+                    && text.chars().any(char::is_alphabetic)
+                    && text.to_uppercase() == *text;
+                let _ = writeln!(out, "\n{}{}", if force { "!" } else { "" }, text);
+            }
+            Element::Character(name) => {
+                let force = !name.chars().any(|c| c.is_ascii_uppercase());
+                let _ = writeln!(out, "\n{}{}", if force { "@" } else { "" }, name);
+            }
+            Element::Parenthetical(text) | Element::Dialogue(text) => {
+                let _ = writeln!(out, "{}", text);
+            }
+        }
+    }
+    out
+}
+
+struct Cursor<'a> {
+    doc: Document<'a>,
+    page: Page<'a>,
+    number: usize,
+    y: f32,
+}
+
+impl<'a> Cursor<'a> {
+    fn new_page(&mut self) {
+        let next = self.doc.page();
+        self.doc.push(std::mem::replace(&mut self.page, next));
+        self.number += 1;
+        self.y = POINTS_PER_INCH;
+        // Page numbers top right, from the second page of the script.
+        if self.number > 1 {
+            let label = format!("{}.", self.number);
+            let x =
+                self.doc.width - POINTS_PER_INCH - pdf::text_width(&label, FONT_SIZE, Font::Mono);
+            self.page
+                .text(x, POINTS_PER_INCH / 2.0, FONT_SIZE, Font::Mono, &label);
+        }
+    }
+}
+
+fn render_pdf(
+    project: &ProjectInfo,
+    layout: &Layout,
+    elements: &[Element],
+    provenance: &Provenance,
+) -> Vec<u8> {
+    let inch = POINTS_PER_INCH;
+    let (w, h) = (layout.width, layout.height);
+    let bottom = h - inch;
+    let mut doc = Document::new(w, h);
+    doc.set_metadata(provenance.xmp());
+    let mut title_page = doc.page();
+    let title = project.name.to_uppercase();
+    let title_x = (w - pdf::text_width(&title, FONT_SIZE, Font::Mono)) / 2.0;
+    title_page.text(title_x.max(inch), h / 3.0, FONT_SIZE, Font::Mono, &title);
+    let mut cursor = Cursor {
+        page: title_page,
+        doc,
+        number: 0,
+        y: inch,
+    };
+    cursor.new_page();
+
+    let dialogue_right = layout.dialogue_left + layout.dialogue_width;
+    for (i, element) in elements.iter().enumerate() {
+        let (left, right, text) = match element {
+            Element::Heading(text, _) | Element::Action(text) => {
+                (layout.action_left, w / inch - 1.0, text)
+            }
+            Element::Character(text) => (layout.character_left, w / inch - 1.0, text),
+            Element::Parenthetical(text) => (layout.parenthetical_left, dialogue_right, text),
+            Element::Dialogue(text) => (layout.dialogue_left, dialogue_right, text),
+        };
+        let spaced = i > 0
+            && matches!(
+                element,
+                Element::Heading(..) | Element::Action(_) | Element::Character(_)
+            );
+        let lines = pdf::wrap(
+            text,
+            ((right - left) * inch).max(inch),
+            FONT_SIZE,
+            Font::Mono,
+        );
+        // Keep headings and cues with at least the line after them.
+        let keep = match element {
+            Element::Heading(..) | Element::Character(_) => 2.0,
+            _ => 1.0,
+        };
+        if spaced {
+            cursor.y += LEADING;
+        }
+        if cursor.y + keep * LEADING > bottom {
+            cursor.new_page();
+        }
+        if let Element::Heading(_, number) = element {
+            if layout.numbered_headings {
+                let label = format!("{}.", number);
+                let x = left * inch - pdf::text_width(&label, FONT_SIZE, Font::Mono) - 12.0;
+                cursor
+                    .page
+                    .text(x.max(12.0), cursor.y, FONT_SIZE, Font::Mono, &label);
+            }
+        }
+        for line in lines {
+            if cursor.y + LEADING > bottom {
+                cursor.new_page();
+            }
+            cursor
+                .page
+                .text(left * inch, cursor.y, FONT_SIZE, Font::Mono, &line);
+            cursor.y += LEADING;
+        }
+    }
+    let Cursor { mut doc, page, .. } = cursor;
+    doc.push(page);
+    doc.finish()
+}
+
+fn load(conn: &Connection, project_id: &str) -> AppResult<(ProjectInfo, Layout, Vec<Element>)> {
+    let project = export::load_project(conn, project_id)?;
+    let layout = load_layout(conn, project_id)?;
+    let elements = script(&layout, &scenes::list_for_project(conn, project_id)?);
+    Ok((project, layout, elements))
+}
+
+#[tauri::command]
+pub fn list_screenplay_conventions() -> Vec<Convention> {
+    CONVENTIONS.to_vec()
+}
+
+/// The screenplay settings of a project that exists.
+pub fn format(conn: &Connection, project_id: &str) -> AppResult<ScreenplayFormat> {
+    projects::get_project(conn, project_id)?;
+    project_format(conn, project_id)
+}
+
+#[tauri::command]
+pub fn get_screenplay_format(
+    db: State<'_, Database>,
+    project_id: String,
+) -> AppResult<ScreenplayFormat> {
+    format(&db.conn(), &project_id)
+}
+
+/// Replace a project's screenplay settings; an empty object goes back to
+/// the convention matching the app language.
+pub fn set_format(
+    conn: &Connection,
+    project_id: &str,
+    format: &ScreenplayFormat,
+) -> AppResult<ScreenplayFormat> {
+    format.check()?;
+    projects::ensure_unlocked(conn, project_id)?;
+    let before = project_format(conn, project_id)?;
+    conn.execute(
+        "UPDATE projects SET screenplay_json = ?2 WHERE id = ?1",
+        params![project_id, serde_json::to_string(format)?],
+    )?;
+    if before != *format {
+        activity::record(
+            conn,
+            project_id,
+            "project",
+            project_id,
+            "updated",
+            "Changed screenplay format",
+            &[FieldChange {
+                field: "screenplay".into(),
+                old: serde_json::to_string(&before)?,
+                new: serde_json::to_string(format)?,
+            }],
+        )?;
+    }
+    Ok(format.clone())
+}
+
+#[tauri::command]
+pub fn set_screenplay_format(
+    db: State<'_, Database>,
+    project_id: String,
+    format: ScreenplayFormat,
+) -> AppResult<ScreenplayFormat> {
+    set_format(&db.conn(), &project_id, &format)
+}
+
+/// Write the project's screenplay as a PDF. Returns the path.
+pub fn write_pdf(conn: &Connection, project_id: &str, path: &str) -> AppResult<String> {
+    analytics::track(conn, project_id, "export", "screenplay_pdf");
+    let (project, layout, elements) = load(conn, project_id)?;
+    let provenance = provenance::for_project(conn, project_id)?;
+    let out = export::resolve_output(path, "screenplay.pdf")?;
+    std::fs::write(&out, render_pdf(&project, &layout, &elements, &provenance))?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_screenplay_pdf(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+) -> AppResult<String> {
+    write_pdf(&db.conn(), &project_id, &path)
+}
+
+/// Write the project's screenplay as a Fountain file. Returns the path.
+pub fn write_fountain(conn: &Connection, project_id: &str, path: &str) -> AppResult<String> {
+    analytics::track(conn, project_id, "export", "fountain");
+    let (project, layout, elements) = load(conn, project_id)?;
+    let out = export::resolve_output(path, "screenplay.fountain")?;
+    std::fs::write(&out, render_fountain(&project, &layout, &elements))?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_fountain(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+) -> AppResult<String> {
+    write_fountain(&db.conn(), &project_id, &path)
+}
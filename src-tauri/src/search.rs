@@ -0,0 +1,103 @@
+use crate::db::Database;
+use crate::error::AppResult;
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::State;
+use ts_rs::TS;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub id: String,
+    pub project_id: String,
+    pub project_name: String,
+    pub title: String,
+    /// Matching text with hits wrapped in `[` `]`.
+    pub snippet: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SearchResults {
+    pub projects: Vec<SearchHit>,
+    pub characters: Vec<SearchHit>,
+    pub scenes: Vec<SearchHit>,
+}
+
+/// Turn free text into an FTS5 query: every word must match, as a prefix,
+/// and FTS syntax characters in the input are treated as plain text.
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("\"{}\"*", t))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn hits(
+    conn: &Connection,
+    entity_type: &str,
+    fts: &str,
+    limit: usize,
+) -> AppResult<Vec<SearchHit>> {
+    let table = match entity_type {
+        "project" => "projects",
+        "character" => "characters",
+        _ => "scenes",
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT s.entity_id, s.project_id, p.name, s.title,
+                snippet(search_index, -1, '[', ']', '…', 12), coalesce(e.created_at, '')
+         FROM search_index s
+         JOIN projects p ON p.id = s.project_id
+         LEFT JOIN {} e ON e.id = s.entity_id
+         WHERE search_index MATCH ?1 AND s.entity_type = ?2 AND p.archived_at IS NULL
+         ORDER BY rank
+         LIMIT ?3",
+        table
+    ))?;
+    let hits = stmt
+        .query_map(rusqlite::params![fts, entity_type, limit as i64], |r| {
+            Ok(SearchHit {
+                id: r.get(0)?,
+                project_id: r.get(1)?,
+                project_name: r.get(2)?,
+                title: r.get(3)?,
+                snippet: r.get(4)?,
+                created_at: r.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(hits)
+}
+
+/// Search project names and synopses, characters and scenes across every
+/// project but archived ones, best matches first within each group.
+/// `limit` caps each group.
+pub fn search(conn: &Connection, query: &str, limit: Option<usize>) -> AppResult<SearchResults> {
+    let Some(fts) = fts_query(query) else {
+        return Ok(SearchResults::default());
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let results = SearchResults {
+        projects: hits(conn, "project", &fts, limit)?,
+        characters: hits(conn, "character", &fts, limit)?,
+        scenes: hits(conn, "scene", &fts, limit)?,
+    };
+    Ok(results)
+}
+
+#[tauri::command]
+pub fn global_search(
+    db: State<'_, Database>,
+    query: String,
+    limit: Option<usize>,
+) -> AppResult<SearchResults> {
+    search(&db.conn(), &query, limit)
+}
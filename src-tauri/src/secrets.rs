@@ -0,0 +1,25 @@
+//! Credentials that shouldn't show up in `get_setting` or exports.
+//! Values are kept in the settings table under a `secret.` prefix that the
+//! settings commands refuse to read, so callers go through here.
+
+use crate::error::AppResult;
+use crate::settings;
+use rusqlite::Connection;
+
+pub const PREFIX: &str = "secret.";
+
+pub fn get(conn: &Connection, name: &str) -> AppResult<Option<String>> {
+    Ok(settings::get(conn, &format!("{}{}", PREFIX, name))?.filter(|v| !v.is_empty()))
+}
+
+pub fn set(conn: &Connection, name: &str, value: &str) -> AppResult<()> {
+    settings::set(conn, &format!("{}{}", PREFIX, name), value)
+}
+
+pub fn delete(conn: &Connection, name: &str) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM settings WHERE key = ?1",
+        [format!("{}{}", PREFIX, name)],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! Key/value settings. Most keys are personal: while a user profile is
+//! active (see `users`) they are read from and written to that user's
+//! scope, falling back to the shared value. Credentials and settings tied
+//! to this machine are always shared.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::{backup, diagnostics, failures, secrets, users};
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::State;
+
+/// Settings holding a credential in the clear.
+const CREDENTIAL_PREFIXES: &[&str] = &["api_key."];
+const CREDENTIAL_KEYS: &[&str] = &[backup::ACCESS_KEY_SETTING];
+
+/// Settings tied to this machine or its current state.
+const LOCAL_PREFIXES: &[&str] = &["tools.", "notifications.batch.", "users."];
+const LOCAL_KEYS: &[&str] = &[
+    failures::QUEUE_PAUSED_SETTING,
+    backup::LAST_RUN_SETTING,
+    diagnostics::PROBE_SETTING,
+];
+
+pub fn is_credential(key: &str) -> bool {
+    key.starts_with(secrets::PREFIX)
+        || CREDENTIAL_PREFIXES.iter().any(|p| key.starts_with(p))
+        || CREDENTIAL_KEYS.contains(&key)
+}
+
+pub fn is_machine_local(key: &str) -> bool {
+    LOCAL_PREFIXES.iter().any(|p| key.starts_with(p)) || LOCAL_KEYS.contains(&key)
+}
+
+/// The user whose scope holds `key`, if it is personal and a user is active.
+fn user_scope(key: &str) -> Option<String> {
+    users::active().filter(|_| !is_credential(key) && !is_machine_local(key))
+}
+
+pub fn get(conn: &Connection, key: &str) -> AppResult<Option<String>> {
+    if let Some(user_id) = user_scope(key) {
+        let own = conn
+            .query_row(
+                "SELECT value FROM user_settings WHERE user_id = ?1 AND key = ?2",
+                params![user_id, key],
+                |r| r.get(0),
+            )
+            .optional()?;
+        if own.is_some() {
+            return Ok(own);
+        }
+    }
+    Ok(conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |r| {
+            r.get(0)
+        })
+        .optional()?)
+}
+
+pub fn get_bool(conn: &Connection, key: &str) -> AppResult<bool> {
+    Ok(matches!(
+        get(conn, key)?.as_deref(),
+        Some("true") | Some("1")
+    ))
+}
+
+pub fn set(conn: &Connection, key: &str, value: &str) -> AppResult<()> {
+    if let Some(user_id) = user_scope(key) {
+        conn.execute(
+            "INSERT INTO user_settings (user_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(user_id, key) DO UPDATE SET value = excluded.value",
+            params![user_id, key, value],
+        )?;
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// Secrets are only reachable through the commands that own them.
+fn check_key(key: &str) -> AppResult<()> {
+    if key.starts_with(secrets::PREFIX) {
+        return Err(AppError::Invalid(format!("'{}' is not a setting", key)));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_setting(db: State<'_, Database>, key: String) -> AppResult<Option<String>> {
+    check_key(&key)?;
+    get(&db.conn(), &key)
+}
+
+#[tauri::command]
+pub fn set_setting(db: State<'_, Database>, key: String, value: String) -> AppResult<()> {
+    check_key(&key)?;
+    set(&db.conn(), &key, &value)
+}
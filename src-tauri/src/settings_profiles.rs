@@ -0,0 +1,358 @@
+//! Settings profiles: everything that makes a workstation feel set up
+//! (provider and tool preferences, style presets, camera and lighting
+//! vocabulary, webhooks) as one JSON file that can be imported on another
+//! machine.
+//!
+//! Credentials never go in a profile. API keys, the secrets store and the
+//! backup access key are left out, and the profile lists which of them
+//! were set so the importing machine can ask for them again. Settings that
+//! only make sense on the exporting machine (tool paths, queue state,
+//! timestamps) are left out as well. With a user profile active, the
+//! profile holds that user's settings and importing writes to their scope.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::notifications::{NotifyEvent, WEBHOOK_KINDS};
+use crate::settings::{self, is_credential, is_machine_local};
+use crate::vocabulary::{self, VocabularyKind};
+use crate::{export, ics, users};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::State;
+use ts_rs::TS;
+
+/// Bumped when the profile layout changes incompatibly.
+pub const PROFILE_FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SettingsProfile {
+    pub format: u32,
+    pub exported_at: String,
+    pub app_version: String,
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    /// User-made presets; the built-in ones ship with every install.
+    #[serde(default)]
+    pub style_presets: Vec<ProfilePreset>,
+    #[serde(default)]
+    pub camera_angles: Vec<ProfileTerm>,
+    #[serde(default)]
+    pub lighting: Vec<ProfileTerm>,
+    /// Webhooks without their URLs, which carry tokens.
+    #[serde(default)]
+    pub webhooks: Vec<ProfileWebhook>,
+    /// Setting keys of the credentials that were set when exporting, e.g.
+    /// `api_key.fal` or `secret.webhook.<id>.url`. Their values are not in
+    /// the profile.
+    #[serde(default)]
+    pub credentials: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilePreset {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub guidance: String,
+    #[serde(default)]
+    pub keywords: String,
+    #[serde(default)]
+    pub negative_prompt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileTerm {
+    pub key: String,
+    pub label: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub prompt_fragment: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileWebhook {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    #[serde(default)]
+    pub events: Vec<NotifyEvent>,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProfileImport {
+    pub settings: usize,
+    pub style_presets: usize,
+    pub vocabulary_terms: usize,
+    pub webhooks: usize,
+    /// Credentials the profile's machine had that this one doesn't; the
+    /// settings screen asks for these.
+    pub missing_credentials: Vec<String>,
+}
+
+fn terms(conn: &Connection, kind: VocabularyKind) -> AppResult<Vec<ProfileTerm>> {
+    Ok(vocabulary::list_terms(conn, kind)?
+        .into_iter()
+        .map(|t| ProfileTerm {
+            key: t.key,
+            label: t.label,
+            description: t.description,
+            prompt_fragment: t.prompt_fragment,
+        })
+        .collect())
+}
+
+fn build(conn: &Connection) -> AppResult<SettingsProfile> {
+    let mut settings = BTreeMap::new();
+    let mut credentials = Vec::new();
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (key, value) in rows {
+        if is_credential(&key) {
+            if !value.is_empty() {
+                credentials.push(key);
+            }
+        } else if !is_machine_local(&key) {
+            settings.insert(key, value);
+        }
+    }
+    // The active user's own values win over the shared ones.
+    if let Some(user_id) = users::active() {
+        let mut stmt = conn.prepare("SELECT key, value FROM user_settings WHERE user_id = ?1")?;
+        let rows = stmt
+            .query_map([&user_id], |r| {
+                Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (key, value) in rows {
+            if !is_credential(&key) && !is_machine_local(&key) {
+                settings.insert(key, value);
+            }
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, guidance, keywords, negative_prompt
+         FROM style_presets WHERE builtin = 0 ORDER BY created_at, rowid",
+    )?;
+    let style_presets = stmt
+        .query_map([], |r| {
+            Ok(ProfilePreset {
+                id: r.get(0)?,
+                name: r.get(1)?,
+                description: r.get(2)?,
+                guidance: r.get(3)?,
+                keywords: r.get(4)?,
+                negative_prompt: r.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, kind, events_json, template, enabled
+         FROM webhooks ORDER BY created_at, rowid",
+    )?;
+    let webhooks = stmt
+        .query_map([], |r| {
+            let events_json: String = r.get(3)?;
+            Ok(ProfileWebhook {
+                id: r.get(0)?,
+                name: r.get(1)?,
+                kind: r.get(2)?,
+                events: serde_json::from_str(&events_json).unwrap_or_default(),
+                template: r.get(4)?,
+                enabled: r.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(SettingsProfile {
+        format: PROFILE_FORMAT,
+        exported_at: ics::utc_stamp(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        settings,
+        style_presets,
+        camera_angles: terms(conn, VocabularyKind::CameraAngle)?,
+        lighting: terms(conn, VocabularyKind::Lighting)?,
+        webhooks,
+        credentials,
+    })
+}
+
+/// The profile at `path`, refused when a newer app wrote it.
+pub fn read_profile(path: &str) -> AppResult<SettingsProfile> {
+    let profile: SettingsProfile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if profile.format > PROFILE_FORMAT {
+        return Err(AppError::Invalid(format!(
+            "settings profile format {} is newer than this app supports; update the app",
+            profile.format
+        )));
+    }
+    Ok(profile)
+}
+
+fn upsert_terms(conn: &Connection, table: &str, terms: &[ProfileTerm]) -> AppResult<usize> {
+    let mut count = 0;
+    for term in terms {
+        let key = term.key.trim().to_lowercase();
+        if key.is_empty() || term.label.trim().is_empty() {
+            continue;
+        }
+        conn.execute(
+            &format!(
+                "INSERT INTO {} (key, label, description, prompt_fragment, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM {0}))
+                 ON CONFLICT(key) DO UPDATE SET
+                    label = excluded.label,
+                    description = excluded.description,
+                    prompt_fragment = excluded.prompt_fragment",
+                table
+            ),
+            params![
+                key,
+                term.label.trim(),
+                term.description,
+                term.prompt_fragment
+            ],
+        )?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Apply a profile on top of the current settings. Entries with the same
+/// key or id are overwritten; nothing is deleted. Credentials and
+/// machine-local settings are ignored even if a hand-edited profile has
+/// them.
+pub fn apply(conn: &Connection, profile: &SettingsProfile) -> AppResult<ProfileImport> {
+    let tx = conn.unchecked_transaction()?;
+    let mut result = ProfileImport {
+        settings: 0,
+        style_presets: 0,
+        vocabulary_terms: 0,
+        webhooks: 0,
+        missing_credentials: Vec::new(),
+    };
+
+    for (key, value) in &profile.settings {
+        if is_credential(key) || is_machine_local(key) {
+            continue;
+        }
+        settings::set(&tx, key, value)?;
+        result.settings += 1;
+    }
+
+    for preset in &profile.style_presets {
+        if preset.name.trim().is_empty() {
+            continue;
+        }
+        // A built-in preset with the same id stays as shipped.
+        let changed = tx.execute(
+            "INSERT INTO style_presets (id, name, description, guidance, keywords, negative_prompt)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                description = excluded.description,
+                guidance = excluded.guidance,
+                keywords = excluded.keywords,
+                negative_prompt = excluded.negative_prompt
+             WHERE builtin = 0",
+            params![
+                preset.id,
+                preset.name.trim(),
+                preset.description,
+                preset.guidance,
+                preset.keywords,
+                preset.negative_prompt
+            ],
+        )?;
+        result.style_presets += changed;
+    }
+
+    result.vocabulary_terms = upsert_terms(&tx, "camera_angles", &profile.camera_angles)?
+        + upsert_terms(&tx, "lighting_styles", &profile.lighting)?;
+
+    for hook in &profile.webhooks {
+        if hook.name.trim().is_empty() || !WEBHOOK_KINDS.contains(&hook.kind.as_str()) {
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO webhooks (id, name, kind, events_json, template, enabled)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                kind = excluded.kind,
+                events_json = excluded.events_json,
+                template = excluded.template,
+                enabled = excluded.enabled",
+            params![
+                hook.id,
+                hook.name.trim(),
+                hook.kind,
+                serde_json::to_string(&hook.events)?,
+                hook.template,
+                hook.enabled
+            ],
+        )?;
+        result.webhooks += 1;
+    }
+
+    for key in &profile.credentials {
+        if !is_credential(key) {
+            continue;
+        }
+        let set = settings::get(&tx, key)?.is_some_and(|v| !v.is_empty());
+        if !set {
+            result.missing_credentials.push(key.clone());
+        }
+    }
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Write this machine's settings profile to `path` (a file, or a folder to
+/// put `settings.aidc-profile.json` in). Returns the path written.
+pub fn write_profile(conn: &Connection, path: &str) -> AppResult<String> {
+    let profile = build(conn)?;
+    let out = export::resolve_output(path, "settings.aidc-profile.json")?;
+    std::fs::write(&out, serde_json::to_string_pretty(&profile)?)?;
+    Ok(out.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn export_settings_profile(db: State<'_, Database>, path: String) -> AppResult<String> {
+    write_profile(&db.conn(), &path)
+}
+
+/// What a profile contains, for showing before importing it.
+#[tauri::command]
+pub fn read_settings_profile(path: String) -> AppResult<SettingsProfile> {
+    read_profile(&path)
+}
+
+/// Apply the profile at `path`; see `apply`.
+pub fn import_profile(conn: &Connection, path: &str) -> AppResult<ProfileImport> {
+    let profile = read_profile(path)?;
+    apply(conn, &profile)
+}
+
+#[tauri::command]
+pub fn import_settings_profile(db: State<'_, Database>, path: String) -> AppResult<ProfileImport> {
+    import_profile(&db.conn(), &path)
+}
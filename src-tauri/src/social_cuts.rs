@@ -0,0 +1,278 @@
+//! Vertical (9:16) and square (1:1) versions of a finished render for
+//! social platforms, cropped and scaled with ffmpeg.
+//!
+//! With reframing on, the crop window follows each scene's focal point,
+//! set with `set_scene_focus`; scenes without one are cropped around the
+//! centre. Scenes are placed in the render with
+//! `publishing::scene_timeline`.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::frames;
+use crate::projects;
+use crate::provenance;
+use crate::publishing::{self, Render};
+use crate::scenes;
+use crate::validation::Validator;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Supported aspects with their output size.
+const ASPECTS: &[(&str, u32, u32)] = &[("9:16", 1080, 1920), ("1:1", 1080, 1080)];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Focus {
+    /// 0 (left) to 1 (right).
+    pub x: f64,
+    /// 0 (top) to 1 (bottom).
+    pub y: f64,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Focus { x: 0.5, y: 0.5 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneFocus {
+    pub scene_id: String,
+    pub focus: Focus,
+}
+
+fn focus_by_scene(conn: &Connection, project_id: &str) -> AppResult<HashMap<String, Focus>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.scene_id, f.x, f.y FROM scene_focus f JOIN scenes s ON s.id = f.scene_id
+         WHERE s.project_id = ?1",
+    )?;
+    let rows = stmt
+        .query_map([project_id], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                Focus {
+                    x: r.get(1)?,
+                    y: r.get(2)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+    Ok(rows)
+}
+
+/// An ffmpeg expression in `t` that steps through `values`, each holding
+/// from its start time until the next one's.
+fn timed(values: &[(f64, f64)]) -> String {
+    let Some((&(_, last), rest)) = values.split_last() else {
+        return "0.5".into();
+    };
+    rest.iter()
+        .rev()
+        .zip(values.iter().skip(1).rev())
+        .fold(format!("{:.4}", last), |expr, (&(_, value), &(end, _))| {
+            format!("if(lt(t,{:.3}),{:.4},{})", end, value, expr)
+        })
+}
+
+/// The crop-and-scale filter for `aspect`, following `focus` (start time
+/// and focal point per segment).
+pub fn filter(aspect: &str, focus: &[(f64, Focus)]) -> AppResult<String> {
+    let &(_, width, height) = ASPECTS
+        .iter()
+        .find(|(a, _, _)| *a == aspect)
+        .ok_or_else(|| AppError::Invalid(format!("unsupported aspect {}", aspect)))?;
+    let ratio = width as f64 / height as f64;
+    let xs: Vec<(f64, f64)> = focus.iter().map(|(t, f)| (*t, f.x)).collect();
+    let ys: Vec<(f64, f64)> = focus.iter().map(|(t, f)| (*t, f.y)).collect();
+    // Quoted so the commas inside stay part of each expression.
+    Ok(format!(
+        "crop=w='min(iw,ih*{r:.6})':h='min(ih,iw/{r:.6})':\
+         x='max(0,min(iw-ow,({fx})*iw-ow/2))':y='max(0,min(ih-oh,({fy})*ih-oh/2))',\
+         scale={w}:{h},setsar=1",
+        r = ratio,
+        fx = timed(&xs),
+        fy = timed(&ys),
+        w = width,
+        h = height
+    ))
+}
+
+fn output_path(render: &Path, aspect: &str) -> PathBuf {
+    let stem = render
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "render".into());
+    render.with_file_name(format!("{}-{}.mp4", stem, aspect.replace(':', "x")))
+}
+
+/// The focal points set on a project's scenes.
+pub fn scene_focus(conn: &Connection, project_id: &str) -> AppResult<Vec<SceneFocus>> {
+    Ok(focus_by_scene(conn, project_id)?
+        .into_iter()
+        .map(|(scene_id, focus)| SceneFocus { scene_id, focus })
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_scene_focus(db: State<'_, Database>, project_id: String) -> AppResult<Vec<SceneFocus>> {
+    scene_focus(&db.conn(), &project_id)
+}
+
+/// Set where the subject of a scene sits in the frame; None goes back to
+/// the centre.
+pub fn set_focus(conn: &Connection, scene_id: &str, focus: Option<Focus>) -> AppResult<()> {
+    let mut v = Validator::new();
+    v.uuid("sceneId", scene_id);
+    if let Some(f) = focus {
+        if !(0.0..=1.0).contains(&f.x) || !(0.0..=1.0).contains(&f.y) {
+            v.error("focus", "must be within the frame (0 to 1)");
+        }
+    }
+    v.finish()?;
+    projects::ensure_scene_unlocked(conn, scene_id)?;
+    scenes::get_scene(conn, scene_id)?;
+    match focus {
+        Some(f) => conn.execute(
+            "INSERT INTO scene_focus (scene_id, x, y) VALUES (?1, ?2, ?3)
+             ON CONFLICT(scene_id) DO UPDATE SET x = excluded.x, y = excluded.y",
+            params![scene_id, f.x, f.y],
+        )?,
+        None => conn.execute("DELETE FROM scene_focus WHERE scene_id = ?1", [scene_id])?,
+    };
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_scene_focus(
+    db: State<'_, Database>,
+    scene_id: String,
+    focus: Option<Focus>,
+) -> AppResult<()> {
+    set_focus(&db.conn(), &scene_id, focus)
+}
+
+/// A render checked and ready to cut, from `prepare`.
+pub struct SocialCut {
+    render: Render,
+    aspect: String,
+    binary: PathBuf,
+    focus: Vec<(f64, Focus)>,
+    metadata: Vec<String>,
+}
+
+impl SocialCut {
+    pub fn project_id(&self) -> &str {
+        &self.render.project_id
+    }
+}
+
+/// Check `aspect` and that the render's file is still there, and work out
+/// where the crop sits for each scene. `reframe` follows each scene's
+/// focal point; off crops around the centre.
+pub fn prepare(
+    conn: &Connection,
+    render_id: &str,
+    aspect: &str,
+    reframe: bool,
+) -> AppResult<SocialCut> {
+    let aspects: Vec<&str> = ASPECTS.iter().map(|(a, _, _)| *a).collect();
+    let mut v = Validator::new();
+    v.uuid("renderId", render_id)
+        .one_of("aspect", aspect, &aspects);
+    v.finish()?;
+    let render = publishing::get_render(conn, render_id)?;
+    let mut focus = vec![(0.0, Focus::default())];
+    if reframe {
+        let points = focus_by_scene(conn, &render.project_id)?;
+        focus.clear();
+        for (start, scene) in publishing::scene_timeline(conn, &render.project_id)? {
+            focus.push((start, points.get(&scene.id).copied().unwrap_or_default()));
+        }
+        if focus.is_empty() {
+            focus.push((0.0, Focus::default()));
+        }
+    }
+    let metadata = provenance::for_project(conn, &render.project_id)?.ffmpeg_args();
+    let binary = frames::ffmpeg_binary(conn)?;
+    if !Path::new(&render.path).is_file() {
+        return Err(AppError::NotFound(format!("render file {}", render.path)));
+    }
+    Ok(SocialCut {
+        render,
+        aspect: aspect.to_string(),
+        binary,
+        focus,
+        metadata,
+    })
+}
+
+/// Run ffmpeg for a prepared cut, writing it next to the render. Returns
+/// the path written.
+pub async fn cut(job: &SocialCut) -> AppResult<PathBuf> {
+    let input = Path::new(&job.render.path);
+    let out = output_path(input, &job.aspect);
+    let output = tokio::process::Command::new(&job.binary)
+        .args(["-v", "error", "-y", "-i"])
+        .arg(input)
+        .args(["-vf", &filter(&job.aspect, &job.focus)?])
+        .args(["-c:v", "libx264", "-preset", "medium", "-crf", "20"])
+        .args([
+            "-pix_fmt",
+            "yuv420p",
+            "-c:a",
+            "aac",
+            "-movflags",
+            "+faststart+use_metadata_tags",
+        ])
+        .args(&job.metadata)
+        .arg(&out)
+        .output()
+        .await
+        .map_err(|e| {
+            AppError::Io(format!(
+                "could not run ffmpeg ({}): {}",
+                job.binary.display(),
+                e
+            ))
+        })?;
+    if !output.status.success() || !out.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("ffmpeg produced no video");
+        return Err(AppError::Io(format!("social cut failed: {}", last)));
+    }
+    Ok(out)
+}
+
+/// Make a 9:16 or 1:1 version of a render, next to it, and record it as a
+/// render of its own so it can be published. `reframe` (the default)
+/// follows each scene's focal point; off crops around the centre.
+#[tauri::command]
+pub async fn generate_social_cut(
+    app: AppHandle,
+    render_id: String,
+    aspect: String,
+    reframe: Option<bool>,
+) -> AppResult<Render> {
+    let job = prepare(
+        &app.state::<Database>().conn(),
+        &render_id,
+        &aspect,
+        reframe.unwrap_or(true),
+    )?;
+    let out = cut(&job).await?;
+    publishing::record_render(
+        &app.state::<Database>().conn(),
+        job.project_id(),
+        &out.to_string_lossy(),
+    )
+}
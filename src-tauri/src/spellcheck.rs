@@ -0,0 +1,349 @@
+//! Spell-checking for prompts and dialog with Spellbook, which reads
+//! Hunspell dictionaries in-process. The dictionaries ship with the app as
+//! resources (`dictionaries/<lang>.aff` and `.dic`, fetched at release
+//! time); more can be dropped into `dictionaries` in the data dir. The
+//! user's own words are a setting, so each user profile keeps its own
+//! dictionary, and the project's character names are always accepted: a
+//! near miss of one is reported with the name as the first suggestion,
+//! since a misspelt name in a prompt quietly loses the character.
+
+use crate::characters;
+use crate::data_dir;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::validation::{Validator, MAX_NAME_LEN, MAX_TEXT_LEN};
+use crate::{i18n, settings};
+use rusqlite::Connection;
+use serde::Serialize;
+use spellbook::Dictionary;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// The user's own words, as a JSON array.
+pub const DICTIONARY_SETTING: &str = "spellcheck.words";
+/// Edits between a misspelling and a character name for the name to be
+/// suggested.
+const MAX_NAME_DISTANCE: usize = 2;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    /// Position in the checked text in UTF-16 code units, as JavaScript
+    /// indexes strings; `end` is exclusive.
+    pub start: usize,
+    pub end: usize,
+    pub suggestions: Vec<String>,
+    /// The character this is probably a misspelling of.
+    pub character: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SpellCheck {
+    /// The Hunspell dictionary used, e.g. `en_US`.
+    pub lang: String,
+    pub misspellings: Vec<Misspelling>,
+}
+
+/// Dictionaries by name, parsed the first time they're used.
+#[derive(Default)]
+pub struct Dictionaries(Mutex<HashMap<String, Arc<Dictionary>>>);
+
+/// Where to look for `<lang>.aff` and `<lang>.dic`: the bundled ones
+/// first, then any the user added.
+fn dictionary_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(resources) = app.path().resource_dir() {
+        dirs.push(resources.join("dictionaries"));
+    }
+    dirs.push(
+        data_dir::configured()
+            .unwrap_or_else(data_dir::default_dir)
+            .join("dictionaries"),
+    );
+    dirs
+}
+
+/// The dictionary `lang`, loaded and parsed on first use.
+fn load(app: &AppHandle, lang: &str) -> AppResult<Arc<Dictionary>> {
+    let loaded = app.state::<Dictionaries>();
+    if let Some(dictionary) = loaded.0.lock().unwrap_or_else(|e| e.into_inner()).get(lang) {
+        return Ok(dictionary.clone());
+    }
+    let dir = dictionary_dirs(app)
+        .into_iter()
+        .find(|dir| {
+            dir.join(format!("{}.aff", lang)).is_file()
+                && dir.join(format!("{}.dic", lang)).is_file()
+        })
+        .ok_or_else(|| AppError::NotFound(format!("the {} dictionary", lang)))?;
+    let aff = std::fs::read_to_string(dir.join(format!("{}.aff", lang)))?;
+    let dic = std::fs::read_to_string(dir.join(format!("{}.dic", lang)))?;
+    let dictionary = Arc::new(
+        Dictionary::new(&aff, &dic)
+            .map_err(|e| AppError::Invalid(format!("the {} dictionary is broken: {}", lang, e)))?,
+    );
+    loaded
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(lang.to_string(), dictionary.clone());
+    Ok(dictionary)
+}
+
+fn dictionary(conn: &Connection) -> AppResult<Vec<String>> {
+    Ok(settings::get(conn, DICTIONARY_SETTING)?
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+fn save_dictionary(conn: &Connection, mut words: Vec<String>) -> AppResult<Vec<String>> {
+    words.sort_by_key(|w| w.to_lowercase());
+    words.dedup();
+    settings::set(conn, DICTIONARY_SETTING, &serde_json::to_string(&words)?)?;
+    Ok(words)
+}
+
+/// The dictionary for the app language.
+fn default_lang(conn: &Connection) -> AppResult<&'static str> {
+    Ok(match i18n::current(conn)?.code() {
+        "en-GB" => "en_GB",
+        "de" => "de_DE",
+        "fr" => "fr_FR",
+        "es" => "es_ES",
+        _ => "en_US",
+    })
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// The closest name part to `word` within `MAX_NAME_DISTANCE`, with the
+/// full name it belongs to.
+fn near_name<'a>(names: &'a [String], word: &str) -> Option<(&'a str, &'a str)> {
+    let word = word.to_lowercase();
+    names
+        .iter()
+        .flat_map(|name| {
+            name.split_whitespace()
+                .map(move |part| (part, name.as_str()))
+        })
+        .filter(|(part, _)| part.chars().count() > MAX_NAME_DISTANCE)
+        .map(|(part, name)| (distance(&word, &part.to_lowercase()), part, name))
+        .filter(|(d, _, _)| *d <= MAX_NAME_DISTANCE)
+        .min_by_key(|(d, _, _)| *d)
+        .map(|(_, part, name)| (part, name))
+}
+
+/// The words of `text` with their UTF-16 start and end: runs of letters,
+/// with apostrophes inside them. Anything with a digit in it is skipped.
+fn words(text: &str) -> Vec<(&str, usize, usize)> {
+    let mut words = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    let mut utf16 = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_alphanumeric = chars.peek().is_some_and(|(_, n)| n.is_alphanumeric());
+        let inside = c.is_alphanumeric()
+            || (matches!(c, '\'' | '\u{2019}') && current.is_some() && next_is_alphanumeric);
+        match (inside, current) {
+            (true, None) => current = Some((i, utf16)),
+            (false, Some((start, start16))) => {
+                words.push((&text[start..i], start16, utf16));
+                current = None;
+            }
+            _ => {}
+        }
+        utf16 += c.len_utf16();
+    }
+    if let Some((start, start16)) = current {
+        words.push((&text[start..], start16, utf16));
+    }
+    words.retain(|(word, _, _)| !word.chars().any(|c| c.is_numeric()));
+    words
+}
+
+/// Check `text` against `dictionary`. `accepted` words, and each part of
+/// the character `names`, are never reported, whatever their case.
+pub fn check(
+    dictionary: &Dictionary,
+    lang: &str,
+    text: &str,
+    accepted: &[String],
+    names: &[String],
+) -> SpellCheck {
+    let accepted: HashSet<String> = accepted
+        .iter()
+        .chain(names)
+        .flat_map(|w| w.split_whitespace())
+        .map(str::to_lowercase)
+        .collect();
+    let mut misspellings = Vec::new();
+    for (word, start, end) in words(text) {
+        if accepted.contains(&word.to_lowercase()) || dictionary.check(word) {
+            continue;
+        }
+        let mut suggestions = Vec::new();
+        dictionary.suggest(word, &mut suggestions);
+        let character = near_name(names, word).map(|(part, name)| {
+            suggestions.retain(|s| s != part);
+            suggestions.insert(0, part.to_string());
+            name.to_string()
+        });
+        misspellings.push(Misspelling {
+            word: word.to_string(),
+            start,
+            end,
+            suggestions,
+            character,
+        });
+    }
+    SpellCheck {
+        lang: lang.to_string(),
+        misspellings,
+    }
+}
+
+/// Text to check, with the words it may use, from `prepare`.
+pub struct TextCheck {
+    text: String,
+    lang: String,
+    accepted: Vec<String>,
+    names: Vec<String>,
+}
+
+impl TextCheck {
+    /// The dictionary to check with, e.g. `en_US`.
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    pub fn run(&self, dictionary: &Dictionary) -> SpellCheck {
+        check(
+            dictionary,
+            &self.lang,
+            &self.text,
+            &self.accepted,
+            &self.names,
+        )
+    }
+}
+
+/// Get `text` ready to check with the dictionary `lang` (e.g. `en_US`;
+/// the app language's by default). Words in the user's dictionary and,
+/// with a `project_id`, the project's character names are accepted.
+pub fn prepare(
+    conn: &Connection,
+    text: &str,
+    lang: Option<&str>,
+    project_id: Option<&str>,
+) -> AppResult<TextCheck> {
+    let mut v = Validator::new();
+    v.max_len("text", text, MAX_TEXT_LEN)
+        .optional_uuid("projectId", project_id);
+    if let Some(lang) = lang {
+        if lang.is_empty()
+            || lang.len() > 16
+            || !lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            v.error("lang", "is not a dictionary name");
+        }
+    }
+    v.finish()?;
+
+    let names = match project_id {
+        Some(id) => characters::list_for_project(conn, id)?
+            .into_iter()
+            .map(|c| c.name)
+            .collect(),
+        None => Vec::new(),
+    };
+    let lang = match lang {
+        Some(lang) => lang,
+        None => default_lang(conn)?,
+    };
+    Ok(TextCheck {
+        text: text.to_string(),
+        lang: lang.to_string(),
+        accepted: dictionary(conn)?,
+        names,
+    })
+}
+
+#[tauri::command]
+pub async fn check_text(
+    app: AppHandle,
+    text: String,
+    lang: Option<String>,
+    project_id: Option<String>,
+) -> AppResult<SpellCheck> {
+    let request = prepare(
+        &app.state::<Database>().conn(),
+        &text,
+        lang.as_deref(),
+        project_id.as_deref(),
+    )?;
+    // Parsing a dictionary and making suggestions both take a while.
+    tauri::async_runtime::spawn_blocking(move || {
+        let dictionary = load(&app, request.lang())?;
+        Ok(request.run(&dictionary))
+    })
+    .await
+    .map_err(|e| AppError::Io(e.to_string()))?
+}
+
+/// The active user's dictionary, alphabetically.
+#[tauri::command]
+pub fn list_dictionary_words(db: State<'_, Database>) -> AppResult<Vec<String>> {
+    dictionary(&db.conn())
+}
+
+/// Add a word to the active user's dictionary. Returns the dictionary.
+pub fn add_word(conn: &Connection, word: &str) -> AppResult<Vec<String>> {
+    let mut v = Validator::new();
+    v.required("word", word)
+        .max_len("word", word.trim(), MAX_NAME_LEN);
+    v.finish()?;
+    let mut words = dictionary(conn)?;
+    words.push(word.trim().to_string());
+    save_dictionary(conn, words)
+}
+
+#[tauri::command]
+pub fn add_dictionary_word(db: State<'_, Database>, word: String) -> AppResult<Vec<String>> {
+    add_word(&db.conn(), &word)
+}
+
+/// Remove a word from the active user's dictionary. Returns the
+/// dictionary.
+pub fn remove_word(conn: &Connection, word: &str) -> AppResult<Vec<String>> {
+    let mut words = dictionary(conn)?;
+    words.retain(|w| w != word.trim());
+    save_dictionary(conn, words)
+}
+
+#[tauri::command]
+pub fn remove_dictionary_word(db: State<'_, Database>, word: String) -> AppResult<Vec<String>> {
+    remove_word(&db.conn(), &word)
+}
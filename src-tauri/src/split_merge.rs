@@ -0,0 +1,456 @@
+//! Splitting one scene in two and merging several into one. Each touches
+//! the scene rows, cast, dialog, takes, comments, tags, dependency links
+//! and numbering, so it runs as one transaction here rather than as a
+//! string of client calls that can fail halfway.
+
+use crate::activity::{self, FieldChange};
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::numbering::{self, RenumberStrategy, Renumbered};
+use crate::ordering;
+use crate::scene_characters::{self, CastMember};
+use crate::scene_links;
+use crate::scenes::{self, Scene};
+use crate::validation::MAX_SCENE_DURATION;
+use crate::workflow::{self, SceneStatus};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SplitResult {
+    /// The original scene, now holding the first part.
+    pub first: Scene,
+    /// The new scene right after it on the board.
+    pub second: Scene,
+    pub renumbered: Vec<Renumbered>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct MergeResult {
+    pub scene: Scene,
+    /// Scenes folded into `scene` and deleted.
+    pub removed: Vec<String>,
+    pub renumbered: Vec<Renumbered>,
+}
+
+/// Byte index of the `chars`th character of `text`.
+fn byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// Cast members whose name appears in `text`, case-insensitively.
+fn mentioned<'a>(cast: &'a [(CastMember, String)], text: &str) -> Vec<&'a CastMember> {
+    let text = text.to_lowercase();
+    cast.iter()
+        .filter(|(_, name)| text.contains(&name.to_lowercase()))
+        .map(|(m, _)| m)
+        .collect()
+}
+
+/// Split a scene's cast (with names) between its two halves by who each
+/// half mentions. When neither half names anyone, both keep the whole cast.
+fn split_cast(
+    cast: &[(CastMember, String)],
+    first: &str,
+    second: &str,
+) -> (Vec<CastMember>, Vec<CastMember>) {
+    let a = mentioned(cast, first);
+    let b = mentioned(cast, second);
+    let everyone = || cast.iter().map(|(m, _)| m.clone()).collect::<Vec<_>>();
+    if a.is_empty() && b.is_empty() {
+        return (everyone(), everyone());
+    }
+    // Anyone named in neither half stays with the first.
+    let first = everyone()
+        .into_iter()
+        .filter(|m| a.contains(&m) || !b.contains(&m))
+        .collect();
+    (first, b.into_iter().cloned().collect())
+}
+
+fn ids(cast: &[CastMember]) -> Vec<String> {
+    cast.iter().map(|m| m.character_id.clone()).collect()
+}
+
+fn board_neighbour_after(conn: &Connection, scene: &Scene) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT order_key FROM scenes WHERE project_id = ?1 AND id != ?2 AND order_key > ?3
+             ORDER BY order_key LIMIT 1",
+            params![scene.project_id, scene.id, scene.order_key],
+            |r| r.get(0),
+        )
+        .optional()?)
+}
+
+fn split_at(
+    conn: &Connection,
+    scene_id: &str,
+    at_description_offset: usize,
+    dialog_line: Option<usize>,
+    strategy: RenumberStrategy,
+) -> AppResult<SplitResult> {
+    workflow::ensure_editable(conn, scene_id)?;
+    let scene = scenes::get_scene(conn, scene_id)?;
+    let total = scene.description.chars().count();
+    if at_description_offset == 0 || at_description_offset >= total {
+        return Err(AppError::Invalid(format!(
+            "split point must be inside the description (1–{})",
+            total.saturating_sub(1)
+        )));
+    }
+    let at = byte_offset(&scene.description, at_description_offset);
+    let (head, tail) = scene.description.split_at(at);
+    let (head, tail) = (head.trim().to_string(), tail.trim().to_string());
+    if head.is_empty() || tail.is_empty() {
+        return Err(AppError::Invalid(
+            "both halves of the description need some text".into(),
+        ));
+    }
+
+    let ratio = at_description_offset as f64 / total as f64;
+    let lines: Vec<&str> = scene.dialog.lines().collect();
+    let cut = dialog_line
+        .unwrap_or_else(|| (lines.len() as f64 * ratio).round() as usize)
+        .min(lines.len());
+    let (dialog_a, dialog_b) = (lines[..cut].join("\n"), lines[cut..].join("\n"));
+    let named: Vec<(CastMember, String)> = scene
+        .cast
+        .iter()
+        .cloned()
+        .zip(scene_characters::names(conn, &scene.characters)?)
+        .collect();
+    let (cast_a, cast_b) = split_cast(
+        &named,
+        &format!("{}\n{}", head, dialog_a),
+        &format!("{}\n{}", tail, dialog_b),
+    );
+    let duration_a = ((scene.duration as f64 * ratio).round() as i64).clamp(1, scene.duration);
+    let duration_b = (scene.duration - duration_a).max(1);
+
+    conn.execute(
+        "UPDATE scenes SET description = ?2, dialog = ?3, duration = ?4 WHERE id = ?1",
+        params![scene.id, head, dialog_a, duration_a],
+    )?;
+    scene_characters::replace(conn, &scene, &cast_a)?;
+    let new_id = uuid::Uuid::new_v4().to_string();
+    let order_key = ordering::key_between(
+        Some(&scene.order_key),
+        board_neighbour_after(conn, &scene)?.as_deref(),
+    )?;
+    conn.execute(
+        "INSERT INTO scenes (id, project_id, scene_number, title, description, prompt,
+            camera_angle, lighting, duration, dialog, sort_order, status, location, created_at,
+            order_key, generation_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        params![
+            new_id,
+            scene.project_id,
+            scene.scene_number,
+            scene.title,
+            tail,
+            scene.prompt,
+            scene.camera_angle,
+            scene.lighting,
+            duration_b,
+            dialog_b,
+            scene.sort_order,
+            SceneStatus::Draft.as_str(),
+            scene.location,
+            clock::now(),
+            order_key,
+            serde_json::to_string(&scene.generation)?
+        ],
+    )?;
+    scene_characters::replace(conn, &scenes::get_scene(conn, &new_id)?, &cast_b)?;
+    conn.execute(
+        "INSERT INTO scene_tags (scene_id, tag, source, created_at)
+         SELECT ?2, tag, source, ?3 FROM scene_tags WHERE scene_id = ?1",
+        params![scene.id, new_id, clock::now()],
+    )?;
+    conn.execute(
+        "UPDATE scene_links SET upstream_id = ?2 WHERE upstream_id = ?1",
+        params![scene.id, new_id],
+    )?;
+
+    activity::record(
+        conn,
+        &scene.project_id,
+        "scene",
+        &scene.id,
+        "split",
+        &format!("Split scene {} in two", scene.scene_number),
+        &[
+            FieldChange {
+                field: "description".into(),
+                old: scene.description.clone(),
+                new: head,
+            },
+            FieldChange {
+                field: "dialog".into(),
+                old: scene.dialog.clone(),
+                new: dialog_a,
+            },
+            FieldChange {
+                field: "characters".into(),
+                old: scene_characters::names(conn, &scene.characters)?.join(", "),
+                new: scene_characters::names(conn, &ids(&cast_a))?.join(", "),
+            },
+        ],
+    )?;
+    activity::record(
+        conn,
+        &scene.project_id,
+        "scene",
+        &new_id,
+        "created",
+        &format!("Added scene split from scene {}", scene.scene_number),
+        &[],
+    )?;
+    let renumbered = numbering::renumber(conn, &scene.project_id, strategy)?;
+    Ok(SplitResult {
+        first: scenes::get_scene(conn, &scene.id)?,
+        second: scenes::get_scene(conn, &new_id)?,
+        renumbered,
+    })
+}
+
+fn merge_into(
+    conn: &Connection,
+    scene_ids: &[String],
+    strategy: RenumberStrategy,
+) -> AppResult<MergeResult> {
+    let mut parts = Vec::new();
+    for id in scene_ids {
+        if parts.iter().any(|s: &Scene| &s.id == id) {
+            continue;
+        }
+        workflow::ensure_editable(conn, id)?;
+        parts.push(scenes::get_scene(conn, id)?);
+    }
+    if parts.len() < 2 {
+        return Err(AppError::Invalid(
+            "choose at least two scenes to merge".into(),
+        ));
+    }
+    if parts.iter().any(|s| s.project_id != parts[0].project_id) {
+        return Err(AppError::Invalid(
+            "scenes belong to different projects".into(),
+        ));
+    }
+    parts.sort_by(|a, b| {
+        (&a.order_key, a.sort_order, a.scene_number).cmp(&(
+            &b.order_key,
+            b.sort_order,
+            b.scene_number,
+        ))
+    });
+    let (keep, rest) = parts.split_first().expect("at least two scenes");
+
+    let join = |field: fn(&Scene) -> &str, sep: &str| {
+        parts
+            .iter()
+            .map(field)
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(sep)
+    };
+    let description = join(|s| &s.description, "\n\n");
+    let prompt = join(|s| &s.prompt, "\n\n");
+    let dialog = join(|s| &s.dialog, "\n");
+    let mut cast: Vec<CastMember> = Vec::new();
+    for m in parts.iter().flat_map(|s| &s.cast) {
+        if !cast.iter().any(|c| c.character_id == m.character_id) {
+            cast.push(m.clone());
+        }
+    }
+    let duration = parts
+        .iter()
+        .map(|s| s.duration)
+        .sum::<i64>()
+        .min(MAX_SCENE_DURATION);
+
+    for other in rest {
+        conn.execute(
+            "UPDATE video_jobs SET scene_id = ?2 WHERE scene_id = ?1",
+            params![other.id, keep.id],
+        )?;
+        conn.execute(
+            "UPDATE comments SET entity_id = ?2 WHERE entity_type = 'scene' AND entity_id = ?1",
+            params![other.id, keep.id],
+        )?;
+        conn.execute(
+            "INSERT OR IGNORE INTO scene_tags (scene_id, tag, source, created_at)
+             SELECT ?2, tag, source, created_at FROM scene_tags WHERE scene_id = ?1",
+            params![other.id, keep.id],
+        )?;
+        // A scheduled scene keeps its shooting day when the kept one has none.
+        conn.execute(
+            "UPDATE OR IGNORE shooting_day_scenes SET scene_id = ?2 WHERE scene_id = ?1",
+            params![other.id, keep.id],
+        )?;
+        // Links that would duplicate one the kept scene already has are
+        // left behind and go with the deleted scene.
+        conn.execute(
+            "UPDATE OR IGNORE scene_links SET upstream_id = ?2 WHERE upstream_id = ?1",
+            params![other.id, keep.id],
+        )?;
+        conn.execute(
+            "UPDATE OR IGNORE scene_links SET downstream_id = ?2 WHERE downstream_id = ?1",
+            params![other.id, keep.id],
+        )?;
+    }
+    // Links between the merged scenes are now self-links.
+    conn.execute(
+        "DELETE FROM scene_links WHERE upstream_id = downstream_id",
+        [],
+    )?;
+    // Merging two scenes with one in between that depends on the first and
+    // feeds the second would loop.
+    let board: Vec<String> = scenes::list_for_project(conn, &keep.project_id)?
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    scene_links::generation_order(conn, &board).map_err(|e| match e {
+        AppError::Invalid(_) => AppError::Invalid(
+            "a scene between these depends on one of them and feeds another; merging would loop"
+                .into(),
+        ),
+        e => e,
+    })?;
+    // Any end-frame links beyond the first into the merged scene would
+    // compete for its start image; keep the oldest.
+    conn.execute(
+        "DELETE FROM scene_links WHERE downstream_id = ?1 AND kind = ?2 AND rowid NOT IN
+            (SELECT rowid FROM scene_links WHERE downstream_id = ?1 AND kind = ?2
+             ORDER BY created_at, rowid LIMIT 1)",
+        params![keep.id, scene_links::kind::END_FRAME],
+    )?;
+
+    conn.execute(
+        "UPDATE scenes SET description = ?2, prompt = ?3, dialog = ?4, duration = ?5
+         WHERE id = ?1",
+        params![keep.id, description, prompt, dialog, duration],
+    )?;
+    scene_characters::replace(conn, keep, &cast)?;
+    let mut removed = Vec::new();
+    for other in rest {
+        scenes::delete(conn, &other.id)?;
+        removed.push(other.id.clone());
+    }
+    let merged = scenes::get_scene(conn, &keep.id)?;
+    let mut changes = Vec::new();
+    activity::diff(
+        &mut changes,
+        "description",
+        &keep.description,
+        &merged.description,
+    );
+    activity::diff(&mut changes, "prompt", &keep.prompt, &merged.prompt);
+    activity::diff(&mut changes, "dialog", &keep.dialog, &merged.dialog);
+    activity::diff(
+        &mut changes,
+        "characters",
+        scene_characters::names(conn, &keep.characters)?.join(", "),
+        scene_characters::names(conn, &merged.characters)?.join(", "),
+    );
+    activity::diff(&mut changes, "duration", keep.duration, merged.duration);
+    activity::record(
+        conn,
+        &keep.project_id,
+        "scene",
+        &keep.id,
+        "merged",
+        &format!(
+            "Merged scenes {} into scene {}",
+            rest.iter()
+                .map(|s| numbering::label(s.scene_number, &s.scene_suffix))
+                .collect::<Vec<_>>()
+                .join(", "),
+            numbering::label(keep.scene_number, &keep.scene_suffix)
+        ),
+        &changes,
+    )?;
+    let renumbered = numbering::renumber(conn, &keep.project_id, strategy)?;
+    Ok(MergeResult {
+        scene: scenes::get_scene(conn, &keep.id)?,
+        removed,
+        renumbered,
+    })
+}
+
+/// Split a scene's description at `at_description_offset` (in characters)
+/// into itself and a new scene right after it. Dialog lines are divided at
+/// `dialog_line`, or in proportion to the description when not given; the
+/// cast and duration follow each half. Takes, comments and the scenes it
+/// depends on stay with the first half; scenes that depend on it move to
+/// the second. Both halves are renumbered with `strategy` (insert-style by
+/// default, so the new scene becomes e.g. 12A).
+pub fn split(
+    conn: &Connection,
+    scene_id: &str,
+    at_description_offset: usize,
+    dialog_line: Option<usize>,
+    strategy: RenumberStrategy,
+) -> AppResult<SplitResult> {
+    let tx = conn.unchecked_transaction()?;
+    let result = split_at(&tx, scene_id, at_description_offset, dialog_line, strategy)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Merge scenes into the first of them on the board. Descriptions,
+/// prompts and dialog are joined in board order, casts are combined and
+/// durations added up. Takes, comments, tags and dependency links of the
+/// others move to the merged scene before they are deleted. The rest of the
+/// board is then renumbered with `strategy`.
+pub fn merge(
+    conn: &Connection,
+    scene_ids: &[String],
+    strategy: RenumberStrategy,
+) -> AppResult<MergeResult> {
+    let tx = conn.unchecked_transaction()?;
+    let result = merge_into(&tx, scene_ids, strategy)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn split_scene(
+    db: State<'_, Database>,
+    scene_id: String,
+    at_description_offset: usize,
+    dialog_line: Option<usize>,
+    strategy: Option<RenumberStrategy>,
+) -> AppResult<SplitResult> {
+    split(
+        &db.conn(),
+        &scene_id,
+        at_description_offset,
+        dialog_line,
+        strategy.unwrap_or(RenumberStrategy::InsertStyle),
+    )
+}
+
+#[tauri::command]
+pub fn merge_scenes(
+    db: State<'_, Database>,
+    ids: Vec<String>,
+    strategy: Option<RenumberStrategy>,
+) -> AppResult<MergeResult> {
+    merge(
+        &db.conn(),
+        &ids,
+        strategy.unwrap_or(RenumberStrategy::InsertStyle),
+    )
+}
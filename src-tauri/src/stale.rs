@@ -0,0 +1,91 @@
+//! Scenes whose takes no longer match what their prompt is built from.
+//! Triggers (migration 34) set `scenes.stale_reason` when a cast member's
+//! description, the scene's location or the project's style preset
+//! changes; character fragment edits can set it too. Only scenes that
+//! already have takes go stale, and queueing a new take clears it.
+
+use crate::bulk::{self, BulkGenerationOptions, BulkResult};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::scenes::{self, Scene, SCENE_COLUMNS};
+use rusqlite::{params, Connection};
+use tauri::{AppHandle, Manager, State};
+
+/// Flag every scene with takes that features a character. Returns their
+/// ids.
+pub fn mark_featuring(
+    conn: &Connection,
+    character_id: &str,
+    reason: &str,
+) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "UPDATE scenes SET stale_reason = ?2
+         WHERE id IN (SELECT scene_id FROM scene_characters WHERE character_id = ?1)
+            AND (video_url <> '' OR EXISTS (SELECT 1 FROM video_jobs j WHERE j.scene_id = scenes.id))
+         RETURNING id",
+    )?;
+    let ids = stmt
+        .query_map(params![character_id, reason], |r| r.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+pub fn list_for_project(conn: &Connection, project_id: &str) -> AppResult<Vec<Scene>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scenes WHERE project_id = ?1 AND stale_reason <> ''
+         ORDER BY order_key, sort_order, scene_number",
+        SCENE_COLUMNS
+    ))?;
+    let scenes = stmt
+        .query_map([project_id], scenes::row_to_scene)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(scenes)
+}
+
+/// A project's stale scenes in board order; each says why in
+/// `staleReason`.
+#[tauri::command]
+pub fn list_stale_scenes(db: State<'_, Database>, project_id: String) -> AppResult<Vec<Scene>> {
+    list_for_project(&db.conn(), &project_id)
+}
+
+/// Keep a scene's takes after all, clearing its stale flag.
+pub fn dismiss(conn: &Connection, scene_id: &str) -> AppResult<Scene> {
+    let changed = conn.execute(
+        "UPDATE scenes SET stale_reason = '' WHERE id = ?1",
+        [scene_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("scene {}", scene_id)));
+    }
+    scenes::get_scene(conn, scene_id)
+}
+
+#[tauri::command]
+pub fn dismiss_stale_scene(db: State<'_, Database>, scene_id: String) -> AppResult<Scene> {
+    dismiss(&db.conn(), &scene_id)
+}
+
+/// Queue a new take for every stale scene in a project, as
+/// `bulk_queue_generation` does. Queued scenes stop being stale; rejected
+/// ones stay flagged.
+#[tauri::command]
+pub async fn regenerate_stale_scenes(
+    app: AppHandle,
+    project_id: String,
+    provider: Option<String>,
+    options: Option<BulkGenerationOptions>,
+) -> AppResult<BulkResult> {
+    let scene_ids: Vec<String> = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        list_for_project(&conn, &project_id)?
+            .into_iter()
+            .map(|s| s.id)
+            .collect()
+    };
+    if scene_ids.is_empty() {
+        return Ok(BulkResult::default());
+    }
+    bulk::bulk_queue_generation(app, scene_ids, provider, options).await
+}
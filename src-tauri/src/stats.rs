@@ -0,0 +1,137 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::jobs::status;
+use crate::workflow::SceneStatus;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::State;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusCount {
+    pub status: SceneStatus,
+    #[ts(type = "number")]
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSpend {
+    pub provider: String,
+    #[ts(type = "number")]
+    pub jobs: i64,
+    pub spend: f64,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ProjectStats {
+    pub project_id: String,
+    #[ts(type = "number")]
+    pub scene_count: i64,
+    /// Every workflow state, in workflow order, including empty ones.
+    pub scenes_by_status: Vec<StatusCount>,
+    /// Sum of scene durations in seconds.
+    #[ts(type = "number")]
+    pub estimated_runtime: i64,
+    pub spend_by_provider: Vec<ProviderSpend>,
+    pub total_spend: f64,
+    #[ts(type = "number")]
+    pub jobs_completed: i64,
+    #[ts(type = "number")]
+    pub jobs_failed: i64,
+    /// Completed over finished (completed + failed) jobs; `None` until a
+    /// job has finished.
+    pub success_rate: Option<f64>,
+    pub last_activity: Option<String>,
+}
+
+/// Dashboard numbers for a project, aggregated in SQL.
+pub fn project_stats(conn: &Connection, project_id: &str) -> AppResult<ProjectStats> {
+    let updated_at: Option<String> = conn
+        .query_row(
+            "SELECT updated_at FROM projects WHERE id = ?1",
+            [project_id],
+            |r| r.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("project {}", project_id)))?;
+
+    let (scene_count, estimated_runtime): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(duration), 0) FROM scenes WHERE project_id = ?1",
+        [project_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+
+    let mut stmt =
+        conn.prepare("SELECT status, COUNT(*) FROM scenes WHERE project_id = ?1 GROUP BY status")?;
+    let counted = stmt
+        .query_map([project_id], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let scenes_by_status = SceneStatus::ALL
+        .into_iter()
+        .map(|st| StatusCount {
+            status: st,
+            count: counted
+                .iter()
+                .find(|(s, _)| s == st.as_str())
+                .map_or(0, |(_, n)| *n),
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(
+        "SELECT j.provider, COUNT(*), COALESCE(SUM(j.cost), 0)
+         FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+         WHERE s.project_id = ?1
+         GROUP BY j.provider ORDER BY 3 DESC, j.provider",
+    )?;
+    let spend_by_provider = stmt
+        .query_map([project_id], |r| {
+            Ok(ProviderSpend {
+                provider: r.get(0)?,
+                jobs: r.get(1)?,
+                spend: r.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let total_spend = spend_by_provider.iter().map(|p| p.spend).sum();
+
+    let (jobs_completed, jobs_failed): (i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(j.status = ?2), 0), COALESCE(SUM(j.status = ?3), 0)
+         FROM video_jobs j JOIN scenes s ON s.id = j.scene_id
+         WHERE s.project_id = ?1",
+        params![project_id, status::COMPLETED, status::FAILED],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+    let finished = jobs_completed + jobs_failed;
+    let success_rate = (finished > 0).then(|| jobs_completed as f64 / finished as f64);
+
+    let logged: Option<String> = conn.query_row(
+        "SELECT MAX(created_at) FROM activity_log WHERE project_id = ?1",
+        [project_id],
+        |r| r.get(0),
+    )?;
+    let last_activity = logged.into_iter().chain(updated_at).max();
+
+    Ok(ProjectStats {
+        project_id: project_id.to_string(),
+        scene_count,
+        scenes_by_status,
+        estimated_runtime,
+        spend_by_provider,
+        total_spend,
+        jobs_completed,
+        jobs_failed,
+        success_rate,
+        last_activity,
+    })
+}
+
+#[tauri::command]
+pub fn get_project_stats(db: State<'_, Database>, project_id: String) -> AppResult<ProjectStats> {
+    project_stats(&db.conn(), &project_id)
+}
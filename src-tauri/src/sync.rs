@@ -0,0 +1,956 @@
+//! Change-log replication so several devices can edit the same project.
+//!
+//! Triggers (migration 21) append to `sync_changes` for every insert,
+//! delete and field edit of a project, character or scene, along with the
+//! value it replaced. A changeset is that log for one project. Importing it
+//! on another device replays each change whose base value still matches the
+//! local one; when both sides edited the same field (or one deleted a row
+//! the other edited) a `sync_conflicts` row is left for the user instead.
+//! Changes keep their origin device and sequence number, so replaying the
+//! same changeset twice, or relaying it through a third device, is harmless.
+//!
+//! Live sessions (see `lan`) can't stop for the user on every clash, so
+//! they merge with `Merge::LastWriter`: of two edits to one field, the
+//! later by Lamport clock (then device id) wins on every device alike.
+
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::projects;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+use ts_rs::TS;
+
+/// Bumped when the changeset layout changes incompatibly. 2: scene casts
+/// sync as `scene_character` rows instead of `characters_json`.
+pub const CHANGESET_FORMAT: u32 = 2;
+
+/// A synced table and the columns its triggers track. Must match the
+/// triggers in migration 21 (scene_suffix: 25, order_key: 26,
+/// generation_json: 29, scene casts: 31).
+struct Entity {
+    kind: &'static str,
+    table: &'static str,
+    columns: &'static [&'static str],
+    /// Whether rows carry a `version` (see `versioning`).
+    versioned: bool,
+}
+
+const ENTITIES: &[Entity] = &[
+    Entity {
+        kind: "project",
+        table: "projects",
+        columns: &["name", "genre", "synopsis", "tone", "generation_json"],
+        versioned: true,
+    },
+    Entity {
+        kind: "character",
+        table: "characters",
+        columns: &[
+            "name",
+            "description",
+            "photo_data",
+            "voice_engine",
+            "voice_id",
+            "voice_name",
+            "voice_speed",
+            "voice_stability",
+            "voice_similarity",
+        ],
+        versioned: true,
+    },
+    Entity {
+        kind: "scene",
+        table: "scenes",
+        columns: &[
+            "scene_number",
+            "title",
+            "description",
+            "prompt",
+            "camera_angle",
+            "lighting",
+            "duration",
+            "dialog",
+            "status",
+            "video_url",
+            "sort_order",
+            "conditioning_image",
+            "location",
+            "scene_suffix",
+            "order_key",
+            "generation_json",
+        ],
+        versioned: true,
+    },
+    Entity {
+        kind: "scene_character",
+        table: "scene_characters",
+        columns: &["scene_id", "character_id", "role", "importance", "position"],
+        versioned: false,
+    },
+];
+
+/// Fields older devices may still send that no longer exist here; their
+/// edits are skipped.
+const RETIRED_FIELDS: &[(&str, &str)] = &[("scene", "characters_json")];
+
+/// Table holding a synced entity kind whose rows carry a `version`, e.g.
+/// `"scene"` -> `"scenes"`.
+pub fn versioned_table_for(kind: &str) -> Option<&'static str> {
+    ENTITIES
+        .iter()
+        .find(|e| e.kind == kind && e.versioned)
+        .map(|e| e.table)
+}
+
+impl Entity {
+    fn find(kind: &str) -> AppResult<&'static Entity> {
+        ENTITIES
+            .iter()
+            .find(|e| e.kind == kind)
+            .ok_or_else(|| AppError::Invalid(format!("unknown sync entity: {}", kind)))
+    }
+
+    fn column(&self, field: &str) -> AppResult<&'static str> {
+        self.columns
+            .iter()
+            .copied()
+            .find(|c| *c == field)
+            .ok_or_else(|| {
+                AppError::Invalid(format!("{} has no synced field {}", self.kind, field))
+            })
+    }
+
+    /// Every column stored in a whole-row snapshot, in trigger order.
+    fn row_columns(&self) -> Vec<&'static str> {
+        let mut cols = vec!["id"];
+        if self.kind != "project" {
+            cols.push("project_id");
+        }
+        cols.extend_from_slice(self.columns);
+        cols
+    }
+
+    /// The row as the same `json_object` text the triggers write, or None
+    /// if it doesn't exist here.
+    fn snapshot(&self, conn: &Connection, id: &str) -> AppResult<Option<String>> {
+        let pairs: Vec<String> = self
+            .row_columns()
+            .iter()
+            .map(|c| format!("'{0}', {0}", c))
+            .collect();
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT json_object({}) FROM {} WHERE id = ?1",
+                    pairs.join(", "),
+                    self.table
+                ),
+                [id],
+                |r| r.get(0),
+            )
+            .optional()?)
+    }
+
+    /// The project the row belongs to, or None if it doesn't exist here.
+    fn project_of(&self, conn: &Connection, id: &str) -> AppResult<Option<String>> {
+        let column = if self.kind == "project" {
+            "id"
+        } else {
+            "project_id"
+        };
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM {} WHERE id = ?1", column, self.table),
+                [id],
+                |r| r.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Current value of one field as JSON text; None if the row is gone.
+    fn value(&self, conn: &Connection, id: &str, field: &str) -> AppResult<Option<String>> {
+        let column = self.column(field)?;
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT json_quote({}) FROM {} WHERE id = ?1",
+                    column, self.table
+                ),
+                [id],
+                |r| r.get(0),
+            )
+            .optional()?)
+    }
+
+    fn set_value(&self, conn: &Connection, id: &str, field: &str, json: &str) -> AppResult<()> {
+        let column = self.column(field)?;
+        conn.execute(
+            &format!(
+                "UPDATE {} SET {} = json_extract(?2, '$') WHERE id = ?1",
+                self.table, column
+            ),
+            params![id, json],
+        )?;
+        Ok(())
+    }
+
+    fn insert(&self, conn: &Connection, snapshot: &str) -> AppResult<()> {
+        // Snapshots from older versions lack later columns; let those
+        // take their defaults.
+        let row: Value = serde_json::from_str(snapshot)?;
+        let cols: Vec<&str> = self
+            .row_columns()
+            .into_iter()
+            .filter(|c| row.get(c).is_some())
+            .collect();
+        let values: Vec<String> = cols
+            .iter()
+            .map(|c| format!("json_extract(?1, '$.{}')", c))
+            .collect();
+        conn.execute(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                self.table,
+                cols.join(", "),
+                values.join(", ")
+            ),
+            [snapshot],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, conn: &Connection, id: &str) -> AppResult<()> {
+        conn.execute(&format!("DELETE FROM {} WHERE id = ?1", self.table), [id])?;
+        Ok(())
+    }
+}
+
+/// One entry of the change log. Values are JSON text: a quoted field value
+/// for `update`, a whole-row object for `insert` (new) and `delete` (old).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub origin_device: String,
+    pub origin_seq: i64,
+    pub lamport: i64,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub project_id: String,
+    pub op: String,
+    #[serde(default)]
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Changeset {
+    pub format: u32,
+    pub device_id: String,
+    pub project_id: String,
+    /// The exporting device's clock; pass it back as `since` next time.
+    pub clock: i64,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SyncDevice {
+    pub device_id: String,
+    #[ts(type = "number")]
+    pub clock: i64,
+    #[ts(type = "number")]
+    pub open_conflicts: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct ChangesetReport {
+    pub applied: usize,
+    /// Already seen, already true here, or about a row deleted here.
+    pub skipped: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SyncConflict {
+    #[ts(type = "number")]
+    pub id: i64,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub project_id: String,
+    /// "field" when both sides edited `field`; "deleted" when the other
+    /// device deleted a row that was edited here.
+    pub kind: String,
+    pub field: String,
+    pub local_value: Value,
+    pub remote_value: Value,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum Resolution {
+    Local,
+    Remote,
+    /// Use the `value` passed alongside; field conflicts only.
+    Custom,
+}
+
+enum Outcome {
+    Applied,
+    Skipped,
+    Conflict {
+        local: String,
+        remote: Option<String>,
+    },
+}
+
+fn device(conn: &Connection) -> AppResult<(String, i64)> {
+    Ok(
+        conn.query_row("SELECT device_id, clock FROM sync_meta", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })?,
+    )
+}
+
+/// This device's id in the change log.
+pub fn device_id(conn: &Connection) -> AppResult<String> {
+    Ok(device(conn)?.0)
+}
+
+/// Mute or unmute the capture triggers for this connection's writes.
+fn set_applying(conn: &Connection, on: bool) -> AppResult<()> {
+    conn.execute("UPDATE sync_meta SET applying = ?1", [on as i64])?;
+    Ok(())
+}
+
+/// Append a change made here that the triggers didn't see, e.g. keeping
+/// the local side of a conflict so other devices converge on it.
+#[allow(clippy::too_many_arguments)]
+fn record_local(
+    conn: &Connection,
+    entity: &Entity,
+    entity_id: &str,
+    project_id: &str,
+    op: &str,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> AppResult<()> {
+    conn.execute("UPDATE sync_meta SET clock = clock + 1", [])?;
+    conn.execute(
+        "INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id,
+            project_id, op, field, old_value, new_value)
+         SELECT m.device_id, (SELECT coalesce(max(origin_seq), 0) + 1 FROM sync_changes
+                WHERE origin_device = m.device_id),
+            m.clock, ?1, ?2, ?3, ?4, ?5, ?6, ?7
+         FROM sync_meta m",
+        params![
+            entity.kind,
+            entity_id,
+            project_id,
+            op,
+            field,
+            old_value,
+            new_value
+        ],
+    )?;
+    Ok(())
+}
+
+fn outside_project(change: &Change) -> AppError {
+    AppError::Forbidden(format!(
+        "a synced change to {} {} reaches outside project {}",
+        change.entity_type, change.entity_id, change.project_id
+    ))
+}
+
+/// Replay one remote change against local state. Triggers must be muted.
+/// A change may only touch rows of its own project, so a peer can't reach
+/// into other projects on this device.
+fn apply(conn: &Connection, change: &Change) -> AppResult<Outcome> {
+    let entity = Entity::find(&change.entity_type)?;
+    let foreign = if entity.kind == "project" {
+        change.entity_id != change.project_id
+    } else {
+        entity
+            .project_of(conn, &change.entity_id)?
+            .is_some_and(|p| p != change.project_id)
+    };
+    if foreign {
+        return Err(outside_project(change));
+    }
+    match change.op.as_str() {
+        "insert" => {
+            let Some(row) = change.new_value.as_deref() else {
+                return Err(AppError::Invalid("insert change without a row".into()));
+            };
+            let snapshot: Value = serde_json::from_str(row)?;
+            if snapshot["id"] != change.entity_id.as_str()
+                || (entity.kind != "project"
+                    && snapshot["project_id"] != change.project_id.as_str())
+            {
+                return Err(outside_project(change));
+            }
+            let parent_missing = entity.kind != "project"
+                && conn
+                    .query_row(
+                        "SELECT 1 FROM projects WHERE id = ?1",
+                        [&change.project_id],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_none();
+            // A cast entry needs its scene and character, which may have
+            // been deleted here, in the same project.
+            let parent_missing = parent_missing
+                || (entity.kind == "scene_character"
+                    && !conn.query_row(
+                        "SELECT EXISTS (SELECT 1 FROM scenes
+                                WHERE id = json_extract(?1, '$.scene_id') AND project_id = ?2)
+                            AND EXISTS (SELECT 1 FROM characters
+                                WHERE id = json_extract(?1, '$.character_id') AND project_id = ?2)",
+                        params![row, change.project_id],
+                        |r| r.get::<_, bool>(0),
+                    )?);
+            if parent_missing || entity.snapshot(conn, &change.entity_id)?.is_some() {
+                return Ok(Outcome::Skipped);
+            }
+            entity.insert(conn, row)?;
+            Ok(Outcome::Applied)
+        }
+        "update" => {
+            let (Some(old), Some(new)) = (change.old_value.as_deref(), change.new_value.as_deref())
+            else {
+                return Err(AppError::Invalid("update change without values".into()));
+            };
+            if RETIRED_FIELDS.contains(&(entity.kind, change.field.as_str())) {
+                return Ok(Outcome::Skipped);
+            }
+            match entity.value(conn, &change.entity_id, &change.field)? {
+                // Deleted here; the deletion wins.
+                None => Ok(Outcome::Skipped),
+                Some(current) if current == new => Ok(Outcome::Skipped),
+                Some(current) if current == old => {
+                    entity.set_value(conn, &change.entity_id, &change.field, new)?;
+                    Ok(Outcome::Applied)
+                }
+                Some(current) => Ok(Outcome::Conflict {
+                    local: current,
+                    remote: Some(new.to_string()),
+                }),
+            }
+        }
+        "delete" => match entity.snapshot(conn, &change.entity_id)? {
+            None => Ok(Outcome::Skipped),
+            Some(current) if Some(current.as_str()) == change.old_value.as_deref() => {
+                entity.delete(conn, &change.entity_id)?;
+                Ok(Outcome::Applied)
+            }
+            Some(current) => Ok(Outcome::Conflict {
+                local: current,
+                remote: None,
+            }),
+        },
+        other => Err(AppError::Invalid(format!("unknown sync op: {}", other))),
+    }
+}
+
+/// Record a conflict, folding it into an open one on the same field so a
+/// run of remote edits leaves a single decision for the user.
+fn record_conflict(
+    conn: &Connection,
+    change_id: i64,
+    change: &Change,
+    local: &str,
+    remote: Option<&str>,
+) -> AppResult<()> {
+    let updated = conn.execute(
+        "UPDATE sync_conflicts SET change_id = ?1, local_value = ?2, remote_value = ?3,
+            created_at = ?7
+         WHERE entity_type = ?4 AND entity_id = ?5 AND field = ?6 AND resolved = 0",
+        params![
+            change_id,
+            local,
+            remote,
+            change.entity_type,
+            change.entity_id,
+            change.field,
+            clock::now()
+        ],
+    )?;
+    if updated == 0 {
+        conn.execute(
+            "INSERT INTO sync_conflicts (change_id, entity_type, entity_id, project_id, field,
+                local_value, remote_value)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                change_id,
+                change.entity_type,
+                change.entity_id,
+                change.project_id,
+                change.field,
+                local,
+                remote
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// The project's log rows matching `condition` (on `?2`), with their ids.
+fn query_changes(
+    conn: &Connection,
+    project_id: &str,
+    condition: &str,
+    value: i64,
+) -> AppResult<Vec<(i64, Change)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, origin_device, origin_seq, lamport, entity_type, entity_id, project_id, op,
+            field, old_value, new_value
+         FROM sync_changes WHERE project_id = ?1 AND {} ORDER BY lamport, id",
+        condition
+    ))?;
+    let rows = stmt.query_map(params![project_id, value], |r| {
+        Ok((
+            r.get(0)?,
+            Change {
+                origin_device: r.get(1)?,
+                origin_seq: r.get(2)?,
+                lamport: r.get(3)?,
+                entity_type: r.get(4)?,
+                entity_id: r.get(5)?,
+                project_id: r.get(6)?,
+                op: r.get(7)?,
+                field: r.get(8)?,
+                old_value: r.get(9)?,
+                new_value: r.get(10)?,
+            },
+        ))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn changeset(conn: &Connection, project_id: &str, changes: Vec<Change>) -> AppResult<Changeset> {
+    let (device_id, clock) = device(conn)?;
+    Ok(Changeset {
+        format: CHANGESET_FORMAT,
+        device_id,
+        project_id: project_id.to_string(),
+        clock,
+        changes,
+    })
+}
+
+pub fn export_changes(conn: &Connection, project_id: &str, since: i64) -> AppResult<Changeset> {
+    let changes = query_changes(conn, project_id, "lamport > ?2", since)?;
+    changeset(
+        conn,
+        project_id,
+        changes.into_iter().map(|(_, c)| c).collect(),
+    )
+}
+
+/// Log rows added here after row `after` (local ids, so changes merged in
+/// from elsewhere count too), and the id to pass as `after` next time.
+/// For streaming changes to a live peer; see `lan`.
+pub fn changes_after(
+    conn: &Connection,
+    project_id: &str,
+    after: i64,
+) -> AppResult<(Changeset, i64)> {
+    let rows = query_changes(conn, project_id, "id > ?2", after)?;
+    let last = rows.iter().map(|(id, _)| *id).max().unwrap_or(after);
+    let changes = rows.into_iter().map(|(_, c)| c).collect();
+    Ok((changeset(conn, project_id, changes)?, last))
+}
+
+/// The id of the newest log row, for starting `changes_after` from now.
+pub fn latest_change(conn: &Connection) -> AppResult<i64> {
+    Ok(
+        conn.query_row("SELECT coalesce(max(id), 0) FROM sync_changes", [], |r| {
+            r.get(0)
+        })?,
+    )
+}
+
+/// Run `f` with the capture triggers muted, so what it writes isn't
+/// logged as local edits; e.g. loading a peer's copy of a project.
+pub fn uncaptured<T>(conn: &Connection, f: impl FnOnce() -> AppResult<T>) -> AppResult<T> {
+    set_applying(conn, true)?;
+    let result = f();
+    set_applying(conn, false)?;
+    result
+}
+
+/// How to settle two edits to the same field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Merge {
+    /// Keep the local value and leave a conflict for the user.
+    Ask,
+    /// Keep whichever edit is later; no conflict is recorded. Rows deleted
+    /// on one side and edited on the other are still left to the user.
+    LastWriter,
+}
+
+/// Whether the remote edit beats the latest local edit of the same field.
+fn remote_is_later(conn: &Connection, change_id: i64, change: &Change) -> AppResult<bool> {
+    let local: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT lamport, origin_device FROM sync_changes
+             WHERE entity_type = ?1 AND entity_id = ?2 AND field = ?3 AND id <> ?4
+             ORDER BY lamport DESC, origin_device DESC LIMIT 1",
+            params![
+                change.entity_type,
+                change.entity_id,
+                change.field,
+                change_id
+            ],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()?;
+    Ok(local.map_or(true, |(lamport, device)| {
+        (change.lamport, change.origin_device.as_str()) > (lamport, device.as_str())
+    }))
+}
+
+pub fn import_changes(conn: &Connection, changeset: &Changeset) -> AppResult<ChangesetReport> {
+    merge_changes(conn, changeset, Merge::Ask)
+}
+
+pub fn merge_changes(
+    conn: &Connection,
+    changeset: &Changeset,
+    merge: Merge,
+) -> AppResult<ChangesetReport> {
+    if changeset.format > CHANGESET_FORMAT {
+        return Err(AppError::Invalid(format!(
+            "changeset format {} is newer than this app supports; update the app",
+            changeset.format
+        )));
+    }
+    let (local_device, _) = device(conn)?;
+    if changeset.device_id == local_device {
+        return Err(AppError::Invalid(
+            "this changeset was exported from this device".into(),
+        ));
+    }
+    if let Some(change) = changeset
+        .changes
+        .iter()
+        .find(|c| c.project_id != changeset.project_id)
+    {
+        return Err(AppError::Forbidden(format!(
+            "changeset for project {} carries a change to project {}",
+            changeset.project_id, change.project_id
+        )));
+    }
+    // Locked and archived projects take no edits from other devices
+    // either. One that isn't here yet is created by the changeset.
+    let known: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1)",
+        [&changeset.project_id],
+        |r| r.get(0),
+    )?;
+    if known {
+        projects::ensure_writable(conn, &changeset.project_id)?;
+    }
+    let mut changes: Vec<&Change> = changeset.changes.iter().collect();
+    changes.sort_by(|a, b| {
+        (a.lamport, &a.origin_device, a.origin_seq).cmp(&(
+            b.lamport,
+            &b.origin_device,
+            b.origin_seq,
+        ))
+    });
+
+    let mut report = ChangesetReport::default();
+    let tx = conn.unchecked_transaction()?;
+    set_applying(&tx, true)?;
+    for change in changes {
+        let seen = change.origin_device == local_device
+            || tx
+                .query_row(
+                    "SELECT 1 FROM sync_changes WHERE origin_device = ?1 AND origin_seq = ?2",
+                    params![change.origin_device, change.origin_seq],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+        if seen {
+            report.skipped += 1;
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO sync_changes (origin_device, origin_seq, lamport, entity_type, entity_id,
+                project_id, op, field, old_value, new_value)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                change.origin_device,
+                change.origin_seq,
+                change.lamport,
+                change.entity_type,
+                change.entity_id,
+                change.project_id,
+                change.op,
+                change.field,
+                change.old_value,
+                change.new_value
+            ],
+        )?;
+        let change_id = tx.last_insert_rowid();
+        match apply(&tx, change)? {
+            Outcome::Applied => report.applied += 1,
+            Outcome::Skipped => report.skipped += 1,
+            Outcome::Conflict {
+                remote: Some(remote),
+                ..
+            } if merge == Merge::LastWriter => {
+                if remote_is_later(&tx, change_id, change)? {
+                    let entity = Entity::find(&change.entity_type)?;
+                    entity.set_value(&tx, &change.entity_id, &change.field, &remote)?;
+                    report.applied += 1;
+                } else {
+                    report.skipped += 1;
+                }
+            }
+            Outcome::Conflict { local, remote } => {
+                record_conflict(&tx, change_id, change, &local, remote.as_deref())?;
+                report.conflicts += 1;
+            }
+        }
+    }
+    tx.execute(
+        "UPDATE sync_meta SET applying = 0, clock = max(clock, ?1)",
+        [changeset.clock],
+    )?;
+    tx.commit()?;
+    Ok(report)
+}
+
+fn parse_json(text: Option<String>) -> Value {
+    text.and_then(|t| serde_json::from_str(&t).ok())
+        .unwrap_or(Value::Null)
+}
+
+/// This device's id and clock, and how many conflicts are open.
+pub fn sync_device(conn: &Connection) -> AppResult<SyncDevice> {
+    let (device_id, clock) = device(conn)?;
+    let open_conflicts = conn.query_row(
+        "SELECT COUNT(*) FROM sync_conflicts WHERE resolved = 0",
+        [],
+        |r| r.get(0),
+    )?;
+    Ok(SyncDevice {
+        device_id,
+        clock,
+        open_conflicts,
+    })
+}
+
+#[tauri::command]
+pub fn get_sync_device(db: State<'_, Database>) -> AppResult<SyncDevice> {
+    sync_device(&db.conn())
+}
+
+/// Write the project's change log after `since` (a clock value from an
+/// earlier changeset, or everything) to `path`. Returns the change count.
+pub fn write_changes(
+    conn: &Connection,
+    project_id: &str,
+    path: &str,
+    since: i64,
+) -> AppResult<usize> {
+    let changeset = export_changes(conn, project_id, since)?;
+    std::fs::write(path, serde_json::to_string(&changeset)?)?;
+    Ok(changeset.changes.len())
+}
+
+#[tauri::command]
+pub fn export_sync_changes(
+    db: State<'_, Database>,
+    project_id: String,
+    path: String,
+    since: Option<i64>,
+) -> AppResult<usize> {
+    write_changes(&db.conn(), &project_id, &path, since.unwrap_or(0))
+}
+
+/// Merge a changeset written by another device. Conflicting edits are not
+/// applied; see `list_sync_conflicts`.
+pub fn read_changes(conn: &Connection, path: &str) -> AppResult<ChangesetReport> {
+    let changeset: Changeset = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let report = import_changes(conn, &changeset)?;
+    tracing::info!(
+        project_id = %changeset.project_id,
+        from = %changeset.device_id,
+        applied = report.applied,
+        conflicts = report.conflicts,
+        "imported sync changes"
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn import_sync_changes(db: State<'_, Database>, path: String) -> AppResult<ChangesetReport> {
+    read_changes(&db.conn(), &path)
+}
+
+/// Open conflicts, oldest first, optionally for one project.
+pub fn conflicts(conn: &Connection, project_id: Option<&str>) -> AppResult<Vec<SyncConflict>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, entity_type, entity_id, project_id, field, local_value, remote_value,
+            created_at
+         FROM sync_conflicts
+         WHERE resolved = 0 AND (?1 IS NULL OR project_id = ?1)
+         ORDER BY created_at, id",
+    )?;
+    let conflicts = stmt
+        .query_map([project_id], |r| {
+            let field: String = r.get(4)?;
+            Ok(SyncConflict {
+                id: r.get(0)?,
+                entity_type: r.get(1)?,
+                entity_id: r.get(2)?,
+                project_id: r.get(3)?,
+                kind: if field.is_empty() { "deleted" } else { "field" }.to_string(),
+                field,
+                local_value: parse_json(r.get(5)?),
+                remote_value: parse_json(r.get(6)?),
+                created_at: r.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(conflicts)
+}
+
+#[tauri::command]
+pub fn list_sync_conflicts(
+    db: State<'_, Database>,
+    project_id: Option<String>,
+) -> AppResult<Vec<SyncConflict>> {
+    conflicts(&db.conn(), project_id.as_deref())
+}
+
+/// Settle a conflict. The outcome is logged as a local change when other
+/// devices don't already have it, so the next changeset brings them along.
+pub fn resolve(
+    conn: &Connection,
+    id: i64,
+    resolution: Resolution,
+    value: Option<Value>,
+) -> AppResult<()> {
+    let (entity_type, entity_id, project_id, field, local, remote): (
+        String,
+        String,
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+    ) = conn
+        .query_row(
+            "SELECT entity_type, entity_id, project_id, field, local_value, remote_value
+             FROM sync_conflicts WHERE id = ?1 AND resolved = 0",
+            [id],
+            |r| {
+                Ok((
+                    r.get(0)?,
+                    r.get(1)?,
+                    r.get(2)?,
+                    r.get(3)?,
+                    r.get(4)?,
+                    r.get(5)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("open sync conflict {}", id)))?;
+    let entity = Entity::find(&entity_type)?;
+
+    let tx = conn.unchecked_transaction()?;
+    if field.is_empty() {
+        // Deleted on the other device, edited here.
+        match resolution {
+            Resolution::Custom => {
+                return Err(AppError::Invalid(
+                    "a deleted row can only be kept or deleted".into(),
+                ))
+            }
+            Resolution::Remote => {
+                set_applying(&tx, true)?;
+                entity.delete(&tx, &entity_id)?;
+                set_applying(&tx, false)?;
+            }
+            Resolution::Local => {
+                if let Some(row) = entity.snapshot(&tx, &entity_id)? {
+                    record_local(
+                        &tx,
+                        entity,
+                        &entity_id,
+                        &project_id,
+                        "insert",
+                        "",
+                        None,
+                        Some(&row),
+                    )?;
+                }
+            }
+        }
+    } else if entity.value(&tx, &entity_id, &field)?.is_some() {
+        let chosen = match resolution {
+            Resolution::Local => local.unwrap_or_else(|| "null".into()),
+            Resolution::Remote => remote.clone().unwrap_or_else(|| "null".into()),
+            Resolution::Custom => match value {
+                Some(v @ (Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_))) => {
+                    serde_json::to_string(&v)?
+                }
+                Some(_) => return Err(AppError::Invalid("value must be a scalar".into())),
+                None => {
+                    return Err(AppError::Invalid(
+                        "a custom resolution needs a value".into(),
+                    ))
+                }
+            },
+        };
+        set_applying(&tx, true)?;
+        entity.set_value(&tx, &entity_id, &field, &chosen)?;
+        set_applying(&tx, false)?;
+        // Store what SQLite made of it so the text matches future triggers.
+        let stored = entity.value(&tx, &entity_id, &field)?.unwrap_or(chosen);
+        if remote.as_deref() != Some(stored.as_str()) {
+            record_local(
+                &tx,
+                entity,
+                &entity_id,
+                &project_id,
+                "update",
+                &field,
+                remote.as_deref(),
+                Some(&stored),
+            )?;
+        }
+    }
+    tx.execute("UPDATE sync_conflicts SET resolved = 1 WHERE id = ?1", [id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resolve_sync_conflict(
+    db: State<'_, Database>,
+    id: i64,
+    resolution: Resolution,
+    value: Option<Value>,
+) -> AppResult<()> {
+    resolve(&db.conn(), id, resolution, value)
+}
@@ -0,0 +1,401 @@
+//! Scene tags ("night", "rain", "close-up", "vfx-heavy") for filtering and
+//! breakdowns. Tags are set by hand or suggested by the LLM from scene
+//! descriptions; suggestions never touch a scene that has manual tags.
+
+use crate::activity::{self, FieldChange};
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, Credential};
+use crate::projects;
+use crate::scenes;
+use crate::workflow;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// Who set a tag, stored in `scene_tags.source`.
+pub mod source {
+    pub const MANUAL: &str = "manual";
+    pub const AUTO: &str = "auto";
+}
+
+const MAX_TAG_LEN: usize = 40;
+const MAX_AUTO_TAGS: usize = 8;
+
+/// Offered to the LLM so tags stay consistent across scenes and projects.
+const SUGGESTED: &[&str] = &[
+    "day",
+    "night",
+    "dawn",
+    "dusk",
+    "interior",
+    "exterior",
+    "rain",
+    "snow",
+    "fog",
+    "close-up",
+    "wide-shot",
+    "tracking-shot",
+    "aerial",
+    "dialogue-heavy",
+    "action",
+    "stunts",
+    "crowd",
+    "vehicles",
+    "animals",
+    "vfx-heavy",
+    "practical-effects",
+    "slow-motion",
+];
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SceneTag {
+    pub scene_id: String,
+    pub tag: String,
+    pub source: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AutoTagResult {
+    /// Scenes given new tags, with the tags.
+    pub tagged: Vec<SceneTags>,
+    /// Scenes left alone: tagged by hand or with nothing to read.
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneTags {
+    pub scene_id: String,
+    pub tags: Vec<String>,
+}
+
+fn row_to_tag(row: &Row) -> rusqlite::Result<SceneTag> {
+    Ok(SceneTag {
+        scene_id: row.get(0)?,
+        tag: row.get(1)?,
+        source: row.get(2)?,
+        created_at: row.get(3)?,
+    })
+}
+
+/// Lower-case, hyphenated, trimmed, so "Close Up" and "close-up" match.
+/// Empty or overlong tags come back as None.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag
+        .trim()
+        .to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '_')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    Some(tag).filter(|t| !t.is_empty() && t.chars().count() <= MAX_TAG_LEN)
+}
+
+pub fn tags_for_project(conn: &Connection, project_id: &str) -> AppResult<Vec<SceneTag>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.scene_id, t.tag, t.source, t.created_at FROM scene_tags t
+         JOIN scenes s ON s.id = t.scene_id
+         WHERE s.project_id = ?1 ORDER BY s.order_key, s.sort_order, s.scene_number, t.tag",
+    )?;
+    let tags = stmt
+        .query_map([project_id], row_to_tag)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+fn tags_for_scene(conn: &Connection, scene_id: &str) -> AppResult<Vec<SceneTag>> {
+    let mut stmt = conn.prepare(
+        "SELECT scene_id, tag, source, created_at FROM scene_tags WHERE scene_id = ?1 ORDER BY tag",
+    )?;
+    let tags = stmt
+        .query_map([scene_id], row_to_tag)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+/// Replace a scene's tags with `tags` from `tag_source`. Returns the tags
+/// before and after, for the activity feed.
+fn replace(
+    conn: &Connection,
+    scene_id: &str,
+    tags: &[String],
+    tag_source: &str,
+) -> AppResult<(String, String)> {
+    let before: Vec<String> = tags_for_scene(conn, scene_id)?
+        .into_iter()
+        .map(|t| t.tag)
+        .collect();
+    conn.execute("DELETE FROM scene_tags WHERE scene_id = ?1", [scene_id])?;
+    let now = clock::now();
+    for tag in tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO scene_tags (scene_id, tag, source, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![scene_id, tag, tag_source, now],
+        )?;
+    }
+    let mut after = tags.to_vec();
+    after.sort();
+    after.dedup();
+    Ok((before.join(", "), after.join(", ")))
+}
+
+#[tauri::command]
+pub fn list_scene_tags(db: State<'_, Database>, project_id: String) -> AppResult<Vec<SceneTag>> {
+    tags_for_project(&db.conn(), &project_id)
+}
+
+/// Set a scene's tags by hand, replacing any it had. An empty list clears
+/// them, which also lets `auto_tag_scenes` tag the scene again.
+pub fn set_tags(conn: &Connection, scene_id: &str, tags: &[String]) -> AppResult<Vec<SceneTag>> {
+    let mut clean = Vec::new();
+    for tag in tags {
+        match normalize(tag) {
+            Some(t) if !clean.contains(&t) => clean.push(t),
+            Some(_) => {}
+            None if tag.trim().is_empty() => {}
+            None => {
+                return Err(AppError::Invalid(format!(
+                    "tag '{}' is longer than {} characters",
+                    tag.trim(),
+                    MAX_TAG_LEN
+                )))
+            }
+        }
+    }
+    let tx = conn.unchecked_transaction()?;
+    workflow::ensure_editable(&tx, scene_id)?;
+    let scene = scenes::get_scene(&tx, scene_id)?;
+    let (old, new) = replace(&tx, scene_id, &clean, source::MANUAL)?;
+    activity::record(
+        &tx,
+        &scene.project_id,
+        "scene",
+        scene_id,
+        "updated",
+        &format!("Tagged scene {}", scene.scene_number),
+        &if old == new {
+            Vec::new()
+        } else {
+            vec![FieldChange {
+                field: "tags".into(),
+                old,
+                new,
+            }]
+        },
+    )?;
+    let tags = tags_for_scene(&tx, scene_id)?;
+    tx.commit()?;
+    Ok(tags)
+}
+
+#[tauri::command]
+pub fn set_scene_tags(
+    db: State<'_, Database>,
+    scene_id: String,
+    tags: Vec<String>,
+) -> AppResult<Vec<SceneTag>> {
+    set_tags(&db.conn(), &scene_id, &tags)
+}
+
+struct Untagged {
+    id: String,
+    number: i64,
+    title: String,
+    description: String,
+    location: String,
+    camera_angle: String,
+    lighting: String,
+}
+
+fn build_prompt(scenes: &[Untagged]) -> String {
+    let listing = scenes
+        .iter()
+        .map(|s| {
+            format!(
+                "[id: {}] Scene {} — {}\nLocation: {}\nCamera: {}\nLighting: {}\nDescription: {}",
+                s.id, s.number, s.title, s.location, s.camera_angle, s.lighting, s.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!(
+        "You are a first assistant director breaking down a film storyboard.
+
+Scenes:
+
+{}
+
+For each scene, list up to {} short keyword tags a crew would filter by: time of day, weather, interior/exterior, shot type, and production needs such as stunts, crowds, vehicles or heavy VFX. Prefer these tags where they fit: {}. Only tag what the scene clearly shows.
+
+Return a JSON array. Each item has fields: sceneId, tags (array of strings). Return ONLY the JSON array.",
+        listing,
+        MAX_AUTO_TAGS,
+        SUGGESTED.join(", ")
+    )
+}
+
+/// A project's scenes waiting for tags, from `prepare_auto_tags`.
+pub struct AutoTagging {
+    project_id: String,
+    credential: Credential,
+    untagged: Vec<Untagged>,
+    skipped: Vec<String>,
+}
+
+impl AutoTagging {
+    /// Whether no scene is left for the LLM to tag.
+    pub fn is_empty(&self) -> bool {
+        self.untagged.is_empty()
+    }
+}
+
+/// Read what auto-tagging a project needs: the LLM credential and the
+/// scenes to tag. Scenes with manual tags or no description are skipped.
+pub fn prepare_auto_tags(conn: &Connection, project_id: &str) -> AppResult<AutoTagging> {
+    projects::get_project(conn, project_id)?;
+    let credential = llm::credential(conn, Some(project_id))?;
+    let manual: Vec<String> = tags_for_project(conn, project_id)?
+        .into_iter()
+        .filter(|t| t.source == source::MANUAL)
+        .map(|t| t.scene_id)
+        .collect();
+    let mut untagged = Vec::new();
+    let mut skipped = Vec::new();
+    for s in scenes::list_for_project(conn, project_id)? {
+        if manual.contains(&s.id) || s.description.trim().is_empty() {
+            skipped.push(s.id);
+            continue;
+        }
+        untagged.push(Untagged {
+            id: s.id,
+            number: s.scene_number,
+            title: s.title,
+            description: s.description,
+            location: s.location,
+            camera_angle: s.camera_angle,
+            lighting: s.lighting,
+        });
+    }
+    Ok(AutoTagging {
+        project_id: project_id.to_string(),
+        credential,
+        untagged,
+        skipped,
+    })
+}
+
+/// Ask the LLM for keyword tags for the prepared scenes.
+pub async fn suggest(db: &Database, tagging: &AutoTagging) -> AppResult<Vec<Value>> {
+    Ok(llm::into_array(
+        llm::generate_json_seeded(
+            db,
+            &tagging.project_id,
+            &tagging.credential,
+            &build_prompt(&tagging.untagged),
+            0.2,
+            llm::ANALYSIS_SEED,
+        )
+        .await?,
+    ))
+}
+
+/// Store the LLM's `items` as `auto` tags, replacing earlier suggestions.
+/// Scenes tagged by hand in the meantime keep their tags.
+pub fn apply_suggestions(
+    conn: &Connection,
+    tagging: AutoTagging,
+    items: &[Value],
+) -> AppResult<AutoTagResult> {
+    let AutoTagging {
+        project_id,
+        untagged,
+        skipped,
+        ..
+    } = tagging;
+    let mut result = AutoTagResult {
+        tagged: Vec::new(),
+        skipped,
+    };
+    let mut suggested: HashMap<&str, Vec<String>> = HashMap::new();
+    for item in items {
+        let Some(scene) = untagged
+            .iter()
+            .find(|s| Some(s.id.as_str()) == item["sceneId"].as_str())
+        else {
+            continue;
+        };
+        let tags = suggested.entry(&scene.id).or_default();
+        for tag in item["tags"].as_array().into_iter().flatten() {
+            if let Some(tag) = tag.as_str().and_then(normalize) {
+                if !tags.contains(&tag) && tags.len() < MAX_AUTO_TAGS {
+                    tags.push(tag);
+                }
+            }
+        }
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let mut changes = Vec::new();
+    for scene in &untagged {
+        let tags = suggested.remove(scene.id.as_str()).unwrap_or_default();
+        // The scene may have been tagged by hand while the LLM was busy.
+        let manual = tags_for_scene(&tx, &scene.id)?
+            .iter()
+            .any(|t| t.source == source::MANUAL);
+        if tags.is_empty() || manual {
+            result.skipped.push(scene.id.clone());
+            continue;
+        }
+        let (old, new) = replace(&tx, &scene.id, &tags, source::AUTO)?;
+        if old != new {
+            changes.push(FieldChange {
+                field: scene.id.clone(),
+                old,
+                new,
+            });
+        }
+        result.tagged.push(SceneTags {
+            scene_id: scene.id.clone(),
+            tags,
+        });
+    }
+    if !changes.is_empty() {
+        activity::record(
+            &tx,
+            &project_id,
+            "project",
+            &project_id,
+            "tagged",
+            &format!("Auto-tagged {} scenes", changes.len()),
+            &changes,
+        )?;
+    }
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Ask the LLM for keyword tags for every scene in a project and store them
+/// as `auto` tags, replacing earlier suggestions. Scenes with manual tags
+/// or no description are skipped.
+#[tauri::command]
+pub async fn auto_tag_scenes(app: AppHandle, project_id: String) -> AppResult<AutoTagResult> {
+    let tagging = prepare_auto_tags(&app.state::<Database>().conn(), &project_id)?;
+    let items = if tagging.is_empty() {
+        Vec::new()
+    } else {
+        suggest(&app.state::<Database>(), &tagging).await?
+    };
+    apply_suggestions(&app.state::<Database>().conn(), tagging, &items)
+}
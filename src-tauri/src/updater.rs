@@ -0,0 +1,199 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::settings;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{App, AppHandle, Manager, State};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use ts_rs::TS;
+
+pub const CHANNEL_SETTING: &str = "updater.channel";
+/// Set to `false` to skip the update check at startup.
+pub const AUTO_CHECK_SETTING: &str = "updater.auto_check";
+
+/// Release manifest per channel. Beta builds are published under the
+/// `beta` release tag so stable users never see them.
+pub const CHANNELS: &[(&str, &str)] = &[
+    (
+        "stable",
+        "https://github.com/tdubofficial/AIDCv1.0.0/releases/latest/download/latest.json",
+    ),
+    (
+        "beta",
+        "https://github.com/tdubofficial/AIDCv1.0.0/releases/download/beta/latest.json",
+    ),
+];
+
+/// Minisign public key for release signatures, supplied at build time
+/// (the release workflow passes the `AIDC_UPDATER_PUBKEY` repository
+/// variable, matching the `TAURI_SIGNING_PRIVATE_KEY` it signs with). A
+/// build without one can still check for updates but refuses to install.
+const PUBKEY: Option<&str> = option_env!("AIDC_UPDATER_PUBKEY");
+
+/// Update found by the last check, held until the user agrees to install.
+#[derive(Default)]
+pub struct PendingUpdate(Mutex<Option<Update>>);
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    #[ts(type = "number")]
+    pub downloaded: u64,
+    #[ts(type = "number | null")]
+    pub total: Option<u64>,
+}
+
+fn updater_err(e: tauri_plugin_updater::Error) -> AppError {
+    AppError::Network(format!("updater: {}", e))
+}
+
+/// The update channel; `stable` unless another was chosen.
+pub fn channel(conn: &Connection) -> AppResult<String> {
+    Ok(settings::get(conn, CHANNEL_SETTING)?.unwrap_or_else(|| "stable".into()))
+}
+
+fn endpoint(channel: &str) -> AppResult<&'static str> {
+    CHANNELS
+        .iter()
+        .find(|(c, _)| *c == channel)
+        .map(|(_, url)| *url)
+        .ok_or_else(|| AppError::Invalid(format!("unknown update channel '{}'", channel)))
+}
+
+/// Ask the channel's manifest for a newer version. Emits `update-available`
+/// when there is one; never downloads or restarts on its own.
+async fn check(app: &AppHandle) -> AppResult<Option<UpdateInfo>> {
+    let channel = channel(&app.state::<Database>().conn())?;
+    let url = endpoint(&channel)?
+        .parse()
+        .map_err(|e| AppError::Invalid(format!("bad update endpoint: {}", e)))?;
+    let mut builder = app
+        .updater_builder()
+        .endpoints(vec![url])
+        .map_err(updater_err)?;
+    if let Some(key) = PUBKEY {
+        builder = builder.pubkey(key);
+    }
+    let update = builder
+        .build()
+        .map_err(updater_err)?
+        .check()
+        .await
+        .map_err(updater_err)?;
+
+    let info = update.as_ref().map(|u| UpdateInfo {
+        version: u.version.clone(),
+        current_version: u.current_version.clone(),
+        channel: channel.clone(),
+        notes: u.body.clone(),
+        date: u.date.map(|d| d.to_string()),
+    });
+    *app.state::<PendingUpdate>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = update;
+    if let Some(info) = &info {
+        AppEvent::UpdateAvailable(info.clone()).emit(app);
+    }
+    Ok(info)
+}
+
+/// Check once in the background after startup unless turned off.
+pub fn setup(app: &App) {
+    app.manage(PendingUpdate::default());
+    let app = app.handle().clone();
+    let enabled = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        settings::get(&conn, AUTO_CHECK_SETTING)
+            .map(|v| v.as_deref() != Some("false"))
+            .unwrap_or(true)
+    };
+    if !enabled || cfg!(debug_assertions) {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = check(&app).await {
+            tracing::warn!(error = %e, "update check failed");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_update_channel(app: AppHandle) -> AppResult<String> {
+    channel(&app.state::<Database>().conn())
+}
+
+/// Switch to a known update channel.
+pub fn set_channel(conn: &Connection, channel: &str) -> AppResult<()> {
+    endpoint(channel)?;
+    settings::set(conn, CHANNEL_SETTING, channel)
+}
+
+#[tauri::command]
+pub fn set_update_channel(
+    db: State<'_, Database>,
+    pending: State<'_, PendingUpdate>,
+    channel: String,
+) -> AppResult<()> {
+    set_channel(&db.conn(), &channel)?;
+    // Whatever was found belongs to the old channel.
+    pending.0.lock().unwrap_or_else(|e| e.into_inner()).take();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn check_for_update(app: AppHandle) -> AppResult<Option<UpdateInfo>> {
+    check(&app).await
+}
+
+/// Download, verify and install the update found by the last check.
+/// Emits `update-progress` while downloading and `update-installed` when
+/// done. The app keeps running; call `restart_app` when the user is ready.
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> AppResult<()> {
+    if PUBKEY.is_none() {
+        return Err(AppError::Invalid(
+            "this build has no update signing key; download the release manually".into(),
+        ));
+    }
+    let update = app
+        .state::<PendingUpdate>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .ok_or_else(|| AppError::Invalid("no update to install; check first".into()))?;
+    let mut downloaded = 0u64;
+    let progress_app = app.clone();
+    update
+        .download_and_install(
+            move |chunk, total| {
+                downloaded += chunk as u64;
+                AppEvent::UpdateProgress(UpdateProgress { downloaded, total }).emit(&progress_app);
+            },
+            || {},
+        )
+        .await
+        .map_err(updater_err)?;
+    AppEvent::UpdateInstalled(update.version.clone()).emit(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restart_app(app: AppHandle) {
+    app.restart();
+}
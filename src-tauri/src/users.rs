@@ -0,0 +1,217 @@
+//! Lightweight local user profiles for studios where several people share
+//! one workstation. Profiles live in the workspace database; the one picked
+//! at launch scopes personal settings (see `settings`) and the recent and
+//! pinned projects list to that person. No profile active means the shared
+//! scope, which is how single-user installs keep working unchanged.
+
+use crate::activity;
+use crate::clock;
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::settings;
+use crate::validation::{Validator, MAX_IMAGE_LEN};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+/// The signed-in profile of this app run.
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Profile picked last time, preselected by the launch picker.
+const LAST_USER_SETTING: &str = "users.last";
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct UserProfile {
+    pub id: String,
+    pub name: String,
+    /// Image data URI, or a short text such as initials or an emoji.
+    pub avatar: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct UserProfileInput {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub avatar: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct UserList {
+    pub active: Option<String>,
+    pub last_user_id: Option<String>,
+    pub users: Vec<UserProfile>,
+}
+
+/// The id of the active profile, if one is signed in.
+pub fn active() -> Option<String> {
+    ACTIVE.read().ok().and_then(|a| a.clone())
+}
+
+fn set_active(user_id: Option<String>) {
+    if let Ok(mut active) = ACTIVE.write() {
+        *active = user_id;
+    }
+}
+
+/// Back to the shared scope, e.g. when the workspace holding the profile
+/// is closed.
+pub fn sign_out() {
+    set_active(None);
+}
+
+const USER_COLUMNS: &str = "id, name, avatar, created_at, last_used_at";
+
+fn row_to_user(row: &Row) -> rusqlite::Result<UserProfile> {
+    Ok(UserProfile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        avatar: row.get(2)?,
+        created_at: row.get(3)?,
+        last_used_at: row.get(4)?,
+    })
+}
+
+pub fn get_user(conn: &Connection, id: &str) -> AppResult<UserProfile> {
+    conn.query_row(
+        &format!("SELECT {} FROM user_profiles WHERE id = ?1", USER_COLUMNS),
+        [id],
+        row_to_user,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("user {}", id)))
+}
+
+/// Every profile by name, with the active one and the one picked last.
+pub fn list(conn: &Connection) -> AppResult<UserList> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM user_profiles ORDER BY name COLLATE NOCASE",
+        USER_COLUMNS
+    ))?;
+    let users = stmt
+        .query_map([], row_to_user)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(UserList {
+        active: active(),
+        last_user_id: settings::get(conn, LAST_USER_SETTING)?
+            .filter(|id| users.iter().any(|u| &u.id == id)),
+        users,
+    })
+}
+
+#[tauri::command]
+pub fn list_users(db: State<'_, Database>) -> AppResult<UserList> {
+    list(&db.conn())
+}
+
+/// Add a profile or rename one. A new profile signs activity with its
+/// name; after that the display name is the user's own setting.
+pub fn save(conn: &Connection, input: &UserProfileInput) -> AppResult<UserProfile> {
+    let mut v = Validator::new();
+    v.name("name", &input.name)
+        .max_len("avatar", &input.avatar, MAX_IMAGE_LEN);
+    v.finish()?;
+    let name = input.name.trim();
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM user_profiles
+            WHERE name = ?1 COLLATE NOCASE AND id != coalesce(?2, ''))",
+        params![name, input.id],
+        |r| r.get(0),
+    )?;
+    if taken {
+        return Err(AppError::Invalid(format!(
+            "there is already a profile called {}",
+            name
+        )));
+    }
+    let id = match &input.id {
+        Some(id) => {
+            let changed = conn.execute(
+                "UPDATE user_profiles SET name = ?2, avatar = ?3 WHERE id = ?1",
+                params![id, name, input.avatar],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound(format!("user {}", id)));
+            }
+            id.clone()
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "INSERT INTO user_profiles (id, name, avatar, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![id, name, input.avatar, clock::now()],
+            )?;
+            tx.execute(
+                "INSERT INTO user_settings (user_id, key, value) VALUES (?1, ?2, ?3)",
+                params![id, activity::ACTOR_SETTING, name],
+            )?;
+            tx.commit()?;
+            id
+        }
+    };
+    get_user(conn, &id)
+}
+
+#[tauri::command]
+pub fn save_user(db: State<'_, Database>, input: UserProfileInput) -> AppResult<UserProfile> {
+    save(&db.conn(), &input)
+}
+
+/// Note a profile as used now and picked last, ahead of signing in as it.
+/// No id means the shared scope and touches nothing.
+pub fn select(conn: &Connection, id: Option<&str>) -> AppResult<Option<UserProfile>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    get_user(conn, id)?;
+    conn.execute(
+        "UPDATE user_profiles SET last_used_at = ?2 WHERE id = ?1",
+        params![id, clock::now()],
+    )?;
+    settings::set(conn, LAST_USER_SETTING, id)?;
+    Ok(Some(get_user(conn, id)?))
+}
+
+/// Sign in as a profile, or pass no id to go back to the shared scope.
+/// Emits `user-changed` with the profile (or null) so windows reload their
+/// settings.
+#[tauri::command]
+pub fn select_user(app: AppHandle, id: Option<String>) -> AppResult<Option<UserProfile>> {
+    let user = select(&app.state::<Database>().conn(), id.as_deref())?;
+    set_active(id);
+    tracing::info!(user = ?user.as_ref().map(|u| &u.id), "selected user profile");
+    AppEvent::UserChanged(user.clone()).emit(&app);
+    Ok(user)
+}
+
+/// Remove a profile with its settings, recent projects and drafts. Projects and
+/// everything in them are shared and stay.
+pub fn delete(conn: &Connection, id: &str) -> AppResult<()> {
+    let changed = conn.execute("DELETE FROM user_profiles WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("user {}", id)));
+    }
+    conn.execute("DELETE FROM drafts WHERE user_id = ?1", [id])?;
+    if active().as_deref() == Some(id) {
+        sign_out();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_user(db: State<'_, Database>, id: String) -> AppResult<()> {
+    delete(&db.conn(), &id)
+}
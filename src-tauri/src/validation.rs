@@ -0,0 +1,131 @@
+//! Input checks run before any write. Each command collects every problem
+//! with its input instead of stopping at the first, and fails with
+//! `AppError::Validation` listing them by field so the UI can mark each
+//! input. Field names are the camelCase names the frontend sends.
+
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::fmt::Display;
+
+pub const MAX_NAME_LEN: usize = 200;
+pub const MAX_TEXT_LEN: usize = 20_000;
+/// Photos and conditioning frames arrive as data URIs.
+pub const MAX_IMAGE_LEN: usize = 15 * 1024 * 1024;
+/// Longest scene, in seconds.
+pub const MAX_SCENE_DURATION: i64 = 600;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Implemented by command inputs that are checked before being written.
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+
+    fn check(&self) -> AppResult<()> {
+        let mut v = Validator::default();
+        self.validate(&mut v);
+        v.finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Validator {
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) -> &mut Self {
+        self.errors.push(FieldError {
+            field: format!("{}{}", self.prefix, field),
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Validate a nested input, with its fields reported as `field.inner`.
+    pub fn nested(&mut self, field: &str, input: &impl Validate) -> &mut Self {
+        let inner = format!("{}{}.", self.prefix, field);
+        let outer = std::mem::replace(&mut self.prefix, inner);
+        input.validate(self);
+        self.prefix = outer;
+        self
+    }
+
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.error(field, "is required");
+        }
+        self
+    }
+
+    pub fn max_len(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.chars().count() > max {
+            self.error(field, format!("must be at most {} characters", max));
+        }
+        self
+    }
+
+    /// A required, trimmed name no longer than `MAX_NAME_LEN`.
+    pub fn name(&mut self, field: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.error(field, "is required");
+        } else {
+            self.max_len(field, value.trim(), MAX_NAME_LEN);
+        }
+        self
+    }
+
+    pub fn range<T: PartialOrd + Display>(
+        &mut self,
+        field: &str,
+        value: T,
+        min: T,
+        max: T,
+    ) -> &mut Self {
+        if !(min <= value && value <= max) {
+            self.error(field, format!("must be between {} and {}", min, max));
+        }
+        self
+    }
+
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) -> &mut Self {
+        if !allowed.contains(&value) {
+            self.error(
+                field,
+                format!("'{}' is not one of: {}", value, allowed.join(", ")),
+            );
+        }
+        self
+    }
+
+    pub fn uuid(&mut self, field: &str, value: &str) -> &mut Self {
+        if uuid::Uuid::parse_str(value).is_err() {
+            self.error(field, "is not a valid id");
+        }
+        self
+    }
+
+    pub fn optional_uuid(&mut self, field: &str, value: Option<&str>) -> &mut Self {
+        if let Some(value) = value {
+            self.uuid(field, value);
+        }
+        self
+    }
+
+    pub fn finish(self) -> AppResult<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+}
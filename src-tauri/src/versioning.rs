@@ -0,0 +1,32 @@
+//! Optimistic concurrency for edits. Versioned rows carry a `version` that
+//! triggers bump on every user-facing change (migration 23). Update
+//! commands take the version the client last read as `expected_version`;
+//! when it is stale they fail with `AppError::Conflict` instead of
+//! overwriting. Clients that don't send a version are not checked.
+
+use crate::error::{AppError, AppResult, EditConflict};
+use serde::Serialize;
+
+/// Reject the edit if the stored row has moved on from `expected`.
+pub fn check<T: Serialize, Y: Serialize>(
+    entity_type: &str,
+    entity_id: &str,
+    expected: Option<i64>,
+    current_version: i64,
+    current: &T,
+    yours: &Y,
+) -> AppResult<()> {
+    match expected {
+        Some(expected) if expected != current_version => {
+            Err(AppError::Conflict(Box::new(EditConflict {
+                entity_type: entity_type.to_string(),
+                entity_id: entity_id.to_string(),
+                expected_version: expected,
+                current_version,
+                yours: serde_json::to_value(yours)?,
+                theirs: serde_json::to_value(current)?,
+            })))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,252 @@
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use ts_rs::TS;
+
+/// Which managed lookup table a term belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum VocabularyKind {
+    CameraAngle,
+    Lighting,
+}
+
+impl VocabularyKind {
+    fn table(self) -> &'static str {
+        match self {
+            VocabularyKind::CameraAngle => "camera_angles",
+            VocabularyKind::Lighting => "lighting_styles",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            VocabularyKind::CameraAngle => "camera angle",
+            VocabularyKind::Lighting => "lighting",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct VocabularyTerm {
+    pub key: String,
+    pub label: String,
+    pub description: String,
+    /// Text spliced into generation prompts when this term is selected.
+    pub prompt_fragment: String,
+    pub is_default: bool,
+    pub builtin: bool,
+    #[ts(type = "number")]
+    pub sort_order: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct VocabularyTermInput {
+    pub key: String,
+    pub label: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub prompt_fragment: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Vocabulary {
+    pub camera_angles: Vec<VocabularyTerm>,
+    pub lighting: Vec<VocabularyTerm>,
+}
+
+fn row_to_term(row: &Row) -> rusqlite::Result<VocabularyTerm> {
+    Ok(VocabularyTerm {
+        key: row.get(0)?,
+        label: row.get(1)?,
+        description: row.get(2)?,
+        prompt_fragment: row.get(3)?,
+        is_default: row.get(4)?,
+        builtin: row.get(5)?,
+        sort_order: row.get(6)?,
+    })
+}
+
+pub fn list_terms(conn: &Connection, kind: VocabularyKind) -> AppResult<Vec<VocabularyTerm>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT key, label, description, prompt_fragment, is_default, builtin, sort_order
+         FROM {} ORDER BY sort_order, label COLLATE NOCASE",
+        kind.table()
+    ))?;
+    let terms = stmt
+        .query_map([], row_to_term)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(terms)
+}
+
+pub fn default_key(conn: &Connection, kind: VocabularyKind) -> AppResult<String> {
+    conn.query_row(
+        &format!(
+            "SELECT key FROM {} ORDER BY is_default DESC, sort_order LIMIT 1",
+            kind.table()
+        ),
+        [],
+        |r| r.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("{} vocabulary is empty", kind.label())))
+}
+
+/// Reject values that aren't in the managed vocabulary.
+pub fn validate(conn: &Connection, kind: VocabularyKind, key: &str) -> AppResult<()> {
+    let exists: bool = conn.query_row(
+        &format!(
+            "SELECT EXISTS(SELECT 1 FROM {} WHERE key = ?1)",
+            kind.table()
+        ),
+        [key],
+        |r| r.get(0),
+    )?;
+    if exists {
+        Ok(())
+    } else {
+        Err(AppError::Invalid(format!(
+            "unknown {} '{}'",
+            kind.label(),
+            key
+        )))
+    }
+}
+
+/// Prompt text for a term, or `None` if it has none or doesn't exist.
+pub fn prompt_fragment(
+    conn: &Connection,
+    kind: VocabularyKind,
+    key: &str,
+) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT prompt_fragment FROM {} WHERE key = ?1",
+                kind.table()
+            ),
+            [key],
+            |r| r.get::<_, String>(0),
+        )
+        .optional()?
+        .filter(|f| !f.is_empty()))
+}
+
+pub fn vocabulary(conn: &Connection) -> AppResult<Vocabulary> {
+    Ok(Vocabulary {
+        camera_angles: list_terms(conn, VocabularyKind::CameraAngle)?,
+        lighting: list_terms(conn, VocabularyKind::Lighting)?,
+    })
+}
+
+#[tauri::command]
+pub fn get_vocabulary(db: State<'_, Database>) -> AppResult<Vocabulary> {
+    vocabulary(&db.conn())
+}
+
+/// Add a user term or edit an existing one. Built-in terms keep their key.
+pub fn save_term(
+    conn: &Connection,
+    kind: VocabularyKind,
+    input: &VocabularyTermInput,
+) -> AppResult<VocabularyTerm> {
+    let key = input.key.trim().to_lowercase();
+    if key.is_empty() || input.label.trim().is_empty() {
+        return Err(AppError::Invalid("key and label are required".into()));
+    }
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (key, label, description, prompt_fragment, sort_order)
+             VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM {0}))
+             ON CONFLICT(key) DO UPDATE SET
+                label = excluded.label,
+                description = excluded.description,
+                prompt_fragment = excluded.prompt_fragment",
+            kind.table()
+        ),
+        params![
+            key,
+            input.label.trim(),
+            input.description,
+            input.prompt_fragment
+        ],
+    )?;
+    Ok(conn.query_row(
+        &format!(
+            "SELECT key, label, description, prompt_fragment, is_default, builtin, sort_order
+             FROM {} WHERE key = ?1",
+            kind.table()
+        ),
+        [&key],
+        row_to_term,
+    )?)
+}
+
+#[tauri::command]
+pub fn save_vocabulary_term(
+    db: State<'_, Database>,
+    kind: VocabularyKind,
+    input: VocabularyTermInput,
+) -> AppResult<VocabularyTerm> {
+    save_term(&db.conn(), kind, &input)
+}
+
+/// Remove a user-added term. Refused while any scene still uses it.
+pub fn delete_term(conn: &Connection, kind: VocabularyKind, key: &str) -> AppResult<()> {
+    let builtin: bool = conn
+        .query_row(
+            &format!("SELECT builtin FROM {} WHERE key = ?1", kind.table()),
+            [key],
+            |r| r.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("{} '{}'", kind.label(), key)))?;
+    if builtin {
+        return Err(AppError::Invalid(format!(
+            "built-in {} '{}' cannot be deleted",
+            kind.label(),
+            key
+        )));
+    }
+    let column = match kind {
+        VocabularyKind::CameraAngle => "camera_angle",
+        VocabularyKind::Lighting => "lighting",
+    };
+    let in_use: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM scenes WHERE {} = ?1", column),
+        [key],
+        |r| r.get(0),
+    )?;
+    if in_use > 0 {
+        return Err(AppError::Invalid(format!(
+            "{} '{}' is used by {} scene(s)",
+            kind.label(),
+            key,
+            in_use
+        )));
+    }
+    conn.execute(
+        &format!("DELETE FROM {} WHERE key = ?1", kind.table()),
+        [key],
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_vocabulary_term(
+    db: State<'_, Database>,
+    kind: VocabularyKind,
+    key: String,
+) -> AppResult<()> {
+    delete_term(&db.conn(), kind, &key)
+}
@@ -0,0 +1,410 @@
+use crate::characters::{self, Character, VoiceProfile};
+use crate::confidential;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::{projects, providers, settings};
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use ts_rs::TS;
+
+const ELEVEN_BASE: &str = "https://api.elevenlabs.io/v1";
+const ELEVEN_TTS_MODEL: &str = "eleven_multilingual_v2";
+
+/// Settings key holding the ElevenLabs API key.
+pub const ELEVENLABS_KEY_SETTING: &str = "api_key.elevenlabs";
+
+pub const SAMPLE_EXTENSIONS: &[&str] = &["mp3", "wav", "m4a", "ogg", "flac", "webm"];
+/// ElevenLabs instant cloning accepts up to 25 samples.
+pub const MAX_SAMPLES: i64 = 25;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct VoiceSample {
+    pub id: String,
+    pub character_id: String,
+    pub file_path: String,
+    pub file_name: String,
+    pub created_at: String,
+}
+
+/// Audio produced for a line of dialog.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct SpeechClip {
+    pub character_id: String,
+    pub file_path: String,
+    pub voice_id: String,
+}
+
+fn row_to_sample(row: &Row) -> rusqlite::Result<VoiceSample> {
+    Ok(VoiceSample {
+        id: row.get(0)?,
+        character_id: row.get(1)?,
+        file_path: row.get(2)?,
+        file_name: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// The ElevenLabs key, unless the character's project is confidential.
+fn elevenlabs_key(conn: &Connection, character_id: &str) -> AppResult<String> {
+    let character = characters::get_character(conn, character_id)?;
+    confidential::ensure_remote_allowed(conn, &character.project_id, "ElevenLabs voices")?;
+    settings::get(conn, ELEVENLABS_KEY_SETTING)?
+        .filter(|k| !k.is_empty())
+        .ok_or_else(|| AppError::Invalid("ElevenLabs API key not configured".into()))
+}
+
+/// Characters used and allowed this billing period on an ElevenLabs plan.
+pub async fn subscription_usage(api_key: &str) -> AppResult<(f64, f64)> {
+    let res = providers::client()
+        .get(format!("{}/user/subscription", ELEVEN_BASE))
+        .header("xi-api-key", api_key)
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "ElevenLabs subscription ({}): {}",
+            status, text
+        )));
+    }
+    let data: Value = res.json().await?;
+    match (
+        data["character_count"].as_f64(),
+        data["character_limit"].as_f64(),
+    ) {
+        (Some(used), Some(limit)) => Ok((used, limit)),
+        _ => Err(AppError::Provider(
+            "ElevenLabs returned no character quota".into(),
+        )),
+    }
+}
+
+fn set_clone_status(conn: &Connection, character_id: &str, status: &str) -> AppResult<()> {
+    conn.execute(
+        "UPDATE characters SET voice_clone_status = ?2 WHERE id = ?1",
+        params![character_id, status],
+    )?;
+    Ok(())
+}
+
+pub fn samples_for(conn: &Connection, character_id: &str) -> AppResult<Vec<VoiceSample>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, character_id, file_path, file_name, created_at
+         FROM voice_samples WHERE character_id = ?1 ORDER BY created_at, rowid",
+    )?;
+    let samples = stmt
+        .query_map([character_id], row_to_sample)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(samples)
+}
+
+#[tauri::command]
+pub fn list_voice_samples(
+    db: State<'_, Database>,
+    character_id: String,
+) -> AppResult<Vec<VoiceSample>> {
+    samples_for(&db.conn(), &character_id)
+}
+
+/// Copy an audio file into `samples_dir` as a cloning sample.
+pub fn add_sample(
+    conn: &Connection,
+    character_id: &str,
+    source: &Path,
+    samples_dir: &Path,
+) -> AppResult<VoiceSample> {
+    let ext = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    if !SAMPLE_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(AppError::Invalid(format!(
+            "unsupported sample format '{}'",
+            ext
+        )));
+    }
+    let file_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let character = characters::get_character(conn, character_id)?;
+    projects::ensure_unlocked(conn, &character.project_id)?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM voice_samples WHERE character_id = ?1",
+        [character_id],
+        |r| r.get(0),
+    )?;
+    if count >= MAX_SAMPLES {
+        return Err(AppError::Invalid(format!(
+            "at most {} samples per voice",
+            MAX_SAMPLES
+        )));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let dest = samples_dir.join(format!("{}.{}", id, ext));
+    std::fs::copy(source, &dest)?;
+    conn.execute(
+        "INSERT INTO voice_samples (id, character_id, file_path, file_name) VALUES (?1, ?2, ?3, ?4)",
+        params![id, character_id, dest.to_string_lossy(), file_name],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id, character_id, file_path, file_name, created_at FROM voice_samples WHERE id = ?1",
+        [&id],
+        row_to_sample,
+    )?)
+}
+
+#[tauri::command]
+pub fn add_voice_sample(
+    db: State<'_, Database>,
+    character_id: String,
+    source_path: String,
+) -> AppResult<VoiceSample> {
+    let samples_dir = db::media_dir("voice-samples")?;
+    add_sample(
+        &db.conn(),
+        &character_id,
+        Path::new(&source_path),
+        &samples_dir,
+    )
+}
+
+/// Delete a sample and its file.
+pub fn remove_sample(conn: &Connection, id: &str) -> AppResult<()> {
+    let (path, project_id): (String, String) = conn
+        .query_row(
+            "SELECT v.file_path, c.project_id FROM voice_samples v
+             JOIN characters c ON c.id = v.character_id WHERE v.id = ?1",
+            [id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("voice sample {}", id))
+            }
+            other => other.into(),
+        })?;
+    projects::ensure_unlocked(conn, &project_id)?;
+    conn.execute("DELETE FROM voice_samples WHERE id = ?1", [id])?;
+    std::fs::remove_file(path).ok();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_voice_sample(db: State<'_, Database>, id: String) -> AppResult<()> {
+    remove_sample(&db.conn(), &id)
+}
+
+/// A character's samples ready to upload, from `prepare_clone`.
+pub struct VoiceClone {
+    api_key: String,
+    character: Character,
+    samples: Vec<(String, String)>,
+}
+
+/// Check a character can be cloned and mark its clone pending. Needs the
+/// ElevenLabs key, an unlocked project and at least one sample.
+pub fn prepare_clone(conn: &Connection, character_id: &str) -> AppResult<VoiceClone> {
+    let api_key = elevenlabs_key(conn, character_id)?;
+    let character = characters::get_character(conn, character_id)?;
+    projects::ensure_unlocked(conn, &character.project_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT file_path, file_name FROM voice_samples WHERE character_id = ?1 ORDER BY created_at",
+    )?;
+    let samples = stmt
+        .query_map([character_id], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if samples.is_empty() {
+        return Err(AppError::Invalid(
+            "add at least one voice sample before cloning".into(),
+        ));
+    }
+    set_clone_status(conn, character_id, "pending")?;
+    Ok(VoiceClone {
+        api_key,
+        character,
+        samples,
+    })
+}
+
+/// Upload the samples to ElevenLabs instant voice cloning; returns the new
+/// voice id.
+pub async fn upload(clone: &VoiceClone) -> AppResult<String> {
+    upload_clone(&clone.api_key, &clone.character, &clone.samples).await
+}
+
+/// Store the outcome of an upload: switch the character to the cloned
+/// voice, or mark the clone failed.
+pub fn record_clone(
+    conn: &Connection,
+    clone: &VoiceClone,
+    voice_id: AppResult<String>,
+) -> AppResult<Character> {
+    let character = &clone.character;
+    match voice_id {
+        Ok(voice_id) => {
+            conn.execute(
+                "UPDATE characters SET voice_engine = 'elevenlabs', voice_id = ?2,
+                    voice_name = ?3, voice_clone_status = 'ready'
+                 WHERE id = ?1",
+                params![
+                    character.id,
+                    voice_id,
+                    format!("{} (cloned)", character.name)
+                ],
+            )?;
+        }
+        Err(e) => {
+            set_clone_status(conn, &character.id, "failed")?;
+            return Err(e);
+        }
+    }
+    characters::get_character(conn, &character.id)
+}
+
+/// Upload the character's samples to ElevenLabs instant voice cloning and
+/// switch the character to the new voice. Emits `character-updated`.
+#[tauri::command]
+pub async fn clone_character_voice(app: AppHandle, character_id: String) -> AppResult<Character> {
+    let clone = prepare_clone(&app.state::<Database>().conn(), &character_id)?;
+    let voice_id = upload(&clone).await;
+    let updated = record_clone(&app.state::<Database>().conn(), &clone, voice_id)?;
+    AppEvent::CharacterUpdated(updated.clone()).emit(&app);
+    Ok(updated)
+}
+
+async fn upload_clone(
+    api_key: &str,
+    character: &Character,
+    samples: &[(String, String)],
+) -> AppResult<String> {
+    let mut form = reqwest::multipart::Form::new()
+        .text("name", format!("AIDC — {}", character.name))
+        .text("description", character.description.clone());
+    for (path, name) in samples {
+        let bytes = tokio::fs::read(path).await?;
+        form = form.part(
+            "files",
+            reqwest::multipart::Part::bytes(bytes).file_name(name.clone()),
+        );
+    }
+
+    let res = providers::client()
+        .post(format!("{}/voices/add", ELEVEN_BASE))
+        .header("xi-api-key", api_key)
+        .multipart(form)
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "elevenlabs clone ({}): {}",
+            status, text
+        )));
+    }
+    let data: Value = res.json().await?;
+    data["voice_id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Provider("elevenlabs returned no voice_id".into()))
+}
+
+/// A line of dialog ready to render, from `prepare_speech`.
+pub struct Speech {
+    api_key: String,
+    character_id: String,
+    text: String,
+    voice: VoiceProfile,
+}
+
+/// Check a line can be rendered in the character's voice. Browser-engine
+/// voices are played live by the frontend and can't be rendered here.
+pub fn prepare_speech(conn: &Connection, character_id: &str, text: &str) -> AppResult<Speech> {
+    if text.trim().is_empty() {
+        return Err(AppError::Invalid("no dialog text".into()));
+    }
+    let voice = characters::get_character(conn, character_id)?.voice;
+    if voice.engine != "elevenlabs" || voice.voice_id.is_empty() {
+        return Err(AppError::Invalid(
+            "character has no ElevenLabs voice; browser voices play in the app".into(),
+        ));
+    }
+    Ok(Speech {
+        api_key: elevenlabs_key(conn, character_id)?,
+        character_id: character_id.to_string(),
+        text: text.to_string(),
+        voice,
+    })
+}
+
+/// Render one line of dialog in the character's voice to an mp3 file.
+pub async fn synthesize(app: &AppHandle, character_id: &str, text: &str) -> AppResult<SpeechClip> {
+    let speech = prepare_speech(&app.state::<Database>().conn(), character_id, text)?;
+    speak(speech).await
+}
+
+/// Have ElevenLabs render a prepared line into the audio media folder.
+pub async fn speak(speech: Speech) -> AppResult<SpeechClip> {
+    let Speech {
+        api_key,
+        character_id,
+        text,
+        voice,
+    } = speech;
+    let res = providers::client()
+        .post(format!("{}/text-to-speech/{}", ELEVEN_BASE, voice.voice_id))
+        .header("xi-api-key", &api_key)
+        .header("Accept", "audio/mpeg")
+        .json(&json!({
+            "text": text,
+            "model_id": ELEVEN_TTS_MODEL,
+            "voice_settings": {
+                "stability": voice.stability,
+                "similarity_boost": voice.similarity,
+                "speed": voice.speed,
+            },
+        }))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        return Err(AppError::Provider(format!(
+            "elevenlabs tts ({}): {}",
+            status, body
+        )));
+    }
+    let bytes = res.bytes().await?;
+    let path: PathBuf = db::media_dir("audio")?.join(format!("{}.mp3", uuid::Uuid::new_v4()));
+    tokio::fs::write(&path, &bytes).await?;
+
+    Ok(SpeechClip {
+        character_id,
+        file_path: path.to_string_lossy().to_string(),
+        voice_id: voice.voice_id,
+    })
+}
+
+#[tauri::command]
+pub async fn synthesize_character_speech(
+    app: AppHandle,
+    character_id: String,
+    text: String,
+) -> AppResult<SpeechClip> {
+    synthesize(&app, &character_id, &text).await
+}
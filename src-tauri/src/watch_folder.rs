@@ -0,0 +1,348 @@
+//! A folder watched for footage rendered outside the app. A video dropped
+//! there is matched to a scene by its file name, moved into the media
+//! folder and added to the scene as a completed take, alongside takes the
+//! app generated.
+//!
+//! A name containing a scene id matches that scene anywhere; otherwise the
+//! leading scene number (`12`, `sc12_v2`, `Scene 12A final`) is looked up
+//! in the folder's project. Files that match nothing, or match a locked
+//! scene, are left where they are and reported with
+//! `watch-folder-skipped`.
+
+use crate::activity;
+use crate::clock;
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::jobs::{self, kind, status};
+use crate::projects;
+use crate::scenes::{self, Scene};
+use crate::settings;
+use crate::validation::Validator;
+use crate::workflow::{self, SceneStatus};
+use notify::{EventKind, RecursiveMode, Watcher as _};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{App, AppHandle, Manager, State};
+use tokio::sync::mpsc;
+use ts_rs::TS;
+
+/// The watched folder; empty turns watching off. Paths are machine-local.
+const FOLDER_SETTING: &str = "tools.watch_folder";
+/// The project whose scene numbers file names refer to.
+const PROJECT_SETTING: &str = "tools.watch_folder_project";
+/// Provider recorded on ingested takes.
+pub const PROVIDER: &str = "external";
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "m4v", "webm", "mkv", "avi"];
+/// A file is ingested once its size holds for this long with no new
+/// events, so half-copied renders are left alone.
+const SETTLE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct WatchFolder {
+    pub path: String,
+    pub project_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFile {
+    pub file: String,
+    pub reason: String,
+}
+
+/// The running watcher; replaced whenever the folder changes.
+#[derive(Default)]
+pub struct FolderWatcher(Mutex<Option<notify::RecommendedWatcher>>);
+
+/// The watched folder and project; an empty path means none.
+pub fn load(conn: &Connection) -> AppResult<WatchFolder> {
+    Ok(WatchFolder {
+        path: settings::get(conn, FOLDER_SETTING)?.unwrap_or_default(),
+        project_id: settings::get(conn, PROJECT_SETTING)?.filter(|p| !p.is_empty()),
+    })
+}
+
+fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// The scene number and letter suffix a file name starts with, after an
+/// optional `scene`/`sc`/`s` prefix: `sc12A_v2` is 12A, `012-final` is 12.
+fn scene_label(stem: &str) -> Option<(i64, String)> {
+    let lower = stem.to_ascii_lowercase();
+    let rest = ["scene", "sc", "s"]
+        .iter()
+        .find_map(|p| lower.strip_prefix(p))
+        .unwrap_or(&lower)
+        .trim_start_matches([' ', '_', '-', '.']);
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    let number = digits.parse().ok()?;
+    let after = &rest[digits.len()..];
+    let letters: String = after
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect();
+    // More than two letters is a word (`12take`), not a suffix.
+    let suffix = if letters.len() <= 2 {
+        letters.to_ascii_uppercase()
+    } else {
+        String::new()
+    };
+    Some((number, suffix))
+}
+
+/// The scene a file name refers to, if exactly one matches.
+pub fn match_scene(
+    conn: &Connection,
+    stem: &str,
+    project_id: Option<&str>,
+) -> AppResult<Option<Scene>> {
+    for (i, _) in stem.char_indices() {
+        if i + 36 > stem.len() {
+            break;
+        }
+        let Some(candidate) = stem.get(i..i + 36) else {
+            continue;
+        };
+        if uuid::Uuid::parse_str(candidate).is_ok() {
+            if let Ok(scene) = scenes::get_scene(conn, candidate) {
+                return Ok(Some(scene));
+            }
+        }
+    }
+    let (Some(project_id), Some((number, suffix))) = (project_id, scene_label(stem)) else {
+        return Ok(None);
+    };
+    let mut matches: Vec<Scene> = scenes::list_for_project(conn, project_id)?
+        .into_iter()
+        .filter(|s| s.scene_number == number && s.scene_suffix.eq_ignore_ascii_case(&suffix))
+        .collect();
+    Ok(if matches.len() == 1 {
+        matches.pop()
+    } else {
+        None
+    })
+}
+
+/// Move `from` to `to`, copying when they are on different drives.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+/// Add `file` to `scene` as a completed take. Returns the take's id.
+fn import_take(conn: &Connection, scene: &Scene, file: &Path) -> AppResult<String> {
+    workflow::ensure_editable(conn, &scene.id)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let extension = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("mp4")
+        .to_ascii_lowercase();
+    let target = db::media_dir("takes")?.join(format!("{}.{}", id, extension));
+    move_file(file, &target)?;
+    let name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let now = clock::now();
+    conn.execute(
+        "INSERT INTO video_jobs (id, scene_id, provider, kind, job_id, status, video_url,
+            started_at, completed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+        params![
+            id,
+            scene.id,
+            PROVIDER,
+            kind::VIDEO,
+            name,
+            status::COMPLETED,
+            target.to_string_lossy(),
+            now
+        ],
+    )?;
+    conn.execute(
+        "UPDATE scenes SET stale_reason = '' WHERE id = ?1",
+        [&scene.id],
+    )?;
+    activity::record(
+        conn,
+        &scene.project_id,
+        "take",
+        &id,
+        "imported",
+        &format!("Imported {} from the watch folder", name),
+        &[],
+    )?;
+    Ok(id)
+}
+
+fn ingest(app: &AppHandle, file: &Path) {
+    let name = file.display().to_string();
+    let result = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        load(&conn).and_then(|config| {
+            let stem = file
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match match_scene(&conn, &stem, config.project_id.as_deref())? {
+                Some(scene) => Ok((import_take(&conn, &scene, file)?, scene.id)),
+                None => Err(AppError::NotFound(
+                    "no single scene matches the file name".into(),
+                )),
+            }
+        })
+    };
+    match result {
+        Ok((take_id, scene_id)) => {
+            tracing::info!(file = %name, take_id = %take_id, "imported take from watch folder");
+            workflow::advance_if_allowed(app, &scene_id, SceneStatus::Review);
+            jobs::emit_job(app, &take_id);
+        }
+        Err(e) => {
+            tracing::warn!(file = %name, error = %e, "skipped watch folder file");
+            AppEvent::WatchFolderSkipped(SkippedFile {
+                file: name,
+                reason: e.to_string(),
+            })
+            .emit(app);
+        }
+    }
+}
+
+/// Ingest files as they settle. Files already in the folder are picked up
+/// first. Ends when the watcher feeding `events` is dropped.
+async fn run(app: AppHandle, folder: PathBuf, mut events: mpsc::UnboundedReceiver<PathBuf>) {
+    // The size seen at the last quiet moment; None until then.
+    let mut pending: HashMap<PathBuf, Option<u64>> = std::fs::read_dir(&folder)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| is_video(p))
+                .map(|p| (p, None))
+                .collect()
+        })
+        .unwrap_or_default();
+    loop {
+        match tokio::time::timeout(SETTLE, events.recv()).await {
+            Ok(Some(path)) => {
+                pending.insert(path, None);
+                continue;
+            }
+            Ok(None) => return,
+            Err(_) => {}
+        }
+        let mut ready = Vec::new();
+        pending.retain(|path, seen| match std::fs::metadata(path) {
+            Ok(meta) if meta.is_file() => {
+                if *seen == Some(meta.len()) && meta.len() > 0 {
+                    ready.push(path.clone());
+                    false
+                } else {
+                    *seen = Some(meta.len());
+                    true
+                }
+            }
+            _ => false,
+        });
+        for path in ready {
+            ingest(&app, &path);
+        }
+    }
+}
+
+/// (Re)start watching the configured folder, or stop if there is none.
+fn start(app: &AppHandle) -> AppResult<()> {
+    let state = app.state::<FolderWatcher>();
+    let mut current = state.0.lock().unwrap_or_else(|e| e.into_inner());
+    // Dropping the old watcher ends its task.
+    *current = None;
+    let config = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        load(&conn)?
+    };
+    if config.path.is_empty() {
+        return Ok(());
+    }
+    let folder = PathBuf::from(&config.path);
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            for path in event.paths.into_iter().filter(|p| is_video(p)) {
+                let _ = tx.send(path);
+            }
+        }
+    })
+    .map_err(|e| AppError::Io(format!("could not watch {}: {}", folder.display(), e)))?;
+    watcher
+        .watch(&folder, RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Io(format!("could not watch {}: {}", folder.display(), e)))?;
+    *current = Some(watcher);
+    tauri::async_runtime::spawn(run(app.clone(), folder, rx));
+    Ok(())
+}
+
+pub fn setup(app: &App) {
+    app.manage(FolderWatcher::default());
+    if let Err(e) = start(app.handle()) {
+        tracing::warn!(error = %e, "could not start the watch folder");
+    }
+}
+
+#[tauri::command]
+pub fn get_watch_folder(db: State<'_, Database>) -> AppResult<WatchFolder> {
+    load(&db.conn())
+}
+
+/// Store the folder to watch (empty stops watching) and the project whose
+/// scene numbers file names refer to.
+pub fn save(conn: &Connection, path: &str, project_id: Option<&str>) -> AppResult<WatchFolder> {
+    let path = path.trim();
+    let mut v = Validator::new();
+    v.optional_uuid("projectId", project_id);
+    if !path.is_empty() && !Path::new(path).is_dir() {
+        v.error("path", "is not a folder");
+    }
+    v.finish()?;
+    if let Some(id) = project_id {
+        projects::get_project(conn, id)?;
+    }
+    settings::set(conn, FOLDER_SETTING, path)?;
+    settings::set(conn, PROJECT_SETTING, project_id.unwrap_or_default())?;
+    load(conn)
+}
+
+/// Watch `path` (empty stops watching), matching scene numbers in
+/// `project_id`. Videos already in the folder are ingested straight away.
+#[tauri::command]
+pub fn set_watch_folder(
+    app: AppHandle,
+    path: String,
+    project_id: Option<String>,
+) -> AppResult<WatchFolder> {
+    let config = save(
+        &app.state::<Database>().conn(),
+        &path,
+        project_id.as_deref(),
+    )?;
+    start(&app)?;
+    Ok(config)
+}
@@ -0,0 +1,199 @@
+//! Several project windows at once. Every window talks to the same
+//! `Database`, whose mutex already serializes their writes; what they need
+//! on top is to hear about each other's edits.
+//!
+//! The sync change log (migration 21) records every insert, edit and
+//! delete of a project, character or scene, whoever made it. An update
+//! hook on each connection passes new log rows to a background task, which
+//! batches them briefly and emits `entity-changed` to all windows once the
+//! writing command has released the database.
+
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::projects;
+use crate::sync;
+use rusqlite::hooks::Action;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use ts_rs::TS;
+
+/// How long to gather changes before broadcasting, so a bulk save becomes
+/// one burst of events rather than a trickle.
+const BATCH_WINDOW: Duration = Duration::from_millis(50);
+
+static FEED: OnceLock<UnboundedSender<i64>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityChanged {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub project_id: String,
+    /// `"created"`, `"updated"` or `"deleted"`.
+    pub action: String,
+    /// Fields touched by updates in this batch.
+    pub fields: Vec<String>,
+    /// The row's version after the change; None once deleted. Windows can
+    /// skip refetching when they already hold this version.
+    #[ts(type = "number | null")]
+    pub version: Option<i64>,
+}
+
+/// Watch `conn` for new change-log rows. Called for every connection
+/// `db::connect` opens; harmless before `spawn_broadcaster` runs.
+pub fn install(conn: &Connection) {
+    conn.update_hook(Some(
+        |action: Action, _db: &str, table: &str, rowid: i64| {
+            if action == Action::SQLITE_INSERT && table == "sync_changes" {
+                if let Some(feed) = FEED.get() {
+                    let _ = feed.send(rowid);
+                }
+            }
+        },
+    ));
+}
+
+fn action_for(op: &str) -> &'static str {
+    match op {
+        "insert" => "created",
+        "delete" => "deleted",
+        _ => "updated",
+    }
+}
+
+/// Collapse a batch of log rows into one event per entity. Rows that were
+/// rolled back are simply not found.
+fn collect(conn: &Connection, ids: &[i64]) -> AppResult<Vec<EntityChanged>> {
+    let mut stmt = conn.prepare(
+        "SELECT entity_type, entity_id, project_id, op, field FROM sync_changes
+         WHERE id IN (SELECT value FROM json_each(?1)) ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map([serde_json::to_string(ids)?], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+                r.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut events: Vec<EntityChanged> = Vec::new();
+    for (entity_type, entity_id, project_id, op, field) in rows {
+        let pos = events
+            .iter()
+            .position(|e| e.entity_type == entity_type && e.entity_id == entity_id);
+        let event = match pos {
+            Some(i) => &mut events[i],
+            None => {
+                events.push(EntityChanged {
+                    entity_type,
+                    entity_id,
+                    project_id,
+                    action: action_for(&op).to_string(),
+                    fields: Vec::new(),
+                    version: None,
+                });
+                events.last_mut().expect("just pushed")
+            }
+        };
+        // Created stays created through later edits; a delete wins.
+        if op == "delete" || event.action != "created" {
+            event.action = action_for(&op).to_string();
+        }
+        if !field.is_empty() && !event.fields.contains(&field) {
+            event.fields.push(field);
+        }
+    }
+
+    for event in &mut events {
+        if event.action == "deleted" {
+            continue;
+        }
+        if let Some(table) = sync::versioned_table_for(&event.entity_type) {
+            event.version = conn
+                .query_row(
+                    &format!("SELECT version FROM {} WHERE id = ?1", table),
+                    [&event.entity_id],
+                    |r| r.get(0),
+                )
+                .optional()?;
+        }
+    }
+    Ok(events)
+}
+
+/// Start the task that turns change-log rows into `entity-changed` events.
+pub fn spawn_broadcaster(app: AppHandle) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    if FEED.set(tx).is_err() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        while let Some(first) = rx.recv().await {
+            tokio::time::sleep(BATCH_WINDOW).await;
+            let mut ids = vec![first];
+            while let Ok(id) = rx.try_recv() {
+                ids.push(id);
+            }
+            let events = {
+                let db = app.state::<Database>();
+                let conn = db.conn();
+                collect(&conn, &ids)
+            };
+            match events {
+                Ok(events) => {
+                    for event in events {
+                        AppEvent::EntityChanged(event).emit(&app);
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "failed to read entity changes"),
+            }
+        }
+    });
+}
+
+fn window_label(project_id: &str) -> String {
+    // Labels allow only alphanumerics and a few separators.
+    let safe: String = project_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    format!("project-{}", safe)
+}
+
+/// Open a project in its own window, or focus the window already showing
+/// it. Returns the window label. The page receives `?project=<id>`.
+#[tauri::command]
+pub async fn open_project_window(app: AppHandle, project_id: String) -> AppResult<String> {
+    let label = window_label(&project_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        window
+            .set_focus()
+            .map_err(|e| AppError::Invalid(e.to_string()))?;
+        return Ok(label);
+    }
+    let name = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        projects::get_project(&conn, &project_id)?.name
+    };
+    WebviewWindowBuilder::new(
+        &app,
+        &label,
+        WebviewUrl::App(format!("index.html?project={}", project_id).into()),
+    )
+    .title(format!("{} — AI Directors Chair", name))
+    .inner_size(1440.0, 900.0)
+    .min_inner_size(1024.0, 700.0)
+    .build()
+    .map_err(|e| AppError::Invalid(format!("could not open window: {}", e)))?;
+    Ok(label)
+}
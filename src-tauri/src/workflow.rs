@@ -0,0 +1,285 @@
+use crate::activity::{self, FieldChange};
+use crate::db::Database;
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::projects;
+use crate::scenes::{self, Scene};
+use crate::versioning;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+/// Review workflow a scene moves through. Stored in `scenes.status` as the
+/// snake_case name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum SceneStatus {
+    Draft,
+    PromptReady,
+    Generating,
+    Review,
+    Approved,
+    Locked,
+}
+
+impl SceneStatus {
+    pub const ALL: [SceneStatus; 6] = [
+        SceneStatus::Draft,
+        SceneStatus::PromptReady,
+        SceneStatus::Generating,
+        SceneStatus::Review,
+        SceneStatus::Approved,
+        SceneStatus::Locked,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SceneStatus::Draft => "draft",
+            SceneStatus::PromptReady => "prompt_ready",
+            SceneStatus::Generating => "generating",
+            SceneStatus::Review => "review",
+            SceneStatus::Approved => "approved",
+            SceneStatus::Locked => "locked",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|st| st.as_str() == s)
+            .ok_or_else(|| AppError::Invalid(format!("unknown scene status '{}'", s)))
+    }
+
+    /// States reachable in one step. Backward moves exist for rework:
+    /// rejecting a take, re-opening a prompt, unlocking.
+    pub fn next(self) -> &'static [SceneStatus] {
+        use SceneStatus::*;
+        match self {
+            Draft => &[PromptReady],
+            PromptReady => &[Draft, Generating],
+            Generating => &[Review, PromptReady],
+            Review => &[Approved, PromptReady, Generating],
+            Approved => &[Locked, Review],
+            Locked => &[Approved],
+        }
+    }
+
+    pub fn can_move_to(self, to: SceneStatus) -> bool {
+        self.next().contains(&to)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+    pub scene_id: String,
+    pub project_id: String,
+    pub from: SceneStatus,
+    pub to: SceneStatus,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedTransition {
+    pub scene_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct BulkTransitionResult {
+    pub updated: Vec<Scene>,
+    pub rejected: Vec<RejectedTransition>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct WorkflowState {
+    pub status: SceneStatus,
+    pub next: &'static [SceneStatus],
+}
+
+/// Refuse edits to a locked scene, or to any scene of a locked project.
+pub fn ensure_editable(conn: &Connection, scene_id: &str) -> AppResult<()> {
+    let scene = scenes::get_scene(conn, scene_id)?;
+    projects::ensure_unlocked(conn, &scene.project_id)?;
+    if SceneStatus::parse(&scene.status)? == SceneStatus::Locked {
+        return Err(AppError::Invalid(format!(
+            "scene {} is locked; unlock it before editing",
+            scene.scene_number
+        )));
+    }
+    Ok(())
+}
+
+/// Validate and apply one transition without emitting anything.
+fn apply(conn: &Connection, scene_id: &str, to: SceneStatus) -> AppResult<StatusChange> {
+    let scene = scenes::get_scene(conn, scene_id)?;
+    projects::ensure_unlocked(conn, &scene.project_id)?;
+    let from = SceneStatus::parse(&scene.status)?;
+    if !from.can_move_to(to) {
+        return Err(AppError::Invalid(format!(
+            "cannot move scene {} from {} to {}",
+            scene.scene_number,
+            from.as_str(),
+            to.as_str()
+        )));
+    }
+    if to == SceneStatus::PromptReady && scene.prompt.trim().is_empty() {
+        return Err(AppError::Invalid(format!(
+            "scene {} has no prompt yet",
+            scene.scene_number
+        )));
+    }
+    conn.execute(
+        "UPDATE scenes SET status = ?2 WHERE id = ?1",
+        params![scene_id, to.as_str()],
+    )?;
+    activity::record(
+        conn,
+        &scene.project_id,
+        "scene",
+        scene_id,
+        "status_changed",
+        &format!(
+            "Moved scene {} from {} to {}",
+            scene.scene_number,
+            from.as_str(),
+            to.as_str()
+        ),
+        &[FieldChange {
+            field: "status".into(),
+            old: from.as_str().into(),
+            new: to.as_str().into(),
+        }],
+    )?;
+    Ok(StatusChange {
+        scene_id: scene.id,
+        project_id: scene.project_id,
+        from,
+        to,
+    })
+}
+
+fn emit_change(app: &AppHandle, change: &StatusChange) {
+    AppEvent::SceneStatusChanged(change.clone()).emit(app);
+}
+
+/// Move a scene along as a side effect of something else (a job starting or
+/// finishing). Does nothing if the scene isn't in a state that allows it.
+pub fn advance_if_allowed(app: &AppHandle, scene_id: &str, to: SceneStatus) {
+    let change = {
+        let db = app.state::<Database>();
+        let conn = db.conn();
+        match scenes::get_scene(&conn, scene_id) {
+            Ok(scene)
+                if SceneStatus::parse(&scene.status).is_ok_and(|from| from.can_move_to(to)) =>
+            {
+                apply(&conn, scene_id, to).ok()
+            }
+            _ => None,
+        }
+    };
+    if let Some(change) = change {
+        emit_change(app, &change);
+    }
+}
+
+#[tauri::command]
+pub fn get_scene_workflow() -> Vec<WorkflowState> {
+    SceneStatus::ALL
+        .into_iter()
+        .map(|status| WorkflowState {
+            status,
+            next: status.next(),
+        })
+        .collect()
+}
+
+/// Move one scene to a new status, checking `expected_version` when given.
+pub fn transition(
+    conn: &Connection,
+    scene_id: &str,
+    to: SceneStatus,
+    expected_version: Option<i64>,
+) -> AppResult<(StatusChange, Scene)> {
+    let before = scenes::get_scene(conn, scene_id)?;
+    versioning::check(
+        "scene",
+        scene_id,
+        expected_version,
+        before.version,
+        &before,
+        &serde_json::json!({ "status": to }),
+    )?;
+    let change = apply(conn, scene_id, to)?;
+    Ok((change, scenes::get_scene(conn, scene_id)?))
+}
+
+/// Move one scene to a new status. Emits `scene-status-changed`.
+#[tauri::command]
+pub fn transition_scene(
+    app: AppHandle,
+    scene_id: String,
+    to: SceneStatus,
+    expected_version: Option<i64>,
+) -> AppResult<Scene> {
+    let (change, scene) = transition(
+        &app.state::<Database>().conn(),
+        &scene_id,
+        to,
+        expected_version,
+    )?;
+    emit_change(&app, &change);
+    Ok(scene)
+}
+
+/// Move many scenes at once. Each scene is checked on its own; scenes that
+/// can't make the move are reported in `rejected` and left as they were.
+/// Returns the changes made alongside the result.
+pub fn bulk_transition(
+    conn: &Connection,
+    scene_ids: &[String],
+    to: SceneStatus,
+) -> AppResult<(BulkTransitionResult, Vec<StatusChange>)> {
+    let mut changes = Vec::new();
+    let mut result = BulkTransitionResult {
+        updated: Vec::new(),
+        rejected: Vec::new(),
+    };
+    let tx = conn.unchecked_transaction()?;
+    for scene_id in scene_ids {
+        match apply(&tx, scene_id, to) {
+            Ok(change) => {
+                result.updated.push(scenes::get_scene(&tx, scene_id)?);
+                changes.push(change);
+            }
+            Err(AppError::Database(e)) => return Err(AppError::Database(e)),
+            Err(e) => result.rejected.push(RejectedTransition {
+                scene_id: scene_id.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    tx.commit()?;
+    Ok((result, changes))
+}
+
+/// Move many scenes at once. Emits `scene-status-changed` for each scene
+/// moved.
+#[tauri::command]
+pub fn bulk_transition_scenes(
+    app: AppHandle,
+    scene_ids: Vec<String>,
+    to: SceneStatus,
+) -> AppResult<BulkTransitionResult> {
+    let (result, changes) = bulk_transition(&app.state::<Database>().conn(), &scene_ids, to)?;
+    for change in &changes {
+        emit_change(&app, change);
+    }
+    Ok(result)
+}
@@ -0,0 +1,233 @@
+//! Named workspaces, each its own SQLite file. The registry lives in a
+//! small JSON meta store next to the databases so it can be read before
+//! any database is open.
+
+use crate::db::{self, Database};
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::network::NetworkMonitor;
+use crate::users;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use ts_rs::TS;
+
+const REGISTRY_FILE: &str = "workspaces.json";
+pub const DEFAULT_ID: &str = "default";
+/// The pre-workspace database, kept in place as the default workspace.
+const DEFAULT_FILE: &str = "projects.db";
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    /// Database file, relative to the app data dir.
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Registry {
+    pub active: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_ID.to_string(),
+            workspaces: vec![Workspace {
+                id: DEFAULT_ID.to_string(),
+                name: "Default".to_string(),
+                file: DEFAULT_FILE.to_string(),
+            }],
+        }
+    }
+}
+
+impl Registry {
+    fn get(&self, id: &str) -> AppResult<&Workspace> {
+        self.workspaces
+            .iter()
+            .find(|w| w.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("workspace {}", id)))
+    }
+
+    fn active(&self) -> &Workspace {
+        self.get(&self.active)
+            .unwrap_or_else(|_| &self.workspaces[0])
+    }
+
+    /// Add a workspace with a new database file. Names are unique, ignoring
+    /// case.
+    pub fn add(&mut self, name: &str) -> AppResult<Workspace> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Invalid("workspace name is required".into()));
+        }
+        if self
+            .workspaces
+            .iter()
+            .any(|w| w.name.eq_ignore_ascii_case(name))
+        {
+            return Err(AppError::Invalid(format!(
+                "a workspace called '{}' already exists",
+                name
+            )));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        let workspace = Workspace {
+            file: format!("workspaces/{}.db", id),
+            id,
+            name: name.to_string(),
+        };
+        self.workspaces.push(workspace.clone());
+        Ok(workspace)
+    }
+
+    pub fn rename(&mut self, id: &str, name: &str) -> AppResult<Workspace> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Invalid("workspace name is required".into()));
+        }
+        let workspace = self
+            .workspaces
+            .iter_mut()
+            .find(|w| w.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("workspace {}", id)))?;
+        workspace.name = name.to_string();
+        Ok(workspace.clone())
+    }
+
+    /// Take a workspace out of the registry. The active and default
+    /// workspaces can't be removed.
+    pub fn remove(&mut self, id: &str) -> AppResult<Workspace> {
+        if id == DEFAULT_ID || id == self.active().id {
+            return Err(AppError::Invalid(
+                "switch away from a workspace before deleting it; the default workspace can't be deleted"
+                    .into(),
+            ));
+        }
+        let workspace = self.get(id)?.clone();
+        self.workspaces.retain(|w| w.id != id);
+        Ok(workspace)
+    }
+}
+
+fn registry_path() -> PathBuf {
+    db::app_dir().join(REGISTRY_FILE)
+}
+
+/// The stored registry, or a registry holding only the default workspace
+/// if there is none yet or it can't be read.
+pub fn load() -> Registry {
+    std::fs::read_to_string(registry_path())
+        .ok()
+        .and_then(|s| serde_json::from_str::<Registry>(&s).ok())
+        .filter(|r| !r.workspaces.is_empty())
+        .unwrap_or_default()
+}
+
+/// Write via a temp file and rename so a crash can't leave half a registry.
+fn save(registry: &Registry) -> AppResult<()> {
+    let path = registry_path();
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(registry)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+fn db_path(workspace: &Workspace) -> PathBuf {
+    db::app_dir().join(&workspace.file)
+}
+
+pub fn active_db_path() -> PathBuf {
+    db_path(load().active())
+}
+
+/// Database file of a workspace, or of the active one when `id` is None.
+pub fn db_path_for(id: Option<&str>) -> AppResult<PathBuf> {
+    let registry = load();
+    match id {
+        Some(id) => Ok(db_path(registry.get(id)?)),
+        None => Ok(db_path(registry.active())),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct WorkspaceList {
+    pub active: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+#[tauri::command]
+pub fn list_workspaces() -> WorkspaceList {
+    let registry = load();
+    WorkspaceList {
+        active: registry.active().id.clone(),
+        workspaces: registry.workspaces,
+    }
+}
+
+/// Create an empty workspace with its own database. Does not switch to it.
+#[tauri::command]
+pub fn create_workspace(name: String) -> AppResult<Workspace> {
+    let mut registry = load();
+    let workspace = registry.add(&name)?;
+    let path = db_path(&workspace);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Create and migrate the file now so a bad location fails here, not on switch.
+    Database::open(&path)?;
+    save(&registry)?;
+    Ok(workspace)
+}
+
+#[tauri::command]
+pub fn rename_workspace(id: String, name: String) -> AppResult<Workspace> {
+    let mut registry = load();
+    let workspace = registry.rename(&id, &name)?;
+    save(&registry)?;
+    Ok(workspace)
+}
+
+/// Point the app at another workspace's database without restarting.
+/// Emits `workspace-switched`; the frontend should reload its data.
+#[tauri::command]
+pub fn switch_workspace(app: AppHandle, id: String) -> AppResult<Workspace> {
+    let mut registry = load();
+    let workspace = registry.get(&id)?.clone();
+    let db = app.state::<Database>();
+    db.reopen(&db_path(&workspace))?;
+    // Profiles belong to the workspace that was closed.
+    users::sign_out();
+    app.state::<NetworkMonitor>().reload(&db);
+    registry.active = workspace.id.clone();
+    save(&registry)?;
+    tracing::info!(workspace = %workspace.id, "switched workspace");
+    AppEvent::WorkspaceSwitched(workspace.clone()).emit(&app);
+    Ok(workspace)
+}
+
+/// Remove a workspace from the registry. Its database file is deleted only
+/// when `delete_file` is set. The active and default workspaces can't be
+/// removed.
+#[tauri::command]
+pub fn delete_workspace(id: String, delete_file: Option<bool>) -> AppResult<()> {
+    let mut registry = load();
+    let workspace = registry.remove(&id)?;
+    save(&registry)?;
+    if delete_file.unwrap_or(false) {
+        match std::fs::remove_file(db_path(&workspace)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
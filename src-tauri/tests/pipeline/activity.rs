@@ -0,0 +1,52 @@
+use crate::project;
+use ai_directors_chair::activity;
+use ai_directors_chair::clock::DateRange;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+
+#[test]
+fn activity_pages_newest_first_within_a_range() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    for n in 1..=3 {
+        let summary = format!("Created scene {}", n);
+        activity::record(&conn, &project_id, "scene", "s", "created", &summary, &[]).unwrap();
+    }
+    // Updates that changed nothing are not recorded.
+    activity::record(&conn, &project_id, "scene", "s", "updated", "Noop", &[]).unwrap();
+
+    let everything = DateRange::default();
+    let summaries = |page: &activity::ActivityPage| -> Vec<String> {
+        page.entries.iter().map(|e| e.summary.clone()).collect()
+    };
+    let first = activity::page(&conn, &project_id, None, Some(2), &everything).unwrap();
+    assert_eq!(summaries(&first), ["Created scene 3", "Created scene 2"]);
+    let rest = activity::page(&conn, &project_id, first.next_cursor, Some(2), &everything).unwrap();
+    assert_eq!(summaries(&rest), ["Created scene 1"]);
+    assert_eq!(rest.next_cursor, None);
+
+    let long_ago = DateRange {
+        from: Some("2000-01-01".into()),
+        to: Some("2000-01-31".into()),
+        utc_offset_minutes: 60,
+    };
+    let old = activity::page(&conn, &project_id, None, None, &long_ago).unwrap();
+    assert!(old.entries.is_empty());
+
+    for range in [
+        DateRange {
+            from: Some("last week".into()),
+            ..DateRange::default()
+        },
+        DateRange {
+            utc_offset_minutes: 100_000,
+            ..DateRange::default()
+        },
+    ] {
+        assert!(matches!(
+            activity::page(&conn, &project_id, None, None, &range),
+            Err(AppError::Invalid(_))
+        ));
+    }
+}
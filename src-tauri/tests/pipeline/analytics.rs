@@ -0,0 +1,75 @@
+use crate::project;
+use ai_directors_chair::analytics::{self, AnalyticsSettings};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+
+#[test]
+fn usage_is_counted_only_when_collection_is_on() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let secret = project(&conn);
+    conn.execute(
+        "UPDATE projects SET confidential = 1 WHERE id = ?1",
+        [&secret],
+    )
+    .unwrap();
+
+    analytics::track(&conn, &project_id, "export", "pdf");
+    assert!(analytics::summary(&conn, None).unwrap().is_empty());
+
+    let settings = AnalyticsSettings {
+        enabled: true,
+        share: false,
+        endpoint: String::new(),
+    };
+    analytics::save_settings(&conn, &settings).unwrap();
+    assert!(analytics::load_settings(&conn).unwrap().enabled);
+    for id in [&project_id, &project_id, &secret] {
+        analytics::track(&conn, id, "export", "pdf");
+    }
+    let counts = analytics::summary(&conn, None).unwrap();
+    assert_eq!(counts.len(), 1);
+    assert_eq!((counts[0].feature.as_str(), counts[0].count), ("export", 2));
+    assert_eq!(
+        analytics::summary(&conn, Some("2000-01-01")).unwrap().len(),
+        1
+    );
+    assert!(matches!(
+        analytics::summary(&conn, Some("yesterday")),
+        Err(AppError::Invalid(_))
+    ));
+
+    assert_eq!(analytics::purge(&conn).unwrap(), 1);
+    assert!(analytics::summary(&conn, None).unwrap().is_empty());
+}
+
+#[test]
+fn usage_reports_need_sharing_and_an_https_endpoint() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        analytics::report(&conn),
+        Err(AppError::Invalid(_))
+    ));
+
+    let plain = AnalyticsSettings {
+        enabled: true,
+        share: true,
+        endpoint: "http://stats.example.com".into(),
+    };
+    assert!(matches!(
+        analytics::save_settings(&conn, &plain),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(!analytics::load_settings(&conn).unwrap().share);
+
+    let secure = AnalyticsSettings {
+        endpoint: " https://stats.example.com/usage ".into(),
+        ..plain
+    };
+    analytics::save_settings(&conn, &secure).unwrap();
+    let (endpoint, counts) = analytics::report(&conn).unwrap();
+    assert_eq!(endpoint, "https://stats.example.com/usage");
+    assert!(counts.is_empty());
+}
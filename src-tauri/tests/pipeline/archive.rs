@@ -0,0 +1,103 @@
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::archive;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+const PHOTO: &str = "data:image/png;base64,iVBORw0KGgo=";
+
+fn media(root: &Path, name: &str) -> PathBuf {
+    let path = root.join("media").join("takes").join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, name).unwrap();
+    path
+}
+
+fn photo(conn: &Connection, project_id: &str) -> String {
+    conn.query_row(
+        "SELECT photo_data FROM characters WHERE project_id = ?1",
+        [project_id],
+        |r| r.get(0),
+    )
+    .unwrap()
+}
+
+#[test]
+fn archiving_moves_media_into_a_zip_and_back() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let project_id = project(&conn);
+    let other = project(&conn);
+    let own = scene(&conn, &project_id, 1, &["Mara"]);
+    let neighbour = scene(&conn, &other, 1, &["Joe"]);
+    let take = media(&root, "take.mp4");
+    let shared = media(&root, "shared.png");
+    for (scene_id, video, image) in [(&own.id, &take, &shared), (&neighbour.id, &shared, &shared)] {
+        conn.execute(
+            "UPDATE scenes SET video_url = ?2, conditioning_image = ?3 WHERE id = ?1",
+            [
+                scene_id.as_str(),
+                &video.to_string_lossy(),
+                &image.to_string_lossy(),
+            ],
+        )
+        .unwrap();
+    }
+    conn.execute(
+        "UPDATE characters SET photo_data = ?2 WHERE project_id = ?1",
+        [&project_id, PHOTO],
+    )
+    .unwrap();
+
+    let archived = archive::archive(&conn, &project_id, &root).unwrap();
+    assert!(archived.archived_at.is_some());
+    assert!(!take.exists());
+    assert!(shared.exists(), "files another project uses stay");
+    assert!(root
+        .join("archives")
+        .join(format!("{}.zip", project_id))
+        .is_file());
+    assert_eq!(photo(&conn, &project_id), "");
+    assert!(matches!(
+        projects::ensure_unlocked(&conn, &project_id),
+        Err(AppError::Locked(_))
+    ));
+    // Archiving twice is a no-op.
+    archive::archive(&conn, &project_id, &root).unwrap();
+
+    let restored = archive::unarchive(&conn, &project_id, &root).unwrap();
+    assert!(restored.archived_at.is_none());
+    assert_eq!(std::fs::read_to_string(&take).unwrap(), "take.mp4");
+    assert_eq!(photo(&conn, &project_id), PHOTO);
+    assert!(!root
+        .join("archives")
+        .join(format!("{}.zip", project_id))
+        .exists());
+    projects::ensure_unlocked(&conn, &project_id).unwrap();
+    std::fs::remove_dir_all(&root).ok();
+}
+
+#[test]
+fn unarchiving_needs_the_archive() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let project_id = project(&conn);
+    assert!(matches!(
+        archive::archive(&conn, "missing", &root),
+        Err(AppError::NotFound(_))
+    ));
+
+    archive::archive(&conn, &project_id, &root).unwrap();
+    std::fs::remove_dir_all(root.join("archives")).unwrap();
+    assert!(matches!(
+        archive::unarchive(&conn, &project_id, &root),
+        Err(AppError::NotFound(_))
+    ));
+    let still = projects::get_project(&conn, &project_id).unwrap();
+    assert!(still.archived_at.is_some());
+    std::fs::remove_dir_all(&root).ok();
+}
@@ -0,0 +1,147 @@
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::backup::{self, BackupSettings};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+
+fn config() -> BackupSettings {
+    BackupSettings {
+        endpoint: " https://s3.example.com ".into(),
+        region: String::new(),
+        bucket: "aidc".into(),
+        prefix: "/nightly".into(),
+        path_style: true,
+        access_key_id: "AKIDEXAMPLE".into(),
+        interval_hours: 24,
+        has_secret_access_key: false,
+        has_passphrase: false,
+        last_run: None,
+        secret_access_key: None,
+        passphrase: None,
+    }
+}
+
+#[test]
+fn backup_settings_keep_secrets_write_only() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        backup::configured(&conn),
+        Err(AppError::Invalid(_))
+    ));
+
+    let saved = backup::save_settings(&conn, &config()).unwrap();
+    assert_eq!(saved.endpoint, "https://s3.example.com");
+    assert_eq!(saved.prefix, "nightly/");
+    assert!(!saved.has_secret_access_key);
+    // Set up but for the secret key and passphrase.
+    assert!(matches!(
+        backup::configured(&conn),
+        Err(AppError::Invalid(_))
+    ));
+
+    let with_secrets = BackupSettings {
+        secret_access_key: Some("wJalrXUtnFEMI".into()),
+        passphrase: Some("correct horse".into()),
+        ..config()
+    };
+    let saved = backup::save_settings(&conn, &with_secrets).unwrap();
+    assert!(saved.has_secret_access_key && saved.has_passphrase);
+    assert!(saved.secret_access_key.is_none());
+    assert!(backup::configured(&conn).is_ok());
+
+    // Omitted secrets are kept; empty ones are cleared.
+    backup::save_settings(&conn, &config()).unwrap();
+    assert!(backup::load_settings(&conn).unwrap().has_passphrase);
+    let cleared = BackupSettings {
+        passphrase: Some(String::new()),
+        ..config()
+    };
+    assert!(
+        !backup::save_settings(&conn, &cleared)
+            .unwrap()
+            .has_passphrase
+    );
+
+    let bad = BackupSettings {
+        endpoint: "not a url".into(),
+        ..config()
+    };
+    assert!(matches!(
+        backup::save_settings(&conn, &bad),
+        Err(AppError::Invalid(_))
+    ));
+}
+
+#[test]
+fn snapshots_copy_the_database_without_confidential_projects() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let dir = scratch_dir();
+    let open = project(&conn);
+    scene(&conn, &open, 1, &["Mara"]);
+    let secret = project(&conn);
+    let hidden = scene(&conn, &secret, 1, &["Quillon"]);
+    let take = dir.join("media").join("takes").join("hidden.mp4");
+    std::fs::create_dir_all(take.parent().unwrap()).unwrap();
+    std::fs::write(&take, "frames").unwrap();
+    conn.execute(
+        "UPDATE scenes SET video_url = ?2 WHERE id = ?1",
+        [hidden.id.as_str(), &take.to_string_lossy()],
+    )
+    .unwrap();
+    conn.execute(
+        "UPDATE projects SET confidential = 1 WHERE id = ?1",
+        [&secret],
+    )
+    .unwrap();
+
+    let (database, left_out) = backup::snapshot(&conn, &dir, &dir.join("live.db")).unwrap();
+    assert!(database.starts_with(b"SQLite format 3\0"));
+    assert!(!database.windows(7).any(|w| w == b"Quillon"));
+    assert!(left_out.contains(&take));
+    assert!(!dir.join("backup-snapshot.db").exists());
+
+    let missing = dir.join("missing");
+    assert!(backup::snapshot(&conn, &missing, &dir.join("live.db")).is_err());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn restores_stay_inside_the_data_dir_and_check_the_database() {
+    assert_eq!(
+        backup::snapshot_ids(vec![
+            "snapshots/20260101T000000Z.json".into(),
+            "objects/abc".into(),
+            "snapshots/20260301T000000Z.json".into(),
+        ]),
+        ["20260301T000000Z", "20260101T000000Z"]
+    );
+    assert_eq!(
+        backup::restore_path("media/takes/a.mp4").unwrap(),
+        ["media", "takes", "a.mp4"]
+            .iter()
+            .collect::<std::path::PathBuf>()
+    );
+    for unsafe_path in ["../outside.db", "media/../../etc/passwd"] {
+        assert!(matches!(
+            backup::restore_path(unsafe_path),
+            Err(AppError::Invalid(_))
+        ));
+    }
+
+    let dir = scratch_dir();
+    let good = dir.join("good.db");
+    Database::open_in_memory()
+        .unwrap()
+        .conn()
+        .execute("VACUUM INTO ?1", [good.to_string_lossy().as_ref()])
+        .unwrap();
+    backup::verify_restored(&good, &dir.join("live.db")).unwrap();
+    let bad = dir.join("bad.db");
+    std::fs::write(&bad, "SQLite format 3\0 but not really").unwrap();
+    assert!(matches!(
+        backup::verify_restored(&bad, &dir.join("live.db")),
+        Err(AppError::Database(_))
+    ));
+    std::fs::remove_dir_all(&dir).ok();
+}
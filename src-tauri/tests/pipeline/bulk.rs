@@ -0,0 +1,163 @@
+use crate::{project, scene};
+use ai_directors_chair::bulk::{self, AssignMode, BulkGenerationOptions, Prepared};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::scenes;
+use ai_directors_chair::{balances, jobs, providers, settings};
+use rusqlite::Connection;
+use std::collections::HashSet;
+
+fn link(conn: &Connection, project_id: &str, upstream: &str, downstream: &str) {
+    conn.execute(
+        "INSERT INTO scene_links (id, project_id, upstream_id, downstream_id, kind, created_at)
+         VALUES (?1, ?2, ?3, ?4, 'end_frame', '2026-01-01T00:00:00Z')",
+        [
+            &uuid::Uuid::new_v4().to_string(),
+            project_id,
+            upstream,
+            downstream,
+        ],
+    )
+    .unwrap();
+}
+
+fn cast(conn: &Connection, scene_id: &str) -> Vec<String> {
+    let scene = scenes::get_scene(conn, scene_id).unwrap();
+    ai_directors_chair::scene_characters::names(conn, &scene.characters).unwrap()
+}
+
+#[test]
+fn bulk_deletes_skip_locked_scenes() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let open = scene(&conn, &project_id, 1, &[]);
+    let locked = scene(&conn, &project_id, 2, &[]);
+    conn.execute(
+        "UPDATE scenes SET status = 'locked' WHERE id = ?1",
+        [&locked.id],
+    )
+    .unwrap();
+
+    let ids = [open.id.clone(), locked.id.clone(), "missing".into()];
+    let result = bulk::delete_scenes(&conn, &ids).unwrap();
+    assert_eq!(result.succeeded, [open.id.as_str()]);
+    let rejected: Vec<&str> = result
+        .rejected
+        .iter()
+        .map(|r| r.scene_id.as_str())
+        .collect();
+    assert_eq!(rejected, [locked.id.as_str(), "missing"]);
+    assert!(scenes::get_scene(&conn, &locked.id).is_ok());
+    assert!(matches!(
+        scenes::get_scene(&conn, &open.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn bulk_casting_adds_removes_and_replaces() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &["Mara"]);
+    let second = scene(&conn, &project_id, 2, &["Joe"]);
+    let ids = [first.id.clone(), second.id.clone()];
+    let names = |list: &[&str]| -> Vec<String> { list.iter().map(|n| n.to_string()).collect() };
+
+    let added =
+        bulk::assign_characters(&conn, &ids, &names(&[" Joe ", ""]), AssignMode::Add).unwrap();
+    // The second scene already had Joe, so only the first changed.
+    assert_eq!(added.succeeded, [first.id.as_str()]);
+    assert_eq!(cast(&conn, &first.id), ["Mara", "Joe"]);
+
+    bulk::assign_characters(&conn, &ids, &names(&["joe"]), AssignMode::Remove).unwrap();
+    assert_eq!(cast(&conn, &first.id), ["Mara"]);
+    assert!(cast(&conn, &second.id).is_empty());
+
+    bulk::assign_characters(&conn, &ids, &names(&["Dina", "Mara"]), AssignMode::Replace).unwrap();
+    assert_eq!(cast(&conn, &second.id), ["Dina", "Mara"]);
+
+    conn.execute(
+        "UPDATE scenes SET status = 'locked' WHERE id = ?1",
+        [&first.id],
+    )
+    .unwrap();
+    let refused = bulk::assign_characters(&conn, &ids, &names(&["Joe"]), AssignMode::Add).unwrap();
+    assert_eq!(refused.succeeded, [second.id.as_str()]);
+    assert_eq!(refused.rejected[0].scene_id, first.id);
+}
+
+#[test]
+fn bulk_generation_queues_upstream_first_and_holds_dependents() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let opening = scene(&conn, &project_id, 1, &[]);
+    let follow = scene(&conn, &project_id, 2, &[]);
+    let orphan = scene(&conn, &project_id, 3, &[]);
+    let elsewhere = scene(&conn, &project_id, 4, &[]);
+    link(&conn, &project_id, &opening.id, &follow.id);
+    link(&conn, &project_id, &elsewhere.id, &orphan.id);
+    let options = BulkGenerationOptions::default();
+
+    let batch: HashSet<&str> = [opening.id.as_str(), follow.id.as_str(), orphan.id.as_str()]
+        .into_iter()
+        .collect();
+    let mut accepted: Vec<Prepared> = Vec::new();
+    // Before its upstream scene is taken, the follow-up can't be queued.
+    assert!(matches!(
+        bulk::prepare_scene(
+            &conn,
+            &follow.id,
+            &batch,
+            &accepted,
+            Some("kling"),
+            &options
+        ),
+        Err(AppError::Invalid(_))
+    ));
+    for scene_id in [&opening.id, &follow.id] {
+        let (prepared, outside) =
+            bulk::prepare_scene(&conn, scene_id, &batch, &accepted, Some("kling"), &options)
+                .unwrap();
+        assert!(outside.is_empty());
+        accepted.push(prepared);
+    }
+    assert_eq!(accepted[1].waits, [opening.id.as_str()]);
+    assert_eq!(accepted[1].request.prompt, follow.prompt);
+    // Its upstream scene is outside the batch and has no finished take.
+    assert!(matches!(
+        bulk::prepare_scene(
+            &conn,
+            &orphan.id,
+            &batch,
+            &accepted,
+            Some("kling"),
+            &options
+        ),
+        Err(AppError::Invalid(_))
+    ));
+
+    bulk::ensure_affordable(&conn, &accepted).unwrap();
+    settings::set(&conn, providers::FAL_KEY_SETTING, "fal-key").unwrap();
+    let (mut fal, _) = balances::cached_balances(&conn, false).unwrap().remove(0);
+    balances::record_fetch(&conn, &mut fal, Ok((0.5, None))).unwrap();
+    assert!(matches!(
+        bulk::ensure_affordable(&conn, &accepted),
+        Err(AppError::Invalid(_))
+    ));
+
+    let queued = bulk::store_batch(&conn, &accepted).unwrap();
+    assert_eq!(queued.len(), 2);
+    let status = |job_id: &str| -> String {
+        conn.query_row(
+            "SELECT status FROM video_jobs WHERE id = ?1",
+            [job_id],
+            |r| r.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(status(&queued[1].1), jobs::status::WAITING);
+    assert_ne!(status(&queued[0].1), jobs::status::WAITING);
+}
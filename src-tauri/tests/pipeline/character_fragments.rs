@@ -0,0 +1,107 @@
+//! Character fragments: versioned texts per character, and the stale
+//! flags editing them raises.
+
+use crate::{project, queue_job, scene};
+use ai_directors_chair::character_fragments::{self, kind};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::{projects, scenes};
+
+#[test]
+fn saving_a_fragment_versions_it_and_flags_generated_scenes() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let generated = scene(&conn, &project_id, 1, &["Mara"]);
+    queue_job(&conn, &generated.id);
+    let mara = generated.characters[0].clone();
+
+    let first =
+        character_fragments::save(&conn, &mara, kind::APPEARANCE, " Silver hair ", false).unwrap();
+    assert_eq!(first.fragment.text, "Silver hair");
+    assert_eq!(first.fragment.version, 1);
+    assert!(first.stale_scenes.is_empty());
+
+    // The same text again is not a new version.
+    let same =
+        character_fragments::save(&conn, &mara, kind::APPEARANCE, "Silver hair", true).unwrap();
+    assert_eq!(same.fragment.version, 1);
+    assert!(same.stale_scenes.is_empty());
+
+    let second =
+        character_fragments::save(&conn, &mara, kind::APPEARANCE, "Cropped hair", true).unwrap();
+    assert_eq!(second.fragment.id, first.fragment.id);
+    assert_eq!(second.fragment.version, 2);
+    assert_eq!(second.stale_scenes, [generated.id.as_str()]);
+    assert!(!scenes::get_scene(&conn, &generated.id)
+        .unwrap()
+        .stale_reason
+        .is_empty());
+
+    let versions = character_fragments::versions(&conn, &first.fragment.id).unwrap();
+    let texts: Vec<_> = versions
+        .iter()
+        .map(|v| (v.version, v.text.as_str()))
+        .collect();
+    assert_eq!(texts, [(2, "Cropped hair"), (1, "Silver hair")]);
+    let listed = character_fragments::list(&conn, &mara).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].text, "Cropped hair");
+}
+
+#[test]
+fn fragments_refuse_unknown_kinds_and_characters() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let mara = scene(&conn, &project_id, 1, &["Mara"]).characters[0].clone();
+
+    assert!(matches!(
+        character_fragments::save(&conn, &mara, "wardrobe", "Red coat", false),
+        Err(AppError::Validation(_))
+    ));
+    let missing = uuid::Uuid::new_v4().to_string();
+    assert!(matches!(
+        character_fragments::save(&conn, &missing, kind::APPEARANCE, "Red coat", false),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        character_fragments::list(&conn, &missing),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        character_fragments::versions(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn deleting_a_fragment_drops_its_history_unless_the_project_is_locked() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let generated = scene(&conn, &project_id, 1, &["Mara"]);
+    queue_job(&conn, &generated.id);
+    let mara = generated.characters[0].clone();
+    let saved = character_fragments::save(&conn, &mara, kind::NEGATIVE, "beard", false).unwrap();
+    let id = saved.fragment.id;
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        character_fragments::delete(&conn, &id, false),
+        Err(AppError::Locked(_))
+    ));
+    projects::unlock(&conn, &project_id, "Test project").unwrap();
+
+    let stale = character_fragments::delete(&conn, &id, true).unwrap();
+    assert_eq!(stale, [generated.id.as_str()]);
+    assert!(character_fragments::list(&conn, &mara).unwrap().is_empty());
+    assert!(matches!(
+        character_fragments::versions(&conn, &id),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        character_fragments::delete(&conn, &id, false),
+        Err(AppError::NotFound(_))
+    ));
+}
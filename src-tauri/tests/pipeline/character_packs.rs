@@ -0,0 +1,102 @@
+//! Character packs written from one project and imported into another.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::character_packs;
+use ai_directors_chair::characters;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use tauri::async_runtime::block_on;
+
+const PHOTO: &str = "data:image/png;base64,iVBORw0KGgo=";
+
+#[test]
+fn packs_carry_photos_and_samples_into_another_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let source = project(&conn);
+    let cast = scene(&conn, &source, 1, &["Mara", "Joe"]).characters;
+    conn.execute(
+        "UPDATE characters SET photo_data = ?2, voice_clone_status = 'pending' WHERE id = ?1",
+        [cast[0].as_str(), PHOTO],
+    )
+    .unwrap();
+    let sample = root.join("line.mp3");
+    std::fs::write(&sample, b"ID3 take").unwrap();
+    conn.execute(
+        "INSERT INTO voice_samples (id, character_id, file_path, file_name)
+         VALUES ('sample-1', ?1, ?2, 'line.mp3')",
+        [cast[0].as_str(), &sample.to_string_lossy()],
+    )
+    .unwrap();
+
+    let loaded = character_packs::load_cast(&conn, &cast).unwrap();
+    let path = block_on(character_packs::write(
+        loaded,
+        &root.to_string_lossy(),
+        None,
+    ))
+    .unwrap();
+    assert!(path.ends_with("characters.aidc-pack.zip"));
+
+    // Joe is already cast in the target project, so only Mara comes over.
+    let target = project(&conn);
+    scene(&conn, &target, 1, &["joe"]);
+    let samples_dir = root.join("samples");
+    std::fs::create_dir_all(&samples_dir).unwrap();
+    let imported = character_packs::import(&conn, &target, &path, &samples_dir).unwrap();
+    assert_eq!(imported.skipped, ["Joe"]);
+    assert_eq!(imported.imported.len(), 1);
+    let mara = &imported.imported[0];
+    assert_eq!(mara.name, "Mara");
+    assert_eq!(mara.project_id, target);
+    assert_eq!(mara.photo_data, PHOTO);
+    assert_eq!(mara.voice.clone_status, "");
+    let copied: String = conn
+        .query_row(
+            "SELECT file_path FROM voice_samples WHERE character_id = ?1",
+            [&mara.id],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert!(copied.starts_with(&*samples_dir.to_string_lossy()));
+    assert_eq!(std::fs::read(copied).unwrap(), b"ID3 take");
+}
+
+#[test]
+fn packs_need_characters_and_an_unlocked_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let source = project(&conn);
+    let cast = scene(&conn, &source, 1, &["Mara"]).characters;
+
+    assert!(matches!(
+        character_packs::load_cast(&conn, &[]),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        character_packs::load_cast(&conn, &["missing".to_string()]),
+        Err(AppError::NotFound(_))
+    ));
+
+    let loaded = character_packs::load_cast(&conn, &cast).unwrap();
+    let path = root.join("cast.zip").to_string_lossy().to_string();
+    block_on(character_packs::write(loaded, &path, Some("Cast".into()))).unwrap();
+    projects::lock(&conn, &source).unwrap();
+    assert!(matches!(
+        character_packs::import(&conn, &source, &path, &root),
+        Err(AppError::Locked(_))
+    ));
+    let target = project(&conn);
+    std::fs::write(root.join("broken.zip"), b"not a zip").unwrap();
+    let broken = root.join("broken.zip").to_string_lossy().to_string();
+    assert!(matches!(
+        character_packs::import(&conn, &target, &broken, &root),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(characters::list_for_project(&conn, &target)
+        .unwrap()
+        .is_empty());
+}
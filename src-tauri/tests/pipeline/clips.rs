@@ -0,0 +1,91 @@
+//! Reference clips: queued once the local-use notice is accepted, then
+//! recorded as ready or failed when yt-dlp finishes.
+
+use crate::{project, scratch_dir};
+use ai_directors_chair::clips::{self, status};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+
+#[test]
+fn clips_queue_record_and_delete() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(!clips::ytdlp(&conn).unwrap().1);
+    clips::acknowledge(&conn).unwrap();
+    assert!(clips::ytdlp(&conn).unwrap().1);
+
+    let (_, ready) = clips::queue(&conn, &project_id, " https://example.com/a ").unwrap();
+    assert_eq!(ready.status, status::DOWNLOADING);
+    assert_eq!(ready.source_url, "https://example.com/a");
+    let file = scratch_dir().join("a.mp4");
+    std::fs::write(&file, b"clip").unwrap();
+    let ready = clips::record(
+        &conn,
+        &ready.id,
+        Ok((
+            file.to_string_lossy().to_string(),
+            "Night diner".into(),
+            12.5,
+        )),
+    )
+    .unwrap();
+    assert_eq!(ready.status, status::READY);
+    assert_eq!(ready.title, "Night diner");
+    assert_eq!(ready.duration, 12.5);
+
+    let (_, failed) = clips::queue(&conn, &project_id, "https://example.com/b").unwrap();
+    let failed = clips::record(
+        &conn,
+        &failed.id,
+        Err(AppError::Provider("HTTP Error 404".into())),
+    )
+    .unwrap();
+    assert_eq!(failed.status, status::FAILED);
+    assert!(failed.error.contains("HTTP Error 404"));
+    assert_eq!(clips::list(&conn, &project_id).unwrap().len(), 2);
+
+    clips::delete(&conn, &ready.id).unwrap();
+    assert!(!file.exists());
+    let left: Vec<_> = clips::list(&conn, &project_id)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    assert_eq!(left, [failed.id.as_str()]);
+    assert!(matches!(
+        clips::delete(&conn, &ready.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn clips_need_the_notice_a_link_and_an_unlocked_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(matches!(
+        clips::queue(&conn, &project_id, "https://example.com/a"),
+        Err(AppError::Invalid(_))
+    ));
+    clips::acknowledge(&conn).unwrap();
+    assert!(matches!(
+        clips::queue(&conn, &project_id, "file:///etc/passwd"),
+        Err(AppError::Invalid(_))
+    ));
+    let (_, clip) = clips::queue(&conn, &project_id, "https://example.com/a").unwrap();
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        clips::queue(&conn, &project_id, "https://example.com/b"),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        clips::delete(&conn, &clip.id),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        clips::record(&conn, "missing", Ok((String::new(), String::new(), 0.0))),
+        Err(AppError::NotFound(_))
+    ));
+}
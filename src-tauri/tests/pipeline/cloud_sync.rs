@@ -0,0 +1,100 @@
+//! Cloud folder sync decisions against bundles standing in for the shared
+//! folder's copy; the uploads and downloads themselves are not run.
+
+use crate::project;
+use ai_directors_chair::cloud_sync::{self, CloudProvider};
+use ai_directors_chair::confidential;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+
+#[test]
+fn pushes_and_pulls_follow_both_sides_versions() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let provider = CloudProvider::Dropbox;
+    cloud_sync::set_folder(&conn, provider, " /Team/AIDC ").unwrap();
+    let project_id = project(&conn);
+
+    let sync = cloud_sync::local(&conn, &project_id, provider).unwrap();
+    assert_eq!(sync.folder, "/Team/AIDC");
+    assert_eq!(cloud_sync::status(&sync).outcome, "local_changes");
+    let bundle = cloud_sync::prepare_push(&conn, &project_id, &sync, false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(bundle.version, 1);
+    let pushed =
+        cloud_sync::record_push(&conn, &project_id, provider, &sync, bundle.clone()).unwrap();
+    assert_eq!(pushed.outcome, "pushed");
+    assert_eq!(pushed.remote_version, Some(1));
+
+    // Someone else publishes version 2.
+    let mut theirs = bundle;
+    theirs.version = 2;
+    theirs.project.name = "Renamed upstream".into();
+    let mut sync = cloud_sync::local(&conn, &project_id, provider).unwrap();
+    sync.remote = Some(theirs);
+    assert_eq!(cloud_sync::status(&sync).outcome, "remote_changes");
+    let refused = cloud_sync::prepare_push(&conn, &project_id, &sync, false).unwrap();
+    assert_eq!(refused.unwrap_err().outcome, "conflict");
+
+    let pulled = cloud_sync::apply_pull(&conn, &project_id, provider, &sync, false).unwrap();
+    assert_eq!(pulled.outcome, "pulled");
+    assert_eq!(
+        projects::get_project(&conn, &project_id).unwrap().name,
+        "Renamed upstream"
+    );
+
+    let mut again = cloud_sync::local(&conn, &project_id, provider).unwrap();
+    again.remote = sync.remote.take();
+    assert_eq!(cloud_sync::status(&again).outcome, "up_to_date");
+    let outcome = cloud_sync::apply_pull(&conn, &project_id, provider, &again, false).unwrap();
+    assert_eq!(outcome.outcome, "up_to_date");
+    let outcome = cloud_sync::prepare_push(&conn, &project_id, &again, false).unwrap();
+    assert_eq!(outcome.unwrap_err().outcome, "up_to_date");
+}
+
+#[test]
+fn syncing_needs_a_folder_a_project_and_a_matching_bundle() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(matches!(
+        cloud_sync::set_folder(&conn, CloudProvider::Dropbox, "Team"),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        cloud_sync::local(&conn, &project_id, CloudProvider::GoogleDrive),
+        Err(AppError::Invalid(_))
+    ));
+
+    let provider = CloudProvider::GoogleDrive;
+    cloud_sync::set_folder(&conn, provider, "folder-id").unwrap();
+    let missing = cloud_sync::local(&conn, "missing", provider).unwrap();
+    assert!(matches!(
+        cloud_sync::prepare_push(&conn, "missing", &missing, false),
+        Err(AppError::NotFound(_))
+    ));
+    let mut sync = cloud_sync::local(&conn, &project_id, provider).unwrap();
+    assert!(matches!(
+        cloud_sync::apply_pull(&conn, &project_id, provider, &sync, true),
+        Err(AppError::NotFound(_))
+    ));
+
+    let other = project(&conn);
+    let other_sync = cloud_sync::local(&conn, &other, provider).unwrap();
+    sync.remote = cloud_sync::prepare_push(&conn, &other, &other_sync, false)
+        .unwrap()
+        .ok();
+    assert!(matches!(
+        cloud_sync::apply_pull(&conn, &project_id, provider, &sync, true),
+        Err(AppError::Invalid(_))
+    ));
+
+    confidential::set(&conn, &project_id, true).unwrap();
+    let sync = cloud_sync::local(&conn, &project_id, provider).unwrap();
+    assert!(matches!(
+        cloud_sync::prepare_push(&conn, &project_id, &sync, false),
+        Err(AppError::Forbidden(_))
+    ));
+}
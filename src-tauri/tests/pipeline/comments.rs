@@ -0,0 +1,85 @@
+//! Comments on scenes, characters and projects.
+
+use crate::{project, scene};
+use ai_directors_chair::comments::{self, CommentInput};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+
+fn input(entity_type: &str, entity_id: &str, body: &str) -> CommentInput {
+    CommentInput {
+        entity_type: entity_type.into(),
+        entity_id: entity_id.into(),
+        author_name: " Dana ".into(),
+        body: body.into(),
+    }
+}
+
+#[test]
+fn comments_are_added_edited_resolved_and_deleted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+
+    let first = comments::add(&conn, &input("scene", &shot.id, " Too dark ")).unwrap();
+    assert_eq!(first.author_name, "Dana");
+    assert_eq!(first.body, "Too dark");
+    let second = comments::add(&conn, &input("scene", &shot.id, "Love the neon")).unwrap();
+    comments::add(&conn, &input("project", &project_id, "Lock by Friday")).unwrap();
+
+    let edited = comments::edit(&conn, &first.id, " Still too dark ").unwrap();
+    assert_eq!(edited.body, "Still too dark");
+    assert!(comments::resolve(&conn, &second.id, true).unwrap().resolved);
+
+    let all = comments::list(&conn, "scene", &shot.id, true).unwrap();
+    assert_eq!(all.len(), 2);
+    let open: Vec<_> = comments::list(&conn, "scene", &shot.id, false)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    assert_eq!(open, [first.id.as_str()]);
+
+    comments::delete(&conn, &first.id).unwrap();
+    assert!(comments::list(&conn, "scene", &shot.id, false)
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        comments::delete(&conn, &first.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn comments_need_a_known_target_and_a_body() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let missing = uuid::Uuid::new_v4().to_string();
+
+    assert!(matches!(
+        comments::add(&conn, &input("scene", &missing, "Too dark")),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        comments::add(&conn, &input("job", &project_id, "Too dark")),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        comments::add(&conn, &input("project", &project_id, "  ")),
+        Err(AppError::Validation(_))
+    ));
+    let comment = comments::add(&conn, &input("project", &project_id, "Ok")).unwrap();
+    assert!(matches!(
+        comments::edit(&conn, &comment.id, ""),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        comments::edit(&conn, &missing, "Too dark"),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        comments::resolve(&conn, &missing, true),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,105 @@
+use crate::{project, queue_job, scene};
+use ai_directors_chair::confidential;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use rusqlite::Connection;
+
+#[test]
+fn confidential_projects_refuse_cloud_services() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let open = project(&conn);
+    let secret = project(&conn);
+    conn.execute(
+        "UPDATE projects SET confidential = 1 WHERE id = ?1",
+        [&secret],
+    )
+    .unwrap();
+    confidential::ensure_remote_allowed(&conn, &open, "Notion").unwrap();
+    assert!(matches!(
+        confidential::ensure_remote_allowed(&conn, &secret, "Notion"),
+        Err(AppError::Forbidden(_))
+    ));
+}
+
+#[test]
+fn backups_leave_no_trace_of_confidential_projects() {
+    let db = Database::open_in_memory().unwrap();
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    let snapshot = dir.join("snapshot.db");
+    {
+        let conn = db.conn();
+        let open = project(&conn);
+        scene(&conn, &open, 1, &["Mara"]);
+        let secret = project(&conn);
+        conn.execute(
+            "UPDATE projects SET confidential = 1, synopsis = 'zebrafish heist' WHERE id = ?1",
+            [&secret],
+        )
+        .unwrap();
+        let hidden = scene(&conn, &secret, 1, &["Quillon"]);
+        conn.execute(
+            "UPDATE scenes SET dialog = 'the zebrafish sleeps at midnight' WHERE id = ?1",
+            [&hidden.id],
+        )
+        .unwrap();
+        let take = queue_job(&conn, &hidden.id);
+        for (kind, id) in [("scene", hidden.id.as_str()), ("take", take.as_str())] {
+            conn.execute(
+                "INSERT INTO comments (id, entity_type, entity_id, author_name, body)
+                 VALUES (?1, ?2, ?3, 'Ed', 'zebrafish notes')",
+                [uuid::Uuid::new_v4().to_string().as_str(), kind, id],
+            )
+            .unwrap();
+        }
+        conn.execute("VACUUM INTO ?1", [snapshot.to_string_lossy().as_ref()])
+            .unwrap();
+    }
+
+    confidential::strip_for_backup(&snapshot, &snapshot, &dir.join("media")).unwrap();
+
+    let copy = Connection::open(&snapshot).unwrap();
+    let count = |sql: &str| -> i64 { copy.query_row(sql, [], |r| r.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM projects"), 1);
+    assert_eq!(count("SELECT COUNT(*) FROM comments"), 0);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM search_index WHERE search_index MATCH 'zebrafish'"),
+        0
+    );
+    assert_eq!(
+        count("SELECT COUNT(*) FROM search_index WHERE search_index MATCH 'mara'"),
+        1
+    );
+    drop(copy);
+    let bytes = std::fs::read(&snapshot).unwrap();
+    for word in [&b"zebrafish"[..], b"Quillon"] {
+        assert!(!bytes.windows(word.len()).any(|w| w == word));
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn owners_mark_projects_confidential_and_back() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(
+        confidential::set(&conn, &project_id, true)
+            .unwrap()
+            .confidential
+    );
+    assert!(matches!(
+        confidential::ensure_remote_allowed(&conn, &project_id, "Notion"),
+        Err(AppError::Forbidden(_))
+    ));
+    assert!(
+        !confidential::set(&conn, &project_id, false)
+            .unwrap()
+            .confidential
+    );
+    assert!(matches!(
+        confidential::set(&conn, "missing", true),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,79 @@
+//! Contact sheets laid out from a project's scenes.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::contact_sheet::{self, ContactSheetOptions};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use tauri::async_runtime::block_on;
+
+fn options(columns: u32) -> ContactSheetOptions {
+    ContactSheetOptions {
+        columns,
+        page_size: "custom".into(),
+        width: Some(400),
+        height: Some(400),
+        portrait: false,
+        format: "png".into(),
+    }
+}
+
+#[test]
+fn scenes_that_do_not_fit_spill_onto_numbered_pages() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &[]);
+    scene(&conn, &project_id, 2, &[]);
+    let path = scratch_dir().join("sheet.png");
+
+    let (scenes, provenance) = contact_sheet::load(&conn, &project_id, &options(1)).unwrap();
+    assert_eq!(scenes.len(), 2);
+    let written = block_on(contact_sheet::write(
+        scenes,
+        provenance,
+        &path.to_string_lossy(),
+        &options(1),
+    ))
+    .unwrap();
+    assert_eq!(written.len(), 2);
+    assert!(written[0].ends_with("sheet.png"));
+    assert!(written[1].ends_with("sheet-2.png"));
+    let page = image::open(&written[1]).unwrap();
+    assert_eq!((page.width(), page.height()), (400, 400));
+}
+
+#[test]
+fn contact_sheets_need_sane_options_and_scenes() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(matches!(
+        contact_sheet::load(&conn, &project_id, &options(1)),
+        Err(AppError::Invalid(_))
+    ));
+    scene(&conn, &project_id, 1, &[]);
+    assert!(matches!(
+        contact_sheet::load(&conn, &project_id, &options(0)),
+        Err(AppError::Invalid(_))
+    ));
+    let tiny = ContactSheetOptions {
+        width: Some(100),
+        ..options(2)
+    };
+    assert!(matches!(
+        contact_sheet::load(&conn, &project_id, &tiny),
+        Err(AppError::Invalid(_))
+    ));
+    let gif = ContactSheetOptions {
+        format: "gif".into(),
+        ..options(2)
+    };
+    assert!(matches!(
+        contact_sheet::load(&conn, &project_id, &gif),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        contact_sheet::load(&conn, "missing", &options(2)),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,67 @@
+//! Continuity findings recorded against adjacent scenes.
+
+use crate::{project, scene};
+use ai_directors_chair::continuity;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use serde_json::json;
+
+#[test]
+fn only_findings_about_adjacent_scenes_are_kept() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &["Mara"]);
+    let second = scene(&conn, &project_id, 2, &["Mara"]);
+    let third = scene(&conn, &project_id, 3, &["Mara"]);
+    let scenes = continuity::load(&conn, &project_id).unwrap();
+    assert_eq!(scenes.len(), 3);
+
+    let findings = [
+        json!({
+            "sceneId": second.id, "previousSceneId": first.id,
+            "category": "wardrobe", "severity": "error", "note": " Coat changes colour ",
+        }),
+        json!({
+            "sceneId": third.id, "previousSceneId": second.id,
+            "category": "weather", "severity": "loud", "note": "Rain stops",
+        }),
+        json!({ "sceneId": third.id, "previousSceneId": first.id, "note": "Not adjacent" }),
+        json!({ "sceneId": second.id, "previousSceneId": first.id, "note": "" }),
+    ];
+    let notes = continuity::record(&conn, &project_id, &scenes, &findings).unwrap();
+    let rows: Vec<_> = notes
+        .iter()
+        .map(|n| (n.category.as_str(), n.severity.as_str(), n.note.as_str()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("wardrobe", "error", "Coat changes colour"),
+            ("other", "warning", "Rain stops")
+        ]
+    );
+
+    // A re-check replaces open notes but keeps dismissed ones.
+    continuity::resolve(&conn, &notes[0].id, true).unwrap();
+    let notes = continuity::record(&conn, &project_id, &scenes, &[]).unwrap();
+    assert_eq!(notes.len(), 1);
+    assert!(notes[0].resolved);
+    assert_eq!(continuity::list(&conn, &project_id).unwrap().len(), 1);
+}
+
+#[test]
+fn checks_need_two_scenes_and_notes_must_exist() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &[]);
+    assert!(matches!(
+        continuity::load(&conn, &project_id),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        continuity::resolve(&conn, "missing", true),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,59 @@
+//! Moving the data folder: a verified copy with media paths repointed.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::data_dir;
+use ai_directors_chair::db;
+use ai_directors_chair::error::AppError;
+use std::path::PathBuf;
+
+#[test]
+fn copies_repoint_media_paths_in_every_database() {
+    let from = scratch_dir();
+    let to = scratch_dir().join("moved");
+    let sample = from.join("media").join("voice-samples").join("line.mp3");
+    std::fs::create_dir_all(sample.parent().unwrap()).unwrap();
+    std::fs::write(&sample, b"ID3 take").unwrap();
+    {
+        let conn = db::connect(&from.join("app.db")).unwrap();
+        let project_id = project(&conn);
+        let mara = scene(&conn, &project_id, 1, &["Mara"]).characters[0].clone();
+        conn.execute(
+            "INSERT INTO voice_samples (id, character_id, file_path, file_name)
+             VALUES ('sample-1', ?1, ?2, 'line.mp3')",
+            [mara.as_str(), &sample.to_string_lossy()],
+        )
+        .unwrap();
+    }
+
+    let report = data_dir::copy(&from, &to, &[PathBuf::from("app.db")]).unwrap();
+    assert!(report.files >= 2);
+    assert!(report.bytes > 8);
+    assert!(sample.exists(), "the old folder is left in place");
+
+    let conn = db::connect(&to.join("app.db")).unwrap();
+    let moved: String = conn
+        .query_row("SELECT file_path FROM voice_samples", [], |r| r.get(0))
+        .unwrap();
+    let expected = to.join("media").join("voice-samples").join("line.mp3");
+    assert_eq!(moved, expected.to_string_lossy());
+    assert_eq!(std::fs::read(expected).unwrap(), b"ID3 take");
+}
+
+#[test]
+fn copies_need_an_empty_folder_outside_the_current_one() {
+    let from = scratch_dir();
+    std::fs::write(from.join("app.db"), b"").unwrap();
+    for to in [PathBuf::from("relative"), from.join("inside")] {
+        assert!(matches!(
+            data_dir::copy(&from, &to, &[]),
+            Err(AppError::Invalid(_))
+        ));
+    }
+    let busy = scratch_dir();
+    std::fs::write(busy.join("keep.txt"), b"mine").unwrap();
+    assert!(matches!(
+        data_dir::copy(&from, &busy, &[]),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(busy.join("keep.txt").exists());
+}
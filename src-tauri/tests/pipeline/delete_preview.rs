@@ -0,0 +1,61 @@
+//! What deleting a project or character would take with it.
+
+use crate::{project, queue_job, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::delete_preview;
+use ai_directors_chair::error::AppError;
+
+#[test]
+fn previews_count_cascaded_rows_and_orphaned_media() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &["Mara"]);
+    scene(&conn, &project_id, 2, &["Mara", "Joe"]);
+    queue_job(&conn, &first.id);
+    let mara = first.characters[0].clone();
+    let sample = root.join("media").join("voice-samples").join("line.mp3");
+    std::fs::create_dir_all(sample.parent().unwrap()).unwrap();
+    std::fs::write(&sample, b"ID3 take").unwrap();
+    conn.execute(
+        "INSERT INTO voice_samples (id, character_id, file_path, file_name)
+         VALUES ('sample-1', ?1, ?2, 'line.mp3')",
+        [mara.as_str(), &sample.to_string_lossy()],
+    )
+    .unwrap();
+
+    let whole = delete_preview::preview(&conn, "project", &project_id, &root).unwrap();
+    assert_eq!(whole.name, "Test project");
+    assert_eq!(whole.scenes, 2);
+    assert_eq!(whole.takes, 1);
+    let rows = |table: &str| whole.rows.iter().find(|c| c.table == table).map(|c| c.rows);
+    assert_eq!(rows("projects"), Some(1));
+    assert_eq!(rows("characters"), Some(2));
+
+    let character = delete_preview::preview(&conn, "character", &mara, &root).unwrap();
+    assert_eq!(character.name, "Mara");
+    assert_eq!(character.scenes, 2);
+    assert_eq!(character.media_files, 1);
+    assert_eq!(character.media_bytes, 8);
+}
+
+#[test]
+fn previews_need_a_known_kind_and_entity() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let project_id = project(&conn);
+    assert!(matches!(
+        delete_preview::preview(&conn, "scene", &project_id, &root),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        delete_preview::preview(&conn, "project", "missing", &root),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        delete_preview::preview(&conn, "character", "missing", &root),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,38 @@
+//! The diagnostics checks that run against the database.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::diagnostics;
+use ai_directors_chair::providers;
+use ai_directors_chair::settings;
+
+#[test]
+fn database_checks_time_reads_and_writes_and_see_configured_keys() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    settings::set(&conn, providers::FAL_KEY_SETTING, "fal-key").unwrap();
+
+    let (database, _, providers) = diagnostics::local_checks(&conn).unwrap();
+    assert!(database.error.is_empty(), "{}", database.error);
+    assert!(database.write_ms >= 0.0);
+    assert_eq!(
+        settings::get(&conn, diagnostics::PROBE_SETTING).unwrap(),
+        None
+    );
+    let configured: Vec<_> = providers
+        .iter()
+        .map(|p| (p.provider.as_str(), p.configured))
+        .collect();
+    assert_eq!(
+        configured,
+        [("fal", true), ("gemini", false), ("elevenlabs", false)]
+    );
+}
+
+#[test]
+fn a_failed_database_check_is_reported_not_raised() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    conn.execute_batch("PRAGMA query_only = ON;").unwrap();
+    let (database, _, _) = diagnostics::local_checks(&conn).unwrap();
+    assert!(!database.error.is_empty());
+}
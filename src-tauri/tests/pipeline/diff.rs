@@ -0,0 +1,177 @@
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::diff::{self, op, DiffHunk};
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::scenes::{self, SceneInput};
+use rusqlite::Connection;
+use std::time::Instant;
+
+fn hunks(old: &str, new: &str) -> Vec<(&'static str, String)> {
+    diff::diff_words(old, new)
+        .hunks
+        .into_iter()
+        .map(|DiffHunk { op, text }| (op, text))
+        .collect()
+}
+
+fn rebuilt(hunks: &[DiffHunk], skip: &str) -> String {
+    hunks
+        .iter()
+        .filter(|h| h.op != skip)
+        .map(|h| h.text.as_str())
+        .collect()
+}
+
+#[test]
+fn inserted_words_are_marked_and_counted() {
+    assert_eq!(
+        hunks("a quiet street", "a quiet rainy street"),
+        vec![
+            (op::EQUAL, "a quiet ".to_string()),
+            (op::INSERT, "rainy ".to_string()),
+            (op::EQUAL, "street".to_string()),
+        ]
+    );
+    let diff = diff::diff_words("a quiet street", "a quiet rainy street");
+    assert_eq!((diff.inserted, diff.deleted), (1, 0));
+}
+
+#[test]
+fn deleted_words_are_marked_and_counted() {
+    assert_eq!(
+        hunks("slow wide pan left", "slow pan left"),
+        vec![
+            (op::EQUAL, "slow ".to_string()),
+            (op::DELETE, "wide ".to_string()),
+            (op::EQUAL, "pan left".to_string()),
+        ]
+    );
+    let diff = diff::diff_words("slow wide pan left", "slow pan left");
+    assert_eq!((diff.inserted, diff.deleted), (0, 1));
+}
+
+#[test]
+fn replaced_words_delete_then_insert() {
+    let diff = diff::diff_words("Mara runs at dawn", "Mara walks at dusk");
+    assert_eq!((diff.inserted, diff.deleted), (2, 2));
+    assert_eq!(rebuilt(&diff.hunks, op::INSERT), "Mara runs at dawn");
+    assert_eq!(rebuilt(&diff.hunks, op::DELETE), "Mara walks at dusk");
+    assert!(diff
+        .hunks
+        .iter()
+        .any(|h| h.op == op::DELETE && h.text.contains("runs")));
+    assert!(diff
+        .hunks
+        .iter()
+        .any(|h| h.op == op::INSERT && h.text.contains("walks")));
+    assert_eq!(hunks("same", "same"), vec![(op::EQUAL, "same".to_string())]);
+    assert!(hunks("", "").is_empty());
+}
+
+#[test]
+fn oversized_texts_diff_quickly_and_losslessly() {
+    let old: String = (0..60_000).map(|i| format!("w{} ", i % 977)).collect();
+    let new: String = (0..60_000).map(|i| format!("w{} ", i % 983)).collect();
+    let started = Instant::now();
+    let diff = diff::diff_words(&old, &new);
+    assert!(started.elapsed().as_secs() < 10);
+    assert_eq!(rebuilt(&diff.hunks, op::INSERT), old);
+    assert_eq!(rebuilt(&diff.hunks, op::DELETE), new);
+    assert!(diff.inserted > 0 && diff.deleted > 0);
+}
+
+fn versions(conn: &Connection, scene_id: &str) -> Vec<i64> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM activity_log WHERE entity_id = ?1 ORDER BY id")
+        .unwrap();
+    stmt.query_map([scene_id], |r| r.get(0))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap()
+}
+
+fn reprompt(conn: &Connection, scene_id: &str, prompt: &str) {
+    let current = scenes::get_scene(conn, scene_id).unwrap();
+    scenes::save(
+        conn,
+        &SceneInput {
+            id: Some(current.id.clone()),
+            project_id: current.project_id.clone(),
+            scene_number: current.scene_number,
+            title: current.title.clone(),
+            description: current.description.clone(),
+            prompt: prompt.into(),
+            camera_angle: None,
+            lighting: None,
+            duration: current.duration,
+            dialog: current.dialog.clone(),
+            characters: current.characters.clone(),
+            sort_order: current.sort_order,
+            location: current.location.clone(),
+            expected_version: None,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn scene_versions_diff_fields_between_activity_entries() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    reprompt(
+        &conn,
+        &shot.id,
+        "Wide shot of an empty diner, rain on the glass",
+    );
+    reprompt(&conn, &shot.id, "Close on Mara, rain on the glass");
+    let ids = versions(&conn, &shot.id);
+    assert_eq!(ids.len(), 3);
+
+    let fields = || vec!["prompt".to_string(), "title".to_string()];
+    let first = diff::scene_versions(&conn, &shot.id, ids[0], Some(ids[1]), fields()).unwrap();
+    assert_eq!(first.len(), 1, "the title didn't change");
+    assert_eq!(first[0].field, "prompt");
+    assert_eq!(first[0].old, shot.prompt);
+    assert_eq!(
+        first[0].new,
+        "Wide shot of an empty diner, rain on the glass"
+    );
+
+    let to_now = diff::scene_versions(&conn, &shot.id, ids[1], None, fields()).unwrap();
+    assert_eq!(to_now[0].new, "Close on Mara, rain on the glass");
+    assert!(
+        diff::scene_versions(&conn, &shot.id, ids[2], None, fields())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn scene_versions_need_known_fields_and_versions() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let other = scene(&conn, &project_id, 2, &[]);
+    let id = versions(&conn, &shot.id)[0];
+    let other_id = versions(&conn, &other.id)[0];
+
+    assert!(matches!(
+        diff::scene_versions(&conn, &shot.id, id, None, vec!["duration".into()]),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        diff::scene_versions(&conn, &shot.id, other_id, None, vec!["prompt".into()]),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        diff::scene_versions(&conn, &shot.id, id, Some(other_id), vec!["prompt".into()]),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        diff::scene_versions(&conn, "missing", id, None, vec!["prompt".into()]),
+        Err(AppError::NotFound(_))
+    ));
+}
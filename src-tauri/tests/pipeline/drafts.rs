@@ -0,0 +1,77 @@
+//! Drafts of fields being edited, and the conflicts they can run into.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::drafts::{self, DraftInput};
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+
+fn draft(entity_type: &str, entity_id: &str, field: &str, text: &str) -> DraftInput {
+    DraftInput {
+        entity_type: entity_type.into(),
+        entity_id: entity_id.into(),
+        field: field.into(),
+        text: text.into(),
+        base_version: None,
+    }
+}
+
+#[test]
+fn drafts_show_saved_text_and_conflicts_until_discarded() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    drafts::save(&conn, &draft("scene", &shot.id, "prompt", "Rain on glass")).unwrap();
+    drafts::save(&conn, &draft("scene", &shot.id, "title", &shot.title)).unwrap();
+
+    let listed = drafts::list(&conn, Some(&project_id), None).unwrap();
+    assert_eq!(
+        listed.len(),
+        1,
+        "a draft matching the saved text is dropped"
+    );
+    assert_eq!(listed[0].text, "Rain on glass");
+    assert_eq!(listed[0].saved_text, shot.prompt);
+    assert!(!listed[0].conflict);
+
+    conn.execute(
+        "UPDATE scenes SET prompt = 'Saved elsewhere' WHERE id = ?1",
+        [&shot.id],
+    )
+    .unwrap();
+    let listed = drafts::list(&conn, Some(&project_id), None).unwrap();
+    assert!(listed[0].conflict);
+
+    let d = &listed[0];
+    drafts::discard(&conn, &d.entity_type, &d.entity_id, &d.field, &d.user_id).unwrap();
+    assert!(drafts::list(&conn, Some(&project_id), None)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn drafts_need_an_editable_field_of_an_unlocked_entity() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    assert!(matches!(
+        drafts::save(&conn, &draft("scene", &shot.id, "duration", "5")),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        drafts::save(&conn, &draft("take", &shot.id, "prompt", "x")),
+        Err(AppError::Validation(_))
+    ));
+    let missing = uuid::Uuid::new_v4().to_string();
+    assert!(matches!(
+        drafts::save(&conn, &draft("scene", &missing, "prompt", "x")),
+        Err(AppError::NotFound(_))
+    ));
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        drafts::save(&conn, &draft("project", &project_id, "name", "Renamed")),
+        Err(AppError::Locked(_))
+    ));
+}
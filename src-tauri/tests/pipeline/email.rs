@@ -0,0 +1,101 @@
+//! SMTP settings for email notifications.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::email::{self, EmailSettings};
+use ai_directors_chair::error::AppError;
+
+fn config() -> EmailSettings {
+    EmailSettings {
+        enabled: true,
+        smtp_host: " smtp.example.com ".into(),
+        smtp_port: 465,
+        smtp_security: "tls".into(),
+        smtp_username: "dana".into(),
+        from: "AIDC <aidc@example.com>".into(),
+        to: "dana@example.com, crew@example.com".into(),
+        events: Vec::new(),
+        has_password: false,
+        smtp_password: Some("hunter2".into()),
+    }
+}
+
+#[test]
+fn settings_save_keep_the_password_and_build_a_test_message() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(!email::load(&conn).unwrap().enabled);
+
+    let saved = email::save(&conn, &config()).unwrap();
+    assert_eq!(saved.smtp_host, "smtp.example.com");
+    assert_eq!(saved.smtp_port, 465);
+    assert!(saved.has_password);
+    assert!(saved.smtp_password.is_none());
+
+    // Leaving the password out keeps it; an empty one clears it.
+    let kept = email::save(
+        &conn,
+        &EmailSettings {
+            smtp_password: None,
+            ..config()
+        },
+    )
+    .unwrap();
+    assert!(kept.has_password);
+    let (_, password, message) = email::test_message(&conn).unwrap();
+    assert_eq!(password.as_deref(), Some("hunter2"));
+    let formatted = String::from_utf8(message.formatted()).unwrap();
+    assert!(formatted.contains("To: dana@example.com, crew@example.com"));
+
+    let cleared = email::save(
+        &conn,
+        &EmailSettings {
+            smtp_password: Some(String::new()),
+            ..config()
+        },
+    )
+    .unwrap();
+    assert!(!cleared.has_password);
+}
+
+#[test]
+fn settings_need_a_server_sender_and_recipients() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        email::test_message(&conn),
+        Err(AppError::Invalid(_))
+    ));
+    for bad in [
+        EmailSettings {
+            smtp_security: "ssl".into(),
+            ..config()
+        },
+        EmailSettings {
+            smtp_host: " ".into(),
+            ..config()
+        },
+        EmailSettings {
+            from: "not an address".into(),
+            ..config()
+        },
+        EmailSettings {
+            to: " , ".into(),
+            ..config()
+        },
+    ] {
+        assert!(matches!(
+            email::save(&conn, &bad),
+            Err(AppError::Invalid(_))
+        ));
+    }
+    // Switched off, an incomplete setup can still be stored.
+    email::save(
+        &conn,
+        &EmailSettings {
+            enabled: false,
+            smtp_host: String::new(),
+            ..config()
+        },
+    )
+    .unwrap();
+}
@@ -0,0 +1,53 @@
+//! Keyed copies of the database, as enabling and disabling encryption
+//! write them before swapping files. The keyring itself is not touched.
+
+use crate::{project, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::encryption;
+use ai_directors_chair::error::AppError;
+use rusqlite::Connection;
+
+fn project_count(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM projects", [], |r| r.get(0))
+}
+
+#[test]
+fn keyed_copies_open_only_with_their_key() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    project(&conn);
+    let dir = scratch_dir();
+    let pass = encryption::new_passphrase(None).unwrap();
+    assert_eq!(pass.len(), 64);
+
+    let encrypted = dir.join("encrypted.db");
+    encryption::rekeyed_copy(&conn, &encrypted, &pass).unwrap();
+    let locked = Connection::open(&encrypted).unwrap();
+    assert!(project_count(&locked).is_err());
+    let keyed = Connection::open(&encrypted).unwrap();
+    keyed.pragma_update(None, "key", &pass).unwrap();
+    assert_eq!(project_count(&keyed).unwrap(), 1);
+
+    let plain = dir.join("plain.db");
+    encryption::rekeyed_copy(&keyed, &plain, "").unwrap();
+    assert_eq!(
+        project_count(&Connection::open(&plain).unwrap()).unwrap(),
+        1
+    );
+}
+
+#[test]
+fn passphrases_and_destinations_are_checked() {
+    assert!(matches!(
+        encryption::new_passphrase(Some("short".into())),
+        Err(AppError::Invalid(_))
+    ));
+    assert_eq!(
+        encryption::new_passphrase(Some("long enough".into())).unwrap(),
+        "long enough"
+    );
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let nowhere = scratch_dir().join("missing").join("copy.db");
+    assert!(encryption::rekeyed_copy(&conn, &nowhere, "long enough").is_err());
+}
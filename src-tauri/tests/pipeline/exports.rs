@@ -0,0 +1,95 @@
+//! The HTML storyboard export and saved export presets.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::export;
+use ai_directors_chair::export_presets::{self, ExportPresetInput, Watermark};
+
+fn preset(name: &str, opacity: f64) -> ExportPresetInput {
+    ExportPresetInput {
+        name: name.into(),
+        watermark: Some(Watermark {
+            project_name: true,
+            work_in_progress: true,
+            timestamp: false,
+            recipient: false,
+            opacity,
+        }),
+        timecode: true,
+    }
+}
+
+#[test]
+fn html_storyboards_link_takes_only_when_asked() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    conn.execute(
+        "UPDATE scenes SET video_url = 'https://cdn.example.com/take.mp4' WHERE id = ?1",
+        [&shot.id],
+    )
+    .unwrap();
+    let dir = scratch_dir();
+
+    let path = export::html(&conn, &project_id, &dir.to_string_lossy(), false).unwrap();
+    assert!(path.ends_with("index.html"));
+    let html = std::fs::read_to_string(&path).unwrap();
+    assert!(html.contains("Test project"));
+    assert!(html.contains("Wide shot of an empty diner"));
+    assert!(!html.contains("take.mp4"));
+
+    let linked = dir.join("linked.html");
+    export::html(&conn, &project_id, &linked.to_string_lossy(), true).unwrap();
+    assert!(std::fs::read_to_string(linked)
+        .unwrap()
+        .contains("https://cdn.example.com/take.mp4"));
+    assert!(matches!(
+        export::html(&conn, "missing", &dir.to_string_lossy(), false),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn export_presets_are_created_updated_and_deleted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let review = export_presets::create(&conn, &preset(" Review ", 0.5)).unwrap();
+    assert_eq!(review.name, "Review");
+    export_presets::create(&conn, &preset("archive", 1.0)).unwrap();
+    let names: Vec<_> = export_presets::list(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(names, ["archive", "Review"]);
+
+    let updated = export_presets::update(&conn, &review.id, &preset("Client", 0.3)).unwrap();
+    assert_eq!(updated.name, "Client");
+    assert_eq!(updated.watermark.unwrap().opacity, 0.3);
+    export_presets::delete(&conn, &review.id).unwrap();
+    assert_eq!(export_presets::list(&conn).unwrap().len(), 1);
+}
+
+#[test]
+fn export_presets_need_a_name_a_sane_opacity_and_to_exist() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        export_presets::create(&conn, &preset(" ", 0.5)),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        export_presets::create(&conn, &preset("Faint", 0.01)),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        export_presets::update(&conn, "missing", &preset("Client", 0.5)),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        export_presets::delete(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,46 @@
+//! Scene fields round-tripped through an external editor's temp file.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::external_editor;
+
+#[test]
+fn staged_fields_write_back_as_edits() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let root = scratch_dir();
+
+    let (file, text) = external_editor::stage(&conn, &shot.id, "prompt", &root).unwrap();
+    assert_eq!(file, root.join(&shot.id).join("scene-1-prompt.txt"));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), text);
+    assert_eq!(text, shot.prompt);
+
+    let version = external_editor::apply(&conn, &shot.id, "prompt", "Close on the jukebox")
+        .unwrap()
+        .unwrap();
+    assert!(version > shot.version);
+    assert_eq!(
+        external_editor::apply(&conn, &shot.id, "prompt", "Close on the jukebox").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn only_long_text_fields_can_be_staged() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let root = scratch_dir();
+    assert!(matches!(
+        external_editor::stage(&conn, &shot.id, "title", &root),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        external_editor::apply(&conn, &shot.id, "title", "Diner"),
+        Err(AppError::Validation(_))
+    ));
+}
@@ -0,0 +1,56 @@
+//! Chaining a scene's start image to the end of the one before it.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::frames;
+
+#[test]
+fn the_next_scene_starts_from_the_finished_take() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &[]);
+    let second = scene(&conn, &project_id, 2, &[]);
+    conn.execute(
+        "UPDATE scenes SET video_url = '/takes/one.mp4' WHERE id = ?1",
+        [&first.id],
+    )
+    .unwrap();
+
+    let (from, next, video) = frames::chain(&conn, &first.id, None).unwrap();
+    assert_eq!(next.id, second.id);
+    assert_eq!(video, "/takes/one.mp4");
+    let next = frames::start_from(&conn, &from, &next, "/frames/one.jpg").unwrap();
+    assert_eq!(next.conditioning_image, "/frames/one.jpg");
+}
+
+#[test]
+fn chaining_needs_a_take_and_a_scene_after_it() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &[]);
+    let second = scene(&conn, &project_id, 2, &[]);
+    assert!(matches!(
+        frames::chain(&conn, &first.id, None),
+        Err(AppError::Invalid(_))
+    ));
+
+    conn.execute(
+        "UPDATE scenes SET video_url = '/takes/two.mp4' WHERE id = ?1",
+        [&second.id],
+    )
+    .unwrap();
+    assert!(matches!(
+        frames::chain(&conn, &second.id, None),
+        Err(AppError::Invalid(_))
+    ));
+
+    let other = project(&conn);
+    let elsewhere = scene(&conn, &other, 1, &[]);
+    assert!(matches!(
+        frames::chain(&conn, &second.id, Some(&elsewhere.id)),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,64 @@
+//! Project generation defaults and the per-scene overrides over them.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::generation::{self, GenerationSettings};
+use ai_directors_chair::projects;
+
+#[test]
+fn scene_overrides_fall_through_to_project_defaults() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let defaults = GenerationSettings {
+        model: Some("wan".into()),
+        resolution: Some("720p".into()),
+        duration: Some(8),
+        ..Default::default()
+    };
+    generation::set_project_defaults(&conn, &project_id, &defaults).unwrap();
+    assert_eq!(
+        generation::project_defaults(&conn, &project_id).unwrap(),
+        defaults
+    );
+
+    let overrides = GenerationSettings {
+        duration: Some(4),
+        ..Default::default()
+    };
+    let shot = generation::set_scene_overrides(&conn, &shot.id, &overrides).unwrap();
+    assert_eq!(shot.generation, overrides);
+    let effective = generation::effective(&conn, &shot).unwrap();
+    assert_eq!(effective.model.as_deref(), Some("wan"));
+    assert_eq!(effective.resolution.as_deref(), Some("720p"));
+    assert_eq!(effective.duration, Some(4));
+}
+
+#[test]
+fn generation_settings_must_suit_the_model_and_an_unlocked_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let wan = GenerationSettings {
+        model: Some("wan".into()),
+        ..Default::default()
+    };
+    generation::set_project_defaults(&conn, &project_id, &wan).unwrap();
+    let too_sharp = GenerationSettings {
+        resolution: Some("1080p".into()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        generation::set_scene_overrides(&conn, &shot.id, &too_sharp),
+        Err(AppError::Validation(_))
+    ));
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        generation::set_project_defaults(&conn, &project_id, &wan),
+        Err(AppError::Locked(_))
+    ));
+}
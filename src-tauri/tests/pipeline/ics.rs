@@ -0,0 +1,322 @@
+//! Calendars written by `ics::calendar` read back through `ics::parse`,
+//! and shooting days and milestones round-tripped through them.
+
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::ics::{self, Event, When};
+use ai_directors_chair::milestones::{self, MilestoneInput};
+use ai_directors_chair::projects;
+use ai_directors_chair::schedule::{self, ShootingDayInput};
+use chrono::{Local, NaiveDate, TimeZone, Utc};
+
+fn event(when: When) -> Event {
+    Event {
+        uid: "event-1@aidc".into(),
+        when,
+        summary: "Day 1".into(),
+        description: String::new(),
+        location: String::new(),
+    }
+}
+
+fn day(project_id: &str, call_time: &str, wrap_time: &str) -> ShootingDayInput {
+    ShootingDayInput {
+        id: None,
+        project_id: project_id.to_string(),
+        shoot_date: "2026-03-02".into(),
+        call_time: call_time.into(),
+        wrap_time: wrap_time.into(),
+        location: "Stage 4, Lot B; gate \\ north".into(),
+        notes: "Bring the rain tower".into(),
+    }
+}
+
+#[test]
+fn long_lines_fold_at_75_octets_and_unfold_intact() {
+    let summary = "Scène de nuit — café désert, néons ".repeat(8);
+    let calendar = ics::calendar(
+        "Test",
+        &[Event {
+            summary: summary.clone(),
+            ..event(When::AllDay("2026-03-02".into()))
+        }],
+    );
+
+    for line in calendar.split("\r\n") {
+        assert!(line.len() <= 75, "{} octets: {:?}", line.len(), line);
+    }
+    assert!(calendar.contains("\r\n "), "expected a folded line");
+    assert_eq!(ics::parse(&calendar)[0].summary, summary);
+}
+
+#[test]
+fn text_escapes_round_trip() {
+    let original = Event {
+        summary: "Day 1, INT; back\\slash".into(),
+        description: "Scenes:\n1. Diner\n2. Alley, \"night\"".into(),
+        location: "Stage 4, Lot B; gate \\ north".into(),
+        ..event(When::AllDay("2026-03-02".into()))
+    };
+    let calendar = ics::calendar("Test; 1, 2", std::slice::from_ref(&original));
+
+    assert!(calendar.contains("SUMMARY:Day 1\\, INT\\; back\\\\slash\r\n"));
+    assert_eq!(ics::parse(&calendar), vec![original]);
+
+    let crlf = Event {
+        description: "one\r\ntwo".into(),
+        ..event(When::AllDay("2026-03-02".into()))
+    };
+    let parsed = ics::parse(&ics::calendar("Test", &[crlf]));
+    assert_eq!(parsed[0].description, "one\ntwo");
+}
+
+#[test]
+fn all_day_and_timed_events_round_trip() {
+    let timed = |start: &str, end: Option<&str>, default_minutes| {
+        event(When::Timed {
+            date: "2026-03-02".into(),
+            start: start.into(),
+            end: end.map(String::from),
+            default_minutes,
+        })
+    };
+    let cases = [
+        (
+            event(When::AllDay("2026-03-02".into())),
+            When::AllDay("2026-03-02".into()),
+        ),
+        (
+            timed("07:30", Some("19:15"), 600),
+            timed("07:30", Some("19:15"), 0).when,
+        ),
+        (
+            timed("07:30", None, 90),
+            timed("07:30", Some("09:00"), 0).when,
+        ),
+        (
+            timed("18:00", Some("02:00"), 600),
+            timed("18:00", Some("02:00"), 0).when,
+        ),
+    ];
+
+    for (written, expected) in cases {
+        let calendar = ics::calendar("Test", std::slice::from_ref(&written));
+        let parsed = ics::parse(&calendar);
+        assert_eq!(parsed.len(), 1, "{:?}", written.when);
+        assert_eq!(parsed[0].when, expected, "{:?}", written.when);
+    }
+}
+
+#[test]
+fn zoned_times_keep_wall_clock_and_utc_becomes_local() {
+    let calendar = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:zoned@example.com\r\n\
+        DTSTART;TZID=\"Europe/Paris\":20260302T083000\r\n\
+        DTEND;TZID=\"Europe/Paris\":20260302T180000\r\n\
+        SUMMARY:Zoned\r\n\
+        BEGIN:VALARM\r\n\
+        ACTION:DISPLAY\r\n\
+        DESCRIPTION:Reminder\r\n\
+        TRIGGER:-PT30M\r\n\
+        END:VALARM\r\n\
+        DESCRIPTION:Kept\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:utc@example.com\r\n\
+        DTSTART:20260302T120000Z\r\n\
+        DURATION:PT1H30M\r\n\
+        SUMMARY:In\r\n\
+        \tUTC\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:all-day@example.com\r\n\
+        DTSTART:20260303\r\n\
+        SUMMARY:All day\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    let events = ics::parse(calendar);
+    assert_eq!(events.len(), 3);
+
+    assert_eq!(
+        events[0].when,
+        When::Timed {
+            date: "2026-03-02".into(),
+            start: "08:30".into(),
+            end: Some("18:00".into()),
+            default_minutes: 0,
+        }
+    );
+    assert_eq!(events[0].description, "Kept");
+
+    let local = |hour, minute| {
+        let utc = NaiveDate::from_ymd_opt(2026, 3, 2)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap();
+        Utc.from_utc_datetime(&utc).with_timezone(&Local)
+    };
+    assert_eq!(events[1].summary, "InUTC");
+    assert_eq!(
+        events[1].when,
+        When::Timed {
+            date: local(12, 0).format("%Y-%m-%d").to_string(),
+            start: local(12, 0).format("%H:%M").to_string(),
+            end: Some(local(13, 30).format("%H:%M").to_string()),
+            default_minutes: 0,
+        }
+    );
+
+    assert_eq!(events[2].when, When::AllDay("2026-03-03".into()));
+}
+
+#[test]
+fn shooting_days_round_trip_through_a_calendar() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let timed = schedule::save_day(&conn, &day(&project_id, "07:00", "19:30")).unwrap();
+    let all_day = schedule::save_day(&conn, &day(&project_id, "", "")).unwrap();
+
+    // Read back into the same project, each event moves its own day.
+    let calendar = schedule::day_calendar(&conn, &timed.id).unwrap();
+    let moved = calendar.replace("20260302T", "20260309T");
+    let imported = schedule::import_calendar(&conn, &project_id, &moved).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].id, timed.id);
+    assert_eq!(imported[0].shoot_date, "2026-03-09");
+    assert_eq!(imported[0].call_time, "07:00");
+    assert_eq!(imported[0].wrap_time, "19:30");
+    assert_eq!(imported[0].location, timed.location);
+    assert_eq!(imported[0].notes, timed.notes);
+
+    // Into another project, they become new days.
+    let other = project(&conn);
+    for id in [&timed.id, &all_day.id] {
+        let source = schedule::get_day(&conn, id).unwrap();
+        let calendar = schedule::day_calendar(&conn, id).unwrap();
+        let imported = schedule::import_calendar(&conn, &other, &calendar).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_ne!(imported[0].id, source.id);
+        assert_eq!(imported[0].project_id, other);
+        assert_eq!(imported[0].shoot_date, source.shoot_date);
+        assert_eq!(imported[0].call_time, source.call_time);
+        assert_eq!(imported[0].wrap_time, source.wrap_time);
+        assert_eq!(imported[0].location, "Stage 4, Lot B; gate \\ north");
+        assert!(imported[0].notes.ends_with("Bring the rain tower"));
+    }
+}
+
+#[test]
+fn importing_a_calendar_is_refused_for_bad_dates_and_locked_projects() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let bad = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20261345\r\n\
+        SUMMARY:Bad\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    assert!(matches!(
+        schedule::import_calendar(&conn, &project_id, bad),
+        Err(AppError::Invalid(_))
+    ));
+
+    let calendar = ics::calendar("Test", &[event(When::AllDay("2026-03-02".into()))]);
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        schedule::import_calendar(&conn, &project_id, &calendar),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        schedule::import_calendar(&conn, "missing", &calendar),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn milestones_round_trip_as_all_day_events() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let milestone = |kind: &str, title: &str, due_date: &str, completed| MilestoneInput {
+        id: None,
+        project_id: project_id.clone(),
+        kind: kind.into(),
+        title: title.into(),
+        due_date: due_date.into(),
+        notes: "Sign-off from: director, producer; \\ legal\nthen deliver".into(),
+        completed,
+    };
+    milestones::save(&conn, &milestone("picture_lock", "", "2026-05-01", false)).unwrap();
+    milestones::save(
+        &conn,
+        &milestone("custom", "Table read, day 2", "2026-02-10", false),
+    )
+    .unwrap();
+    milestones::save(&conn, &milestone("script_locked", "", "2026-01-15", true)).unwrap();
+
+    let open = ics::parse(&milestones::calendar(&conn, &project_id, false).unwrap());
+    let rows: Vec<_> = open
+        .iter()
+        .map(|e| (e.summary.as_str(), e.when.clone()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            (
+                "Test project: Table read, day 2",
+                When::AllDay("2026-02-10".into())
+            ),
+            (
+                "Test project: Picture lock",
+                When::AllDay("2026-05-01".into())
+            ),
+        ]
+    );
+    assert_eq!(
+        open[0].description,
+        "Sign-off from: director, producer; \\ legal\nthen deliver"
+    );
+
+    let all = ics::parse(&milestones::calendar(&conn, &project_id, true).unwrap());
+    assert_eq!(all.len(), 3);
+
+    assert!(matches!(
+        milestones::calendar(&conn, "missing", false),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn milestones_are_deleted_only_while_unlocked() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let input = |kind: &str| MilestoneInput {
+        id: None,
+        project_id: project_id.clone(),
+        kind: kind.into(),
+        title: String::new(),
+        due_date: "2026-05-01".into(),
+        notes: String::new(),
+        completed: false,
+    };
+    let lock = milestones::save(&conn, &input("picture_lock")).unwrap();
+    let delivery = milestones::save(&conn, &input("delivery")).unwrap();
+    milestones::delete(&conn, &lock.id).unwrap();
+    let left: Vec<_> = milestones::list_for_project(&conn, &project_id)
+        .unwrap()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(left, std::slice::from_ref(&delivery.id));
+    assert!(matches!(
+        milestones::delete(&conn, &lock.id),
+        Err(AppError::NotFound(_))
+    ));
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        milestones::delete(&conn, &delivery.id),
+        Err(AppError::Locked(_))
+    ));
+}
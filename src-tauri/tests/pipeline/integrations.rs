@@ -0,0 +1,104 @@
+//! Notion and Airtable tokens, project links and what a sync sends.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::integrations::{self, Connector, IntegrationLink};
+use std::collections::BTreeMap;
+
+fn airtable() -> IntegrationLink {
+    IntegrationLink {
+        connector: Connector::Airtable,
+        target: " appDiner ".into(),
+        table: "Scenes".into(),
+        fields: BTreeMap::from([
+            ("title".to_string(), "Name".to_string()),
+            ("prompt".to_string(), "Prompt".to_string()),
+        ]),
+        last_synced_at: None,
+    }
+}
+
+#[test]
+fn tokens_connect_and_disconnect() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let connected = |conn| {
+        integrations::status(conn)
+            .unwrap()
+            .into_iter()
+            .filter(|s| s.connected)
+            .map(|s| s.connector)
+            .collect::<Vec<_>>()
+    };
+    assert!(connected(&conn).is_empty());
+    integrations::set_token(&conn, Connector::Notion, " secret_abc ").unwrap();
+    assert_eq!(connected(&conn), [Connector::Notion]);
+    integrations::set_token(&conn, Connector::Notion, "  ").unwrap();
+    assert!(connected(&conn).is_empty());
+}
+
+#[test]
+fn linked_projects_sync_their_mapped_fields() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &[]);
+    let blank = integrations::link(&conn, &project_id, Connector::Airtable).unwrap();
+    assert!(blank.target.is_empty());
+    assert_eq!(blank.fields["title"], "Name");
+
+    let saved = integrations::save_link(&conn, &project_id, &airtable()).unwrap();
+    assert_eq!(saved.target, "appDiner");
+    integrations::set_token(&conn, Connector::Airtable, "pat123").unwrap();
+    let (token, link, scenes) =
+        integrations::prepare(&conn, &project_id, Connector::Airtable).unwrap();
+    assert_eq!(token, "pat123");
+    assert_eq!(link.table, "Scenes");
+    assert_eq!(scenes.len(), 1);
+    let properties: Vec<_> = scenes[0].1.keys().map(String::as_str).collect();
+    assert_eq!(properties, ["Name", "Prompt"]);
+
+    integrations::mark_synced(&conn, &project_id, Connector::Airtable).unwrap();
+    assert!(integrations::link(&conn, &project_id, Connector::Airtable)
+        .unwrap()
+        .last_synced_at
+        .is_some());
+}
+
+#[test]
+fn links_need_known_fields_a_target_and_a_token() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(matches!(
+        integrations::link(&conn, "missing", Connector::Notion),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        integrations::prepare(&conn, &project_id, Connector::Airtable),
+        Err(AppError::Invalid(_))
+    ));
+
+    let mut budget = airtable();
+    budget.fields.insert("budget".into(), "Budget".into());
+    assert!(matches!(
+        integrations::save_link(&conn, &project_id, &budget),
+        Err(AppError::Invalid(_))
+    ));
+    let notion = IntegrationLink {
+        connector: Connector::Notion,
+        target: "not a database".into(),
+        ..airtable()
+    };
+    assert!(matches!(
+        integrations::save_link(&conn, &project_id, &notion),
+        Err(AppError::Invalid(_))
+    ));
+
+    integrations::save_link(&conn, &project_id, &airtable()).unwrap();
+    assert!(matches!(
+        integrations::prepare(&conn, &project_id, Connector::Airtable),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,61 @@
+//! Invite tokens for LAN guests: issuing, listing and revoking them.
+
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::invites::{self, InviteInput};
+use ai_directors_chair::roles::Role;
+
+fn input(project_id: &str, role: Role) -> InviteInput {
+    InviteInput {
+        project_id: project_id.to_string(),
+        name: " Sam ".into(),
+        role,
+        expires_in_days: Some(7),
+    }
+}
+
+#[test]
+fn issued_tokens_let_guests_in_until_revoked() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let issued = invites::issue(&conn, &input(&project_id, Role::Commenter)).unwrap();
+    assert!(issued.token.starts_with("aidc_"));
+    assert_eq!(issued.invite.name, "Sam");
+    assert!(issued.invite.expires_at.is_some());
+    assert!(invites::required(&conn, &project_id).unwrap());
+
+    let (id, role) = invites::redeem(&conn, &project_id, &issued.token, "laptop").unwrap();
+    assert_eq!(
+        (id.as_str(), role),
+        (issued.invite.id.as_str(), Role::Commenter)
+    );
+    let listed = invites::list(&conn, &project_id, std::slice::from_ref(&id)).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].connected);
+    assert_eq!(listed[0].last_device_id.as_deref(), Some("laptop"));
+
+    let revoked = invites::revoke(&conn, &id).unwrap();
+    assert!(revoked.revoked_at.is_some());
+    assert!(!invites::is_live(&conn, &id).unwrap());
+    assert!(matches!(
+        invites::redeem(&conn, &project_id, &issued.token, "laptop"),
+        Err(AppError::Forbidden(_))
+    ));
+}
+
+#[test]
+fn ownership_cannot_be_handed_out() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(matches!(
+        invites::issue(&conn, &input(&project_id, Role::Owner)),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        invites::revoke(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
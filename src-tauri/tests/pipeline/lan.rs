@@ -0,0 +1,74 @@
+//! Who gets into a LAN session, and the host's copy a guest starts from.
+
+use crate::{project, scene};
+use ai_directors_chair::bundle;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::invites::{self, InviteInput};
+use ai_directors_chair::lan;
+use ai_directors_chair::roles::Role;
+use ai_directors_chair::scenes;
+
+#[test]
+fn guests_get_in_with_a_live_token_or_as_viewers() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert_eq!(
+        lan::admit(&conn, &project_id, None, "tablet").unwrap(),
+        (None, Role::Viewer)
+    );
+
+    let issued = invites::issue(
+        &conn,
+        &InviteInput {
+            project_id: project_id.clone(),
+            name: "Sam".into(),
+            role: Role::Editor,
+            expires_in_days: None,
+        },
+    )
+    .unwrap();
+    let (token_id, role) = lan::admit(&conn, &project_id, Some(&issued.token), "tablet").unwrap();
+    assert_eq!(token_id, Some(issued.invite.id));
+    assert_eq!(role, Role::Editor);
+    assert!(matches!(
+        lan::admit(&conn, &project_id, None, "tablet"),
+        Err(AppError::Forbidden(_))
+    ));
+    assert!(matches!(
+        lan::admit(&conn, &project_id, Some("aidc_wrong"), "tablet"),
+        Err(AppError::Forbidden(_))
+    ));
+}
+
+#[test]
+fn guests_start_from_the_hosts_copy() {
+    let host = Database::open_in_memory().unwrap();
+    let host = host.conn();
+    let project_id = project(&host);
+    scene(&host, &project_id, 1, &[]);
+    let (project, device_id, _) = lan::hosting(&host, &project_id).unwrap();
+    assert_eq!(project.id, project_id);
+    assert_eq!(lan::identity(&host).unwrap().0, device_id);
+    assert!(matches!(
+        lan::hosting(&host, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+
+    let guest = Database::open_in_memory().unwrap();
+    let guest = guest.conn();
+    let copy = bundle::export_bundle(&host, &project_id).unwrap();
+    lan::take_host_copy(&guest, &copy).unwrap();
+    assert_eq!(
+        scenes::list_for_project(&guest, &project_id).unwrap().len(),
+        1
+    );
+
+    let mut newer = copy;
+    newer.format += 1;
+    assert!(matches!(
+        lan::take_host_copy(&guest, &newer),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,87 @@
+//! The shared asset library and the projects linking its assets.
+
+use crate::{project, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::library::{self, AssetKind};
+use ai_directors_chair::projects;
+
+#[test]
+fn assets_are_added_once_linked_and_deleted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let dir = scratch_dir();
+    let source = scratch_dir().join("Diner Neon.png");
+    std::fs::write(&source, b"not really a png").unwrap();
+
+    let asset = library::add(
+        &dir,
+        &source,
+        AssetKind::ReferenceImage,
+        None,
+        vec![" Neon ".into()],
+    )
+    .unwrap();
+    assert_eq!(asset.name, "Diner Neon");
+    assert_eq!(asset.tags, ["neon"]);
+    let again = library::add(
+        &dir,
+        &source,
+        AssetKind::ReferenceImage,
+        Some("Other"),
+        vec![],
+    )
+    .unwrap();
+    assert_eq!(again.name, "Diner Neon");
+    assert_eq!(library::list(&dir, None, "neon").len(), 1);
+    assert!(library::list(&dir, Some(AssetKind::Lut), "").is_empty());
+
+    let renamed = library::update(&dir, &asset.id, "Neon sign", vec!["night".into()]).unwrap();
+    assert_eq!(renamed.tags, ["night"]);
+    library::link(&conn, &dir, &project_id, &asset.id).unwrap();
+    let linked = library::list_for_project(&conn, &dir, &project_id).unwrap();
+    assert_eq!(linked.len(), 1);
+    assert_eq!(linked[0].name, "Neon sign");
+    assert!(linked[0].path.is_some());
+
+    library::unlink(&conn, &project_id, &asset.id).unwrap();
+    assert!(library::list_for_project(&conn, &dir, &project_id)
+        .unwrap()
+        .is_empty());
+    library::link(&conn, &dir, &project_id, &asset.id).unwrap();
+    library::delete(&conn, &dir, &asset.id).unwrap();
+    assert!(library::list(&dir, None, "").is_empty());
+    assert!(library::list_for_project(&conn, &dir, &project_id)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn assets_need_a_matching_file_a_name_and_an_open_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let dir = scratch_dir();
+    let source = scratch_dir().join("grade.cube");
+    std::fs::write(&source, b"LUT_3D_SIZE 2").unwrap();
+    assert!(matches!(
+        library::add(&dir, &source, AssetKind::Music, None, vec![]),
+        Err(AppError::Invalid(_))
+    ));
+
+    let lut = library::add(&dir, &source, AssetKind::Lut, None, vec![]).unwrap();
+    assert!(matches!(
+        library::update(&dir, &lut.id, "  ", vec![]),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        library::delete(&conn, &dir, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        library::link(&conn, &dir, &project_id, &lut.id),
+        Err(AppError::Locked(_))
+    ));
+}
@@ -0,0 +1,33 @@
+//! What a lip-sync take is made from.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::lipsync;
+
+#[test]
+fn lip_sync_takes_need_a_reference_photo() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    let mara = &shot.characters[0];
+    conn.execute(
+        "UPDATE scenes SET dialog = 'Coffee?' WHERE id = ?1",
+        [&shot.id],
+    )
+    .unwrap();
+    assert!(matches!(
+        lipsync::sources(&conn, &shot.id, mara),
+        Err(AppError::Invalid(_))
+    ));
+
+    conn.execute(
+        "UPDATE characters SET photo_data = 'data:image/png;base64,AAAA' WHERE id = ?1",
+        [mara],
+    )
+    .unwrap();
+    let (photo, dialog) = lipsync::sources(&conn, &shot.id, mara).unwrap();
+    assert_eq!(photo, "data:image/png;base64,AAAA");
+    assert_eq!(dialog, "Coffee?");
+}
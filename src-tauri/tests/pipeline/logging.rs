@@ -0,0 +1,100 @@
+//! Reading back the app's JSON logs and zipping them for a bug report.
+
+use crate::scratch_dir;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::logging::{self, LogFilter};
+use std::io::Read;
+
+fn log_dir() -> std::path::PathBuf {
+    let dir = scratch_dir();
+    std::fs::write(
+        dir.join("aidc.2026-10-13.log"),
+        "{\"level\":\"INFO\",\"fields\":{\"message\":\"app started\"}}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("aidc.2026-10-14.log"),
+        "{\"level\":\"ERROR\",\"fields\":{\"message\":\"upload failed\"}}\n\
+         not json\n\
+         {\"level\":\"DEBUG\",\"fields\":{\"message\":\"polled queue\"}}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+    dir
+}
+
+#[test]
+fn recent_logs_filter_newest_first() {
+    let dir = log_dir();
+    let messages = |filter: LogFilter| -> Vec<String> {
+        logging::recent(&dir, &filter)
+            .unwrap()
+            .iter()
+            .map(|e| e["fields"]["message"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(
+        messages(LogFilter::default()),
+        ["polled queue", "upload failed", "app started"]
+    );
+    assert_eq!(
+        messages(LogFilter {
+            level: Some("warn".into()),
+            ..Default::default()
+        }),
+        ["upload failed"]
+    );
+    assert_eq!(
+        messages(LogFilter {
+            contains: Some("STARTED".into()),
+            ..Default::default()
+        }),
+        ["app started"]
+    );
+    assert_eq!(
+        messages(LogFilter {
+            limit: Some(1),
+            ..Default::default()
+        }),
+        ["polled queue"]
+    );
+    assert!(
+        logging::recent(&scratch_dir().join("none"), &LogFilter::default())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn support_bundles_hide_credentials() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES ('api_key.fal', 'fal-123'),
+            ('secret.integrations.notion_token', 'secret_abc'), ('ui.theme', 'dark')",
+        [],
+    )
+    .unwrap();
+    let out = scratch_dir();
+
+    let path = logging::support_bundle(&conn, &log_dir(), &out.to_string_lossy()).unwrap();
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut settings = String::new();
+    zip.by_name("settings.json")
+        .unwrap()
+        .read_to_string(&mut settings)
+        .unwrap();
+    assert!(settings.contains("\"ui.theme\": \"dark\""));
+    assert!(settings.contains("[redacted]"));
+    assert!(!settings.contains("fal-123"));
+    assert!(!settings.contains("secret_abc"));
+    assert!(zip.by_name("logs/aidc.2026-10-14.log").is_ok());
+
+    // A log dir that is really a file can't be read.
+    let not_a_dir = out.join("aidc-support.zip");
+    assert!(matches!(
+        logging::support_bundle(&conn, &not_a_dir, &out.join("again.zip").to_string_lossy()),
+        Err(AppError::Io(_))
+    ));
+}
@@ -0,0 +1,1134 @@
+//! End-to-end checks of the generation pipeline against an in-memory
+//! database and the mock provider: scenes and cast, job submission and
+//! polling, the failure policy, and stale flags. Commands are reached
+//! through the `fn(&Connection, ..)` cores they wrap, one module per area
+//! below; project, scene, character, settings and sync cores are covered
+//! here. The parts that need a running app (dialogs, windows, the
+//! keyring, live providers) are not.
+
+use ai_directors_chair::balances;
+use ai_directors_chair::characters::{self, CharacterInput, CharacterSortField};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::events::{AppEvent, SignInFailed};
+use ai_directors_chair::export_presets;
+use ai_directors_chair::failures::{self, Outcome};
+use ai_directors_chair::handoff;
+use ai_directors_chair::i18n::{self, Locale};
+use ai_directors_chair::inbox;
+use ai_directors_chair::jobs::{self, kind, status};
+use ai_directors_chair::mock::{self, MockSettings};
+use ai_directors_chair::paging::Direction;
+use ai_directors_chair::projects::{self, ProjectInput};
+use ai_directors_chair::providers::{GenerationRequest, QueueState};
+use ai_directors_chair::redaction::{self, RedactionPolicy, Redactor};
+use ai_directors_chair::scene_characters::{self, importance, CastMember};
+use ai_directors_chair::scene_import::{self, Table};
+use ai_directors_chair::scenes::{self, Scene, SceneFilter, SceneInput, SceneSort, SceneSortField};
+use ai_directors_chair::settings;
+use ai_directors_chair::sync::{self, Change, Changeset, Merge, Resolution};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use tauri::async_runtime::block_on;
+
+mod activity;
+mod analytics;
+mod archive;
+mod backup;
+mod bulk;
+mod character_fragments;
+mod character_packs;
+mod clips;
+mod cloud_sync;
+mod comments;
+mod confidential;
+mod contact_sheet;
+mod continuity;
+mod data_dir;
+mod delete_preview;
+mod diagnostics;
+mod diff;
+mod drafts;
+mod email;
+mod encryption;
+mod exports;
+mod external_editor;
+mod frames;
+mod generation;
+mod ics;
+mod integrations;
+mod invites;
+mod lan;
+mod library;
+mod lipsync;
+mod logging;
+mod migration_assistant;
+mod migrations;
+mod mirror;
+mod moderation;
+mod moodboards;
+mod network;
+mod notifications;
+mod numbering;
+mod oauth;
+mod ordering;
+mod pitch_deck;
+mod presets;
+mod previews;
+mod project_file;
+mod prompts;
+mod props;
+mod provider_balances;
+mod provider_cache;
+mod publishing;
+mod quick_capture;
+mod reports;
+mod review_upload;
+mod roles;
+mod runtime;
+mod scene_graph;
+mod scene_links;
+mod schedule;
+mod screenplay;
+mod search;
+mod settings_profiles;
+mod social_cuts;
+mod spellcheck;
+mod split_merge;
+mod stale;
+mod stats;
+mod tags;
+mod takes;
+mod updater;
+mod users;
+mod vocabulary;
+mod voices;
+mod watch_folder;
+mod workflow;
+mod workspaces;
+
+fn project(conn: &Connection) -> String {
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO projects (id, name) VALUES (?1, 'Test project')",
+        [&id],
+    )
+    .unwrap();
+    id
+}
+
+fn scene(conn: &Connection, project_id: &str, number: i64, cast: &[&str]) -> Scene {
+    scenes::save(
+        conn,
+        &SceneInput {
+            id: None,
+            project_id: project_id.to_string(),
+            scene_number: number,
+            title: format!("Scene {}", number),
+            description: "A diner at night".into(),
+            prompt: "Wide shot of an empty diner, neon flickering".into(),
+            camera_angle: None,
+            lighting: None,
+            duration: 5,
+            dialog: String::new(),
+            characters: cast.iter().map(|c| c.to_string()).collect(),
+            sort_order: number,
+            location: "INT. DINER - NIGHT".into(),
+            expected_version: None,
+        },
+    )
+    .unwrap()
+}
+
+fn request() -> GenerationRequest {
+    GenerationRequest {
+        prompt: "Wide shot of an empty diner".into(),
+        negative_prompt: None,
+        image_url: None,
+        duration: 5,
+        aspect_ratio: "16:9".into(),
+        seed: None,
+        resolution: None,
+        motion_strength: None,
+    }
+}
+
+fn queue_job(conn: &Connection, scene_id: &str) -> String {
+    jobs::insert_job(
+        conn,
+        scene_id,
+        mock::MODEL,
+        kind::VIDEO,
+        &serde_json::to_string(&request()).unwrap(),
+    )
+    .unwrap()
+}
+
+/// An empty directory of its own under the system temp dir.
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn fresh_database_is_fully_migrated() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let tables: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+             AND name IN ('video_jobs', 'job_logs', 'provider_balances', 'character_fragments')",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(tables, 4);
+    assert_eq!(balances::fal_remaining(&conn).unwrap(), None);
+}
+
+#[test]
+fn saving_a_scene_casts_new_characters_by_name() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let saved = scene(&conn, &project_id, 1, &["Mara", "Joe"]);
+
+    let cast = scene_characters::list_for_scene(&conn, &saved.id).unwrap();
+    let names: Vec<&str> = cast.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["Mara", "Joe"]);
+    assert_eq!(
+        scenes::get_scene(&conn, &saved.id)
+            .unwrap()
+            .characters
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn deleted_scenes_are_gone_from_the_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let kept = scene(&conn, &project_id, 1, &[]);
+    let dropped = scene(&conn, &project_id, 2, &[]);
+    scenes::delete(&conn, &dropped.id).unwrap();
+
+    let left = scenes::list_for_project(&conn, &project_id).unwrap();
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].id, kept.id);
+    assert!(matches!(
+        scenes::get_scene(&conn, &dropped.id),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        scenes::delete(&conn, &dropped.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn settings_are_stored_in_the_database() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert_eq!(settings::get(&conn, "ui.theme").unwrap(), None);
+    settings::set(&conn, "ui.theme", "dark").unwrap();
+    assert_eq!(
+        settings::get(&conn, "ui.theme").unwrap().as_deref(),
+        Some("dark")
+    );
+
+    let bare = Connection::open_in_memory().unwrap();
+    assert!(matches!(
+        settings::set(&bare, "ui.theme", "dark"),
+        Err(AppError::Database(_))
+    ));
+}
+
+#[test]
+fn cast_roles_are_set_removed_and_searched() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara", "Joe"]);
+    let (mara, joe) = (&shot.characters[0], &shot.characters[1]);
+
+    let lead = CastMember {
+        character_id: mara.clone(),
+        role: "witness".into(),
+        importance: importance::LEAD.into(),
+    };
+    let cast = scene_characters::set_member(&conn, &shot.id, &lead).unwrap();
+    assert_eq!(cast[0].role, "witness");
+    assert_eq!(
+        scene_characters::cast(&conn, &shot.id).unwrap()[0].importance,
+        importance::LEAD
+    );
+    let leading = scene_characters::featuring(&conn, mara, Some(importance::LEAD)).unwrap();
+    assert_eq!(leading.len(), 1);
+    assert!(
+        scene_characters::featuring(&conn, joe, Some(importance::LEAD))
+            .unwrap()
+            .is_empty()
+    );
+
+    assert_eq!(
+        scene_characters::remove(&conn, &shot.id, joe)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(matches!(
+        scene_characters::remove(&conn, &shot.id, joe),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        scene_characters::cast(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        scene_characters::featuring(&conn, "missing", None),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn scene_and_character_pages_filter_sort_and_count() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &["Mara"]);
+    scene(&conn, &project_id, 2, &["Joe"]);
+    scene(&conn, &project_id, 3, &["Mara", "Joe"]);
+
+    let newest_first = SceneSort {
+        field: SceneSortField::SceneNumber,
+        direction: Direction::Desc,
+    };
+    let page = scenes::list_page(
+        &conn,
+        &project_id,
+        &SceneFilter::default(),
+        newest_first,
+        None,
+        Some(2),
+    )
+    .unwrap();
+    let numbers: Vec<i64> = page.items.iter().map(|s| s.scene_number).collect();
+    assert_eq!((numbers, page.total), (vec![3, 2], 3));
+
+    let featuring = SceneFilter {
+        character: Some("mara".into()),
+        ..SceneFilter::default()
+    };
+    let page = scenes::list_page(
+        &conn,
+        &project_id,
+        &featuring,
+        SceneSort::default(),
+        None,
+        None,
+    )
+    .unwrap();
+    let numbers: Vec<i64> = page.items.iter().map(|s| s.scene_number).collect();
+    assert_eq!(numbers, [1, 3]);
+
+    let page = characters::list_page(
+        &conn,
+        &project_id,
+        Some(" JO "),
+        CharacterSortField::Name,
+        Direction::Asc,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].name, "Joe");
+}
+
+#[test]
+fn recent_projects_list_pinned_then_recently_opened() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let [old, opened, pinned] = [project(&conn), project(&conn), project(&conn)];
+    conn.execute(
+        "UPDATE projects SET created_at = '2000-01-01T00:00:00Z'",
+        [],
+    )
+    .unwrap();
+    projects::mark_opened(&conn, &opened).unwrap();
+    assert!(projects::set_pinned(&conn, &pinned, true).unwrap().pinned);
+
+    let ids: Vec<String> = projects::recent(&conn, 10, false)
+        .unwrap()
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    assert_eq!(ids, [pinned, opened, old]);
+    assert!(matches!(
+        projects::mark_opened(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn project_edits_are_checked_against_the_loaded_version() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let loaded = projects::get_project(&conn, &project_id).unwrap();
+    let input = |name: &str| ProjectInput {
+        name: name.into(),
+        genre: "Noir".into(),
+        synopsis: String::new(),
+        tone: String::new(),
+        expected_version: Some(loaded.version),
+    };
+    let saved = projects::update(&conn, &project_id, &input("Night Diner")).unwrap();
+    assert_eq!(saved.name, "Night Diner");
+    assert!(matches!(
+        projects::update(&conn, &project_id, &input("Day Diner")),
+        Err(AppError::Conflict(_))
+    ));
+}
+
+#[test]
+fn unlocking_a_project_needs_its_name() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(projects::lock(&conn, &project_id)
+        .unwrap()
+        .locked_at
+        .is_some());
+    assert!(matches!(
+        projects::unlock(&conn, &project_id, "Another project"),
+        Err(AppError::Invalid(_))
+    ));
+    let unlocked = projects::unlock(&conn, &project_id, " test PROJECT ").unwrap();
+    assert!(unlocked.locked_at.is_none());
+}
+
+#[test]
+fn mock_jobs_wait_until_their_delay_passes() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let scene = scene(&conn, &project_id, 1, &[]);
+    mock::save(
+        &conn,
+        &MockSettings {
+            delay_secs: 600,
+            failure_rate: 0.0,
+        },
+    )
+    .unwrap();
+
+    let mut log = Vec::new();
+    let request_id = mock::submit(&conn, &request(), &mut log).unwrap();
+    assert!(request_id.starts_with("mock-"));
+    let state = block_on(mock::poll(Path::new("ffmpeg"), &request_id, &mut log)).unwrap();
+    assert!(matches!(state, QueueState::Waiting));
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|e| e.status == Some(200)));
+    assert_eq!(
+        jobs::get_job(&conn, &queue_job(&conn, &scene.id))
+            .unwrap()
+            .status,
+        status::OFFLINE
+    );
+}
+
+#[test]
+fn an_offline_job_is_submitted_once_when_dispatch_and_flush_race() {
+    let db = Database::open_in_memory().unwrap();
+    let id = {
+        let conn = db.conn();
+        let project_id = project(&conn);
+        let scene = scene(&conn, &project_id, 1, &[]);
+        queue_job(&conn, &scene.id)
+    };
+
+    // One thread stands in for `queue_generation`, the other for
+    // `flush_deferred`; both see the job offline and try to submit it.
+    let barrier = std::sync::Barrier::new(2);
+    let submissions: Vec<String> = std::thread::scope(|s| {
+        let racers: Vec<_> = (0..2)
+            .map(|_| {
+                s.spawn(|| {
+                    barrier.wait();
+                    let conn = db.conn();
+                    let (_, _, request_json) = jobs::claim(&conn, &id).unwrap()?;
+                    let request = serde_json::from_str(&request_json).unwrap();
+                    Some(mock::submit(&conn, &request, &mut Vec::new()).unwrap())
+                })
+            })
+            .collect();
+        racers
+            .into_iter()
+            .filter_map(|r| r.join().unwrap())
+            .collect()
+    });
+    assert_eq!(submissions.len(), 1);
+
+    let conn = db.conn();
+    assert_eq!(jobs::get_job(&conn, &id).unwrap().status, status::PENDING);
+    assert!(jobs::claim(&conn, &id).unwrap().is_none());
+    assert_eq!(jobs::count_deferred(&conn).unwrap(), 0);
+}
+
+#[test]
+fn failed_mock_jobs_follow_the_retry_policy() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let scene = scene(&conn, &project_id, 1, &[]);
+    mock::save(
+        &conn,
+        &MockSettings {
+            delay_secs: 0,
+            failure_rate: 1.0,
+        },
+    )
+    .unwrap();
+    let job_id = queue_job(&conn, &scene.id);
+
+    let mut log = Vec::new();
+    let request_id = mock::submit(&conn, &request(), &mut log).unwrap();
+    let QueueState::Failed(error) =
+        block_on(mock::poll(Path::new("ffmpeg"), &request_id, &mut log)).unwrap()
+    else {
+        panic!("mock job with a failure rate of 1 didn't fail");
+    };
+
+    let outcome = failures::handle(&conn, &job_id, &error, None).unwrap();
+    let job = jobs::get_job(&conn, &job_id).unwrap();
+    match outcome {
+        Outcome::Retrying | Outcome::Paused => assert_eq!(job.status, status::OFFLINE),
+        Outcome::Failed => assert_eq!(job.status, status::FAILED),
+    }
+    assert!(!job.failure_kind.is_empty());
+}
+
+#[test]
+fn transient_failures_retry_then_fail() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let scene = scene(&conn, &project_id, 1, &[]);
+    let job_id = queue_job(&conn, &scene.id);
+
+    for _ in 0..3 {
+        let outcome = failures::handle(&conn, &job_id, "gateway timed out", Some(504)).unwrap();
+        assert_eq!(outcome, Outcome::Retrying);
+    }
+    let outcome = failures::handle(&conn, &job_id, "gateway timed out", Some(504)).unwrap();
+    assert_eq!(outcome, Outcome::Failed);
+
+    let job = jobs::get_job(&conn, &job_id).unwrap();
+    assert_eq!(job.status, status::FAILED);
+    assert_eq!(job.attempts, 3);
+}
+
+#[test]
+fn quota_failures_pause_the_queue() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let scene = scene(&conn, &project_id, 1, &[]);
+    let job_id = queue_job(&conn, &scene.id);
+
+    let outcome = failures::handle(&conn, &job_id, "out of credits", Some(402)).unwrap();
+    assert_eq!(outcome, Outcome::Paused);
+    assert!(failures::queue_paused(&conn).unwrap().is_some());
+    assert_eq!(
+        jobs::get_job(&conn, &job_id).unwrap().status,
+        status::OFFLINE
+    );
+
+    let summary = failures::summary(&conn, &project_id).unwrap();
+    assert_eq!(summary.counts.len(), 1);
+    assert_eq!(summary.counts[0].kind, "quota");
+
+    failures::resume(&conn).unwrap();
+    assert_eq!(failures::queue_paused(&conn).unwrap(), None);
+}
+
+#[test]
+fn content_policy_failures_flag_the_scene() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let scene = scene(&conn, &project_id, 1, &[]);
+    let job_id = queue_job(&conn, &scene.id);
+
+    let outcome =
+        failures::handle(&conn, &job_id, "prompt flagged by safety filter", Some(422)).unwrap();
+    assert_eq!(outcome, Outcome::Failed);
+    let summary = failures::summary(&conn, &project_id).unwrap();
+    assert_eq!(summary.needs_prompt_edit, [scene.id]);
+}
+
+#[test]
+fn editing_a_character_makes_their_generated_scenes_stale() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let generated = scene(&conn, &project_id, 1, &["Mara"]);
+    let untouched = scene(&conn, &project_id, 2, &["Mara"]);
+    queue_job(&conn, &generated.id);
+
+    let mara = characters::get_character(&conn, &generated.characters[0]).unwrap();
+    characters::save(
+        &conn,
+        &CharacterInput {
+            id: Some(mara.id.clone()),
+            project_id: project_id.clone(),
+            name: mara.name.clone(),
+            description: "Cropped silver hair".into(),
+            photo_data: String::new(),
+            expected_version: None,
+        },
+    )
+    .unwrap();
+
+    assert!(!scenes::get_scene(&conn, &generated.id)
+        .unwrap()
+        .stale_reason
+        .is_empty());
+    assert!(scenes::get_scene(&conn, &untouched.id)
+        .unwrap()
+        .stale_reason
+        .is_empty());
+
+    // A new take clears the flag.
+    queue_job(&conn, &generated.id);
+    assert!(scenes::get_scene(&conn, &generated.id)
+        .unwrap()
+        .stale_reason
+        .is_empty());
+}
+
+#[test]
+fn character_voices_save_unless_invalid_or_stale() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let mara_id = scene(&conn, &project_id, 1, &["Mara"]).characters[0].clone();
+    conn.execute(
+        "UPDATE characters SET voice_clone_status = 'pending' WHERE id = ?1",
+        [&mara_id],
+    )
+    .unwrap();
+    let mara = characters::get_character(&conn, &mara_id).unwrap();
+
+    let mut voice = mara.voice.clone();
+    voice.engine = "elevenlabs".into();
+    voice.voice_id = "voice-1".into();
+    voice.speed = 1.2;
+    voice.clone_status = "ready".into();
+    let saved = characters::set_voice(&conn, &mara_id, &voice, Some(mara.version)).unwrap();
+    assert_eq!(saved.voice.engine, "elevenlabs");
+    assert_eq!(saved.voice.voice_id, "voice-1");
+    assert_eq!(saved.voice.speed, 1.2);
+    assert_eq!(
+        saved.voice.clone_status, "pending",
+        "cloning owns the status"
+    );
+
+    voice.speed = 3.0;
+    assert!(matches!(
+        characters::set_voice(&conn, &mara_id, &voice, None),
+        Err(AppError::Validation(_))
+    ));
+    voice.speed = 1.0;
+    assert!(matches!(
+        characters::set_voice(&conn, &mara_id, &voice, Some(mara.version)),
+        Err(AppError::Conflict(_))
+    ));
+    assert!(matches!(
+        characters::set_voice(&conn, &uuid::Uuid::new_v4().to_string(), &voice, None),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn locked_projects_refuse_edits() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let existing = scene(&conn, &project_id, 1, &["Mara"]);
+    conn.execute(
+        "UPDATE projects SET locked_at = datetime('now') WHERE id = ?1",
+        [&project_id],
+    )
+    .unwrap();
+
+    let mut input = SceneInput {
+        id: Some(existing.id.clone()),
+        project_id: project_id.clone(),
+        scene_number: 1,
+        title: "Renamed".into(),
+        description: existing.description.clone(),
+        prompt: existing.prompt.clone(),
+        camera_angle: None,
+        lighting: None,
+        duration: 5,
+        dialog: String::new(),
+        characters: Vec::new(),
+        sort_order: 1,
+        location: existing.location.clone(),
+        expected_version: None,
+    };
+    assert!(matches!(
+        scenes::save(&conn, &input),
+        Err(AppError::Locked(_))
+    ));
+    input.id = None;
+    assert!(matches!(
+        scenes::save(&conn, &input),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        scenes::delete(&conn, &existing.id),
+        Err(AppError::Locked(_))
+    ));
+    assert_eq!(
+        scenes::list_for_project(&conn, &project_id).unwrap().len(),
+        1
+    );
+}
+
+#[test]
+fn exports_format_for_the_locale() {
+    let de = Locale::get("de_AT.UTF-8").unwrap();
+    assert_eq!(de.code(), "de");
+    assert_eq!(de.date("2026-03-01T18:04:05Z"), "01.03.2026");
+    assert_eq!(de.money(1234.5), "1.234,50 $");
+    assert_eq!(de.t("callSheet.cast"), "Besetzung");
+
+    let uk = Locale::get("en-gb").unwrap();
+    assert_eq!(uk.code(), "en-GB");
+    assert_eq!(uk.date("2026-03-01"), "01/03/2026");
+    // Regional catalogs fall back to their language for strings.
+    assert_eq!(uk.t("callSheet.cast"), "Cast");
+    assert_eq!(Locale::fallback().time("07:30"), "7:30 AM");
+    assert!(Locale::get("tlh").is_none());
+}
+
+#[test]
+fn ideas_become_scenes_and_leave_the_inbox() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 4, &[]);
+    let idea = inbox::append(&conn, "  Rooftop chase\nShe drops the key  ", "inbox").unwrap();
+
+    let converted = inbox::convert_to_scene(&conn, &idea.id, &project_id).unwrap();
+    assert_eq!(converted.scene_number, 5);
+    assert_eq!(converted.title, "Rooftop chase");
+    assert_eq!(converted.description, "Rooftop chase\nShe drops the key");
+    assert!(matches!(
+        inbox::get(&conn, &idea.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn ideas_are_listed_edited_and_deleted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let first = inbox::append(&conn, "Rooftop chase", "inbox").unwrap();
+    let second = inbox::append(&conn, "Diner at dawn", "quick-capture").unwrap();
+    let ids: Vec<_> = inbox::list(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|i| i.id)
+        .collect();
+    assert_eq!(ids, [second.id.clone(), first.id.clone()]);
+
+    let edited = inbox::update(&conn, &first.id, "  Rooftop chase at night ").unwrap();
+    assert_eq!(edited.text, "Rooftop chase at night");
+    assert!(matches!(
+        inbox::update(&conn, &first.id, "   "),
+        Err(AppError::Validation(_))
+    ));
+    inbox::delete(&conn, &second.id).unwrap();
+    assert_eq!(inbox::list(&conn).unwrap().len(), 1);
+    assert!(matches!(
+        inbox::update(&conn, &second.id, "Diner"),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        inbox::delete(&conn, &second.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn translations_follow_the_chosen_locale() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    settings::set(&conn, i18n::LOCALE_SETTING, "de").unwrap();
+    let locales = i18n::locales(&conn).unwrap();
+    assert_eq!(locales.current, "de");
+    assert_eq!(locales.available.len(), 5);
+    assert_eq!(i18n::translations(&conn, None).unwrap().locale, "de");
+
+    let french = i18n::translations(&conn, Some("fr")).unwrap();
+    assert_eq!(french.locale, "fr");
+    assert!(french.catalog.contains_key("callSheet.cast"));
+    assert!(matches!(
+        i18n::translations(&conn, Some("tlh")),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn spreadsheet_rows_import_through_a_suggested_mapping() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let csv = "Scene;Name;Action;Cast;Length\n\
+               3;Diner;\"Mara waits; the neon hums\";Mara, Joe;0:08\n\
+               ;Rooftop;Joe runs;Joe;6s\n\
+               x;Broken;;;\n";
+    let table = Table::parse(csv).unwrap();
+    let mapping = scene_import::suggest(&table.columns);
+    assert_eq!(mapping.fields["description"], "Action");
+
+    let report = scene_import::import(&conn, &project_id, &table, &mapping).unwrap();
+    assert_eq!(report.created.len(), 2);
+    assert_eq!(report.skipped.len(), 1);
+    let imported = scenes::list_for_project(&conn, &project_id).unwrap();
+    let summary: Vec<(i64, &str, i64, usize)> = imported
+        .iter()
+        .map(|s| {
+            (
+                s.scene_number,
+                s.title.as_str(),
+                s.duration,
+                s.characters.len(),
+            )
+        })
+        .collect();
+    assert_eq!(summary, [(3, "Diner", 8, 2), (4, "Rooftop", 6, 1)]);
+}
+
+#[test]
+fn import_previews_read_the_file_and_suggest_a_mapping() {
+    let path = scratch_dir().join("scenes.csv");
+    std::fs::write(&path, "Scene,Action\n1,Mara waits\n2,Joe runs\n").unwrap();
+    let preview = scene_import::preview(&path.to_string_lossy()).unwrap();
+    assert_eq!(preview.row_count, 2);
+    assert_eq!(preview.suggested.fields["description"], "Action");
+    assert!(matches!(
+        scene_import::preview(&scratch_dir().join("missing.csv").to_string_lossy()),
+        Err(AppError::Io(_))
+    ));
+}
+
+#[test]
+fn timecode_overlay_labels_each_scene_for_its_span() {
+    let filter = export_presets::timecode_filter(&[
+        (0.0, "Scene 1".into()),
+        (5.0, "Scene 2A".into()),
+        (9.0, "Scene O'Neil".into()),
+    ]);
+    assert!(filter.starts_with("drawtext=text='%{pts\\:hms}'"));
+    assert!(filter.contains("text='Scene 1'") && filter.contains("between(t,0.000,5.000)"));
+    assert!(filter.contains("between(t,5.000,9.000)"));
+    assert!(filter.contains("Scene O\u{2019}Neil") && filter.contains("gte(t,9.000)"));
+}
+
+#[test]
+fn events_serialize_as_their_listener_name_and_payload() {
+    let event = serde_json::to_value(AppEvent::OfflineQueueFlushing(3)).unwrap();
+    assert_eq!(event["event"], "offline-queue-flushing");
+    assert_eq!(event["payload"], 3);
+    let event = serde_json::to_value(AppEvent::ProviderAccountFailed(SignInFailed {
+        provider: "openai".to_string(),
+        error: "denied".into(),
+    }))
+    .unwrap();
+    assert_eq!(event["event"], "provider-account-failed");
+    assert_eq!(event["payload"]["provider"], "openai");
+}
+
+#[test]
+fn support_exports_scrub_keys_and_confidential_prompts() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let secret = scene(&conn, &project_id, 1, &[]);
+    conn.execute(
+        "UPDATE scenes SET prompt = 'The twist: Mara was the ghost all along',
+            prompt_confidential = 1 WHERE id = ?1",
+        [&secret.id],
+    )
+    .unwrap();
+    scene(&conn, &project_id, 2, &[]);
+    settings::set(&conn, "api_key.runway", "rw_live_0123456789").unwrap();
+
+    let redactor = Redactor::new(&conn).unwrap();
+    let line = redactor.line(
+        r#"{"level":"WARN","fields":{"message":"submit failed: The twist: Mara was the ghost all along","url":"https://api.example/v1?key=rw_live_0123456789","api_key":"abc"}}"#,
+    );
+    assert!(!line.contains("ghost") && line.contains("[confidential prompt]"));
+    assert!(!line.contains("rw_live") && !line.contains("\"abc\""));
+    let text = redactor.text("Wide shot of an empty diner, neon flickering");
+    assert_eq!(text, "Wide shot of an empty diner, neon flickering");
+}
+
+#[test]
+fn redaction_is_switched_per_kind_and_marked_per_scene() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let policy = redaction::set_policy(
+        &conn,
+        &RedactionPolicy {
+            api_keys: true,
+            confidential_prompts: false,
+            character_photos: true,
+        },
+    )
+    .unwrap();
+    assert!(!policy.confidential_prompts);
+    assert!(!redaction::policy(&conn).unwrap().confidential_prompts);
+
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    assert!(
+        redaction::mark_prompt_confidential(&conn, &shot.id, true)
+            .unwrap()
+            .prompt_confidential
+    );
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        redaction::mark_prompt_confidential(&conn, &shot.id, false),
+        Err(AppError::Locked(_))
+    ));
+}
+
+fn title_change(project_id: &str, scene_id: &str, seq: i64) -> Change {
+    Change {
+        origin_device: "guest-device".into(),
+        origin_seq: seq,
+        lamport: 1_000,
+        entity_type: "scene".into(),
+        entity_id: scene_id.to_string(),
+        project_id: project_id.to_string(),
+        op: "update".into(),
+        field: "title".into(),
+        old_value: Some("\"Scene 1\"".into()),
+        new_value: Some("\"Hijacked\"".into()),
+    }
+}
+
+#[test]
+fn synced_changes_stay_inside_their_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let shared = project(&conn);
+    let private = project(&conn);
+    let target = scene(&conn, &private, 1, &[]);
+    let changeset = |changes| Changeset {
+        format: sync::CHANGESET_FORMAT,
+        device_id: "guest-device".into(),
+        project_id: shared.clone(),
+        clock: 1_000,
+        changes,
+    };
+
+    // Claiming the session's project for another project's row.
+    let disguised = changeset(vec![title_change(&shared, &target.id, 1)]);
+    assert!(matches!(
+        sync::merge_changes(&conn, &disguised, Merge::LastWriter),
+        Err(AppError::Forbidden(_))
+    ));
+    // Naming the other project outright.
+    let open = changeset(vec![title_change(&private, &target.id, 2)]);
+    assert!(matches!(
+        sync::merge_changes(&conn, &open, Merge::LastWriter),
+        Err(AppError::Forbidden(_))
+    ));
+    assert_eq!(
+        scenes::get_scene(&conn, &target.id).unwrap().title,
+        "Scene 1"
+    );
+
+    // The project's own rows still merge, unless it is locked.
+    let own = scene(&conn, &shared, 1, &[]);
+    conn.execute(
+        "UPDATE projects SET locked_at = datetime('now') WHERE id = ?1",
+        [&shared],
+    )
+    .unwrap();
+    let edit = changeset(vec![title_change(&shared, &own.id, 3)]);
+    assert!(matches!(
+        sync::merge_changes(&conn, &edit, Merge::LastWriter),
+        Err(AppError::Locked(_))
+    ));
+    conn.execute(
+        "UPDATE projects SET locked_at = NULL WHERE id = ?1",
+        [&shared],
+    )
+    .unwrap();
+    let report = sync::merge_changes(&conn, &edit, Merge::LastWriter).unwrap();
+    assert_eq!(report.applied, 1);
+    assert_eq!(scenes::get_scene(&conn, &own.id).unwrap().title, "Hijacked");
+}
+
+#[test]
+fn conflicting_edits_wait_for_the_user_to_resolve_them() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    conn.execute(
+        "UPDATE scenes SET title = 'Local cut' WHERE id = ?1",
+        [&shot.id],
+    )
+    .unwrap();
+
+    let dir = scratch_dir();
+    let own = dir.join("own.json");
+    assert!(sync::write_changes(&conn, &project_id, &own.to_string_lossy(), 0).unwrap() > 0);
+    assert!(matches!(
+        sync::read_changes(&conn, &own.to_string_lossy()),
+        Err(AppError::Invalid(_))
+    ));
+
+    let remote = dir.join("remote.json");
+    let changeset = Changeset {
+        format: sync::CHANGESET_FORMAT,
+        device_id: "guest-device".into(),
+        project_id: project_id.clone(),
+        clock: 1_000,
+        changes: vec![title_change(&project_id, &shot.id, 1)],
+    };
+    std::fs::write(&remote, serde_json::to_string(&changeset).unwrap()).unwrap();
+    let report = sync::read_changes(&conn, &remote.to_string_lossy()).unwrap();
+    assert_eq!(report.conflicts, 1);
+    assert_eq!(sync::sync_device(&conn).unwrap().open_conflicts, 1);
+
+    let open = sync::conflicts(&conn, Some(&project_id)).unwrap();
+    assert_eq!(open[0].field, "title");
+    assert!(matches!(
+        sync::resolve(
+            &conn,
+            open[0].id,
+            Resolution::Custom,
+            Some(serde_json::json!(["not", "a", "scalar"]))
+        ),
+        Err(AppError::Invalid(_))
+    ));
+    sync::resolve(
+        &conn,
+        open[0].id,
+        Resolution::Custom,
+        Some(serde_json::json!("Final cut")),
+    )
+    .unwrap();
+    assert_eq!(
+        scenes::get_scene(&conn, &shot.id).unwrap().title,
+        "Final cut"
+    );
+    assert!(sync::conflicts(&conn, None).unwrap().is_empty());
+    assert!(matches!(
+        sync::resolve(&conn, open[0].id, Resolution::Local, None),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn command_bindings_import_only_exported_types() {
+    // ts-rs writes the type files from the library's tests, which cargo
+    // runs before these.
+    let dir = Path::new(env!("TS_RS_EXPORT_DIR"));
+    let commands = std::fs::read_to_string(dir.join("commands.ts")).unwrap();
+    let unresolved: Vec<&str> = commands
+        .lines()
+        .filter_map(|line| line.strip_prefix("import type ")?.split("from \"./").nth(1))
+        .filter_map(|name| name.strip_suffix("\";"))
+        .filter(|name| !dir.join(format!("{}.ts", name)).exists())
+        .collect();
+    assert!(unresolved.is_empty(), "no bindings for {:?}", unresolved);
+}
+
+/// Hex text that deflate can't shrink much, `len` characters long.
+fn noise(len: usize) -> String {
+    let mut x: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            char::from_digit((x % 16) as u32, 16).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn encrypted_bundles_round_trip_and_reject_tampering() {
+    let db = Database::open_in_memory().unwrap();
+    let dialog = noise(3 << 20);
+    let project_id = {
+        let conn = db.conn();
+        let project_id = project(&conn);
+        let cast = scene(&conn, &project_id, 1, &["Mara"]);
+        // Big enough that the bundle spans several chunks.
+        conn.execute(
+            "UPDATE scenes SET dialog = ?2 WHERE id = ?1",
+            rusqlite::params![cast.id, dialog],
+        )
+        .unwrap();
+        project_id
+    };
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).unwrap();
+    let sealed = dir.join("handoff.aidcx");
+    let passphrase = "correct horse battery staple";
+    handoff::export(&db, &project_id, passphrase, &sealed).unwrap();
+
+    let other = Database::open_in_memory().unwrap();
+    let refused = |path: &Path, passphrase: &str| {
+        matches!(
+            handoff::import(&other, path, passphrase),
+            Err(AppError::Invalid(_))
+        )
+    };
+    assert!(refused(&sealed, "wrong horse battery staple"));
+    let bytes = std::fs::read(&sealed).unwrap();
+    let mut flipped = bytes.clone();
+    let middle = flipped.len() / 2;
+    flipped[middle] ^= 1;
+    // The age header ends with its MAC line, followed by a 16-byte nonce
+    // and the payload in 64 KiB chunks, each with a 16-byte tag. Cut it
+    // after the second chunk, so whole chunks are missing from the end.
+    let mac_line = bytes.windows(5).position(|w| w == b"\n--- ").unwrap() + 1;
+    let header = mac_line + bytes[mac_line..].iter().position(|&b| b == b'\n').unwrap() + 1;
+    let truncated = bytes[..header + 16 + 2 * ((64 << 10) + 16)].to_vec();
+    let garbage = noise(4096).into_bytes();
+    for damaged in [flipped, truncated, garbage] {
+        let path = dir.join("damaged.aidcx");
+        std::fs::write(&path, damaged).unwrap();
+        assert!(refused(&path, passphrase));
+    }
+    let projects: i64 = other
+        .conn()
+        .query_row("SELECT COUNT(*) FROM projects", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(projects, 0);
+
+    let imported = handoff::import(&other, &sealed, passphrase).unwrap();
+    assert_eq!(imported.id, project_id);
+    let conn = other.conn();
+    let scenes = scenes::list_for_project(&conn, &project_id).unwrap();
+    assert_eq!(scenes.len(), 1);
+    // Not assert_eq: a failure would print megabytes.
+    assert!(scenes[0].dialog == dialog);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,56 @@
+//! Previewing a migration on a copy and picking a backup to roll back to.
+
+use crate::scratch_dir;
+use ai_directors_chair::db;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::migration_assistant;
+use rusqlite::Connection;
+
+#[test]
+fn old_databases_preview_their_migration_and_keep_a_backup() {
+    let path = scratch_dir().join("aidc.db");
+    let conn = Connection::open(&path).unwrap();
+    db::migrate_to(&conn, 30).unwrap();
+    conn.execute("INSERT INTO projects (id, name) VALUES ('p1', 'Diner')", [])
+        .unwrap();
+    let backup = migration_assistant::backup_before_migrating(&conn, &path)
+        .unwrap()
+        .unwrap();
+    drop(conn);
+
+    let preview = migration_assistant::preview(&path).unwrap();
+    assert_eq!(preview.from_version, 30);
+    assert_eq!(preview.to_version, db::schema_version());
+    assert!(preview.pending > 0);
+    assert_eq!(preview.error, "");
+    assert!(!preview.tables_added.is_empty());
+
+    let backups = migration_assistant::backups(&path).unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].version, 30);
+    let chosen = migration_assistant::verified_backup(&path, &backup.to_string_lossy()).unwrap();
+    assert_eq!(chosen.version, 30);
+}
+
+#[test]
+fn only_existing_databases_and_their_own_backups_are_used() {
+    let dir = scratch_dir();
+    assert!(matches!(
+        migration_assistant::preview(&dir.join("missing.db")),
+        Err(AppError::NotFound(_))
+    ));
+
+    let path = dir.join("aidc.db");
+    let stray = dir.join("other.db.pre-v30.bak");
+    std::fs::write(&stray, b"").unwrap();
+    assert!(matches!(
+        migration_assistant::verified_backup(&path, &stray.to_string_lossy()),
+        Err(AppError::NotFound(_))
+    ));
+    let broken = dir.join("aidc.db.pre-v30.bak");
+    std::fs::write(&broken, b"not a database").unwrap();
+    assert!(matches!(
+        migration_assistant::verified_backup(&path, &broken.to_string_lossy()),
+        Err(AppError::Database(_))
+    ));
+}
@@ -0,0 +1,141 @@
+//! Migrations run against rows written by the schema before them.
+
+use ai_directors_chair::db;
+use rusqlite::{params, Connection};
+
+/// A database at schema `version`, as an older release left it.
+fn legacy_database(version: usize) -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+    db::migrate_to(&conn, version).unwrap();
+    conn
+}
+
+fn cast(conn: &Connection, scene_id: &str) -> Vec<(String, String, String, i64)> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT sc.id, sc.character_id, c.name, sc.position
+             FROM scene_characters sc JOIN characters c ON c.id = sc.character_id
+             WHERE sc.scene_id = ?1 ORDER BY sc.position",
+        )
+        .unwrap();
+    stmt.query_map([scene_id], |r| {
+        Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?))
+    })
+    .unwrap()
+    .collect::<rusqlite::Result<_>>()
+    .unwrap()
+}
+
+fn character_id(conn: &Connection, project_id: &str, name: &str) -> String {
+    conn.query_row(
+        "SELECT id FROM characters WHERE project_id = ?1 AND lower(name) = lower(?2)",
+        [project_id, name],
+        |r| r.get(0),
+    )
+    .unwrap()
+}
+
+#[test]
+fn scene_casts_move_from_characters_json_to_scene_characters() {
+    let conn = legacy_database(30);
+    for (project_id, name) in [("p1", "Diner"), ("p2", "Harbour")] {
+        conn.execute(
+            "INSERT INTO projects (id, name) VALUES (?1, ?2)",
+            [project_id, name],
+        )
+        .unwrap();
+    }
+    for (id, project_id, name) in [
+        ("c-mara", "p1", "Mara"),
+        ("c-joe", "p1", "Joe"),
+        ("c-mara-2", "p2", "Mara"),
+    ] {
+        conn.execute(
+            "INSERT INTO characters (id, project_id, name) VALUES (?1, ?2, ?3)",
+            [id, project_id, name],
+        )
+        .unwrap();
+    }
+    for (number, (id, project_id, characters_json)) in [
+        // Names match whatever their case and padding; unknown ones are new.
+        ("s1", "p1", r#"["mara", " JOE ", "Dina"]"#),
+        // The same unknown name again, a character id, and entries to skip.
+        ("s2", "p1", r#"["DINA", "c-mara", "", "  ", 42, null]"#),
+        ("s3", "p1", "not json"),
+        ("s4", "p1", "[]"),
+        ("s5", "p2", r#"["Mara", "dina"]"#),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        conn.execute(
+            "INSERT INTO scenes (id, project_id, scene_number, characters_json)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![id, project_id, number as i64 + 1, characters_json],
+        )
+        .unwrap();
+    }
+
+    db::migrate(&conn).unwrap();
+
+    let dina = character_id(&conn, "p1", "dina");
+    let row = |scene: &str, character: &str, name: &str, position| {
+        (
+            format!("{}:{}", scene, character),
+            character.to_string(),
+            name.to_string(),
+            position,
+        )
+    };
+    let dina_name = cast(&conn, "s1")[2].2.clone();
+    assert!(dina_name.eq_ignore_ascii_case("dina"), "{}", dina_name);
+    assert_eq!(
+        cast(&conn, "s1"),
+        [
+            row("s1", "c-mara", "Mara", 0),
+            row("s1", "c-joe", "Joe", 1),
+            row("s1", &dina, &dina_name, 2),
+        ]
+    );
+    assert_eq!(
+        cast(&conn, "s2"),
+        [
+            row("s2", &dina, &dina_name, 0),
+            row("s2", "c-mara", "Mara", 1)
+        ]
+    );
+    assert!(cast(&conn, "s3").is_empty());
+    assert!(cast(&conn, "s4").is_empty());
+
+    // Unknown names become one character per project, never shared.
+    let names = |project_id: &str| -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM characters WHERE project_id = ?1 ORDER BY lower(name)")
+            .unwrap();
+        stmt.query_map([project_id], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    };
+    assert_eq!(names("p1"), [dina_name.as_str(), "Joe", "Mara"]);
+    assert_eq!(names("p2"), ["dina", "Mara"]);
+    let harbour_dina = character_id(&conn, "p2", "dina");
+    assert_ne!(harbour_dina, dina);
+    assert_eq!(
+        cast(&conn, "s5"),
+        [
+            row("s5", "c-mara-2", "Mara", 0),
+            row("s5", &harbour_dina, "dina", 1)
+        ]
+    );
+
+    let has_column: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('scenes') WHERE name = 'characters_json'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(has_column, 0);
+}
@@ -0,0 +1,48 @@
+//! Mirroring a project into a folder of JSON files.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::mirror;
+
+#[test]
+fn mirrored_projects_are_written_straight_away() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    let dir = scratch_dir();
+
+    let mirror = mirror::set(&conn, &project_id, Some(&dir.to_string_lossy()))
+        .unwrap()
+        .unwrap();
+    assert!(mirror.mirrored_at.is_some());
+    assert!(dir.join("project.json").exists());
+    assert!(dir
+        .join("scenes")
+        .join(format!("{}.json", shot.id))
+        .exists());
+    assert!(dir
+        .join("characters")
+        .join(format!("{}.json", shot.characters[0]))
+        .exists());
+
+    assert!(mirror::set(&conn, &project_id, None).unwrap().is_none());
+    assert!(mirror::get(&conn, &project_id).unwrap().is_none());
+    assert!(dir.join("project.json").exists());
+}
+
+#[test]
+fn mirrors_need_an_absolute_folder_and_a_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(matches!(
+        mirror::set(&conn, &project_id, Some("relative/folder")),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        mirror::set(&conn, "missing", None),
+        Err(AppError::NotFound(_))
+    ));
+}
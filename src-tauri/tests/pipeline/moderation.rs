@@ -0,0 +1,62 @@
+//! Prompt checks before scenes go to a provider.
+
+use crate::{project, scene};
+use ai_directors_chair::confidential;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::moderation::{self, OPENAI_KEY_SETTING};
+use ai_directors_chair::settings;
+use tauri::async_runtime::block_on;
+
+#[test]
+fn local_rules_flag_scenes_by_prompt_or_description() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let calm = scene(&conn, &project_id, 1, &[]);
+    let grim = scene(&conn, &project_id, 2, &[]);
+    conn.execute(
+        "UPDATE scenes SET prompt = '', description = 'Gore on the diner floor' WHERE id = ?1",
+        [&grim.id],
+    )
+    .unwrap();
+
+    let config = moderation::load_config(&conn, Some(&project_id)).unwrap();
+    let texts = moderation::scene_texts(&conn, &project_id, None).unwrap();
+    assert_eq!(
+        texts[1],
+        (grim.id.clone(), "Gore on the diner floor".into())
+    );
+    let results = block_on(moderation::check_scenes(&config, texts)).unwrap();
+    assert_eq!(results[0].scene_id.as_deref(), Some(calm.id.as_str()));
+    assert!(!results[0].flagged);
+    assert_eq!(results[1].categories, ["violence/graphic"]);
+    assert_eq!(results[1].engine, "local");
+
+    let only_calm = moderation::scene_texts(&conn, &project_id, Some(&[calm.id])).unwrap();
+    assert_eq!(only_calm.len(), 1);
+    assert!(block_on(moderation::check_scenes(&config, Vec::new()))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn openai_checks_need_a_key_unless_the_project_is_confidential() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    settings::set(&conn, "moderation.engine", "openai").unwrap();
+    settings::set(&conn, OPENAI_KEY_SETTING, "").unwrap();
+
+    let config = moderation::load_config(&conn, None).unwrap();
+    assert!(matches!(
+        block_on(moderation::check_prompt(&config, "A nude statue".into())),
+        Err(AppError::Invalid(_))
+    ));
+
+    confidential::set(&conn, &project_id, true).unwrap();
+    let config = moderation::load_config(&conn, Some(&project_id)).unwrap();
+    let result = block_on(moderation::check_prompt(&config, "A nude statue".into())).unwrap();
+    assert_eq!(result.engine, "local");
+    assert_eq!(result.categories, ["sexual"]);
+}
@@ -0,0 +1,133 @@
+//! Moodboards, their items, and fetching references from the web.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::moodboards::{self, MoodboardItemInput};
+use ai_directors_chair::projects;
+use ai_directors_chair::references;
+
+fn link(url: &str) -> MoodboardItemInput {
+    MoodboardItemInput {
+        source_path: None,
+        url: Some(url.into()),
+        generated: false,
+        caption: String::new(),
+    }
+}
+
+#[test]
+fn boards_hold_ordered_items_that_can_condition_scenes() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let media = scratch_dir();
+    let project_id = project(&conn);
+    let board = moodboards::create(&conn, &project_id, "Diner", "").unwrap();
+    let board = moodboards::update(&conn, &board.id, "Night diner", "Neon", None).unwrap();
+    assert_eq!(
+        moodboards::list(&conn, &project_id).unwrap()[0].name,
+        "Night diner"
+    );
+
+    let photo = media.join("source.png");
+    std::fs::write(&photo, b"png").unwrap();
+    let imported = moodboards::add_item(
+        &conn,
+        &media,
+        &board.id,
+        &MoodboardItemInput {
+            source_path: Some(photo.to_string_lossy().into_owned()),
+            url: None,
+            generated: false,
+            caption: String::new(),
+        },
+    )
+    .unwrap();
+    assert_eq!(imported.source, "imported");
+    assert!(imported.image.starts_with(&*media.to_string_lossy()));
+    let remote =
+        moodboards::add_item(&conn, &media, &board.id, &link("https://img.example/a.jpg")).unwrap();
+    moodboards::reorder_items(&conn, &board.id, std::slice::from_ref(&remote.id)).unwrap();
+    let order: Vec<String> = moodboards::items(&conn, &board.id)
+        .unwrap()
+        .into_iter()
+        .map(|i| i.id)
+        .collect();
+    assert_eq!(order, [remote.id.clone(), imported.id.clone()]);
+
+    moodboards::update_item(&conn, &remote.id, "Red booths", true, None).unwrap();
+    assert_eq!(
+        moodboards::prompt_references(&conn, &project_id).unwrap(),
+        ["Red booths"]
+    );
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let conditioned = moodboards::set_scene_conditioning(&conn, &remote.id, &shot.id).unwrap();
+    assert_eq!(conditioned.conditioning_image, "https://img.example/a.jpg");
+
+    moodboards::delete_item(&conn, &imported.id).unwrap();
+    assert!(!std::path::Path::new(&imported.image).exists());
+    moodboards::delete(&conn, &board.id).unwrap();
+    assert!(moodboards::list(&conn, &project_id).unwrap().is_empty());
+}
+
+#[test]
+fn items_need_an_image_and_an_unlocked_board() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let media = scratch_dir();
+    let project_id = project(&conn);
+    let board = moodboards::create(&conn, &project_id, "Diner", "").unwrap();
+    assert!(matches!(
+        moodboards::add_item(&conn, &media, &board.id, &link("ftp://img.example/a.jpg")),
+        Err(AppError::Invalid(_))
+    ));
+    let elsewhere = scene(&conn, &project(&conn), 1, &[]);
+    let item =
+        moodboards::add_item(&conn, &media, &board.id, &link("https://img.example/a.jpg")).unwrap();
+    assert!(matches!(
+        moodboards::set_scene_conditioning(&conn, &item.id, &elsewhere.id),
+        Err(AppError::Invalid(_))
+    ));
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        moodboards::create(&conn, &project_id, "Kitchen", ""),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        moodboards::delete_item(&conn, &item.id),
+        Err(AppError::Locked(_))
+    ));
+}
+
+#[test]
+fn fetched_references_are_stored_as_web_items() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let media = scratch_dir();
+    let board = moodboards::create(&conn, &project(&conn), "Diner", "").unwrap();
+    assert!(matches!(
+        references::parse_link(&conn, &board.id, "file:///etc/passwd"),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        references::parse_link(&conn, "missing", "https://example.com"),
+        Err(AppError::NotFound(_))
+    ));
+
+    let page = references::parse_link(&conn, &board.id, " https://example.com/diner ").unwrap();
+    let item = references::store(
+        &conn,
+        &media,
+        &board.id,
+        &page,
+        b"png",
+        "image/png; charset=binary",
+        "Night diner",
+    )
+    .unwrap();
+    assert_eq!(item.source, "web");
+    assert_eq!(item.source_url, "https://example.com/diner");
+    assert!(item.image.ends_with(".png"));
+    assert_eq!(std::fs::read(&item.image).unwrap(), b"png");
+}
@@ -0,0 +1,35 @@
+//! Offline mode and the deferred-job count.
+
+use crate::{project, queue_job, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::jobs::status;
+use ai_directors_chair::network::{self, NetworkMonitor};
+use rusqlite::Connection;
+
+#[test]
+fn offline_mode_is_remembered_and_counts_deferred_jobs() {
+    let db = Database::open_in_memory().unwrap();
+    let monitor = NetworkMonitor::new(false);
+    {
+        let conn = db.conn();
+        let project_id = project(&conn);
+        let shot = scene(&conn, &project_id, 1, &[]);
+        let job = queue_job(&conn, &shot.id);
+        conn.execute(
+            "UPDATE video_jobs SET status = ?2 WHERE id = ?1",
+            [job.as_str(), status::OFFLINE],
+        )
+        .unwrap();
+        monitor.set_forced_offline(&conn, true).unwrap();
+        let status = monitor.status(&conn).unwrap();
+        assert!(status.forced_offline);
+        assert_eq!(status.deferred_jobs, 1);
+    }
+    assert!(network::load_forced_offline(&db));
+}
+
+#[test]
+fn status_needs_the_jobs_table() {
+    let bare = Connection::open_in_memory().unwrap();
+    assert!(NetworkMonitor::new(false).status(&bare).is_err());
+}
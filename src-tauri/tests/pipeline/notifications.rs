@@ -0,0 +1,78 @@
+//! Webhooks and the render-finished notification.
+
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::notifications::{self, NotifyEvent, WebhookInput};
+
+fn input(url: Option<&str>) -> WebhookInput {
+    WebhookInput {
+        id: None,
+        name: "Team channel".into(),
+        kind: "slack".into(),
+        events: vec![NotifyEvent::JobCompleted],
+        template: String::new(),
+        enabled: true,
+        url: url.map(str::to_string),
+    }
+}
+
+#[test]
+fn webhooks_keep_their_url_secret_and_send_a_sample() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let hook = notifications::save(&conn, &input(Some("https://hooks.example/abc"))).unwrap();
+    assert!(hook.has_url);
+    assert_eq!(notifications::list(&conn).unwrap().len(), 1);
+
+    let (url, body) = notifications::test_delivery(&conn, &hook.id).unwrap();
+    assert_eq!(url, "https://hooks.example/abc");
+    assert_eq!(
+        body["text"],
+        "Test project: scene 1 take from kling completed"
+    );
+
+    notifications::delete(&conn, &hook.id).unwrap();
+    assert!(matches!(
+        notifications::delete(&conn, &hook.id),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn webhooks_need_a_known_kind_and_an_http_url() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        notifications::save(&conn, &input(None)),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        notifications::save(&conn, &input(Some("ftp://hooks.example"))),
+        Err(AppError::Invalid(_))
+    ));
+    let mut teams = input(Some("https://hooks.example/abc"));
+    teams.kind = "teams".into();
+    assert!(matches!(
+        notifications::save(&conn, &teams),
+        Err(AppError::Invalid(_))
+    ));
+}
+
+#[test]
+fn finished_renders_are_recorded_for_their_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let (render, notification) =
+        notifications::render_finished(&conn, &project_id, "/renders/cut.mp4").unwrap();
+    assert_eq!(render.path, "/renders/cut.mp4");
+    assert_eq!(
+        notification.render(""),
+        "Test project: render finished (/renders/cut.mp4)"
+    );
+    assert!(matches!(
+        notifications::render_finished(&conn, "missing", "/renders/cut.mp4"),
+        Err(AppError::NotFound(_))
+    ));
+}
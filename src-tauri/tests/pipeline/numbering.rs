@@ -0,0 +1,169 @@
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::numbering::{self, RenumberStrategy};
+use ai_directors_chair::{projects, scenes};
+use rusqlite::{params, Connection};
+
+/// A project whose scenes, in board order, are labelled `labels`.
+fn board(conn: &Connection, labels: &[&str]) -> String {
+    let project_id = project(conn);
+    for (i, label) in labels.iter().enumerate() {
+        let split = label
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(label.len());
+        let (number, suffix) = label.split_at(split);
+        let created = scene(conn, &project_id, i as i64 + 1, &[]);
+        conn.execute(
+            "UPDATE scenes SET scene_number = ?2, scene_suffix = ?3, order_key = ?4 WHERE id = ?1",
+            params![
+                created.id,
+                number.parse::<i64>().unwrap(),
+                suffix,
+                format!("{:04}", i)
+            ],
+        )
+        .unwrap();
+    }
+    project_id
+}
+
+fn labels(conn: &Connection, project_id: &str) -> Vec<String> {
+    scenes::list_for_project(conn, project_id)
+        .unwrap()
+        .iter()
+        .map(|s| numbering::label(s.scene_number, &s.scene_suffix))
+        .collect()
+}
+
+#[test]
+fn renumbering_follows_board_order() {
+    let twenty_seven_inserted: Vec<&str> = std::iter::once("1")
+        .chain(std::iter::repeat_n("9A", 27))
+        .chain(std::iter::once("2"))
+        .collect();
+    let mut lettered = vec!["1"];
+    let letters: Vec<String> = (b'A'..=b'Z')
+        .map(|c| format!("1{}", c as char))
+        .chain(std::iter::once("1AA".to_string()))
+        .collect();
+    lettered.extend(letters.iter().map(String::as_str));
+    lettered.push("2");
+
+    use RenumberStrategy::{InsertStyle, Sequential};
+    let cases: Vec<(&str, RenumberStrategy, Vec<&str>, Vec<&str>)> = vec![
+        (
+            "in order",
+            InsertStyle,
+            vec!["1", "2", "3"],
+            vec!["1", "2", "3"],
+        ),
+        (
+            "gaps are kept",
+            InsertStyle,
+            vec!["1", "5", "9"],
+            vec!["1", "5", "9"],
+        ),
+        (
+            "existing letters stay",
+            InsertStyle,
+            vec!["1", "1A", "1B", "2"],
+            vec!["1", "1A", "1B", "2"],
+        ),
+        (
+            "a stray letter is re-lettered",
+            InsertStyle,
+            vec!["1", "7C", "2"],
+            vec!["1", "1A", "2"],
+        ),
+        (
+            "duplicate numbers keep the later one",
+            InsertStyle,
+            vec!["1", "2", "2", "3"],
+            vec!["1", "1A", "2", "3"],
+        ),
+        (
+            "a moved scene is lettered after its new neighbour",
+            InsertStyle,
+            vec!["1", "3", "2", "4"],
+            vec!["1", "1A", "2", "4"],
+        ),
+        (
+            "scenes before the first kept number letter from 0",
+            InsertStyle,
+            vec!["5", "6A", "1", "2"],
+            vec!["0A", "0B", "1", "2"],
+        ),
+        (
+            "letters run on past Z",
+            InsertStyle,
+            twenty_seven_inserted.clone(),
+            lettered,
+        ),
+        (
+            "sequential drops letters and gaps",
+            Sequential,
+            vec!["1", "1A", "5", "2", "2"],
+            vec!["1", "2", "3", "4", "5"],
+        ),
+    ];
+
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    for (name, strategy, before, after) in cases {
+        let project_id = board(&conn, &before);
+        let mapping = numbering::renumber(&conn, &project_id, strategy).unwrap();
+        assert_eq!(labels(&conn, &project_id), after, "{}", name);
+        let changed = before.iter().zip(&after).filter(|(b, a)| b != a).count();
+        assert_eq!(mapping.len(), changed, "{}", name);
+        for m in &mapping {
+            assert_ne!(m.old_label, m.new_label, "{}", name);
+        }
+        // Renumbering again changes nothing.
+        assert!(
+            numbering::renumber(&conn, &project_id, strategy)
+                .unwrap()
+                .is_empty(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn renumbering_is_recorded_in_the_activity_feed() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = board(&conn, &["1", "3", "2"]);
+    let mapping = numbering::renumber(&conn, &project_id, RenumberStrategy::Sequential).unwrap();
+    assert_eq!(mapping.len(), 2);
+    assert_eq!(
+        (mapping[0].old_label.as_str(), mapping[0].new_label.as_str()),
+        ("3", "2")
+    );
+    let summary: String = conn
+        .query_row(
+            "SELECT summary FROM activity_log WHERE entity_id = ?1 AND action = 'renumbered'",
+            [&project_id],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(summary, "Renumbered 2 scenes (sequential)");
+}
+
+#[test]
+fn a_locked_project_keeps_its_numbers() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = board(&conn, &["1", "3", "2"]);
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        numbering::renumber_project(&conn, &project_id, RenumberStrategy::Sequential),
+        Err(AppError::Locked(_))
+    ));
+    assert_eq!(labels(&conn, &project_id), ["1", "3", "2"]);
+
+    let other = board(&conn, &["1", "3", "2"]);
+    let mapping = numbering::renumber_project(&conn, &other, RenumberStrategy::Sequential).unwrap();
+    assert_eq!(mapping.len(), 2);
+}
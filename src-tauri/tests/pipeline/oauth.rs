@@ -0,0 +1,27 @@
+//! Which AI providers can sign in with OAuth.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::oauth;
+use ai_directors_chair::settings;
+
+#[test]
+fn providers_sign_in_once_a_client_is_configured() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    if option_env!("AIDC_GOOGLE_CLIENT_ID").is_none() {
+        let accounts = oauth::accounts(&conn).unwrap();
+        assert_eq!(accounts[0].provider, "gemini");
+        assert!(!accounts[0].available);
+        assert!(!accounts[0].connected);
+    }
+
+    settings::set(&conn, "oauth.gemini.client_id", "client-123").unwrap();
+    let client = oauth::provider_client(&conn, "gemini").unwrap();
+    assert_eq!(client.account, "provider.gemini");
+    assert_eq!(client.client_id, "client-123");
+    assert!(matches!(
+        oauth::provider_client(&conn, "kling"),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,40 @@
+//! Moving scenes on the board.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::ordering;
+use ai_directors_chair::scenes;
+
+#[test]
+fn a_moved_scene_lands_after_its_new_neighbour() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let [first, second, third] = [1, 2, 3].map(|n| scene(&conn, &project_id, n, &[]).id);
+
+    ordering::move_after(&conn, &third, Some(&first)).unwrap();
+    ordering::move_after(&conn, &second, None).unwrap();
+    let order: Vec<String> = scenes::list_for_project(&conn, &project_id)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    assert_eq!(order, [second, first, third]);
+}
+
+#[test]
+fn scenes_only_move_within_their_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let here = scene(&conn, &project(&conn), 1, &[]);
+    let there = scene(&conn, &project(&conn), 1, &[]);
+    assert!(matches!(
+        ordering::move_after(&conn, &here.id, Some(&there.id)),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        ordering::move_after(&conn, "missing", None),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,32 @@
+//! Pitch deck PDFs.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::pitch_deck;
+use tauri::async_runtime::block_on;
+
+#[test]
+fn a_pitch_deck_is_written_as_a_pdf() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    let path = scratch_dir().join("deck.pdf");
+
+    let source =
+        pitch_deck::load(&conn, &project_id, Some(std::slice::from_ref(&shot.id))).unwrap();
+    let written = block_on(pitch_deck::write(source, &path.to_string_lossy())).unwrap();
+    assert!(written.ends_with("deck.pdf"));
+    assert!(std::fs::read(&written).unwrap().starts_with(b"%PDF"));
+}
+
+#[test]
+fn pitch_decks_need_a_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        pitch_deck::load(&conn, "missing", None),
+        Err(AppError::NotFound(_))
+    ));
+}
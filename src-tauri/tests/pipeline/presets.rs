@@ -0,0 +1,73 @@
+//! Style presets and a project's default.
+
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::presets::{self, StylePresetInput};
+use ai_directors_chair::projects;
+
+fn input(name: &str) -> StylePresetInput {
+    StylePresetInput {
+        name: name.into(),
+        description: String::new(),
+        guidance: "Muted colours, long lenses".into(),
+        keywords: "noir".into(),
+        negative_prompt: String::new(),
+    }
+}
+
+#[test]
+fn a_deleted_preset_stops_being_the_project_default() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let preset = presets::create(&conn, &input("Night")).unwrap();
+    let preset = presets::update(&conn, &preset.id, &input("Night city")).unwrap();
+    assert!(presets::list(&conn)
+        .unwrap()
+        .iter()
+        .any(|p| p.name == "Night city"));
+
+    presets::set_project_default(&conn, &project_id, Some(&preset.id), None).unwrap();
+    assert_eq!(
+        presets::project_default(&conn, &project_id)
+            .unwrap()
+            .unwrap()
+            .id,
+        preset.id
+    );
+    presets::delete(&conn, &preset.id).unwrap();
+    assert!(presets::project_default(&conn, &project_id)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn presets_need_a_name_and_built_ins_stay() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        presets::create(&conn, &input(" ")),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        presets::update(&conn, "missing", &input("Night")),
+        Err(AppError::NotFound(_))
+    ));
+    let builtin = presets::list(&conn)
+        .unwrap()
+        .into_iter()
+        .find(|p| p.builtin)
+        .unwrap();
+    assert!(matches!(
+        presets::delete(&conn, &builtin.id),
+        Err(AppError::Invalid(_))
+    ));
+
+    let project_id = project(&conn);
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        presets::set_project_default(&conn, &project_id, Some(&builtin.id), None),
+        Err(AppError::Locked(_))
+    ));
+}
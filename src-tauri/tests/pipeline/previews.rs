@@ -0,0 +1,48 @@
+//! GIF and clip previews of a scene's take.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::previews::{self, Kind, PreviewOptions};
+use ai_directors_chair::settings;
+use tauri::async_runtime::block_on;
+
+#[test]
+fn previews_are_cut_from_the_finished_take() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let shot = scene(&conn, &project(&conn), 1, &[]);
+    let path = scratch_dir().to_string_lossy().into_owned();
+    let options = PreviewOptions::default();
+    assert!(matches!(
+        previews::load(&conn, &shot.id, &path, &options, Kind::Gif),
+        Err(AppError::Invalid(_))
+    ));
+
+    conn.execute(
+        "UPDATE scenes SET video_url = '/takes/one.mp4' WHERE id = ?1",
+        [&shot.id],
+    )
+    .unwrap();
+    settings::set(&conn, "tools.ffmpeg_path", "/nonexistent/ffmpeg").unwrap();
+    let source = previews::load(&conn, &shot.id, &path, &options, Kind::Clip).unwrap();
+    assert!(matches!(
+        block_on(previews::render(source)),
+        Err(AppError::Io(_))
+    ));
+}
+
+#[test]
+fn previews_are_at_most_ten_seconds() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let shot = scene(&conn, &project(&conn), 1, &[]);
+    let options = PreviewOptions {
+        seconds: Some(30.0),
+        ..Default::default()
+    };
+    assert!(matches!(
+        previews::load(&conn, &shot.id, "preview.gif", &options, Kind::Gif),
+        Err(AppError::Validation(_))
+    ));
+}
@@ -0,0 +1,37 @@
+//! Saving and opening `.aidc` project files.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::project_file;
+
+#[test]
+fn a_saved_project_file_opens_in_another_database() {
+    let path = scratch_dir().join("diner.aidc");
+    let project_id = {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let project_id = project(&conn);
+        scene(&conn, &project_id, 1, &[]);
+        let saved = project_file::save(&conn, &project_id, &path).unwrap();
+        assert_eq!(saved.file_path.as_deref(), Some(&*path.to_string_lossy()));
+        project_id
+    };
+
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let opened = project_file::open(&conn, &path).unwrap();
+    assert_eq!(opened.id, project_id);
+    assert_eq!(opened.name, "Test project");
+}
+
+#[test]
+fn only_project_files_open() {
+    let path = scratch_dir().join("notes.aidc");
+    std::fs::write(&path, "not a zip").unwrap();
+    let db = Database::open_in_memory().unwrap();
+    assert!(matches!(
+        project_file::open(&db.conn(), &path),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,79 @@
+//! Prompt enhancement and assembly outside the generation path.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::presets::{self, StylePresetInput};
+use ai_directors_chair::prompts;
+use tauri::async_runtime::block_on;
+
+#[test]
+fn without_an_llm_enhancement_falls_back_to_preset_keywords() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let preset = presets::create(
+        &conn,
+        &StylePresetInput {
+            name: "Noir".into(),
+            description: String::new(),
+            guidance: String::new(),
+            keywords: "high contrast, rain".into(),
+            negative_prompt: "daylight".into(),
+        },
+    )
+    .unwrap();
+
+    let enhancement = prompts::prepare(
+        &conn,
+        "  A diner at night. ",
+        "kling",
+        Some(&project_id),
+        Some(&preset.id),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let enhanced = block_on(prompts::enhance(enhancement)).unwrap();
+    assert!(!enhanced.llm_enhanced);
+    assert_eq!(enhanced.enhanced, "A diner at night, high contrast, rain");
+    assert_eq!(enhanced.preset_id.as_deref(), Some(preset.id.as_str()));
+}
+
+#[test]
+fn enhancement_needs_a_description_and_a_known_provider() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    for (description, provider) in [(" ", "kling"), ("A diner", "no-such-model")] {
+        assert!(matches!(
+            prompts::prepare(&conn, description, provider, None, None, None, None, None),
+            Err(AppError::Invalid(_))
+        ));
+    }
+}
+
+#[test]
+fn assembled_prompts_export_as_text_or_json() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let assembled = prompts::for_scene(&conn, &shot.id).unwrap();
+    assert_eq!(assembled.scene, "1");
+    assert!(assembled.prompt.contains("empty diner"));
+    assert!(matches!(
+        prompts::for_scene(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+
+    let dir = scratch_dir().to_string_lossy().into_owned();
+    let written = prompts::export_all(&conn, &project_id, &dir, "text").unwrap();
+    assert!(written.ends_with("prompts.txt"));
+    let text = std::fs::read_to_string(&written).unwrap();
+    assert!(text.starts_with("Test project\n\n## Scene 1"));
+    assert!(matches!(
+        prompts::export_all(&conn, &project_id, &dir, "pdf"),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,96 @@
+//! Props and wardrobe attached to scenes and characters.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use ai_directors_chair::props::{self, PropInput, PropKind};
+
+fn input(project_id: &str, name: &str) -> PropInput {
+    PropInput {
+        id: None,
+        project_id: project_id.into(),
+        name: name.into(),
+        description: String::new(),
+        prompt_fragment: String::new(),
+    }
+}
+
+#[test]
+fn attached_items_show_in_their_scene() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    let mara = &shot.characters[0];
+    let compass = props::save(&conn, PropKind::Prop, &input(&project_id, "Brass compass")).unwrap();
+    let jacket = props::save(
+        &conn,
+        PropKind::Wardrobe,
+        &input(&project_id, "Red leather jacket"),
+    )
+    .unwrap();
+    assert_eq!(props::list(&conn, &project_id, None).unwrap().len(), 2);
+    assert_eq!(
+        props::list(&conn, &project_id, Some(PropKind::Prop)).unwrap()[0].id,
+        compass.id
+    );
+
+    let on_scene = props::attach(&conn, PropKind::Prop, &compass.id, Some(&shot.id), None).unwrap();
+    props::attach(&conn, PropKind::Wardrobe, &jacket.id, None, Some(mara)).unwrap();
+    assert_eq!(props::links(&conn, &project_id).unwrap().len(), 2);
+    let names: Vec<String> = props::details_for_scene(&conn, &shot.id)
+        .unwrap()
+        .into_iter()
+        .map(|d| d.name)
+        .collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"Red leather jacket".to_string()));
+
+    props::detach(&conn, PropKind::Prop, on_scene.id).unwrap();
+    props::delete(&conn, PropKind::Wardrobe, &jacket.id).unwrap();
+    assert!(props::details_for_scene(&conn, &shot.id)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn items_attach_somewhere_in_their_own_unlocked_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let elsewhere = scene(&conn, &project(&conn), 1, &[]);
+    let compass = props::save(&conn, PropKind::Prop, &input(&project_id, "Brass compass")).unwrap();
+    assert!(matches!(
+        props::attach(&conn, PropKind::Prop, &compass.id, None, None),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        props::attach(
+            &conn,
+            PropKind::Prop,
+            &compass.id,
+            Some(&elsewhere.id),
+            None
+        ),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        props::detach(&conn, PropKind::Prop, 999),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        props::details_for_scene(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        props::save(&conn, PropKind::Prop, &input(&project_id, "Umbrella")),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        props::delete(&conn, PropKind::Prop, &compass.id),
+        Err(AppError::Locked(_))
+    ));
+}
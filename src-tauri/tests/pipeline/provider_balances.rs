@@ -0,0 +1,52 @@
+use ai_directors_chair::balances;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::{providers, settings};
+
+#[test]
+fn balances_are_fetched_when_stale_and_keep_figures_on_failure() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let listed = balances::cached_balances(&conn, false).unwrap();
+    let providers_listed: Vec<&str> = listed.iter().map(|(b, _)| b.provider.as_str()).collect();
+    assert_eq!(providers_listed, ["fal", "elevenlabs", "gemini"]);
+    // No keys yet: nothing to fetch, and each says why.
+    assert!(listed.iter().all(|(_, key)| key.is_none()));
+    assert!(listed[0].0.error.contains("API key not configured"));
+    assert!(!listed[2].0.supported);
+
+    settings::set(&conn, providers::FAL_KEY_SETTING, "fal-key").unwrap();
+    let (mut fal, key) = balances::cached_balances(&conn, false)
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap();
+    assert_eq!(key.as_deref(), Some("fal-key"));
+    balances::record_fetch(&conn, &mut fal, Ok((12.5, None))).unwrap();
+    assert_eq!(balances::fal_remaining(&conn).unwrap(), Some(12.5));
+
+    // Fresh and without errors, it comes from the cache until refreshed.
+    let (cached, key) = balances::cached_balances(&conn, false)
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap();
+    assert_eq!((cached.remaining, key), (Some(12.5), None));
+    let (mut fal, key) = balances::cached_balances(&conn, true)
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap();
+    assert!(key.is_some());
+
+    let failed = Err(AppError::Provider("401 Unauthorized".into()));
+    balances::record_fetch(&conn, &mut fal, failed).unwrap();
+    let (after, key) = balances::cached_balances(&conn, false)
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap();
+    assert!(after.error.contains("401"));
+    assert_eq!(after.remaining, Some(12.5));
+    assert!(key.is_some(), "a failed fetch is retried");
+}
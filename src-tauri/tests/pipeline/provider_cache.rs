@@ -0,0 +1,119 @@
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::{AppError, AppResult};
+use ai_directors_chair::provider_cache;
+use std::cell::Cell;
+use std::time::Duration;
+use tauri::async_runtime::block_on;
+
+const WEEK: Duration = Duration::from_secs(7 * 24 * 3600);
+
+fn cached(db: &Database) -> i64 {
+    db.conn()
+        .query_row("SELECT COUNT(*) FROM provider_cache", [], |r| r.get(0))
+        .unwrap()
+}
+
+#[test]
+fn cached_answers_are_reused_until_forgotten() {
+    let db = Database::open_in_memory().unwrap();
+    let key = provider_cache::key("llm", &["model", "prompt"]);
+    let calls = Cell::new(0);
+    let fetch = || async {
+        calls.set(calls.get() + 1);
+        AppResult::Ok(format!("answer {}", calls.get()))
+    };
+
+    let first: String =
+        block_on(provider_cache::get_or_fetch(&db, "llm", &key, WEEK, fetch)).unwrap();
+    let second: String =
+        block_on(provider_cache::get_or_fetch(&db, "llm", &key, WEEK, fetch)).unwrap();
+    assert_eq!((first.as_str(), second.as_str()), ("answer 1", "answer 1"));
+    assert_eq!(calls.get(), 1);
+
+    provider_cache::forget(&db.conn(), &key).unwrap();
+    let third: String =
+        block_on(provider_cache::get_or_fetch(&db, "llm", &key, WEEK, fetch)).unwrap();
+    assert_eq!(third, "answer 2");
+    assert_eq!(cached(&db), 1);
+}
+
+#[test]
+fn failed_fetches_are_not_cached() {
+    let db = Database::open_in_memory().unwrap();
+    let key = provider_cache::key("llm", &["model", "prompt"]);
+    let failed: AppResult<String> = block_on(provider_cache::get_or_fetch(
+        &db,
+        "llm",
+        &key,
+        WEEK,
+        || async { Err(AppError::Network("offline".into())) },
+    ));
+    assert!(failed.is_err());
+    assert_eq!(cached(&db), 0);
+}
+
+#[test]
+fn confidential_projects_skip_the_cache() {
+    let db = Database::open_in_memory().unwrap();
+    let (open, secret) = {
+        let conn = db.conn();
+        let open = project(&conn);
+        let secret = project(&conn);
+        conn.execute(
+            "UPDATE projects SET confidential = 1 WHERE id = ?1",
+            [&secret],
+        )
+        .unwrap();
+        (open, secret)
+    };
+    let key = provider_cache::key("llm", &["model", "secret prompt"]);
+    let calls = Cell::new(0);
+    let fetch = || async {
+        calls.set(calls.get() + 1);
+        AppResult::Ok(calls.get())
+    };
+
+    for _ in 0..2 {
+        let _: i32 = block_on(provider_cache::get_or_fetch_for_project(
+            &db, &secret, "llm", &key, WEEK, fetch,
+        ))
+        .unwrap();
+    }
+    assert_eq!(calls.get(), 2);
+    assert_eq!(cached(&db), 0);
+
+    for _ in 0..2 {
+        let _: i32 = block_on(provider_cache::get_or_fetch_for_project(
+            &db, &open, "llm", &key, WEEK, fetch,
+        ))
+        .unwrap();
+    }
+    assert_eq!(calls.get(), 3);
+    assert_eq!(cached(&db), 1);
+}
+
+#[test]
+fn clearing_can_keep_other_providers_entries() {
+    let db = Database::open_in_memory().unwrap();
+    for provider in ["llm", "tts"] {
+        let key = provider_cache::key(provider, &["prompt"]);
+        let _: String = block_on(provider_cache::get_or_fetch(
+            &db,
+            provider,
+            &key,
+            WEEK,
+            || async { AppResult::Ok("answer".to_string()) },
+        ))
+        .unwrap();
+    }
+    assert_eq!(provider_cache::clear(&db.conn(), Some("llm")).unwrap(), 1);
+    assert_eq!(provider_cache::clear(&db.conn(), None).unwrap(), 1);
+    assert_eq!(cached(&db), 0);
+
+    let bare = rusqlite::Connection::open_in_memory().unwrap();
+    assert!(matches!(
+        provider_cache::clear(&bare, None),
+        Err(AppError::Database(_))
+    ));
+}
@@ -0,0 +1,68 @@
+//! Renders and where they were published.
+
+use crate::{project, scratch_dir};
+use ai_directors_chair::confidential;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::publishing::{self, PublishMetadata, PublishTarget};
+use ai_directors_chair::settings;
+
+#[test]
+fn published_renders_keep_their_video_url() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let path = scratch_dir().join("cut.mp4");
+    std::fs::write(&path, b"video").unwrap();
+    let render = publishing::record_render(&conn, &project_id, &path.to_string_lossy()).unwrap();
+    settings::set(&conn, "publish.vimeo.client_id", "client-123").unwrap();
+
+    publishing::prepare_upload(
+        &conn,
+        &render.id,
+        PublishTarget::Vimeo,
+        &PublishMetadata::default(),
+    )
+    .unwrap();
+    let published = publishing::record_publication(
+        &conn,
+        &render.id,
+        PublishTarget::Vimeo,
+        "42",
+        "https://vimeo.com/42",
+        "Test project",
+    )
+    .unwrap();
+    assert_eq!(published.publications[0].url, "https://vimeo.com/42");
+    let renders = publishing::renders(&conn, &project_id).unwrap();
+    assert_eq!(renders.len(), 1);
+    assert_eq!(renders[0].publications.len(), 1);
+}
+
+#[test]
+fn uploads_need_a_known_privacy_and_an_open_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let render = publishing::record_render(&conn, &project_id, "/renders/gone.mp4").unwrap();
+    settings::set(&conn, "publish.vimeo.client_id", "client-123").unwrap();
+    let upload = |metadata: &PublishMetadata| {
+        publishing::prepare_upload(&conn, &render.id, PublishTarget::Vimeo, metadata)
+    };
+    assert!(matches!(
+        upload(&PublishMetadata {
+            privacy: "friends".into(),
+            ..Default::default()
+        }),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        upload(&PublishMetadata::default()),
+        Err(AppError::NotFound(_))
+    ));
+    confidential::set(&conn, &project_id, true).unwrap();
+    assert!(matches!(
+        upload(&PublishMetadata::default()),
+        Err(AppError::Forbidden(_))
+    ));
+}
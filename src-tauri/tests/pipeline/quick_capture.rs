@@ -0,0 +1,34 @@
+//! The quick capture shortcut setting.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::quick_capture::{self, DEFAULT_SHORTCUT};
+
+#[test]
+fn switching_shortcuts_releases_the_old_one() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert_eq!(quick_capture::shortcut(&conn).unwrap(), DEFAULT_SHORTCUT);
+    assert!(quick_capture::change(&conn, DEFAULT_SHORTCUT)
+        .unwrap()
+        .is_none());
+
+    let off = quick_capture::change(&conn, "  ").unwrap().unwrap();
+    assert_eq!(off.accelerator, "");
+    assert!(off.register.is_none());
+    assert!(off.release.is_some());
+    let other = quick_capture::change(&conn, " CmdOrCtrl+Shift+K ")
+        .unwrap()
+        .unwrap();
+    assert_eq!(other.accelerator, "CmdOrCtrl+Shift+K");
+    assert!(other.register.is_some());
+}
+
+#[test]
+fn shortcuts_must_parse() {
+    let db = Database::open_in_memory().unwrap();
+    assert!(matches!(
+        quick_capture::change(&db.conn(), "Shift+Nope"),
+        Err(AppError::Invalid(_))
+    ));
+}
@@ -0,0 +1,40 @@
+//! Character, location and status breakdowns.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::reports;
+
+#[test]
+fn breakdowns_group_scenes_and_export_as_csv() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &["Mara"]);
+    scene(&conn, &project_id, 2, &["Mara", "Joe"]);
+
+    let (scenes, characters) = reports::load(&conn, &project_id).unwrap();
+    let cast = reports::by_character(&scenes, &characters);
+    let mara = cast.iter().find(|c| c.name == "Mara").unwrap();
+    assert_eq!(mara.scene_numbers, [1, 2]);
+
+    let dir = scratch_dir().to_string_lossy().into_owned();
+    let written = reports::export_csv(&conn, &project_id, "character", &dir).unwrap();
+    assert!(written.ends_with("breakdown-character.csv"));
+    assert!(std::fs::read_to_string(&written).unwrap().contains("Mara"));
+}
+
+#[test]
+fn breakdowns_need_a_project_and_a_known_report() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        reports::load(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    let project_id = project(&conn);
+    assert!(matches!(
+        reports::export_csv(&conn, &project_id, "budget", "breakdown.csv"),
+        Err(AppError::Invalid(_))
+    ));
+}
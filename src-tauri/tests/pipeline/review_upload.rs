@@ -0,0 +1,136 @@
+//! Review settings, and takes and renders checked before they're uploaded
+//! for review.
+
+use crate::{project, queue_job, scene, scratch_dir};
+use ai_directors_chair::confidential;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::jobs;
+use ai_directors_chair::review_upload::{self, ReviewCopy, ReviewPlatform, ReviewSettingsInput};
+
+fn generic(url: &str, token: Option<&str>) -> ReviewSettingsInput {
+    ReviewSettingsInput {
+        platform: ReviewPlatform::Generic,
+        frameio_project: String::new(),
+        generic_url: url.into(),
+        token: token.map(Into::into),
+    }
+}
+
+#[test]
+fn finished_takes_are_prepared_and_keep_their_review_link() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let saved = review_upload::save_settings(
+        &conn,
+        &generic("https://review.example.com/upload", Some("secret")),
+    )
+    .unwrap();
+    assert!(saved.connected);
+    let (platform, token, target) = review_upload::destination(&conn).unwrap();
+    assert_eq!(platform, ReviewPlatform::Generic);
+    assert_eq!(token, "secret");
+    assert_eq!(target, "https://review.example.com/upload");
+
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let take_id = queue_job(&conn, &shot.id);
+    let take = jobs::get_job(&conn, &take_id).unwrap();
+    jobs::complete(&conn, &take, "https://cdn.example.com/take.mp4").unwrap();
+
+    let prepared =
+        review_upload::prepare_take(&conn, &take_id, platform, &ReviewCopy::default()).unwrap();
+    assert!(prepared.name().starts_with("Scene 1"));
+    assert!(prepared.name().ends_with(".mp4"));
+
+    review_upload::record_take_upload(
+        &conn,
+        &take_id,
+        prepared.name(),
+        platform,
+        "https://review.example.com/v/1",
+    )
+    .unwrap();
+    assert_eq!(
+        jobs::get_job(&conn, &take_id).unwrap().review_url,
+        "https://review.example.com/v/1"
+    );
+}
+
+#[test]
+fn uploads_need_a_token_an_https_url_and_a_finished_take() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        review_upload::save_settings(&conn, &generic("http://review.example.com", None)),
+        Err(AppError::Validation(_))
+    ));
+    review_upload::save_settings(&conn, &generic("https://review.example.com", Some(""))).unwrap();
+    assert!(matches!(
+        review_upload::destination(&conn),
+        Err(AppError::Invalid(_))
+    ));
+
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let take_id = queue_job(&conn, &shot.id);
+    assert!(matches!(
+        review_upload::prepare_take(
+            &conn,
+            &take_id,
+            ReviewPlatform::Generic,
+            &ReviewCopy::default()
+        ),
+        Err(AppError::Invalid(_))
+    ));
+}
+
+#[test]
+fn renders_must_exist_and_confidential_projects_stay_local() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let copy = ReviewCopy::default();
+    let missing = scratch_dir().join("missing.mp4");
+    assert!(matches!(
+        review_upload::prepare_render(
+            &conn,
+            &project_id,
+            &missing.to_string_lossy(),
+            ReviewPlatform::Generic,
+            &copy
+        ),
+        Err(AppError::NotFound(_))
+    ));
+
+    let render = scratch_dir().join("render.mp4");
+    std::fs::write(&render, b"mp4").unwrap();
+    let path = render.to_string_lossy();
+    let prepared =
+        review_upload::prepare_render(&conn, &project_id, &path, ReviewPlatform::Generic, &copy)
+            .unwrap();
+    assert_eq!(prepared.name(), "Test project.mp4");
+    review_upload::record_render_upload(
+        &conn,
+        &project_id,
+        ReviewPlatform::Generic,
+        "https://review.example.com/v/2",
+    )
+    .unwrap();
+
+    confidential::set(&conn, &project_id, true).unwrap();
+    assert!(matches!(
+        review_upload::prepare_render(&conn, &project_id, &path, ReviewPlatform::Generic, &copy),
+        Err(AppError::Forbidden(_))
+    ));
+}
+
+#[test]
+fn review_copies_need_a_known_render() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        review_upload::prepare_copy(&conn, "missing", &ReviewCopy::default()),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,57 @@
+//! Members of a shared project and the role they're given.
+
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::roles::{self, MemberInput, Role};
+
+fn device(role: Option<Role>) -> MemberInput {
+    MemberInput {
+        kind: "device".into(),
+        member_id: "device-1".into(),
+        name: "Edit bay".into(),
+        role,
+    }
+}
+
+#[test]
+fn members_are_added_and_removed_by_the_owner() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    assert!(roles::members(&conn, &project_id).unwrap().is_empty());
+    assert_eq!(roles::my_role(&conn, &project_id).unwrap(), Role::Owner);
+
+    let members = roles::set_member(&conn, &project_id, &device(Some(Role::Editor))).unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].role, Role::Editor);
+    assert_eq!(
+        roles::members(&conn, &project_id).unwrap()[0].name,
+        "Edit bay"
+    );
+
+    assert!(roles::set_member(&conn, &project_id, &device(None))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn members_need_a_known_kind_and_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let mut input = device(Some(Role::Viewer));
+    input.kind = "team".into();
+    assert!(matches!(
+        roles::set_member(&conn, &project_id, &input),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        roles::members(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        roles::my_role(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,53 @@
+//! Runtime estimates at the saved reading speed and pacing.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::runtime::{self, RuntimeSettings};
+use std::collections::BTreeMap;
+
+#[test]
+fn saved_settings_drive_the_estimate() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &[]);
+    let settings = RuntimeSettings {
+        words_per_minute: 120.0,
+        pacing: BTreeMap::from([(" Noir ".to_string(), 1.2), (" ".to_string(), 9.0)]),
+    };
+    runtime::save_settings(&conn, &settings).unwrap();
+
+    let saved = runtime::load_settings(&conn).unwrap();
+    assert_eq!(saved.words_per_minute, 120.0);
+    assert_eq!(saved.pacing, BTreeMap::from([("noir".to_string(), 1.2)]));
+    let estimate = runtime::estimate(&conn, &project_id, None).unwrap();
+    assert_eq!(estimate.words_per_minute, 120.0);
+    assert_eq!(estimate.scenes.len(), 1);
+}
+
+#[test]
+fn settings_out_of_range_are_refused() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let slow = RuntimeSettings {
+        words_per_minute: 10.0,
+        pacing: BTreeMap::new(),
+    };
+    assert!(matches!(
+        runtime::save_settings(&conn, &slow),
+        Err(AppError::Invalid(_))
+    ));
+    let rushed = RuntimeSettings {
+        words_per_minute: 150.0,
+        pacing: BTreeMap::from([("action".to_string(), 10.0)]),
+    };
+    assert!(matches!(
+        runtime::save_settings(&conn, &rushed),
+        Err(AppError::Invalid(_))
+    ));
+    assert_eq!(
+        runtime::load_settings(&conn).unwrap().words_per_minute,
+        150.0
+    );
+}
@@ -0,0 +1,61 @@
+//! Saving scenes, characters and the cast between them in one go.
+
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use ai_directors_chair::scene_graph::{self, SceneGraph};
+use ai_directors_chair::scenes;
+use serde_json::json;
+
+fn graph(project_id: &str) -> SceneGraph {
+    serde_json::from_value(json!({
+        "characters": [{ "key": "c1", "projectId": project_id, "name": "Mara" }],
+        "scenes": [{
+            "key": "s1",
+            "projectId": project_id,
+            "sceneNumber": 1,
+            "title": "Diner",
+            "prompt": "Wide shot of an empty diner, neon flickering"
+        }],
+        "links": [{ "scene": "s1", "character": "c1" }]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn new_scenes_and_characters_are_linked_by_key() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let saved = scene_graph::save(&conn, &project_id, &graph(&project_id)).unwrap();
+    let scene_id = &saved.keys["s1"];
+    let character_id = &saved.keys["c1"];
+    assert_eq!(saved.scenes.len(), 1);
+    assert_eq!(
+        scenes::get_scene(&conn, scene_id).unwrap().characters,
+        std::slice::from_ref(character_id)
+    );
+}
+
+#[test]
+fn a_failed_graph_saves_nothing() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let mut bad = graph(&project_id);
+    bad.links[0].character = "missing".into();
+    assert!(matches!(
+        scene_graph::save(&conn, &project_id, &bad),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(scenes::list_for_project(&conn, &project_id)
+        .unwrap()
+        .is_empty());
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        scene_graph::save(&conn, &project_id, &graph(&project_id)),
+        Err(AppError::Locked(_))
+    ));
+}
@@ -0,0 +1,58 @@
+//! Scenes that continue from, or wait for, another scene.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use ai_directors_chair::scene_links::{self, kind, SceneLinkInput};
+
+fn link(upstream: &str, downstream: &str) -> SceneLinkInput {
+    SceneLinkInput {
+        upstream_id: upstream.into(),
+        downstream_id: downstream.into(),
+        kind: None,
+    }
+}
+
+#[test]
+fn links_are_created_listed_and_deleted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &[]);
+    let second = scene(&conn, &project_id, 2, &[]);
+
+    let created = scene_links::create(&conn, &link(&first.id, &second.id)).unwrap();
+    assert_eq!(created.kind, kind::END_FRAME);
+    assert_eq!(scene_links::list(&conn, &project_id).unwrap().len(), 1);
+    scene_links::delete(&conn, &created.id).unwrap();
+    assert!(scene_links::list(&conn, &project_id).unwrap().is_empty());
+}
+
+#[test]
+fn links_may_not_loop_or_touch_a_locked_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &[]);
+    let second = scene(&conn, &project_id, 2, &[]);
+    assert!(matches!(
+        scene_links::create(&conn, &link(&first.id, &first.id)),
+        Err(AppError::Invalid(_))
+    ));
+    let created = scene_links::create(&conn, &link(&first.id, &second.id)).unwrap();
+    assert!(matches!(
+        scene_links::create(&conn, &link(&second.id, &first.id)),
+        Err(AppError::Invalid(_))
+    ));
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        scene_links::delete(&conn, &created.id),
+        Err(AppError::Locked(_))
+    ));
+    assert!(matches!(
+        scene_links::delete(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
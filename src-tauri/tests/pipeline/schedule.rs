@@ -0,0 +1,78 @@
+//! Shooting days, the scenes shot on them, and their call sheets.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use ai_directors_chair::schedule::{self, ShootingDayInput};
+
+fn day(project_id: &str, shoot_date: &str) -> ShootingDayInput {
+    ShootingDayInput {
+        id: None,
+        project_id: project_id.to_string(),
+        shoot_date: shoot_date.into(),
+        call_time: "07:00".into(),
+        wrap_time: "19:00".into(),
+        location: "Stage 4".into(),
+        notes: String::new(),
+    }
+}
+
+#[test]
+fn days_take_scenes_and_export_their_sheets() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    let first = schedule::save_day(&conn, &day(&project_id, "2026-03-02")).unwrap();
+    let second = schedule::save_day(&conn, &day(&project_id, "2026-03-03")).unwrap();
+
+    schedule::set_day_scenes(&conn, &first.id, std::slice::from_ref(&shot.id)).unwrap();
+    let moved =
+        schedule::set_day_scenes(&conn, &second.id, std::slice::from_ref(&shot.id)).unwrap();
+    assert_eq!(moved.scene_ids, std::slice::from_ref(&shot.id));
+    let days = schedule::list_days(&conn, &project_id).unwrap();
+    assert!(days[0].scene_ids.is_empty());
+
+    let dir = scratch_dir();
+    let sheet = schedule::write_call_sheet(&conn, &second.id, &dir.to_string_lossy()).unwrap();
+    assert!(std::fs::read(&sheet).unwrap().starts_with(b"%PDF"));
+    let ics = dir.join("day.ics");
+    let written = schedule::write_day_ics(&conn, &second.id, &ics.to_string_lossy()).unwrap();
+    assert!(std::fs::read_to_string(&written)
+        .unwrap()
+        .contains("BEGIN:VEVENT"));
+
+    schedule::delete_day(&conn, &first.id).unwrap();
+    let imported = schedule::import_ics(&conn, &project_id, &written).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(schedule::list_days(&conn, &project_id).unwrap().len(), 1);
+}
+
+#[test]
+fn days_reject_other_projects_scenes_and_locked_projects() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let other = project(&conn);
+    let elsewhere = scene(&conn, &other, 1, &[]);
+    let shoot = schedule::save_day(&conn, &day(&project_id, "2026-03-02")).unwrap();
+    assert!(matches!(
+        schedule::set_day_scenes(&conn, &shoot.id, &[elsewhere.id]),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        schedule::import_ics(&conn, &project_id, "/nonexistent/days.ics"),
+        Err(AppError::Io(_))
+    ));
+    assert!(matches!(
+        schedule::write_call_sheet(&conn, "missing", "call-sheet.pdf"),
+        Err(AppError::NotFound(_))
+    ));
+
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        schedule::delete_day(&conn, &shoot.id),
+        Err(AppError::Locked(_))
+    ));
+}
@@ -0,0 +1,58 @@
+//! Screenplay formatting and the PDF and Fountain exports.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use ai_directors_chair::screenplay::{self, ScreenplayFormat};
+
+#[test]
+fn formats_are_saved_and_shape_the_exports() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &[]);
+    assert_eq!(
+        screenplay::format(&conn, &project_id).unwrap(),
+        ScreenplayFormat::default()
+    );
+    let a4 = ScreenplayFormat {
+        page_size: Some("a4".into()),
+        numbered_headings: Some(true),
+        ..Default::default()
+    };
+    screenplay::set_format(&conn, &project_id, &a4).unwrap();
+    assert_eq!(screenplay::format(&conn, &project_id).unwrap(), a4);
+
+    let dir = scratch_dir().to_string_lossy().into_owned();
+    let pdf = screenplay::write_pdf(&conn, &project_id, &dir).unwrap();
+    assert!(std::fs::read(&pdf).unwrap().starts_with(b"%PDF"));
+    let fountain = screenplay::write_fountain(&conn, &project_id, &dir).unwrap();
+    assert!(std::fs::read_to_string(&fountain)
+        .unwrap()
+        .contains("INT. DINER - NIGHT"));
+}
+
+#[test]
+fn formats_are_checked_and_locked_with_the_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        screenplay::format(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    let project_id = project(&conn);
+    let legal = ScreenplayFormat {
+        page_size: Some("legal".into()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        screenplay::set_format(&conn, &project_id, &legal),
+        Err(AppError::Validation(_))
+    ));
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        screenplay::set_format(&conn, &project_id, &ScreenplayFormat::default()),
+        Err(AppError::Locked(_))
+    ));
+}
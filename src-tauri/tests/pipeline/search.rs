@@ -0,0 +1,35 @@
+//! Searching projects, characters and scenes at once.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::search;
+
+#[test]
+fn matches_are_grouped_by_kind() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    scene(&conn, &project_id, 1, &["Mara"]);
+
+    let results = search::search(&conn, "dine", None).unwrap();
+    assert_eq!(results.scenes.len(), 1);
+    assert_eq!(results.scenes[0].project_name, "Test project");
+    assert_eq!(
+        search::search(&conn, "mara", Some(5))
+            .unwrap()
+            .characters
+            .len(),
+        1
+    );
+    assert!(search::search(&conn, "  ", None).unwrap().scenes.is_empty());
+}
+
+#[test]
+fn searching_needs_the_index() {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    assert!(matches!(
+        search::search(&conn, "diner", None),
+        Err(AppError::Database(_))
+    ));
+}
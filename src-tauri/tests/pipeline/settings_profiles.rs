@@ -0,0 +1,45 @@
+//! Settings profiles carried from one machine to another.
+
+use crate::scratch_dir;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::settings;
+use ai_directors_chair::settings_profiles::{self, PROFILE_FORMAT};
+
+#[test]
+fn profiles_carry_settings_but_not_credentials() {
+    let source = Database::open_in_memory().unwrap();
+    let conn = source.conn();
+    settings::set(&conn, "runtime.wpm", "130").unwrap();
+    settings::set(&conn, "api_key.fal", "secret").unwrap();
+    let path = scratch_dir().join("settings.aidc-profile.json");
+    let written = settings_profiles::write_profile(&conn, &path.to_string_lossy()).unwrap();
+    let profile = settings_profiles::read_profile(&written).unwrap();
+    assert_eq!(profile.format, PROFILE_FORMAT);
+    assert!(profile.credentials.contains(&"api_key.fal".to_string()));
+    assert!(!profile.settings.contains_key("api_key.fal"));
+
+    let target = Database::open_in_memory().unwrap();
+    let conn = target.conn();
+    let imported = settings_profiles::import_profile(&conn, &written).unwrap();
+    assert_eq!(imported.missing_credentials, ["api_key.fal".to_string()]);
+    assert_eq!(
+        settings::get(&conn, "runtime.wpm").unwrap().as_deref(),
+        Some("130")
+    );
+}
+
+#[test]
+fn profiles_from_a_newer_app_are_refused() {
+    let path = scratch_dir().join("future.aidc-profile.json");
+    std::fs::write(
+        &path,
+        r#"{"format": 99, "exportedAt": "2030-01-01T00:00:00Z", "appVersion": "9.0.0"}"#,
+    )
+    .unwrap();
+    let db = Database::open_in_memory().unwrap();
+    assert!(matches!(
+        settings_profiles::import_profile(&db.conn(), &path.to_string_lossy()),
+        Err(AppError::Invalid(_))
+    ));
+}
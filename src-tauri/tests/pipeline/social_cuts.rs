@@ -0,0 +1,61 @@
+//! Focal points and the vertical and square cuts that follow them.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::publishing;
+use ai_directors_chair::settings;
+use ai_directors_chair::social_cuts::{self, Focus};
+use tauri::async_runtime::block_on;
+
+#[test]
+fn focus_is_set_and_cuts_are_prepared() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    social_cuts::set_focus(&conn, &shot.id, Some(Focus { x: 0.2, y: 0.4 })).unwrap();
+    let focus = social_cuts::scene_focus(&conn, &project_id).unwrap();
+    assert_eq!(focus.len(), 1);
+    assert_eq!(focus[0].focus.x, 0.2);
+    social_cuts::set_focus(&conn, &shot.id, None).unwrap();
+    assert!(social_cuts::scene_focus(&conn, &project_id)
+        .unwrap()
+        .is_empty());
+
+    let path = scratch_dir().join("render.mp4");
+    std::fs::write(&path, b"mp4").unwrap();
+    let render = publishing::record_render(&conn, &project_id, &path.to_string_lossy()).unwrap();
+    let job = social_cuts::prepare(&conn, &render.id, "9:16", true).unwrap();
+    assert_eq!(job.project_id(), project_id);
+
+    settings::set(&conn, "tools.ffmpeg_path", "/nonexistent/ffmpeg").unwrap();
+    let job = social_cuts::prepare(&conn, &render.id, "1:1", false).unwrap();
+    assert!(matches!(
+        block_on(social_cuts::cut(&job)),
+        Err(AppError::Io(_))
+    ));
+}
+
+#[test]
+fn cuts_need_a_known_aspect_and_the_render_file() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    assert!(matches!(
+        social_cuts::set_focus(&conn, &shot.id, Some(Focus { x: 1.5, y: 0.5 })),
+        Err(AppError::Validation(_))
+    ));
+
+    let missing = scratch_dir().join("missing.mp4");
+    let render = publishing::record_render(&conn, &project_id, &missing.to_string_lossy()).unwrap();
+    assert!(matches!(
+        social_cuts::prepare(&conn, &render.id, "4:3", true),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        social_cuts::prepare(&conn, &render.id, "9:16", true),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,99 @@
+use crate::project;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::spellcheck;
+use spellbook::Dictionary;
+
+const AFF: &str = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwz'\n\nSFX S Y 1\nSFX S 0 s .\n";
+const DIC: &str = "5\ndawn/S\nwalk/S\nthe\nat\nrain\n";
+
+#[test]
+fn misspellings_are_found_with_suggestions_and_utf16_offsets() {
+    let dictionary = Dictionary::new(AFF, DIC).unwrap();
+    let text = "The rain at dwan.\r\n🎬 Marra walks 4K, don't wlak";
+    let check = spellcheck::check(
+        &dictionary,
+        "en_US",
+        text,
+        &["DON'T".to_string()],
+        &["Mara Quill".to_string()],
+    );
+    assert_eq!(check.lang, "en_US");
+    let found: Vec<(&str, usize, usize)> = check
+        .misspellings
+        .iter()
+        .map(|m| (m.word.as_str(), m.start, m.end))
+        .collect();
+    assert_eq!(
+        found,
+        vec![("dwan", 12, 16), ("Marra", 22, 27), ("wlak", 44, 48)]
+    );
+
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    for m in &check.misspellings {
+        assert_eq!(String::from_utf16(&utf16[m.start..m.end]).unwrap(), m.word);
+    }
+    assert_eq!(check.misspellings[0].suggestions[0], "dawn");
+    assert_eq!(check.misspellings[0].character, None);
+    assert_eq!(check.misspellings[1].suggestions[0], "Mara");
+    assert_eq!(
+        check.misspellings[1].character.as_deref(),
+        Some("Mara Quill")
+    );
+}
+
+#[test]
+fn accepted_words_and_names_are_never_reported() {
+    let dictionary = Dictionary::new(AFF, DIC).unwrap();
+    let check = spellcheck::check(
+        &dictionary,
+        "en_US",
+        "quill and MARA walk at dawn",
+        &["and".to_string()],
+        &["Mara Quill".to_string()],
+    );
+    assert!(check.misspellings.is_empty());
+}
+
+#[test]
+fn checks_accept_the_users_words_and_the_projects_cast() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    crate::scene(&conn, &project_id, 1, &["Marra"]);
+    assert_eq!(
+        spellcheck::add_word(&conn, " dwan ").unwrap(),
+        ["dwan".to_string()]
+    );
+    spellcheck::add_word(&conn, "wlak").unwrap();
+    assert_eq!(
+        spellcheck::remove_word(&conn, "wlak").unwrap(),
+        ["dwan".to_string()]
+    );
+
+    let dictionary = Dictionary::new(AFF, DIC).unwrap();
+    let request =
+        spellcheck::prepare(&conn, "Marra at dwan, wlak", None, Some(&project_id)).unwrap();
+    assert_eq!(request.lang(), "en_US");
+    let found: Vec<String> = request
+        .run(&dictionary)
+        .misspellings
+        .into_iter()
+        .map(|m| m.word)
+        .collect();
+    assert_eq!(found, ["wlak"]);
+}
+
+#[test]
+fn checks_refuse_bad_dictionary_names_and_blank_words() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        spellcheck::prepare(&conn, "text", Some("../en_US"), None),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        spellcheck::add_word(&conn, "  "),
+        Err(AppError::Validation(_))
+    ));
+}
@@ -0,0 +1,58 @@
+//! Splitting a scene in two and merging scenes back into one.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::numbering::RenumberStrategy;
+use ai_directors_chair::projects;
+use ai_directors_chair::scenes;
+use ai_directors_chair::split_merge;
+
+#[test]
+fn a_split_scene_merges_back_into_one() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+
+    let split =
+        split_merge::split(&conn, &shot.id, 8, None, RenumberStrategy::InsertStyle).unwrap();
+    assert_eq!(split.first.id, shot.id);
+    assert_eq!(split.first.description.trim(), "A diner");
+    assert_eq!(split.second.description.trim(), "at night");
+    assert_eq!(
+        scenes::list_for_project(&conn, &project_id).unwrap().len(),
+        2
+    );
+
+    let merged = split_merge::merge(
+        &conn,
+        &[split.first.id.clone(), split.second.id.clone()],
+        RenumberStrategy::Sequential,
+    )
+    .unwrap();
+    assert_eq!(merged.scene.id, shot.id);
+    assert_eq!(merged.removed, [split.second.id]);
+    assert_eq!(
+        scenes::list_for_project(&conn, &project_id).unwrap().len(),
+        1
+    );
+}
+
+#[test]
+fn splits_and_merges_need_an_editable_scene() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        split_merge::split(&conn, "missing", 1, None, RenumberStrategy::InsertStyle),
+        Err(AppError::NotFound(_))
+    ));
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &[]);
+    let second = scene(&conn, &project_id, 2, &[]);
+    projects::lock(&conn, &project_id).unwrap();
+    assert!(matches!(
+        split_merge::merge(&conn, &[first.id, second.id], RenumberStrategy::Sequential),
+        Err(AppError::Locked(_))
+    ));
+}
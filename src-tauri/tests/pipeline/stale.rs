@@ -0,0 +1,37 @@
+//! Scenes whose takes no longer match their characters.
+
+use crate::{project, queue_job, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::stale;
+
+#[test]
+fn stale_scenes_are_listed_until_dismissed() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &["Mara"]);
+    queue_job(&conn, &shot.id);
+    let marked = stale::mark_featuring(&conn, &shot.characters[0], "Mara changed").unwrap();
+    assert_eq!(marked, std::slice::from_ref(&shot.id));
+    assert_eq!(
+        stale::list_for_project(&conn, &project_id).unwrap().len(),
+        1
+    );
+
+    let kept = stale::dismiss(&conn, &shot.id).unwrap();
+    assert_eq!(kept.stale_reason, "");
+    assert!(stale::list_for_project(&conn, &project_id)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn dismissing_needs_a_known_scene() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        stale::dismiss(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,38 @@
+//! Dashboard numbers for a project.
+
+use crate::{project, queue_job, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::jobs;
+use ai_directors_chair::stats;
+use ai_directors_chair::workflow::SceneStatus;
+
+#[test]
+fn stats_count_scenes_by_status_and_finished_jobs() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    scene(&conn, &project_id, 2, &[]);
+    let job = jobs::get_job(&conn, &queue_job(&conn, &shot.id)).unwrap();
+    jobs::complete(&conn, &job, "https://cdn.example.com/take.mp4").unwrap();
+
+    let stats = stats::project_stats(&conn, &project_id).unwrap();
+    assert_eq!(stats.scene_count, 2);
+    assert_eq!(stats.scenes_by_status.len(), SceneStatus::ALL.len());
+    assert_eq!(stats.scenes_by_status[0].status, SceneStatus::Draft);
+    assert_eq!(stats.scenes_by_status[0].count, 2);
+    assert_eq!(stats.jobs_completed, 1);
+    assert_eq!(stats.jobs_failed, 0);
+    assert_eq!(stats.success_rate, Some(1.0));
+}
+
+#[test]
+fn stats_need_a_known_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        stats::project_stats(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,61 @@
+//! Scene tags, set by hand or suggested by the LLM.
+
+use crate::{project, scene};
+use ai_directors_chair::confidential;
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::llm::GEMINI_KEY_SETTING;
+use ai_directors_chair::settings;
+use ai_directors_chair::tags;
+use serde_json::json;
+
+#[test]
+fn manual_tags_are_normalized_and_kept_from_auto_tagging() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let manual = scene(&conn, &project_id, 1, &[]);
+    let auto = scene(&conn, &project_id, 2, &[]);
+    let set = tags::set_tags(
+        &conn,
+        &manual.id,
+        &["Night Exterior".into(), "night exterior".into()],
+    )
+    .unwrap();
+    assert_eq!(set.len(), 1);
+    assert_eq!(set[0].tag, "night-exterior");
+
+    settings::set(&conn, GEMINI_KEY_SETTING, "key").unwrap();
+    let tagging = tags::prepare_auto_tags(&conn, &project_id).unwrap();
+    assert!(!tagging.is_empty());
+    let items = [
+        json!({ "sceneId": auto.id, "tags": ["Diner", "night"] }),
+        json!({ "sceneId": manual.id, "tags": ["ignored"] }),
+    ];
+    let result = tags::apply_suggestions(&conn, tagging, &items).unwrap();
+    assert_eq!(result.skipped, std::slice::from_ref(&manual.id));
+    assert_eq!(result.tagged.len(), 1);
+    assert_eq!(result.tagged[0].scene_id, auto.id);
+    assert_eq!(result.tagged[0].tags, ["diner", "night"]);
+}
+
+#[test]
+fn bad_tags_and_unready_projects_are_refused() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    assert!(matches!(
+        tags::set_tags(&conn, &shot.id, &["x".repeat(41)]),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        tags::prepare_auto_tags(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    confidential::set(&conn, &project_id, true).unwrap();
+    assert!(matches!(
+        tags::prepare_auto_tags(&conn, &project_id),
+        Err(AppError::Forbidden(_))
+    ));
+}
@@ -0,0 +1,97 @@
+//! Takes as the provider finishes or refuses them, and as they're rated
+//! and annotated.
+
+use crate::{project, queue_job, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::failures::Outcome;
+use ai_directors_chair::job_logs::{self, Exchange};
+use ai_directors_chair::jobs::{self, status, TakeSort};
+
+#[test]
+fn takes_are_rated_noted_and_sorted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let first = queue_job(&conn, &shot.id);
+    let second = queue_job(&conn, &shot.id);
+    assert_eq!(jobs::deferred(&conn).unwrap().len(), 2);
+
+    let job = jobs::get_job(&conn, &first).unwrap();
+    jobs::complete(&conn, &job, "https://cdn.example.com/first.mp4").unwrap();
+    let job = jobs::get_job(&conn, &first).unwrap();
+    assert_eq!(job.status, status::COMPLETED);
+    assert!(job.completed_at.is_some());
+
+    assert_eq!(jobs::rate(&conn, &first, Some(4)).unwrap().rating, Some(4));
+    assert_eq!(
+        jobs::set_note(&conn, &first, "  keep the flicker ")
+            .unwrap()
+            .note,
+        "keep the flicker"
+    );
+    let by_rating: Vec<_> = jobs::list_for_scene(&conn, &shot.id, None, TakeSort::Rating)
+        .unwrap()
+        .into_iter()
+        .map(|j| j.id)
+        .collect();
+    assert_eq!(by_rating, [first.clone(), second]);
+    let rated = jobs::list_for_scene(&conn, &shot.id, Some(4), TakeSort::Newest).unwrap();
+    assert_eq!(rated.len(), 1);
+    assert_eq!(jobs::rate(&conn, &first, None).unwrap().rating, None);
+}
+
+#[test]
+fn refused_takes_fail_and_ratings_stay_in_range() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let job_id = queue_job(&conn, &shot.id);
+    let job = jobs::get_job(&conn, &job_id).unwrap();
+
+    let outcome = jobs::fail(&conn, &job, "prompt flagged by safety filter", Some(422)).unwrap();
+    assert_eq!(outcome, Outcome::Failed);
+    assert!(matches!(
+        jobs::rate(&conn, &job_id, Some(6)),
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        jobs::set_note(&conn, &uuid::Uuid::new_v4().to_string(), "lost"),
+        Err(AppError::NotFound(_))
+    ));
+}
+
+#[test]
+fn debug_info_redacts_logged_exchanges() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let job_id = queue_job(&conn, &shot.id);
+    let mut exchange = Exchange::new("GET", "https://queue.fal.run/requests/1", None);
+    exchange.status = Some(200);
+    exchange.response = "waiting for key-123".into();
+    job_logs::record(
+        &conn,
+        &job_id,
+        job_logs::phase::POLL,
+        &[exchange],
+        &["key-123"],
+    )
+    .unwrap();
+
+    let info = job_logs::debug_info(&conn, &job_id).unwrap();
+    assert_eq!(info.job.id, job_id);
+    assert_eq!(
+        info.request.unwrap()["prompt"],
+        "Wide shot of an empty diner"
+    );
+    assert_eq!(info.logs.len(), 1);
+    assert!(!info.logs[0].response.contains("key-123"));
+    assert!(matches!(
+        job_logs::debug_info(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
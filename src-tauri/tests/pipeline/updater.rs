@@ -0,0 +1,25 @@
+//! The update channel the app checks for releases.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::updater;
+
+#[test]
+fn update_channel_defaults_to_stable_and_can_switch() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert_eq!(updater::channel(&conn).unwrap(), "stable");
+    updater::set_channel(&conn, "beta").unwrap();
+    assert_eq!(updater::channel(&conn).unwrap(), "beta");
+}
+
+#[test]
+fn unknown_update_channels_are_refused() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        updater::set_channel(&conn, "nightly"),
+        Err(AppError::Invalid(_))
+    ));
+    assert_eq!(updater::channel(&conn).unwrap(), "stable");
+}
@@ -0,0 +1,63 @@
+//! Local profiles for people sharing one workstation.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::users::{self, UserProfileInput};
+
+fn input(name: &str) -> UserProfileInput {
+    UserProfileInput {
+        id: None,
+        name: name.into(),
+        avatar: "AB".into(),
+    }
+}
+
+#[test]
+fn profiles_are_added_picked_renamed_and_removed() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let ana = users::save(&conn, &input("Ana")).unwrap();
+    users::save(&conn, &input("Ben")).unwrap();
+
+    let picked = users::select(&conn, Some(&ana.id)).unwrap().unwrap();
+    assert!(picked.last_used_at.is_some());
+    assert!(users::select(&conn, None).unwrap().is_none());
+    let list = users::list(&conn).unwrap();
+    assert_eq!(list.last_user_id.as_deref(), Some(ana.id.as_str()));
+    let names: Vec<_> = list.users.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, ["Ana", "Ben"]);
+
+    let renamed = users::save(
+        &conn,
+        &UserProfileInput {
+            id: Some(ana.id.clone()),
+            ..input("Anna")
+        },
+    )
+    .unwrap();
+    assert_eq!(renamed.name, "Anna");
+
+    users::delete(&conn, &ana.id).unwrap();
+    let list = users::list(&conn).unwrap();
+    assert_eq!(list.users.len(), 1);
+    assert_eq!(list.last_user_id, None);
+}
+
+#[test]
+fn profile_names_are_unique_and_ids_must_exist() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    users::save(&conn, &input("Ana")).unwrap();
+    assert!(matches!(
+        users::save(&conn, &input("ana")),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        users::select(&conn, Some("missing")),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        users::delete(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
@@ -0,0 +1,55 @@
+//! Camera angle and lighting terms managed by the user.
+
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::vocabulary::{self, VocabularyKind, VocabularyTermInput};
+
+fn dutch() -> VocabularyTermInput {
+    VocabularyTermInput {
+        key: " Dutch ".into(),
+        label: "Dutch angle".into(),
+        description: String::new(),
+        prompt_fragment: "tilted dutch angle".into(),
+    }
+}
+
+#[test]
+fn user_terms_are_added_listed_and_deleted() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let term = vocabulary::save_term(&conn, VocabularyKind::CameraAngle, &dutch()).unwrap();
+    assert_eq!(term.key, "dutch");
+    assert!(!term.builtin);
+    let listed = vocabulary::vocabulary(&conn).unwrap();
+    assert!(listed.camera_angles.iter().any(|t| t.key == "dutch"));
+    assert!(listed.lighting.iter().all(|t| t.key != "dutch"));
+
+    vocabulary::delete_term(&conn, VocabularyKind::CameraAngle, "dutch").unwrap();
+    let listed = vocabulary::vocabulary(&conn).unwrap();
+    assert!(listed.camera_angles.iter().all(|t| t.key != "dutch"));
+}
+
+#[test]
+fn built_in_missing_and_blank_terms_are_refused() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    assert!(matches!(
+        vocabulary::save_term(
+            &conn,
+            VocabularyKind::Lighting,
+            &VocabularyTermInput {
+                label: " ".into(),
+                ..dutch()
+            }
+        ),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        vocabulary::delete_term(&conn, VocabularyKind::CameraAngle, "closeup"),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        vocabulary::delete_term(&conn, VocabularyKind::Lighting, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+}
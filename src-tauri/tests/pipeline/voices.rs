@@ -0,0 +1,77 @@
+//! Voice samples, cloning and dialog rendered with ElevenLabs.
+
+use crate::{project, scene, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::projects;
+use ai_directors_chair::settings;
+use ai_directors_chair::voices::{self, ELEVENLABS_KEY_SETTING};
+
+#[test]
+fn samples_are_cloned_into_a_voice_that_speaks() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let project_id = project(&conn);
+    let mara = scene(&conn, &project_id, 1, &["Mara"]).characters[0].clone();
+    let source = root.join("Line.MP3");
+    std::fs::write(&source, b"ID3 take").unwrap();
+    let sample = voices::add_sample(&conn, &mara, &source, &root).unwrap();
+    assert_eq!(sample.file_name, "Line.MP3");
+    assert!(sample.file_path.ends_with(".mp3"));
+    assert_eq!(voices::samples_for(&conn, &mara).unwrap().len(), 1);
+    assert!(matches!(
+        voices::prepare_speech(&conn, &mara, "Hello"),
+        Err(AppError::Invalid(_))
+    ));
+
+    settings::set(&conn, ELEVENLABS_KEY_SETTING, "key").unwrap();
+    let clone = voices::prepare_clone(&conn, &mara).unwrap();
+    let failed = voices::record_clone(&conn, &clone, Err(AppError::Provider("down".into())));
+    assert!(matches!(failed, Err(AppError::Provider(_))));
+    let clone = voices::prepare_clone(&conn, &mara).unwrap();
+    let cloned = voices::record_clone(&conn, &clone, Ok("voice-1".into())).unwrap();
+    assert_eq!(cloned.voice.engine, "elevenlabs");
+    assert_eq!(cloned.voice.voice_id, "voice-1");
+    assert_eq!(cloned.voice.clone_status, "ready");
+    voices::prepare_speech(&conn, &mara, "Hello").unwrap();
+
+    voices::remove_sample(&conn, &sample.id).unwrap();
+    assert!(voices::samples_for(&conn, &mara).unwrap().is_empty());
+    assert!(!std::path::Path::new(&sample.file_path).exists());
+}
+
+#[test]
+fn samples_need_audio_an_unlocked_project_and_a_key() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let root = scratch_dir();
+    let project_id = project(&conn);
+    let mara = scene(&conn, &project_id, 1, &["Mara"]).characters[0].clone();
+    let notes = root.join("notes.txt");
+    std::fs::write(&notes, b"not audio").unwrap();
+    assert!(matches!(
+        voices::add_sample(&conn, &mara, &notes, &root),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        voices::prepare_clone(&conn, &mara),
+        Err(AppError::Invalid(_))
+    ));
+    settings::set(&conn, ELEVENLABS_KEY_SETTING, "key").unwrap();
+    assert!(matches!(
+        voices::prepare_clone(&conn, &mara),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        voices::remove_sample(&conn, "missing"),
+        Err(AppError::NotFound(_))
+    ));
+    projects::lock(&conn, &project_id).unwrap();
+    let line = root.join("line.wav");
+    std::fs::write(&line, b"RIFF").unwrap();
+    assert!(matches!(
+        voices::add_sample(&conn, &mara, &line, &root),
+        Err(AppError::Locked(_))
+    ));
+}
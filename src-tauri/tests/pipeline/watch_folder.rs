@@ -0,0 +1,41 @@
+//! The folder watched for renders made outside the app.
+
+use crate::{project, scratch_dir};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::watch_folder;
+
+#[test]
+fn watch_folder_is_stored_and_cleared() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let folder = scratch_dir();
+    let path = folder.to_string_lossy();
+    let saved = watch_folder::save(&conn, &format!(" {} ", path), Some(&project_id)).unwrap();
+    assert_eq!(saved.path, path);
+    assert_eq!(saved.project_id.as_deref(), Some(project_id.as_str()));
+    assert_eq!(watch_folder::load(&conn).unwrap().path, path);
+
+    let cleared = watch_folder::save(&conn, "", None).unwrap();
+    assert_eq!(cleared.path, "");
+    assert_eq!(cleared.project_id, None);
+}
+
+#[test]
+fn watch_folder_needs_a_folder_and_a_known_project() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let file = scratch_dir().join("take.mp4");
+    std::fs::write(&file, b"video").unwrap();
+    assert!(matches!(
+        watch_folder::save(&conn, &file.to_string_lossy(), None),
+        Err(AppError::Invalid(_))
+    ));
+    let missing = uuid::Uuid::new_v4().to_string();
+    assert!(matches!(
+        watch_folder::save(&conn, "", Some(&missing)),
+        Err(AppError::NotFound(_))
+    ));
+    assert_eq!(watch_folder::load(&conn).unwrap().path, "");
+}
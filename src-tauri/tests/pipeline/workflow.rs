@@ -0,0 +1,57 @@
+//! Scenes moving through the review workflow.
+
+use crate::{project, scene};
+use ai_directors_chair::db::Database;
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::workflow::{self, SceneStatus};
+
+#[test]
+fn scenes_move_one_step_at_a_time() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let shot = scene(&conn, &project_id, 1, &[]);
+    let (change, moved) = workflow::transition(
+        &conn,
+        &shot.id,
+        SceneStatus::PromptReady,
+        Some(shot.version),
+    )
+    .unwrap();
+    assert_eq!(change.from, SceneStatus::Draft);
+    assert_eq!(moved.status, "prompt_ready");
+
+    assert!(matches!(
+        workflow::transition(&conn, &shot.id, SceneStatus::Approved, None),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        workflow::transition(&conn, &shot.id, SceneStatus::Generating, Some(shot.version)),
+        Err(AppError::Conflict(_))
+    ));
+}
+
+#[test]
+fn bulk_moves_report_scenes_that_cannot_move() {
+    let db = Database::open_in_memory().unwrap();
+    let conn = db.conn();
+    let project_id = project(&conn);
+    let first = scene(&conn, &project_id, 1, &[]);
+    let second = scene(&conn, &project_id, 2, &[]);
+    workflow::transition(&conn, &second.id, SceneStatus::PromptReady, None).unwrap();
+
+    let ids = [first.id.clone(), second.id.clone()];
+    let (result, changes) =
+        workflow::bulk_transition(&conn, &ids, SceneStatus::PromptReady).unwrap();
+    assert_eq!(result.updated.len(), 1);
+    assert_eq!(result.updated[0].id, first.id);
+    assert_eq!(result.rejected.len(), 1);
+    assert_eq!(result.rejected[0].scene_id, second.id);
+    assert_eq!(changes.len(), 1);
+
+    let bare = rusqlite::Connection::open_in_memory().unwrap();
+    assert!(matches!(
+        workflow::bulk_transition(&bare, &ids, SceneStatus::Draft),
+        Err(AppError::Database(_))
+    ));
+}
@@ -0,0 +1,41 @@
+//! The registry of workspaces, each with its own database file.
+
+use ai_directors_chair::error::AppError;
+use ai_directors_chair::workspaces::{Registry, DEFAULT_ID};
+
+#[test]
+fn workspaces_are_added_renamed_and_removed() {
+    let mut registry = Registry::default();
+    let added = registry.add(" Client work ").unwrap();
+    assert_eq!(added.name, "Client work");
+    assert_eq!(added.file, format!("workspaces/{}.db", added.id));
+    let renamed = registry.rename(&added.id, "Archive").unwrap();
+    assert_eq!(renamed.name, "Archive");
+    assert_eq!(registry.workspaces.len(), 2);
+
+    let removed = registry.remove(&added.id).unwrap();
+    assert_eq!(removed.name, "Archive");
+    assert_eq!(registry.workspaces.len(), 1);
+}
+
+#[test]
+fn default_active_and_duplicate_workspaces_are_refused() {
+    let mut registry = Registry::default();
+    registry.add("Client work").unwrap();
+    assert!(matches!(
+        registry.add("client WORK"),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        registry.rename("missing", "Other"),
+        Err(AppError::NotFound(_))
+    ));
+    assert!(matches!(
+        registry.remove(DEFAULT_ID),
+        Err(AppError::Invalid(_))
+    ));
+    assert!(matches!(
+        registry.remove("missing"),
+        Err(AppError::NotFound(_))
+    ));
+}